@@ -0,0 +1,203 @@
+//! Publishing and receiving 4 MiB images through a pair of `PluginContext`s, with and without
+//! zstd compression of the payloads. Run it with
+//!
+//!     cargo bench --bench compression
+//!
+//! Before measuring the throughput, it prints how many bytes the payload of each sample image
+//! takes on the wire: a raw RGB bitmap of a synthetic camera frame, and the same frame encoded
+//! as a PNG without deflate compression, with fast compression and with the best compression.
+//! Payloads that zstd cannot make any smaller, such as a well compressed PNG, go out as they are.
+//!
+
+use std::io::Write;
+use std::thread;
+use std::time::Duration;
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use flate2::write::ZlibEncoder;
+use flate2::Crc;
+use plyoreacto::compression::Compression;
+use plyoreacto::events::{get_event_type_bytes_filter, NewImage};
+use plyoreacto::plugin::PluginContext;
+use zmq::Socket;
+
+const WIDTH: usize = 1024;
+const HEIGHT: usize = 1365;
+// the size over which the compressing publisher compresses
+const THRESHOLD: usize = 64 * 1024;
+
+// A frame of RGB pixels that looks like a camera's: smooth gradients, a few shapes, and noise
+// in the lowest bits.
+fn camera_frame() -> Vec<u8> {
+    let mut noise: u32 = 0x9e37_79b9;
+    let mut pixels = Vec::with_capacity(WIDTH * HEIGHT * 3);
+    for y in 0..HEIGHT {
+        for x in 0..WIDTH {
+            // xorshift, so that the frame is the same on every run
+            noise ^= noise << 13;
+            noise ^= noise >> 17;
+            noise ^= noise << 5;
+            let (dx, dy) = (x as i64 - 600, y as i64 - 500);
+            let spot = if dx * dx + dy * dy < 200 * 200 { 80 } else { 0 };
+            let base = (x * 255 / WIDTH) as u8 / 2 + spot;
+            let jitter = (noise & 3) as u8;
+            pixels.extend_from_slice(&[
+                base + jitter,
+                (y * 255 / HEIGHT) as u8 / 2 + jitter,
+                base / 2 + 60 + jitter,
+            ]);
+        }
+    }
+    pixels
+}
+
+// Append the PNG chunk `kind` holding `data` to `png`.
+fn png_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+    let mut crc = Crc::new();
+    crc.update(kind);
+    crc.update(data);
+    png.extend_from_slice(&crc.sum().to_be_bytes());
+}
+
+// The RGB `pixels` encoded as a PNG, deflating its scanlines at `level`, as image libraries do.
+fn png(pixels: &[u8], level: flate2::Compression) -> Vec<u8> {
+    let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+    let mut header = Vec::new();
+    header.extend_from_slice(&(WIDTH as u32).to_be_bytes());
+    header.extend_from_slice(&(HEIGHT as u32).to_be_bytes());
+    // 8 bits per channel, RGB, deflate, adaptive filtering, no interlacing
+    header.extend_from_slice(&[8, 2, 0, 0, 0]);
+    png_chunk(&mut png, b"IHDR", &header);
+    let mut scanlines = ZlibEncoder::new(Vec::new(), level);
+    for row in pixels.chunks(WIDTH * 3) {
+        // no filter on any scanline
+        scanlines.write_all(&[0]).unwrap();
+        scanlines.write_all(row).unwrap();
+    }
+    png_chunk(&mut png, b"IDAT", &scanlines.finish().unwrap());
+    png_chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+fn image(format: &str, image: Vec<u8>) -> NewImage {
+    NewImage {
+        image_uuid: "1234".to_string(),
+        image_format: format.to_string(),
+        image,
+        location: None,
+    }
+}
+
+// A subscriber to the new images published on the inproc endpoint `name`.
+fn subscriber(ctx: &zmq::Context, name: &str) -> Socket {
+    let socket = ctx.socket(zmq::SUB).unwrap();
+    socket
+        .set_subscribe(&get_event_type_bytes_filter("NewImageEvent").unwrap())
+        .unwrap();
+    socket.connect(&format!("inproc://{}", name)).unwrap();
+    socket
+}
+
+// A publishing and a receiving context connected over inproc, the publisher compressing as
+// `compression` says.
+struct Link {
+    ctx: zmq::Context,
+    name: String,
+    publisher: PluginContext,
+    receiver: PluginContext,
+}
+
+impl Link {
+    fn new(name: &str, compression: Option<Compression>) -> Self {
+        let ctx = zmq::Context::new();
+        let pub_socket = ctx.socket(zmq::PUB).unwrap();
+        pub_socket.bind(&format!("inproc://{}", name)).unwrap();
+        let sub_socket = subscriber(&ctx, name);
+        // give the subscription time to reach the pub socket
+        thread::sleep(Duration::from_millis(100));
+        let mut publisher = PluginContext::new(1, pub_socket, ctx.socket(zmq::SUB).unwrap());
+        publisher.set_compression(compression);
+        let receiver = PluginContext::new(2, ctx.socket(zmq::PUB).unwrap(), sub_socket);
+        Link {
+            ctx,
+            name: name.to_string(),
+            publisher,
+            receiver,
+        }
+    }
+
+    // The size of the payload frame each of `images` is sent with, as seen by a subscriber of
+    // its own, which would only pile up the images sent afterwards if it were kept.
+    fn sent_sizes(&mut self, images: &[NewImage]) -> Vec<usize> {
+        let raw = subscriber(&self.ctx, &self.name);
+        thread::sleep(Duration::from_millis(100));
+        images
+            .iter()
+            .map(|image| {
+                self.round_trip(image);
+                raw.recv_multipart(0).unwrap()[2].len()
+            })
+            .collect()
+    }
+
+    fn round_trip(&mut self, image: &NewImage) {
+        self.publisher.publish(image).unwrap();
+        self.receiver.next_event().unwrap().decode().unwrap();
+    }
+}
+
+fn print_sizes(plain: &mut Link, compressing: &mut Link, samples: &[(&str, NewImage)]) {
+    let images: Vec<_> = samples.iter().map(|(_, image)| image.clone()).collect();
+    let sizes = plain
+        .sent_sizes(&images)
+        .into_iter()
+        .zip(compressing.sent_sizes(&images));
+    println!(
+        "{:<24} {:>12} {:>12} {:>8}",
+        "payload", "plain", "zstd", "saved"
+    );
+    for ((name, _), (before, after)) in samples.iter().zip(sizes) {
+        let saved = 100.0 * (before - after) as f64 / before as f64;
+        println!("{:<24} {:>12} {:>12} {:>7.1}%", name, before, after, saved);
+    }
+}
+
+fn compression(c: &mut Criterion) {
+    let pixels = camera_frame();
+    let samples = [
+        ("raw bitmap", image("raw", pixels.clone())),
+        (
+            "png, stored",
+            image("png", png(&pixels, flate2::Compression::none())),
+        ),
+        (
+            "png, fast",
+            image("png", png(&pixels, flate2::Compression::fast())),
+        ),
+        (
+            "png, best",
+            image("png", png(&pixels, flate2::Compression::best())),
+        ),
+    ];
+    let mut plain = Link::new("plain", None);
+    let mut compressing = Link::new("compressing", Some(Compression::new(THRESHOLD)));
+    print_sizes(&mut plain, &mut compressing, &samples);
+
+    let mut group = c.benchmark_group("compression");
+    group.throughput(Throughput::Bytes(pixels.len() as u64));
+    for (name, image) in &samples[..2] {
+        group.bench_function(format!("plain, {}", name), |b| {
+            b.iter(|| plain.round_trip(image))
+        });
+        group.bench_function(format!("zstd, {}", name), |b| {
+            b.iter(|| compressing.round_trip(image))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, compression);
+criterion_main!(benches);
@@ -0,0 +1,175 @@
+//! End-to-end benchmarks of engines running over inproc only, so that no TCP port has to be
+//! free. Run them with
+//!
+//!     cargo bench --bench engine
+//!
+//! `single hop` is the latency of a small event published by the host application until a host
+//! subscriber receives it back from the proxy; `image pipeline` is how many 64 KiB images per
+//! second the `NewImagePlugin`, `ImageScorePlugin` and `ImageStorePlugin` get through, from
+//! starting the engine until it stops after the last image is stored; `forwarding` is how many
+//! small events per second go through `zmq::proxy`, through the engine's own forwarding loop, and
+//! through the loop with a `PayloadSizeLimit` middleware.
+//!
+
+use std::time::{Duration, Instant};
+
+use criterion::{criterion_group, criterion_main, Criterion, SamplingMode, Throughput};
+use plyoreacto::event_engine::{EngineHandle, EventEngineBuilder, Hwm, Transport};
+use plyoreacto::events::{event_type_header, recv_event_frames, Event, PluginHeartbeat};
+use plyoreacto::image_score_plugin::{FixedScorer, ImageScorePlugin};
+use plyoreacto::image_store_plugin::ImageStorePlugin;
+use plyoreacto::middleware::PayloadSizeLimit;
+use plyoreacto::new_image_plugin::NewImagePlugin;
+use plyoreacto::plugin_registry::PluginRegistry;
+use zmq::Socket;
+
+const IMAGE_SIZE: usize = 64 * 1024;
+// fewer than the high-water marks let through, so that none is dropped
+const FORWARDED_EVENTS: u64 = 500;
+
+// An engine without plugins, and a host subscriber receiving its heartbeat events.
+fn start_single_hop() -> (EngineHandle, Socket) {
+    start_engine("single-hop", EventEngineBuilder::new())
+}
+
+// An engine built by `builder`, without plugins, with inproc endpoints named after `name`, and a
+// host subscriber receiving its heartbeat events.
+fn start_engine(name: &str, builder: EventEngineBuilder) -> (EngineHandle, Socket) {
+    let context = zmq::Context::new();
+    let engine = builder
+        .incoming_inproc(&format!("messages-{}", name))
+        .outgoing_inproc(&format!("events-{}", name))
+        .transport(Transport::InprocOnly)
+        .context(context.clone())
+        .plugins(PluginRegistry::new())
+        .start()
+        .unwrap();
+    let subscriber = context.socket(zmq::SUB).unwrap();
+    subscriber
+        .set_subscribe(&event_type_header("PluginHeartbeatEvent"))
+        .unwrap();
+    subscriber
+        .connect(&format!("inproc://events-{}", name))
+        .unwrap();
+    // the subscription takes a moment to reach the proxy; probe until it has
+    subscriber.set_rcvtimeo(10).unwrap();
+    loop {
+        engine.publish(&heartbeat(0)).unwrap();
+        if recv_event_frames(&subscriber, 0).is_ok() {
+            break;
+        }
+    }
+    while recv_event_frames(&subscriber, zmq::DONTWAIT).is_ok() {}
+    subscriber.set_rcvtimeo(-1).unwrap();
+    (engine, subscriber)
+}
+
+fn heartbeat(seq: u64) -> Event {
+    Event::PluginHeartbeat(PluginHeartbeat { plugin_id: 1, seq })
+}
+
+fn single_hop(c: &mut Criterion) {
+    let (engine, subscriber) = start_single_hop();
+    let event = heartbeat(1);
+    c.bench_function("single hop", |b| {
+        b.iter(|| {
+            engine.publish(&event).unwrap();
+            recv_event_frames(&subscriber, 0).unwrap()
+        })
+    });
+    drop(subscriber);
+    engine.shutdown().unwrap();
+}
+
+// Run the three image plugins over `images` images, returning how long it took from starting
+// the engine until it stopped. Nothing may be dropped, or the engine would never stop.
+fn run_image_pipeline(images: u64) -> Duration {
+    let labrador = FixedScorer::new(vec![("labrador".to_string(), 0.9)]);
+    let mut plugins = PluginRegistry::new();
+    plugins
+        .register_plugin(Box::new(
+            NewImagePlugin::new(0)
+                .images(images as usize)
+                .image_size(IMAGE_SIZE),
+        ))
+        .unwrap()
+        .register_plugin(Box::new(
+            ImageScorePlugin::with_scorer(1, Box::new(labrador)).images(images as usize),
+        ))
+        .unwrap()
+        .register_plugin(Box::new(ImageStorePlugin::new(2).images(images as usize)))
+        .unwrap();
+    for plugin_id in 0..3 {
+        plugins.hwm(plugin_id, Hwm::new(0)).unwrap();
+    }
+
+    let started = Instant::now();
+    let engine = EventEngineBuilder::new()
+        .incoming_inproc("messages-image-pipeline")
+        .outgoing_inproc("events-image-pipeline")
+        .transport(Transport::InprocOnly)
+        .incoming_hwm(Hwm::new(0))
+        .outgoing_hwm(Hwm::new(0))
+        .stop_after("ImageStoredEvent", images)
+        .plugins(plugins)
+        .start()
+        .unwrap();
+    assert!(
+        engine.wait_for_stop(Duration::from_secs(60)),
+        "the pipeline did not store {} images",
+        images
+    );
+    let elapsed = started.elapsed();
+    engine.shutdown().unwrap();
+    elapsed
+}
+
+fn image_pipeline(c: &mut Criterion) {
+    let mut group = c.benchmark_group("image pipeline");
+    group
+        .throughput(Throughput::Elements(1))
+        .sampling_mode(SamplingMode::Flat)
+        .sample_size(10);
+    group.bench_function("64 KiB images", |b| b.iter_custom(run_image_pipeline));
+    group.finish();
+}
+
+fn forwarding(c: &mut Criterion) {
+    let mut group = c.benchmark_group("forwarding");
+    group.throughput(Throughput::Elements(FORWARDED_EVENTS));
+    let cores = [
+        ("zmq proxy", EventEngineBuilder::new().max_payload_size(0)),
+        (
+            "loop",
+            EventEngineBuilder::new()
+                .max_payload_size(0)
+                .forwarding_loop(true),
+        ),
+        (
+            "loop with a middleware",
+            EventEngineBuilder::new()
+                .max_payload_size(0)
+                .middleware(Box::new(PayloadSizeLimit::new(IMAGE_SIZE))),
+        ),
+    ];
+    for (i, (name, builder)) in cores.into_iter().enumerate() {
+        let (engine, subscriber) = start_engine(&format!("forwarding-{}", i), builder);
+        let events: Vec<Event> = (0..FORWARDED_EVENTS).map(heartbeat).collect();
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                for event in &events {
+                    engine.publish(event).unwrap();
+                }
+                for _ in 0..FORWARDED_EVENTS {
+                    recv_event_frames(&subscriber, 0).unwrap();
+                }
+            })
+        });
+        drop(subscriber);
+        engine.shutdown().unwrap();
+    }
+    group.finish();
+}
+
+criterion_group!(benches, single_hop, image_pipeline, forwarding);
+criterion_main!(benches);
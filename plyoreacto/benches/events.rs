@@ -0,0 +1,155 @@
+//! How long serializing an event of each type takes, and deserializing it back into an `Event`.
+//! Run it with
+//!
+//!     cargo bench --bench events
+//!
+//! The images are 64 KiB, like those of the pipeline throughput benchmark in `benches/engine.rs`.
+//!
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use flatbuffers::FlatBufferBuilder;
+use plyoreacto::events::{
+    event_type_names, EngineHeartbeat, Event, EventTypeCount, EventTypeLatency, EventsDropped,
+    ImageDeleted, ImageDeletedRequest, ImageRejected, ImageScore, ImageScoreFailed, ImageScored,
+    ImageStoreFailed, ImageStored, MetricsSnapshot, NewImage, PluginFailed, PluginHeartbeat,
+    PluginJoined, PluginLeft, PluginRestarted, WebhookDeliveryFailed,
+};
+
+const IMAGE_UUID: &str = "6f1c9d3e-2b7a-4c55-9e41-0d8f3a7b2c10";
+
+// An event of every type, with fields of realistic sizes.
+fn events() -> Vec<Event> {
+    let image_uuid = IMAGE_UUID.to_string();
+    let event_types = ["NewImageEvent", "ImageScoredEvent", "ImageStoredEvent"];
+    vec![
+        Event::NewImage(NewImage {
+            image_uuid: image_uuid.clone(),
+            image_format: "png".to_string(),
+            image: vec![7; 64 * 1024],
+            location: None,
+        }),
+        Event::ImageScored(ImageScored {
+            image_uuid: image_uuid.clone(),
+            scores: ["labrador", "poodle", "beagle"]
+                .iter()
+                .map(|label| ImageScore {
+                    label: label.to_string(),
+                    probability: 0.3,
+                })
+                .collect(),
+        }),
+        Event::ImageStored(ImageStored {
+            image_uuid: image_uuid.clone(),
+            path: format!("images/6f/1c/{}.png", IMAGE_UUID),
+            deduplicated: false,
+        }),
+        Event::ImageDeleted(ImageDeleted {
+            image_uuid: image_uuid.clone(),
+            existed: true,
+        }),
+        Event::PluginTerminate,
+        Event::PluginFailed(PluginFailed {
+            plugin_id: 2,
+            message: "plugin panicked: index out of bounds".to_string(),
+        }),
+        Event::PluginRestarted(PluginRestarted {
+            plugin_id: 2,
+            restart_count: 1,
+        }),
+        Event::ImageScoreFailed(ImageScoreFailed {
+            image_uuid: image_uuid.clone(),
+            error: "unsupported image format".to_string(),
+        }),
+        Event::ImageRejected(ImageRejected {
+            image_uuid: image_uuid.clone(),
+            top_label: "labrador".to_string(),
+            probability: 0.4,
+        }),
+        Event::ImageStoreFailed(ImageStoreFailed {
+            image_uuid: image_uuid.clone(),
+            error: "no space left on device".to_string(),
+        }),
+        Event::ImageDeletedRequest(ImageDeletedRequest {
+            image_uuid: image_uuid.clone(),
+        }),
+        Event::MetricsSnapshot(MetricsSnapshot {
+            counts: event_types
+                .iter()
+                .map(|event_type| EventTypeCount {
+                    event_type: event_type.to_string(),
+                    count: 1000,
+                    bytes: 64 * 1024 * 1000,
+                })
+                .collect(),
+            latencies: event_types
+                .iter()
+                .map(|event_type| EventTypeLatency {
+                    event_type: event_type.to_string(),
+                    count: 1000,
+                    p50_us: 120,
+                    p95_us: 480,
+                    p99_us: 900,
+                    max_us: 2500,
+                })
+                .collect(),
+        }),
+        Event::WebhookDeliveryFailed(WebhookDeliveryFailed {
+            image_uuid: image_uuid.clone(),
+            event_type: "ImageStoredEvent".to_string(),
+            url: "http://hooks.example.com/images".to_string(),
+            status_code: 503,
+            error: "service unavailable".to_string(),
+        }),
+        Event::PluginJoined(PluginJoined { plugin_id: 3 }),
+        Event::PluginLeft(PluginLeft {
+            plugin_id: 3,
+            reason: "no heartbeat for 10s".to_string(),
+        }),
+        Event::EngineHeartbeat(EngineHeartbeat {
+            seq: 42,
+            uptime_ms: 60_000,
+        }),
+        Event::PluginHeartbeat(PluginHeartbeat {
+            plugin_id: 1,
+            seq: 42,
+        }),
+        Event::EventsDropped(EventsDropped {
+            plugin_id: 1,
+            missed: 17,
+        }),
+    ]
+}
+
+fn serialization(c: &mut Criterion) {
+    let events = events();
+    assert_eq!(
+        events.len(),
+        event_type_names().len(),
+        "every event type is benchmarked"
+    );
+    let mut bldr = FlatBufferBuilder::new();
+
+    let mut group = c.benchmark_group("encode");
+    for event in &events {
+        group.bench_with_input(
+            BenchmarkId::from_parameter(event.type_name()),
+            event,
+            |b, event| b.iter(|| event.encode(&mut bldr).len()),
+        );
+    }
+    group.finish();
+
+    let mut group = c.benchmark_group("decode");
+    for event in &events {
+        let payload = event.encode(&mut bldr).to_vec();
+        group.bench_with_input(
+            BenchmarkId::from_parameter(event.type_name()),
+            &payload,
+            |b, payload| b.iter(|| Event::decode(payload).unwrap()),
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, serialization);
+criterion_main!(benches);
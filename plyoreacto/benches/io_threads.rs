@@ -0,0 +1,105 @@
+//! How many 16 KiB images per second an engine forwards to two external subscribers over TCP,
+//! with one and with two zmq I/O threads (see `EventEngineBuilder::io_threads`). Run it with
+//!
+//!     cargo bench --bench io_threads
+//!
+//! Each subscriber has a context of its own, as it would in a process of its own, so that only
+//! the engine's I/O threads are shared. It binds the loopback ports 27559 and 27560.
+//!
+
+use std::time::Duration;
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use plyoreacto::event_engine::{EngineHandle, EventEngineBuilder, Hwm};
+use plyoreacto::events::{event_type_header, recv_event_frames, Event, NewImage};
+use plyoreacto::plugin_registry::PluginRegistry;
+use zmq::Socket;
+
+const IMAGE_SIZE: usize = 16 * 1024;
+const IMAGES: u64 = 200;
+const SUBSCRIBERS: usize = 2;
+const OUTGOING_PORT: u16 = 27560;
+
+fn image(image: Vec<u8>) -> Event {
+    Event::NewImage(NewImage {
+        image_uuid: "00000000-0000-0000-0000-000000000000".to_string(),
+        image_format: "png".to_string(),
+        image,
+        location: None,
+    })
+}
+
+// An engine without plugins running `io_threads` I/O threads, and the external subscribers to
+// its images, which have all received one.
+fn start_engine(io_threads: i32) -> (EngineHandle, Vec<Socket>) {
+    let engine = EventEngineBuilder::new()
+        .incoming_port(27559)
+        .outgoing_port(OUTGOING_PORT)
+        .incoming_inproc("messages-io-threads")
+        .outgoing_inproc("events-io-threads")
+        .io_threads(io_threads)
+        .incoming_hwm(Hwm::new(0))
+        .outgoing_hwm(Hwm::new(0))
+        .max_payload_size(0)
+        .plugins(PluginRegistry::new())
+        .start()
+        .unwrap();
+    let subscribers: Vec<Socket> = (0..SUBSCRIBERS)
+        .map(|_| {
+            let subscriber = zmq::Context::new().socket(zmq::SUB).unwrap();
+            subscriber.set_rcvhwm(0).unwrap();
+            subscriber
+                .set_subscribe(&event_type_header("NewImageEvent"))
+                .unwrap();
+            subscriber
+                .connect(&format!("tcp://127.0.0.1:{}", OUTGOING_PORT))
+                .unwrap();
+            subscriber
+        })
+        .collect();
+    // the subscriptions take a moment to reach the proxy; probe until every one has
+    let probe = image(Vec::new());
+    for subscriber in &subscribers {
+        subscriber.set_rcvtimeo(10).unwrap();
+        loop {
+            engine.publish(&probe).unwrap();
+            if recv_event_frames(subscriber, 0).is_ok() {
+                break;
+            }
+        }
+    }
+    std::thread::sleep(Duration::from_millis(50));
+    for subscriber in &subscribers {
+        while recv_event_frames(subscriber, zmq::DONTWAIT).is_ok() {}
+        subscriber.set_rcvtimeo(-1).unwrap();
+    }
+    (engine, subscribers)
+}
+
+fn io_threads(c: &mut Criterion) {
+    let mut group = c.benchmark_group("two TCP subscribers");
+    group.throughput(Throughput::Elements(IMAGES));
+    let event = image(vec![7; IMAGE_SIZE]);
+    for io_threads in [1, 2] {
+        let (engine, subscribers) = start_engine(io_threads);
+        let name = format!("{} I/O thread(s)", io_threads);
+        group.bench_function(name.as_str(), |b| {
+            b.iter(|| {
+                for _ in 0..IMAGES {
+                    engine.publish(&event).unwrap();
+                }
+                for subscriber in &subscribers {
+                    for _ in 0..IMAGES {
+                        recv_event_frames(subscriber, 0).unwrap();
+                    }
+                }
+            })
+        });
+        drop(subscribers);
+        engine.shutdown().unwrap();
+    }
+    group.finish();
+}
+
+criterion_group!(benches, io_threads);
+criterion_main!(benches);
@@ -0,0 +1,151 @@
+//! Publishing and receiving 1 MiB images the way events were sent before the zero-copy path,
+//! copying the finished flatbuffer into a Vec and every received frame out of its zmq message,
+//! against the way `PluginContext` does it now. Run it with
+//!
+//!     cargo bench --bench publish
+//!
+//! Before measuring the throughput, it prints how many allocations (and how many bytes) each way
+//! takes per event on the Rust heap; the buffers zmq allocates itself are not counted.
+//!
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use flatbuffers::FlatBufferBuilder;
+use plyoreacto::events::{
+    event_type_header, make_new_image_msg, make_new_image_msg_copy, parse_event_envelope,
+    parse_event_messages, recv_event_frames, send_event_msg_with_meta, EventMeta,
+};
+use plyoreacto::events_generated::events::root_as_event;
+use zmq::Socket;
+
+const IMAGE_SIZE: usize = 1 << 20;
+
+// Counts the allocations made through the global allocator.
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static ALLOCATED_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED_BYTES.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED_BYTES.fetch_add(new_size, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+// A connected pair of sockets to send the events through.
+struct Link {
+    _ctx: zmq::Context,
+    sender: Socket,
+    receiver: Socket,
+}
+
+impl Link {
+    fn new(name: &str) -> Self {
+        let ctx = zmq::Context::new();
+        let receiver = ctx.socket(zmq::PAIR).unwrap();
+        receiver.bind(&format!("inproc://{}", name)).unwrap();
+        let sender = ctx.socket(zmq::PAIR).unwrap();
+        sender.connect(&format!("inproc://{}", name)).unwrap();
+        Link {
+            _ctx: ctx,
+            sender,
+            receiver,
+        }
+    }
+}
+
+// Send a new image the way events were sent before, and receive it back: the finished
+// flatbuffer goes through a Vec, and the received frames are copied out of their messages.
+fn round_trip_copying(link: &Link, bldr: &mut FlatBufferBuilder, image: &[u8]) -> usize {
+    let data = make_new_image_msg_copy(bldr, "1234", "jpeg", image).unwrap();
+    let meta = EventMeta::new(1);
+    let sender = &link.sender;
+    sender
+        .send(event_type_header("NewImageEvent"), zmq::SNDMORE)
+        .unwrap();
+    sender.send(meta.to_bytes(), zmq::SNDMORE).unwrap();
+    sender.send(&data[..], 0).unwrap();
+
+    let frames = link.receiver.recv_multipart(0).unwrap();
+    let (_, _, payload) = parse_event_envelope(frames).unwrap();
+    let event = root_as_event(&payload).unwrap();
+    let new_image = event.event_as_new_image_event().unwrap();
+    new_image.image().unwrap().len()
+}
+
+// Send a new image the way `PluginContext::publish` does, and receive it back the way
+// `PluginContext::next_event` does.
+fn round_trip_zero_copy(link: &Link, bldr: &mut FlatBufferBuilder, image: &[u8]) -> usize {
+    let data = make_new_image_msg(bldr, "1234", "jpeg", image).unwrap();
+    let meta = EventMeta::new(1);
+    send_event_msg_with_meta(&link.sender, "NewImageEvent", &meta, data).unwrap();
+
+    let frames = recv_event_frames(&link.receiver, 0).unwrap();
+    let (_, _, payload) = parse_event_messages(frames).unwrap();
+    let event = root_as_event(&payload).unwrap();
+    let new_image = event.event_as_new_image_event().unwrap();
+    new_image.image().unwrap().len()
+}
+
+type RoundTrip = fn(&Link, &mut FlatBufferBuilder, &[u8]) -> usize;
+
+// Print the allocations of one round trip, once the builder has grown to its size.
+fn report_allocations(name: &str, round_trip: RoundTrip, image: &[u8]) {
+    let link = Link::new(&format!("allocations-{}", name));
+    let mut bldr = FlatBufferBuilder::new();
+    round_trip(&link, &mut bldr, image);
+    let rounds = 100;
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    let allocated_bytes = ALLOCATED_BYTES.load(Ordering::Relaxed);
+    for _ in 0..rounds {
+        round_trip(&link, &mut bldr, image);
+    }
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - allocations;
+    let allocated_bytes = ALLOCATED_BYTES.load(Ordering::Relaxed) - allocated_bytes;
+    println!(
+        "{}: {} allocations ({} bytes) per 1 MiB image",
+        name,
+        allocations / rounds,
+        allocated_bytes / rounds
+    );
+}
+
+fn publish(c: &mut Criterion) {
+    let image = vec![7; IMAGE_SIZE];
+    let round_trips: [(&str, RoundTrip); 2] = [
+        ("copying", round_trip_copying),
+        ("zero-copy", round_trip_zero_copy),
+    ];
+    for (name, round_trip) in round_trips {
+        report_allocations(name, round_trip, &image);
+    }
+
+    let mut group = c.benchmark_group("publish 1 MiB image");
+    group.throughput(Throughput::Bytes(IMAGE_SIZE as u64));
+    for (name, round_trip) in round_trips {
+        let link = Link::new(&format!("throughput-{}", name));
+        let mut bldr = FlatBufferBuilder::new();
+        group.bench_function(name, |b| b.iter(|| round_trip(&link, &mut bldr, &image)));
+    }
+    group.finish();
+}
+
+criterion_group!(benches, publish);
+criterion_main!(benches);
@@ -0,0 +1,64 @@
+//! An image scorer running as an external plugin, in a process of its own.
+//! Start an engine with an external plugin registered (e.g., a `PluginRegistry` with
+//! `register_external(3)` in place of the image scoring plugin), then run
+//!
+//!     cargo run --example external_scorer -- <engine host> <plugin id>
+//!
+//! The host defaults to localhost and the plugin id to 3. Every image gets a random score for
+//! the label "labrador", like the engine's own image scoring plugin.
+//!
+
+use plyoreacto::events::{Event, ImageScore, ImageScored};
+use plyoreacto::external::ExternalPluginClient;
+use plyoreacto::image_score_plugin::{ImageScorer, RandomScorer};
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let engine_host = args.next().unwrap_or_else(|| "localhost".to_string());
+    let plugin_id = match args.next() {
+        Some(plugin_id) => plugin_id.parse().expect("the plugin id must be a number"),
+        None => 3,
+    };
+
+    println!(
+        "External scorer connecting to the engine on {} as plugin {}",
+        engine_host, plugin_id
+    );
+    let mut client = ExternalPluginClient::connect(&engine_host, plugin_id, &["NewImageEvent"])
+        .expect("could not connect to the engine");
+    let mut scorer = RandomScorer;
+    loop {
+        let msg = client.next_event().expect("could not receive an event");
+        let image = match msg.decode() {
+            Ok(Event::NewImage(image)) => image,
+            Ok(Event::PluginTerminate) => {
+                println!("External scorer got terminate event, exiting");
+                return;
+            }
+            Ok(event) => {
+                println!("External scorer ignoring {}", event.type_name());
+                continue;
+            }
+            Err(e) => {
+                println!("External scorer could not decode {}: {}", msg.event_type, e);
+                continue;
+            }
+        };
+        let scores = scorer
+            .score(&image.image, &image.image_format)
+            .expect("the random scorer does not fail");
+        let event = ImageScored {
+            image_uuid: image.image_uuid.clone(),
+            scores: scores
+                .into_iter()
+                .map(|(label, probability)| ImageScore { label, probability })
+                .collect(),
+        };
+        match &msg.meta {
+            Some(meta) => client.publish_reply(meta, &event),
+            None => client.publish(&event),
+        }
+        .expect("could not publish the scores");
+        println!("External scorer scored image {}", image.image_uuid);
+    }
+}
@@ -0,0 +1,52 @@
+# automatically generated by the FlatBuffers compiler, do not modify
+
+# namespace: events
+
+import flatbuffers
+from flatbuffers.compat import import_numpy
+np = import_numpy()
+
+class BackpressureEvent(object):
+    __slots__ = ['_tab']
+
+    @classmethod
+    def GetRootAs(cls, buf, offset=0):
+        n = flatbuffers.encode.Get(flatbuffers.packer.uoffset, buf, offset)
+        x = BackpressureEvent()
+        x.Init(buf, n + offset)
+        return x
+
+    @classmethod
+    def GetRootAsBackpressureEvent(cls, buf, offset=0):
+        """This method is deprecated. Please switch to GetRootAs."""
+        return cls.GetRootAs(buf, offset)
+    # BackpressureEvent
+    def Init(self, buf, pos):
+        self._tab = flatbuffers.table.Table(buf, pos)
+
+    # BackpressureEvent
+    def PluginId(self):
+        o = flatbuffers.number_types.UOffsetTFlags.py_type(self._tab.Offset(4))
+        if o != 0:
+            return self._tab.Get(flatbuffers.number_types.Int32Flags, o + self._tab.Pos)
+        return 0
+
+    # BackpressureEvent
+    def Backlog(self):
+        o = flatbuffers.number_types.UOffsetTFlags.py_type(self._tab.Offset(6))
+        if o != 0:
+            return self._tab.Get(flatbuffers.number_types.Uint64Flags, o + self._tab.Pos)
+        return 0
+
+def BackpressureEventStart(builder): builder.StartObject(2)
+def Start(builder):
+    return BackpressureEventStart(builder)
+def BackpressureEventAddPluginId(builder, pluginId): builder.PrependInt32Slot(0, pluginId, 0)
+def AddPluginId(builder, pluginId):
+    return BackpressureEventAddPluginId(builder, pluginId)
+def BackpressureEventAddBacklog(builder, backlog): builder.PrependUint64Slot(1, backlog, 0)
+def AddBacklog(builder, backlog):
+    return BackpressureEventAddBacklog(builder, backlog)
+def BackpressureEventEnd(builder): return builder.EndObject()
+def End(builder):
+    return BackpressureEventEnd(builder)
\ No newline at end of file
@@ -0,0 +1,52 @@
+# automatically generated by the FlatBuffers compiler, do not modify
+
+# namespace: events
+
+import flatbuffers
+from flatbuffers.compat import import_numpy
+np = import_numpy()
+
+class EngineHeartbeatEvent(object):
+    __slots__ = ['_tab']
+
+    @classmethod
+    def GetRootAs(cls, buf, offset=0):
+        n = flatbuffers.encode.Get(flatbuffers.packer.uoffset, buf, offset)
+        x = EngineHeartbeatEvent()
+        x.Init(buf, n + offset)
+        return x
+
+    @classmethod
+    def GetRootAsEngineHeartbeatEvent(cls, buf, offset=0):
+        """This method is deprecated. Please switch to GetRootAs."""
+        return cls.GetRootAs(buf, offset)
+    # EngineHeartbeatEvent
+    def Init(self, buf, pos):
+        self._tab = flatbuffers.table.Table(buf, pos)
+
+    # EngineHeartbeatEvent
+    def Seq(self):
+        o = flatbuffers.number_types.UOffsetTFlags.py_type(self._tab.Offset(4))
+        if o != 0:
+            return self._tab.Get(flatbuffers.number_types.Uint64Flags, o + self._tab.Pos)
+        return 0
+
+    # EngineHeartbeatEvent
+    def UptimeMs(self):
+        o = flatbuffers.number_types.UOffsetTFlags.py_type(self._tab.Offset(6))
+        if o != 0:
+            return self._tab.Get(flatbuffers.number_types.Uint64Flags, o + self._tab.Pos)
+        return 0
+
+def EngineHeartbeatEventStart(builder): builder.StartObject(2)
+def Start(builder):
+    return EngineHeartbeatEventStart(builder)
+def EngineHeartbeatEventAddSeq(builder, seq): builder.PrependUint64Slot(0, seq, 0)
+def AddSeq(builder, seq):
+    return EngineHeartbeatEventAddSeq(builder, seq)
+def EngineHeartbeatEventAddUptimeMs(builder, uptimeMs): builder.PrependUint64Slot(1, uptimeMs, 0)
+def AddUptimeMs(builder, uptimeMs):
+    return EngineHeartbeatEventAddUptimeMs(builder, uptimeMs)
+def EngineHeartbeatEventEnd(builder): return builder.EndObject()
+def End(builder):
+    return EngineHeartbeatEventEnd(builder)
\ No newline at end of file
@@ -0,0 +1,62 @@
+# automatically generated by the FlatBuffers compiler, do not modify
+
+# namespace: events
+
+import flatbuffers
+from flatbuffers.compat import import_numpy
+np = import_numpy()
+
+class EngineStartedEvent(object):
+    __slots__ = ['_tab']
+
+    @classmethod
+    def GetRootAs(cls, buf, offset=0):
+        n = flatbuffers.encode.Get(flatbuffers.packer.uoffset, buf, offset)
+        x = EngineStartedEvent()
+        x.Init(buf, n + offset)
+        return x
+
+    @classmethod
+    def GetRootAsEngineStartedEvent(cls, buf, offset=0):
+        """This method is deprecated. Please switch to GetRootAs."""
+        return cls.GetRootAs(buf, offset)
+    # EngineStartedEvent
+    def Init(self, buf, pos):
+        self._tab = flatbuffers.table.Table(buf, pos)
+
+    # EngineStartedEvent
+    def EngineId(self):
+        o = flatbuffers.number_types.UOffsetTFlags.py_type(self._tab.Offset(4))
+        if o != 0:
+            return self._tab.Get(flatbuffers.number_types.Uint32Flags, o + self._tab.Pos)
+        return 0
+
+    # EngineStartedEvent
+    def ProtocolVersion(self):
+        o = flatbuffers.number_types.UOffsetTFlags.py_type(self._tab.Offset(6))
+        if o != 0:
+            return self._tab.Get(flatbuffers.number_types.Uint16Flags, o + self._tab.Pos)
+        return 0
+
+    # EngineStartedEvent
+    def StartedAtMs(self):
+        o = flatbuffers.number_types.UOffsetTFlags.py_type(self._tab.Offset(8))
+        if o != 0:
+            return self._tab.Get(flatbuffers.number_types.Uint64Flags, o + self._tab.Pos)
+        return 0
+
+def EngineStartedEventStart(builder): builder.StartObject(3)
+def Start(builder):
+    return EngineStartedEventStart(builder)
+def EngineStartedEventAddEngineId(builder, engineId): builder.PrependUint32Slot(0, engineId, 0)
+def AddEngineId(builder, engineId):
+    return EngineStartedEventAddEngineId(builder, engineId)
+def EngineStartedEventAddProtocolVersion(builder, protocolVersion): builder.PrependUint16Slot(1, protocolVersion, 0)
+def AddProtocolVersion(builder, protocolVersion):
+    return EngineStartedEventAddProtocolVersion(builder, protocolVersion)
+def EngineStartedEventAddStartedAtMs(builder, startedAtMs): builder.PrependUint64Slot(2, startedAtMs, 0)
+def AddStartedAtMs(builder, startedAtMs):
+    return EngineStartedEventAddStartedAtMs(builder, startedAtMs)
+def EngineStartedEventEnd(builder): return builder.EndObject()
+def End(builder):
+    return EngineStartedEventEnd(builder)
\ No newline at end of file
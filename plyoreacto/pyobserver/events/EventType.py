@@ -8,3 +8,27 @@ class EventType(object):
     ImageScoredEvent = 2
     ImageStoredEvent = 3
     ImageDeletedEvent = 4
+    PluginTerminateEvent = 5
+    PluginFailedEvent = 6
+    PluginRestartedEvent = 7
+    ImageScoreFailedEvent = 8
+    ImageRejectedEvent = 9
+    ImageStoreFailedEvent = 10
+    ImageDeletedRequestEvent = 11
+    MetricsSnapshotEvent = 12
+    WebhookDeliveryFailedEvent = 13
+    PluginJoinedEvent = 14
+    PluginLeftEvent = 15
+    EngineHeartbeatEvent = 16
+    PluginHeartbeatEvent = 17
+    EventsDroppedEvent = 18
+    EventRejectedEvent = 19
+    CustomEvent = 20
+    BackpressureEvent = 21
+    BackpressureRelievedEvent = 22
+    DeadLetterEvent = 23
+    SyncProbeEvent = 24
+    EngineStartedEvent = 25
+    EngineStoppingEvent = 26
+    PluginSkippedEvent = 27
+    TickEvent = 28
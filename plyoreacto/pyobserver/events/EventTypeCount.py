@@ -0,0 +1,62 @@
+# automatically generated by the FlatBuffers compiler, do not modify
+
+# namespace: events
+
+import flatbuffers
+from flatbuffers.compat import import_numpy
+np = import_numpy()
+
+class EventTypeCount(object):
+    __slots__ = ['_tab']
+
+    @classmethod
+    def GetRootAs(cls, buf, offset=0):
+        n = flatbuffers.encode.Get(flatbuffers.packer.uoffset, buf, offset)
+        x = EventTypeCount()
+        x.Init(buf, n + offset)
+        return x
+
+    @classmethod
+    def GetRootAsEventTypeCount(cls, buf, offset=0):
+        """This method is deprecated. Please switch to GetRootAs."""
+        return cls.GetRootAs(buf, offset)
+    # EventTypeCount
+    def Init(self, buf, pos):
+        self._tab = flatbuffers.table.Table(buf, pos)
+
+    # EventTypeCount
+    def EventType(self):
+        o = flatbuffers.number_types.UOffsetTFlags.py_type(self._tab.Offset(4))
+        if o != 0:
+            return self._tab.String(o + self._tab.Pos)
+        return None
+
+    # EventTypeCount
+    def Count(self):
+        o = flatbuffers.number_types.UOffsetTFlags.py_type(self._tab.Offset(6))
+        if o != 0:
+            return self._tab.Get(flatbuffers.number_types.Uint64Flags, o + self._tab.Pos)
+        return 0
+
+    # EventTypeCount
+    def Bytes(self):
+        o = flatbuffers.number_types.UOffsetTFlags.py_type(self._tab.Offset(8))
+        if o != 0:
+            return self._tab.Get(flatbuffers.number_types.Uint64Flags, o + self._tab.Pos)
+        return 0
+
+def EventTypeCountStart(builder): builder.StartObject(3)
+def Start(builder):
+    return EventTypeCountStart(builder)
+def EventTypeCountAddEventType(builder, eventType): builder.PrependUOffsetTRelativeSlot(0, flatbuffers.number_types.UOffsetTFlags.py_type(eventType), 0)
+def AddEventType(builder, eventType):
+    return EventTypeCountAddEventType(builder, eventType)
+def EventTypeCountAddCount(builder, count): builder.PrependUint64Slot(1, count, 0)
+def AddCount(builder, count):
+    return EventTypeCountAddCount(builder, count)
+def EventTypeCountAddBytes(builder, bytes): builder.PrependUint64Slot(2, bytes, 0)
+def AddBytes(builder, bytes):
+    return EventTypeCountAddBytes(builder, bytes)
+def EventTypeCountEnd(builder): return builder.EndObject()
+def End(builder):
+    return EventTypeCountEnd(builder)
\ No newline at end of file
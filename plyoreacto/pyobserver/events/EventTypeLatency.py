@@ -0,0 +1,92 @@
+# automatically generated by the FlatBuffers compiler, do not modify
+
+# namespace: events
+
+import flatbuffers
+from flatbuffers.compat import import_numpy
+np = import_numpy()
+
+class EventTypeLatency(object):
+    __slots__ = ['_tab']
+
+    @classmethod
+    def GetRootAs(cls, buf, offset=0):
+        n = flatbuffers.encode.Get(flatbuffers.packer.uoffset, buf, offset)
+        x = EventTypeLatency()
+        x.Init(buf, n + offset)
+        return x
+
+    @classmethod
+    def GetRootAsEventTypeLatency(cls, buf, offset=0):
+        """This method is deprecated. Please switch to GetRootAs."""
+        return cls.GetRootAs(buf, offset)
+    # EventTypeLatency
+    def Init(self, buf, pos):
+        self._tab = flatbuffers.table.Table(buf, pos)
+
+    # EventTypeLatency
+    def EventType(self):
+        o = flatbuffers.number_types.UOffsetTFlags.py_type(self._tab.Offset(4))
+        if o != 0:
+            return self._tab.String(o + self._tab.Pos)
+        return None
+
+    # EventTypeLatency
+    def Count(self):
+        o = flatbuffers.number_types.UOffsetTFlags.py_type(self._tab.Offset(6))
+        if o != 0:
+            return self._tab.Get(flatbuffers.number_types.Uint64Flags, o + self._tab.Pos)
+        return 0
+
+    # EventTypeLatency
+    def P50Us(self):
+        o = flatbuffers.number_types.UOffsetTFlags.py_type(self._tab.Offset(8))
+        if o != 0:
+            return self._tab.Get(flatbuffers.number_types.Uint64Flags, o + self._tab.Pos)
+        return 0
+
+    # EventTypeLatency
+    def P95Us(self):
+        o = flatbuffers.number_types.UOffsetTFlags.py_type(self._tab.Offset(10))
+        if o != 0:
+            return self._tab.Get(flatbuffers.number_types.Uint64Flags, o + self._tab.Pos)
+        return 0
+
+    # EventTypeLatency
+    def P99Us(self):
+        o = flatbuffers.number_types.UOffsetTFlags.py_type(self._tab.Offset(12))
+        if o != 0:
+            return self._tab.Get(flatbuffers.number_types.Uint64Flags, o + self._tab.Pos)
+        return 0
+
+    # EventTypeLatency
+    def MaxUs(self):
+        o = flatbuffers.number_types.UOffsetTFlags.py_type(self._tab.Offset(14))
+        if o != 0:
+            return self._tab.Get(flatbuffers.number_types.Uint64Flags, o + self._tab.Pos)
+        return 0
+
+def EventTypeLatencyStart(builder): builder.StartObject(6)
+def Start(builder):
+    return EventTypeLatencyStart(builder)
+def EventTypeLatencyAddEventType(builder, eventType): builder.PrependUOffsetTRelativeSlot(0, flatbuffers.number_types.UOffsetTFlags.py_type(eventType), 0)
+def AddEventType(builder, eventType):
+    return EventTypeLatencyAddEventType(builder, eventType)
+def EventTypeLatencyAddCount(builder, count): builder.PrependUint64Slot(1, count, 0)
+def AddCount(builder, count):
+    return EventTypeLatencyAddCount(builder, count)
+def EventTypeLatencyAddP50Us(builder, p50Us): builder.PrependUint64Slot(2, p50Us, 0)
+def AddP50Us(builder, p50Us):
+    return EventTypeLatencyAddP50Us(builder, p50Us)
+def EventTypeLatencyAddP95Us(builder, p95Us): builder.PrependUint64Slot(3, p95Us, 0)
+def AddP95Us(builder, p95Us):
+    return EventTypeLatencyAddP95Us(builder, p95Us)
+def EventTypeLatencyAddP99Us(builder, p99Us): builder.PrependUint64Slot(4, p99Us, 0)
+def AddP99Us(builder, p99Us):
+    return EventTypeLatencyAddP99Us(builder, p99Us)
+def EventTypeLatencyAddMaxUs(builder, maxUs): builder.PrependUint64Slot(5, maxUs, 0)
+def AddMaxUs(builder, maxUs):
+    return EventTypeLatencyAddMaxUs(builder, maxUs)
+def EventTypeLatencyEnd(builder): return builder.EndObject()
+def End(builder):
+    return EventTypeLatencyEnd(builder)
\ No newline at end of file
@@ -31,12 +31,22 @@ class ImageDeletedEvent(object):
             return self._tab.String(o + self._tab.Pos)
         return None
 
-def ImageDeletedEventStart(builder): builder.StartObject(1)
+    # ImageDeletedEvent
+    def Existed(self):
+        o = flatbuffers.number_types.UOffsetTFlags.py_type(self._tab.Offset(6))
+        if o != 0:
+            return bool(self._tab.Get(flatbuffers.number_types.BoolFlags, o + self._tab.Pos))
+        return False
+
+def ImageDeletedEventStart(builder): builder.StartObject(2)
 def Start(builder):
     return ImageDeletedEventStart(builder)
 def ImageDeletedEventAddImageUuid(builder, imageUuid): builder.PrependUOffsetTRelativeSlot(0, flatbuffers.number_types.UOffsetTFlags.py_type(imageUuid), 0)
 def AddImageUuid(builder, imageUuid):
     return ImageDeletedEventAddImageUuid(builder, imageUuid)
+def ImageDeletedEventAddExisted(builder, existed): builder.PrependBoolSlot(1, existed, 0)
+def AddExisted(builder, existed):
+    return ImageDeletedEventAddExisted(builder, existed)
 def ImageDeletedEventEnd(builder): return builder.EndObject()
 def End(builder):
     return ImageDeletedEventEnd(builder)
\ No newline at end of file
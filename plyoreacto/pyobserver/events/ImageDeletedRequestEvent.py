@@ -0,0 +1,42 @@
+# automatically generated by the FlatBuffers compiler, do not modify
+
+# namespace: events
+
+import flatbuffers
+from flatbuffers.compat import import_numpy
+np = import_numpy()
+
+class ImageDeletedRequestEvent(object):
+    __slots__ = ['_tab']
+
+    @classmethod
+    def GetRootAs(cls, buf, offset=0):
+        n = flatbuffers.encode.Get(flatbuffers.packer.uoffset, buf, offset)
+        x = ImageDeletedRequestEvent()
+        x.Init(buf, n + offset)
+        return x
+
+    @classmethod
+    def GetRootAsImageDeletedRequestEvent(cls, buf, offset=0):
+        """This method is deprecated. Please switch to GetRootAs."""
+        return cls.GetRootAs(buf, offset)
+    # ImageDeletedRequestEvent
+    def Init(self, buf, pos):
+        self._tab = flatbuffers.table.Table(buf, pos)
+
+    # ImageDeletedRequestEvent
+    def ImageUuid(self):
+        o = flatbuffers.number_types.UOffsetTFlags.py_type(self._tab.Offset(4))
+        if o != 0:
+            return self._tab.String(o + self._tab.Pos)
+        return None
+
+def ImageDeletedRequestEventStart(builder): builder.StartObject(1)
+def Start(builder):
+    return ImageDeletedRequestEventStart(builder)
+def ImageDeletedRequestEventAddImageUuid(builder, imageUuid): builder.PrependUOffsetTRelativeSlot(0, flatbuffers.number_types.UOffsetTFlags.py_type(imageUuid), 0)
+def AddImageUuid(builder, imageUuid):
+    return ImageDeletedRequestEventAddImageUuid(builder, imageUuid)
+def ImageDeletedRequestEventEnd(builder): return builder.EndObject()
+def End(builder):
+    return ImageDeletedRequestEventEnd(builder)
\ No newline at end of file
@@ -0,0 +1,62 @@
+# automatically generated by the FlatBuffers compiler, do not modify
+
+# namespace: events
+
+import flatbuffers
+from flatbuffers.compat import import_numpy
+np = import_numpy()
+
+class ImageRejectedEvent(object):
+    __slots__ = ['_tab']
+
+    @classmethod
+    def GetRootAs(cls, buf, offset=0):
+        n = flatbuffers.encode.Get(flatbuffers.packer.uoffset, buf, offset)
+        x = ImageRejectedEvent()
+        x.Init(buf, n + offset)
+        return x
+
+    @classmethod
+    def GetRootAsImageRejectedEvent(cls, buf, offset=0):
+        """This method is deprecated. Please switch to GetRootAs."""
+        return cls.GetRootAs(buf, offset)
+    # ImageRejectedEvent
+    def Init(self, buf, pos):
+        self._tab = flatbuffers.table.Table(buf, pos)
+
+    # ImageRejectedEvent
+    def ImageUuid(self):
+        o = flatbuffers.number_types.UOffsetTFlags.py_type(self._tab.Offset(4))
+        if o != 0:
+            return self._tab.String(o + self._tab.Pos)
+        return None
+
+    # ImageRejectedEvent
+    def TopLabel(self):
+        o = flatbuffers.number_types.UOffsetTFlags.py_type(self._tab.Offset(6))
+        if o != 0:
+            return self._tab.String(o + self._tab.Pos)
+        return None
+
+    # ImageRejectedEvent
+    def Probability(self):
+        o = flatbuffers.number_types.UOffsetTFlags.py_type(self._tab.Offset(8))
+        if o != 0:
+            return self._tab.Get(flatbuffers.number_types.Float32Flags, o + self._tab.Pos)
+        return 0.0
+
+def ImageRejectedEventStart(builder): builder.StartObject(3)
+def Start(builder):
+    return ImageRejectedEventStart(builder)
+def ImageRejectedEventAddImageUuid(builder, imageUuid): builder.PrependUOffsetTRelativeSlot(0, flatbuffers.number_types.UOffsetTFlags.py_type(imageUuid), 0)
+def AddImageUuid(builder, imageUuid):
+    return ImageRejectedEventAddImageUuid(builder, imageUuid)
+def ImageRejectedEventAddTopLabel(builder, topLabel): builder.PrependUOffsetTRelativeSlot(1, flatbuffers.number_types.UOffsetTFlags.py_type(topLabel), 0)
+def AddTopLabel(builder, topLabel):
+    return ImageRejectedEventAddTopLabel(builder, topLabel)
+def ImageRejectedEventAddProbability(builder, probability): builder.PrependFloat32Slot(2, probability, 0.0)
+def AddProbability(builder, probability):
+    return ImageRejectedEventAddProbability(builder, probability)
+def ImageRejectedEventEnd(builder): return builder.EndObject()
+def End(builder):
+    return ImageRejectedEventEnd(builder)
\ No newline at end of file
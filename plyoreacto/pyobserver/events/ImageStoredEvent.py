@@ -31,12 +31,32 @@ class ImageStoredEvent(object):
             return self._tab.String(o + self._tab.Pos)
         return None
 
-def ImageStoredEventStart(builder): builder.StartObject(1)
+    # ImageStoredEvent
+    def Path(self):
+        o = flatbuffers.number_types.UOffsetTFlags.py_type(self._tab.Offset(6))
+        if o != 0:
+            return self._tab.String(o + self._tab.Pos)
+        return None
+
+    # ImageStoredEvent
+    def Deduplicated(self):
+        o = flatbuffers.number_types.UOffsetTFlags.py_type(self._tab.Offset(8))
+        if o != 0:
+            return bool(self._tab.Get(flatbuffers.number_types.BoolFlags, o + self._tab.Pos))
+        return False
+
+def ImageStoredEventStart(builder): builder.StartObject(3)
 def Start(builder):
     return ImageStoredEventStart(builder)
 def ImageStoredEventAddImageUuid(builder, imageUuid): builder.PrependUOffsetTRelativeSlot(0, flatbuffers.number_types.UOffsetTFlags.py_type(imageUuid), 0)
 def AddImageUuid(builder, imageUuid):
     return ImageStoredEventAddImageUuid(builder, imageUuid)
+def ImageStoredEventAddPath(builder, path): builder.PrependUOffsetTRelativeSlot(1, flatbuffers.number_types.UOffsetTFlags.py_type(path), 0)
+def AddPath(builder, path):
+    return ImageStoredEventAddPath(builder, path)
+def ImageStoredEventAddDeduplicated(builder, deduplicated): builder.PrependBoolSlot(2, deduplicated, 0)
+def AddDeduplicated(builder, deduplicated):
+    return ImageStoredEventAddDeduplicated(builder, deduplicated)
 def ImageStoredEventEnd(builder): return builder.EndObject()
 def End(builder):
     return ImageStoredEventEnd(builder)
\ No newline at end of file
@@ -0,0 +1,94 @@
+# automatically generated by the FlatBuffers compiler, do not modify
+
+# namespace: events
+
+import flatbuffers
+from flatbuffers.compat import import_numpy
+np = import_numpy()
+
+class MetricsSnapshotEvent(object):
+    __slots__ = ['_tab']
+
+    @classmethod
+    def GetRootAs(cls, buf, offset=0):
+        n = flatbuffers.encode.Get(flatbuffers.packer.uoffset, buf, offset)
+        x = MetricsSnapshotEvent()
+        x.Init(buf, n + offset)
+        return x
+
+    @classmethod
+    def GetRootAsMetricsSnapshotEvent(cls, buf, offset=0):
+        """This method is deprecated. Please switch to GetRootAs."""
+        return cls.GetRootAs(buf, offset)
+    # MetricsSnapshotEvent
+    def Init(self, buf, pos):
+        self._tab = flatbuffers.table.Table(buf, pos)
+
+    # MetricsSnapshotEvent
+    def Counts(self, j):
+        o = flatbuffers.number_types.UOffsetTFlags.py_type(self._tab.Offset(4))
+        if o != 0:
+            x = self._tab.Vector(o)
+            x += flatbuffers.number_types.UOffsetTFlags.py_type(j) * 4
+            x = self._tab.Indirect(x)
+            from events.EventTypeCount import EventTypeCount
+            obj = EventTypeCount()
+            obj.Init(self._tab.Bytes, x)
+            return obj
+        return None
+
+    # MetricsSnapshotEvent
+    def CountsLength(self):
+        o = flatbuffers.number_types.UOffsetTFlags.py_type(self._tab.Offset(4))
+        if o != 0:
+            return self._tab.VectorLen(o)
+        return 0
+
+    # MetricsSnapshotEvent
+    def CountsIsNone(self):
+        o = flatbuffers.number_types.UOffsetTFlags.py_type(self._tab.Offset(4))
+        return o == 0
+
+    # MetricsSnapshotEvent
+    def Latencies(self, j):
+        o = flatbuffers.number_types.UOffsetTFlags.py_type(self._tab.Offset(6))
+        if o != 0:
+            x = self._tab.Vector(o)
+            x += flatbuffers.number_types.UOffsetTFlags.py_type(j) * 4
+            x = self._tab.Indirect(x)
+            from events.EventTypeLatency import EventTypeLatency
+            obj = EventTypeLatency()
+            obj.Init(self._tab.Bytes, x)
+            return obj
+        return None
+
+    # MetricsSnapshotEvent
+    def LatenciesLength(self):
+        o = flatbuffers.number_types.UOffsetTFlags.py_type(self._tab.Offset(6))
+        if o != 0:
+            return self._tab.VectorLen(o)
+        return 0
+
+    # MetricsSnapshotEvent
+    def LatenciesIsNone(self):
+        o = flatbuffers.number_types.UOffsetTFlags.py_type(self._tab.Offset(6))
+        return o == 0
+
+def MetricsSnapshotEventStart(builder): builder.StartObject(2)
+def Start(builder):
+    return MetricsSnapshotEventStart(builder)
+def MetricsSnapshotEventAddCounts(builder, counts): builder.PrependUOffsetTRelativeSlot(0, flatbuffers.number_types.UOffsetTFlags.py_type(counts), 0)
+def AddCounts(builder, counts):
+    return MetricsSnapshotEventAddCounts(builder, counts)
+def MetricsSnapshotEventStartCountsVector(builder, numElems): return builder.StartVector(4, numElems, 4)
+def StartCountsVector(builder, numElems):
+    return MetricsSnapshotEventStartCountsVector(builder, numElems)
+def MetricsSnapshotEventAddLatencies(builder, latencies): builder.PrependUOffsetTRelativeSlot(1, flatbuffers.number_types.UOffsetTFlags.py_type(latencies), 0)
+def AddLatencies(builder, latencies):
+    return MetricsSnapshotEventAddLatencies(builder, latencies)
+def MetricsSnapshotEventStartLatenciesVector(builder, numElems): return builder.StartVector(4, numElems, 4)
+def StartLatenciesVector(builder, numElems):
+    return MetricsSnapshotEventStartLatenciesVector(builder, numElems)
+def MetricsSnapshotEventEnd(builder): return builder.EndObject()
+def End(builder):
+    return MetricsSnapshotEventEnd(builder)
\ No newline at end of file
@@ -65,7 +65,14 @@ class NewImageEvent(object):
         o = flatbuffers.number_types.UOffsetTFlags.py_type(self._tab.Offset(8))
         return o == 0
 
-def NewImageEventStart(builder): builder.StartObject(3)
+    # NewImageEvent
+    def Location(self):
+        o = flatbuffers.number_types.UOffsetTFlags.py_type(self._tab.Offset(10))
+        if o != 0:
+            return self._tab.String(o + self._tab.Pos)
+        return None
+
+def NewImageEventStart(builder): builder.StartObject(4)
 def Start(builder):
     return NewImageEventStart(builder)
 def NewImageEventAddImageUuid(builder, imageUuid): builder.PrependUOffsetTRelativeSlot(0, flatbuffers.number_types.UOffsetTFlags.py_type(imageUuid), 0)
@@ -77,6 +84,9 @@ def AddImageFormat(builder, imageFormat):
 def NewImageEventAddImage(builder, image): builder.PrependUOffsetTRelativeSlot(2, flatbuffers.number_types.UOffsetTFlags.py_type(image), 0)
 def AddImage(builder, image):
     return NewImageEventAddImage(builder, image)
+def NewImageEventAddLocation(builder, location): builder.PrependUOffsetTRelativeSlot(3, flatbuffers.number_types.UOffsetTFlags.py_type(location), 0)
+def AddLocation(builder, location):
+    return NewImageEventAddLocation(builder, location)
 def NewImageEventStartImageVector(builder, numElems): return builder.StartVector(1, numElems, 1)
 def StartImageVector(builder, numElems):
     return NewImageEventStartImageVector(builder, numElems)
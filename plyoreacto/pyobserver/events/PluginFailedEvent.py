@@ -0,0 +1,52 @@
+# automatically generated by the FlatBuffers compiler, do not modify
+
+# namespace: events
+
+import flatbuffers
+from flatbuffers.compat import import_numpy
+np = import_numpy()
+
+class PluginFailedEvent(object):
+    __slots__ = ['_tab']
+
+    @classmethod
+    def GetRootAs(cls, buf, offset=0):
+        n = flatbuffers.encode.Get(flatbuffers.packer.uoffset, buf, offset)
+        x = PluginFailedEvent()
+        x.Init(buf, n + offset)
+        return x
+
+    @classmethod
+    def GetRootAsPluginFailedEvent(cls, buf, offset=0):
+        """This method is deprecated. Please switch to GetRootAs."""
+        return cls.GetRootAs(buf, offset)
+    # PluginFailedEvent
+    def Init(self, buf, pos):
+        self._tab = flatbuffers.table.Table(buf, pos)
+
+    # PluginFailedEvent
+    def PluginId(self):
+        o = flatbuffers.number_types.UOffsetTFlags.py_type(self._tab.Offset(4))
+        if o != 0:
+            return self._tab.Get(flatbuffers.number_types.Int32Flags, o + self._tab.Pos)
+        return 0
+
+    # PluginFailedEvent
+    def Message(self):
+        o = flatbuffers.number_types.UOffsetTFlags.py_type(self._tab.Offset(6))
+        if o != 0:
+            return self._tab.String(o + self._tab.Pos)
+        return None
+
+def PluginFailedEventStart(builder): builder.StartObject(2)
+def Start(builder):
+    return PluginFailedEventStart(builder)
+def PluginFailedEventAddPluginId(builder, pluginId): builder.PrependInt32Slot(0, pluginId, 0)
+def AddPluginId(builder, pluginId):
+    return PluginFailedEventAddPluginId(builder, pluginId)
+def PluginFailedEventAddMessage(builder, message): builder.PrependUOffsetTRelativeSlot(1, flatbuffers.number_types.UOffsetTFlags.py_type(message), 0)
+def AddMessage(builder, message):
+    return PluginFailedEventAddMessage(builder, message)
+def PluginFailedEventEnd(builder): return builder.EndObject()
+def End(builder):
+    return PluginFailedEventEnd(builder)
\ No newline at end of file
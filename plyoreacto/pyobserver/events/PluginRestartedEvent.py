@@ -0,0 +1,52 @@
+# automatically generated by the FlatBuffers compiler, do not modify
+
+# namespace: events
+
+import flatbuffers
+from flatbuffers.compat import import_numpy
+np = import_numpy()
+
+class PluginRestartedEvent(object):
+    __slots__ = ['_tab']
+
+    @classmethod
+    def GetRootAs(cls, buf, offset=0):
+        n = flatbuffers.encode.Get(flatbuffers.packer.uoffset, buf, offset)
+        x = PluginRestartedEvent()
+        x.Init(buf, n + offset)
+        return x
+
+    @classmethod
+    def GetRootAsPluginRestartedEvent(cls, buf, offset=0):
+        """This method is deprecated. Please switch to GetRootAs."""
+        return cls.GetRootAs(buf, offset)
+    # PluginRestartedEvent
+    def Init(self, buf, pos):
+        self._tab = flatbuffers.table.Table(buf, pos)
+
+    # PluginRestartedEvent
+    def PluginId(self):
+        o = flatbuffers.number_types.UOffsetTFlags.py_type(self._tab.Offset(4))
+        if o != 0:
+            return self._tab.Get(flatbuffers.number_types.Int32Flags, o + self._tab.Pos)
+        return 0
+
+    # PluginRestartedEvent
+    def RestartCount(self):
+        o = flatbuffers.number_types.UOffsetTFlags.py_type(self._tab.Offset(6))
+        if o != 0:
+            return self._tab.Get(flatbuffers.number_types.Uint32Flags, o + self._tab.Pos)
+        return 0
+
+def PluginRestartedEventStart(builder): builder.StartObject(2)
+def Start(builder):
+    return PluginRestartedEventStart(builder)
+def PluginRestartedEventAddPluginId(builder, pluginId): builder.PrependInt32Slot(0, pluginId, 0)
+def AddPluginId(builder, pluginId):
+    return PluginRestartedEventAddPluginId(builder, pluginId)
+def PluginRestartedEventAddRestartCount(builder, restartCount): builder.PrependUint32Slot(1, restartCount, 0)
+def AddRestartCount(builder, restartCount):
+    return PluginRestartedEventAddRestartCount(builder, restartCount)
+def PluginRestartedEventEnd(builder): return builder.EndObject()
+def End(builder):
+    return PluginRestartedEventEnd(builder)
\ No newline at end of file
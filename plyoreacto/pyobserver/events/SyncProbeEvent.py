@@ -0,0 +1,42 @@
+# automatically generated by the FlatBuffers compiler, do not modify
+
+# namespace: events
+
+import flatbuffers
+from flatbuffers.compat import import_numpy
+np = import_numpy()
+
+class SyncProbeEvent(object):
+    __slots__ = ['_tab']
+
+    @classmethod
+    def GetRootAs(cls, buf, offset=0):
+        n = flatbuffers.encode.Get(flatbuffers.packer.uoffset, buf, offset)
+        x = SyncProbeEvent()
+        x.Init(buf, n + offset)
+        return x
+
+    @classmethod
+    def GetRootAsSyncProbeEvent(cls, buf, offset=0):
+        """This method is deprecated. Please switch to GetRootAs."""
+        return cls.GetRootAs(buf, offset)
+    # SyncProbeEvent
+    def Init(self, buf, pos):
+        self._tab = flatbuffers.table.Table(buf, pos)
+
+    # SyncProbeEvent
+    def Seq(self):
+        o = flatbuffers.number_types.UOffsetTFlags.py_type(self._tab.Offset(4))
+        if o != 0:
+            return self._tab.Get(flatbuffers.number_types.Uint64Flags, o + self._tab.Pos)
+        return 0
+
+def SyncProbeEventStart(builder): builder.StartObject(1)
+def Start(builder):
+    return SyncProbeEventStart(builder)
+def SyncProbeEventAddSeq(builder, seq): builder.PrependUint64Slot(0, seq, 0)
+def AddSeq(builder, seq):
+    return SyncProbeEventAddSeq(builder, seq)
+def SyncProbeEventEnd(builder): return builder.EndObject()
+def End(builder):
+    return SyncProbeEventEnd(builder)
\ No newline at end of file
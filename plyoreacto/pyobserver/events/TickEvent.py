@@ -0,0 +1,52 @@
+# automatically generated by the FlatBuffers compiler, do not modify
+
+# namespace: events
+
+import flatbuffers
+from flatbuffers.compat import import_numpy
+np = import_numpy()
+
+class TickEvent(object):
+    __slots__ = ['_tab']
+
+    @classmethod
+    def GetRootAs(cls, buf, offset=0):
+        n = flatbuffers.encode.Get(flatbuffers.packer.uoffset, buf, offset)
+        x = TickEvent()
+        x.Init(buf, n + offset)
+        return x
+
+    @classmethod
+    def GetRootAsTickEvent(cls, buf, offset=0):
+        """This method is deprecated. Please switch to GetRootAs."""
+        return cls.GetRootAs(buf, offset)
+    # TickEvent
+    def Init(self, buf, pos):
+        self._tab = flatbuffers.table.Table(buf, pos)
+
+    # TickEvent
+    def Seq(self):
+        o = flatbuffers.number_types.UOffsetTFlags.py_type(self._tab.Offset(4))
+        if o != 0:
+            return self._tab.Get(flatbuffers.number_types.Uint64Flags, o + self._tab.Pos)
+        return 0
+
+    # TickEvent
+    def ScheduledAtMs(self):
+        o = flatbuffers.number_types.UOffsetTFlags.py_type(self._tab.Offset(6))
+        if o != 0:
+            return self._tab.Get(flatbuffers.number_types.Uint64Flags, o + self._tab.Pos)
+        return 0
+
+def TickEventStart(builder): builder.StartObject(2)
+def Start(builder):
+    return TickEventStart(builder)
+def TickEventAddSeq(builder, seq): builder.PrependUint64Slot(0, seq, 0)
+def AddSeq(builder, seq):
+    return TickEventAddSeq(builder, seq)
+def TickEventAddScheduledAtMs(builder, scheduledAtMs): builder.PrependUint64Slot(1, scheduledAtMs, 0)
+def AddScheduledAtMs(builder, scheduledAtMs):
+    return TickEventAddScheduledAtMs(builder, scheduledAtMs)
+def TickEventEnd(builder): return builder.EndObject()
+def End(builder):
+    return TickEventEnd(builder)
\ No newline at end of file
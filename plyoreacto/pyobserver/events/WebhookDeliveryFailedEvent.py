@@ -0,0 +1,82 @@
+# automatically generated by the FlatBuffers compiler, do not modify
+
+# namespace: events
+
+import flatbuffers
+from flatbuffers.compat import import_numpy
+np = import_numpy()
+
+class WebhookDeliveryFailedEvent(object):
+    __slots__ = ['_tab']
+
+    @classmethod
+    def GetRootAs(cls, buf, offset=0):
+        n = flatbuffers.encode.Get(flatbuffers.packer.uoffset, buf, offset)
+        x = WebhookDeliveryFailedEvent()
+        x.Init(buf, n + offset)
+        return x
+
+    @classmethod
+    def GetRootAsWebhookDeliveryFailedEvent(cls, buf, offset=0):
+        """This method is deprecated. Please switch to GetRootAs."""
+        return cls.GetRootAs(buf, offset)
+    # WebhookDeliveryFailedEvent
+    def Init(self, buf, pos):
+        self._tab = flatbuffers.table.Table(buf, pos)
+
+    # WebhookDeliveryFailedEvent
+    def ImageUuid(self):
+        o = flatbuffers.number_types.UOffsetTFlags.py_type(self._tab.Offset(4))
+        if o != 0:
+            return self._tab.String(o + self._tab.Pos)
+        return None
+
+    # WebhookDeliveryFailedEvent
+    def EventType(self):
+        o = flatbuffers.number_types.UOffsetTFlags.py_type(self._tab.Offset(6))
+        if o != 0:
+            return self._tab.String(o + self._tab.Pos)
+        return None
+
+    # WebhookDeliveryFailedEvent
+    def Url(self):
+        o = flatbuffers.number_types.UOffsetTFlags.py_type(self._tab.Offset(8))
+        if o != 0:
+            return self._tab.String(o + self._tab.Pos)
+        return None
+
+    # WebhookDeliveryFailedEvent
+    def StatusCode(self):
+        o = flatbuffers.number_types.UOffsetTFlags.py_type(self._tab.Offset(10))
+        if o != 0:
+            return self._tab.Get(flatbuffers.number_types.Uint16Flags, o + self._tab.Pos)
+        return 0
+
+    # WebhookDeliveryFailedEvent
+    def Error(self):
+        o = flatbuffers.number_types.UOffsetTFlags.py_type(self._tab.Offset(12))
+        if o != 0:
+            return self._tab.String(o + self._tab.Pos)
+        return None
+
+def WebhookDeliveryFailedEventStart(builder): builder.StartObject(5)
+def Start(builder):
+    return WebhookDeliveryFailedEventStart(builder)
+def WebhookDeliveryFailedEventAddImageUuid(builder, imageUuid): builder.PrependUOffsetTRelativeSlot(0, flatbuffers.number_types.UOffsetTFlags.py_type(imageUuid), 0)
+def AddImageUuid(builder, imageUuid):
+    return WebhookDeliveryFailedEventAddImageUuid(builder, imageUuid)
+def WebhookDeliveryFailedEventAddEventType(builder, eventType): builder.PrependUOffsetTRelativeSlot(1, flatbuffers.number_types.UOffsetTFlags.py_type(eventType), 0)
+def AddEventType(builder, eventType):
+    return WebhookDeliveryFailedEventAddEventType(builder, eventType)
+def WebhookDeliveryFailedEventAddUrl(builder, url): builder.PrependUOffsetTRelativeSlot(2, flatbuffers.number_types.UOffsetTFlags.py_type(url), 0)
+def AddUrl(builder, url):
+    return WebhookDeliveryFailedEventAddUrl(builder, url)
+def WebhookDeliveryFailedEventAddStatusCode(builder, statusCode): builder.PrependUint16Slot(3, statusCode, 0)
+def AddStatusCode(builder, statusCode):
+    return WebhookDeliveryFailedEventAddStatusCode(builder, statusCode)
+def WebhookDeliveryFailedEventAddError(builder, error): builder.PrependUOffsetTRelativeSlot(4, flatbuffers.number_types.UOffsetTFlags.py_type(error), 0)
+def AddError(builder, error):
+    return WebhookDeliveryFailedEventAddError(builder, error)
+def WebhookDeliveryFailedEventEnd(builder): return builder.EndObject()
+def End(builder):
+    return WebhookDeliveryFailedEventEnd(builder)
\ No newline at end of file
@@ -2,10 +2,38 @@
 Module with utilities for working with Flatbuffer messages representing events.
 """
 
+import zstandard
+
 from events.Event import Event
 from events.EventType import EventType
 from events import NewImageEvent, ImageScoredEvent, ImageStoredEvent, ImageDeletedEvent
 
+# flag in the last byte of an envelope whose payload is zstd-compressed
+META_COMPRESSED = 2
+
+
+def parse_event_frames(frames):
+    """
+    Takes the frames of a message as received from a socket and returns its event type name and
+    the Flatbuffers bytes. Messages are sent in two frames: the header (the type name followed by
+    a NUL byte) and the Flatbuffers bytes. Events published by Rust plugins have a third frame,
+    with their envelope, between the two; it is skipped, after decompressing the payload if the
+    envelope says it is compressed. Single frame messages, with the Flatbuffers bytes directly
+    after the header, are accepted too.
+    """
+    if len(frames) == 3:
+        header, meta, payload = frames
+        if meta[-1] & META_COMPRESSED:
+            payload = zstandard.ZstdDecompressor().decompress(payload)
+    elif len(frames) == 2:
+        header, payload = frames
+    elif len(frames) == 1:
+        end = frames[0].index(b"\0") + 1
+        header, payload = frames[0][:end], frames[0][end:]
+    else:
+        raise Exception(f"Bad message; expected 1 to 3 frames, got {len(frames)}")
+    return header.rstrip(b"\0").decode(), payload
+
 
 def bytes_to_event(b):
     """
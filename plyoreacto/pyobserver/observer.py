@@ -18,9 +18,11 @@ def start(pub_socket, sub_socket, bldr):
 
     # process unlimited messages
     while True:
-        msg_bytes = sub_socket.recv(copy=True)
+        frames = sub_socket.recv_multipart(copy=True)
         messages = messages + 1
         print("Observer got a new event\n")
+        # the first frame holds the event type, the second the event itself
+        _, msg_bytes = msgevents.parse_event_frames(frames)
         # convert the byte array to a specialized event
         event = msgevents.bytes_to_typed_event(msg_bytes)
         image_uuid = event.ImageUuid()
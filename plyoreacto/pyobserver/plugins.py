@@ -8,6 +8,8 @@ import zmq
 
 # network-addressable host of the engine
 ENGINE_HOST = 'engine'
+# TCP port of the engine's sync socket
+ENGINE_SYNC_PORT = 5000
 
 # TODO -- "discover" plugins dynamically from a configuration file, etc.
 import observer
@@ -43,31 +45,24 @@ def get_sub_socket(context):
     # sub_socket.setsockopt_string(zmq.SUBSCRIBE, "type:1,")
 
 def get_event_type_bytes_filter(sub):
-    # return a byte array corresponding to the filter needed to subscribe to a message
-    if sub == 'NewImageEvent':
-        return bytes([12, 0, 0, 0, 8, 0, 14, 0, 7, 0, 8, 0, 8, 0, 0, 0, 0, 0, 0, 1])
-    if sub == 'ImageScoredEvent':
-        return bytes([12, 0, 0, 0, 8, 0, 12, 0, 7, 0, 8, 0, 8, 0, 0, 0, 0, 0, 0, 2])
-    if sub == "ImageStoredEvent":
-        return bytes([12, 0, 0, 0, 8, 0, 14, 0, 7, 0, 8, 0, 8, 0, 0, 0, 0, 0, 0, 3])
-    if sub == "ImageDeletedEvent":
-        return  bytes([12, 0, 0, 0, 8, 0, 14, 0, 7, 0, 8, 0, 8, 0, 0, 0, 0, 0, 0, 4])
+    # return a byte array corresponding to the filter needed to subscribe to a message;
+    # every message starts with a header made of the event type name followed by a NUL byte
+    return sub.encode() + b"\0"
 
 def sync_plugin(plugin_id, context):
     # sync with the engine
     sync = context.socket(zmq.REQ)
-    # Plugins connect to sync sockets on different ports; the numbering is 0-based, starting at 5000.
-    
-    sync_port = 5000 + plugin_id
-    sync_socket_connect_str = f"tcp://{ENGINE_HOST}:{sync_port}"
+    # All plugins connect to the same sync socket and announce themselves with their plugin id.
+    sync_socket_connect_str = f"tcp://{ENGINE_HOST}:{ENGINE_SYNC_PORT}"
     sync.connect(sync_socket_connect_str)
     print(f"{datetime.datetime.now()}: plugin {plugin_id} connected to sync socket: {sync_socket_connect_str}.")
-    sync.send_string("ok")
+    sync.send_string(f"ready {plugin_id}")
     print(f"{datetime.datetime.now()}: plugin {plugin_id} sent message on sync socket.")
-    # wait for reply
-    sync.recv_string()
-    print(f"{datetime.datetime.now()}: plugin {plugin_id} got reply on sync socket.")
-    return True
+    # wait for reply; the engine replies "abort" when some other plugin failed to sync in time,
+    # and a reply starting with "rejected" when it does not accept this plugin's id
+    reply = sync.recv_string()
+    print(f"{datetime.datetime.now()}: plugin {plugin_id} got reply on sync socket: {reply}.")
+    return reply == "ok"
 
 def start_plugin(plugin, context):
     # create publish and subscribe sockets for the plugins
@@ -84,7 +79,9 @@ def start_plugin(plugin, context):
     bldr = flatbuffers.Builder(1024)
 
     # sync with the engine
-    sync_plugin(plugin_id, context)
+    if not sync_plugin(plugin_id, context):
+        print(f"{datetime.datetime.now()}: engine aborted startup; plugin {plugin_id} will not be started")
+        return None
 
     # start plugin in a separate thread
     p_thread = threading.Thread(target=plugin['start_function'], 
@@ -103,7 +100,9 @@ def main():
 
     # start plugins in their own thread
     for p in python_plugins:
-        threads.append(start_plugin(p, context))
+        t = start_plugin(p, context)
+        if t is not None:
+            threads.append(t)
 
     # wait for all threads to complete
     for t in threads:
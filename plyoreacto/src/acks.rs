@@ -0,0 +1,955 @@
+//! At-least-once delivery. The engine forwards events over PUB/SUB, which drops those of a
+//! plugin that is down, e.g., while it is restarted. An engine started with
+//! `EventEngineBuilder::acknowledged_delivery(..)` delivers the events of chosen types to chosen
+//! plugins at least once instead: it forwards events with its own loop, which holds on to each
+//! of them in a retry buffer until the plugin acknowledges it with `PluginContext::ack`, over a
+//! DEALER socket of the plugin connected to a ROUTER socket of the engine. Once the oldest event
+//! of a type has waited for the timeout, it is sent again to that plugin alone, over the same
+//! back-channel, followed by the others of its type the plugin has not acknowledged, in the
+//! order they were forwarded. An event sent the maximum number of times, or the oldest one of
+//! a full buffer, is published as a DeadLetterEvent instead (see the `dead_letter_plugin`
+//! module). With a directory, the buffer is kept on disk as well, and an engine started again
+//! sends the events left in it once their timeout is up.
+//!
+//! ```no_run
+//! use plyoreacto::acks::AckConfig;
+//! use plyoreacto::event_engine::EventEngineBuilder;
+//!
+//! EventEngineBuilder::new()
+//!     .acknowledged_delivery(
+//!         AckConfig::new()
+//!             .deliver("NewImageEvent", 3)
+//!             .deliver("ImageScoredEvent", 3),
+//!     )
+//!     .run()
+//!     .expect("Error from engine");
+//! ```
+//!
+//! A plugin may get an event more than once, e.g., when it failed after handling the event but
+//! before acknowledging it. `PluginContext::next_event` skips the events the plugin already
+//! acknowledged; a plugin restarted in between does not know about those, so it should
+//! deduplicate them itself, as the image store plugin does by image uuid. Only the events
+//! published with an envelope (see `EventMeta`) can be acknowledged, and only the plugins run
+//! by the engine, in a thread of their own, can acknowledge them.
+//!
+
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use flatbuffers::FlatBufferBuilder;
+use log::{debug, warn};
+use uuid::Uuid;
+use zmq::Socket;
+
+use crate::event_engine::{bind, connect, create_socket, EngineConfig, EngineError};
+use crate::events::{
+    event_type_names, peek_event_messages, recv_event_frames, send_event_msg, DeadLetter,
+    EventMeta, EventPayload,
+};
+use crate::middleware::send_frames;
+use crate::plugin_registry::PluginRegistry;
+
+/// How long the engine waits for an acknowledgement by default before sending an event again.
+pub const DEFAULT_ACK_TIMEOUT: Duration = Duration::from_secs(5);
+/// How many times the engine sends an event by default before dead-lettering it.
+pub const DEFAULT_MAX_ATTEMPTS: u32 = 5;
+/// How many events of a type the engine holds for a plugin by default.
+pub const DEFAULT_CAPACITY: usize = 10_000;
+
+// How many of the events it acknowledged a plugin remembers, to skip them if they come again.
+const ACKNOWLEDGED_MEMORY: usize = 10_000;
+
+// Suffix of the files the events are kept in.
+const EVENT_SUFFIX: &str = ".event";
+
+/// Which events an engine delivers at least once, and how; see the module documentation.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AckConfig {
+    // the event types delivered at least once, each with the id of the plugin it is delivered to
+    pub deliveries: Vec<(String, i32)>,
+    // how long the engine waits for an acknowledgement before sending an event again
+    pub timeout: Duration,
+    // how many times an event is sent to a plugin, the first time included, before it is
+    // dead-lettered
+    pub max_attempts: u32,
+    // how many events of a type the engine holds for a plugin before dead-lettering the oldest
+    pub capacity: usize,
+    // directory the buffer is kept in as well, created if it does not exist; None (the default)
+    // keeps it in memory only
+    pub dir: Option<PathBuf>,
+}
+
+impl Default for AckConfig {
+    fn default() -> Self {
+        AckConfig {
+            deliveries: Vec::new(),
+            timeout: DEFAULT_ACK_TIMEOUT,
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            capacity: DEFAULT_CAPACITY,
+            dir: None,
+        }
+    }
+}
+
+impl AckConfig {
+    /// Deliver no event at least once yet; the engine waits 5 s for an acknowledgement, sends
+    /// an event up to 5 times and holds up to 10000 events of each type for each plugin, in
+    /// memory.
+    pub fn new() -> Self {
+        AckConfig::default()
+    }
+
+    /// Deliver the events of type `event_type` at least once to the plugin `plugin_id`, which
+    /// must be run by the engine and subscribe to them.
+    pub fn deliver(mut self, event_type: &str, plugin_id: i32) -> Self {
+        self.deliveries.push((event_type.to_string(), plugin_id));
+        self
+    }
+
+    /// Send an event again once it has waited for `timeout` without being acknowledged.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Dead-letter an event once it has been sent `attempts` times without being acknowledged.
+    pub fn max_attempts(mut self, attempts: u32) -> Self {
+        self.max_attempts = attempts;
+        self
+    }
+
+    /// Hold up to `events` events of each type for each plugin.
+    pub fn capacity(mut self, events: usize) -> Self {
+        self.capacity = events;
+        self
+    }
+
+    /// Keep the buffer in `dir` as well, for an engine started again to send the events that
+    /// were not acknowledged.
+    pub fn dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.dir = Some(dir.into());
+        self
+    }
+
+    // The event types delivered at least once to the plugin `plugin_id`.
+    fn event_types(&self, plugin_id: i32) -> HashSet<String> {
+        self.deliveries
+            .iter()
+            .filter(|(_, id)| *id == plugin_id)
+            .map(|(event_type, _)| event_type.clone())
+            .collect()
+    }
+
+    // Check that the events are of known types, and delivered to plugins run by the engine in a
+    // thread of their own.
+    pub(crate) fn check(&self, plugins: &PluginRegistry) -> Result<(), EngineError> {
+        for (event_type, plugin_id) in &self.deliveries {
+            if !event_type_names().contains(&event_type.as_str()) {
+                return Err(EngineError::UnknownEventType {
+                    event_type: event_type.clone(),
+                });
+            }
+            let plugin = plugins.plugins.iter().find(|p| p.plugin.id() == *plugin_id);
+            match plugin {
+                None => {
+                    return Err(EngineError::UnknownPluginId {
+                        plugin_id: *plugin_id,
+                    })
+                }
+                Some(plugin) if plugin.workers > 1 => {
+                    return Err(EngineError::PluginSpawn {
+                        plugin_id: *plugin_id,
+                        reason: "the workers of a pool cannot acknowledge events".to_string(),
+                    })
+                }
+                Some(_) => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+// The endpoint of the ROUTER socket the plugins acknowledge their events on.
+fn endpoint(config: &EngineConfig) -> String {
+    format!("inproc://{}-acks", config.outgoing_inproc)
+}
+
+// The plugin end of the back-channel: the DEALER socket the plugin acknowledges its events on
+// and receives them again on, named after the plugin id, and the events it acknowledged last.
+pub(crate) struct AckChannel {
+    pub(crate) socket: Socket,
+    // the event types delivered at least once to the plugin
+    event_types: HashSet<String>,
+    // the ids of the events acknowledged last, oldest first, and the same as a set
+    acknowledged: VecDeque<Uuid>,
+    acknowledged_ids: HashSet<Uuid>,
+}
+
+impl AckChannel {
+    // The back-channel of the plugin `plugin_id`, if some of its events are delivered at least
+    // once.
+    pub(crate) fn connect(
+        context: &zmq::Context,
+        config: &EngineConfig,
+        plugin_id: i32,
+    ) -> Result<Option<Self>, EngineError> {
+        let Some(acks) = &config.acknowledged_delivery else {
+            return Ok(None);
+        };
+        let event_types = acks.event_types(plugin_id);
+        if event_types.is_empty() {
+            return Ok(None);
+        }
+        let name = format!("plugin {} acks", plugin_id);
+        let socket = create_socket(context, config, zmq::DEALER, &name)?;
+        socket
+            .set_identity(plugin_id.to_string().as_bytes())
+            .map_err(|source| EngineError::Socket {
+                socket: name.clone(),
+                source,
+            })?;
+        connect(&socket, &endpoint(config))?;
+        Ok(Some(AckChannel {
+            socket,
+            event_types,
+            acknowledged: VecDeque::new(),
+            acknowledged_ids: HashSet::new(),
+        }))
+    }
+
+    // Whether the events of type `event_type` are delivered at least once to the plugin.
+    pub(crate) fn delivers(&self, event_type: &str) -> bool {
+        self.event_types.contains(event_type)
+    }
+
+    // Whether the plugin acknowledged the event `event_id` lately.
+    pub(crate) fn is_acknowledged(&self, event_id: &Uuid) -> bool {
+        self.acknowledged_ids.contains(event_id)
+    }
+
+    // Tell the engine that the event `meta` was handled.
+    pub(crate) fn ack(&mut self, meta: &EventMeta) -> zmq::Result<()> {
+        self.socket.send(&meta.event_id.as_bytes()[..], 0)?;
+        if self.acknowledged_ids.insert(meta.event_id) {
+            self.acknowledged.push_back(meta.event_id);
+        }
+        if self.acknowledged.len() > ACKNOWLEDGED_MEMORY {
+            if let Some(oldest) = self.acknowledged.pop_front() {
+                self.acknowledged_ids.remove(&oldest);
+            }
+        }
+        Ok(())
+    }
+}
+
+// An event waiting for a plugin to acknowledge it.
+struct Unacked {
+    event_id: Uuid,
+    // the header, envelope and payload frames, as forwarded
+    frames: Vec<Vec<u8>>,
+    // how many times it was sent to the plugin
+    attempts: u32,
+    // when it was last sent
+    sent: Instant,
+    // the file it is kept in, with a directory
+    path: Option<PathBuf>,
+}
+
+// The engine end of the back-channels, and the events waiting for their acknowledgement; run
+// by the forwarding loop.
+pub(crate) struct RetryBuffer {
+    router: Socket,
+    // the plugins each event type is delivered at least once to
+    targets: HashMap<&'static str, Vec<i32>>,
+    // the events waiting for their acknowledgement, by plugin and type, in the order they were
+    // forwarded
+    queues: BTreeMap<(i32, &'static str), VecDeque<Unacked>>,
+    timeout: Duration,
+    max_attempts: u32,
+    capacity: usize,
+    dir: Option<PathBuf>,
+    // number of the next file an event is kept in, so that the files sort in the order the
+    // events were forwarded
+    next_file: u64,
+    bldr: FlatBufferBuilder<'static>,
+}
+
+impl RetryBuffer {
+    // Bind the ROUTER socket, before the plugins connect to it, and take back the events left in
+    // the directory, if any.
+    pub(crate) fn new(
+        context: &zmq::Context,
+        config: &EngineConfig,
+        acks: &AckConfig,
+    ) -> Result<Self, EngineError> {
+        let mut targets = HashMap::<&'static str, Vec<i32>>::new();
+        for (event_type, plugin_id) in &acks.deliveries {
+            let event_type = event_type_names()
+                .iter()
+                .find(|name| **name == event_type.as_str())
+                .ok_or_else(|| EngineError::UnknownEventType {
+                    event_type: event_type.clone(),
+                })?;
+            targets.entry(event_type).or_default().push(*plugin_id);
+        }
+        let router = create_socket(context, config, zmq::ROUTER, "acks")?;
+        // a restarted plugin takes over the name of the one it replaces
+        router
+            .set_router_handover(true)
+            .map_err(|source| EngineError::Socket {
+                socket: "acks".to_string(),
+                source,
+            })?;
+        bind(&router, &endpoint(config))?;
+        let mut buffer = RetryBuffer {
+            router,
+            targets,
+            queues: BTreeMap::new(),
+            timeout: acks.timeout,
+            max_attempts: acks.max_attempts,
+            capacity: acks.capacity,
+            dir: acks.dir.clone(),
+            next_file: 0,
+            bldr: FlatBufferBuilder::new(),
+        };
+        if let Some(dir) = &acks.dir {
+            buffer
+                .load(dir)
+                .map_err(|source| EngineError::RetryBuffer {
+                    dir: dir.clone(),
+                    source,
+                })?;
+        }
+        Ok(buffer)
+    }
+
+    pub(crate) fn router(&self) -> &Socket {
+        &self.router
+    }
+
+    // Take back the events an earlier engine kept in `dir` and did not see acknowledged; those
+    // no longer delivered at least once are dropped.
+    fn load(&mut self, dir: &Path) -> io::Result<()> {
+        fs::create_dir_all(dir)?;
+        let mut files = Vec::new();
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if let Some((number, plugin_id)) = parse_file_name(&path) {
+                files.push((number, plugin_id, path));
+            }
+        }
+        files.sort();
+        for (number, plugin_id, path) in files {
+            self.next_file = self.next_file.max(number + 1);
+            let frames = read_frames(&fs::read(&path)?);
+            let held = frames
+                .as_deref()
+                .and_then(peek_event_messages)
+                .and_then(|(event_type, meta, _)| Some((event_type, meta?)));
+            let delivered = |event_type| {
+                self.targets
+                    .get(event_type)
+                    .is_some_and(|plugin_ids| plugin_ids.contains(&plugin_id))
+            };
+            match (frames, held) {
+                (Some(frames), Some((event_type, meta))) if delivered(event_type) => self
+                    .queues
+                    .entry((plugin_id, event_type))
+                    .or_default()
+                    .push_back(Unacked {
+                        event_id: meta.event_id,
+                        frames,
+                        attempts: 1,
+                        sent: Instant::now(),
+                        path: Some(path),
+                    }),
+                _ => {
+                    warn!(
+                        "Engine dropped the event in {}, which is not delivered at least once to plugin {}",
+                        path.display(), plugin_id
+                    );
+                    fs::remove_file(&path)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Hold on to the message `frames`, about to be forwarded, if it is an event delivered at least
+    // once; the oldest event of a full buffer is published on `sinks` as a DeadLetterEvent.
+    pub(crate) fn hold(&mut self, frames: &[zmq::Message], sinks: &[&Socket]) -> zmq::Result<()> {
+        let Some((event_type, Some(meta), _)) = peek_event_messages(frames) else {
+            return Ok(());
+        };
+        let Some(plugin_ids) = self.targets.get(event_type).cloned() else {
+            return Ok(());
+        };
+        let frames: Vec<Vec<u8>> = frames.iter().map(|frame| frame.to_vec()).collect();
+        for plugin_id in plugin_ids {
+            let path = self.keep(plugin_id, &frames);
+            let queue = self.queues.entry((plugin_id, event_type)).or_default();
+            queue.push_back(Unacked {
+                event_id: meta.event_id,
+                frames: frames.clone(),
+                attempts: 1,
+                sent: Instant::now(),
+                path,
+            });
+            if queue.len() > self.capacity {
+                let oldest = queue.pop_front().expect("the queue is not empty");
+                let reason = format!(
+                    "plugin {} had {} {}s left to acknowledge",
+                    plugin_id, self.capacity, event_type
+                );
+                self.dead_letter(plugin_id, event_type, oldest, &reason, sinks)?;
+            }
+        }
+        Ok(())
+    }
+
+    // Keep `frames`, with a directory, in a file of their own for the plugin `plugin_id`; a
+    // failure is logged, and the event is held in memory only.
+    fn keep(&mut self, plugin_id: i32, frames: &[Vec<u8>]) -> Option<PathBuf> {
+        let dir = self.dir.as_ref()?;
+        let name = format!("{:020}-{}{}", self.next_file, plugin_id, EVENT_SUFFIX);
+        self.next_file += 1;
+        let path = dir.join(&name);
+        // written next to its final path and renamed, so that a half written file is never read
+        let partial = dir.join(format!(".{}", name));
+        match fs::write(&partial, write_frames(frames)).and_then(|()| fs::rename(&partial, &path)) {
+            Ok(()) => Some(path),
+            Err(e) => {
+                warn!(
+                    plugin_id;
+                    "Engine could not keep an event for plugin {} in {}: {}",
+                    plugin_id, path.display(), e
+                );
+                None
+            }
+        }
+    }
+
+    // Forget the events the plugins acknowledged since the last call.
+    pub(crate) fn acknowledged(&mut self) -> zmq::Result<()> {
+        loop {
+            let frames = match recv_event_frames(&self.router, zmq::DONTWAIT) {
+                Ok(frames) => frames,
+                Err(zmq::Error::EAGAIN) => return Ok(()),
+                Err(e) => return Err(e),
+            };
+            // the router puts the name of the plugin, its id, before what the plugin sent
+            let plugin_id = std::str::from_utf8(&frames[0])
+                .ok()
+                .and_then(|name| name.parse::<i32>().ok());
+            let event_id = frames.get(1).and_then(|id| Uuid::from_slice(id).ok());
+            match (plugin_id, event_id) {
+                (Some(plugin_id), Some(event_id)) => self.forget(plugin_id, event_id),
+                _ => warn!("Engine got an acknowledgement that is not an event id"),
+            }
+        }
+    }
+
+    // Forget the event `event_id`, acknowledged by the plugin `plugin_id`.
+    fn forget(&mut self, plugin_id: i32, event_id: Uuid) {
+        let queues = self
+            .queues
+            .iter_mut()
+            .filter(|((id, _), _)| *id == plugin_id);
+        for (_, queue) in queues {
+            if let Some(i) = queue.iter().position(|e| e.event_id == event_id) {
+                let unacked = queue.remove(i).expect("the event is in the queue");
+                discard(unacked.path.as_deref());
+                return;
+            }
+        }
+        debug!(
+            plugin_id;
+            "Engine got an acknowledgement of plugin {} for an event it does not hold",
+            plugin_id
+        );
+    }
+
+    // How long, in milliseconds, until the oldest event of a plugin and type has waited for the
+    // timeout, for polling; -1 if no event is waiting.
+    pub(crate) fn poll_timeout(&self) -> i64 {
+        let now = Instant::now();
+        self.queues
+            .values()
+            .filter_map(VecDeque::front)
+            .map(|oldest| {
+                let wait = (oldest.sent + self.timeout).saturating_duration_since(now);
+                wait.as_micros().div_ceil(1000) as i64
+            })
+            .min()
+            .unwrap_or(-1)
+    }
+
+    // Send the events of each plugin and type again to the plugin once the oldest has waited for
+    // the timeout, in the order they were forwarded; those already sent the maximum number of
+    // times are published on `sinks` as DeadLetterEvents instead.
+    pub(crate) fn retransmit(&mut self, sinks: &[&Socket]) -> zmq::Result<()> {
+        let now = Instant::now();
+        let mut dead = Vec::new();
+        for (&(plugin_id, event_type), queue) in self.queues.iter_mut() {
+            let due = |oldest: &Unacked| now >= oldest.sent + self.timeout;
+            if !queue.front().is_some_and(due) {
+                continue;
+            }
+            // the events sent the most times are the oldest
+            while queue
+                .front()
+                .is_some_and(|oldest| oldest.attempts >= self.max_attempts)
+            {
+                let oldest = queue.pop_front().expect("the queue is not empty");
+                dead.push((plugin_id, event_type, oldest));
+            }
+            if queue.is_empty() {
+                continue;
+            }
+            debug!(
+                plugin_id, event_type;
+                "Engine sends {} {}(s) again to plugin {}",
+                queue.len(), event_type, plugin_id
+            );
+            let name = plugin_id.to_string();
+            for unacked in queue.iter_mut() {
+                self.router.send(name.as_bytes(), zmq::SNDMORE)?;
+                send_frames(&self.router, unacked.frames.iter().map(Vec::as_slice))?;
+                unacked.attempts += 1;
+                unacked.sent = now;
+            }
+        }
+        for (plugin_id, event_type, unacked) in dead {
+            let reason = format!(
+                "plugin {} did not acknowledge it after {} attempts",
+                plugin_id, unacked.attempts
+            );
+            self.dead_letter(plugin_id, event_type, unacked, &reason, sinks)?;
+        }
+        Ok(())
+    }
+
+    // Publish `unacked` on `sinks` as a DeadLetterEvent, and forget it.
+    fn dead_letter(
+        &mut self,
+        plugin_id: i32,
+        event_type: &str,
+        unacked: Unacked,
+        reason: &str,
+        sinks: &[&Socket],
+    ) -> zmq::Result<()> {
+        warn!(
+            plugin_id, event_type;
+            "Engine dead-lettered a {} for plugin {}: {}",
+            event_type, plugin_id, reason
+        );
+        discard(unacked.path.as_deref());
+        // events are only held with an envelope, in a frame of its own
+        let [_, envelope, payload] = <[Vec<u8>; 3]>::try_from(unacked.frames)
+            .expect("events are held as a header, an envelope and a payload frame");
+        let dead_letter = DeadLetter {
+            plugin_id,
+            event_type: event_type.to_string(),
+            envelope,
+            payload,
+            reason: reason.to_string(),
+        };
+        let data = dead_letter
+            .build(&mut self.bldr)
+            .expect("building an event does not fail");
+        for socket in sinks {
+            send_event_msg(socket, "DeadLetterEvent", data)?;
+        }
+        Ok(())
+    }
+}
+
+// Remove the file an event was kept in, if any.
+fn discard(path: Option<&Path>) {
+    if let Some(path) = path {
+        if let Err(e) = fs::remove_file(path) {
+            warn!("Engine could not remove {}: {}", path.display(), e);
+        }
+    }
+}
+
+// The number and plugin id in the name of a file an event is kept in; None for other files,
+// including those being written.
+fn parse_file_name(path: &Path) -> Option<(u64, i32)> {
+    let name = path.file_name()?.to_str()?.strip_suffix(EVENT_SUFFIX)?;
+    let (number, plugin_id) = name.split_once('-')?;
+    Some((number.parse().ok()?, plugin_id.parse().ok()?))
+}
+
+// The frames of a message, each after its length as 4 little-endian bytes.
+fn write_frames(frames: &[Vec<u8>]) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    for frame in frames {
+        bytes.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(frame);
+    }
+    bytes
+}
+
+// The frames `write_frames` wrote to `bytes`; None if they are cut short.
+fn read_frames(mut bytes: &[u8]) -> Option<Vec<Vec<u8>>> {
+    let mut frames = Vec::new();
+    while !bytes.is_empty() {
+        let (len, rest) = bytes.split_first_chunk::<4>()?;
+        let len = u32::from_le_bytes(*len) as usize;
+        if rest.len() < len {
+            return None;
+        }
+        frames.push(rest[..len].to_vec());
+        bytes = &rest[len..];
+    }
+    Some(frames)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::event_engine::{EventEngineBuilder, Transport};
+    use crate::events::{event_type_header, Event, NewImage};
+    use crate::image_score_plugin::{FixedScorer, ImageScorePlugin};
+    use crate::image_store_plugin::ImageStorePlugin;
+    use crate::new_image_plugin::NewImagePlugin;
+    use crate::plugin::{Plugin, PluginContext, PluginError};
+    use crate::plugin_registry::RestartPolicy;
+    use crate::storage::{InMemoryStore, StorageBackend, StorageError, StoredLocation};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    fn config(name: &str, acks: AckConfig) -> EngineConfig {
+        EventEngineBuilder::new()
+            .outgoing_inproc(name)
+            .acknowledged_delivery(acks)
+            .build()
+    }
+
+    // The frames of a NewImageEvent with envelope `meta`, as the engine forwards them.
+    fn new_image_frames(
+        bldr: &mut FlatBufferBuilder<'static>,
+        meta: &EventMeta,
+    ) -> Vec<zmq::Message> {
+        let image = NewImage {
+            image_uuid: meta.event_id.to_string(),
+            image_format: "png".to_string(),
+            image: vec![1, 2, 3],
+            location: None,
+        };
+        vec![
+            zmq::Message::from(&event_type_header("NewImageEvent")[..]),
+            zmq::Message::from(&meta.to_bytes()[..]),
+            zmq::Message::from(image.build(bldr).unwrap()),
+        ]
+    }
+
+    // The event id in the envelope of the message received next on `socket`.
+    fn recv_event_id(socket: &Socket) -> Uuid {
+        let frames = recv_event_frames(socket, 0).unwrap();
+        let (_, meta, _) = peek_event_messages(&frames).unwrap();
+        meta.unwrap().event_id
+    }
+
+    #[test]
+    fn test_unacknowledged_events_are_sent_again_in_order() {
+        let context = zmq::Context::new();
+        let acks = AckConfig::new()
+            .deliver("NewImageEvent", 3)
+            .timeout(Duration::from_millis(50))
+            .max_attempts(2);
+        let config = config("test-acks-again", acks.clone());
+        let mut buffer = RetryBuffer::new(&context, &config, &acks).unwrap();
+        let mut channel = AckChannel::connect(&context, &config, 3).unwrap().unwrap();
+        assert!(AckChannel::connect(&context, &config, 4).unwrap().is_none());
+        let sink = context.socket(zmq::PAIR).unwrap();
+        sink.bind("inproc://test-acks-again-sink").unwrap();
+        let dead_letters = context.socket(zmq::PAIR).unwrap();
+        dead_letters
+            .connect("inproc://test-acks-again-sink")
+            .unwrap();
+
+        let mut bldr = FlatBufferBuilder::new();
+        let metas: Vec<EventMeta> = (0..3).map(|_| EventMeta::new(1)).collect();
+        for meta in &metas {
+            buffer
+                .hold(&new_image_frames(&mut bldr, meta), &[&sink])
+                .unwrap();
+        }
+        // events without an envelope cannot be acknowledged, so they are not held
+        let unwrapped = vec![
+            zmq::Message::from(&event_type_header("NewImageEvent")[..]),
+            zmq::Message::from(&[][..]),
+        ];
+        buffer.hold(&unwrapped, &[&sink]).unwrap();
+        assert!(buffer.poll_timeout() <= 50);
+        channel.ack(&metas[1]).unwrap();
+        assert!(channel.is_acknowledged(&metas[1].event_id));
+
+        // the events left are sent again, in the order they were forwarded
+        std::thread::sleep(Duration::from_millis(60));
+        buffer.acknowledged().unwrap();
+        buffer.retransmit(&[&sink]).unwrap();
+        assert_eq!(recv_event_id(&channel.socket), metas[0].event_id);
+        assert_eq!(recv_event_id(&channel.socket), metas[2].event_id);
+
+        // and dead-lettered once sent the maximum number of times
+        std::thread::sleep(Duration::from_millis(60));
+        buffer.acknowledged().unwrap();
+        buffer.retransmit(&[&sink]).unwrap();
+        for meta in [metas[0], metas[2]] {
+            let frames = recv_event_frames(&dead_letters, 0).unwrap();
+            let (event_type, _, payload) = peek_event_messages(&frames).unwrap();
+            assert_eq!(event_type, "DeadLetterEvent");
+            let Event::DeadLetter(dead_letter) = Event::decode(payload).unwrap() else {
+                panic!("not a dead letter");
+            };
+            assert_eq!(dead_letter.plugin_id, 3);
+            assert_eq!(dead_letter.meta(), Some(meta));
+            assert_eq!(
+                dead_letter.reason,
+                "plugin 3 did not acknowledge it after 2 attempts"
+            );
+        }
+        assert_eq!(buffer.poll_timeout(), -1);
+    }
+
+    #[test]
+    fn test_full_buffer_dead_letters_the_oldest_event() {
+        let context = zmq::Context::new();
+        let acks = AckConfig::new().deliver("NewImageEvent", 3).capacity(2);
+        let config = config("test-acks-full", acks.clone());
+        let mut buffer = RetryBuffer::new(&context, &config, &acks).unwrap();
+        let sink = context.socket(zmq::PAIR).unwrap();
+        sink.bind("inproc://test-acks-full-sink").unwrap();
+        let dead_letters = context.socket(zmq::PAIR).unwrap();
+        dead_letters
+            .connect("inproc://test-acks-full-sink")
+            .unwrap();
+
+        let mut bldr = FlatBufferBuilder::new();
+        let metas: Vec<EventMeta> = (0..3).map(|_| EventMeta::new(1)).collect();
+        for meta in &metas {
+            buffer
+                .hold(&new_image_frames(&mut bldr, meta), &[&sink])
+                .unwrap();
+        }
+        let frames = recv_event_frames(&dead_letters, 0).unwrap();
+        let (_, _, payload) = peek_event_messages(&frames).unwrap();
+        let Event::DeadLetter(dead_letter) = Event::decode(payload).unwrap() else {
+            panic!("not a dead letter");
+        };
+        assert_eq!(dead_letter.meta(), Some(metas[0]));
+        assert_eq!(
+            dead_letter.reason,
+            "plugin 3 had 2 NewImageEvents left to acknowledge"
+        );
+    }
+
+    #[test]
+    fn test_unacknowledged_events_are_kept_on_disk() {
+        let dir = std::env::temp_dir().join(format!("plyoreacto-acks-{}", Uuid::new_v4()));
+        let context = zmq::Context::new();
+        let acks = AckConfig::new()
+            .deliver("NewImageEvent", 3)
+            .timeout(Duration::from_millis(20))
+            .dir(&dir);
+        let mut bldr = FlatBufferBuilder::new();
+        let metas: Vec<EventMeta> = (0..3).map(|_| EventMeta::new(1)).collect();
+        {
+            let config = config("test-acks-disk", acks.clone());
+            let mut buffer = RetryBuffer::new(&context, &config, &acks).unwrap();
+            let mut channel = AckChannel::connect(&context, &config, 3).unwrap().unwrap();
+            for meta in &metas {
+                buffer
+                    .hold(&new_image_frames(&mut bldr, meta), &[])
+                    .unwrap();
+            }
+            channel.ack(&metas[1]).unwrap();
+            // the acknowledgement is on its way, and read by the next poll
+            let mut items = [buffer.router().as_poll_item(zmq::POLLIN)];
+            zmq::poll(&mut items, 1000).unwrap();
+            buffer.acknowledged().unwrap();
+        }
+        // a file being written is not read
+        fs::write(dir.join(".00000000000000000009-3.event"), b"").unwrap();
+
+        // the engine started again sends the events that were not acknowledged
+        let config = config("test-acks-disk-again", acks.clone());
+        let mut buffer = RetryBuffer::new(&context, &config, &acks).unwrap();
+        let channel = AckChannel::connect(&context, &config, 3).unwrap().unwrap();
+        std::thread::sleep(Duration::from_millis(30));
+        buffer.retransmit(&[]).unwrap();
+        assert_eq!(recv_event_id(&channel.socket), metas[0].event_id);
+        assert_eq!(recv_event_id(&channel.socket), metas[2].event_id);
+        // new events are kept after them
+        let meta = EventMeta::new(1);
+        buffer
+            .hold(&new_image_frames(&mut bldr, &meta), &[])
+            .unwrap();
+        let mut files: Vec<_> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter_map(|path| parse_file_name(&path))
+            .collect();
+        files.sort();
+        assert_eq!(files, vec![(0, 3), (2, 3), (3, 3)]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    // A store counting how many times each image is put in it.
+    #[derive(Clone, Default)]
+    struct CountingStore {
+        store: InMemoryStore,
+        puts: Arc<Mutex<HashMap<String, usize>>>,
+    }
+
+    impl StorageBackend for CountingStore {
+        fn put(
+            &mut self,
+            image_uuid: &str,
+            image_format: &str,
+            image: &[u8],
+        ) -> Result<StoredLocation, StorageError> {
+            *self
+                .puts
+                .lock()
+                .unwrap()
+                .entry(image_uuid.to_string())
+                .or_default() += 1;
+            self.store.put(image_uuid, image_format, image)
+        }
+
+        fn get(&self, image_uuid: &str) -> Result<Option<Vec<u8>>, StorageError> {
+            self.store.get(image_uuid)
+        }
+
+        fn delete(&mut self, image_uuid: &str) -> Result<bool, StorageError> {
+            self.store.delete(image_uuid)
+        }
+    }
+
+    // An image store plugin that fails once it has stored its images, the first time it runs.
+    struct KilledStore {
+        store: ImageStorePlugin,
+        killed: bool,
+    }
+
+    impl Plugin for KilledStore {
+        fn id(&self) -> i32 {
+            self.store.id()
+        }
+
+        fn name(&self) -> &str {
+            self.store.name()
+        }
+
+        fn subscriptions(&self) -> &[&str] {
+            self.store.subscriptions()
+        }
+
+        fn start(self: Box<Self>, ctx: PluginContext) -> Result<(), PluginError> {
+            let killed = self.killed;
+            Box::new(self.store).start(ctx)?;
+            match killed {
+                true => Err(PluginError::Other("killed".to_string())),
+                false => Ok(()),
+            }
+        }
+    }
+
+    #[test]
+    fn test_restarted_store_stores_every_image_once() {
+        const IMAGES: usize = 20;
+        let store = CountingStore::default();
+        let runs = Arc::new(AtomicUsize::new(0));
+        let factory_store = store.clone();
+        let factory_runs = Arc::clone(&runs);
+        let labrador = FixedScorer::new(vec![("labrador".to_string(), 1.0)]);
+        let mut plugins = crate::plugin_registry::PluginRegistry::new();
+        plugins
+            .register_plugin(Box::new(NewImagePlugin::new(1).images(IMAGES)))
+            .unwrap()
+            .register_plugin(Box::new(
+                ImageScorePlugin::with_scorer(2, Box::new(labrador)).images(IMAGES),
+            ))
+            .unwrap()
+            .register_restartable(
+                RestartPolicy::Always {
+                    max_retries: 1,
+                    backoff: Duration::from_millis(300),
+                },
+                move || {
+                    // the first store is killed after a few images, the events after them lost
+                    let killed = factory_runs.fetch_add(1, Ordering::SeqCst) == 0;
+                    let images = if killed { 3 } else { IMAGES };
+                    Box::new(KilledStore {
+                        store: ImageStorePlugin::new(3)
+                            .images(images)
+                            .storage(Box::new(factory_store.clone())),
+                        killed,
+                    })
+                },
+            )
+            .unwrap();
+        let acks = AckConfig::new()
+            .deliver("NewImageEvent", 3)
+            .deliver("ImageScoredEvent", 3)
+            .timeout(Duration::from_millis(200))
+            .max_attempts(20);
+        let engine = EventEngineBuilder::new()
+            .incoming_inproc("messages-acks-restart")
+            .outgoing_inproc("events-acks-restart")
+            .transport(Transport::InprocOnly)
+            .acknowledged_delivery(acks)
+            .plugins(plugins)
+            .start()
+            .unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(20);
+        while store.store.len() < IMAGES {
+            assert!(
+                Instant::now() < deadline,
+                "{} images stored",
+                store.store.len()
+            );
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        // nothing is left to send again, and nothing was stored twice
+        std::thread::sleep(Duration::from_millis(500));
+        assert_eq!(runs.load(Ordering::SeqCst), 2);
+        let puts = store.puts.lock().unwrap().clone();
+        assert_eq!(puts.len(), IMAGES);
+        assert!(puts.values().all(|puts| *puts == 1), "{:?}", puts);
+        engine.shutdown().unwrap();
+    }
+
+    #[test]
+    fn test_deliveries_are_checked() {
+        let mut plugins = crate::plugin_registry::PluginRegistry::new();
+        plugins
+            .register_plugin(Box::new(ImageStorePlugin::new(3)))
+            .unwrap();
+        let registered = plugins;
+        assert!(AckConfig::new()
+            .deliver("ImageScoredEvent", 3)
+            .check(&registered)
+            .is_ok());
+        assert!(matches!(
+            AckConfig::new()
+                .deliver("ImageScoredEvent", 4)
+                .check(&registered),
+            Err(EngineError::UnknownPluginId { plugin_id: 4 })
+        ));
+        assert!(matches!(
+            AckConfig::new()
+                .deliver("ImageScored", 3)
+                .check(&registered),
+            Err(EngineError::UnknownEventType { .. })
+        ));
+    }
+}
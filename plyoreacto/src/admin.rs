@@ -0,0 +1,385 @@
+//! The engine's admin socket.
+//! An engine started with `EventEngineBuilder::admin_port` answers requests on a REP socket bound
+//! to `tcp://<bind address>:<admin port>`, so that it can be inspected from a script in any
+//! language with a zmq binding. Every request is a single word, and every reply a JSON object:
+//!
+//! - `status`: the engine's uptime, whether its proxy is running, and the state of each plugin it
+//!   started (`starting`, `running`, `finished` or `failed`), whether it is synced, how many times
+//!   it was restarted and, if it answers heartbeats, its liveness
+//! - `plugins`: the registered plugins with their subscriptions and restart policy, and the
+//!   external plugins; these subscribe on their own socket, so only their ids are known
+//! - `counters`: the per event type counts and latencies of the latest MetricsSnapshotEvent, which
+//!   requires a running metrics plugin
+//! - `stats`: the count, bytes and time last seen (in milliseconds since the Unix epoch) of the
+//!   events of each type the engine forwarded, as `EngineHandle::stats` has them
+//!
+//! Any other request is answered with an object with an `error` field.
+//!
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Instant, UNIX_EPOCH};
+
+use log::{error, info};
+use serde_json::{json, Value};
+use zmq::Socket;
+
+use crate::compression::decode_event_frames;
+use crate::event_engine::{
+    bind, connect, create_socket, EngineConfig, EngineError, PluginLivenessMap, PluginStatus,
+    PluginStatuses,
+};
+use crate::events::{get_event_type_bytes_filter, retry_on_eintr, Event};
+use crate::plugin::event_fields;
+use crate::plugin_registry::{PluginRegistry, RestartPolicy};
+use crate::stats::{EventTypeStats, ForwardingStats};
+
+// Control socket and thread answering the admin requests.
+pub(crate) type Admin = (Socket, JoinHandle<()>);
+
+// A plugin started by the engine, as registered.
+struct PluginInfo {
+    plugin_id: i32,
+    name: String,
+    subscriptions: Vec<String>,
+    restart_policy: RestartPolicy,
+}
+
+// What the admin socket reports on, shared with the rest of the engine.
+pub(crate) struct AdminState {
+    // when the engine started
+    started: Instant,
+    // cleared by the proxy thread once the proxy stops
+    proxy_running: Arc<AtomicBool>,
+    statuses: PluginStatuses,
+    liveness: PluginLivenessMap,
+    plugins: Vec<PluginInfo>,
+    // id of each external plugin and whether it is required
+    external_plugins: Vec<(i32, bool)>,
+    stats: Arc<ForwardingStats>,
+}
+
+impl AdminState {
+    // The state of an engine starting `plugins`; called before the engine takes them over.
+    pub(crate) fn new(
+        plugins: &PluginRegistry,
+        proxy_running: &Arc<AtomicBool>,
+        statuses: &PluginStatuses,
+        liveness: &PluginLivenessMap,
+        stats: &Arc<ForwardingStats>,
+    ) -> Self {
+        AdminState {
+            started: Instant::now(),
+            proxy_running: Arc::clone(proxy_running),
+            statuses: Arc::clone(statuses),
+            liveness: Arc::clone(liveness),
+            plugins: plugins
+                .plugins
+                .iter()
+                .map(|p| PluginInfo {
+                    plugin_id: p.plugin.id(),
+                    name: p.plugin.name().to_string(),
+                    subscriptions: p
+                        .plugin
+                        .subscriptions()
+                        .iter()
+                        .map(|s| s.to_string())
+                        .collect(),
+                    restart_policy: p.restart_policy,
+                })
+                .collect(),
+            external_plugins: plugins
+                .external_plugins
+                .iter()
+                .map(|p| (p.plugin_id, p.required))
+                .collect(),
+            stats: Arc::clone(stats),
+        }
+    }
+}
+
+// The answer to a `status` request; `restarts` has the restart count of every restarted plugin.
+fn status(state: &AdminState, restarts: &HashMap<i32, u32>) -> Value {
+    let statuses = state.statuses.lock().expect("plugin status lock poisoned");
+    let liveness = state
+        .liveness
+        .lock()
+        .expect("plugin liveness lock poisoned");
+    let plugins: Vec<Value> = state
+        .plugins
+        .iter()
+        .map(|p| {
+            let (plugin_state, error) = match statuses.get(&p.plugin_id) {
+                None | Some(PluginStatus::Starting) => ("starting", None),
+                Some(PluginStatus::Running) => ("running", None),
+                Some(PluginStatus::Finished) => ("finished", None),
+                Some(PluginStatus::Failed(message)) => ("failed", Some(message.clone())),
+            };
+            let plugin_liveness = liveness.get(&p.plugin_id).map(|l| {
+                json!({
+                    "last_seq": l.last_seq,
+                    "missed_beats": l.missed_beats,
+                    "unresponsive": l.unresponsive,
+                })
+            });
+            json!({
+                "plugin_id": p.plugin_id,
+                "name": p.name,
+                "state": plugin_state,
+                "error": error,
+                "synced": plugin_state != "starting",
+                "restarts": restarts.get(&p.plugin_id).copied().unwrap_or(0),
+                "liveness": plugin_liveness,
+            })
+        })
+        .collect();
+    let proxy = if state.proxy_running.load(Ordering::SeqCst) {
+        "running"
+    } else {
+        "stopped"
+    };
+    json!({
+        "uptime_ms": state.started.elapsed().as_millis() as u64,
+        "proxy": proxy,
+        "plugins": plugins,
+    })
+}
+
+// The answer to a `plugins` request.
+fn plugins(state: &AdminState) -> Value {
+    let plugins: Vec<Value> = state
+        .plugins
+        .iter()
+        .map(|p| {
+            let restart_policy = match p.restart_policy {
+                RestartPolicy::Never => json!("never"),
+                RestartPolicy::Always {
+                    max_retries,
+                    backoff,
+                } => json!({"max_retries": max_retries, "backoff_ms": backoff.as_millis() as u64}),
+            };
+            json!({
+                "plugin_id": p.plugin_id,
+                "name": p.name,
+                "subscriptions": p.subscriptions,
+                "restart_policy": restart_policy,
+            })
+        })
+        .collect();
+    let external_plugins: Vec<Value> = state
+        .external_plugins
+        .iter()
+        .map(|(plugin_id, required)| json!({"plugin_id": plugin_id, "required": required}))
+        .collect();
+    json!({"plugins": plugins, "external_plugins": external_plugins})
+}
+
+// The answer to a `stats` request.
+fn stats(state: &AdminState) -> Value {
+    let type_stats = |stats: &EventTypeStats| {
+        let last_seen = stats.last_seen.map(|t| {
+            let since_epoch = t.duration_since(UNIX_EPOCH).unwrap_or_default();
+            since_epoch.as_millis() as u64
+        });
+        json!({"count": stats.count, "bytes": stats.bytes, "last_seen_ms": last_seen})
+    };
+    let snapshot = state.stats.snapshot();
+    let event_types: serde_json::Map<String, Value> = snapshot
+        .event_types
+        .iter()
+        .map(|(name, stats)| (name.clone(), type_stats(stats)))
+        .collect();
+    json!({"event_types": event_types, "other": type_stats(&snapshot.other)})
+}
+
+// Answer the requests received on `socket` until TERMINATE is received on `control`, keeping
+// track of the restarts and metrics snapshots published on `events`, whose payloads may be up
+// to `max_payload_size` once decompressed.
+fn answer_requests(
+    socket: Socket,
+    events: Socket,
+    control: Socket,
+    state: AdminState,
+    max_payload_size: usize,
+) {
+    let mut restarts = HashMap::new();
+    let mut counters = None;
+    loop {
+        let mut items = [
+            socket.as_poll_item(zmq::POLLIN),
+            events.as_poll_item(zmq::POLLIN),
+            control.as_poll_item(zmq::POLLIN),
+        ];
+        if retry_on_eintr(|| zmq::poll(&mut items, -1)).is_err() || items[2].is_readable() {
+            return;
+        }
+        if items[1].is_readable() {
+            let frames = match events.recv_multipart(0) {
+                Ok(frames) => frames,
+                Err(_) => return,
+            };
+            match decode_event_frames(frames, max_payload_size) {
+                Some(Ok(Event::PluginRestarted(e))) => {
+                    restarts.insert(e.plugin_id, e.restart_count);
+                }
+                Some(Ok(event @ Event::MetricsSnapshot(_))) => {
+                    counters = Some(event_fields(&event))
+                }
+                _ => {}
+            }
+        }
+        if items[0].is_readable() {
+            let request = match socket.recv_bytes(0) {
+                Ok(request) => request,
+                Err(_) => return,
+            };
+            let reply = match String::from_utf8_lossy(&request).trim() {
+                "status" => status(&state, &restarts),
+                "plugins" => plugins(&state),
+                "stats" => stats(&state),
+                "counters" => counters.clone().unwrap_or_else(|| {
+                    json!({"error": "no metrics snapshot published; is a metrics plugin running?"})
+                }),
+                request => json!({
+                    "error": format!(
+                        "unknown request {:?}; expected status, plugins, counters or stats",
+                        request
+                    )
+                }),
+            };
+            if let Err(e) = socket.send(reply.to_string().as_bytes(), 0) {
+                error!("Engine could not answer admin request: {}", e);
+            }
+        }
+    }
+}
+
+// Start the thread answering the admin requests, if the engine has an admin port.
+pub(crate) fn start_admin(
+    context: &zmq::Context,
+    config: &EngineConfig,
+    state: AdminState,
+) -> Result<Option<Admin>, EngineError> {
+    let port = match config.admin_port {
+        Some(port) => port,
+        None => return Ok(None),
+    };
+    let socket_name = "admin";
+    let socket = create_socket(context, config, zmq::REP, socket_name)?;
+    // a reply to a client that went away must not keep the engine's context from terminating
+    socket.set_linger(0).map_err(|source| EngineError::Socket {
+        socket: socket_name.to_string(),
+        source,
+    })?;
+    bind(&socket, &config.tcp_endpoint(port))?;
+    let events_name = "admin events";
+    let events = create_socket(context, config, zmq::SUB, events_name)?;
+    for event_type in ["PluginRestartedEvent", "MetricsSnapshotEvent"] {
+        let filter_bytes =
+            get_event_type_bytes_filter(event_type).expect("the admin events are event types");
+        events
+            .set_subscribe(&filter_bytes)
+            .map_err(|source| EngineError::Socket {
+                socket: events_name.to_string(),
+                source,
+            })?;
+    }
+    connect(&events, &config.outgoing_endpoint())?;
+
+    let control_endpoint = format!("inproc://{}-admin-control", config.outgoing_inproc);
+    let admin_control = create_socket(context, config, zmq::PAIR, "admin control")?;
+    bind(&admin_control, &control_endpoint)?;
+    let control = create_socket(context, config, zmq::PAIR, "admin")?;
+    connect(&control, &control_endpoint)?;
+    info!("Engine answering admin requests on port {}", port);
+    let max_payload_size = config.max_payload_size;
+    let admin_thread = thread::spawn(move || {
+        answer_requests(socket, events, admin_control, state, max_payload_size)
+    });
+    Ok(Some((control, admin_thread)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::event_engine::EventEngineBuilder;
+    use crate::image_score_plugin::ImageScorePlugin;
+    use crate::image_store_plugin::ImageStorePlugin;
+    use crate::new_image_plugin::NewImagePlugin;
+
+    // Send `request` to the admin socket on `port` and parse the reply.
+    fn query(port: u16, request: &str) -> Value {
+        let context = zmq::Context::new();
+        let socket = context.socket(zmq::REQ).unwrap();
+        socket.set_rcvtimeo(5000).unwrap();
+        socket.set_linger(0).unwrap();
+        socket
+            .connect(&format!("tcp://localhost:{}", port))
+            .unwrap();
+        socket.send(request, 0).unwrap();
+        serde_json::from_slice(&socket.recv_bytes(0).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_status_reports_running_plugins() {
+        // the pipeline keeps running while the watched directory stays empty
+        let dir = std::env::temp_dir().join(format!("plyoreacto-admin-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir(&dir).unwrap();
+        let mut plugins = PluginRegistry::new();
+        plugins
+            .register_plugin(Box::new(NewImagePlugin::watch(0, &dir)))
+            .unwrap()
+            .register_plugin(Box::new(ImageScorePlugin::new(1)))
+            .unwrap()
+            .register_plugin(Box::new(ImageStorePlugin::new(2)))
+            .unwrap()
+            .register_optional_external(3)
+            .unwrap();
+        let engine = EventEngineBuilder::new()
+            .incoming_port(3559)
+            .outgoing_port(3560)
+            .sync_port(3000)
+            .admin_port(3580)
+            .plugins(plugins)
+            .start()
+            .unwrap();
+
+        // the plugins are running once they are synced, i.e., by the time the engine started
+        let status = query(3580, "status");
+        assert_eq!(status["proxy"], "running");
+        let states: Vec<(i64, &str)> = status["plugins"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|p| {
+                (
+                    p["plugin_id"].as_i64().unwrap(),
+                    p["state"].as_str().unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(states, vec![(0, "running"), (1, "running"), (2, "running")]);
+        assert!(status["plugins"][0]["synced"].as_bool().unwrap());
+        assert_eq!(status["plugins"][0]["restarts"], 0);
+
+        let registered = query(3580, "plugins");
+        assert_eq!(
+            registered["plugins"][1]["subscriptions"],
+            json!(["NewImageEvent"])
+        );
+        assert_eq!(
+            registered["external_plugins"],
+            json!([{"plugin_id": 3, "required": false}])
+        );
+        assert!(query(3580, "counters")["error"].is_string());
+        // the pipeline is waiting for images, so the engine forwarded none
+        let stats = query(3580, "stats");
+        assert_eq!(stats["event_types"], json!({}));
+        assert_eq!(stats["other"]["count"], 0);
+        assert!(query(3580, "uptime")["error"].is_string());
+        engine.shutdown().unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,749 @@
+//! The engine and its plugins as tasks of a tokio runtime, for applications built on tokio that
+//! would rather not give every plugin a thread of its own and the proxy another. Plugins
+//! implementing `AsyncPlugin` get an `AsyncPluginContext` whose `next_event` waits for the
+//! events without blocking the runtime, and plugins implementing `Plugin` run as they are in the
+//! runtime's blocking threads (see `tokio::task::spawn_blocking`):
+//!
+//! ```no_run
+//! use plyoreacto::async_engine::{event_engine_async, AsyncPluginRegistry};
+//! use plyoreacto::event_engine::{EngineError, EventEngineBuilder};
+//! use plyoreacto::image_score_plugin::ImageScorePlugin;
+//! use plyoreacto::image_store_plugin::ImageStorePlugin;
+//!
+//! # async fn run() -> Result<(), EngineError> {
+//! let mut plugins = AsyncPluginRegistry::new();
+//! plugins
+//!     .register_async(Box::new(ImageScorePlugin::new(1)))?
+//!     .register_blocking(Box::new(ImageStorePlugin::new(2)))?;
+//! let config = EventEngineBuilder::new().build();
+//! let engine = event_engine_async(&config, plugins).await?;
+//! engine.stopped().await;
+//! engine.shutdown().await
+//! # }
+//! ```
+//!
+//! The sockets are the engine's usual ones, so external plugins and the other processes of an
+//! application see no difference; a zmq socket tells the runtime it may have something to
+//! receive through the file descriptor of its `ZMQ_FD` option. The image scoring plugin is both
+//! a `Plugin` and an `AsyncPlugin`. The async engine does not have the last-value cache, event
+//! log, admin socket, heartbeats, metrics or registrations of external plugins of the engine of
+//! the `event_engine` module yet, and refuses configurations asking for them, nor does it take
+//! middlewares, pools or restartable plugins.
+//!
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::io;
+use std::os::unix::io::RawFd;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use flatbuffers::FlatBufferBuilder;
+use log::{error, info};
+use tokio::io::unix::AsyncFd;
+use tokio::sync::{oneshot, watch};
+use tokio::task::{JoinError, JoinHandle};
+use zmq::Socket;
+
+use crate::event_engine::{
+    bind_sync_sockets, check_subscriptions, claim_ipc_paths, connect, create_plugin_sockets,
+    create_socket, get_incoming_socket, get_outgoing_socket, panic_message, remove_ipc_files,
+    run_plugin, set_io_threads, sync_plugins, sync_with_engine, EngineConfig, EngineError, Hwm,
+    PluginSockets, PluginStatuses, SyncedPlugins, Transport, UnknownSubscription, SYNC_READY,
+};
+use crate::events::{
+    get_event_type_bytes_filter, recv_event_frames, send_event_msg, send_plugin_terminate_event,
+    Event, EventMeta, EventPayload,
+};
+use crate::middleware::{send_frames, PayloadLimit, FORWARD_BATCH};
+use crate::plugin::{EventMsg, Plugin, PluginContext, PluginError};
+use crate::plugin_registry::check_plugin_ids;
+
+/// What the `start` of an `AsyncPlugin` returns: the future running the plugin.
+pub type PluginFuture = Pin<Box<dyn Future<Output = Result<(), PluginError>> + Send>>;
+
+/// A plugin the async engine runs as a task of its runtime. Like a `Plugin` it declares its id,
+/// name and subscriptions, but its `start` returns a future, which gets the plugin's sockets in
+/// an `AsyncPluginContext`.
+pub trait AsyncPlugin: Send {
+    /// The id of the plugin, unique among the plugins of the engine.
+    fn id(&self) -> i32;
+
+    /// The name of the plugin, used in log output.
+    fn name(&self) -> &str;
+
+    /// The event types the plugin subscribes to; the PluginTerminateEvent always arrives.
+    fn subscriptions(&self) -> &[&str];
+
+    /// The future running the plugin once it has synced with the engine; it should return when
+    /// it receives the PluginTerminateEvent.
+    fn start(self: Box<Self>, ctx: AsyncPluginContext) -> PluginFuture;
+}
+
+// A zmq socket whose file descriptor is registered with the runtime; the registration is
+// dropped before the socket, which owns the descriptor.
+struct AsyncSocket {
+    fd: AsyncFd<RawFd>,
+    socket: Socket,
+}
+
+impl AsyncSocket {
+    fn new(socket: Socket) -> io::Result<Self> {
+        let fd = AsyncFd::new(socket.get_fd()?)?;
+        Ok(AsyncSocket { fd, socket })
+    }
+
+    // Wait until the socket has a message to receive. zmq signals the descriptor
+    // (edge-triggered) when the state of the socket may have changed, and every look at the
+    // socket may consume the signal, so the readiness is cleared before looking again: whatever
+    // changes after the look signals the descriptor anew. A socket is not `Sync`, hence the
+    // `&mut self` of a future that can be sent to another thread.
+    async fn readable(&mut self) -> io::Result<()> {
+        loop {
+            if self.socket.get_events()?.contains(zmq::POLLIN) {
+                return Ok(());
+            }
+            self.fd.readable().await?.clear_ready();
+        }
+    }
+}
+
+/// The sockets of an `AsyncPlugin`: a `PluginContext` whose `next_event` waits for the events
+/// without blocking the runtime.
+pub struct AsyncPluginContext {
+    // the registration of the sub socket's descriptor, dropped before the context closes it
+    fd: AsyncFd<RawFd>,
+    ctx: PluginContext,
+    // how long `next_event` waits before checking whether the engine is stopping; None waits
+    // for the next event however long it takes
+    recv_timeout: Option<Duration>,
+}
+
+impl AsyncPluginContext {
+    /// Wrap `ctx` for a plugin running on the current tokio runtime, which must have its I/O
+    /// driver enabled. Like `PluginContext::next_event`, `next_event` checks whether the engine
+    /// is stopping whenever the receive timeout of the sub socket expires.
+    pub fn new(ctx: PluginContext) -> Result<Self, PluginError> {
+        let fd = AsyncFd::new(ctx.sub_socket.get_fd()?)?;
+        let recv_timeout = u64::try_from(ctx.sub_socket.get_rcvtimeo()?)
+            .ok()
+            .filter(|millis| *millis > 0)
+            .map(Duration::from_millis);
+        Ok(AsyncPluginContext {
+            fd,
+            ctx,
+            recv_timeout,
+        })
+    }
+
+    /// The id of the plugin, recorded as the source of the events it publishes.
+    pub fn plugin_id(&self) -> i32 {
+        self.ctx.plugin_id
+    }
+
+    /// The wrapped context, e.g., to `answer_heartbeats` or read its `dropped_events`.
+    pub fn context(&mut self) -> &mut PluginContext {
+        &mut self.ctx
+    }
+
+    /// As `PluginContext::publish`. A PUB socket never waits for its subscribers, dropping the
+    /// events past its high-water mark instead, so this returns as soon as the event is queued.
+    pub async fn publish(&mut self, event: &impl EventPayload) -> Result<EventMeta, PluginError> {
+        self.ctx.publish(event)
+    }
+
+    /// As `PluginContext::publish_reply`.
+    pub async fn publish_reply(
+        &mut self,
+        to: &EventMeta,
+        event: &impl EventPayload,
+    ) -> Result<EventMeta, PluginError> {
+        self.ctx.publish_reply(to, event)
+    }
+
+    /// As `PluginContext::next_event`, but waiting for the event lets the runtime run its other
+    /// tasks.
+    pub async fn next_event(&mut self) -> Result<EventMsg, PluginError> {
+        loop {
+            if let Some(msg) = self.ctx.try_next_event()? {
+                return Ok(msg);
+            }
+            // cleared before the next try, which sees whatever arrived since (see
+            // `AsyncSocket::readable`)
+            let ready = self.fd.readable();
+            match self.recv_timeout {
+                Some(timeout) => match tokio::time::timeout(timeout, ready).await {
+                    Ok(guard) => guard?.clear_ready(),
+                    Err(_) if self.ctx.is_stopping() => return Err(PluginError::Stopped),
+                    Err(_) => {}
+                },
+                None => ready.await?.clear_ready(),
+            }
+        }
+    }
+}
+
+// A plugin of an `AsyncPluginRegistry`.
+enum RegisteredPlugin {
+    Async(Box<dyn AsyncPlugin>),
+    Blocking(Box<dyn Plugin>),
+}
+
+impl RegisteredPlugin {
+    fn id(&self) -> i32 {
+        match self {
+            RegisteredPlugin::Async(plugin) => plugin.id(),
+            RegisteredPlugin::Blocking(plugin) => plugin.id(),
+        }
+    }
+
+    fn subscriptions(&self) -> &[&str] {
+        match self {
+            RegisteredPlugin::Async(plugin) => plugin.subscriptions(),
+            RegisteredPlugin::Blocking(plugin) => plugin.subscriptions(),
+        }
+    }
+}
+
+/// The plugins an engine started with `event_engine_async` runs: `AsyncPlugin`s, as tasks of
+/// the runtime, and `Plugin`s, each in a blocking thread of the runtime for as long as it runs.
+#[derive(Default)]
+pub struct AsyncPluginRegistry {
+    plugins: Vec<RegisteredPlugin>,
+}
+
+impl AsyncPluginRegistry {
+    pub fn new() -> Self {
+        AsyncPluginRegistry::default()
+    }
+
+    /// Register a plugin run as a task of the runtime.
+    pub fn register_async(
+        &mut self,
+        plugin: Box<dyn AsyncPlugin>,
+    ) -> Result<&mut Self, EngineError> {
+        self.add(RegisteredPlugin::Async(plugin))
+    }
+
+    /// Register a plugin run in a blocking thread of the runtime, e.g., one of the plugins
+    /// written for the engine of the `event_engine` module, or one spending long stretches on
+    /// the CPU.
+    pub fn register_blocking(&mut self, plugin: Box<dyn Plugin>) -> Result<&mut Self, EngineError> {
+        self.add(RegisteredPlugin::Blocking(plugin))
+    }
+
+    /// The ids of the registered plugins, in the order they were registered.
+    pub fn plugin_ids(&self) -> Vec<i32> {
+        self.plugins.iter().map(RegisteredPlugin::id).collect()
+    }
+
+    fn add(&mut self, plugin: RegisteredPlugin) -> Result<&mut Self, EngineError> {
+        let plugin_id = plugin.id();
+        if self.plugin_ids().contains(&plugin_id) {
+            return Err(EngineError::DuplicatePluginId { plugin_id });
+        }
+        self.plugins.push(plugin);
+        Ok(self)
+    }
+}
+
+/// A running async engine, returned by `event_engine_async`. Dropping it stops the proxy as
+/// `shutdown` does, but does not wait for the plugins.
+pub struct AsyncEngineHandle {
+    context: zmq::Context,
+    publisher: Mutex<(Socket, FlatBufferBuilder<'static>)>,
+    // stops the proxy task, which then publishes the PluginTerminateEvent
+    stop: oneshot::Sender<()>,
+    // true once the proxy task has stopped
+    stopped: watch::Receiver<bool>,
+    proxy: JoinHandle<Result<(), EngineError>>,
+    plugins: Vec<(i32, JoinHandle<Result<(), PluginError>>)>,
+    stopping: Arc<AtomicBool>,
+    rejected_events: Arc<AtomicU64>,
+    ipc_paths: Vec<std::path::PathBuf>,
+}
+
+impl AsyncEngineHandle {
+    /// Publish `event` to the plugins and external subscribers, as if it came from a plugin.
+    pub fn publish(&self, event: &Event) -> Result<(), EngineError> {
+        let mut publisher = self.publisher.lock().expect("publisher lock poisoned");
+        let (socket, bldr) = &mut *publisher;
+        event
+            .send(socket, bldr)
+            .map_err(|source| EngineError::Io(source.into()))
+    }
+
+    /// Wait for the proxy to stop, e.g., once it has forwarded the events of
+    /// `EventEngineBuilder::stop_after`; the engine still has to be shut down.
+    pub async fn stopped(&self) {
+        let mut stopped = self.stopped.clone();
+        // an error means the proxy task is gone, which it only is once stopped
+        let _ = stopped.wait_for(|stopped| *stopped).await;
+    }
+
+    /// How many events the proxy dropped for being over the maximum payload size.
+    pub fn rejected_events(&self) -> u64 {
+        self.rejected_events.load(Ordering::Relaxed)
+    }
+
+    /// The results of the plugins that return within `timeout`, by plugin id; each plugin's
+    /// result is only returned once. A plugin that panicked gets an error with the panic
+    /// message.
+    pub async fn wait_for_plugins(
+        &mut self,
+        timeout: Duration,
+    ) -> HashMap<i32, Result<(), PluginError>> {
+        let deadline = tokio::time::Instant::now() + timeout;
+        let mut results = HashMap::new();
+        let mut running = Vec::new();
+        for (plugin_id, mut plugin) in self.plugins.drain(..) {
+            match tokio::time::timeout_at(deadline, &mut plugin).await {
+                Ok(joined) => {
+                    results.insert(plugin_id, plugin_result(joined));
+                }
+                Err(_) => running.push((plugin_id, plugin)),
+            }
+        }
+        self.plugins = running;
+        results
+    }
+
+    /// Stop the proxy, which publishes the PluginTerminateEvent, wait for the plugins to return
+    /// and close the sockets.
+    pub async fn shutdown(self) -> Result<(), EngineError> {
+        let AsyncEngineHandle {
+            context,
+            publisher,
+            stop,
+            proxy,
+            plugins,
+            stopping,
+            ipc_paths,
+            ..
+        } = self;
+        stopping.store(true, Ordering::SeqCst);
+        // the proxy may have stopped by itself already
+        let _ = stop.send(());
+        let proxied = proxy.await.unwrap_or_else(|e| {
+            Err(EngineError::Io(io::Error::other(format!(
+                "the proxy task failed: {}",
+                e
+            ))))
+        });
+        for (plugin_id, plugin) in plugins {
+            if let Err(e) = plugin_result(plugin.await) {
+                error!(plugin_id; "plugin {} returned an error: {}", plugin_id, e);
+            }
+        }
+        drop(publisher);
+        // terminating the context waits for the sockets to send what they hold, up to their
+        // linger period
+        let terminated = tokio::task::spawn_blocking(move || drop(context)).await;
+        remove_ipc_files(&ipc_paths);
+        if terminated.is_err() {
+            error!("Engine could not terminate its zmq context");
+        }
+        proxied
+    }
+}
+
+// The option of `config` the async engine does not implement, if it asks for one.
+fn unsupported_option(config: &EngineConfig) -> Option<&'static str> {
+    #[cfg(feature = "prometheus")]
+    if config.metrics_addr.is_some() {
+        return Some("metrics_addr");
+    }
+    [
+        ("last_value_cache", !config.last_value_cache.is_empty()),
+        ("event_log", config.event_log.is_some()),
+        ("admin_port", config.admin_port.is_some()),
+        ("heartbeat_interval", !config.heartbeat_interval.is_zero()),
+        ("registration_window", !config.registration_window.is_zero()),
+        ("profile", config.profile.is_some()),
+        ("plugins", !config.plugins.is_empty()),
+        (
+            "acknowledged_delivery",
+            config.acknowledged_delivery.is_some(),
+        ),
+    ]
+    .into_iter()
+    .find_map(|(option, set)| set.then_some(option))
+}
+
+/// Start an engine on the current tokio runtime, which must have its I/O driver and timers
+/// enabled (see `tokio::runtime::Builder::enable_all`): bind the engine sockets, start and sync
+/// the plugins, and run the proxy as a task. Returns once the plugins are synced; the sync
+/// handshake itself runs in a blocking thread. A current-thread runtime is enough for
+/// the engine and its async plugins.
+pub async fn event_engine_async(
+    config: &EngineConfig,
+    plugins: AsyncPluginRegistry,
+) -> Result<AsyncEngineHandle, EngineError> {
+    info!("Starting async EVENT engine");
+    check_plugin_ids(&plugins.plugin_ids(), &[])?;
+    if let Some(option) = unsupported_option(config) {
+        return Err(EngineError::AsyncUnsupported { option });
+    }
+    if config.curve.is_some() && config.transport == Transport::Tcp {
+        return Err(EngineError::CurveTcpOnly);
+    }
+    let unknown = plugins
+        .plugins
+        .iter()
+        .flat_map(|p| UnknownSubscription::find(p.id(), p.subscriptions()))
+        .collect();
+    check_subscriptions(config, unknown)?;
+    let stop_after = match &config.stop_after {
+        Some((event_type, count)) => {
+            let header = get_event_type_bytes_filter(event_type).map_err(|_| {
+                EngineError::UnknownEventType {
+                    event_type: event_type.clone(),
+                }
+            })?;
+            Some((header, *count))
+        }
+        None => None,
+    };
+    let rejected_events = Arc::new(AtomicU64::new(0));
+    let limit = (config.max_payload_size > 0).then(|| PayloadLimit {
+        max_bytes: config.max_payload_size,
+        rejected: Arc::clone(&rejected_events),
+    });
+    let context = zmq::Context::new();
+    set_io_threads(&context, config, true)?;
+    let ipc_paths = claim_ipc_paths(config)?;
+    let mut config = config.clone();
+    let outgoing = get_outgoing_socket(&context, &mut config)?;
+    let incoming = get_incoming_socket(&context, &mut config)?;
+    let sync_sockets = bind_sync_sockets(&context, &mut config, false)?;
+    let config = &config;
+    let publisher = create_socket(&context, config, zmq::PUB, "publisher")?;
+    connect(&publisher, &config.incoming_endpoint())?;
+
+    let stopping = Arc::new(AtomicBool::new(false));
+    let mut synced_plugins = SyncedPlugins {
+        plugin_ids: plugins.plugin_ids(),
+        external_ids: Vec::new(),
+        restartable_ids: Vec::new(),
+        optional_ids: Vec::new(),
+        skipped: Vec::new(),
+    };
+    let required_ids = synced_plugins.plugin_ids.clone();
+    let mut plugin_tasks = Vec::new();
+    for plugin in plugins.plugins {
+        let plugin_id = plugin.id();
+        let sockets = create_plugin_sockets(
+            &context,
+            config,
+            plugin_id,
+            plugin.subscriptions(),
+            Hwm::default(),
+        )?;
+        let task = spawn_plugin(plugin, sockets, config, &stopping)?;
+        plugin_tasks.push((plugin_id, task));
+    }
+
+    // the handshake blocks for as long as the plugins take to sync, which the async plugins
+    // do on this runtime
+    let sync_config = config.clone();
+    let (incoming, outgoing, synced) = tokio::task::spawn_blocking(move || {
+        let synced = sync_plugins(
+            &sync_config,
+            sync_sockets,
+            &mut synced_plugins,
+            &[required_ids],
+            &PluginStatuses::default(),
+            &incoming,
+            &outgoing,
+        );
+        (incoming, outgoing, synced.map(drop))
+    })
+    .await
+    .map_err(|e| EngineError::Io(io::Error::other(e.to_string())))?;
+    synced?;
+
+    let incoming = AsyncSocket::new(incoming)?;
+    let (stop, stop_rx) = oneshot::channel();
+    let (stopped_tx, stopped) = watch::channel(false);
+    let proxy = tokio::spawn(async move {
+        info!("Engine starting main proxy");
+        let mut outgoing = outgoing;
+        let proxied = forward_events(incoming, &mut outgoing, limit, stop_after, stop_rx).await;
+        let _ = stopped_tx.send(true);
+        proxied?;
+        info!("Engine proxy terminated, publishing plugin terminate event");
+        send_plugin_terminate_event(&mut outgoing, &mut FlatBufferBuilder::new())?;
+        Ok(())
+    });
+
+    Ok(AsyncEngineHandle {
+        context,
+        publisher: Mutex::new((publisher, FlatBufferBuilder::new())),
+        stop,
+        stopped,
+        proxy,
+        plugins: plugin_tasks,
+        stopping,
+        rejected_events,
+        ipc_paths,
+    })
+}
+
+// Start `plugin` on its `sockets`, as a task of the runtime or in a blocking thread; either way
+// it first syncs with the engine.
+fn spawn_plugin(
+    plugin: RegisteredPlugin,
+    sockets: PluginSockets,
+    config: &EngineConfig,
+    stopping: &Arc<AtomicBool>,
+) -> Result<JoinHandle<Result<(), PluginError>>, EngineError> {
+    let plugin_id = plugin.id();
+    let PluginSockets {
+        pub_socket,
+        sub_socket,
+        sync,
+        ..
+    } = sockets;
+    let mut ctx = PluginContext::new(plugin_id, pub_socket, sub_socket)
+        .with_stopping(Arc::clone(stopping))
+        .with_subscriptions(plugin.subscriptions());
+    ctx.set_max_payload_size(config.max_payload_size);
+    ctx.set_compression(config.compression);
+    ctx.set_codec(config.codec);
+    ctx.set_engine_id(config.engine_id);
+    let not_synced =
+        move || PluginError::Other(format!("plugin {} did not sync with the engine", plugin_id));
+    let sync_timeout = config.sync_timeout;
+    let plugin = match plugin {
+        RegisteredPlugin::Blocking(plugin) => {
+            return Ok(tokio::task::spawn_blocking(move || {
+                if !sync_with_engine(plugin_id, &sync, &ctx.sub_socket, sync_timeout) {
+                    return Err(not_synced());
+                }
+                drop(sync);
+                run_plugin(plugin, ctx)
+            }));
+        }
+        RegisteredPlugin::Async(plugin) => plugin,
+    };
+    let spawn_error = |e: &dyn std::fmt::Display| EngineError::PluginSpawn {
+        plugin_id,
+        reason: e.to_string(),
+    };
+    let mut sync = AsyncSocket::new(sync).map_err(|e| spawn_error(&e))?;
+    let ctx = AsyncPluginContext::new(ctx).map_err(|e| spawn_error(&e))?;
+    Ok(tokio::spawn(async move {
+        if !sync_with_engine_async(plugin_id, &mut sync).await {
+            return Err(not_synced());
+        }
+        drop(sync);
+        run_async_plugin(plugin, ctx).await
+    }))
+}
+
+// As `event_engine::sync_with_engine`, without blocking the runtime.
+async fn sync_with_engine_async(plugin_id: i32, sync: &mut AsyncSocket) -> bool {
+    // no probe: the engine releases the plugin on its first reply, and its context skips the
+    // probes published to it meanwhile
+    let msg = format!("{} {}", SYNC_READY, plugin_id);
+    // a REQ socket queues its request for the engine, bound or not yet
+    if let Err(e) = sync.socket.send(msg.as_str(), zmq::DONTWAIT) {
+        error!(plugin_id; "plugin {} could not send sync message: {}", plugin_id, e);
+        return false;
+    }
+    if let Err(e) = sync.readable().await {
+        error!(plugin_id; "plugin {} could not wait for the sync reply: {}", plugin_id, e);
+        return false;
+    }
+    match sync.socket.recv_msg(zmq::DONTWAIT) {
+        Ok(reply) if reply.as_str() == Some("ok") => {
+            info!(plugin_id; "plugin {} got sync reply, will now wait for messages", plugin_id);
+            true
+        }
+        Ok(reply) => {
+            error!(plugin_id; "plugin {} got sync reply {:?}, exiting", plugin_id, reply.as_str());
+            false
+        }
+        Err(e) => {
+            error!(plugin_id; "plugin {} got error trying to receive sync reply: {}", plugin_id, e);
+            false
+        }
+    }
+}
+
+// Run the future of an async plugin, as `event_engine::run_plugin` runs a plugin's start
+// function.
+async fn run_async_plugin(
+    plugin: Box<dyn AsyncPlugin>,
+    ctx: AsyncPluginContext,
+) -> Result<(), PluginError> {
+    let plugin_id = plugin.id();
+    let name = plugin.name().to_string();
+    info!(plugin_id; "Executing start function for plugin {} ({})", plugin_id, name);
+    match plugin.start(ctx).await {
+        Ok(()) => Ok(()),
+        // the plugin was still waiting for an event when the engine shut down
+        Err(PluginError::Stopped) => {
+            info!(plugin_id; "plugin {} ({}) stopped with the engine", plugin_id, name);
+            Ok(())
+        }
+        Err(error) => {
+            error!(
+                plugin_id;
+                "got error executing start function of plugin {} ({}): {}",
+                plugin_id, name, error
+            );
+            Err(error)
+        }
+    }
+}
+
+// The result of a plugin's task; a panic is returned as an error with the panic message.
+fn plugin_result(joined: Result<Result<(), PluginError>, JoinError>) -> Result<(), PluginError> {
+    joined.unwrap_or_else(|e| {
+        let message = match e.try_into_panic() {
+            Ok(payload) => panic_message(payload.as_ref()),
+            Err(e) => e.to_string(),
+        };
+        Err(PluginError::Other(message))
+    })
+}
+
+// Forward the events received on `incoming` to `outgoing` until told to `stop` (or the handle
+// is dropped), or until it has forwarded `stop_after` events with the given header. Events with
+// a payload over `limit` are dropped, and an EventRejectedEvent is published in their place.
+async fn forward_events(
+    mut incoming: AsyncSocket,
+    outgoing: &mut Socket,
+    limit: Option<PayloadLimit>,
+    stop_after: Option<(Vec<u8>, u64)>,
+    mut stop: oneshot::Receiver<()>,
+) -> Result<(), EngineError> {
+    let proxy_error = |source| EngineError::Proxy { source };
+    let mut bldr = FlatBufferBuilder::new();
+    let mut forwarded = 0;
+    loop {
+        tokio::select! {
+            _ = &mut stop => return Ok(()),
+            ready = incoming.readable() => ready?,
+        }
+        // take a burst in one go, as the engine's own loop does
+        for _ in 0..FORWARD_BATCH {
+            let frames = match recv_event_frames(&incoming.socket, zmq::DONTWAIT) {
+                Ok(frames) => frames,
+                Err(zmq::Error::EAGAIN) => break,
+                Err(e) => return Err(proxy_error(e)),
+            };
+            if let Some(rejected) = limit.as_ref().and_then(|limit| limit.reject(&frames)) {
+                let data = rejected
+                    .build(&mut bldr)
+                    .expect("building an event does not fail");
+                send_event_msg(outgoing, rejected.event_type(), data).map_err(proxy_error)?;
+                continue;
+            }
+            send_frames(outgoing, frames.iter().map(|frame| &frame[..])).map_err(proxy_error)?;
+            if let Some((header, count)) = &stop_after {
+                if frames[0].starts_with(header) {
+                    forwarded += 1;
+                    if forwarded == *count {
+                        info!("Engine forwarded {} events, stopping the proxy", count);
+                        return Ok(());
+                    }
+                }
+            }
+        }
+        // a steady stream of events must not keep the runtime's other tasks from running
+        tokio::task::yield_now().await;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::event_engine::EventEngineBuilder;
+    use crate::events::NewImage;
+    use crate::image_score_plugin::{FixedScorer, ImageScorePlugin};
+    use crate::image_store_plugin::ImageStorePlugin;
+    use crate::storage::InMemoryStore;
+
+    fn runtime() -> tokio::runtime::Runtime {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_image_pipeline_on_a_current_thread_runtime() {
+        let storage = InMemoryStore::new();
+        let scorer = FixedScorer::new(vec![("labrador".to_string(), 0.9)]);
+        let mut plugins = AsyncPluginRegistry::new();
+        plugins
+            .register_async(Box::new(
+                ImageScorePlugin::with_scorer(1, Box::new(scorer)).images(5),
+            ))
+            .unwrap()
+            .register_blocking(Box::new(
+                ImageStorePlugin::new(2)
+                    .images(5)
+                    .storage(Box::new(storage.clone())),
+            ))
+            .unwrap();
+        let config = EventEngineBuilder::new()
+            .incoming_inproc("messages-async")
+            .outgoing_inproc("events-async")
+            .transport(Transport::InprocOnly)
+            .stop_after("ImageStoredEvent", 5)
+            .build();
+
+        runtime().block_on(async {
+            let mut engine = event_engine_async(&config, plugins).await.unwrap();
+            for i in 0..5 {
+                let new_image = NewImage {
+                    image_uuid: format!("async-{}", i),
+                    image_format: "png".to_string(),
+                    image: vec![i, 1, 2, 3],
+                    location: None,
+                };
+                engine.publish(&Event::NewImage(new_image)).unwrap();
+            }
+            tokio::time::timeout(Duration::from_secs(10), engine.stopped())
+                .await
+                .expect("the pipeline did not store the images");
+            let results = engine.wait_for_plugins(Duration::from_secs(5)).await;
+            assert_eq!(results.len(), 2, "plugins did not exit: {:?}", results);
+            assert!(results.values().all(|result| result.is_ok()));
+            engine.shutdown().await.unwrap();
+        });
+        assert_eq!(storage.len(), 5);
+        let stored = crate::storage::StorageBackend::get(&storage, "async-3").unwrap();
+        assert_eq!(stored, Some(vec![3, 1, 2, 3]));
+    }
+
+    #[test]
+    fn test_unsupported_options_and_duplicate_ids_are_refused() {
+        let mut plugins = AsyncPluginRegistry::new();
+        plugins
+            .register_async(Box::new(ImageScorePlugin::new(1)))
+            .unwrap();
+        assert!(matches!(
+            plugins.register_blocking(Box::new(ImageStorePlugin::new(1))),
+            Err(EngineError::DuplicatePluginId { plugin_id: 1 })
+        ));
+        let config = EventEngineBuilder::new()
+            .incoming_inproc("messages-async-unsupported")
+            .outgoing_inproc("events-async-unsupported")
+            .transport(Transport::InprocOnly)
+            .last_value_cache(&["ImageStoredEvent"])
+            .build();
+        let started = runtime().block_on(event_engine_async(&config, plugins));
+        assert!(matches!(
+            started,
+            Err(EngineError::AsyncUnsupported {
+                option: "last_value_cache"
+            })
+        ));
+    }
+}
@@ -0,0 +1,339 @@
+//! plyoreacto-send, which publishes an event to an engine from the shell, the counterpart of
+//! plyoreacto-tap. Run
+//!
+//!     plyoreacto-send new-image --file <path> [--format <format>] [--uuid <uuid>] [<option>...]
+//!     plyoreacto-send raw --type <event type> --json <JSON object> [<option>...]
+//!
+//! to publish a NewImageEvent of the image in a file (its format defaults to the extension of the
+//! file, and a new uuid, printed, is made up unless one is given), or an event of any type from
+//! its JSON object, as made by `Event::to_json`. The options are
+//!
+//! - `--address <host:port or endpoint>`: the incoming socket of the engine (localhost:5559 by
+//!   default)
+//! - `--codec <flatbuffers or json>`: how the payload is serialized (flatbuffers by default)
+//! - `--timeout <ms>`: how long to wait for the engine (5000 by default)
+//!
+//! A publisher's first messages are dropped until the engine's subscription reaches it, so the
+//! event is only sent once it has: plyoreacto-send publishes on an XPUB socket, which receives the
+//! subscriptions, and exits with an error if none arrives in time, or if the event cannot be made.
+//!
+
+use std::path::Path;
+use std::process::ExitCode;
+use std::time::Duration;
+
+use flatbuffers::FlatBufferBuilder;
+use plyoreacto::events::{send_event_msg_with_meta, Codec, Event, EventMeta, NewImage};
+
+const USAGE: &str = "usage: plyoreacto-send new-image --file <path> [--format <format>] \
+                     [--uuid <uuid>] [<option>...]
+       plyoreacto-send raw --type <event type> --json <JSON object> [<option>...]
+options: --address <host:port or endpoint> (localhost:5559), --codec <flatbuffers or json>, \
+                     --timeout <ms> (5000)";
+
+// The incoming socket of an engine with the default ports, on this host.
+const DEFAULT_ADDRESS: &str = "localhost:5559";
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+// The source plugin id of the envelopes of the events sent, which no plugin published.
+const SEND_SOURCE_PLUGIN_ID: i32 = -1;
+
+// The event to send, as given on the command line.
+#[derive(Debug, PartialEq)]
+enum Command {
+    NewImage {
+        file: String,
+        format: Option<String>,
+        image_uuid: Option<String>,
+    },
+    Raw {
+        event_type: String,
+        json: String,
+    },
+}
+
+// Where and how to send the event, as given on the command line.
+#[derive(Debug, PartialEq)]
+struct Options {
+    endpoint: String,
+    codec: Codec,
+    timeout: Duration,
+    command: Command,
+}
+
+// The options of the arguments `args` (without the name of the program); Ok(None) for --help.
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Option<Options>, String> {
+    let mut args = args.into_iter();
+    let mut subcommand = None;
+    let mut address = DEFAULT_ADDRESS.to_string();
+    let mut codec = Codec::Flatbuffers;
+    let mut timeout = DEFAULT_TIMEOUT;
+    let (mut file, mut format, mut image_uuid, mut event_type, mut json) =
+        (None, None, None, None, None);
+    while let Some(arg) = args.next() {
+        let mut value = |what: &str| args.next().ok_or(format!("{} takes {}", arg, what));
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "--address" => address = value("a host:port or an endpoint")?,
+            "--codec" => {
+                codec = match value("flatbuffers or json")?.as_str() {
+                    "flatbuffers" => Codec::Flatbuffers,
+                    "json" => Codec::Json,
+                    codec => return Err(format!("unknown codec {}", codec)),
+                }
+            }
+            "--timeout" => {
+                let ms = value("a number of milliseconds")?;
+                let ms = ms.parse().map_err(|_| {
+                    format!("--timeout takes a number of milliseconds, not {:?}", ms)
+                })?;
+                timeout = Duration::from_millis(ms);
+            }
+            "--file" => file = Some(value("the path of an image")?),
+            "--format" => format = Some(value("an image format, e.g., jpg")?),
+            "--uuid" => image_uuid = Some(value("the uuid of the image")?),
+            "--type" => event_type = Some(value("an event type")?),
+            "--json" => json = Some(value("the JSON object of an event")?),
+            _ if arg.starts_with('-') => return Err(format!("unknown option {}", arg)),
+            _ if subcommand.is_none() => subcommand = Some(arg),
+            _ => return Err(format!("unexpected argument {}", arg)),
+        }
+    }
+    let command = match subcommand.as_deref() {
+        Some("new-image") => {
+            if event_type.is_some() || json.is_some() {
+                return Err("--type and --json are for raw events".to_string());
+            }
+            Command::NewImage {
+                file: file.ok_or("new-image takes the --file of the image")?,
+                format,
+                image_uuid,
+            }
+        }
+        Some("raw") => {
+            if file.is_some() || format.is_some() || image_uuid.is_some() {
+                return Err("--file, --format and --uuid are for new images".to_string());
+            }
+            Command::Raw {
+                event_type: event_type.ok_or("raw takes the --type of the event")?,
+                json: json.ok_or("raw takes the --json object of the event")?,
+            }
+        }
+        Some(subcommand) => return Err(format!("unknown command {}", subcommand)),
+        None => return Err("expected a command, new-image or raw".to_string()),
+    };
+    // a host:port is on TCP; anything else, e.g., "ipc:///run/plyoreacto/incoming.sock", is
+    // taken as it is
+    let endpoint = match address.contains("://") {
+        true => address,
+        false => format!("tcp://{}", address),
+    };
+    Ok(Some(Options {
+        endpoint,
+        codec,
+        timeout,
+        command,
+    }))
+}
+
+// The event of `command`.
+fn event(command: &Command) -> Result<Event, String> {
+    match command {
+        Command::NewImage {
+            file,
+            format,
+            image_uuid,
+        } => {
+            let image =
+                std::fs::read(file).map_err(|e| format!("could not read {}: {}", file, e))?;
+            let image_format = match format {
+                Some(format) => format.clone(),
+                None => Path::new(file)
+                    .extension()
+                    .and_then(|extension| extension.to_str())
+                    .map(str::to_lowercase)
+                    .ok_or(format!(
+                        "give the --format of {}, which has no extension",
+                        file
+                    ))?,
+            };
+            Ok(Event::NewImage(NewImage {
+                image_uuid: match image_uuid {
+                    Some(image_uuid) => image_uuid.clone(),
+                    None => uuid::Uuid::new_v4().to_string(),
+                },
+                image_format,
+                image,
+                location: None,
+            }))
+        }
+        Command::Raw { event_type, json } => {
+            Event::from_json(event_type, json.as_bytes()).map_err(|e| e.to_string())
+        }
+    }
+}
+
+// Send `event` as `options` say, once the engine subscribed.
+fn send(options: &Options, event: &Event) -> Result<(), String> {
+    let socket_error = |e: zmq::Error| format!("could not publish to {}: {}", options.endpoint, e);
+    let context = zmq::Context::new();
+    let socket = context.socket(zmq::XPUB).map_err(socket_error)?;
+    // the event is sent before exiting, unless the engine goes away in the meantime
+    let timeout_ms = options.timeout.as_millis().try_into().unwrap_or(i32::MAX);
+    socket.set_linger(timeout_ms).map_err(socket_error)?;
+    socket.set_rcvtimeo(timeout_ms).map_err(socket_error)?;
+    socket.connect(&options.endpoint).map_err(socket_error)?;
+    match socket.recv_bytes(0) {
+        // a subscription: 1, then the prefix subscribed to
+        Ok(subscription) if subscription.first() == Some(&1) => {}
+        Ok(_) => return Err(format!("unexpected message from {}", options.endpoint)),
+        Err(zmq::Error::EAGAIN) => {
+            return Err(format!(
+                "no engine subscribed on {} within {} ms",
+                options.endpoint,
+                options.timeout.as_millis()
+            ))
+        }
+        Err(e) => return Err(socket_error(e)),
+    }
+    let mut meta = EventMeta::new(SEND_SOURCE_PLUGIN_ID);
+    meta.codec = options.codec;
+    let mut bldr = FlatBufferBuilder::new();
+    let json;
+    let payload = match options.codec {
+        Codec::Flatbuffers => event.encode(&mut bldr),
+        Codec::Json => {
+            json = event.to_json();
+            &json
+        }
+    };
+    send_event_msg_with_meta(&socket, event.type_name(), &meta, payload).map_err(socket_error)
+}
+
+fn main() -> ExitCode {
+    let options = match parse_args(std::env::args().skip(1)) {
+        Ok(Some(options)) => options,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        }
+        Err(e) => {
+            eprintln!("plyoreacto-send: {}\n{}", e, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+    let sent = event(&options.command).and_then(|event| {
+        send(&options, &event)?;
+        Ok(event)
+    });
+    match sent {
+        Ok(Event::NewImage(new_image)) => {
+            println!("{}", new_image.image_uuid);
+            ExitCode::SUCCESS
+        }
+        Ok(_) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("plyoreacto-send: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Option<Options>, String> {
+        parse_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let options = parse(&["new-image", "--file", "photo.JPG"])
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            options,
+            Options {
+                endpoint: "tcp://localhost:5559".to_string(),
+                codec: Codec::Flatbuffers,
+                timeout: DEFAULT_TIMEOUT,
+                command: Command::NewImage {
+                    file: "photo.JPG".to_string(),
+                    format: None,
+                    image_uuid: None,
+                },
+            }
+        );
+        let args = [
+            "--codec",
+            "json",
+            "raw",
+            "--type",
+            "ImageDeletedRequestEvent",
+            "--json",
+            r#"{"image_uuid": "5b0c"}"#,
+            "--address",
+            "ipc:///tmp/incoming.sock",
+            "--timeout",
+            "250",
+        ];
+        let options = parse(&args).unwrap().unwrap();
+        assert_eq!(
+            options,
+            Options {
+                endpoint: "ipc:///tmp/incoming.sock".to_string(),
+                codec: Codec::Json,
+                timeout: Duration::from_millis(250),
+                command: Command::Raw {
+                    event_type: "ImageDeletedRequestEvent".to_string(),
+                    json: r#"{"image_uuid": "5b0c"}"#.to_string(),
+                },
+            }
+        );
+        assert_eq!(parse(&["--help"]), Ok(None));
+        for args in [
+            &[][..],
+            &["new-image"],
+            &["new-image", "--file"],
+            &["new-image", "--file", "a.png", "--type", "NewImageEvent"],
+            &["raw", "--type", "ImageScoredEvent"],
+            &[
+                "raw",
+                "--file",
+                "a.png",
+                "--type",
+                "NewImageEvent",
+                "--json",
+                "{}",
+            ],
+            &["delete", "--file", "a.png"],
+            &["new-image", "extra", "--file", "a.png"],
+            &["new-image", "--file", "a.png", "--codec", "protobuf"],
+            &["new-image", "--file", "a.png", "--timeout", "soon"],
+        ] {
+            assert!(parse(args).is_err(), "{:?}", args);
+        }
+    }
+
+    #[test]
+    fn test_raw_events_are_decoded() {
+        let command = |event_type: &str, json: &str| Command::Raw {
+            event_type: event_type.to_string(),
+            json: json.to_string(),
+        };
+        let json = r#"{"image_uuid": "5b0c", "path": "", "deduplicated": false}"#;
+        match event(&command("ImageStoredEvent", json)).unwrap() {
+            Event::ImageStored(stored) => assert_eq!(stored.image_uuid, "5b0c"),
+            event => panic!("expected an ImageStoredEvent, got {:?}", event),
+        }
+        assert!(event(&command("ImageStoredEvent", r#"{"image_uuid": "5b0c"}"#)).is_err());
+        assert!(event(&command("NoSuchEvent", "{}")).is_err());
+        let missing = Command::NewImage {
+            file: "/no/such/image.png".to_string(),
+            format: None,
+            image_uuid: None,
+        };
+        assert!(event(&missing).unwrap_err().contains("/no/such/image.png"));
+    }
+}
@@ -0,0 +1,271 @@
+//! plyoreacto-tap, which prints the events an engine publishes, for debugging without writing a
+//! plugin. Run
+//!
+//!     plyoreacto-tap [--address <host:port or endpoint>] [--json] [--count <n>] [<event type>...]
+//!
+//! to subscribe to the outgoing socket of the engine (localhost:5560 by default) and print each
+//! event of the given types (all of them if none is given) on a line of its own: its timestamp,
+//! type, image uuid, payload size and the fields of its payload worth reading, or, with `--json`,
+//! the JSON object the logger plugin writes. With `--count`, it exits after that many events.
+//! Events are received as plugins receive them (see `PluginContext::next_event`), so compressed
+//! and JSON payloads are printed as any other.
+//!
+
+use std::process::ExitCode;
+
+use plyoreacto::events::get_event_type_bytes_filter;
+use plyoreacto::plugin::{PluginContext, PluginError};
+use serde_json::Value;
+
+const USAGE: &str = "usage: plyoreacto-tap [--address <host:port or endpoint>] [--json] \
+                     [--count <n>] [<event type>...]";
+
+// The outgoing socket of an engine with the default ports, on this host.
+const DEFAULT_ADDRESS: &str = "localhost:5560";
+
+// What to tap, and how to print it, as given on the command line.
+#[derive(Debug, PartialEq)]
+struct Options {
+    endpoint: String,
+    event_types: Vec<String>,
+    json: bool,
+    count: Option<u64>,
+}
+
+// The options of the arguments `args` (without the name of the program); Ok(None) for --help.
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Option<Options>, String> {
+    let mut address = DEFAULT_ADDRESS.to_string();
+    let mut options = Options {
+        endpoint: String::new(),
+        event_types: Vec::new(),
+        json: false,
+        count: None,
+    };
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "--json" => options.json = true,
+            "--address" => {
+                address = args
+                    .next()
+                    .ok_or("--address takes a host:port or an endpoint")?;
+            }
+            "--count" => {
+                let count = args.next().ok_or("--count takes a number of events")?;
+                let count = count
+                    .parse()
+                    .map_err(|_| format!("--count takes a number of events, not {:?}", count))?;
+                options.count = Some(count);
+            }
+            _ if arg.starts_with('-') => return Err(format!("unknown option {}", arg)),
+            event_type => {
+                get_event_type_bytes_filter(event_type)
+                    .map_err(|_| format!("unknown event type {}", event_type))?;
+                options.event_types.push(event_type.to_string());
+            }
+        }
+    }
+    // a host:port is on TCP; anything else, e.g., "ipc:///run/plyoreacto/outgoing.sock", is
+    // taken as it is
+    options.endpoint = match address.contains("://") {
+        true => address,
+        false => format!("tcp://{}", address),
+    };
+    Ok(Some(options))
+}
+
+// The summary line of an event, from its JSON object (see `EventMsg::to_json`), e.g.,
+// "2024-05-01 12:00:00.250 ImageScoredEvent 5b0c... 120 B scores={"labrador":0.93}".
+fn summary(entry: &Value) -> String {
+    let mut line = format!(
+        "{} {} {} {} B",
+        utc_time(entry["timestamp_ms"].as_u64().unwrap_or_default()),
+        entry["type"].as_str().unwrap_or_default(),
+        entry["uuid"].as_str().unwrap_or("-"),
+        entry["payload_size"],
+    );
+    if let Some(fields) = entry["fields"].as_object() {
+        for (name, value) in fields {
+            match value {
+                Value::Null => {}
+                Value::String(value) => line.push_str(&format!(" {}={}", name, value)),
+                value => line.push_str(&format!(" {}={}", name, value)),
+            }
+        }
+    }
+    if let Some(error) = entry["decode_error"].as_str() {
+        line.push_str(&format!(" decode_error={:?}", error));
+    }
+    line
+}
+
+// A time in milliseconds since the epoch as a UTC date and time, e.g., 2024-05-01 12:00:00.250.
+fn utc_time(timestamp_ms: u64) -> String {
+    let (secs, ms) = (timestamp_ms / 1000, timestamp_ms % 1000);
+    let (days, secs) = (secs / 86400, secs % 86400);
+    // days since the epoch to a civil date, after http://howardhinnant.github.io/date_algorithms.html
+    let z = days as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}.{:03}",
+        year,
+        month,
+        day,
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60,
+        ms
+    )
+}
+
+fn tap(options: &Options) -> Result<(), PluginError> {
+    let context = zmq::Context::new();
+    let sub_socket = context.socket(zmq::SUB)?;
+    sub_socket.connect(&options.endpoint)?;
+    if options.event_types.is_empty() {
+        sub_socket.set_subscribe(b"")?;
+    }
+    for event_type in &options.event_types {
+        let filter = get_event_type_bytes_filter(event_type).map_err(PluginError::Other)?;
+        sub_socket.set_subscribe(&filter)?;
+    }
+    // the context of a plugin never publishing, for receiving the events as plugins do
+    let pub_socket = context.socket(zmq::PUB)?;
+    let mut ctx = PluginContext::new(0, pub_socket, sub_socket);
+    let mut tapped = 0;
+    while options.count != Some(tapped) {
+        let entry = match ctx.next_event() {
+            Ok(msg) => msg.to_json(),
+            Err(PluginError::Socket(e)) => return Err(PluginError::Socket(e)),
+            // e.g., a message of an older engine, or a malformed event
+            Err(e) => {
+                eprintln!("plyoreacto-tap: skipped a message: {}", e);
+                continue;
+            }
+        };
+        match options.json {
+            true => println!("{}", entry),
+            false => println!("{}", summary(&entry)),
+        }
+        tapped += 1;
+    }
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let options = match parse_args(std::env::args().skip(1)) {
+        Ok(Some(options)) => options,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        }
+        Err(e) => {
+            eprintln!("plyoreacto-tap: {}\n{}", e, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+    eprintln!(
+        "plyoreacto-tap: tapping {} for {}",
+        options.endpoint,
+        match options.event_types.is_empty() {
+            true => "all events".to_string(),
+            false => options.event_types.join(", "),
+        }
+    );
+    match tap(&options) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("plyoreacto-tap: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    fn parse(args: &[&str]) -> Result<Option<Options>, String> {
+        parse_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let options = parse(&[]).unwrap().unwrap();
+        assert_eq!(
+            options,
+            Options {
+                endpoint: "tcp://localhost:5560".to_string(),
+                event_types: Vec::new(),
+                json: false,
+                count: None,
+            }
+        );
+        let args = [
+            "--json",
+            "ImageScoredEvent",
+            "--count",
+            "3",
+            "--address",
+            "10.0.0.5:6560",
+            "ImageStoredEvent",
+        ];
+        let options = parse(&args).unwrap().unwrap();
+        assert_eq!(
+            options,
+            Options {
+                endpoint: "tcp://10.0.0.5:6560".to_string(),
+                event_types: vec![
+                    "ImageScoredEvent".to_string(),
+                    "ImageStoredEvent".to_string()
+                ],
+                json: true,
+                count: Some(3),
+            }
+        );
+        let options = parse(&["--address", "ipc:///tmp/outgoing.sock"])
+            .unwrap()
+            .unwrap();
+        assert_eq!(options.endpoint, "ipc:///tmp/outgoing.sock");
+        assert_eq!(parse(&["--help"]), Ok(None));
+        assert!(parse(&["NoSuchEvent"]).is_err());
+        assert!(parse(&["--count", "many"]).is_err());
+        assert!(parse(&["--count"]).is_err());
+        assert!(parse(&["--verbose"]).is_err());
+    }
+
+    #[test]
+    fn test_summary() {
+        let entry = json!({
+            "type": "ImageStoredEvent",
+            "timestamp_ms": 1714564800250_u64,
+            "uuid": "5b0c",
+            "payload_size": 120,
+            "fields": {"path": "/images/5b0c.png", "deduplicated": false, "location": null},
+        });
+        assert_eq!(
+            summary(&entry),
+            "2024-05-01 12:00:00.250 ImageStoredEvent 5b0c 120 B deduplicated=false \
+             path=/images/5b0c.png"
+        );
+        let entry = json!({
+            "type": "PluginTerminateEvent",
+            "timestamp_ms": 0,
+            "payload_size": 16,
+            "fields": {},
+        });
+        assert_eq!(
+            summary(&entry),
+            "1970-01-01 00:00:00.000 PluginTerminateEvent - 16 B"
+        );
+    }
+}
@@ -0,0 +1,517 @@
+//! Bridge plugin.
+//! This plugin forwards the events of some types from another engine, e.g., the
+//! ImageStoredEvents of the engines of camera hosts to a central engine: it connects a SUB socket
+//! to the outgoing endpoint of the remote engine, subscribes to the event types listed and
+//! publishes each event received on its own engine (see `PluginContext::publish_bridged`). The
+//! events keep their envelope, marked `via_bridge`, and with it the id of the engine they were
+//! first published on (see `EventEngineBuilder::engine_id`); those without one, such as the
+//! events of the remote engine itself, get a new one.
+//!
+//! An event that crossed a bridge is never forwarded again, so that two engines can be bridged
+//! both ways without sending each other's events back and forth forever. Events thus cross a
+//! single bridge: engines are bridged directly to each engine they get events from, not through
+//! one another.
+//!
+//! The SUB socket reconnects on its own when the remote engine restarts, waiting longer after
+//! each failed attempt (see `BridgePlugin::reconnect_backoff`), and subscribes again; heartbeats
+//! tell it when the remote host is gone without closing the connection. The events published
+//! while the bridge is disconnected are lost.
+//!
+
+use std::time::Duration;
+
+use log::{debug, info, warn};
+use zmq::{Socket, SocketEvent};
+
+use crate::curve::{make_client, CurveClient};
+use crate::event_engine::{connect, EngineError};
+use crate::events::{
+    event_type_header, known_event_type, peek_event_messages, recv_event_frames, retry_on_eintr,
+    EventError, EventMeta,
+};
+use crate::plugin::{Plugin, PluginContext, PluginError};
+
+// Used unless set with the builder methods of `BridgePlugin`.
+const DEFAULT_RECONNECT_INTERVAL: Duration = Duration::from_millis(100);
+const DEFAULT_MAX_RECONNECT_INTERVAL: Duration = Duration::from_secs(10);
+const DEFAULT_HEARTBEAT_INTERVAL: Duration = Duration::from_secs(1);
+// The connection is dropped when the remote engine has not answered a heartbeat for this many
+// heartbeat intervals.
+const HEARTBEAT_MISSED_BEATS: u32 = 3;
+// How often the plugin checks whether its engine is shutting down, in milliseconds.
+const POLL_INTERVAL_MS: i64 = 100;
+const SUB_NAME: &str = "bridge sub";
+
+/// The bridge plugin, for registering with a `PluginRegistry`.
+pub struct BridgePlugin {
+    plugin_id: i32,
+    endpoint: String,
+    event_types: Vec<&'static str>,
+    reconnect_interval: Duration,
+    max_reconnect_interval: Duration,
+    heartbeat_interval: Duration,
+    curve: Option<CurveClient>,
+}
+
+impl BridgePlugin {
+    /// A plugin forwarding the events of `event_types` published on the engine whose outgoing
+    /// endpoint is `endpoint`, e.g., "tcp://camera-1:5560". The PluginTerminateEvent of the
+    /// remote engine is never forwarded. Fails if one of the types is not an event type.
+    pub fn new(
+        plugin_id: i32,
+        endpoint: impl Into<String>,
+        event_types: &[&str],
+    ) -> Result<Self, EngineError> {
+        let mut forwarded = Vec::new();
+        for event_type in event_types {
+            let name =
+                known_event_type(event_type).ok_or_else(|| EngineError::UnknownEventType {
+                    event_type: event_type.to_string(),
+                })?;
+            if name != "PluginTerminateEvent" && !forwarded.contains(&name) {
+                forwarded.push(name);
+            }
+        }
+        Ok(BridgePlugin {
+            plugin_id,
+            endpoint: endpoint.into(),
+            event_types: forwarded,
+            reconnect_interval: DEFAULT_RECONNECT_INTERVAL,
+            max_reconnect_interval: DEFAULT_MAX_RECONNECT_INTERVAL,
+            heartbeat_interval: DEFAULT_HEARTBEAT_INTERVAL,
+            curve: None,
+        })
+    }
+
+    /// Wait `interval` before connecting again to a remote engine that went away, doubled after
+    /// each failed attempt up to `max_interval`.
+    pub fn reconnect_backoff(mut self, interval: Duration, max_interval: Duration) -> Self {
+        self.reconnect_interval = interval;
+        self.max_reconnect_interval = max_interval;
+        self
+    }
+
+    /// Send a heartbeat to the remote engine every `interval`, and reconnect when it has not
+    /// answered for three of them; zero sends none, leaving it to TCP to notice a lost host.
+    pub fn heartbeat_interval(mut self, interval: Duration) -> Self {
+        self.heartbeat_interval = interval;
+        self
+    }
+
+    /// Connect as a CURVE client of a remote engine configured for CURVE.
+    pub fn curve(mut self, curve: CurveClient) -> Self {
+        self.curve = Some(curve);
+        self
+    }
+
+    // The SUB socket connected to the remote engine, and the socket its connections and
+    // disconnections are reported on.
+    fn connect(&self, context: &zmq::Context) -> Result<(Socket, Socket), EngineError> {
+        let socket_error = |source| EngineError::Socket {
+            socket: SUB_NAME.to_string(),
+            source,
+        };
+        let remote = context
+            .socket(zmq::SUB)
+            .map_err(|source| EngineError::SocketCreation {
+                socket: SUB_NAME.to_string(),
+                source,
+            })?;
+        // the events not received yet are of no use once the plugin exits
+        remote.set_linger(0).map_err(socket_error)?;
+        remote
+            .set_reconnect_ivl(millis(self.reconnect_interval))
+            .map_err(socket_error)?;
+        remote
+            .set_reconnect_ivl_max(millis(self.max_reconnect_interval))
+            .map_err(socket_error)?;
+        if !self.heartbeat_interval.is_zero() {
+            remote
+                .set_heartbeat_ivl(millis(self.heartbeat_interval))
+                .map_err(socket_error)?;
+            remote
+                .set_heartbeat_timeout(millis(self.heartbeat_interval * HEARTBEAT_MISSED_BEATS))
+                .map_err(socket_error)?;
+        }
+        if let Some(curve) = &self.curve {
+            make_client(&remote, SUB_NAME, curve)?;
+        }
+        for event_type in &self.event_types {
+            remote
+                .set_subscribe(&event_type_header(event_type))
+                .map_err(socket_error)?;
+        }
+        // the context is the plugin's own, so the name is unique
+        let monitor_endpoint = "inproc://bridge-monitor";
+        let events = SocketEvent::CONNECTED.to_raw() | SocketEvent::DISCONNECTED.to_raw();
+        remote
+            .monitor(monitor_endpoint, events as i32)
+            .map_err(socket_error)?;
+        let monitor = context
+            .socket(zmq::PAIR)
+            .map_err(|source| EngineError::SocketCreation {
+                socket: "bridge monitor".to_string(),
+                source,
+            })?;
+        monitor.set_linger(0).map_err(socket_error)?;
+        connect(&monitor, monitor_endpoint)?;
+        connect(&remote, &self.endpoint)?;
+        Ok((remote, monitor))
+    }
+
+    // Log the connection or disconnection reported on `monitor`.
+    fn log_connection(&self, monitor: &Socket) -> Result<(), PluginError> {
+        let frames = retry_on_eintr(|| monitor.recv_multipart(0))?;
+        let event = frames
+            .first()
+            .and_then(|frame| frame.get(..2))
+            .map(|raw| u16::from_ne_bytes([raw[0], raw[1]]));
+        if event == Some(SocketEvent::CONNECTED.to_raw()) {
+            info!(
+                plugin_id = self.plugin_id;
+                "Bridge plugin connected to {}", self.endpoint
+            );
+        } else if event == Some(SocketEvent::DISCONNECTED.to_raw()) {
+            warn!(
+                plugin_id = self.plugin_id;
+                "Bridge plugin lost {}, reconnecting", self.endpoint
+            );
+        }
+        Ok(())
+    }
+
+    // Publish the event received from the remote engine in `frames` on the local one, unless it
+    // crossed a bridge already.
+    fn forward(&self, ctx: &mut PluginContext, frames: &[zmq::Message]) -> Result<(), PluginError> {
+        let Some((event_type, meta, payload)) = peek_event_messages(frames) else {
+            warn!(
+                plugin_id = self.plugin_id;
+                "Bridge plugin skipped a malformed message from {}", self.endpoint
+            );
+            return Ok(());
+        };
+        let meta = match meta {
+            Some(meta) if meta.via_bridge => {
+                debug!(
+                    plugin_id = self.plugin_id;
+                    "Bridge plugin skipped the {} {} from engine {}, which crossed a bridge already",
+                    event_type, meta.event_id, meta.origin_engine_id
+                );
+                return Ok(());
+            }
+            Some(meta) => meta,
+            None => EventMeta::new(self.plugin_id),
+        };
+        match ctx.publish_bridged(event_type, &meta, payload) {
+            Err(PluginError::Event(e @ EventError::TooLarge { .. })) => {
+                warn!(
+                    plugin_id = self.plugin_id;
+                    "Bridge plugin dropped the {} {} from {}: {}",
+                    event_type, meta.event_id, self.endpoint, e
+                );
+                Ok(())
+            }
+            result => result.map(|_| ()),
+        }
+    }
+}
+
+impl Plugin for BridgePlugin {
+    fn id(&self) -> i32 {
+        self.plugin_id
+    }
+
+    fn name(&self) -> &str {
+        "bridge"
+    }
+
+    fn subscriptions(&self) -> &[&str] {
+        &[]
+    }
+
+    fn start(self: Box<Self>, mut ctx: PluginContext) -> Result<(), PluginError> {
+        // the remote engine is not reached through the engine's context, which may be inproc
+        // only; the sockets go before the context, which would wait for them otherwise
+        let context = zmq::Context::new();
+        let (remote, monitor) = self
+            .connect(&context)
+            .map_err(|e| PluginError::Other(e.to_string()))?;
+        info!(
+            plugin_id = self.plugin_id;
+            "Bridge plugin forwarding {:?} from {}", self.event_types, self.endpoint
+        );
+        loop {
+            let mut items = [
+                ctx.sub_socket.as_poll_item(zmq::POLLIN),
+                remote.as_poll_item(zmq::POLLIN),
+                monitor.as_poll_item(zmq::POLLIN),
+            ];
+            zmq::poll(&mut items, POLL_INTERVAL_MS)?;
+            let readable: Vec<bool> = items.iter().map(|item| item.is_readable()).collect();
+            if readable[0] {
+                while let Some(msg) = ctx.try_next_event()? {
+                    if msg.event_type == "PluginTerminateEvent" {
+                        info!(
+                            plugin_id = self.plugin_id;
+                            "Bridge plugin got terminate event, exiting"
+                        );
+                        return Ok(());
+                    }
+                }
+            }
+            if readable[1] {
+                loop {
+                    match recv_event_frames(&remote, zmq::DONTWAIT) {
+                        Ok(frames) => self.forward(&mut ctx, &frames)?,
+                        Err(zmq::Error::EAGAIN) => break,
+                        Err(e) => return Err(e.into()),
+                    }
+                }
+            }
+            if readable[2] {
+                self.log_connection(&monitor)?;
+            }
+            if !readable.contains(&true) && ctx.is_stopping() {
+                return Err(PluginError::Stopped);
+            }
+        }
+    }
+}
+
+// `duration` in milliseconds, as the zmq socket options take it.
+fn millis(duration: Duration) -> i32 {
+    duration.as_millis().try_into().unwrap_or(i32::MAX)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::event_engine::{EngineHandle, EventEngineBuilder};
+    use crate::events::{Event, ImageStored};
+    use crate::plugin_registry::PluginRegistry;
+    use std::collections::HashMap;
+    use std::sync::mpsc::{channel, Receiver, Sender};
+    use std::time::Instant;
+
+    // Publishes an ImageStoredEvent for each image uuid it is sent, until the sender is dropped.
+    struct ImagePublisher {
+        images_rx: Receiver<String>,
+    }
+
+    impl Plugin for ImagePublisher {
+        fn id(&self) -> i32 {
+            1
+        }
+
+        fn name(&self) -> &str {
+            "image-publisher"
+        }
+
+        fn subscriptions(&self) -> &[&str] {
+            &[]
+        }
+
+        fn start(self: Box<Self>, mut ctx: PluginContext) -> Result<(), PluginError> {
+            for image_uuid in self.images_rx.iter() {
+                ctx.publish(&ImageStored {
+                    image_uuid,
+                    path: String::new(),
+                    deduplicated: false,
+                })?;
+            }
+            Ok(())
+        }
+    }
+
+    // Reports the image uuid and envelope of every ImageStoredEvent it receives.
+    struct StoredObserver {
+        stored_tx: Sender<(String, EventMeta)>,
+    }
+
+    impl Plugin for StoredObserver {
+        fn id(&self) -> i32 {
+            2
+        }
+
+        fn name(&self) -> &str {
+            "stored-observer"
+        }
+
+        fn subscriptions(&self) -> &[&str] {
+            &["ImageStoredEvent"]
+        }
+
+        fn start(self: Box<Self>, mut ctx: PluginContext) -> Result<(), PluginError> {
+            loop {
+                let msg = ctx.next_event()?;
+                if msg.event_type == "PluginTerminateEvent" {
+                    return Ok(());
+                }
+                if let (Ok(Event::ImageStored(stored)), Some(meta)) = (msg.decode(), msg.meta) {
+                    let _ = self.stored_tx.send((stored.image_uuid, meta));
+                }
+            }
+        }
+    }
+
+    // An engine with id `engine_id` on the ports from `port` running an `ImagePublisher`, a
+    // `StoredObserver` and, for `bridged_from`, a bridge of the ImageStoredEvents of the engine
+    // whose ports start there.
+    struct BridgedEngine {
+        engine: EngineHandle,
+        images_tx: Sender<String>,
+        stored_rx: Receiver<(String, EventMeta)>,
+    }
+
+    impl BridgedEngine {
+        fn start(engine_id: u32, port: u16, bridged_from: Option<u16>) -> Self {
+            let (images_tx, images_rx) = channel();
+            let (stored_tx, stored_rx) = channel();
+            let mut plugins = PluginRegistry::new();
+            plugins
+                .register_plugin(Box::new(ImagePublisher { images_rx }))
+                .unwrap()
+                .register_plugin(Box::new(StoredObserver { stored_tx }))
+                .unwrap();
+            if let Some(remote_port) = bridged_from {
+                let endpoint = format!("tcp://127.0.0.1:{}", remote_port + 1);
+                let bridge = BridgePlugin::new(3, endpoint, &["ImageStoredEvent"])
+                    .unwrap()
+                    .reconnect_backoff(Duration::from_millis(10), Duration::from_millis(100));
+                plugins.register_plugin(Box::new(bridge)).unwrap();
+            }
+            let engine = EventEngineBuilder::new()
+                .incoming_port(port)
+                .outgoing_port(port + 1)
+                .sync_port(port + 2)
+                .engine_id(engine_id)
+                .plugins(plugins)
+                .start()
+                .unwrap();
+            BridgedEngine {
+                engine,
+                images_tx,
+                stored_rx,
+            }
+        }
+
+        // Publish probe images until `other` gets one over the bridge, for the subscriptions
+        // of the bridge to have reached this engine; the probes are then drained from both.
+        fn await_bridge(&self, other: &BridgedEngine) {
+            let deadline = Instant::now() + Duration::from_secs(10);
+            'probing: while Instant::now() < deadline {
+                self.images_tx.send("probe".to_string()).unwrap();
+                while let Ok((image_uuid, _)) =
+                    other.stored_rx.recv_timeout(Duration::from_millis(50))
+                {
+                    if image_uuid == "probe" {
+                        break 'probing;
+                    }
+                }
+            }
+            std::thread::sleep(Duration::from_millis(200));
+            for engine in [self, other] {
+                while engine.stored_rx.try_recv().is_ok() {}
+            }
+        }
+
+        // The envelopes of the images received, by image uuid, once `count` were or after a
+        // while, and then a moment longer for any that would come twice.
+        fn stored(&self, count: usize) -> HashMap<String, Vec<EventMeta>> {
+            let mut stored: HashMap<String, Vec<EventMeta>> = HashMap::new();
+            let deadline = Instant::now() + Duration::from_secs(10);
+            let mut received = 0;
+            while received < count {
+                let timeout = deadline.saturating_duration_since(Instant::now());
+                let Ok((image_uuid, meta)) = self.stored_rx.recv_timeout(timeout) else {
+                    break;
+                };
+                stored.entry(image_uuid).or_default().push(meta);
+                received += 1;
+            }
+            while let Ok((image_uuid, meta)) =
+                self.stored_rx.recv_timeout(Duration::from_millis(300))
+            {
+                stored.entry(image_uuid).or_default().push(meta);
+            }
+            stored
+        }
+
+        fn shutdown(self) {
+            drop(self.images_tx);
+            self.engine.shutdown().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_events_cross_a_two_way_bridge_once() {
+        let first = BridgedEngine::start(1, 26761, Some(26771));
+        let second = BridgedEngine::start(2, 26771, Some(26761));
+        first.await_bridge(&second);
+        second.await_bridge(&first);
+
+        for i in 0..5 {
+            first.images_tx.send(format!("first-{}", i)).unwrap();
+            second.images_tx.send(format!("second-{}", i)).unwrap();
+        }
+        for (engine, engine_id) in [(&first, 1), (&second, 2)] {
+            let stored = engine.stored(10);
+            assert_eq!(stored.len(), 10, "images on engine {}", engine_id);
+            for (image_uuid, metas) in stored {
+                // once, whether published on the engine or on the other one
+                assert_eq!(metas.len(), 1, "{} on engine {}", image_uuid, engine_id);
+                let meta = metas[0];
+                let origin = if image_uuid.starts_with("first") {
+                    1
+                } else {
+                    2
+                };
+                assert_eq!(meta.origin_engine_id, origin);
+                assert_eq!(meta.via_bridge, origin != engine_id);
+                assert_eq!(meta.source_plugin_id, if meta.via_bridge { 3 } else { 1 });
+            }
+        }
+        first.shutdown();
+        second.shutdown();
+    }
+
+    #[test]
+    fn test_bridge_reconnects_to_a_restarted_engine() {
+        let central = BridgedEngine::start(1, 26781, Some(26791));
+        let camera = BridgedEngine::start(2, 26791, None);
+        camera.await_bridge(&central);
+        camera.shutdown();
+
+        let camera = BridgedEngine::start(2, 26791, None);
+        camera.await_bridge(&central);
+        for i in 0..5 {
+            camera.images_tx.send(format!("restarted-{}", i)).unwrap();
+        }
+        let stored = central.stored(5);
+        assert_eq!(stored.len(), 5);
+        assert!(stored
+            .values()
+            .all(|metas| metas.len() == 1 && metas[0].via_bridge));
+        camera.shutdown();
+        central.shutdown();
+    }
+
+    #[test]
+    fn test_bridged_event_types_are_checked() {
+        assert!(matches!(
+            BridgePlugin::new(3, "tcp://127.0.0.1:5560", &["ImageStoredEvnt"]),
+            Err(EngineError::UnknownEventType { event_type }) if event_type == "ImageStoredEvnt"
+        ));
+        let bridge = BridgePlugin::new(
+            3,
+            "tcp://127.0.0.1:5560",
+            &[
+                "ImageStoredEvent",
+                "PluginTerminateEvent",
+                "ImageStoredEvent",
+            ],
+        )
+        .unwrap();
+        assert_eq!(bridge.event_types, ["ImageStoredEvent"]);
+    }
+}
@@ -0,0 +1,272 @@
+//! A callback seeing a copy of every event the engine forwards, for audit logs or metrics of
+//! one's own without a plugin subscribed to everything. An engine started with
+//! `EventEngineBuilder::capture(CaptureConfig::new(consumer))` calls `consumer` with the type
+//! and the serialized event (decompressed, if it was published compressed) of each event its
+//! proxy forwards, in the order forwarded, in a thread of its own.
+//!
+//! Like the event log, the consumer reads the copies the proxy sends to its capture socket
+//! through a bounded buffer, so that a slow consumer never holds the proxy up: events arriving
+//! while the buffer is full are dropped, and counted in `EngineHandle::capture_dropped`. A
+//! consumer that panics gets no more events, and the rest are counted as dropped. The events
+//! the engine sends its plugins directly, the EngineStartedEvent and the PluginTerminateEvent
+//! sent once the proxy has stopped, are not captured.
+//!
+
+use std::borrow::Cow;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+use log::{error, warn};
+use zmq::Socket;
+
+use crate::compression::decompressed;
+use crate::event_engine::{bind, connect, create_socket, subscribe_to_capture};
+use crate::event_engine::{EngineConfig, EngineError};
+use crate::event_log::{read_capture, Captured};
+
+const DEFAULT_BUFFER: usize = 10_000;
+
+// Called with the type and the serialized event of every event forwarded.
+type Consumer = Box<dyn Fn(&str, &[u8]) + Send>;
+
+/// The consumer of the events an engine forwards; see the module documentation.
+pub struct CaptureConfig {
+    consumer: Consumer,
+    // events held for the consumer before dropping the next
+    buffer: usize,
+}
+
+impl fmt::Debug for CaptureConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CaptureConfig")
+            .field("buffer", &self.buffer)
+            .finish_non_exhaustive()
+    }
+}
+
+impl CaptureConfig {
+    /// Call `consumer` with every event forwarded, buffering up to 10000 events for it.
+    pub fn new(consumer: impl Fn(&str, &[u8]) + Send + 'static) -> Self {
+        CaptureConfig {
+            consumer: Box::new(consumer),
+            buffer: DEFAULT_BUFFER,
+        }
+    }
+
+    /// Buffer up to `events` events for the consumer.
+    pub fn buffer(mut self, events: usize) -> Self {
+        self.buffer = events;
+        self
+    }
+}
+
+// Control socket and threads of the capture consumer, and how many events it dropped.
+pub(crate) struct Capture {
+    control: Mutex<Socket>,
+    reader_thread: JoinHandle<()>,
+    consumer_thread: JoinHandle<()>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl Capture {
+    // How many events were dropped because the consumer did not keep up, or had panicked.
+    pub(crate) fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    // Hand the events captured so far to the consumer and join the threads once it has seen
+    // them; called once the proxy has stopped.
+    pub(crate) fn stop(self) -> Result<(), EngineError> {
+        self.control
+            .into_inner()
+            .expect("capture lock poisoned")
+            .send("TERMINATE", 0)
+            .map_err(|source| EngineError::Shutdown { source })?;
+        self.reader_thread
+            .join()
+            .expect("Engine capture reader thread panicked");
+        // the panic of a consumer is the host's, and already reported
+        if self.consumer_thread.join().is_err() {
+            error!("Engine capture consumer panicked");
+        }
+        Ok(())
+    }
+}
+
+// Subscribe to the capture socket and start the threads handing its events to the consumer, if
+// the engine has one; called before the proxy starts, so that it sees every event forwarded.
+pub(crate) fn start_capture(
+    context: &zmq::Context,
+    config: &EngineConfig,
+    capture: Option<CaptureConfig>,
+) -> Result<Option<Capture>, EngineError> {
+    let Some(CaptureConfig { consumer, buffer }) = capture else {
+        return Ok(None);
+    };
+    let captured = subscribe_to_capture(context, config, "captured events")?;
+
+    let control_endpoint = format!("inproc://{}-capture-control", config.outgoing_inproc);
+    let capture_control = create_socket(context, config, zmq::PAIR, "capture control")?;
+    bind(&capture_control, &control_endpoint)?;
+    let control = create_socket(context, config, zmq::PAIR, "capture consumer")?;
+    connect(&control, &control_endpoint)?;
+
+    let (events, to_consume) = mpsc::sync_channel(buffer);
+    let dropped = Arc::new(AtomicU64::new(0));
+    let reader_dropped = Arc::clone(&dropped);
+    let reader_thread = thread::spawn(move || {
+        read_capture(
+            captured,
+            capture_control,
+            events,
+            reader_dropped,
+            "capture consumer",
+        )
+    });
+    let consumer_thread = thread::spawn(move || consume(consumer, to_consume));
+    Ok(Some(Capture {
+        control: Mutex::new(control),
+        reader_thread,
+        consumer_thread,
+        dropped,
+    }))
+}
+
+// Call the consumer with the events handed over, until the reader stops.
+fn consume(consumer: Consumer, events: Receiver<Captured>) {
+    for (event_type, mut meta, payload) in events {
+        match decompressed(&mut meta, Cow::Borrowed(&payload[..]), 0) {
+            Ok(event) => consumer(event_type, &event),
+            Err(e) => warn!("Engine capture skipping a {}: {}", event_type, e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::event_engine::{EngineHandle, EventEngineBuilder, Transport};
+    use crate::events::{Event, NewImage, PluginHeartbeat};
+    use crate::plugin_registry::PluginRegistry;
+    use std::collections::HashMap;
+    use std::sync::mpsc::SyncSender;
+    use std::time::{Duration, Instant};
+
+    fn heartbeat(seq: u64) -> Event {
+        Event::PluginHeartbeat(PluginHeartbeat { plugin_id: 1, seq })
+    }
+
+    // Wait up to 5 s for the proxy to have forwarded `count` events, of any type.
+    fn wait_for_forwarded(engine: &EngineHandle, count: u64) -> u64 {
+        let forwarded = || engine.stats().event_types.values().map(|s| s.count).sum();
+        let started = Instant::now();
+        while forwarded() < count && started.elapsed() < Duration::from_secs(5) {
+            thread::sleep(Duration::from_millis(1));
+        }
+        forwarded()
+    }
+
+    #[test]
+    fn test_every_event_is_captured_once() {
+        let captured = Arc::new(Mutex::new(Vec::<(String, Vec<u8>)>::new()));
+        let consumer_captured = Arc::clone(&captured);
+        let engine = EventEngineBuilder::new()
+            .incoming_inproc("messages-capture")
+            .outgoing_inproc("events-capture")
+            .transport(Transport::InprocOnly)
+            .plugins(PluginRegistry::new())
+            .capture(CaptureConfig::new(move |event_type, event| {
+                let mut captured = consumer_captured.lock().unwrap();
+                captured.push((event_type.to_string(), event.to_vec()));
+            }))
+            .start()
+            .unwrap();
+        let image = Event::NewImage(NewImage {
+            image_uuid: "captured".to_string(),
+            image_format: "png".to_string(),
+            image: vec![0; 1000],
+            location: None,
+        });
+        for seq in 0..200 {
+            engine.publish(&heartbeat(seq)).unwrap();
+            if seq % 10 == 0 {
+                engine.publish(&image).unwrap();
+            }
+        }
+        assert_eq!(wait_for_forwarded(&engine, 220), 220);
+        engine.shutdown().unwrap();
+
+        let captured = captured.lock().unwrap();
+        let mut seen = HashMap::new();
+        for (event_type, event) in captured.iter() {
+            match Event::decode(event).unwrap() {
+                Event::PluginHeartbeat(PluginHeartbeat { seq, .. }) => {
+                    assert_eq!(event_type, "PluginHeartbeatEvent");
+                    *seen.entry(seq).or_insert(0) += 1;
+                }
+                // published by the engine on shutdown, and forwarded last
+                Event::EngineStopping(_) => assert_eq!(event_type, "EngineStoppingEvent"),
+                captured => {
+                    assert_eq!(captured, image);
+                    assert_eq!(event_type, "NewImageEvent");
+                }
+            }
+        }
+        assert_eq!(captured.len(), 221);
+        assert_eq!(captured[220].0, "EngineStoppingEvent");
+        assert_eq!(seen.len(), 200);
+        assert!(seen.values().all(|&times| times == 1));
+    }
+
+    #[test]
+    fn test_a_slow_consumer_does_not_hold_up_the_proxy() {
+        // the consumer is stuck on its first event until released
+        let (release, released) = mpsc::channel::<()>();
+        let released = Mutex::new(released);
+        let consumed = Arc::new(AtomicU64::new(0));
+        let consumer_consumed = Arc::clone(&consumed);
+        let (subscriber_ready, ready): (SyncSender<()>, _) = mpsc::sync_channel(1);
+        let engine = EventEngineBuilder::new()
+            .incoming_inproc("messages-capture-slow")
+            .outgoing_inproc("events-capture-slow")
+            .transport(Transport::InprocOnly)
+            .plugins(PluginRegistry::new())
+            .capture(
+                CaptureConfig::new(move |_, _| {
+                    if consumer_consumed.fetch_add(1, Ordering::SeqCst) == 0 {
+                        let _ = subscriber_ready.try_send(());
+                        let _ = released.lock().unwrap().recv();
+                    }
+                })
+                .buffer(2),
+            )
+            .start()
+            .unwrap();
+        engine.publish(&heartbeat(0)).unwrap();
+        ready.recv_timeout(Duration::from_secs(5)).unwrap();
+        for seq in 1..100 {
+            engine.publish(&heartbeat(seq)).unwrap();
+        }
+        // the proxy forwards every one all the same
+        assert_eq!(wait_for_forwarded(&engine, 100), 100);
+        // the reader drops the events that do not fit soon after
+        let published = Instant::now();
+        while engine.capture_dropped() < 97 && published.elapsed() < Duration::from_secs(5) {
+            thread::sleep(Duration::from_millis(1));
+        }
+        let dropped = engine.capture_dropped();
+        assert_eq!(dropped, 97);
+        release.send(()).unwrap();
+        // the first event and the two buffered
+        while consumed.load(Ordering::SeqCst) < 3 && published.elapsed() < Duration::from_secs(5) {
+            thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(consumed.load(Ordering::SeqCst) + dropped, 100);
+        engine.shutdown().unwrap();
+        // and the EngineStoppingEvent
+        assert_eq!(consumed.load(Ordering::SeqCst), 4);
+    }
+}
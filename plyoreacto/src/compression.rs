@@ -0,0 +1,269 @@
+//! zstd compression of event payloads, for the multi-megabyte images sent over TCP to external
+//! plugins. A publisher with a `Compression` (see `EventEngineBuilder::compression` and
+//! `PluginContext::set_compression`) compresses the payloads bigger than its threshold, when
+//! that makes them smaller, and says so in their envelope; `PluginContext::next_event`
+//! decompresses them again, so plugin code never sees compressed bytes. Each payload is flagged
+//! on its own, so publishers that compress and publishers that do not can share an engine, and
+//! the Python plugins (`pyobserver/msgevents.py`) decompress them too.
+//!
+//! ```no_run
+//! use plyoreacto::compression::Compression;
+//! use plyoreacto::event_engine::EventEngineBuilder;
+//!
+//! EventEngineBuilder::new()
+//!     .compression(Compression::new(64 * 1024))
+//!     .run()
+//!     .expect("Error from engine");
+//! ```
+//!
+//! The engine forwards the payloads as they were published: its middlewares, event log and
+//! maximum payload size see them compressed. Events without an envelope, such as those of the
+//! engine itself, are never compressed.
+//!
+
+use std::io::Read;
+use std::ops::Deref;
+
+use crate::events::{parse_event_envelope, Event, EventError, EventMeta};
+
+// zstd's own default, a good trade of speed for size.
+const DEFAULT_LEVEL: i32 = 3;
+
+/// When and how hard a publisher compresses the payloads of its events.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Compression {
+    /// The payloads bigger than this many bytes are compressed.
+    pub threshold: usize,
+    /// The zstd compression level, from 1 (fastest) to 22 (smallest).
+    pub level: i32,
+}
+
+impl Compression {
+    /// Compress the payloads bigger than `threshold` bytes at zstd's default level.
+    pub fn new(threshold: usize) -> Self {
+        Compression {
+            threshold,
+            level: DEFAULT_LEVEL,
+        }
+    }
+
+    /// The zstd compression level, from 1 (fastest) to 22 (smallest).
+    pub fn level(mut self, level: i32) -> Self {
+        self.level = level;
+        self
+    }
+
+    // The compressed `payload`, or None if it is not over the threshold or would not get any
+    // smaller (e.g., a JPEG image). The frame records the size of the payload, which
+    // `decompress` checks before decompressing it.
+    pub(crate) fn compress(&self, payload: &[u8]) -> Option<Vec<u8>> {
+        if payload.len() <= self.threshold {
+            return None;
+        }
+        zstd::bulk::compress(payload, self.level)
+            .ok()
+            .filter(|compressed| compressed.len() < payload.len())
+    }
+}
+
+// The serialized event of a received message: `payload` itself, or its decompressed bytes if
+// the envelope `meta` says it is compressed, in which case the flag is cleared. A payload that
+// would decompress to more than `max_bytes` is refused, unless `max_bytes` is zero.
+pub(crate) fn decompressed<P>(
+    meta: &mut Option<EventMeta>,
+    payload: P,
+    max_bytes: usize,
+) -> Result<P, EventError>
+where
+    P: Deref<Target = [u8]> + From<Vec<u8>>,
+{
+    match meta {
+        Some(meta) if meta.compressed => {
+            let decompressed = decompress(&payload, max_bytes)?;
+            meta.compressed = false;
+            Ok(decompressed.into())
+        }
+        _ => Ok(payload),
+    }
+}
+
+// Decode the event of a message received with `recv_multipart`, decompressing its payload if
+// need be (see `decompressed`); None if the message is not an event.
+pub(crate) fn decode_event_frames(
+    frames: Vec<Vec<u8>>,
+    max_bytes: usize,
+) -> Option<Result<Event, EventError>> {
+    let (event_type, mut meta, payload) = parse_event_envelope(frames)?;
+    let payload = decompressed(&mut meta, payload, max_bytes);
+    Some(payload.and_then(|payload| Event::decode_with_meta(event_type, meta.as_ref(), &payload)))
+}
+
+// Decompress `compressed`, refusing to produce more than `max_bytes` (if not zero), whatever
+// size its frame claims.
+fn decompress(compressed: &[u8], max_bytes: usize) -> Result<Vec<u8>, EventError> {
+    let claimed = zstd::zstd_safe::get_frame_content_size(compressed)
+        .ok()
+        .flatten()
+        .and_then(|size| usize::try_from(size).ok());
+    let too_large = |size| EventError::TooLarge {
+        size,
+        limit: max_bytes,
+    };
+    if let Some(size) = claimed.filter(|size| max_bytes > 0 && *size > max_bytes) {
+        return Err(too_large(size));
+    }
+    let decoder =
+        zstd::stream::read::Decoder::with_buffer(compressed).map_err(EventError::Decompress)?;
+    let limit = if max_bytes > 0 {
+        max_bytes as u64 + 1
+    } else {
+        u64::MAX
+    };
+    let mut payload = Vec::with_capacity(claimed.unwrap_or_default());
+    decoder
+        .take(limit)
+        .read_to_end(&mut payload)
+        .map_err(EventError::Decompress)?;
+    if max_bytes > 0 && payload.len() > max_bytes {
+        return Err(too_large(payload.len()));
+    }
+    Ok(payload)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::events::{get_event_type_bytes_filter, EventPayload, NewImage};
+    use crate::plugin::{PluginContext, PluginError};
+    use flatbuffers::FlatBufferBuilder;
+    use rand::Rng;
+    use std::thread;
+    use std::time::Duration;
+
+    // A context receiving the NewImageEvents published on `endpoints`, and a raw subscriber to
+    // them, which sees the payloads as they were sent.
+    fn receivers(context: &zmq::Context, endpoints: &[&str]) -> (PluginContext, zmq::Socket) {
+        let sub_socket = context.socket(zmq::SUB).unwrap();
+        let raw = context.socket(zmq::SUB).unwrap();
+        let filter = get_event_type_bytes_filter("NewImageEvent").unwrap();
+        for socket in [&sub_socket, &raw] {
+            socket.set_subscribe(&filter).unwrap();
+            socket.set_rcvtimeo(5000).unwrap();
+            for endpoint in endpoints {
+                socket.connect(endpoint).unwrap();
+            }
+        }
+        // give the subscriptions time to reach the pub sockets
+        thread::sleep(Duration::from_millis(100));
+        let ctx = PluginContext::new(1, context.socket(zmq::PUB).unwrap(), sub_socket);
+        (ctx, raw)
+    }
+
+    // A context of the plugin `plugin_id` publishing on `endpoint`, compressing as `compression`
+    // says.
+    fn publisher(
+        context: &zmq::Context,
+        endpoint: &str,
+        plugin_id: i32,
+        compression: Option<Compression>,
+    ) -> PluginContext {
+        let pub_socket = context.socket(zmq::PUB).unwrap();
+        pub_socket.bind(endpoint).unwrap();
+        let mut ctx = PluginContext::new(plugin_id, pub_socket, context.socket(zmq::SUB).unwrap());
+        ctx.set_compression(compression);
+        ctx
+    }
+
+    // An image of `size` bytes that compresses well, like the pixels of a screenshot.
+    fn image(size: usize) -> NewImage {
+        NewImage {
+            image_uuid: format!("image-{}", size),
+            image_format: "raw".to_string(),
+            image: (0..size).map(|i| (i / 64 % 8) as u8).collect(),
+            location: None,
+        }
+    }
+
+    #[test]
+    fn test_only_payloads_over_the_threshold_that_shrink_are_compressed() {
+        let compression = Compression::new(1024);
+        let mut bldr = FlatBufferBuilder::new();
+        assert_eq!(
+            compression.compress(image(512).build(&mut bldr).unwrap()),
+            None
+        );
+        let payload = image(64 * 1024).build(&mut bldr).unwrap().to_vec();
+        let compressed = compression.compress(&payload).unwrap();
+        assert!(compressed.len() < payload.len() / 10);
+        assert_eq!(decompress(&compressed, 0).unwrap(), payload);
+        assert!(matches!(
+            decompress(&compressed, 1024),
+            Err(EventError::TooLarge { size, limit: 1024 }) if size == payload.len()
+        ));
+        assert!(matches!(
+            decompress(&compressed[..compressed.len() / 2], 0),
+            Err(EventError::Decompress(_))
+        ));
+        let mut noise = vec![0; 64 * 1024];
+        rand::thread_rng().fill(&mut noise[..]);
+        assert_eq!(compression.level(19).compress(&noise), None);
+    }
+
+    #[test]
+    fn test_compressed_and_plain_publishers_interoperate() {
+        let context = zmq::Context::new();
+        let endpoints = [
+            "inproc://test-compression-compressing",
+            "inproc://test-compression-plain",
+        ];
+        let compression = Some(Compression::new(1024));
+        let mut compressing = publisher(&context, endpoints[0], 2, compression);
+        let mut plain = publisher(&context, endpoints[1], 3, None);
+        let (mut receiver, raw) = receivers(&context, &endpoints);
+
+        let big = image(256 * 1024);
+        let small = image(100);
+        let published = [
+            (compressing.publish(&big).unwrap(), &big),
+            (compressing.publish(&small).unwrap(), &small),
+            (plain.publish(&big).unwrap(), &big),
+        ];
+        assert_eq!(
+            published.map(|(meta, _)| meta.compressed),
+            [true, false, false]
+        );
+        // the two publishers are fair-queued, so match what arrives by event id
+        let published_as = |event_id| {
+            published
+                .iter()
+                .find(|(meta, _)| meta.event_id == event_id)
+                .unwrap()
+        };
+        for _ in 0..published.len() {
+            let msg = receiver.next_event().unwrap();
+            let received = msg.meta.unwrap();
+            assert!(!received.compressed);
+            let (_, expected) = published_as(received.event_id);
+            assert_eq!(
+                msg.decode().unwrap(),
+                crate::events::Event::NewImage((*expected).clone())
+            );
+
+            let frames = raw.recv_multipart(0).unwrap();
+            let sent = EventMeta::from_bytes(&frames[1]).unwrap();
+            let (meta, expected) = published_as(sent.event_id);
+            assert_eq!(sent.compressed, meta.compressed);
+            if meta.compressed {
+                assert!(frames[2].len() < expected.image.len() / 10);
+            }
+        }
+
+        // a payload that decompresses past the receiver's limit is refused
+        receiver.set_max_payload_size(1024 * 1024);
+        compressing.publish(&image(2 * 1024 * 1024)).unwrap();
+        assert!(matches!(
+            receiver.next_event(),
+            Err(PluginError::Event(EventError::TooLarge { .. }))
+        ));
+    }
+}
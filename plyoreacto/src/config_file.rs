@@ -0,0 +1,563 @@
+//! The configuration of an engine and its plugins read from a TOML file, for operators to
+//! configure an engine without changing the code it is built with:
+//!
+//! ```toml
+//! [engine]
+//! incoming_port = 6559
+//! outgoing_port = 6560
+//! transport = "tcp"
+//! incoming_hwm = { send = 5000, receive = 5000 }
+//! sync_timeout_ms = 10000
+//!
+//! [[plugin]]
+//! name = "image-score"
+//! subscriptions = ["NewImageEvent"]
+//! settings = { threshold = 0.5 }
+//!
+//! [[plugin]]
+//! name = "image-store"
+//! enabled = false
+//! ```
+//!
+//! `EngineConfig::from_toml_file(path)` reads the settings of the `[engine]` table over the
+//! defaults, those it leaves out keeping theirs, and hands it to
+//! `EventEngineBuilder::from_config`, whose settings given afterwards override those of the
+//! file. Durations are given in milliseconds, by keys ending in `_ms`, and the transport as in
+//! `Transport`. Reading fails for a file that is not TOML, names a key the configuration does
+//! not have or gives a value of the wrong type.
+//!
+//! Each `[[plugin]]` entry applies to the registered plugins run by the engine of its `name`
+//! (see `Plugin::name`) when the engine starts, which fails with
+//! `EngineError::UnknownPluginName` for an entry naming none of them. An entry leaves its
+//! plugins out if it is not `enabled`, as a profile would, subscribes them to its
+//! `subscriptions` instead of their own if it has any, and gives them its `settings` (see the
+//! `settings` module), under those they were registered with, which win.
+//!
+
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::event_engine::{EngineConfig, Hwm, Transport};
+use crate::settings::PluginSettings;
+
+/// A `[[plugin]]` entry of a configuration file, see `EngineConfig::plugins`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PluginEntry {
+    /// The name of the plugins the entry applies to.
+    pub name: String,
+    /// Whether the engine starts the plugins; true if left out.
+    #[serde(default = "enabled")]
+    pub enabled: bool,
+    /// The event types the plugins subscribe to instead of their own, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subscriptions: Option<Vec<String>>,
+    /// The settings of the plugins that the code did not give them.
+    #[serde(default, skip_serializing_if = "PluginSettings::is_empty")]
+    pub settings: PluginSettings,
+}
+
+fn enabled() -> bool {
+    true
+}
+
+impl PluginEntry {
+    /// An entry for the plugins named `name` that leaves them as they were registered.
+    pub fn new(name: &str) -> Self {
+        PluginEntry {
+            name: name.to_string(),
+            enabled: true,
+            subscriptions: None,
+            settings: PluginSettings::new(),
+        }
+    }
+}
+
+/// Why a configuration file could not be read or written.
+#[derive(Debug)]
+pub enum ConfigFileError {
+    /// The file could not be read.
+    Read { path: PathBuf, source: io::Error },
+    /// The file, or the string for `EngineConfig::from_toml`, is not a configuration, e.g.,
+    /// because it is not TOML or has a key the configuration does not.
+    Parse {
+        path: Option<PathBuf>,
+        source: toml::de::Error,
+    },
+    /// More than one `[[plugin]]` entry has this name.
+    DuplicatePlugin { name: String },
+    /// The value of this key, e.g., `plugin.settings.retries`, is null, which TOML has no value
+    /// for.
+    Null { key: String },
+    /// The configuration could not be written as TOML.
+    Write { source: toml::ser::Error },
+}
+
+impl fmt::Display for ConfigFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigFileError::Read { path, source } => {
+                write!(
+                    f,
+                    "could not read configuration file {}: {}",
+                    path.display(),
+                    source
+                )
+            }
+            ConfigFileError::Parse {
+                path: Some(path),
+                source,
+            } => write!(
+                f,
+                "invalid configuration file {}: {}",
+                path.display(),
+                source
+            ),
+            ConfigFileError::Parse { path: None, source } => {
+                write!(f, "invalid configuration: {}", source)
+            }
+            ConfigFileError::DuplicatePlugin { name } => {
+                write!(f, "more than one [[plugin]] entry is named {:?}", name)
+            }
+            ConfigFileError::Null { key } => {
+                write!(f, "{} is null, which TOML cannot represent", key)
+            }
+            ConfigFileError::Write { source } => {
+                write!(f, "could not write the configuration as TOML: {}", source)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigFileError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ConfigFileError::Read { source, .. } => Some(source),
+            ConfigFileError::Parse { source, .. } => Some(source),
+            ConfigFileError::Write { source } => Some(source),
+            ConfigFileError::DuplicatePlugin { .. } | ConfigFileError::Null { .. } => None,
+        }
+    }
+}
+
+// The contents of a configuration file.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    #[serde(default)]
+    engine: EngineTable,
+    #[serde(default, rename = "plugin")]
+    plugins: Vec<PluginEntry>,
+}
+
+// The settings of the `[engine]` table, each of them of the field of `EngineConfig` of its name;
+// those left out keep the value they had.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct EngineTable {
+    incoming_port: Option<u16>,
+    outgoing_port: Option<u16>,
+    incoming_inproc: Option<String>,
+    outgoing_inproc: Option<String>,
+    sync_port: Option<u16>,
+    admin_port: Option<u16>,
+    bind_address: Option<String>,
+    transport: Option<Transport>,
+    incoming_hwm: Option<Hwm>,
+    outgoing_hwm: Option<Hwm>,
+    io_threads: Option<i32>,
+    sync_timeout_ms: Option<u64>,
+    registration_window_ms: Option<u64>,
+    external_heartbeat_timeout_ms: Option<u64>,
+    heartbeat_interval_ms: Option<u64>,
+    heartbeat_missed_beats: Option<u64>,
+    socket_linger_ms: Option<u64>,
+    plugin_recv_timeout_ms: Option<u64>,
+    stopping_grace_period_ms: Option<u64>,
+    skip_missing_external_plugins: Option<bool>,
+    allow_unknown_subscriptions: Option<bool>,
+    max_payload_size: Option<usize>,
+    engine_id: Option<u32>,
+    profile: Option<String>,
+}
+
+impl EngineTable {
+    // Every setting of `config` a file can give.
+    fn of(config: &EngineConfig) -> Self {
+        EngineTable {
+            incoming_port: Some(config.incoming_port),
+            outgoing_port: Some(config.outgoing_port),
+            incoming_inproc: Some(config.incoming_inproc.clone()),
+            outgoing_inproc: Some(config.outgoing_inproc.clone()),
+            sync_port: Some(config.sync_port),
+            admin_port: config.admin_port,
+            bind_address: Some(config.bind_address.clone()),
+            transport: Some(config.transport.clone()),
+            incoming_hwm: Some(config.incoming_hwm),
+            outgoing_hwm: Some(config.outgoing_hwm),
+            io_threads: Some(config.io_threads),
+            sync_timeout_ms: millis(config.sync_timeout),
+            registration_window_ms: millis(config.registration_window),
+            external_heartbeat_timeout_ms: millis(config.external_heartbeat_timeout),
+            heartbeat_interval_ms: millis(config.heartbeat_interval),
+            heartbeat_missed_beats: Some(config.heartbeat_missed_beats),
+            socket_linger_ms: millis(config.socket_linger),
+            plugin_recv_timeout_ms: millis(config.plugin_recv_timeout),
+            stopping_grace_period_ms: millis(config.stopping_grace_period),
+            skip_missing_external_plugins: Some(config.skip_missing_external_plugins),
+            allow_unknown_subscriptions: Some(config.allow_unknown_subscriptions),
+            // no payload is bigger than a TOML integer anyway
+            max_payload_size: Some(config.max_payload_size.min(i64::MAX as usize)),
+            engine_id: Some(config.engine_id),
+            profile: config.profile.clone(),
+        }
+    }
+
+    // Set the settings of `config` the table gives.
+    fn apply(self, config: &mut EngineConfig) {
+        let set = |value: &mut Duration, millis: Option<u64>| {
+            if let Some(millis) = millis {
+                *value = Duration::from_millis(millis);
+            }
+        };
+        set(&mut config.sync_timeout, self.sync_timeout_ms);
+        set(&mut config.registration_window, self.registration_window_ms);
+        set(
+            &mut config.external_heartbeat_timeout,
+            self.external_heartbeat_timeout_ms,
+        );
+        set(&mut config.heartbeat_interval, self.heartbeat_interval_ms);
+        set(&mut config.socket_linger, self.socket_linger_ms);
+        set(&mut config.plugin_recv_timeout, self.plugin_recv_timeout_ms);
+        set(
+            &mut config.stopping_grace_period,
+            self.stopping_grace_period_ms,
+        );
+        if let Some(port) = self.incoming_port {
+            config.incoming_port = port;
+        }
+        if let Some(port) = self.outgoing_port {
+            config.outgoing_port = port;
+        }
+        if let Some(name) = self.incoming_inproc {
+            config.incoming_inproc = name;
+        }
+        if let Some(name) = self.outgoing_inproc {
+            config.outgoing_inproc = name;
+        }
+        if let Some(port) = self.sync_port {
+            config.sync_port = port;
+        }
+        if let Some(port) = self.admin_port {
+            config.admin_port = Some(port);
+        }
+        if let Some(address) = self.bind_address {
+            config.bind_address = address;
+        }
+        if let Some(transport) = self.transport {
+            config.transport = transport;
+        }
+        if let Some(hwm) = self.incoming_hwm {
+            config.incoming_hwm = hwm;
+        }
+        if let Some(hwm) = self.outgoing_hwm {
+            config.outgoing_hwm = hwm;
+        }
+        if let Some(io_threads) = self.io_threads {
+            config.io_threads = io_threads;
+        }
+        if let Some(beats) = self.heartbeat_missed_beats {
+            config.heartbeat_missed_beats = beats;
+        }
+        if let Some(skip) = self.skip_missing_external_plugins {
+            config.skip_missing_external_plugins = skip;
+        }
+        if let Some(allow) = self.allow_unknown_subscriptions {
+            config.allow_unknown_subscriptions = allow;
+        }
+        if let Some(max_bytes) = self.max_payload_size {
+            config.max_payload_size = max_bytes;
+        }
+        if let Some(engine_id) = self.engine_id {
+            config.engine_id = engine_id;
+        }
+        if let Some(profile) = self.profile {
+            config.profile = Some(profile);
+        }
+    }
+}
+
+// The milliseconds of `duration`, unless there are more than a TOML integer holds.
+fn millis(duration: Duration) -> Option<u64> {
+    let millis = i64::try_from(duration.as_millis()).ok()?;
+    u64::try_from(millis).ok()
+}
+
+impl EngineConfig {
+    /// The configuration of the TOML file `path`: the default one, with the settings of its
+    /// `[engine]` table and its `[[plugin]]` entries; see the `config_file` module.
+    pub fn from_toml_file(path: impl AsRef<Path>) -> Result<Self, ConfigFileError> {
+        let path = path.as_ref();
+        let toml = std::fs::read_to_string(path).map_err(|source| ConfigFileError::Read {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        EngineConfig::from_toml(&toml).map_err(|e| match e {
+            ConfigFileError::Parse { source, .. } => ConfigFileError::Parse {
+                path: Some(path.to_path_buf()),
+                source,
+            },
+            e => e,
+        })
+    }
+
+    /// The configuration of the contents `toml` of a configuration file.
+    pub fn from_toml(toml: &str) -> Result<Self, ConfigFileError> {
+        let file: ConfigFile =
+            toml::from_str(toml).map_err(|source| ConfigFileError::Parse { path: None, source })?;
+        for (i, entry) in file.plugins.iter().enumerate() {
+            if file.plugins[..i].iter().any(|seen| seen.name == entry.name) {
+                return Err(ConfigFileError::DuplicatePlugin {
+                    name: entry.name.clone(),
+                });
+            }
+        }
+        let mut config = EngineConfig::default();
+        file.engine.apply(&mut config);
+        config.plugins = file.plugins;
+        Ok(config)
+    }
+
+    /// The configuration as the contents of a configuration file, which `from_toml` reads back
+    /// as this configuration but for the settings a file cannot give (e.g., `curve` or
+    /// `event_log`), which it leaves to their default, as it does durations too long for a TOML
+    /// integer of milliseconds. Fails for a plugin setting that is null.
+    pub fn to_toml(&self) -> Result<String, ConfigFileError> {
+        for entry in &self.plugins {
+            let settings =
+                serde_json::to_value(&entry.settings).expect("plugin settings are JSON values");
+            if let Some(key) = null_key("plugin.settings", &settings) {
+                return Err(ConfigFileError::Null { key });
+            }
+        }
+        let file = ConfigFile {
+            engine: EngineTable::of(self),
+            plugins: self.plugins.clone(),
+        };
+        toml::to_string(&file).map_err(|source| ConfigFileError::Write { source })
+    }
+}
+
+// The key of the first null in `value`, that of the key `key`, if any.
+fn null_key(key: &str, value: &Value) -> Option<String> {
+    match value {
+        Value::Null => Some(key.to_string()),
+        Value::Array(values) => values.iter().find_map(|value| null_key(key, value)),
+        Value::Object(table) => table
+            .iter()
+            .find_map(|(name, value)| null_key(&format!("{}.{}", key, name), value)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::event_engine::EventEngineBuilder;
+    use serde_json::json;
+
+    fn write_file(name: &str, toml: &str) -> PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("plyoreacto-{}-{}.toml", name, uuid::Uuid::new_v4()));
+        std::fs::write(&path, toml).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_a_partial_file_keeps_the_other_defaults() {
+        let path = write_file(
+            "partial",
+            r#"
+            [engine]
+            incoming_port = 6559
+            transport = "tcp"
+            sync_timeout_ms = 2500
+            incoming_hwm = { send = 5000 }
+
+            [[plugin]]
+            name = "image-score"
+            settings = { threshold = 0.5 }
+            "#,
+        );
+        let config = EngineConfig::from_toml_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let defaults = EngineConfig::default();
+        assert_eq!(config.incoming_port, 6559);
+        assert_eq!(config.transport, Transport::Tcp);
+        assert_eq!(config.sync_timeout, Duration::from_millis(2500));
+        assert_eq!(
+            config.incoming_hwm,
+            Hwm {
+                send: Some(5000),
+                receive: None
+            }
+        );
+        assert_eq!(config.outgoing_port, defaults.outgoing_port);
+        assert_eq!(config.outgoing_hwm, defaults.outgoing_hwm);
+        assert_eq!(config.socket_linger, defaults.socket_linger);
+        assert_eq!(config.profile, None);
+        let entry = &config.plugins[0];
+        assert_eq!(entry.name, "image-score");
+        assert!(entry.enabled);
+        assert_eq!(entry.subscriptions, None);
+        assert_eq!(entry.settings.get_f64("threshold").unwrap(), 0.5);
+
+        // an empty file is the default configuration
+        assert_eq!(EngineConfig::from_toml("").unwrap(), defaults);
+    }
+
+    #[test]
+    fn test_malformed_files_are_rejected() {
+        let parse_error = |toml: &str| match EngineConfig::from_toml(toml) {
+            Err(ConfigFileError::Parse { path: None, source }) => source.to_string(),
+            Err(e) => panic!("expected a parse error, got {}", e),
+            Ok(config) => panic!("read {:?}", config),
+        };
+        // not TOML
+        assert!(parse_error("[engine\nincoming_port = 6559").contains("line 1"));
+        // a key the configuration does not have, e.g., misspelled
+        assert!(
+            parse_error("[engine]\nincoming_prot = 6559").contains("unknown field `incoming_prot`")
+        );
+        assert!(parse_error("[[plugin]]\nname = \"logger\"\nenable = false")
+            .contains("unknown field `enable`"));
+        assert!(parse_error("[engines]\n").contains("unknown field `engines`"));
+        // a value of the wrong type
+        assert!(parse_error("[engine]\nincoming_port = \"6559\"").contains("incoming_port"));
+        assert!(parse_error("[engine]\nincoming_port = 65536").contains("incoming_port"));
+        assert!(parse_error("[engine]\ntransport = \"udp\"").contains("udp"));
+        // an entry without a name
+        assert!(parse_error("[[plugin]]\nenabled = false").contains("missing field `name`"));
+
+        let duplicate = "[[plugin]]\nname = \"logger\"\n[[plugin]]\nname = \"logger\"";
+        assert_eq!(
+            EngineConfig::from_toml(duplicate).unwrap_err().to_string(),
+            "more than one [[plugin]] entry is named \"logger\""
+        );
+        let path = write_file("malformed", "[engine]\nincoming_port = -1");
+        match EngineConfig::from_toml_file(&path) {
+            Err(e @ ConfigFileError::Parse { path: Some(_), .. }) => {
+                assert!(e
+                    .to_string()
+                    .starts_with(&format!("invalid configuration file {}", path.display())))
+            }
+            other => panic!("expected a parse error, got {:?}", other),
+        }
+        std::fs::remove_file(&path).unwrap();
+        let missing = path.with_extension("missing");
+        assert!(matches!(
+            EngineConfig::from_toml_file(&missing),
+            Err(ConfigFileError::Read { .. })
+        ));
+    }
+
+    #[test]
+    fn test_the_builder_overrides_the_file() {
+        let config = EngineConfig::from_toml(
+            r#"
+            [engine]
+            incoming_port = 6559
+            outgoing_port = 6560
+            plugin_recv_timeout_ms = 50
+            "#,
+        )
+        .unwrap();
+        let config = EventEngineBuilder::from_config(config)
+            .outgoing_port(7560)
+            .sync_timeout(Duration::from_secs(3))
+            .build();
+        // given by the file only
+        assert_eq!(config.incoming_port, 6559);
+        assert_eq!(config.plugin_recv_timeout, Duration::from_millis(50));
+        // by the file and the builder
+        assert_eq!(config.outgoing_port, 7560);
+        // by the builder only
+        assert_eq!(config.sync_timeout, Duration::from_secs(3));
+        // by neither
+        assert_eq!(config.sync_port, EngineConfig::default().sync_port);
+    }
+
+    #[test]
+    fn test_a_configuration_round_trips_through_a_file() {
+        let mut config = EventEngineBuilder::new()
+            .incoming_port(6559)
+            .incoming_inproc("messages \"quoted\"")
+            .admin_port(6570)
+            .transport(Transport::Tcp)
+            .incoming_hwm(Hwm::new(5000))
+            .outgoing_hwm(Hwm {
+                send: None,
+                receive: Some(0),
+            })
+            .sync_timeout(Duration::from_millis(2500))
+            .heartbeat_interval(Duration::from_secs(1))
+            .with_profile("scoring")
+            .build();
+        let mut score = PluginEntry::new("image-score");
+        score.subscriptions = Some(vec!["NewImageEvent".to_string()]);
+        score.settings = PluginSettings::new()
+            .set("threshold", 0.5)
+            .set("labels", json!(["labrador", "poodle"]))
+            .set("limits", json!({"max images": 10, "ratio": 1.0}));
+        let mut store = PluginEntry::new("image-store");
+        store.enabled = false;
+        config.plugins = vec![score, store];
+
+        let path = write_file("round-trip", &config.to_toml().unwrap());
+        let read = EngineConfig::from_toml_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(read, config);
+        assert_eq!(
+            EngineConfig::from_toml(&EngineConfig::default().to_toml().unwrap()).unwrap(),
+            EngineConfig::default()
+        );
+
+        let mut entry = PluginEntry::new("logger");
+        entry.settings = PluginSettings::new().set("retries", Value::Null);
+        config.plugins = vec![entry];
+        assert_eq!(
+            config.to_toml().unwrap_err().to_string(),
+            "plugin.settings.retries is null, which TOML cannot represent"
+        );
+    }
+
+    #[test]
+    fn test_settings_beyond_toml_integers_are_written_readably() {
+        let mut config = EventEngineBuilder::new()
+            .sync_timeout(Duration::MAX)
+            .plugin_recv_timeout(Duration::from_millis(i64::MAX as u64 + 1))
+            .stopping_grace_period(Duration::from_millis(i64::MAX as u64))
+            .build();
+        config.max_payload_size = usize::MAX;
+
+        let toml = config.to_toml().unwrap();
+        assert!(!toml.contains("sync_timeout_ms"), "{}", toml);
+        assert!(!toml.contains("plugin_recv_timeout_ms"), "{}", toml);
+        let read = EngineConfig::from_toml(&toml).unwrap();
+        // the durations too long are left to their default
+        let default = EngineConfig::default();
+        assert_eq!(read.sync_timeout, default.sync_timeout);
+        assert_eq!(read.plugin_recv_timeout, default.plugin_recv_timeout);
+        assert_eq!(
+            read.stopping_grace_period,
+            Duration::from_millis(i64::MAX as u64)
+        );
+        // and no payload could be bigger than the biggest size a file can give
+        assert_eq!(read.max_payload_size, i64::MAX as usize);
+    }
+}
@@ -0,0 +1,351 @@
+//! CurveZMQ encryption and client authentication for the engine's TCP (and ipc) endpoints.
+//! An engine started with `EventEngineBuilder::curve(CurveServer { .. })` makes its incoming,
+//! outgoing and external sync sockets CURVE servers, so that everything sent over them is
+//! encrypted, and answers the ZAP requests of its zmq context on a thread of its own: a client is
+//! only let in if its public key is on the server's allow-list, and every other one is rejected
+//! with a warning naming its address. External plugins connect with
+//! `ExternalPluginClient::connect_with_curve` (or `register_with_curve`), giving their keypair
+//! and the engine's public key. The plugins the engine starts talk to it over inproc, which
+//! CURVE does not apply to.
+//!
+//! Keys are 32 bytes; `z85_key` and `z85` convert them from and to the 40 character Z85 text
+//! that `curve_keygen` and the other zmq tools print.
+//!
+//! CURVE needs a libzmq built with libsodium (see `curve_supported`); with another, generating
+//! a keypair or starting an engine or client configured for CURVE fails with
+//! `EngineError::CurveUnsupported`.
+//!
+
+use std::fmt;
+use std::thread::{self, JoinHandle};
+
+use log::{debug, error, info, warn};
+use zmq::Socket;
+
+use crate::event_engine::{bind, connect, create_socket, EngineConfig, EngineError};
+use crate::events::retry_on_eintr;
+
+// Every zmq context has at most one ZAP handler, bound here.
+const ZAP_ENDPOINT: &str = "inproc://zeromq.zap.01";
+const ZAP_VERSION: &[u8] = b"1.0";
+
+/// A CURVE public key or secret key.
+pub type CurveKey = [u8; 32];
+
+/// A CURVE keypair. Its `Debug` output leaves out the secret key.
+#[derive(Clone, PartialEq, Eq)]
+pub struct CurveKeyPair {
+    pub public_key: CurveKey,
+    pub secret_key: CurveKey,
+}
+
+impl CurveKeyPair {
+    /// A new random keypair, or `EngineError::CurveUnsupported` if libzmq has no CURVE.
+    pub fn generate() -> Result<Self, EngineError> {
+        // the only error libzmq generates a keypair with is ENOTSUP
+        let keypair = zmq::CurveKeyPair::new().map_err(|_| EngineError::CurveUnsupported)?;
+        Ok(CurveKeyPair {
+            public_key: keypair.public_key,
+            secret_key: keypair.secret_key,
+        })
+    }
+}
+
+impl fmt::Debug for CurveKeyPair {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CurveKeyPair")
+            .field("public_key", &z85(&self.public_key))
+            .finish_non_exhaustive()
+    }
+}
+
+/// The CURVE settings of an engine: its keypair, and the public keys of the clients it lets in.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CurveServer {
+    pub keys: CurveKeyPair,
+    pub allowed_clients: Vec<CurveKey>,
+}
+
+/// The CURVE settings of an external plugin: its keypair, and the public key of the engine.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CurveClient {
+    pub keys: CurveKeyPair,
+    pub server_public_key: CurveKey,
+}
+
+/// Whether the libzmq linked in supports CURVE, which takes it being built with libsodium.
+pub fn curve_supported() -> bool {
+    zmq::has("curve").unwrap_or(false)
+}
+
+// `EngineError::CurveUnsupported` unless libzmq supports CURVE, checked before configuring a
+// socket for it, which would fail with a bare "Operation not supported".
+fn check_curve_supported() -> Result<(), EngineError> {
+    match curve_supported() {
+        true => Ok(()),
+        false => Err(EngineError::CurveUnsupported),
+    }
+}
+
+/// The key encoded in the Z85 text `text`, or None if it is not a 40 character Z85 key.
+pub fn z85_key(text: &str) -> Option<CurveKey> {
+    if text.len() != 40 {
+        return None;
+    }
+    zmq::z85_decode(text).ok()?.try_into().ok()
+}
+
+/// The Z85 text of `key`.
+pub fn z85(key: &CurveKey) -> String {
+    zmq::z85_encode(key).expect("a key is a multiple of 4 bytes long")
+}
+
+// Make `socket`, named `name` in errors, a CURVE server; it applies to the connections made
+// afterwards, so this is called before the socket is bound.
+pub(crate) fn make_server(
+    socket: &Socket,
+    name: &str,
+    server: &CurveServer,
+) -> Result<(), EngineError> {
+    check_curve_supported()?;
+    let socket_error = |source| EngineError::Socket {
+        socket: name.to_string(),
+        source,
+    };
+    socket.set_curve_server(true).map_err(socket_error)?;
+    socket
+        .set_curve_secretkey(&server.keys.secret_key)
+        .map_err(socket_error)
+}
+
+// Make `socket`, named `name` in errors, a CURVE client of the server `client` names; called
+// before the socket is connected.
+pub(crate) fn make_client(
+    socket: &Socket,
+    name: &str,
+    client: &CurveClient,
+) -> Result<(), EngineError> {
+    check_curve_supported()?;
+    let socket_error = |source| EngineError::Socket {
+        socket: name.to_string(),
+        source,
+    };
+    socket
+        .set_curve_serverkey(&client.server_public_key)
+        .map_err(socket_error)?;
+    socket
+        .set_curve_publickey(&client.keys.public_key)
+        .map_err(socket_error)?;
+    socket
+        .set_curve_secretkey(&client.keys.secret_key)
+        .map_err(socket_error)
+}
+
+// Control socket and thread answering the ZAP requests.
+pub(crate) type Zap = (Socket, JoinHandle<()>);
+
+// Start answering the ZAP requests of `context` if the engine is configured for CURVE; called
+// before any of its sockets is bound, so that no client gets in unchecked.
+pub(crate) fn start_zap_handler(
+    context: &zmq::Context,
+    config: &EngineConfig,
+) -> Result<Option<Zap>, EngineError> {
+    let server = match &config.curve {
+        Some(server) => server.clone(),
+        None => return Ok(None),
+    };
+    check_curve_supported()?;
+    let socket_name = "ZAP handler";
+    let socket = create_socket(context, config, zmq::REP, socket_name)?;
+    socket.set_linger(0).map_err(|source| EngineError::Socket {
+        socket: socket_name.to_string(),
+        source,
+    })?;
+    bind(&socket, ZAP_ENDPOINT)?;
+
+    let control_endpoint = format!("inproc://{}-zap-control", config.outgoing_inproc);
+    let zap_control = create_socket(context, config, zmq::PAIR, "ZAP control")?;
+    bind(&zap_control, &control_endpoint)?;
+    let control = create_socket(context, config, zmq::PAIR, "ZAP")?;
+    connect(&control, &control_endpoint)?;
+    info!(
+        "Engine accepting {} CURVE clients",
+        server.allowed_clients.len()
+    );
+    let zap_thread = thread::spawn(move || answer_zap_requests(socket, zap_control, server));
+    Ok(Some((control, zap_thread)))
+}
+
+// Answer ZAP requests until TERMINATE is received on `control` or the context is terminated.
+fn answer_zap_requests(socket: Socket, control: Socket, server: CurveServer) {
+    loop {
+        let mut items = [
+            socket.as_poll_item(zmq::POLLIN),
+            control.as_poll_item(zmq::POLLIN),
+        ];
+        if let Err(e) = retry_on_eintr(|| zmq::poll(&mut items, -1)) {
+            zap_thread_exits(e);
+            return;
+        }
+        if items[1].is_readable() {
+            return;
+        }
+        let request = match retry_on_eintr(|| socket.recv_multipart(0)) {
+            Ok(request) => request,
+            Err(e) => {
+                zap_thread_exits(e);
+                return;
+            }
+        };
+        let reply = answer_zap_request(&request, &server);
+        if let Err(e) = socket.send_multipart(reply, 0) {
+            error!("Engine could not answer ZAP request: {}", e);
+        }
+    }
+}
+
+// Log why the ZAP thread stops answering, which is only expected once the context is terminated:
+// CURVE clients cannot connect without it.
+fn zap_thread_exits(e: zmq::Error) {
+    if e == zmq::Error::ETERM {
+        debug!("Engine stopped answering ZAP requests: {}", e);
+    } else {
+        error!(
+            "Engine stopped answering ZAP requests, CURVE clients cannot connect: {}",
+            e
+        );
+    }
+}
+
+// The reply to a ZAP request: "version, request id, domain, address, identity, mechanism,
+// credentials...", where the credentials of a CURVE client are its public key.
+fn answer_zap_request(request: &[Vec<u8>], server: &CurveServer) -> Vec<Vec<u8>> {
+    let reply = |request_id: &[u8], status_code: &str, status_text: &str| {
+        vec![
+            ZAP_VERSION.to_vec(),
+            request_id.to_vec(),
+            status_code.as_bytes().to_vec(),
+            status_text.as_bytes().to_vec(),
+            Vec::new(),
+            Vec::new(),
+        ]
+    };
+    let (request_id, address, mechanism, credentials) = match request {
+        [version, request_id, _domain, address, _identity, mechanism, credentials @ ..]
+            if version == ZAP_VERSION =>
+        {
+            (request_id, address, mechanism, credentials)
+        }
+        _ => {
+            warn!("Engine ignoring malformed ZAP request");
+            return reply(b"", "500", "malformed request");
+        }
+    };
+    let address = String::from_utf8_lossy(address);
+    match (mechanism.as_slice(), credentials) {
+        // the sockets that are not CURVE servers, e.g., the admin socket
+        (b"NULL", _) => reply(request_id, "200", "OK"),
+        (b"CURVE", [public_key])
+            if server
+                .allowed_clients
+                .iter()
+                .any(|allowed| allowed[..] == public_key[..]) =>
+        {
+            debug!(peer_address = address.as_ref(); "Engine let in CURVE client from {}", address);
+            reply(request_id, "200", "OK")
+        }
+        (b"CURVE", [public_key]) => {
+            let public_key = public_key
+                .as_slice()
+                .try_into()
+                .map(|public_key| z85(&public_key))
+                .unwrap_or_default();
+            warn!(
+                peer_address = address.as_ref(), public_key = public_key.as_str();
+                "Engine rejected CURVE client {} from {}: its key is not allowed",
+                public_key, address
+            );
+            reply(request_id, "400", "client key not allowed")
+        }
+        (mechanism, _) => {
+            let mechanism = String::from_utf8_lossy(mechanism);
+            warn!(
+                peer_address = address.as_ref();
+                "Engine rejected {} client from {}", mechanism, address
+            );
+            reply(request_id, "400", "mechanism not allowed")
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_z85_keys_round_trip() {
+        if !curve_supported() {
+            return;
+        }
+        let keys = CurveKeyPair::generate().unwrap();
+        let text = z85(&keys.public_key);
+        assert_eq!(text.len(), 40);
+        assert_eq!(z85_key(&text), Some(keys.public_key));
+        assert_eq!(z85_key("too short"), None);
+        assert!(!format!("{:?}", keys).contains(&z85(&keys.secret_key)));
+    }
+
+    #[test]
+    fn test_zap_requests_of_unknown_clients_are_rejected() {
+        if !curve_supported() {
+            return;
+        }
+        let client = CurveKeyPair::generate().unwrap();
+        let stranger = CurveKeyPair::generate().unwrap();
+        let server = CurveServer {
+            keys: CurveKeyPair::generate().unwrap(),
+            allowed_clients: vec![client.public_key],
+        };
+        let request = |mechanism: &str, public_key: &CurveKey| {
+            vec![
+                b"1.0".to_vec(),
+                b"7".to_vec(),
+                Vec::new(),
+                b"127.0.0.1".to_vec(),
+                Vec::new(),
+                mechanism.as_bytes().to_vec(),
+                public_key.to_vec(),
+            ]
+        };
+        let status = |reply: Vec<Vec<u8>>| (reply[1].clone(), reply[2].clone());
+
+        let reply = answer_zap_request(&request("CURVE", &client.public_key), &server);
+        assert_eq!(status(reply), (b"7".to_vec(), b"200".to_vec()));
+        let reply = answer_zap_request(&request("CURVE", &stranger.public_key), &server);
+        assert_eq!(status(reply), (b"7".to_vec(), b"400".to_vec()));
+        let reply = answer_zap_request(&request("PLAIN", &client.public_key), &server);
+        assert_eq!(status(reply), (b"7".to_vec(), b"400".to_vec()));
+    }
+
+    #[test]
+    fn test_curve_without_libzmq_support_is_a_clear_error() {
+        if curve_supported() {
+            return;
+        }
+        assert!(matches!(
+            CurveKeyPair::generate(),
+            Err(EngineError::CurveUnsupported)
+        ));
+        let context = zmq::Context::new();
+        let socket = context.socket(zmq::PUB).unwrap();
+        let server = CurveServer {
+            keys: CurveKeyPair {
+                public_key: [1; 32],
+                secret_key: [2; 32],
+            },
+            allowed_clients: Vec::new(),
+        };
+        let error = make_server(&socket, "incoming", &server).unwrap_err();
+        assert!(error.to_string().contains("libsodium"), "{}", error);
+    }
+}
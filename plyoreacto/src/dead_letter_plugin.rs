@@ -0,0 +1,187 @@
+//! Dead letter plugin.
+//! This plugin subscribes to DeadLetterEvent messages, published for the events a plugin failed
+//! to process (see `PluginContext::dead_letter`), and writes each to a file of its own in a
+//! directory, as the JSON of the `DeadLetter`: the event as it was received, envelope and
+//! payload included, and why it failed. `read_dead_letters` reads them back, for inspecting the
+//! events or publishing them again with `DeadLetter::event` once the plugin is fixed.
+//! Failing to write a dead letter does not stop the plugin: the dead letter is logged and
+//! dropped.
+//!
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use log::{error, info, warn};
+
+use crate::events::{now_ms, DeadLetter, Event};
+use crate::plugin::{Plugin, PluginContext, PluginError};
+
+/// The dead letter plugin, for registering with a `PluginRegistry`. The file of a dead letter is
+/// named after the time it was written and its event id, e.g.,
+/// `1700000000000-<uuid>.json`, so that the files sort in the order they were written.
+pub struct DeadLetterPlugin {
+    plugin_id: i32,
+    dir: PathBuf,
+}
+
+impl DeadLetterPlugin {
+    /// A plugin writing the dead letters to the directory `dir`, which is created if needed.
+    pub fn new(plugin_id: i32, dir: impl Into<PathBuf>) -> Self {
+        DeadLetterPlugin {
+            plugin_id,
+            dir: dir.into(),
+        }
+    }
+
+    // Write `dead_letter` to the file `name`.
+    fn write(&self, name: &str, dead_letter: &DeadLetter) -> io::Result<PathBuf> {
+        fs::create_dir_all(&self.dir)?;
+        let path = self.dir.join(name);
+        // written next to its final path and renamed, so that a reader never sees half of it
+        let partial = self.dir.join(format!(".{}", name));
+        fs::write(&partial, serde_json::to_vec(dead_letter)?)?;
+        fs::rename(&partial, &path)?;
+        Ok(path)
+    }
+}
+
+impl Plugin for DeadLetterPlugin {
+    fn id(&self) -> i32 {
+        self.plugin_id
+    }
+
+    fn name(&self) -> &str {
+        "dead-letter"
+    }
+
+    fn subscriptions(&self) -> &[&str] {
+        &["DeadLetterEvent"]
+    }
+
+    fn start(self: Box<Self>, mut ctx: PluginContext) -> Result<(), PluginError> {
+        loop {
+            let msg = ctx.next_event()?;
+            if msg.event_type == "PluginTerminateEvent" {
+                info!(plugin_id = ctx.plugin_id; "Dead letter plugin got terminate event, exiting");
+                return Ok(());
+            }
+            let dead_letter = match msg.decode() {
+                Ok(Event::DeadLetter(dead_letter)) => dead_letter,
+                Ok(_) => continue,
+                Err(e) => {
+                    warn!(
+                        plugin_id = ctx.plugin_id;
+                        "Dead letter plugin could not read a dead letter: {}", e
+                    );
+                    continue;
+                }
+            };
+            let event_id = msg
+                .meta
+                .map(|meta| meta.event_id)
+                .unwrap_or_else(uuid::Uuid::new_v4);
+            let name = format!("{}-{}.json", now_ms(), event_id);
+            match self.write(&name, &dead_letter) {
+                Ok(path) => info!(
+                    plugin_id = ctx.plugin_id;
+                    "Dead letter plugin wrote the {} plugin {} failed to process to {}: {}",
+                    dead_letter.event_type, dead_letter.plugin_id, path.display(), dead_letter.reason
+                ),
+                Err(e) => error!(
+                    plugin_id = ctx.plugin_id;
+                    "Dead letter plugin dropped the {} plugin {} failed to process ({}): could not write {}: {}",
+                    dead_letter.event_type, dead_letter.plugin_id, dead_letter.reason,
+                    self.dir.join(&name).display(), e
+                ),
+            }
+        }
+    }
+}
+
+/// The dead letters a `DeadLetterPlugin` wrote to `dir`, in the order it wrote them.
+pub fn read_dead_letters(dir: impl AsRef<Path>) -> io::Result<Vec<DeadLetter>> {
+    let mut paths = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let name = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or_default();
+        if name.ends_with(".json") && !name.starts_with('.') {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+    paths
+        .iter()
+        .map(|path| Ok(serde_json::from_slice(&fs::read(path)?)?))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::events::{
+        get_event_type_bytes_filter, send_event_msg_with_meta, send_plugin_terminate_event,
+        EventMeta, EventPayload, NewImage,
+    };
+    use flatbuffers::FlatBufferBuilder;
+
+    #[test]
+    fn test_dead_letters_are_written_and_read_back() {
+        let dir = std::env::temp_dir().join(format!("plyoreacto-dead-{}", uuid::Uuid::new_v4()));
+        let context = zmq::Context::new();
+        let mut events = context.socket(zmq::PUB).unwrap();
+        events.bind("inproc://test-dead-letters").unwrap();
+        let sub_socket = context.socket(zmq::SUB).unwrap();
+        sub_socket.connect("inproc://test-dead-letters").unwrap();
+        for sub in ["DeadLetterEvent", "PluginTerminateEvent"] {
+            sub_socket
+                .set_subscribe(&get_event_type_bytes_filter(sub).unwrap())
+                .unwrap();
+        }
+        let ctx = PluginContext::new(7, context.socket(zmq::PUB).unwrap(), sub_socket);
+        // give the subscriptions time to reach the pub socket
+        std::thread::sleep(std::time::Duration::from_millis(100));
+        let plugin = DeadLetterPlugin::new(7, &dir);
+        let plugin = std::thread::spawn(move || Box::new(plugin).start(ctx));
+
+        let image = NewImage {
+            image_uuid: "1234".to_string(),
+            image_format: "png".to_string(),
+            image: vec![1, 2, 3],
+            location: None,
+        };
+        let mut bldr = FlatBufferBuilder::new();
+        let meta = EventMeta::new(1);
+        let dead_letters: Vec<DeadLetter> = ["first", "second"]
+            .into_iter()
+            .map(|reason| DeadLetter {
+                plugin_id: 2,
+                event_type: "NewImageEvent".to_string(),
+                envelope: meta.to_bytes(),
+                payload: image.build(&mut bldr).unwrap().to_vec(),
+                reason: reason.to_string(),
+            })
+            .collect();
+        for dead_letter in &dead_letters {
+            let payload = dead_letter.build(&mut bldr).unwrap();
+            send_event_msg_with_meta(&events, "DeadLetterEvent", &EventMeta::new(2), payload)
+                .unwrap();
+            // files written in the same millisecond would sort by their event id
+            std::thread::sleep(std::time::Duration::from_millis(2));
+        }
+        send_plugin_terminate_event(&mut events, &mut bldr).unwrap();
+        plugin.join().unwrap().unwrap();
+
+        // a file being written is not read
+        fs::write(dir.join(".partial.json"), b"{").unwrap();
+        let read = read_dead_letters(&dir).unwrap();
+        assert_eq!(read, dead_letters);
+        assert_eq!(read[0].meta(), Some(meta));
+        // the event can be published again as it was
+        assert_eq!(read[0].event().unwrap(), Event::NewImage(image));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
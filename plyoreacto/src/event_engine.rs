@@ -1,241 +1,7039 @@
-use std::thread;
+use std::any::Any;
+use std::collections::HashMap;
+use std::fmt;
+#[cfg(unix)]
+use std::fs;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
 
-use crate::events::get_event_type_bytes_filter;
+use crate::acks::{AckChannel, AckConfig, RetryBuffer};
+use crate::admin::{start_admin, Admin, AdminState};
+use crate::capture::{start_capture, Capture, CaptureConfig};
+use crate::compression::{decode_event_frames, decompressed, Compression};
+use crate::config_file::PluginEntry;
+use crate::curve::{make_server, start_zap_handler, CurveServer, Zap};
+use crate::event_log::{
+    replay, start_event_log, EventLog, EventLogConfig, EventLogError, ReplaySpeed,
+};
+use crate::events::{
+    closest_event_type, event_type_header, event_type_names, excluded_event_type,
+    get_event_type_bytes_filter, known_event_type, make_plugin_failed_msg, make_plugin_joined_msg,
+    make_plugin_left_msg, make_plugin_restarted_msg, now_ms, parse_event_messages,
+    recv_event_frames, retry_on_eintr, schema_versions, schema_versions_compatible, send_event_msg,
+    send_plugin_terminate_event, wildcard_prefix, Codec, EngineHeartbeat, EngineStarted,
+    EngineStopping, Event, EventPayload, PluginSkipped, SyncProbe, SCHEMA_VERSION,
+};
 
-use super::image_score_plugin;
-use super::image_store_plugin;
-use super::new_image_plugin;
+use crate::external::SUBSCRIPTION_SETTLE_TIME;
+use crate::last_value_cache::LastValueCache;
+use crate::middleware::{forward_events, Middleware, PayloadLimit};
+use crate::plugin::{PausePolicy, PauseState, Plugin, PluginContext, PluginError};
+use crate::plugin_registry::{
+    default_plugins, PluginConfig, PluginRegistry, RestartPolicy, RESERVED_PLUGIN_IDS,
+};
+#[cfg(feature = "prometheus")]
+use crate::prometheus::{start_exporter, EngineMetrics, Exporter};
+use crate::settings::SettingsError;
+use crate::stats::{EngineStats, ForwardingStats};
 use flatbuffers::FlatBufferBuilder;
+use log::{debug, error, info, warn};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
 use zmq::Socket;
 
-// Basic structure of a plugin configuration.
+// Default ports and endpoint names used by the engine.
+const DEFAULT_INCOMING_PORT: u16 = 5559;
+const DEFAULT_OUTGOING_PORT: u16 = 5560;
+const DEFAULT_INCOMING_INPROC: &str = "messages";
+const DEFAULT_OUTGOING_INPROC: &str = "events";
+const DEFAULT_SYNC_PORT: u16 = 5000;
+const DEFAULT_BIND_ADDRESS: &str = "127.0.0.1";
+const DEFAULT_SYNC_TIMEOUT: Duration = Duration::from_secs(30);
+const DEFAULT_EXTERNAL_HEARTBEAT_TIMEOUT: Duration = Duration::from_secs(10);
+const DEFAULT_HEARTBEAT_MISSED_BEATS: u64 = 3;
+const DEFAULT_SOCKET_LINGER: Duration = Duration::from_millis(100);
+// the zmq default
+const DEFAULT_IO_THREADS: i32 = 1;
+pub(crate) const DEFAULT_PLUGIN_RECV_TIMEOUT: Duration = Duration::from_millis(100);
+pub(crate) const DEFAULT_MAX_PAYLOAD_SIZE: usize = 64 * 1024 * 1024;
 
-struct ExternalPluginConfig {
-    // Every plugin gets a unique id
-    plugin_id: i32,
-}
+// How long plugins get to exit after the PluginTerminateEvent is published, before the zmq
+// context is terminated underneath them.
+const PLUGIN_EXIT_GRACE_PERIOD: Duration = Duration::from_secs(5);
+const DEFAULT_STOPPING_GRACE_PERIOD: Duration = Duration::from_millis(100);
 
-struct PluginConfig<'a> {
-    // Every plugin gets a unique id
-    plugin_id: i32,
-    // The set of events the plugin wants to subscribe to; str's must match event names.
-    subscriptions: &'a [&'a str],
-    // the start function for the plugin
-    // todo -- would be nice to centralize this function signature
-    start_function: fn(&mut Socket, &mut Socket, &mut FlatBufferBuilder) -> std::io::Result<()>,
-}
-
-// Constant structure of all plugins defined in the system
-// Update this config whenever changes need to be made to the plugins that will be run.
-const PLUGINS: [PluginConfig; 3] = [
-    PluginConfig {
-        plugin_id: 0,
-        subscriptions: &[],
-        start_function: new_image_plugin::start,
+// How often the engine checks whether the dependencies of the plugins it is about to answer the
+// sync of are running.
+const DEPENDENCY_POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+/// Errors raised by the engine while setting up its sockets, starting plugins, and running
+/// the proxy. Failures inside plugin threads are reported by `EngineHandle::plugin_status` and
+/// `EngineHandle::wait_for_plugins`.
+#[derive(Debug)]
+pub enum EngineError {
+    /// A zmq socket could not be created.
+    SocketCreation { socket: String, source: zmq::Error },
+    /// A socket could not be bound to an endpoint, e.g., because the port is already in use.
+    Bind {
+        endpoint: String,
+        source: zmq::Error,
+    },
+    /// A socket could not be connected to an endpoint.
+    Connect {
+        endpoint: String,
+        source: zmq::Error,
+    },
+    /// An option could not be set on an existing socket (e.g., a subscription).
+    Socket { socket: String, source: zmq::Error },
+    /// The sync handshake with a plugin failed.
+    Sync { plugin_id: i32, source: zmq::Error },
+    /// These plugins did not sync within the sync timeout.
+    SyncTimeout { plugin_ids: Vec<i32> },
+    /// The engine answered the sync message of an external plugin with something other than
+    /// "ok", e.g., because no plugin with its id is registered.
+    SyncRejected { plugin_id: i32, reply: String },
+    /// A plugin id was registered twice.
+    DuplicatePluginId { plugin_id: i32 },
+    /// The ids of these plugins cannot be started with, e.g., because two plugins share one;
+    /// found before any socket is created.
+    InvalidPluginConfig { problems: Vec<InvalidPluginId> },
+    /// No plugin run by the engine is registered with this id.
+    UnknownPluginId { plugin_id: i32 },
+    /// A plugin could not be started.
+    PluginSpawn { plugin_id: i32, reason: String },
+    /// A plugin could not be configured with its settings; see `Plugin::configure`.
+    PluginSettings {
+        plugin_id: i32,
+        source: SettingsError,
+    },
+    /// A plugin depends on a plugin, given by id or by name, that is neither run by the engine
+    /// nor a required external plugin; see `PluginRegistry::depends_on`.
+    UnknownDependency { plugin_id: i32, dependency: String },
+    /// Plugins depend on each other in a cycle, which goes through these plugins (as
+    /// `name (id)`), back to the first.
+    DependencyCycle { cycle: Vec<String> },
+    /// There is no event type with this name.
+    UnknownEventType { event_type: String },
+    /// The plugins define no profile with this name, but these; see `PluginRegistry::profile`.
+    UnknownProfile {
+        profile: String,
+        profiles: Vec<String>,
+    },
+    /// An entry of the configuration file names no plugin run by the engine, but these are; see
+    /// `EngineConfig::plugins`.
+    UnknownPluginName { name: String, names: Vec<String> },
+    /// Plugins subscribe to event types that are neither built in nor registered, see
+    /// `EventEngineBuilder::allow_unknown_subscriptions`.
+    UnknownSubscriptions {
+        subscriptions: Vec<UnknownSubscription>,
     },
-    PluginConfig {
-        plugin_id: 1,
-        subscriptions: &["NewImageEvent"],
-        start_function: image_score_plugin::start,
+    /// The proxy stopped with an error.
+    Proxy { source: zmq::Error },
+    /// The engine could not be shut down cleanly.
+    Shutdown { source: zmq::Error },
+    /// An event could not be published by the engine.
+    Io(std::io::Error),
+    /// The event log could not be started in this directory.
+    EventLog {
+        dir: PathBuf,
+        source: std::io::Error,
     },
-    PluginConfig {
-        plugin_id: 2,
-        subscriptions: &["ImageScoredEvent"],
-        start_function: image_store_plugin::start,
+    /// No event log could be read in this directory.
+    Replay { dir: PathBuf, source: EventLogError },
+    /// The retry buffer of the events delivered at least once could not be kept in this
+    /// directory; see `AckConfig::dir`.
+    RetryBuffer {
+        dir: PathBuf,
+        source: std::io::Error,
     },
-];
+    /// The HTTP server of the metrics endpoint could not be started.
+    #[cfg(feature = "prometheus")]
+    MetricsServer { addr: String, reason: String },
+    /// External plugins were registered, or allowed to register, with an engine that binds no
+    /// TCP sockets.
+    ExternalPluginsInprocOnly,
+    /// The socket file of an ipc endpoint already exists, e.g., because an engine using it did
+    /// not shut down; see `EventEngineBuilder::unlink_stale_ipc`.
+    #[cfg(unix)]
+    IpcPathExists { path: PathBuf },
+    /// An engine binding TCP only was configured for CURVE, which its own plugins would need
+    /// keys for.
+    CurveTcpOnly,
+    /// The engine or a client was configured for CURVE, but the libzmq linked in was built
+    /// without it (i.e., without libsodium); see `curve::curve_supported`.
+    CurveUnsupported,
+    /// The zmq context was to run this many I/O threads, but it needs at least one; see
+    /// `EventEngineBuilder::io_threads`.
+    InvalidIoThreads { io_threads: i32 },
+    /// The async engine was configured with an option it does not implement (yet), named as the
+    /// field of `EngineConfig`.
+    #[cfg(all(feature = "async", unix))]
+    AsyncUnsupported { option: &'static str },
+}
 
-const EXTERNAL_PLUGINS: [ExternalPluginConfig; 1] = [
-    ExternalPluginConfig {
-        plugin_id: 3,
+impl fmt::Display for EngineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EngineError::SocketCreation { socket, source } => {
+                write!(f, "could not create {} socket: {}", socket, source)
+            }
+            EngineError::Bind { endpoint, source } => {
+                write!(f, "could not bind socket to {}: {}", endpoint, source)
+            }
+            EngineError::Connect { endpoint, source } => {
+                write!(f, "could not connect socket to {}: {}", endpoint, source)
+            }
+            EngineError::Socket { socket, source } => {
+                write!(f, "could not configure {} socket: {}", socket, source)
+            }
+            EngineError::Sync { plugin_id, source } => {
+                write!(f, "could not sync with plugin {}: {}", plugin_id, source)
+            }
+            EngineError::SyncTimeout { plugin_ids } => {
+                write!(
+                    f,
+                    "plugins {:?} did not sync within the timeout",
+                    plugin_ids
+                )
+            }
+            EngineError::SyncRejected { plugin_id, reply } => {
+                write!(f, "engine did not sync plugin {}: {}", plugin_id, reply)
+            }
+            EngineError::DuplicatePluginId { plugin_id } => {
+                write!(f, "plugin id {} is already registered", plugin_id)
+            }
+            EngineError::InvalidPluginConfig { problems } => {
+                write!(f, "invalid plugin ids: ")?;
+                for (i, problem) in problems.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", problem)?;
+                }
+                Ok(())
+            }
+            EngineError::UnknownPluginId { plugin_id } => {
+                write!(f, "no plugin run by the engine has id {}", plugin_id)
+            }
+            EngineError::PluginSpawn { plugin_id, reason } => {
+                write!(f, "could not start plugin {}: {}", plugin_id, reason)
+            }
+            EngineError::PluginSettings { plugin_id, source } => {
+                write!(f, "could not configure plugin {}: {}", plugin_id, source)
+            }
+            EngineError::UnknownDependency {
+                plugin_id,
+                dependency,
+            } => write!(
+                f,
+                "plugin {} depends on {}, which is neither run by the engine nor a required \
+                 external plugin",
+                plugin_id, dependency
+            ),
+            EngineError::DependencyCycle { cycle } => {
+                let first = cycle.first().map(String::as_str).unwrap_or_default();
+                write!(
+                    f,
+                    "plugins depend on each other in a cycle: {} -> {}",
+                    cycle.join(" -> "),
+                    first
+                )
+            }
+            EngineError::UnknownEventType { event_type } => {
+                write!(f, "unknown event type {:?}", event_type)
+            }
+            EngineError::UnknownProfile { profile, profiles } => {
+                write!(
+                    f,
+                    "unknown plugin profile {:?} (profiles: {:?})",
+                    profile, profiles
+                )
+            }
+            EngineError::UnknownPluginName { name, names } => {
+                write!(
+                    f,
+                    "no plugin run by the engine is named {:?} (plugins: {:?})",
+                    name, names
+                )
+            }
+            EngineError::UnknownSubscriptions { subscriptions } => {
+                write!(f, "subscriptions to unknown event types: ")?;
+                for (i, subscription) in subscriptions.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", subscription)?;
+                }
+                Ok(())
+            }
+            EngineError::Proxy { source } => write!(f, "proxy stopped with an error: {}", source),
+            EngineError::Shutdown { source } => {
+                write!(f, "could not shut down the engine: {}", source)
+            }
+            EngineError::Io(source) => write!(f, "could not publish event: {}", source),
+            EngineError::EventLog { dir, source } => {
+                write!(f, "could not log events to {}: {}", dir.display(), source)
+            }
+            EngineError::Replay { dir, source } => {
+                write!(
+                    f,
+                    "could not replay events from {}: {}",
+                    dir.display(),
+                    source
+                )
+            }
+            EngineError::RetryBuffer { dir, source } => {
+                write!(
+                    f,
+                    "could not keep the retry buffer in {}: {}",
+                    dir.display(),
+                    source
+                )
+            }
+            #[cfg(feature = "prometheus")]
+            EngineError::MetricsServer { addr, reason } => {
+                write!(f, "could not serve metrics on {}: {}", addr, reason)
+            }
+            EngineError::ExternalPluginsInprocOnly => {
+                write!(f, "external plugins cannot reach an inproc-only engine")
+            }
+            #[cfg(unix)]
+            EngineError::IpcPathExists { path } => write!(
+                f,
+                "ipc socket file {} already exists; is another engine using it?",
+                path.display()
+            ),
+            EngineError::CurveTcpOnly => write!(
+                f,
+                "CURVE needs the inproc endpoints for the plugins the engine starts"
+            ),
+            EngineError::CurveUnsupported => write!(
+                f,
+                "CURVE is not supported by this libzmq, which was built without libsodium"
+            ),
+            EngineError::InvalidIoThreads { io_threads } => write!(
+                f,
+                "the zmq context needs at least one I/O thread, not {}",
+                io_threads
+            ),
+            #[cfg(all(feature = "async", unix))]
+            EngineError::AsyncUnsupported { option } => {
+                write!(f, "the async engine does not support {}", option)
+            }
+        }
     }
-];
+}
 
+impl std::error::Error for EngineError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            EngineError::SocketCreation { source, .. }
+            | EngineError::Bind { source, .. }
+            | EngineError::Connect { source, .. }
+            | EngineError::Socket { source, .. }
+            | EngineError::Sync { source, .. }
+            | EngineError::Proxy { source }
+            | EngineError::Shutdown { source } => Some(source),
+            EngineError::Io(source)
+            | EngineError::EventLog { source, .. }
+            | EngineError::RetryBuffer { source, .. } => Some(source),
+            EngineError::Replay { source, .. } => Some(source),
+            EngineError::PluginSettings { source, .. } => Some(source),
+            EngineError::SyncTimeout { .. }
+            | EngineError::SyncRejected { .. }
+            | EngineError::DuplicatePluginId { .. }
+            | EngineError::InvalidPluginConfig { .. }
+            | EngineError::UnknownPluginId { .. }
+            | EngineError::PluginSpawn { .. }
+            | EngineError::UnknownDependency { .. }
+            | EngineError::DependencyCycle { .. }
+            | EngineError::UnknownEventType { .. }
+            | EngineError::UnknownProfile { .. }
+            | EngineError::UnknownPluginName { .. }
+            | EngineError::UnknownSubscriptions { .. }
+            | EngineError::ExternalPluginsInprocOnly
+            | EngineError::CurveTcpOnly
+            | EngineError::CurveUnsupported
+            | EngineError::InvalidIoThreads { .. } => None,
+            #[cfg(feature = "prometheus")]
+            EngineError::MetricsServer { .. } => None,
+            #[cfg(unix)]
+            EngineError::IpcPathExists { .. } => None,
+            #[cfg(all(feature = "async", unix))]
+            EngineError::AsyncUnsupported { .. } => None,
+        }
+    }
+}
 
-fn get_outgoing_socket(context: &zmq::Context) -> std::io::Result<Socket> {
-    let outgoing = context
-        .socket(zmq::PUB)
-        .expect("Engine could not create outgoing socket");
-    outgoing
-        .bind("tcp://*:5560")
-        .expect("Engine could not bind outgoing TCP socket");
-    outgoing
-        .bind("inproc://events")
-        .expect("Engine could not bind outgoing inproc socket");
-    Ok(outgoing)
+impl From<std::io::Error> for EngineError {
+    fn from(error: std::io::Error) -> Self {
+        EngineError::Io(error)
+    }
 }
 
-fn get_incoming_socket(context: &zmq::Context) -> std::io::Result<Socket> {
-    let incoming = context
-        .socket(zmq::SUB)
-        .expect("Engine could not create incoming socket");
-    incoming
-        .bind("tcp://*:5559")
-        .expect("Engine could not bind incoming TCP socket");
-    incoming
-        .bind("inproc://messages")
-        .expect("Engine could not bind incoming inproc socket");
-    // subscribe to all events
-    let filter = String::new();
-    incoming
-        .set_subscribe(filter.as_bytes())
-        .expect("Engine could not subscribe to all events on incoming socket");
-    Ok(incoming)
+/// Why a plugin id cannot be started with; see `EngineError::InvalidPluginConfig`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum InvalidPluginId {
+    /// More than one plugin run by the engine, or more than one external plugin, has this id.
+    Duplicate { plugin_id: i32 },
+    /// Both a plugin run by the engine and an external plugin have this id.
+    InternalAndExternal { plugin_id: i32 },
+    /// Plugin ids are never negative; those are the source of the events no plugin published,
+    /// e.g., replayed ones.
+    Negative { plugin_id: i32 },
+    /// The id is one of the `RESERVED_PLUGIN_IDS`.
+    Reserved { plugin_id: i32 },
 }
 
-fn start_plugin<F>(
-    ctx: &zmq::Context,
-    plugin_id: i32,
-    subscriptions: &[&str],
-    start: F,
-) -> std::io::Result<()>
-where
-    // todo -- would be good to centralize this signature with the one defined earlier for the
-    // plugin config.
-    F: FnOnce(&mut Socket, &mut Socket, &mut FlatBufferBuilder) -> std::io::Result<()>
-        + std::marker::Send
-        + 'static,
-{
-    // Create the socket that plugin will use to publish new events
-    let mut pub_socket = ctx.socket(zmq::PUB).expect("could not create pub socket.");
-    pub_socket
-        .connect("inproc://messages")
-        .expect("could not connect to pub socket");
-    println!("plugin {} connected to pub socket.", plugin_id);
+impl fmt::Display for InvalidPluginId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InvalidPluginId::Duplicate { plugin_id } => {
+                write!(f, "{} (registered more than once)", plugin_id)
+            }
+            InvalidPluginId::InternalAndExternal { plugin_id } => {
+                write!(
+                    f,
+                    "{} (registered both as run by the engine and external)",
+                    plugin_id
+                )
+            }
+            InvalidPluginId::Negative { plugin_id } => write!(f, "{} (negative)", plugin_id),
+            InvalidPluginId::Reserved { plugin_id } => {
+                write!(f, "{} (reserved for the engine)", plugin_id)
+            }
+        }
+    }
+}
 
-    // Create the socket that plugin will use to subscribe to events
-    let mut sub_socket = ctx
-        .socket(zmq::SUB)
-        .expect("could not create subscription socket.");
-    sub_socket
-        .connect("inproc://events")
-        .expect("could not connect to subscriptions socket");
-    // Subscribe only to events of interest
-    for sub in subscriptions {
-        let filter_bytes = get_event_type_bytes_filter(sub).expect("could not get bytes filter");
-        sub_socket
-            .set_subscribe(&filter_bytes)
-            .expect("could not subscribe to event type");
+/// A subscription of a plugin to an event type that is neither built in nor registered.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnknownSubscription {
+    pub plugin_id: i32,
+    pub event_type: String,
+    /// The known event type the name is most likely a misspelling of, if any.
+    pub suggestion: Option<&'static str>,
+}
+
+impl UnknownSubscription {
+    // The unknown subscriptions among `subscriptions`, those of the plugin `plugin_id`.
+    // Wildcards are never unknown, whether or not they match a known event type; exclusions
+    // are checked like the subscriptions they negate.
+    pub(crate) fn find(plugin_id: i32, subscriptions: &[&str]) -> Vec<UnknownSubscription> {
+        subscriptions
+            .iter()
+            .map(|subscription| excluded_event_type(subscription).unwrap_or(subscription))
+            .filter(|event_type| wildcard_prefix(event_type).is_none())
+            .filter(|event_type| known_event_type(event_type).is_none())
+            .map(|event_type| UnknownSubscription {
+                plugin_id,
+                event_type: event_type.to_string(),
+                suggestion: closest_event_type(event_type),
+            })
+            .collect()
     }
+}
 
-    // Create the sync socket that plugin will use to sync with engine and other plugins
-    let sync = ctx
-        .socket(zmq::REQ)
-        .expect("plugin could not create sync socket.");
-    let sync_endpoint_port = 5000 + plugin_id;
-    let sync_endpoint = format!("inproc://sync-{}", sync_endpoint_port);
-    sync.connect(&sync_endpoint)
-        .expect("plugin could not connect to sync socket.");
-    println!("plugin {} connected to sync socket.", plugin_id);
+impl fmt::Display for UnknownSubscription {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?} (plugin {}", self.event_type, self.plugin_id)?;
+        if let Some(suggestion) = self.suggestion {
+            write!(f, ", did you mean {}?", suggestion)?;
+        }
+        write!(f, ")")
+    }
+}
 
-    // start the plugin thread
-    thread::spawn(move || {
-        // connect to and send sync message on sync socket
-        let msg = "ready";
-        sync.send(msg, 0)
-            .expect("plugin could not send sync message");
-        println!("plugin {} sent sync message.", plugin_id);
-        // wait for reply from engine
-        let _msg = sync
-            .recv_msg(0)
-            .expect("plugin got error trying to receive sync reply");
-        println!(
-            "plugin {} got sync reply, will now block for messages",
-            plugin_id
+// Fail with the `unknown` subscriptions of plugins, unless the engine allows them, when it only
+// warns about them.
+pub(crate) fn check_subscriptions(
+    config: &EngineConfig,
+    unknown: Vec<UnknownSubscription>,
+) -> Result<(), EngineError> {
+    if unknown.is_empty() {
+        return Ok(());
+    }
+    if !config.allow_unknown_subscriptions {
+        return Err(EngineError::UnknownSubscriptions {
+            subscriptions: unknown,
+        });
+    }
+    for subscription in unknown {
+        warn!(
+            plugin_id = subscription.plugin_id;
+            "plugin {} subscribes to unknown event type {:?}",
+            subscription.plugin_id, subscription.event_type
         );
+    }
+    Ok(())
+}
 
-        let mut bldr = FlatBufferBuilder::new();
+// The subscriptions the sub socket of a plugin subscribing with `subscriptions` is subscribed
+// with: all but the exclusions, which the plugin's context applies (see
+// `PluginContext::with_subscriptions`), or a wildcard matching every event type if there are only
+// exclusions.
+pub(crate) fn subscribed<'a>(subscriptions: &[&'a str]) -> Vec<&'a str> {
+    let included: Vec<&str> = subscriptions
+        .iter()
+        .filter(|subscription| excluded_event_type(subscription).is_none())
+        .copied()
+        .collect();
+    if included.is_empty() && !subscriptions.is_empty() {
+        return vec!["*"];
+    }
+    included
+}
 
-        // now execute the actual plugin function
-        println!("Executing start function for plugin {}", plugin_id);
-        start(&mut pub_socket, &mut sub_socket, &mut bldr)
-            .expect("got error executing plugin start function");
-    });
+// The filter of a subscription to `event_type`, which must be a known event type unless the
+// engine allows unknown subscriptions, or to the event types matching a wildcard: the header
+// frames of those start with its prefix.
+pub(crate) fn subscription_filter(config: &EngineConfig, event_type: &str) -> Option<Vec<u8>> {
+    if let Some(prefix) = wildcard_prefix(event_type) {
+        return Some(prefix.as_bytes().to_vec());
+    }
+    match get_event_type_bytes_filter(event_type) {
+        Ok(filter) => Some(filter),
+        Err(_) if config.allow_unknown_subscriptions => Some(event_type_header(event_type)),
+        Err(_) => None,
+    }
+}
 
-    Ok(())
+/// High-water marks of a socket, in messages: how many messages it queues for sending (SNDHWM)
+/// and for receiving (RCVHWM). PUB and SUB sockets drop the messages past them, so a burst of
+/// events that outgrows them is lost for its slower subscribers; `PluginContext::dropped_events`
+/// detects the loss. `None` keeps the zmq default of 1000, and 0 means no limit.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Hwm {
+    pub send: Option<i32>,
+    pub receive: Option<i32>,
 }
 
-fn sync_plugins(context: zmq::Context) -> std::io::Result<()> {
-    let total_subscribers = PLUGINS.len() + EXTERNAL_PLUGINS.len();
-    let mut sync_sockets = Vec::<zmq::Socket>::new();
-
-    // wait for all plugins to sync
-    let mut ready_subscribers = 0;
-    // the approach below assumes each plugin has been assigned a specific port which implies a degree of
-    // coordination between engine and plugins. we could send all sync messages on the same socket/port
-    while ready_subscribers < total_subscribers {
-        // each subscriber gets its own port
-        let port = 5000 + ready_subscribers;
-        // synchronization sockets --
-        let sync = context
-            .socket(zmq::REP)
-            .expect("Engine could not create synchronization socket");
-        let tcp_addr = format!("tcp://*:{}", port);
-        let inproc_addr = format!("inproc://sync-{}", port);
-        sync.bind(&tcp_addr)
-            .expect("Engine could not bind sync TCP socket.");
-        println!("Engine bound to sync TCP socket on port: {}", &port);
-        sync.bind(&inproc_addr)
-            .expect("Engine could not bind sync inproc socket.");
-        println!("Engine bound to sync inproc socket: {}", &inproc_addr);
-        // receive message from plugin
-        let _msg = sync
-            .recv_msg(0)
-            .expect("Engine got error receiving sync message");
-        println!("Engine got sync message on sync socket {}", &port);
-        sync_sockets.push(sync);
-        ready_subscribers += 1;
-    }
-    // send a reply to all plugins
-    let mut msg_sent = 0;
-    while msg_sent < total_subscribers {
-        let reply = "ok";
-        let sync = sync_sockets.pop().expect("Could not get sync socket");
-        println!("Engine sending reply message to {}", &msg_sent);
-        sync.send(reply, 0)
-            .expect("Engine got error trying to send sync reply.");
-        msg_sent += 1;
+impl Hwm {
+    /// The same high-water mark for sending and receiving.
+    pub fn new(hwm: i32) -> Self {
+        Hwm {
+            send: Some(hwm),
+            receive: Some(hwm),
+        }
     }
+}
 
-    Ok(())
+/// TCP keepalive of the connections of a socket, which finds out about peers that went away
+/// without closing them and keeps idle connections through NATs and firewalls open. `None` keeps
+/// the OS default; zmq sets the times in whole seconds.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TcpKeepalive {
+    /// How long a connection is idle before the first probe (TCP_KEEPIDLE).
+    pub idle: Option<Duration>,
+    /// How long between two probes (TCP_KEEPINTVL).
+    pub interval: Option<Duration>,
+    /// How many probes go unanswered before the connection is dropped (TCP_KEEPCNT).
+    pub count: Option<i32>,
+}
+
+/// Which transports an engine binds its incoming, outgoing and sync sockets on; in a
+/// configuration file, `"inproc-only"`, `"tcp"`, `"both"` or `{ ipc = { dir = "..." } }`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Transport {
+    /// The inproc endpoints only, so that no TCP port has to be free: the engine is an event bus
+    /// within its process, which external plugins cannot reach.
+    InprocOnly,
+    /// The TCP ports only; the plugins the engine starts and the host's sockets connect to it
+    /// over TCP as well.
+    Tcp,
+    /// The inproc endpoints for the plugins the engine starts and the host's sockets, and the
+    /// TCP ports for external plugins and subscribers.
+    #[default]
+    Both,
+    /// The inproc endpoints for the plugins the engine starts and the host's sockets, and Unix
+    /// domain sockets in the directory `dir` (`incoming.sock`, `outgoing.sock` and `sync.sock`)
+    /// instead of the TCP ports, for external plugins on the same host.
+    #[cfg(unix)]
+    Ipc { dir: PathBuf },
+}
+
+/// Ports and endpoint names used by an engine and the plugins it starts.
+/// Two engines can run side by side (in the same process or on the same host) as long as
+/// their configurations do not overlap.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EngineConfig {
+    // TCP port the engine receives newly published events on; 0 for one the system picks,
+    // which `EngineHandle::config` then has, as for the other ports
+    pub incoming_port: u16,
+    // TCP port the engine publishes events to subscribers on
+    pub outgoing_port: u16,
+    // name of the inproc endpoint plugins publish new events to (i.e., inproc://<name>)
+    pub incoming_inproc: String,
+    // name of the inproc endpoint plugins subscribe to events on
+    pub outgoing_inproc: String,
+    // TCP port of the sync socket external plugins announce themselves on; it is only bound
+    // when there are external plugins
+    pub sync_port: u16,
+    // how long the engine waits for all plugins to sync before giving up
+    pub sync_timeout: Duration,
+    // start without the external plugins that did not sync in time instead of failing
+    pub skip_missing_external_plugins: bool,
+    // let plugins subscribe to event types that are neither built in nor registered, instead
+    // of failing
+    pub allow_unknown_subscriptions: bool,
+    // how long after startup external plugins may register on the TCP sync socket to be
+    // assigned an id; zero (the default) does not accept registrations
+    pub registration_window: Duration,
+    // how long an external plugin sending heartbeats may go without one before the engine
+    // publishes a PluginLeftEvent for it; clients send one every quarter of it
+    pub external_heartbeat_timeout: Duration,
+    // how often the engine publishes an EngineHeartbeatEvent; zero (the default) publishes none
+    pub heartbeat_interval: Duration,
+    // how many heartbeats in a row a plugin answering them may miss before it is flagged as
+    // unresponsive
+    pub heartbeat_missed_beats: u64,
+    // high-water marks of the socket the engine receives events on and of the one it publishes
+    // them on
+    pub incoming_hwm: Hwm,
+    pub outgoing_hwm: Hwm,
+    // TCP port of the admin socket answering status requests; None (the default) binds none
+    pub admin_port: Option<u16>,
+    // how long a closed socket may still try to send the messages it holds, which terminating
+    // the zmq context waits for
+    pub socket_linger: Duration,
+    // how many I/O threads the zmq context runs when the engine creates it (one of the host's
+    // keeps its own); at least one
+    pub io_threads: i32,
+    // TCP keepalive of the connections of the incoming and outgoing sockets; None (the default)
+    // leaves it to the OS
+    pub tcp_keepalive: Option<TcpKeepalive>,
+    // how long `PluginContext::next_event` waits for an event before checking whether the
+    // engine is shutting down; zero waits for the next event however long it takes
+    pub plugin_recv_timeout: Duration,
+    // address (e.g., "0.0.0.0:9100") of the HTTP metrics endpoint; None (the default) serves none
+    #[cfg(feature = "prometheus")]
+    pub metrics_addr: Option<String>,
+    // which transports the incoming, outgoing and sync sockets are bound on
+    pub transport: Transport,
+    // address of the interface the TCP ports (including the sync and admin ones) are bound on:
+    // "127.0.0.1" (the default), another one such as "192.168.1.10" or "[::1]", or "*" for all
+    // interfaces
+    pub bind_address: String,
+    // remove the socket files of the ipc endpoints left behind by an engine that did not shut
+    // down, instead of refusing to start
+    #[cfg(unix)]
+    pub unlink_stale_ipc: bool,
+    // keypair and allowed clients making the TCP and ipc sockets CURVE servers; None (the
+    // default) leaves them unencrypted
+    pub curve: Option<CurveServer>,
+    // stop the proxy once it has forwarded this many events of this type; None (the default)
+    // runs until the engine is shut down
+    pub stop_after: Option<(String, u64)>,
+    // directory and segment size of the log of the forwarded events; None (the default) logs
+    // none
+    pub event_log: Option<EventLogConfig>,
+    // the event types whose most recent event is sent again to each new subscriber; empty (the
+    // default) caches none
+    pub last_value_cache: Vec<String>,
+    // forward events with the engine's own loop even without a payload size limit, middlewares
+    // or a last-value cache, which need it
+    pub forwarding_loop: bool,
+    // the biggest event payload, in bytes, that plugins may publish and the engine forwards;
+    // zero forwards events of any size
+    pub max_payload_size: usize,
+    // when the plugins compress the payloads of the events they publish; None (the default)
+    // compresses none
+    pub compression: Option<Compression>,
+    // how the plugins serialize the events they publish
+    pub codec: Codec,
+    // the events delivered at least once to some plugins; None (the default) delivers every
+    // event at most once
+    pub acknowledged_delivery: Option<AckConfig>,
+    // id of the engine, recorded as the origin of the events its plugins publish
+    pub engine_id: u32,
+    // how long the proxy keeps forwarding after the EngineStoppingEvent on shutdown
+    pub stopping_grace_period: Duration,
+    // what the plugins paused with `EngineHandle::pause_plugin` do with the events they receive
+    pub pause_policy: PausePolicy,
+    // the profile of the plugins (see `PluginRegistry::profile`) the engine starts; None (the
+    // default) starts them all
+    pub profile: Option<String>,
+    // the `[[plugin]]` entries of the configuration file (see the `config_file` module) applied
+    // to the registered plugins of their name when the engine starts; empty (the default)
+    // starts the plugins as they were registered
+    pub plugins: Vec<PluginEntry>,
+}
+
+impl Default for EngineConfig {
+    fn default() -> Self {
+        EngineConfig {
+            incoming_port: DEFAULT_INCOMING_PORT,
+            outgoing_port: DEFAULT_OUTGOING_PORT,
+            incoming_inproc: DEFAULT_INCOMING_INPROC.to_string(),
+            outgoing_inproc: DEFAULT_OUTGOING_INPROC.to_string(),
+            sync_port: DEFAULT_SYNC_PORT,
+            sync_timeout: DEFAULT_SYNC_TIMEOUT,
+            skip_missing_external_plugins: false,
+            allow_unknown_subscriptions: false,
+            registration_window: Duration::ZERO,
+            external_heartbeat_timeout: DEFAULT_EXTERNAL_HEARTBEAT_TIMEOUT,
+            heartbeat_interval: Duration::ZERO,
+            heartbeat_missed_beats: DEFAULT_HEARTBEAT_MISSED_BEATS,
+            incoming_hwm: Hwm::default(),
+            outgoing_hwm: Hwm::default(),
+            admin_port: None,
+            socket_linger: DEFAULT_SOCKET_LINGER,
+            io_threads: DEFAULT_IO_THREADS,
+            tcp_keepalive: None,
+            plugin_recv_timeout: DEFAULT_PLUGIN_RECV_TIMEOUT,
+            #[cfg(feature = "prometheus")]
+            metrics_addr: None,
+            transport: Transport::default(),
+            bind_address: DEFAULT_BIND_ADDRESS.to_string(),
+            #[cfg(unix)]
+            unlink_stale_ipc: false,
+            curve: None,
+            stop_after: None,
+            event_log: None,
+            last_value_cache: Vec::new(),
+            forwarding_loop: false,
+            max_payload_size: DEFAULT_MAX_PAYLOAD_SIZE,
+            compression: None,
+            codec: Codec::Flatbuffers,
+            acknowledged_delivery: None,
+            engine_id: 0,
+            stopping_grace_period: DEFAULT_STOPPING_GRACE_PERIOD,
+            pause_policy: PausePolicy::default(),
+            profile: None,
+            plugins: Vec::new(),
+        }
+    }
+}
+
+impl EngineConfig {
+    pub fn incoming_tcp_endpoint(&self) -> String {
+        self.tcp_endpoint(self.incoming_port)
+    }
+
+    pub fn outgoing_tcp_endpoint(&self) -> String {
+        self.tcp_endpoint(self.outgoing_port)
+    }
+
+    pub fn incoming_inproc_endpoint(&self) -> String {
+        format!("inproc://{}", self.incoming_inproc)
+    }
+
+    pub fn outgoing_inproc_endpoint(&self) -> String {
+        format!("inproc://{}", self.outgoing_inproc)
+    }
+
+    pub fn sync_tcp_endpoint(&self) -> String {
+        self.tcp_endpoint(self.sync_port)
+    }
+
+    pub fn sync_inproc_endpoint(&self) -> String {
+        format!("inproc://{}-sync", self.outgoing_inproc)
+    }
+
+    /// The endpoint the sockets in the engine's process (those of the plugins it starts and of
+    /// the host application) publish new events to: the inproc one, unless the engine binds TCP
+    /// only.
+    pub fn incoming_endpoint(&self) -> String {
+        match self.transport {
+            Transport::Tcp => self.local_tcp_endpoint(self.incoming_port),
+            _ => self.incoming_inproc_endpoint(),
+        }
+    }
+
+    /// The endpoint the sockets in the engine's process subscribe to events on.
+    pub fn outgoing_endpoint(&self) -> String {
+        match self.transport {
+            Transport::Tcp => self.local_tcp_endpoint(self.outgoing_port),
+            _ => self.outgoing_inproc_endpoint(),
+        }
+    }
+
+    /// The endpoint the plugins the engine starts sync on.
+    pub fn sync_endpoint(&self) -> String {
+        match self.transport {
+            Transport::Tcp => self.local_tcp_endpoint(self.sync_port),
+            _ => self.sync_inproc_endpoint(),
+        }
+    }
+
+    /// The endpoint of the socket `name` ("incoming", "outgoing" or "sync") on
+    /// `Transport::Ipc`, e.g., `ipc:///run/plyoreacto/incoming.sock`; None on the other
+    /// transports.
+    #[cfg(unix)]
+    pub fn ipc_endpoint(&self, name: &str) -> Option<String> {
+        self.ipc_path(name)
+            .map(|path| format!("ipc://{}", path.display()))
+    }
+
+    #[cfg(unix)]
+    fn ipc_path(&self, name: &str) -> Option<PathBuf> {
+        match &self.transport {
+            Transport::Ipc { dir } => Some(dir.join(format!("{}.sock", name))),
+            _ => None,
+        }
+    }
+
+    // The endpoint of the socket `name` that external plugins reach the engine on, on `port`
+    // over TCP; an inproc-only engine has none.
+    #[cfg_attr(not(unix), allow(unused_variables))]
+    fn external_endpoint(&self, name: &str, port: u16) -> Option<String> {
+        match &self.transport {
+            Transport::InprocOnly => None,
+            #[cfg(unix)]
+            Transport::Ipc { .. } => self.ipc_endpoint(name),
+            _ => Some(self.tcp_endpoint(port)),
+        }
+    }
+
+    pub(crate) fn tcp_endpoint(&self, port: u16) -> String {
+        format!("tcp://{}:{}", self.bind_address, port)
+    }
+
+    // The endpoint the sockets in the engine's process connect to `port` on: the loopback
+    // interface if the engine binds all of them.
+    fn local_tcp_endpoint(&self, port: u16) -> String {
+        let address = match self.bind_address.as_str() {
+            "[::]" => "[::1]",
+            address if is_all_interfaces(address) => "127.0.0.1",
+            address => address,
+        };
+        format!("tcp://{}:{}", address, port)
+    }
+}
+
+// Whether binding `address` binds all interfaces of the host.
+pub(crate) fn is_all_interfaces(address: &str) -> bool {
+    matches!(address, "*" | "0.0.0.0" | "[::]")
+}
+
+/// Builds an `EngineConfig` piece by piece; any setting that is not provided keeps its default.
+///
+/// ```no_run
+/// use plyoreacto::event_engine::EventEngineBuilder;
+///
+/// EventEngineBuilder::new()
+///     .incoming_port(6559)
+///     .outgoing_port(6560)
+///     .sync_port(6000)
+///     .run()
+///     .expect("Error from engine");
+/// ```
+pub struct EventEngineBuilder {
+    config: EngineConfig,
+    // context supplied by the host application, if any; see `context()`
+    context: Option<zmq::Context>,
+    plugins: PluginRegistry,
+    // in the order they see the events
+    middlewares: Vec<Box<dyn Middleware>>,
+    // see `stop_when()`
+    stop_when: Option<StopPredicate>,
+    // see `capture()`
+    capture: Option<CaptureConfig>,
+}
+
+// A condition over the events the engine forwards for stopping its proxy, see
+// `EventEngineBuilder::stop_when`.
+type StopPredicate = Box<dyn FnMut(&Event) -> bool + Send>;
+
+impl Default for EventEngineBuilder {
+    fn default() -> Self {
+        EventEngineBuilder {
+            config: EngineConfig::default(),
+            context: None,
+            plugins: default_plugins(),
+            middlewares: Vec::new(),
+            stop_when: None,
+            capture: None,
+        }
+    }
+}
+
+impl fmt::Debug for EventEngineBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EventEngineBuilder")
+            .field("config", &self.config)
+            .field("shared_context", &self.context.is_some())
+            .field("plugin_ids", &self.plugins.plugin_ids())
+            .field("middlewares", &self.middlewares.len())
+            .field("stop_when", &self.stop_when.is_some())
+            .field("capture", &self.capture)
+            .finish()
+    }
 }
 
-fn start_plugins(context: zmq::Context) -> std::io::Result<()> {
-    // call start_plugin with the zmq context and the config for each plugin,
-    // as defined in the PLUGINS constant
-    for plugin in PLUGINS {
-        start_plugin(
-            &context,
-            plugin.plugin_id,
-            plugin.subscriptions,
-            plugin.start_function,
+impl EventEngineBuilder {
+    pub fn new() -> Self {
+        EventEngineBuilder::default()
+    }
+
+    /// Start from `config`, e.g., one read with `EngineConfig::from_toml_file`, instead of the
+    /// default configuration: the settings provided afterwards override those of `config`.
+    pub fn from_config(config: EngineConfig) -> Self {
+        EventEngineBuilder {
+            config,
+            ..EventEngineBuilder::default()
+        }
+    }
+
+    pub fn incoming_port(mut self, port: u16) -> Self {
+        self.config.incoming_port = port;
+        self
+    }
+
+    pub fn outgoing_port(mut self, port: u16) -> Self {
+        self.config.outgoing_port = port;
+        self
+    }
+
+    pub fn incoming_inproc(mut self, name: &str) -> Self {
+        self.config.incoming_inproc = name.to_string();
+        self
+    }
+
+    pub fn outgoing_inproc(mut self, name: &str) -> Self {
+        self.config.outgoing_inproc = name.to_string();
+        self
+    }
+
+    pub fn sync_port(mut self, port: u16) -> Self {
+        self.config.sync_port = port;
+        self
+    }
+
+    pub fn sync_timeout(mut self, timeout: Duration) -> Self {
+        self.config.sync_timeout = timeout;
+        self
+    }
+
+    pub fn skip_missing_external_plugins(mut self, skip: bool) -> Self {
+        self.config.skip_missing_external_plugins = skip;
+        self
+    }
+
+    /// Let plugins subscribe to event types that are neither built in nor registered with the
+    /// `EventTypeRegistry`, e.g., to types a newer version of the schema is going to add, with
+    /// a warning; by default such a subscription, most likely a typo, fails the startup with
+    /// `EngineError::UnknownSubscriptions`.
+    pub fn allow_unknown_subscriptions(mut self, allow: bool) -> Self {
+        self.config.allow_unknown_subscriptions = allow;
+        self
+    }
+
+    /// Accept registrations of external plugins for `window` after startup; the engine waits
+    /// for the whole window (within the sync timeout) before it starts.
+    pub fn registration_window(mut self, window: Duration) -> Self {
+        self.config.registration_window = window;
+        self
+    }
+
+    /// Consider an external plugin gone, and publish a PluginLeftEvent for it, once it has not
+    /// sent a heartbeat for `timeout`.
+    pub fn external_heartbeat_timeout(mut self, timeout: Duration) -> Self {
+        self.config.external_heartbeat_timeout = timeout;
+        self
+    }
+
+    /// Publish an EngineHeartbeatEvent every `interval`; see `EngineHandle::plugin_liveness`.
+    pub fn heartbeat_interval(mut self, interval: Duration) -> Self {
+        self.config.heartbeat_interval = interval;
+        self
+    }
+
+    /// Flag a plugin answering the heartbeats as unresponsive once it misses `beats` in a row.
+    pub fn heartbeat_missed_beats(mut self, beats: u64) -> Self {
+        self.config.heartbeat_missed_beats = beats;
+        self
+    }
+
+    /// Set the high-water marks of the socket the engine receives the published events on; see
+    /// `PluginRegistry::hwm` for those of the plugins' sockets.
+    pub fn incoming_hwm(mut self, hwm: Hwm) -> Self {
+        self.config.incoming_hwm = hwm;
+        self
+    }
+
+    /// Set the high-water marks of the socket the engine forwards the events to subscribers on.
+    pub fn outgoing_hwm(mut self, hwm: Hwm) -> Self {
+        self.config.outgoing_hwm = hwm;
+        self
+    }
+
+    /// Answer `status`, `plugins` and `counters` requests with JSON on a REP socket bound to
+    /// `port`; see the `admin` module.
+    pub fn admin_port(mut self, port: u16) -> Self {
+        self.config.admin_port = Some(port);
+        self
+    }
+
+    /// How long a socket of the engine or its plugins may hold on to unsent messages once it is
+    /// closed (100 ms by default); shutting down waits at most that long for them.
+    pub fn socket_linger(mut self, linger: Duration) -> Self {
+        self.config.socket_linger = linger;
+        self
+    }
+
+    /// Run `io_threads` I/O threads (one by default) on the zmq context of the engine; each
+    /// keeps about one core busy sending and receiving over TCP, so an engine with many external
+    /// subscribers needs more. Only applies to a context the engine creates, not to one given
+    /// with `context`, and starting fails with `EngineError::InvalidIoThreads` for less than one.
+    pub fn io_threads(mut self, io_threads: i32) -> Self {
+        self.config.io_threads = io_threads;
+        self
+    }
+
+    /// Turn on TCP keepalive for the connections of the incoming and outgoing sockets, e.g., so
+    /// that a firewall does not drop those of quiet external subscribers.
+    pub fn tcp_keepalive(mut self, keepalive: TcpKeepalive) -> Self {
+        self.config.tcp_keepalive = Some(keepalive);
+        self
+    }
+
+    /// How often a plugin waiting in `PluginContext::next_event` checks whether the engine is
+    /// shutting down (every 100 ms by default), so that it returns `PluginError::Stopped` even
+    /// if it missed the `PluginTerminateEvent`; zero never checks.
+    pub fn plugin_recv_timeout(mut self, timeout: Duration) -> Self {
+        self.config.plugin_recv_timeout = timeout;
+        self
+    }
+
+    /// Serve the engine's counters on `http://<addr>/metrics`, in the Prometheus text format;
+    /// see the `prometheus` module.
+    #[cfg(feature = "prometheus")]
+    pub fn metrics_addr(mut self, addr: &str) -> Self {
+        self.config.metrics_addr = Some(addr.to_string());
+        self
+    }
+
+    /// Bind the sockets on these transports; with `Transport::InprocOnly` starting the engine
+    /// fails if external plugins are registered or may register.
+    pub fn transport(mut self, transport: Transport) -> Self {
+        self.config.transport = transport;
+        self
+    }
+
+    /// Bind the TCP ports on the interface with address `address` (e.g., "192.168.1.10", or an
+    /// IPv6 literal such as "[::1]") instead of on the loopback interface only.
+    pub fn bind_address(mut self, address: &str) -> Self {
+        self.config.bind_address = address.to_string();
+        self
+    }
+
+    /// Bind the TCP ports on all interfaces (i.e., `tcp://*:<port>`, as the engine did before it
+    /// bound them on the loopback interface by default), so that plugins and subscribers on other
+    /// hosts can reach them; false goes back to the loopback interface.
+    pub fn expose_external(self, expose: bool) -> Self {
+        self.bind_address(if expose { "*" } else { DEFAULT_BIND_ADDRESS })
+    }
+
+    /// Stop the proxy once it has forwarded `count` events of type `event_type` (e.g., to stop
+    /// after the last image of a batch is stored), publishing the `PluginTerminateEvent` as on
+    /// shutdown; `run()` then returns, and `EngineHandle::wait_for_stop` tells when it happened.
+    pub fn stop_after(mut self, event_type: &str, count: u64) -> Self {
+        self.config.stop_after = Some((event_type.to_string(), count));
+        self
+    }
+
+    /// Stop the proxy as `stop_after` does once `predicate` returns true for an event it
+    /// forwarded, e.g., the `ImageStoredEvent` of a given image, or the last of a workload with a
+    /// count kept by the closure. Every event the engine forwards is decoded for the predicate,
+    /// in a thread of its own, in the order forwarded; with `stop_after` too, the proxy stops on
+    /// whichever condition is met first. Only the engine started by `start()` or `run()` checks
+    /// the predicate, not the async engine.
+    pub fn stop_when(mut self, predicate: impl FnMut(&Event) -> bool + Send + 'static) -> Self {
+        self.stop_when = Some(Box::new(predicate));
+        self
+    }
+
+    /// Call the consumer of `capture` with a copy of every event the proxy forwards, in a thread
+    /// of its own; see the `capture` module. Only the engine started by `start()` or `run()`
+    /// captures events, not the async engine.
+    pub fn capture(mut self, capture: CaptureConfig) -> Self {
+        self.capture = Some(capture);
+        self
+    }
+
+    /// Remove the socket files of the ipc endpoints that an engine which did not shut down left
+    /// behind, instead of failing with `EngineError::IpcPathExists`; see `Transport::Ipc`.
+    #[cfg(unix)]
+    pub fn unlink_stale_ipc(mut self, unlink: bool) -> Self {
+        self.config.unlink_stale_ipc = unlink;
+        self
+    }
+
+    /// Encrypt the traffic on the TCP (or ipc) sockets with CURVE and only let in the clients
+    /// with an allowed public key; see the `curve` module.
+    pub fn curve(mut self, server: CurveServer) -> Self {
+        self.config.curve = Some(server);
+        self
+    }
+
+    /// Append every event the proxy forwards to the segment files of an event log; see the
+    /// `event_log` module.
+    pub fn event_log(mut self, event_log: EventLogConfig) -> Self {
+        self.config.event_log = Some(event_log);
+        self
+    }
+
+    /// Remember the most recent event of each of these types (e.g., "MetricsSnapshotEvent") and
+    /// send it again whenever a subscriber to its type connects; see the `last_value_cache`
+    /// module. Starting the engine fails if one of them is not an event type.
+    pub fn last_value_cache(mut self, event_types: &[&str]) -> Self {
+        self.config.last_value_cache = event_types.iter().map(|t| t.to_string()).collect();
+        self
+    }
+
+    /// Hand every event the engine forwards to `middleware`, after the middlewares added before
+    /// it; see the `middleware` module.
+    pub fn middleware(mut self, middleware: Box<dyn Middleware>) -> Self {
+        self.middlewares.push(middleware);
+        self
+    }
+
+    /// Deliver some events at least once to some plugins, which acknowledge them; see the
+    /// `acks` module. The engine then forwards events with its own loop. Starting the engine
+    /// fails if an event type is unknown, or a plugin is not run by the engine or runs as a pool.
+    pub fn acknowledged_delivery(mut self, acks: AckConfig) -> Self {
+        self.config.acknowledged_delivery = Some(acks);
+        self
+    }
+
+    /// Record `engine_id` as the origin of the events the plugins publish (see
+    /// `EventMeta::origin_engine_id`), as external plugins connecting with the same
+    /// configuration do, for engines bridged together with a `BridgePlugin` to tell their
+    /// events apart. Defaults to 0.
+    pub fn engine_id(mut self, engine_id: u32) -> Self {
+        self.config.engine_id = engine_id;
+        self
+    }
+
+    /// Keep the proxy forwarding events for `period` after publishing the `EngineStoppingEvent`
+    /// on shutdown, for the plugins to flush what they hold and publish the events that come
+    /// of it. Defaults to 100 ms.
+    pub fn stopping_grace_period(mut self, period: Duration) -> Self {
+        self.config.stopping_grace_period = period;
+        self
+    }
+
+    /// Have the plugins paused with `EngineHandle::pause_plugin` hold or drop the events they
+    /// receive as `policy` says. Defaults to holding up to 1000 events, and dropping the rest.
+    pub fn pause_policy(mut self, policy: PausePolicy) -> Self {
+        self.config.pause_policy = policy;
+        self
+    }
+
+    /// Start only the plugins of the profile `name` (see `PluginRegistry::profile`); starting
+    /// the engine fails with `EngineError::UnknownProfile` if its plugins define none by that
+    /// name. The engine only syncs with the plugins of the profile.
+    pub fn with_profile(mut self, name: &str) -> Self {
+        self.config.profile = Some(name.to_string());
+        self
+    }
+
+    /// Forward events with the engine's own loop instead of `zmq::proxy` even without a payload
+    /// size limit, middleware, last-value cache or acknowledged delivery, which always use it.
+    pub fn forwarding_loop(mut self, forwarding_loop: bool) -> Self {
+        self.config.forwarding_loop = forwarding_loop;
+        self
+    }
+
+    /// Limit the size of the event payloads to `max_bytes` (64 MiB by default): the plugins get
+    /// `EventError::TooLarge` from `PluginContext::publish` for a bigger event, and the engine
+    /// drops the ones that other publishers, such as external plugins, send anyway, publishing
+    /// an `EventRejectedEvent` instead. Zero lifts the limit, and lets the engine forward events
+    /// with `zmq::proxy`.
+    pub fn max_payload_size(mut self, max_bytes: usize) -> Self {
+        self.config.max_payload_size = max_bytes;
+        self
+    }
+
+    /// Have the plugins compress the payloads of the events they publish as `compression` says,
+    /// as external plugins connecting with the same configuration do; see the `compression`
+    /// module.
+    pub fn compression(mut self, compression: Compression) -> Self {
+        self.config.compression = Some(compression);
+        self
+    }
+
+    /// Have the plugins serialize the events they publish with `codec`, as external plugins
+    /// connecting with the same configuration do: `Codec::Json` for external plugins that
+    /// cannot use flatbuffers. Plugins receive the events of any codec, so publishers with
+    /// different codecs can share an engine; the engine's own events are always flatbuffers.
+    pub fn codec(mut self, codec: Codec) -> Self {
+        self.config.codec = codec;
+        self
+    }
+
+    /// Run the engine sockets and plugin threads on a context owned by the host application,
+    /// so that host sockets on the same context can reach the engine over inproc.
+    pub fn context(mut self, context: zmq::Context) -> Self {
+        self.context = Some(context);
+        self
+    }
+
+    /// Start these plugins instead of the `default_plugins()`.
+    pub fn plugins(mut self, plugins: PluginRegistry) -> Self {
+        self.plugins = plugins;
+        self
+    }
+
+    pub fn config(&self) -> &EngineConfig {
+        &self.config
+    }
+
+    pub fn build(self) -> EngineConfig {
+        self.config
+    }
+
+    /// Start the engine with the configuration built so far; see `start_event_engine`.
+    pub fn start(self) -> Result<EngineHandle, EngineError> {
+        let (context, owns_context) = match self.context {
+            Some(context) => (context, false),
+            None => (zmq::Context::new(), true),
+        };
+        start_engine(
+            &self.config,
+            context,
+            owns_context,
+            self.plugins,
+            self.middlewares,
+            self.stop_when,
+            self.capture,
         )
-        .expect("could not start plugin");
     }
-    // once all plugins have been started, sync them with individual messages on the
-    // REQ-REP sockets
-    sync_plugins(context).unwrap();
-    Ok(())
+
+    /// Start the engine with the configuration built so far and block until it stops.
+    pub fn run(self) -> Result<(), EngineError> {
+        self.start()?.join()
+    }
 }
 
-pub fn event_engine() -> std::io::Result<()> {
-    println!("Starting EVENT engine");
-    // zmq context to be used by this engine and all plugin threads
-    let context = zmq::Context::new();
+/// State of a plugin started by the engine, see `EngineHandle::plugin_status`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PluginStatus {
+    /// The plugin has not synced with the engine yet.
+    Starting,
+    /// The plugin's start function is running.
+    Running,
+    /// The start function returned.
+    Finished,
+    /// The start function panicked or returned an error, described by the message.
+    Failed(String),
+}
 
-    // incoming and outgoing sockets for the engine
-    let outgoing = get_outgoing_socket(&context).expect("could not create outgoing socket");
-    let incoming = get_incoming_socket(&context).expect("could not create incoming socket");
+// Status of every plugin started by the engine, shared with the plugin threads.
+pub(crate) type PluginStatuses = Arc<Mutex<HashMap<i32, PluginStatus>>>;
 
-    // start plugins in their own thread
-    start_plugins(context).unwrap();
+// Pause state of every plugin started by the engine, shared with the plugin threads.
+pub(crate) type PluginPauses = Arc<Mutex<HashMap<i32, Arc<PauseState>>>>;
 
-    // proxy from incoming to outgoing sockets;
-    // this call blocks forever
-    println!("Engine starting main proxy");
-    let _result = zmq::proxy(&incoming, &outgoing)
-        .expect("Engine got error running proxy; socket was closed?");
+// What the threads of the plugins started by the engine share with the rest of the engine.
+struct PluginShared {
+    statuses: PluginStatuses,
+    pauses: PluginPauses,
+    // set when the engine is shutting down, so that failed plugins are not restarted
+    stopping: Arc<AtomicBool>,
+    // only set when the engine has a metrics address
+    #[cfg(feature = "prometheus")]
+    metrics: Option<Arc<EngineMetrics>>,
+}
 
-    // should never get here
-    Ok(())
+impl PluginShared {
+    // The pause state of the plugin `plugin_id`, shared by all its contexts.
+    fn pause(&self, plugin_id: i32) -> Arc<PauseState> {
+        let mut pauses = self.pauses.lock().expect("plugin pause lock poisoned");
+        Arc::clone(pauses.entry(plugin_id).or_default())
+    }
+}
+
+/// How a plugin answers the engine heartbeats, see `EngineHandle::plugin_liveness`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PluginLiveness {
+    /// The seq of the last heartbeat the plugin answered.
+    pub last_seq: u64,
+    /// Heartbeats published since that one, and not answered, before the latest.
+    pub missed_beats: u64,
+    /// Whether the plugin missed as many heartbeats in a row as the engine allows, e.g.,
+    /// because it is stuck handling an event.
+    pub unresponsive: bool,
+}
+
+impl PluginLiveness {
+    // The liveness of a plugin that last answered heartbeat `last_seq` once heartbeat `seq` has
+    // been published; the latest heartbeat does not count as missed yet.
+    fn new(last_seq: u64, seq: u64, config: &EngineConfig) -> Self {
+        let missed_beats = seq.saturating_sub(last_seq).saturating_sub(1);
+        PluginLiveness {
+            last_seq,
+            missed_beats,
+            unresponsive: missed_beats >= config.heartbeat_missed_beats,
+        }
+    }
+}
+
+// Liveness of every plugin that answered a heartbeat, shared with the heartbeat thread.
+pub(crate) type PluginLivenessMap = Arc<Mutex<HashMap<i32, PluginLiveness>>>;
+
+// A plugin thread and the id of its plugin; the thread returns what the plugin's start function
+// returned (for a restarted plugin, its last start).
+type PluginThread = (i32, JoinHandle<Result<(), PluginError>>);
+
+// Join the plugin threads that exit before `deadline`, returning their results; the threads that
+// are still running are left in `plugin_threads`.
+fn join_plugins(
+    plugin_threads: &mut Vec<PluginThread>,
+    deadline: Instant,
+) -> HashMap<i32, Result<(), PluginError>> {
+    while Instant::now() < deadline && plugin_threads.iter().any(|(_, t)| !t.is_finished()) {
+        thread::sleep(Duration::from_millis(10));
+    }
+    let (finished, running) = plugin_threads
+        .drain(..)
+        .partition::<Vec<_>, _>(|(_, t)| t.is_finished());
+    *plugin_threads = running;
+    finished
+        .into_iter()
+        .map(|(plugin_id, plugin_thread)| {
+            let result = plugin_thread
+                .join()
+                .unwrap_or_else(|payload| Err(PluginError::Other(panic_message(payload.as_ref()))));
+            (plugin_id, result)
+        })
+        .collect()
+}
+
+// Control socket and thread answering the sync messages of restarted and joining plugins.
+type Resync = (Socket, JoinHandle<()>);
+
+// The threads of the plugins started, the resync thread, if any plugin can sync later, and the
+// optional plugins left out, with why.
+type StartedPlugins = (Vec<PluginThread>, Option<Resync>, Vec<(i32, String)>);
+
+// Control socket and thread publishing the engine heartbeats.
+type Heartbeat = (Socket, JoinHandle<()>);
+
+// Control socket of the proxy, shared by the engine handle and the thread stopping the engine
+// after a number of events; either may send TERMINATE.
+struct ProxyControl {
+    socket: Socket,
+    // the proxy no longer reads the socket once it got TERMINATE
+    terminated: bool,
+}
+
+impl ProxyControl {
+    // Stop the proxy, unless that was done already. The socket must stay open until the proxy
+    // has read TERMINATE from it, or the message is lost.
+    fn terminate(&mut self) -> zmq::Result<()> {
+        if !self.terminated {
+            self.socket.send("TERMINATE", 0)?;
+            self.terminated = true;
+        }
+        Ok(())
+    }
+}
+
+/// Handle to a running engine, returned by `start_event_engine`.
+/// The proxy runs in its own thread, so the thread that started the engine is free to do other
+/// work until it calls `join()` or `shutdown()`. The handle can be shared between threads, e.g.,
+/// for all of them to `publish` events.
+pub struct EngineHandle {
+    // zmq context shared by the engine and all plugin threads
+    context: zmq::Context,
+    // false when the context was supplied by the host, in which case it is never terminated
+    owns_context: bool,
+    // configuration the engine was started with, for the host's subscriptions
+    config: EngineConfig,
+    // control socket of the steerable proxy; zmq sockets are not Sync, so the sockets of the
+    // handle are only used behind a lock
+    control: Arc<Mutex<ProxyControl>>,
+    // socket and builder for the events published with `publish`; connected to the incoming
+    // inproc endpoint like the plugins' pub sockets
+    publisher: Mutex<(Socket, FlatBufferBuilder<'static>)>,
+    proxy_thread: JoinHandle<Result<(), EngineError>>,
+    // threads of the plugins that have not been waited for yet
+    plugin_threads: Mutex<Vec<PluginThread>>,
+    plugin_statuses: PluginStatuses,
+    plugin_pauses: PluginPauses,
+    // set when the engine is shutting down, so that failed plugins are not restarted
+    stopping: Arc<AtomicBool>,
+    // only started when there are restartable plugins or external plugins may join
+    resync: Option<Mutex<Resync>>,
+    plugin_liveness: PluginLivenessMap,
+    // only started when the engine has a heartbeat interval
+    heartbeat: Option<Mutex<Heartbeat>>,
+    // only started when the engine has an admin port
+    admin: Option<Mutex<Admin>>,
+    // only started when the engine is configured for CURVE
+    zap: Option<Mutex<Zap>>,
+    // only started when the engine has a metrics address
+    #[cfg(feature = "prometheus")]
+    exporter: Option<Mutex<Exporter>>,
+    // only started when the engine has an event log
+    event_log: Option<EventLog>,
+    // only started when the engine has a capture consumer
+    capture: Option<Capture>,
+    // how many events the proxy dropped for being over the maximum payload size
+    rejected_events: Arc<AtomicU64>,
+    // counted by the forwarding loop
+    stats: Arc<ForwardingStats>,
+    // only started when the engine stops after a number of events
+    stop_thread: Option<JoinHandle<()>>,
+    // socket files of the ipc endpoints, removed on shutdown
+    #[cfg(unix)]
+    ipc_paths: Vec<PathBuf>,
+}
+
+impl EngineHandle {
+    /// The configuration the engine was started with, with the TCP ports it bound for those set
+    /// to 0.
+    pub fn config(&self) -> &EngineConfig {
+        &self.config
+    }
+
+    /// Whether the proxy is still forwarding events.
+    pub fn is_running(&self) -> bool {
+        !self.proxy_thread.is_finished()
+    }
+
+    /// Status of the plugin with the given id, if it was started by this engine; external
+    /// plugins are not tracked. A plugin that failed has also published a `PluginFailedEvent`,
+    /// and a restartable plugin goes back to `Starting` when it is restarted.
+    pub fn plugin_status(&self, plugin_id: i32) -> Option<PluginStatus> {
+        self.plugin_statuses
+            .lock()
+            .expect("plugin status lock poisoned")
+            .get(&plugin_id)
+            .cloned()
+    }
+
+    /// How the plugins answering the engine heartbeats (see `PluginContext::answer_heartbeats`)
+    /// are doing, by plugin id; plugins that never answered one are left out. Always empty
+    /// unless the engine was started with a heartbeat interval.
+    pub fn plugin_liveness(&self) -> HashMap<i32, PluginLiveness> {
+        self.plugin_liveness
+            .lock()
+            .expect("plugin liveness lock poisoned")
+            .clone()
+    }
+
+    /// Wait up to `timeout` for the proxy to stop, as it does once it has forwarded the events
+    /// set with `EventEngineBuilder::stop_after`, and return whether it has. The engine still
+    /// has to be shut down.
+    pub fn wait_for_stop(&self, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        while Instant::now() < deadline && !self.proxy_thread.is_finished() {
+            thread::sleep(Duration::from_millis(1));
+        }
+        self.proxy_thread.is_finished()
+    }
+
+    /// How many events the event log dropped because its writer did not keep up (or stopped on
+    /// an error, which is logged); always 0 for an engine without an event log.
+    pub fn event_log_dropped(&self) -> u64 {
+        self.event_log.as_ref().map_or(0, EventLog::dropped)
+    }
+
+    /// How many events the capture consumer was not called with because it did not keep up (or
+    /// had panicked); always 0 for an engine without one. See `EventEngineBuilder::capture`.
+    pub fn capture_dropped(&self) -> u64 {
+        self.capture.as_ref().map_or(0, Capture::dropped)
+    }
+
+    /// Stop delivering events to the plugin `plugin_id`, without stopping its thread: its
+    /// `next_event` holds the events it receives, or drops them, as the pause policy says (see
+    /// `EventEngineBuilder::pause_policy`), until `resume_plugin`. The PluginTerminateEvent
+    /// still comes, and a plugin answering the engine heartbeats still answers them as long as
+    /// it waits for an event. Fails with `EngineError::UnknownPluginId` unless the engine runs
+    /// the plugin.
+    pub fn pause_plugin(&self, plugin_id: i32) -> Result<(), EngineError> {
+        if !self.plugin_pause(plugin_id)?.set_paused(true) {
+            info!(plugin_id; "plugin {} paused", plugin_id);
+        }
+        Ok(())
+    }
+
+    /// Deliver events to the plugin `plugin_id` again, the events it held while it was paused
+    /// first. A plugin waiting for an event gets them within the plugin receive timeout (see
+    /// `EventEngineBuilder::plugin_recv_timeout`), or with the next event it receives.
+    pub fn resume_plugin(&self, plugin_id: i32) -> Result<(), EngineError> {
+        let pause = self.plugin_pause(plugin_id)?;
+        if pause.set_paused(false) {
+            info!(
+                plugin_id;
+                "plugin {} resumed ({} event(s) dropped while paused so far)",
+                plugin_id, pause.dropped()
+            );
+        }
+        Ok(())
+    }
+
+    /// How many events the plugin `plugin_id` dropped while it was paused, because the pause
+    /// policy drops them or it held as many as it may; always 0 for a plugin the engine does
+    /// not run.
+    pub fn paused_dropped(&self, plugin_id: i32) -> u64 {
+        self.plugin_pause(plugin_id)
+            .map_or(0, |pause| pause.dropped())
+    }
+
+    fn plugin_pause(&self, plugin_id: i32) -> Result<Arc<PauseState>, EngineError> {
+        self.plugin_pauses
+            .lock()
+            .expect("plugin pause lock poisoned")
+            .get(&plugin_id)
+            .cloned()
+            .ok_or(EngineError::UnknownPluginId { plugin_id })
+    }
+
+    /// How many events the engine dropped, publishing an `EventRejectedEvent` for each, because
+    /// their payload was over the maximum payload size.
+    pub fn rejected_events(&self) -> u64 {
+        self.rejected_events.load(Ordering::Relaxed)
+    }
+
+    /// How many events of each type the engine forwarded, how many bytes they made and when the
+    /// latest was; see the `stats` module. Always empty for an engine forwarding with
+    /// `zmq::proxy`.
+    pub fn stats(&self) -> EngineStats {
+        self.stats.snapshot()
+    }
+
+    /// Publish `event` to the plugins and external subscribers, as if it came from a plugin.
+    pub fn publish(&self, event: &Event) -> Result<(), EngineError> {
+        let mut publisher = self.publisher.lock().expect("publisher lock poisoned");
+        let (socket, bldr) = &mut *publisher;
+        event
+            .send(socket, bldr)
+            .map_err(|source| EngineError::Io(source.into()))
+    }
+
+    /// Publish the events logged in `dir` by an engine with an event log again, in their order
+    /// and with their envelopes marked as replayed, returning how many were published once the
+    /// last one has been. Records that cannot be read, e.g., one torn by a crash at the end of a
+    /// segment, are skipped with a warning. A replay does not wait for the engine: events its
+    /// high-water marks do not let through are dropped, as they would be from a plugin.
+    pub fn replay(&self, dir: impl AsRef<Path>, speed: ReplaySpeed) -> Result<u64, EngineError> {
+        let mut publisher = self.publisher.lock().expect("publisher lock poisoned");
+        let (socket, bldr) = &mut *publisher;
+        replay(socket, bldr, dir.as_ref(), speed)
+    }
+
+    /// Receive the events of the given types (e.g., "ImageStoredEvent") published from now on,
+    /// decoded. The subscription takes a moment to reach the engine, so events published right
+    /// after this returns may be missed. The events are forwarded by a thread of their own,
+    /// which exits after the `PluginTerminateEvent` or once the receiver is dropped.
+    pub fn subscribe(&self, event_types: &[&str]) -> Result<Receiver<Event>, EngineError> {
+        let sub_name = "host sub";
+        let sub_socket = create_socket(&self.context, &self.config, zmq::SUB, sub_name)?;
+        // subscribe to the terminate event too, so that the thread knows when to exit
+        let forward_terminate = event_types.contains(&"PluginTerminateEvent");
+        let max_payload_size = self.config.max_payload_size;
+        for event_type in std::iter::once(&"PluginTerminateEvent").chain(event_types) {
+            let filter_bytes = get_event_type_bytes_filter(event_type).map_err(|_| {
+                EngineError::UnknownEventType {
+                    event_type: event_type.to_string(),
+                }
+            })?;
+            sub_socket
+                .set_subscribe(&filter_bytes)
+                .map_err(|source| EngineError::Socket {
+                    socket: sub_name.to_string(),
+                    source,
+                })?;
+        }
+        connect(&sub_socket, &self.config.outgoing_endpoint())?;
+
+        let (event_tx, event_rx) = mpsc::channel();
+        thread::spawn(move || loop {
+            // this fails once the engine terminates the context
+            let frames = match recv_event_frames(&sub_socket, 0) {
+                Ok(frames) => frames,
+                Err(_) => return,
+            };
+            let (event_type, mut meta, payload) = match parse_event_messages(frames) {
+                Some(event) => event,
+                None => continue,
+            };
+            let terminate = event_type == "PluginTerminateEvent";
+            if !terminate || forward_terminate {
+                let decoded =
+                    decompressed(&mut meta, payload, max_payload_size).and_then(|payload| {
+                        Event::decode_with_meta(event_type, meta.as_ref(), &payload)
+                    });
+                match decoded {
+                    Ok(event) => {
+                        if event_tx.send(event).is_err() {
+                            return;
+                        }
+                    }
+                    Err(e) => {
+                        error!(event_type; "host subscriber could not decode {}: {}", event_type, e)
+                    }
+                }
+            }
+            if terminate {
+                return;
+            }
+        });
+        Ok(event_rx)
+    }
+
+    /// Wait up to `timeout` for the plugins started by the engine to exit, and return the result
+    /// of each plugin that did, by plugin id. A plugin fails with the error its start function
+    /// returned or the message it panicked with. Plugins that are still running are left out;
+    /// they can be waited for again, and each plugin's result is only returned once.
+    pub fn wait_for_plugins(&self, timeout: Duration) -> HashMap<i32, Result<(), PluginError>> {
+        let deadline = Instant::now() + timeout;
+        let mut plugin_threads = self.plugin_threads.lock().expect("plugin lock poisoned");
+        join_plugins(&mut plugin_threads, deadline)
+    }
+
+    /// Block until the proxy stops.
+    pub fn join(self) -> Result<(), EngineError> {
+        self.proxy_thread
+            .join()
+            .expect("Engine proxy thread panicked")
+    }
+
+    /// Stop the proxy by sending TERMINATE on its control socket and join all engine and plugin
+    /// threads. A running proxy first forwards an `EngineStoppingEvent` and keeps forwarding for
+    /// the stopping grace period (see `EventEngineBuilder::stopping_grace_period`). Once the
+    /// proxy has stopped the engine publishes a `PluginTerminateEvent`, which every plugin is
+    /// subscribed to; plugins that are still running after a grace period are stopped by
+    /// terminating the zmq context (any blocking socket call then returns an error). A context
+    /// supplied by the host is left alone; plugins still running on it after the grace period
+    /// are detached rather than joined. Plugins that fail during shutdown are not restarted.
+    pub fn shutdown(self) -> Result<(), EngineError> {
+        self.shutdown_with_reason("shutdown requested")
+    }
+
+    /// Like `shutdown`, with `reason` in the `EngineStoppingEvent`.
+    pub fn shutdown_with_reason(self, reason: &str) -> Result<(), EngineError> {
+        if !self.proxy_thread.is_finished() {
+            info!("Engine stopping: {}", reason);
+            self.publish(&Event::EngineStopping(EngineStopping {
+                reason: reason.to_string(),
+            }))?;
+            thread::sleep(self.config.stopping_grace_period);
+        }
+        let EngineHandle {
+            mut context,
+            owns_context,
+            control,
+            publisher,
+            proxy_thread,
+            plugin_threads,
+            stopping,
+            resync,
+            heartbeat,
+            admin,
+            zap,
+            #[cfg(feature = "prometheus")]
+            exporter,
+            event_log,
+            capture,
+            stop_thread,
+            #[cfg(unix)]
+            ipc_paths,
+            ..
+        } = self;
+        let mut plugin_threads = plugin_threads.into_inner().expect("plugin lock poisoned");
+        control
+            .lock()
+            .expect("control lock poisoned")
+            .terminate()
+            .map_err(|source| EngineError::Shutdown { source })?;
+        let result = proxy_thread.join().expect("Engine proxy thread panicked");
+        // only once the proxy has published the PluginTerminateEvent, for the plugins waiting
+        // on their sub socket to get it rather than `PluginError::Stopped`
+        stopping.store(true, Ordering::SeqCst);
+        // give the plugins a chance to process the terminate event and return
+        join_plugins(
+            &mut plugin_threads,
+            Instant::now() + PLUGIN_EXIT_GRACE_PERIOD,
+        );
+        if let Some(resync) = resync {
+            let (resync_control, resync_thread) =
+                resync.into_inner().expect("resync lock poisoned");
+            resync_control
+                .send("TERMINATE", 0)
+                .map_err(|source| EngineError::Shutdown { source })?;
+            resync_thread.join().expect("Engine resync thread panicked");
+        }
+        if let Some(heartbeat) = heartbeat {
+            let (heartbeat_control, heartbeat_thread) =
+                heartbeat.into_inner().expect("heartbeat lock poisoned");
+            heartbeat_control
+                .send("TERMINATE", 0)
+                .map_err(|source| EngineError::Shutdown { source })?;
+            heartbeat_thread
+                .join()
+                .expect("Engine heartbeat thread panicked");
+        }
+        if let Some(admin) = admin {
+            let (admin_control, admin_thread) = admin.into_inner().expect("admin lock poisoned");
+            admin_control
+                .send("TERMINATE", 0)
+                .map_err(|source| EngineError::Shutdown { source })?;
+            admin_thread.join().expect("Engine admin thread panicked");
+        }
+        if let Some(zap) = zap {
+            let (zap_control, zap_thread) = zap.into_inner().expect("ZAP lock poisoned");
+            zap_control
+                .send("TERMINATE", 0)
+                .map_err(|source| EngineError::Shutdown { source })?;
+            zap_thread.join().expect("Engine ZAP thread panicked");
+        }
+        #[cfg(feature = "prometheus")]
+        if let Some(exporter) = exporter {
+            let (exporter_control, exporter_thread) =
+                exporter.into_inner().expect("exporter lock poisoned");
+            exporter_control
+                .send("TERMINATE", 0)
+                .map_err(|source| EngineError::Shutdown { source })?;
+            exporter_thread
+                .join()
+                .expect("Engine metrics exporter thread panicked");
+        }
+        if let Some(event_log) = event_log {
+            event_log.stop()?;
+        }
+        if let Some(capture) = capture {
+            capture.stop()?;
+        }
+        // every socket must be closed before the context can be terminated
+        drop(control);
+        drop(publisher);
+        if !owns_context {
+            #[cfg(unix)]
+            remove_ipc_files(&ipc_paths);
+            return result;
+        }
+        context
+            .destroy()
+            .map_err(|source| EngineError::Shutdown { source })?;
+        #[cfg(unix)]
+        remove_ipc_files(&ipc_paths);
+        // the stop thread exits on the PluginTerminateEvent, or on the terminated context
+        if let Some(stop_thread) = stop_thread {
+            stop_thread.join().expect("Engine stop thread panicked");
+        }
+        for (_, plugin_thread) in plugin_threads {
+            // plugins that were blocked on a socket when the context was terminated fail; that
+            // still means their thread has exited.
+            let _ = plugin_thread.join();
+        }
+        result
+    }
+}
+
+// Create a socket of the given type that lingers for the `socket_linger` of `config` once it is
+// closed; `name` identifies the socket in the error.
+pub(crate) fn create_socket(
+    context: &zmq::Context,
+    config: &EngineConfig,
+    socket_type: zmq::SocketType,
+    name: &str,
+) -> Result<Socket, EngineError> {
+    let socket = context
+        .socket(socket_type)
+        .map_err(|source| EngineError::SocketCreation {
+            socket: name.to_string(),
+            source,
+        })?;
+    socket
+        .set_linger(config.socket_linger.as_millis() as i32)
+        .map_err(|source| EngineError::Socket {
+            socket: name.to_string(),
+            source,
+        })?;
+    Ok(socket)
+}
+
+pub(crate) fn bind(socket: &Socket, endpoint: &str) -> Result<(), EngineError> {
+    set_ipv6(socket, endpoint)?;
+    socket.bind(endpoint).map_err(|source| EngineError::Bind {
+        endpoint: endpoint.to_string(),
+        source,
+    })
+}
+
+// Bind `socket` on the endpoint `name` of the engine for other processes, if it has one,
+// returning the port bound: the one the system picked if `port` is 0.
+fn bind_external(
+    socket: &Socket,
+    config: &EngineConfig,
+    name: &str,
+    port: u16,
+) -> Result<u16, EngineError> {
+    match config.external_endpoint(name, port) {
+        Some(endpoint) => {
+            bind(socket, &endpoint)?;
+            bound_port(socket, &endpoint, port)
+        }
+        None => Ok(port),
+    }
+}
+
+// The port `socket` was just bound on at `endpoint`: `port`, unless that is 0 on a TCP endpoint,
+// for which the system picked one.
+fn bound_port(socket: &Socket, endpoint: &str, port: u16) -> Result<u16, EngineError> {
+    if port != 0 || !endpoint.starts_with("tcp://") {
+        return Ok(port);
+    }
+    let bind_error = |source| EngineError::Bind {
+        endpoint: endpoint.to_string(),
+        source,
+    };
+    // e.g., "tcp://127.0.0.1:41235"
+    let last_endpoint = socket.get_last_endpoint().map_err(bind_error)?;
+    last_endpoint
+        .ok()
+        .and_then(|last_endpoint| last_endpoint.rsplit(':').next()?.parse().ok())
+        .ok_or(bind_error(zmq::Error::EINVAL))
+}
+
+pub(crate) fn connect(socket: &Socket, endpoint: &str) -> Result<(), EngineError> {
+    set_ipv6(socket, endpoint)?;
+    socket
+        .connect(endpoint)
+        .map_err(|source| EngineError::Connect {
+            endpoint: endpoint.to_string(),
+            source,
+        })
+}
+
+// zmq sockets only take IPv6 endpoints, e.g., "tcp://[::1]:5559", once told to.
+fn set_ipv6(socket: &Socket, endpoint: &str) -> Result<(), EngineError> {
+    if !endpoint.starts_with("tcp://[") {
+        return Ok(());
+    }
+    socket.set_ipv6(true).map_err(|source| EngineError::Socket {
+        socket: endpoint.to_string(),
+        source,
+    })
+}
+
+pub(crate) fn get_outgoing_socket(
+    context: &zmq::Context,
+    config: &mut EngineConfig,
+) -> Result<Socket, EngineError> {
+    // an XPUB socket passes the subscriptions on to the proxy, which answers them from the cache
+    if config.last_value_cache.is_empty() {
+        let outgoing = create_socket(context, config, zmq::PUB, "outgoing")?;
+        return bind_outgoing_socket(outgoing, config);
+    }
+    let outgoing = create_socket(context, config, zmq::XPUB, "outgoing")?;
+    // every subscription, not only the first to a type, is to get the cached event
+    outgoing
+        .set_xpub_verbose(true)
+        .map_err(|source| EngineError::Socket {
+            socket: "outgoing".to_string(),
+            source,
+        })?;
+    bind_outgoing_socket(outgoing, config)
+}
+
+fn bind_outgoing_socket(
+    outgoing: Socket,
+    config: &mut EngineConfig,
+) -> Result<Socket, EngineError> {
+    set_hwm(&outgoing, "outgoing", config.outgoing_hwm)?;
+    set_tcp_keepalive(&outgoing, "outgoing", config.tcp_keepalive)?;
+    if let Some(server) = &config.curve {
+        make_server(&outgoing, "outgoing", server)?;
+    }
+    config.outgoing_port = bind_external(&outgoing, config, "outgoing", config.outgoing_port)?;
+    if config.transport != Transport::Tcp {
+        bind(&outgoing, &config.outgoing_inproc_endpoint())?;
+    }
+    Ok(outgoing)
+}
+
+// The socket the proxy sends a copy of every event it forwards to, if the metrics exporter, the
+// event log or the capture consumer (when `consumed`) reads them. A PUB socket drops the copies
+// they do not keep up with instead of blocking the proxy.
+fn get_capture_socket(
+    context: &zmq::Context,
+    config: &EngineConfig,
+    consumed: bool,
+) -> Result<Option<Socket>, EngineError> {
+    #[cfg(feature = "prometheus")]
+    let exporting = config.metrics_addr.is_some();
+    #[cfg(not(feature = "prometheus"))]
+    let exporting = false;
+    if !exporting && config.event_log.is_none() && !consumed {
+        return Ok(None);
+    }
+    let capture = create_socket(context, config, zmq::PUB, "capture")?;
+    bind(&capture, &capture_endpoint(config))?;
+    Ok(Some(capture))
+}
+
+fn capture_endpoint(config: &EngineConfig) -> String {
+    format!("inproc://{}-capture", config.outgoing_inproc)
+}
+
+// A socket receiving every event the proxy captures, named `name` in errors; connected before
+// the proxy starts.
+pub(crate) fn subscribe_to_capture(
+    context: &zmq::Context,
+    config: &EngineConfig,
+    name: &str,
+) -> Result<Socket, EngineError> {
+    let captured = create_socket(context, config, zmq::SUB, name)?;
+    captured
+        .set_subscribe(b"")
+        .map_err(|source| EngineError::Socket {
+            socket: name.to_string(),
+            source,
+        })?;
+    connect(&captured, &capture_endpoint(config))?;
+    Ok(captured)
+}
+
+pub(crate) fn get_incoming_socket(
+    context: &zmq::Context,
+    config: &mut EngineConfig,
+) -> Result<Socket, EngineError> {
+    let incoming = create_socket(context, config, zmq::SUB, "incoming")?;
+    set_hwm(&incoming, "incoming", config.incoming_hwm)?;
+    set_tcp_keepalive(&incoming, "incoming", config.tcp_keepalive)?;
+    if let Some(server) = &config.curve {
+        make_server(&incoming, "incoming", server)?;
+    }
+    config.incoming_port = bind_external(&incoming, config, "incoming", config.incoming_port)?;
+    if config.transport != Transport::Tcp {
+        bind(&incoming, &config.incoming_inproc_endpoint())?;
+    }
+    // subscribe to all events
+    let filter = String::new();
+    incoming
+        .set_subscribe(filter.as_bytes())
+        .map_err(|source| EngineError::Socket {
+            socket: "incoming".to_string(),
+            source,
+        })?;
+    Ok(incoming)
+}
+
+// Set the high-water marks of `socket`, named `name` in errors. They only apply to the
+// connections made afterwards, so this is called before the socket is bound or connected.
+fn set_hwm(socket: &Socket, name: &str, hwm: Hwm) -> Result<(), EngineError> {
+    let socket_error = |source| EngineError::Socket {
+        socket: name.to_string(),
+        source,
+    };
+    if let Some(send) = hwm.send {
+        socket.set_sndhwm(send).map_err(socket_error)?;
+    }
+    if let Some(receive) = hwm.receive {
+        socket.set_rcvhwm(receive).map_err(socket_error)?;
+    }
+    Ok(())
+}
+
+// Turn on the TCP keepalive of `socket`, named `name` in errors, unless `keepalive` is None; like
+// the high-water marks, before the socket is bound.
+fn set_tcp_keepalive(
+    socket: &Socket,
+    name: &str,
+    keepalive: Option<TcpKeepalive>,
+) -> Result<(), EngineError> {
+    let Some(keepalive) = keepalive else {
+        return Ok(());
+    };
+    let socket_error = |source| EngineError::Socket {
+        socket: name.to_string(),
+        source,
+    };
+    socket.set_tcp_keepalive(1).map_err(socket_error)?;
+    if let Some(idle) = keepalive.idle {
+        socket
+            .set_tcp_keepalive_idle(idle.as_secs() as i32)
+            .map_err(socket_error)?;
+    }
+    if let Some(interval) = keepalive.interval {
+        socket
+            .set_tcp_keepalive_intvl(interval.as_secs() as i32)
+            .map_err(socket_error)?;
+    }
+    if let Some(count) = keepalive.count {
+        socket.set_tcp_keepalive_cnt(count).map_err(socket_error)?;
+    }
+    Ok(())
+}
+
+// Size the I/O thread pool of `context` as `config` says if the engine created it (`owns`); the
+// threads start with the first socket, so this comes before any is created.
+pub(crate) fn set_io_threads(
+    context: &zmq::Context,
+    config: &EngineConfig,
+    owns: bool,
+) -> Result<(), EngineError> {
+    let invalid = || EngineError::InvalidIoThreads {
+        io_threads: config.io_threads,
+    };
+    if config.io_threads < 1 {
+        return Err(invalid());
+    }
+    if owns {
+        context
+            .set_io_threads(config.io_threads)
+            .map_err(|_| invalid())?;
+    }
+    Ok(())
+}
+
+// The message a plugin panicked with.
+pub(crate) fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "plugin panicked".to_string()
+    }
+}
+
+// Sockets a plugin syncs with the engine and runs on; a restarted plugin gets new ones.
+pub(crate) struct PluginSockets {
+    // socket the plugin publishes new events on
+    pub(crate) pub_socket: Socket,
+    // socket the plugin's subscribed events arrive on
+    pub(crate) sub_socket: Socket,
+    // socket the plugin syncs with the engine on
+    pub(crate) sync: Socket,
+    // the back-channel the plugin acknowledges the events delivered at least once on, if any
+    pub(crate) acks: Option<AckChannel>,
+}
+
+pub(crate) fn create_plugin_sockets(
+    ctx: &zmq::Context,
+    config: &EngineConfig,
+    plugin_id: i32,
+    subscriptions: &[&str],
+    hwm: Hwm,
+) -> Result<PluginSockets, EngineError> {
+    // Create the socket that plugin will use to publish new events
+    let pub_name = format!("plugin {} pub", plugin_id);
+    let pub_socket = create_socket(ctx, config, zmq::PUB, &pub_name)?;
+    set_hwm(&pub_socket, &pub_name, hwm)?;
+    connect(&pub_socket, &config.incoming_endpoint())?;
+    debug!(plugin_id; "plugin {} connected to pub socket.", plugin_id);
+
+    // Create the socket that plugin will use to subscribe to events
+    let sub_name = format!("plugin {} sub", plugin_id);
+    let sub_socket = create_socket(ctx, config, zmq::SUB, &sub_name)?;
+    set_hwm(&sub_socket, &sub_name, hwm)?;
+    // wake the plugin now and then, so that it sees the engine shutting down
+    if !config.plugin_recv_timeout.is_zero() {
+        sub_socket
+            .set_rcvtimeo(config.plugin_recv_timeout.as_millis() as i32)
+            .map_err(|source| EngineError::Socket {
+                socket: sub_name.clone(),
+                source,
+            })?;
+    }
+    connect(&sub_socket, &config.outgoing_endpoint())?;
+    // Subscribe only to events of interest, plus the terminate event every plugin must handle and,
+    // last, the probes the engine publishes while the plugin syncs: once one arrives, so have the
+    // other subscriptions
+    let probe = std::iter::once("SyncProbeEvent");
+    for sub in std::iter::once("PluginTerminateEvent")
+        .chain(subscribed(subscriptions))
+        .chain(probe)
+    {
+        let filter_bytes =
+            subscription_filter(config, sub).ok_or_else(|| EngineError::UnknownSubscriptions {
+                subscriptions: UnknownSubscription::find(plugin_id, &[sub]),
+            })?;
+        sub_socket
+            .set_subscribe(&filter_bytes)
+            .map_err(|source| EngineError::Socket {
+                socket: sub_name.clone(),
+                source,
+            })?;
+    }
+
+    // Create the sync socket that plugin will use to sync with engine and other plugins
+    let sync = create_socket(ctx, config, zmq::REQ, &format!("plugin {} sync", plugin_id))?;
+    connect(&sync, &config.sync_endpoint())?;
+    debug!(plugin_id; "plugin {} connected to sync socket.", plugin_id);
+
+    let acks = AckChannel::connect(ctx, config, plugin_id)?;
+
+    Ok(PluginSockets {
+        pub_socket,
+        sub_socket,
+        sync,
+        acks,
+    })
+}
+
+// Announce the plugin on its sync socket and wait for the engine's reply, confirming the probe
+// the engine publishes to `sub` first if it asks for one (see `SYNC_PROBE`), for at most
+// `timeout`; anything but "ok" means the plugin must not start.
+pub(crate) fn sync_with_engine(
+    plugin_id: i32,
+    sync: &Socket,
+    sub: &Socket,
+    timeout: Duration,
+) -> bool {
+    let msg = format!("{} {} {}", SYNC_READY, plugin_id, SYNC_PROBE);
+    if let Err(e) = retry_on_eintr(|| sync.send(msg.as_str(), 0)) {
+        error!(plugin_id; "plugin {} could not send sync message: {}", plugin_id, e);
+        return false;
+    }
+    debug!(plugin_id; "plugin {} sent sync message.", plugin_id);
+    let mut reply = retry_on_eintr(|| sync.recv_msg(0));
+    if matches!(&reply, Ok(reply) if reply.as_str() == Some(SYNC_PROBE)) {
+        if let Err(e) = confirm_probe(plugin_id, sub, Instant::now() + timeout) {
+            error!(plugin_id; "plugin {} got no probe from the engine: {}", plugin_id, e);
+            return false;
+        }
+        let msg = format!("{} {}", SYNC_PROBED, plugin_id);
+        if let Err(e) = retry_on_eintr(|| sync.send(msg.as_str(), 0)) {
+            error!(plugin_id; "plugin {} could not confirm the probe: {}", plugin_id, e);
+            return false;
+        }
+        debug!(plugin_id; "plugin {} confirmed the probe.", plugin_id);
+        reply = retry_on_eintr(|| sync.recv_msg(0));
+    }
+    match reply {
+        Ok(reply) if reply.as_str() == Some("ok") => {
+            info!(plugin_id; "plugin {} got sync reply, will now block for messages", plugin_id);
+            true
+        }
+        Ok(reply) => {
+            error!(plugin_id; "plugin {} got sync reply {:?}, exiting", plugin_id, reply.as_str());
+            false
+        }
+        Err(e) => {
+            error!(plugin_id; "plugin {} got error trying to receive sync reply: {}", plugin_id, e);
+            false
+        }
+    }
+}
+
+// Wait for a SyncProbeEvent on `sub` until `deadline`, dropping whatever else comes first, and
+// unsubscribe from those once one has come: the plugin is not to see them again, but for those
+// already on their way, which its context skips.
+pub(crate) fn confirm_probe(plugin_id: i32, sub: &Socket, deadline: Instant) -> zmq::Result<()> {
+    let probe = event_type_header("SyncProbeEvent");
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(zmq::Error::EAGAIN);
+        }
+        let mut items = [sub.as_poll_item(zmq::POLLIN)];
+        if retry_on_eintr(|| zmq::poll(&mut items, remaining.as_millis() as i64))? == 0 {
+            continue;
+        }
+        let frames = match recv_event_frames(sub, zmq::DONTWAIT) {
+            Ok(frames) => frames,
+            Err(zmq::Error::EAGAIN) => continue,
+            Err(e) => return Err(e),
+        };
+        if frames[0].starts_with(&probe) {
+            debug!(plugin_id; "plugin {} got a probe from the engine", plugin_id);
+            return sub.set_unsubscribe(&probe);
+        }
+    }
+}
+
+// Run the plugin's start function; a panic is returned as an error with the panic message.
+pub(crate) fn run_plugin(plugin: Box<dyn Plugin>, ctx: PluginContext) -> Result<(), PluginError> {
+    let plugin_id = plugin.id();
+    let name = plugin.name().to_string();
+    info!(plugin_id; "Executing start function for plugin {} ({})", plugin_id, name);
+    // a panicking plugin must not take the thread down without a trace
+    let error = match panic::catch_unwind(AssertUnwindSafe(|| plugin.start(ctx))) {
+        Ok(Ok(())) => return Ok(()),
+        // the plugin was still waiting for an event when the engine shut down
+        Ok(Err(PluginError::Stopped)) => {
+            info!(plugin_id; "plugin {} ({}) stopped with the engine", plugin_id, name);
+            return Ok(());
+        }
+        Ok(Err(e)) => e,
+        Err(payload) => PluginError::Other(panic_message(payload.as_ref())),
+    };
+    error!(
+        plugin_id;
+        "got error executing start function of plugin {} ({}): {}",
+        plugin_id, name, error
+    );
+    Err(error)
+}
+
+// The builder of a thread running a plugin, named `name` and with the stack size the plugin was
+// registered with, if any.
+fn plugin_thread_builder(name: String, stack_size: Option<usize>) -> thread::Builder {
+    let builder = thread::Builder::new().name(name);
+    match stack_size {
+        Some(bytes) => builder.stack_size(bytes),
+        None => builder,
+    }
+}
+
+fn start_plugin(
+    ctx: &zmq::Context,
+    config: &EngineConfig,
+    plugin_config: PluginConfig,
+    shared: &PluginShared,
+) -> Result<PluginThread, EngineError> {
+    let PluginConfig {
+        mut plugin,
+        factory,
+        restart_policy,
+        hwm,
+        stack_size,
+        settings,
+        ..
+    } = plugin_config;
+    let plugin_id = plugin.id();
+    plugin
+        .configure(&settings)
+        .map_err(|source| EngineError::PluginSettings { plugin_id, source })?;
+    let mut sockets =
+        create_plugin_sockets(ctx, config, plugin_id, plugin.subscriptions(), hwm)?;
+
+    // Create the socket the engine publishes the PluginFailedEvent and PluginRestartedEvent of
+    // the plugin on; it is connected now so that it is ready by the time the plugin has synced
+    let engine_socket = create_socket(
+        ctx,
+        config,
+        zmq::PUB,
+        &format!("plugin {} engine", plugin_id),
+    )?;
+    connect(&engine_socket, &config.incoming_endpoint())?;
+
+    let statuses = Arc::clone(&shared.statuses);
+    let set_status = move |status| {
+        statuses
+            .lock()
+            .expect("plugin status lock poisoned")
+            .insert(plugin_id, status);
+    };
+    set_status(PluginStatus::Starting);
+
+    let ctx = ctx.clone();
+    let config = config.clone();
+    let stopping = Arc::clone(&shared.stopping);
+    let pause = shared.pause(plugin_id);
+    #[cfg(feature = "prometheus")]
+    let counters = shared.metrics.as_ref().and_then(|m| m.plugin(plugin_id));
+    // start the plugin thread
+    let plugin_thread = plugin_thread_builder(plugin.name().to_string(), stack_size)
+        .spawn(move || {
+            let mut bldr = FlatBufferBuilder::new();
+            let mut restart_count = 0;
+            loop {
+                let synced = sync_with_engine(
+                    plugin_id,
+                    &sockets.sync,
+                    &sockets.sub_socket,
+                    config.sync_timeout,
+                );
+                if !synced {
+                    return Err(PluginError::Other(format!(
+                        "plugin {} did not sync with the engine",
+                        plugin_id
+                    )));
+                }
+                // the sync socket is not needed once the plugin has synced
+                let PluginSockets {
+                    pub_socket,
+                    sub_socket,
+                    acks,
+                    ..
+                } = sockets;
+                if restart_count > 0 {
+                    let data = make_plugin_restarted_msg(&mut bldr, plugin_id, restart_count)
+                        .expect("could not build plugin restarted event");
+                    if let Err(e) = send_event_msg(&engine_socket, "PluginRestartedEvent", data) {
+                        error!(
+                            plugin_id;
+                            "could not publish restart of plugin {}: {}",
+                            plugin_id, e
+                        );
+                    }
+                }
+
+                set_status(PluginStatus::Running);
+                let mut plugin_ctx = PluginContext::new(plugin_id, pub_socket, sub_socket)
+                    .with_stopping(Arc::clone(&stopping))
+                    .with_subscriptions(plugin.subscriptions())
+                    .with_acks(acks)
+                    .with_pause(Arc::clone(&pause), config.pause_policy);
+                plugin_ctx.set_max_payload_size(config.max_payload_size);
+                plugin_ctx.set_compression(config.compression);
+                plugin_ctx.set_codec(config.codec);
+                plugin_ctx.set_engine_id(config.engine_id);
+                plugin_ctx.set_settings(settings.clone());
+                #[cfg(feature = "prometheus")]
+                let plugin_ctx = plugin_ctx.with_counters(counters.clone());
+                let error = match run_plugin(plugin, plugin_ctx) {
+                    Ok(()) => {
+                        set_status(PluginStatus::Finished);
+                        return Ok(());
+                    }
+                    Err(error) => error,
+                };
+                let message = error.to_string();
+                set_status(PluginStatus::Failed(message.clone()));
+                let data = make_plugin_failed_msg(&mut bldr, plugin_id, &message)
+                    .expect("could not build plugin failed event");
+                // this fails when the plugin failed because the engine terminated the context
+                if let Err(e) = send_event_msg(&engine_socket, "PluginFailedEvent", data) {
+                    error!(plugin_id; "could not publish failure of plugin {}: {}", plugin_id, e);
+                }
+
+                // restart the plugin, unless it has used up its restarts or the engine is
+                // shutting down
+                let (factory, backoff) = match (&factory, restart_policy) {
+                    (
+                        Some(factory),
+                        RestartPolicy::Always {
+                            max_retries,
+                            backoff,
+                        },
+                    ) if restart_count < max_retries => (factory, backoff),
+                    _ => return Err(error),
+                };
+                thread::sleep(backoff);
+                if stopping.load(Ordering::SeqCst) {
+                    return Err(error);
+                }
+                restart_count += 1;
+                #[cfg(feature = "prometheus")]
+                if let Some(counters) = &counters {
+                    counters.restarts.fetch_add(1, Ordering::Relaxed);
+                }
+                warn!(plugin_id; "restarting plugin {} (restart {})", plugin_id, restart_count);
+                plugin = factory();
+                if let Err(e) = plugin.configure(&settings) {
+                    error!(plugin_id; "could not restart plugin {}: {}", plugin_id, e);
+                    return Err(e.into());
+                }
+                let subscriptions = plugin.subscriptions();
+                sockets = match create_plugin_sockets(&ctx, &config, plugin_id, subscriptions, hwm) {
+                    Ok(sockets) => sockets,
+                    Err(e) => {
+                        error!(plugin_id; "could not restart plugin {}: {}", plugin_id, e);
+                        return Err(PluginError::Other(e.to_string()));
+                    }
+                };
+                set_status(PluginStatus::Starting);
+            }
+        })
+        .map_err(|source| EngineError::PluginSpawn {
+            plugin_id,
+            reason: source.to_string(),
+        })?;
+
+    Ok((plugin_id, plugin_thread))
+}
+
+// Run the workers of a pooled plugin (see `PluginRegistry::register_pool`), each in a thread of
+// its own with its own sockets and builder, behind a distribution stage: the pool's thread is
+// subscribed to the plugin's events and pushes each one to a single worker, taking the workers
+// in turn and skipping those whose queue is full, and the PluginTerminateEvent to every worker.
+// The workers publish on the incoming endpoint like any plugin. The pool syncs with the engine
+// once every worker is up, and its thread returns once they all have, with the first error.
+fn start_pool(
+    ctx: &zmq::Context,
+    config: &EngineConfig,
+    plugin_config: PluginConfig,
+    shared: &PluginShared,
+) -> Result<PluginThread, EngineError> {
+    let PluginConfig {
+        plugin,
+        factory,
+        workers,
+        hwm,
+        stack_size,
+        settings,
+        ..
+    } = plugin_config;
+    let plugin_id = plugin.id();
+    let name = plugin.name().to_string();
+    let factory = factory.expect("pooled plugins are registered with a factory");
+    let mut plugins = vec![plugin];
+    plugins.extend((1..workers).map(|_| factory()));
+    for plugin in plugins.iter_mut() {
+        plugin
+            .configure(&settings)
+            .map_err(|source| EngineError::PluginSettings { plugin_id, source })?;
+    }
+    let plugin = &plugins[0];
+    let PluginSockets {
+        sub_socket, sync, ..
+    } = create_plugin_sockets(ctx, config, plugin_id, plugin.subscriptions(), hwm)?;
+    let engine_socket = create_socket(
+        ctx,
+        config,
+        zmq::PUB,
+        &format!("plugin {} engine", plugin_id),
+    )?;
+    connect(&engine_socket, &config.incoming_endpoint())?;
+
+    // each worker pulls its events from a push socket of its own, bound before the worker
+    // connects to it
+    let mut pushes = Vec::with_capacity(workers);
+    let mut worker_sockets = Vec::with_capacity(workers);
+    for worker in 0..workers {
+        let endpoint = format!(
+            "inproc://{}-pool-{}-{}",
+            config.outgoing_inproc, plugin_id, worker
+        );
+        let push_name = format!("plugin {} worker {} push", plugin_id, worker);
+        let push = create_socket(ctx, config, zmq::PUSH, &push_name)?;
+        set_hwm(&push, &push_name, hwm)?;
+        bind(&push, &endpoint)?;
+        let pull_name = format!("plugin {} worker {} pull", plugin_id, worker);
+        let pull = create_socket(ctx, config, zmq::PULL, &pull_name)?;
+        set_hwm(&pull, &pull_name, hwm)?;
+        if !config.plugin_recv_timeout.is_zero() {
+            pull.set_rcvtimeo(config.plugin_recv_timeout.as_millis() as i32)
+                .map_err(|source| EngineError::Socket {
+                    socket: pull_name,
+                    source,
+                })?;
+        }
+        connect(&pull, &endpoint)?;
+        let pub_name = format!("plugin {} worker {} pub", plugin_id, worker);
+        let pub_socket = create_socket(ctx, config, zmq::PUB, &pub_name)?;
+        set_hwm(&pub_socket, &pub_name, hwm)?;
+        connect(&pub_socket, &config.incoming_endpoint())?;
+        pushes.push(push);
+        worker_sockets.push((pub_socket, pull));
+    }
+
+    let statuses = Arc::clone(&shared.statuses);
+    let set_status = move |status| {
+        statuses
+            .lock()
+            .expect("plugin status lock poisoned")
+            .insert(plugin_id, status);
+    };
+    set_status(PluginStatus::Starting);
+    let config = config.clone();
+    let stopping = Arc::clone(&shared.stopping);
+    let pause = shared.pause(plugin_id);
+    #[cfg(feature = "prometheus")]
+    let counters = shared.metrics.as_ref().and_then(|m| m.plugin(plugin_id));
+    let pool_thread = thread::Builder::new()
+        .name(name.clone())
+        .spawn(move || {
+            // the workers wait for the pool to sync before they start
+            let (ready_tx, ready_rx) = mpsc::channel();
+            let mut starts = Vec::with_capacity(workers);
+            let mut worker_threads = Vec::with_capacity(workers);
+            for (worker, (plugin, (pub_socket, pull))) in
+                plugins.into_iter().zip(worker_sockets).enumerate()
+            {
+                let (start_tx, start_rx) = mpsc::channel::<bool>();
+                starts.push(start_tx);
+                let ready_tx = ready_tx.clone();
+                let mut plugin_ctx = PluginContext::new(plugin_id, pub_socket, pull)
+                    .with_stopping(Arc::clone(&stopping))
+                    .with_subscriptions(plugin.subscriptions())
+                    .without_gap_detection()
+                    .with_pause(Arc::clone(&pause), config.pause_policy);
+                plugin_ctx.set_max_payload_size(config.max_payload_size);
+                plugin_ctx.set_compression(config.compression);
+                plugin_ctx.set_codec(config.codec);
+                plugin_ctx.set_engine_id(config.engine_id);
+                plugin_ctx.set_settings(settings.clone());
+                #[cfg(feature = "prometheus")]
+                let plugin_ctx = plugin_ctx.with_counters(counters.clone());
+                let worker_name = format!("{} worker {}", name, worker);
+                let worker_thread = plugin_thread_builder(worker_name, stack_size)
+                    .spawn(move || {
+                        let _ = ready_tx.send(());
+                        match start_rx.recv() {
+                            Ok(true) => run_plugin(plugin, plugin_ctx),
+                            _ => Ok(()),
+                        }
+                    })
+                    .map_err(|e| PluginError::Other(e.to_string()))?;
+                worker_threads.push(worker_thread);
+            }
+            drop(ready_tx);
+            let all_ready = (0..workers).all(|_| ready_rx.recv().is_ok());
+            let synced = all_ready
+                && sync_with_engine(plugin_id, &sync, &sub_socket, config.sync_timeout);
+            for start in &starts {
+                let _ = start.send(synced);
+            }
+            drop(sync);
+            if !synced {
+                return Err(PluginError::Other(format!(
+                    "plugin {} did not sync with the engine",
+                    plugin_id
+                )));
+            }
+            set_status(PluginStatus::Running);
+            distribute_events(&sub_socket, &pushes, &worker_threads, &stopping);
+            // the workers that are left get the events pushed to them before they exit
+            drop(sub_socket);
+            let results: Vec<_> = worker_threads
+                .into_iter()
+                .map(|worker_thread| {
+                    worker_thread.join().unwrap_or_else(|payload| {
+                        Err(PluginError::Other(panic_message(payload.as_ref())))
+                    })
+                })
+                .collect();
+            drop(pushes);
+            match results.into_iter().find_map(Result::err) {
+                None => {
+                    set_status(PluginStatus::Finished);
+                    Ok(())
+                }
+                Some(error) => {
+                    let message = error.to_string();
+                    set_status(PluginStatus::Failed(message.clone()));
+                    let mut bldr = FlatBufferBuilder::new();
+                    let data = make_plugin_failed_msg(&mut bldr, plugin_id, &message)
+                        .expect("could not build plugin failed event");
+                    if let Err(e) = send_event_msg(&engine_socket, "PluginFailedEvent", data) {
+                        error!(plugin_id; "could not publish failure of plugin {}: {}", plugin_id, e);
+                    }
+                    Err(error)
+                }
+            }
+        })
+        .map_err(|source| EngineError::PluginSpawn {
+            plugin_id,
+            reason: source.to_string(),
+        })?;
+    Ok((plugin_id, pool_thread))
+}
+
+// Push each event received on `sub_socket` to one of the workers' `pushes`, in turn, until the
+// PluginTerminateEvent, which goes to every worker, the engine is stopping or every worker has
+// returned.
+fn distribute_events(
+    sub_socket: &Socket,
+    pushes: &[Socket],
+    worker_threads: &[JoinHandle<Result<(), PluginError>>],
+    stopping: &AtomicBool,
+) {
+    let terminate = get_event_type_bytes_filter("PluginTerminateEvent")
+        .expect("PluginTerminateEvent is an event type");
+    let mut next = 0;
+    loop {
+        let frames = match recv_event_frames(sub_socket, 0) {
+            Ok(frames) => frames,
+            Err(zmq::Error::EAGAIN) => {
+                if stopping.load(Ordering::SeqCst) || worker_threads.iter().all(|t| t.is_finished())
+                {
+                    return;
+                }
+                continue;
+            }
+            // the context was terminated
+            Err(_) => return,
+        };
+        if frames[0].starts_with(&terminate) {
+            for push in pushes {
+                let frames = frames.iter().map(|frame| frame.to_vec());
+                let _ = push.send_multipart(frames, 0);
+            }
+            return;
+        }
+        // the first worker from `next` on that is still running and has room for the event, or
+        // else the first one still running, once it has room
+        let running: Vec<usize> = (0..pushes.len())
+            .map(|i| (next + i) % pushes.len())
+            .filter(|&worker| !worker_threads[worker].is_finished())
+            .collect();
+        let worker = running.iter().copied().find(|&worker| {
+            pushes[worker]
+                .get_events()
+                .is_ok_and(|events| events.contains(zmq::POLLOUT))
+        });
+        let Some(worker) = worker.or(running.first().copied()) else {
+            return;
+        };
+        if pushes[worker].send_multipart(frames, 0).is_err() {
+            return;
+        }
+        next = (worker + 1) % pushes.len();
+    }
+}
+
+// Plugins announce themselves on the sync socket with "ready <plugin_id>"; external plugins add
+// the schema versions they decode, e.g., "ready 7 1-2", and are rejected if they cannot decode
+// the events of the engine's plugins or the engine cannot decode theirs.
+pub(crate) const SYNC_READY: &str = "ready";
+
+// External plugins without an id ask the engine for one with "register <name> <event type> ...",
+// listing the events they subscribe to. The engine answers "registered <plugin_id> <incoming
+// endpoint> <outgoing endpoint>", with the endpoints as it binds them (e.g.,
+// "tcp://127.0.0.1:5559" or "ipc:///run/plyoreacto/incoming.sock"), and the plugin then syncs
+// with "ready <plugin_id>" like any other.
+pub(crate) const SYNC_REGISTER: &str = "register";
+pub(crate) const SYNC_REGISTERED: &str = "registered";
+
+// Once synced, external plugins send "heartbeat <plugin_id>" on the sync socket at regular
+// intervals; the engine answers "ok" and publishes a PluginLeftEvent for a plugin whose
+// heartbeats stop.
+pub(crate) const SYNC_HEARTBEAT: &str = "heartbeat";
+
+// Plugins confirming that events reach them end their "ready" message with "probe", e.g.,
+// "ready 7 probe" or "ready 7 1-2 probe". At startup the engine answers them "probe" and
+// publishes a SyncProbeEvent every `SYNC_PROBE_INTERVAL` until each has answered "probed
+// <plugin_id>", once one came on its sub socket; only then are they told "ok". So the pipes from
+// the engine to every plugin are up before any plugin starts publishing, and none misses the
+// first events. Plugins syncing once the engine runs are told "ok" right away.
+pub(crate) const SYNC_PROBE: &str = "probe";
+pub(crate) const SYNC_PROBED: &str = "probed";
+const SYNC_PROBE_INTERVAL: Duration = Duration::from_millis(10);
+
+// Whether the "ready" message `msg` asks for a probe.
+fn confirms_probe(msg: &[u8]) -> bool {
+    msg.strip_suffix(SYNC_PROBE.as_bytes())
+        .is_some_and(|rest| rest.ends_with(b" "))
+}
+
+// Returns the plugin id of a "<command> <plugin_id>" sync message.
+fn parse_plugin_msg(msg: &[u8], command: &str) -> Option<i32> {
+    let msg = std::str::from_utf8(msg).ok()?;
+    let (word, plugin_id) = msg.split_once(' ')?;
+    if word != command {
+        return None;
+    }
+    plugin_id.parse().ok()
+}
+
+// Returns the plugin id of a "ready <plugin_id> <schema versions>" sync message and the schema
+// versions the plugin decodes (see `schema_versions`), which the plugins started by the engine,
+// and the external plugins from before they were exchanged, leave out. The message may end with
+// "probe" (see `SYNC_PROBE`).
+fn parse_sync_msg(msg: &[u8]) -> Option<(i32, Option<&str>)> {
+    let msg = std::str::from_utf8(msg).ok()?;
+    let msg = msg
+        .strip_suffix(SYNC_PROBE)
+        .and_then(|msg| msg.strip_suffix(' '))
+        .unwrap_or(msg);
+    let mut words = msg.split(' ');
+    if words.next()? != SYNC_READY {
+        return None;
+    }
+    let plugin_id = words.next()?.parse().ok()?;
+    let versions = words.next();
+    if words.next().is_some() {
+        return None;
+    }
+    Some((plugin_id, versions))
+}
+
+// The rejection of a plugin decoding the schema `versions`, for which the events of the engine
+// are too new, or its own events too new for the engine.
+fn incompatible_schema(plugin_id: i32, versions: &str) -> String {
+    format!(
+        "rejected: plugin {} decodes schema versions {}, the engine {}",
+        plugin_id,
+        versions,
+        schema_versions()
+    )
+}
+
+// Returns the name and subscriptions of a "register <name> <event type> ..." sync message.
+fn parse_register_msg(msg: &[u8]) -> Option<(&str, Vec<&str>)> {
+    let msg = std::str::from_utf8(msg).ok()?;
+    let mut words = msg.split_whitespace();
+    if words.next()? != SYNC_REGISTER {
+        return None;
+    }
+    let name = words.next()?;
+    Some((name, words.collect()))
+}
+
+// The rejection of a sync message that is not "ready <plugin_id>".
+fn unexpected_sync_msg(msg: &[u8]) -> String {
+    format!(
+        "rejected: expected \"{} <plugin_id>\", got {:?}",
+        SYNC_READY,
+        String::from_utf8_lossy(msg)
+    )
+}
+
+// Reply on the ROUTER sync socket to the REQ socket with the given identity.
+fn send_sync_reply(sync: &Socket, identity: &[u8], reply: &str) -> zmq::Result<()> {
+    retry_on_eintr(|| sync.send(identity, zmq::SNDMORE))?;
+    retry_on_eintr(|| sync.send("", zmq::SNDMORE))?;
+    retry_on_eintr(|| sync.send(reply, 0))
+}
+
+// The plugins the engine syncs, kept up to date as external plugins register.
+pub(crate) struct SyncedPlugins {
+    // every plugin id in use, the plugins started by the engine included
+    pub(crate) plugin_ids: Vec<i32>,
+    // plugins running outside the engine, which may sync over TCP and join at any time
+    pub(crate) external_ids: Vec<i32>,
+    // plugins started by the engine that sync again when they are restarted
+    pub(crate) restartable_ids: Vec<i32>,
+    // plugins started by the engine that it starts without if they do not sync in time
+    pub(crate) optional_ids: Vec<i32>,
+    // the optional plugins left out at startup, and why
+    pub(crate) skipped: Vec<(i32, String)>,
+}
+
+impl SyncedPlugins {
+    // Register an external plugin called `name` subscribing to `subscriptions` and return the
+    // id assigned to it, or the rejection to reply with.
+    fn register(
+        &mut self,
+        config: &EngineConfig,
+        name: &str,
+        subscriptions: &[&str],
+    ) -> Result<i32, String> {
+        if config.registration_window.is_zero() {
+            return Err("rejected: the engine does not accept registrations".to_string());
+        }
+        let unknown = UnknownSubscription::find(0, subscriptions);
+        if let Some(unknown) = unknown.first().filter(|_| !config.allow_unknown_subscriptions) {
+            let suggestion = unknown
+                .suggestion
+                .map(|suggestion| format!(" (did you mean {}?)", suggestion))
+                .unwrap_or_default();
+            return Err(format!(
+                "rejected: unknown event type {:?}{}",
+                unknown.event_type, suggestion
+            ));
+        }
+        let plugin_id = self.plugin_ids.iter().max().map_or(0, |id| id + 1);
+        if RESERVED_PLUGIN_IDS.contains(&plugin_id) {
+            return Err("rejected: no plugin id is left to assign".to_string());
+        }
+        self.plugin_ids.push(plugin_id);
+        self.external_ids.push(plugin_id);
+        info!(
+            plugin_id;
+            "Engine registered external plugin {} ({}) subscribing to {:?}",
+            plugin_id, name, subscriptions
+        );
+        Ok(plugin_id)
+    }
+}
+
+// The reply to a plugin that registered as `plugin_id`.
+fn registered_reply(config: &EngineConfig, plugin_id: i32) -> String {
+    format!(
+        "{} {} {} {}",
+        SYNC_REGISTERED,
+        plugin_id,
+        config
+            .external_endpoint("incoming", config.incoming_port)
+            .unwrap_or_default(),
+        config
+            .external_endpoint("outgoing", config.outgoing_port)
+            .unwrap_or_default()
+    )
+}
+
+// A message received on a ROUTER sync socket from a REQ socket: its identity and the body.
+fn recv_sync_msg(sync: &Socket) -> zmq::Result<Option<(Vec<u8>, Vec<u8>)>> {
+    // a message from a REQ socket arrives as its identity, an empty delimiter and the body
+    let frames = retry_on_eintr(|| sync.recv_multipart(0))?;
+    match frames.as_slice() {
+        [identity, delimiter, msg] if delimiter.is_empty() => {
+            Ok(Some((identity.clone(), msg.clone())))
+        }
+        _ => {
+            warn!("Engine ignoring malformed sync message: {:?}", frames);
+            Ok(None)
+        }
+    }
+}
+
+// The sync sockets of the engine, bound before any plugin starts so that `config` has the TCP
+// sync port bound if it was 0: the one the plugins started by the engine sync on first, then the
+// TCP one for external plugins if it is bound apart from it.
+pub(crate) fn bind_sync_sockets(
+    context: &zmq::Context,
+    config: &mut EngineConfig,
+    external_plugins: bool,
+) -> Result<Vec<Socket>, EngineError> {
+    // plugins started by the engine sync over inproc only (unless the engine binds TCP only, when
+    // every plugin syncs on the TCP sync socket); a TCP (or ipc) sync socket is only bound
+    // besides when there are external plugins or registrations are accepted, and only external
+    // plugins may sync on it (an external plugin running in the host's process can also use
+    // inproc).
+    let mut sync_sockets = Vec::<Socket>::new();
+    let sync = create_socket(context, config, zmq::ROUTER, "sync")?;
+    if config.transport == Transport::Tcp {
+        set_sync_linger(&sync, "sync")?;
+    }
+    bind(&sync, &config.sync_endpoint())?;
+    if config.transport == Transport::Tcp {
+        config.sync_port = bound_port(&sync, &config.sync_endpoint(), config.sync_port)?;
+    }
+    debug!("Engine bound to sync socket: {}", config.sync_endpoint());
+    sync_sockets.push(sync);
+    if config.transport == Transport::Tcp
+        || !external_plugins && config.registration_window.is_zero()
+    {
+        return Ok(sync_sockets);
+    }
+    if let Some(endpoint) = config.external_endpoint("sync", config.sync_port) {
+        let external_sync = create_socket(context, config, zmq::ROUTER, "external sync")?;
+        set_sync_linger(&external_sync, "external sync")?;
+        if let Some(server) = &config.curve {
+            make_server(&external_sync, "external sync", server)?;
+        }
+        config.sync_port = bind_external(&external_sync, config, "sync", config.sync_port)?;
+        let endpoint = config
+            .external_endpoint("sync", config.sync_port)
+            .unwrap_or(endpoint);
+        debug!("Engine bound to external sync socket: {}", endpoint);
+        sync_sockets.push(external_sync);
+    }
+    Ok(sync_sockets)
+}
+
+// Sync the plugins at startup on `sync_sockets` (see `bind_sync_sockets`), the required ones
+// being those of the startup `waves` (see `PluginRegistry::startup_waves`), whose replies wait
+// for the plugins of the waves before them to be running as `statuses` says. Returns the sync
+// sockets, for the plugins syncing later.
+pub(crate) fn sync_plugins(
+    config: &EngineConfig,
+    sync_sockets: Vec<Socket>,
+    plugins: &mut SyncedPlugins,
+    waves: &[Vec<i32>],
+    statuses: &PluginStatuses,
+    incoming: &Socket,
+    outgoing: &Socket,
+) -> Result<Vec<Socket>, EngineError> {
+    // Plugins sync in any order. Startup waits for the required plugins, including those
+    // registering during the registration window; optional external plugins syncing meanwhile
+    // are answered along with them.
+    let mut required_ids = waves.concat();
+    let sync_error = |source| EngineError::Socket {
+        socket: "sync".to_string(),
+        source,
+    };
+
+    // wait for the required plugins to sync (and confirm a probe, if they asked for one) and for
+    // the registration window to close, or for the sync timeout to expire; for every plugin that
+    // synced, the sync socket it used and the identity of its REQ socket are kept so that it can
+    // be replied to
+    let mut synced = HashMap::<i32, (usize, Vec<u8>)>::new();
+    // likewise, the plugins told to wait for a probe, until they confirm one
+    let mut probing = HashMap::<i32, (usize, Vec<u8>)>::new();
+    let mut probe = SyncProbe { seq: 0 };
+    let mut bldr = FlatBufferBuilder::new();
+    let started = Instant::now();
+    let mut next_probe = started;
+    let registration_deadline = started + config.registration_window;
+    let deadline = started + config.sync_timeout;
+    loop {
+        let until = if required_ids.iter().any(|id| !synced.contains_key(id)) || !probing.is_empty()
+        {
+            deadline
+        } else {
+            registration_deadline.min(deadline)
+        };
+        let mut remaining = until.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            break;
+        }
+        if !probing.is_empty() {
+            if Instant::now() >= next_probe {
+                probe.seq += 1;
+                let data = probe.build(&mut bldr)?;
+                send_event_msg(outgoing, probe.event_type(), data).map_err(|source| {
+                    EngineError::Socket {
+                        socket: "outgoing".to_string(),
+                        source,
+                    }
+                })?;
+                next_probe = Instant::now() + SYNC_PROBE_INTERVAL;
+            }
+            remaining = remaining.min(next_probe.saturating_duration_since(Instant::now()));
+        }
+        let mut items: Vec<zmq::PollItem> = sync_sockets
+            .iter()
+            .map(|sync| sync.as_poll_item(zmq::POLLIN))
+            .collect();
+        match zmq::poll(&mut items, remaining.as_millis() as i64) {
+            // interrupted by a signal: poll again, for what is left of the wait
+            Err(zmq::Error::EINTR) => continue,
+            polled => polled.map_err(sync_error)?,
+        };
+        let readable: Vec<usize> = (0..items.len())
+            .filter(|i| items[*i].is_readable())
+            .collect();
+        for i in readable {
+            let sync = &sync_sockets[i];
+            let (identity, msg) = match recv_sync_msg(sync).map_err(sync_error)? {
+                Some(msg) => msg,
+                None => continue,
+            };
+            let over_tcp = i > 0;
+            if let Some(plugin_id) = parse_plugin_msg(&msg, SYNC_PROBED) {
+                if probing.get(&plugin_id) == Some(&(i, identity.clone())) {
+                    debug!(
+                        plugin_id;
+                        "Engine got probe confirmation from plugin {} after {} probe(s)",
+                        plugin_id, probe.seq
+                    );
+                    probing.remove(&plugin_id);
+                    synced.insert(plugin_id, (i, identity));
+                    continue;
+                }
+            }
+            let rejection = match parse_sync_msg(&msg) {
+                None => match parse_register_msg(&msg) {
+                    None => unexpected_sync_msg(&msg),
+                    Some((name, subscriptions)) => {
+                        match plugins.register(config, name, &subscriptions) {
+                            Ok(plugin_id) => {
+                                required_ids.push(plugin_id);
+                                let reply = registered_reply(config, plugin_id);
+                                send_sync_reply(sync, &identity, &reply).map_err(sync_error)?;
+                                continue;
+                            }
+                            Err(rejection) => rejection,
+                        }
+                    }
+                },
+                Some((plugin_id, Some(versions))) if !schema_versions_compatible(versions) => {
+                    incompatible_schema(plugin_id, versions)
+                }
+                Some((plugin_id, _)) if !plugins.plugin_ids.contains(&plugin_id) => {
+                    format!("rejected: plugin {} is not registered", plugin_id)
+                }
+                Some((plugin_id, _)) if over_tcp && !plugins.external_ids.contains(&plugin_id) => {
+                    format!("rejected: plugin {} syncs over inproc", plugin_id)
+                }
+                Some((plugin_id, _))
+                    if synced.contains_key(&plugin_id) || probing.contains_key(&plugin_id) =>
+                {
+                    format!("rejected: plugin {} already synced", plugin_id)
+                }
+                Some((plugin_id, _)) if confirms_probe(&msg) => {
+                    info!(plugin_id; "Engine got sync message from plugin {}, probing it", plugin_id);
+                    send_sync_reply(sync, &identity, SYNC_PROBE).map_err(sync_error)?;
+                    probing.insert(plugin_id, (i, identity));
+                    continue;
+                }
+                Some((plugin_id, _)) => {
+                    info!(plugin_id; "Engine got sync message from plugin {}", plugin_id);
+                    synced.insert(plugin_id, (i, identity));
+                    continue;
+                }
+            };
+            warn!("Engine replying to sync message: {}", rejection);
+            send_sync_reply(sync, &identity, &rejection).map_err(sync_error)?;
+        }
+    }
+
+    let (skipped, missing): (Vec<i32>, Vec<i32>) = required_ids
+        .iter()
+        .filter(|plugin_id| !synced.contains_key(plugin_id))
+        .copied()
+        .partition(|plugin_id| plugins.optional_ids.contains(plugin_id));
+    for plugin_id in skipped {
+        let reason = format!("did not sync within {:?}", config.sync_timeout);
+        warn!(plugin_id; "optional plugin {} {}; starting without it", plugin_id, reason);
+        statuses
+            .lock()
+            .expect("plugin status lock poisoned")
+            .insert(plugin_id, PluginStatus::Failed(reason.clone()));
+        plugins.skipped.push((plugin_id, reason));
+    }
+    if !missing.is_empty() {
+        let only_external = missing.iter().all(|id| plugins.external_ids.contains(id));
+        if config.skip_missing_external_plugins && only_external {
+            warn!(
+                plugin_ids:? = missing;
+                "external plugins {:?} did not sync within {:?}; starting without them",
+                missing, config.sync_timeout
+            );
+        } else {
+            // let the plugins that did sync know they should not start
+            for (i, identity) in synced.values() {
+                let _ = send_sync_reply(&sync_sockets[*i], identity, "abort");
+            }
+            error!(
+                plugin_ids:? = missing;
+                "Engine giving up: plugins {:?} did not sync within {:?}",
+                missing, config.sync_timeout
+            );
+            return Err(EngineError::SyncTimeout {
+                plugin_ids: missing,
+            });
+        }
+    }
+
+    // over TCP, the plugins' connections and subscriptions reach the engine sockets some time
+    // after the plugins connect, as those of external plugins do
+    if config.transport == Transport::Tcp {
+        thread::sleep(SUBSCRIPTION_SETTLE_TIME);
+    }
+    // the engine sockets only attach the pipes of newly connected plugins (and send them their
+    // subscriptions) when they process pending commands, which otherwise first happens once the
+    // proxy is running; do it now so events published right after the sync reply are not dropped.
+    for (socket, name) in [(incoming, "incoming"), (outgoing, "outgoing")] {
+        socket.get_events().map_err(|source| EngineError::Socket {
+            socket: name.to_string(),
+            source,
+        })?;
+    }
+    // straight to the plugins, which are all subscribed by now: nothing they publish can come
+    // before it
+    let engine_started = EngineStarted {
+        engine_id: config.engine_id,
+        protocol_version: SCHEMA_VERSION,
+        started_at_ms: now_ms(),
+    };
+    let data = engine_started.build(&mut bldr)?;
+    send_event_msg(outgoing, engine_started.event_type(), data).map_err(|source| {
+        EngineError::Socket {
+            socket: "outgoing".to_string(),
+            source,
+        }
+    })?;
+    // send a reply to all plugins that synced, a wave at a time; the plugins outside the waves
+    // (optional external plugins and those that registered) depend on none and are answered
+    // with the first one; the optional plugins left out are not waited for
+    let skipped = |plugin_id: &&i32| plugins.skipped.iter().any(|(id, _)| id == *plugin_id);
+    let mut reply_waves: Vec<Vec<i32>> = waves
+        .iter()
+        .map(|wave| wave.iter().filter(|id| !skipped(id)).copied().collect())
+        .collect();
+    if reply_waves.is_empty() {
+        reply_waves.push(Vec::new());
+    }
+    let others: Vec<i32> = plugins
+        .plugin_ids
+        .iter()
+        .filter(|plugin_id| !waves.iter().any(|wave| wave.contains(plugin_id)))
+        .copied()
+        .collect();
+    reply_waves[0].extend(others);
+    for (n, wave) in reply_waves.iter().enumerate() {
+        if n > 0 {
+            if let Err(starting) = wait_until_running(&reply_waves[n - 1], statuses, config) {
+                let unanswered = reply_waves[n..].iter().flatten();
+                for (i, identity) in unanswered.filter_map(|plugin_id| synced.get(plugin_id)) {
+                    let _ = send_sync_reply(&sync_sockets[*i], identity, "abort");
+                }
+                error!(
+                    plugin_ids:? = starting;
+                    "Engine giving up: plugins {:?} did not start within {:?}",
+                    starting, config.sync_timeout
+                );
+                return Err(EngineError::SyncTimeout {
+                    plugin_ids: starting,
+                });
+            }
+        }
+        for plugin_id in wave {
+            if let Some((i, identity)) = synced.get(plugin_id) {
+                debug!(plugin_id = *plugin_id; "Engine sending reply message to {}", plugin_id);
+                send_sync_reply(&sync_sockets[*i], identity, "ok").map_err(|source| {
+                    EngineError::Sync {
+                        plugin_id: *plugin_id,
+                        source,
+                    }
+                })?;
+            }
+        }
+    }
+
+    Ok(sync_sockets)
+}
+
+// Wait for the plugins among `plugin_ids` run by the engine to get past starting, i.e., to have
+// received their sync reply, for at most the sync timeout. Fails with the ids of those that did
+// not.
+fn wait_until_running(
+    plugin_ids: &[i32],
+    statuses: &PluginStatuses,
+    config: &EngineConfig,
+) -> Result<(), Vec<i32>> {
+    let deadline = Instant::now() + config.sync_timeout;
+    loop {
+        let starting: Vec<i32> = {
+            let statuses = statuses.lock().expect("plugin status lock poisoned");
+            plugin_ids
+                .iter()
+                .filter(|plugin_id| statuses.get(plugin_id) == Some(&PluginStatus::Starting))
+                .copied()
+                .collect()
+        };
+        if starting.is_empty() {
+            return Ok(());
+        }
+        if Instant::now() >= deadline {
+            return Err(starting);
+        }
+        thread::sleep(DEPENDENCY_POLL_INTERVAL);
+    }
+}
+
+// A reply to an external plugin that went away must not keep the engine's context from
+// terminating.
+fn set_sync_linger(sync: &Socket, name: &str) -> Result<(), EngineError> {
+    sync.set_linger(0).map_err(|source| EngineError::Socket {
+        socket: name.to_string(),
+        source,
+    })
+}
+
+// Answer the plugins syncing after startup until TERMINATE is received on `control`: restarted
+// plugins on the first sync socket, and external plugins (re)joining, registering or sending
+// heartbeats on either socket. Each external plugin that syncs gets a PluginJoinedEvent, and
+// each whose heartbeats stop a PluginLeftEvent, published on `membership`.
+fn resync_plugins(
+    sync_sockets: Vec<Socket>,
+    control: Socket,
+    membership: Socket,
+    config: EngineConfig,
+    mut plugins: SyncedPlugins,
+) {
+    let mut bldr = FlatBufferBuilder::new();
+    // when each external plugin sending heartbeats sent its last one
+    let mut heartbeats = HashMap::<i32, Instant>::new();
+    loop {
+        let mut items: Vec<zmq::PollItem> = sync_sockets
+            .iter()
+            .chain([&control])
+            .map(|socket| socket.as_poll_item(zmq::POLLIN))
+            .collect();
+        // wake up in time to notice the first plugin whose heartbeats stop
+        let timeout = heartbeats
+            .values()
+            .map(|last| {
+                let expiry = *last + config.external_heartbeat_timeout;
+                expiry.saturating_duration_since(Instant::now()).as_millis() as i64
+            })
+            .min()
+            .unwrap_or(-1);
+        let polled = retry_on_eintr(|| zmq::poll(&mut items, timeout));
+        if polled.is_err() || items[sync_sockets.len()].is_readable() {
+            return;
+        }
+        let now = Instant::now();
+        let left: Vec<i32> = heartbeats
+            .iter()
+            .filter(|(_, last)| now.duration_since(**last) >= config.external_heartbeat_timeout)
+            .map(|(plugin_id, _)| *plugin_id)
+            .collect();
+        for plugin_id in left {
+            heartbeats.remove(&plugin_id);
+            let reason = format!("no heartbeat for {:?}", config.external_heartbeat_timeout);
+            warn!(plugin_id; "Engine lost external plugin {}: {}", plugin_id, reason);
+            let data = make_plugin_left_msg(&mut bldr, plugin_id, &reason)
+                .expect("could not build plugin left event");
+            if let Err(e) = send_event_msg(&membership, "PluginLeftEvent", data) {
+                error!(plugin_id; "could not publish leave of plugin {}: {}", plugin_id, e);
+            }
+        }
+        let readable: Vec<usize> = (0..sync_sockets.len())
+            .filter(|i| items[*i].is_readable())
+            .collect();
+        for i in readable {
+            let sync = &sync_sockets[i];
+            let (identity, msg) = match recv_sync_msg(sync) {
+                Ok(Some(msg)) => msg,
+                Ok(None) => continue,
+                Err(_) => return,
+            };
+            if let Some(plugin_id) = parse_plugin_msg(&msg, SYNC_HEARTBEAT) {
+                let reply = if plugins.external_ids.contains(&plugin_id) {
+                    heartbeats.insert(plugin_id, Instant::now());
+                    "ok".to_string()
+                } else {
+                    format!("rejected: plugin {} is not an external plugin", plugin_id)
+                };
+                if let Err(e) = send_sync_reply(sync, &identity, &reply) {
+                    error!("Engine could not reply to heartbeat: {}", e);
+                }
+                continue;
+            }
+            let over_tcp = i > 0;
+            let mut joined_id = None;
+            let reply = match parse_sync_msg(&msg) {
+                None => match parse_register_msg(&msg) {
+                    None => unexpected_sync_msg(&msg),
+                    Some((name, subscriptions)) => {
+                        match plugins.register(&config, name, &subscriptions) {
+                            Ok(plugin_id) => registered_reply(&config, plugin_id),
+                            Err(rejection) => rejection,
+                        }
+                    }
+                },
+                Some((plugin_id, Some(versions))) if !schema_versions_compatible(versions) => {
+                    incompatible_schema(plugin_id, versions)
+                }
+                Some((plugin_id, _)) if plugins.external_ids.contains(&plugin_id) => {
+                    info!(plugin_id; "Engine got sync message from joining plugin {}", plugin_id);
+                    joined_id = Some(plugin_id);
+                    "ok".to_string()
+                }
+                Some((plugin_id, _)) if !plugins.plugin_ids.contains(&plugin_id) => {
+                    format!("rejected: plugin {} is not registered", plugin_id)
+                }
+                Some((plugin_id, _)) if over_tcp => {
+                    format!("rejected: plugin {} syncs over inproc", plugin_id)
+                }
+                Some((plugin_id, _)) if !plugins.restartable_ids.contains(&plugin_id) => {
+                    format!("rejected: plugin {} is not restartable", plugin_id)
+                }
+                Some((plugin_id, _)) => {
+                    info!(plugin_id; "Engine got sync message from restarted plugin {}", plugin_id);
+                    "ok".to_string()
+                }
+            };
+            if let Err(e) = send_sync_reply(sync, &identity, &reply) {
+                error!("Engine could not reply to sync message: {}", e);
+                continue;
+            }
+            if let Some(plugin_id) = joined_id {
+                let data = make_plugin_joined_msg(&mut bldr, plugin_id)
+                    .expect("could not build plugin joined event");
+                if let Err(e) = send_event_msg(&membership, "PluginJoinedEvent", data) {
+                    error!(plugin_id; "could not publish join of plugin {}: {}", plugin_id, e);
+                }
+            }
+        }
+    }
+}
+
+fn start_plugins(
+    context: &zmq::Context,
+    config: &EngineConfig,
+    plugins: PluginRegistry,
+    sync_sockets: Vec<Socket>,
+    shared: &PluginShared,
+    incoming: &Socket,
+    outgoing: &Socket,
+) -> Result<StartedPlugins, EngineError> {
+    // the plugins started by the engine and the required external plugins must sync before the
+    // engine starts, in the order of their dependencies
+    let mut waves = plugins.startup_waves()?;
+    let required_ids: Vec<i32> = waves.concat();
+    let mut synced_plugins = SyncedPlugins {
+        plugin_ids: plugins.plugin_ids(),
+        external_ids: plugins.external_plugin_ids(),
+        restartable_ids: plugins
+            .plugins
+            .iter()
+            .filter(|p| p.restart_policy != RestartPolicy::Never)
+            .map(|p| p.plugin.id())
+            .collect(),
+        optional_ids: plugins
+            .plugins
+            .iter()
+            .filter(|p| p.optional)
+            .map(|p| p.plugin.id())
+            .collect(),
+        skipped: Vec::new(),
+    };
+    // call start_plugin with the zmq context and the config for each plugin, dependencies
+    // first; an optional plugin that cannot be started is left out of the sync
+    let mut plugin_configs = plugins.plugins;
+    plugin_configs.sort_by_key(|p| required_ids.iter().position(|id| *id == p.plugin.id()));
+    let mut plugin_threads = Vec::new();
+    for plugin in plugin_configs {
+        let (plugin_id, optional) = (plugin.plugin.id(), plugin.optional);
+        let started = if plugin.workers > 1 {
+            start_pool(context, config, plugin, shared)
+        } else {
+            start_plugin(context, config, plugin, shared)
+        };
+        match started {
+            Ok(plugin_thread) => plugin_threads.push(plugin_thread),
+            Err(e) if optional => {
+                let reason = e.to_string();
+                warn!(plugin_id; "optional plugin {} not started: {}", plugin_id, reason);
+                shared
+                    .statuses
+                    .lock()
+                    .expect("plugin status lock poisoned")
+                    .insert(plugin_id, PluginStatus::Failed(reason.clone()));
+                for wave in waves.iter_mut() {
+                    wave.retain(|id| *id != plugin_id);
+                }
+                synced_plugins.skipped.push((plugin_id, reason));
+            }
+            Err(e) => return Err(e),
+        }
+    }
+    // connected before the plugins sync, so that it is ready by the time a plugin joins late
+    let membership = create_socket(context, config, zmq::PUB, "plugin membership")?;
+    connect(&membership, &config.incoming_endpoint())?;
+    // once all plugins have been started, sync them on the engine's sync socket
+    let sync_sockets = sync_plugins(
+        config,
+        sync_sockets,
+        &mut synced_plugins,
+        &waves,
+        &shared.statuses,
+        incoming,
+        outgoing,
+    )?;
+    let skipped = std::mem::take(&mut synced_plugins.skipped);
+    // with only the inproc sync socket and nothing to restart, no plugin can sync later
+    if sync_sockets.len() == 1 && synced_plugins.restartable_ids.is_empty() {
+        return Ok((plugin_threads, None, skipped));
+    }
+
+    // restarted plugins sync again on the inproc sync socket, and external plugins join on
+    // either; both are answered by a thread of their own until the engine shuts down
+    let control_endpoint = format!("inproc://{}-resync-control", config.outgoing_inproc);
+    let resync_control = create_socket(context, config, zmq::PAIR, "resync control")?;
+    bind(&resync_control, &control_endpoint)?;
+    let control = create_socket(context, config, zmq::PAIR, "resync")?;
+    connect(&control, &control_endpoint)?;
+    let config = config.clone();
+    let resync_thread = thread::spawn(move || {
+        resync_plugins(
+            sync_sockets,
+            resync_control,
+            membership,
+            config,
+            synced_plugins,
+        )
+    });
+    Ok((plugin_threads, Some((control, resync_thread)), skipped))
+}
+
+// Publish an EngineHeartbeatEvent on `publisher` every heartbeat interval and record the
+// PluginHeartbeatEvents answering them, received on `answers`, in `liveness`, until TERMINATE
+// is received on `control`.
+fn send_heartbeats(
+    publisher: Socket,
+    answers: Socket,
+    control: Socket,
+    config: EngineConfig,
+    liveness: PluginLivenessMap,
+) {
+    let started = Instant::now();
+    let mut bldr = FlatBufferBuilder::new();
+    // seq of the next heartbeat
+    let mut seq: u64 = 0;
+    let mut next_beat = started + config.heartbeat_interval;
+    loop {
+        let timeout = next_beat.saturating_duration_since(Instant::now());
+        let mut items = [
+            answers.as_poll_item(zmq::POLLIN),
+            control.as_poll_item(zmq::POLLIN),
+        ];
+        let polled = retry_on_eintr(|| zmq::poll(&mut items, timeout.as_millis() as i64));
+        if polled.is_err() || items[1].is_readable() {
+            return;
+        }
+        if items[0].is_readable() {
+            let frames = match retry_on_eintr(|| answers.recv_multipart(0)) {
+                Ok(frames) => frames,
+                Err(_) => return,
+            };
+            let answer = match decode_event_frames(frames, config.max_payload_size) {
+                Some(Ok(Event::PluginHeartbeat(answer))) => answer,
+                _ => continue,
+            };
+            let mut liveness = liveness.lock().expect("plugin liveness lock poisoned");
+            let last_seq = liveness
+                .get(&answer.plugin_id)
+                .map_or(answer.seq, |l| l.last_seq.max(answer.seq));
+            // `seq` - 1 is the latest heartbeat published
+            let plugin_liveness = PluginLiveness::new(last_seq, seq.saturating_sub(1), &config);
+            liveness.insert(answer.plugin_id, plugin_liveness);
+        }
+        let now = Instant::now();
+        if now < next_beat {
+            continue;
+        }
+        let heartbeat = Event::EngineHeartbeat(EngineHeartbeat {
+            seq,
+            uptime_ms: now.duration_since(started).as_millis() as u64,
+        });
+        if let Err(e) = heartbeat.send(&publisher, &mut bldr) {
+            error!("Engine could not publish heartbeat {}: {}", seq, e);
+        }
+        let mut liveness = liveness.lock().expect("plugin liveness lock poisoned");
+        for (plugin_id, plugin_liveness) in liveness.iter_mut() {
+            let updated = PluginLiveness::new(plugin_liveness.last_seq, seq, &config);
+            if updated.unresponsive && !plugin_liveness.unresponsive {
+                warn!(
+                    plugin_id = *plugin_id;
+                    "plugin {} missed {} heartbeats",
+                    plugin_id, updated.missed_beats
+                );
+            }
+            *plugin_liveness = updated;
+        }
+        seq += 1;
+        next_beat += config.heartbeat_interval;
+    }
+}
+
+// Start the thread publishing the engine heartbeats, if the engine has a heartbeat interval.
+fn start_heartbeats(
+    context: &zmq::Context,
+    config: &EngineConfig,
+    liveness: &PluginLivenessMap,
+) -> Result<Option<Heartbeat>, EngineError> {
+    if config.heartbeat_interval.is_zero() {
+        return Ok(None);
+    }
+    let publisher = create_socket(context, config, zmq::PUB, "heartbeat publisher")?;
+    connect(&publisher, &config.incoming_endpoint())?;
+    let answers_name = "heartbeat answers";
+    let answers = create_socket(context, config, zmq::SUB, answers_name)?;
+    let filter_bytes = get_event_type_bytes_filter("PluginHeartbeatEvent")
+        .expect("PluginHeartbeatEvent is an event type");
+    answers
+        .set_subscribe(&filter_bytes)
+        .map_err(|source| EngineError::Socket {
+            socket: answers_name.to_string(),
+            source,
+        })?;
+    connect(&answers, &config.outgoing_endpoint())?;
+
+    let control_endpoint = format!("inproc://{}-heartbeat-control", config.outgoing_inproc);
+    let heartbeat_control = create_socket(context, config, zmq::PAIR, "heartbeat control")?;
+    bind(&heartbeat_control, &control_endpoint)?;
+    let control = create_socket(context, config, zmq::PAIR, "heartbeat")?;
+    connect(&control, &control_endpoint)?;
+    let config = config.clone();
+    let liveness = Arc::clone(liveness);
+    let heartbeat_thread = thread::spawn(move || {
+        send_heartbeats(publisher, answers, heartbeat_control, config, liveness)
+    });
+    Ok(Some((control, heartbeat_thread)))
+}
+
+/// Start the engine with the default ports and endpoints and block until it stops.
+pub fn event_engine() -> Result<(), EngineError> {
+    event_engine_with_config(&EngineConfig::default())
+}
+
+/// Start the engine with the given configuration and block until it stops.
+pub fn event_engine_with_config(config: &EngineConfig) -> Result<(), EngineError> {
+    start_event_engine(config)?.join()
+}
+
+/// Start the engine with the default ports and endpoints on a context owned by the host
+/// application and block until it stops. Host sockets on `ctx` can connect to the engine's
+/// inproc endpoints.
+pub fn event_engine_with_context(ctx: zmq::Context) -> Result<(), EngineError> {
+    start_event_engine_with_context(&EngineConfig::default(), ctx)?.join()
+}
+
+/// Start the engine: bind the engine sockets, start and sync all plugins, and run the proxy
+/// in its own thread. Returns once the plugins are synced.
+pub fn start_event_engine(config: &EngineConfig) -> Result<EngineHandle, EngineError> {
+    // zmq context to be used by this engine and all plugin threads
+    start_engine(
+        config,
+        zmq::Context::new(),
+        true,
+        default_plugins(),
+        Vec::new(),
+        None,
+        None,
+    )
+}
+
+/// Like `start_event_engine`, but the engine sockets and plugin threads use the given context.
+pub fn start_event_engine_with_context(
+    config: &EngineConfig,
+    context: zmq::Context,
+) -> Result<EngineHandle, EngineError> {
+    start_engine(
+        config,
+        context,
+        false,
+        default_plugins(),
+        Vec::new(),
+        None,
+        None,
+    )
+}
+
+fn start_engine(
+    config: &EngineConfig,
+    context: zmq::Context,
+    owns_context: bool,
+    mut plugins: PluginRegistry,
+    mut middlewares: Vec<Box<dyn Middleware>>,
+    stop_when: Option<StopPredicate>,
+    capture_config: Option<CaptureConfig>,
+) -> Result<EngineHandle, EngineError> {
+    info!("Starting EVENT engine");
+    plugins.apply_entries(&config.plugins)?;
+    if let Some(profile) = &config.profile {
+        plugins.select_profile(profile)?;
+    }
+    plugins.check_ids()?;
+    set_io_threads(&context, config, owns_context)?;
+    if config.transport == Transport::InprocOnly
+        && (!plugins.external_plugins.is_empty() || !config.registration_window.is_zero())
+    {
+        return Err(EngineError::ExternalPluginsInprocOnly);
+    }
+    if config.curve.is_some() && config.transport == Transport::Tcp {
+        return Err(EngineError::CurveTcpOnly);
+    }
+    let unknown = plugins
+        .plugins
+        .iter()
+        .flat_map(|p| UnknownSubscription::find(p.plugin.id(), p.plugin.subscriptions()))
+        .collect();
+    check_subscriptions(config, unknown)?;
+    if let Some(unknown) = config
+        .last_value_cache
+        .iter()
+        .find(|event_type| !event_type_names().contains(&event_type.as_str()))
+    {
+        return Err(EngineError::UnknownEventType {
+            event_type: unknown.clone(),
+        });
+    }
+    if let Some(acks) = &config.acknowledged_delivery {
+        acks.check(&plugins)?;
+    }
+    let mut cache = (!config.last_value_cache.is_empty())
+        .then(|| LastValueCache::new(&config.last_value_cache));
+    let rejected_events = Arc::new(AtomicU64::new(0));
+    let limit = (config.max_payload_size > 0).then(|| PayloadLimit {
+        max_bytes: config.max_payload_size,
+        rejected: Arc::clone(&rejected_events),
+    });
+    let forwarding_loop = config.forwarding_loop
+        || limit.is_some()
+        || !middlewares.is_empty()
+        || cache.is_some()
+        || config.acknowledged_delivery.is_some();
+    #[cfg(unix)]
+    let ipc_paths = claim_ipc_paths(config)?;
+    // every client connecting to a CURVE server is checked by the ZAP handler, which therefore
+    // runs before any socket is bound
+    let zap = start_zap_handler(&context, config)?;
+
+    // incoming, outgoing and sync sockets for the engine, bound before anything connects to
+    // them, for the configuration to have the TCP ports bound
+    let mut config = config.clone();
+    let mut outgoing = get_outgoing_socket(&context, &mut config)?;
+    let mut incoming = get_incoming_socket(&context, &mut config)?;
+    let sync_sockets =
+        bind_sync_sockets(&context, &mut config, !plugins.external_plugins.is_empty())?;
+    let config = &config;
+
+    // the proxy stops when it receives TERMINATE on its control socket; the engine handle owns
+    // the other end of the pair
+    let control_endpoint = format!("inproc://{}-control", config.outgoing_inproc);
+    let mut proxy_control = create_socket(&context, config, zmq::PAIR, "proxy control")?;
+    bind(&proxy_control, &control_endpoint)?;
+    let control = create_socket(&context, config, zmq::PAIR, "control")?;
+    connect(&control, &control_endpoint)?;
+    let control = Arc::new(Mutex::new(ProxyControl {
+        socket: control,
+        terminated: false,
+    }));
+    // subscribed before the plugins start publishing
+    let stop_thread = start_stop_thread(&context, config, &control, stop_when)?;
+    // bound before the plugins connect to it
+    let mut retry_buffer = match &config.acknowledged_delivery {
+        Some(acks) => Some(RetryBuffer::new(&context, config, acks)?),
+        None => None,
+    };
+
+    // socket for the events the host publishes; it is connected before the plugins sync so that
+    // it is ready once the engine has started
+    let publisher = create_socket(&context, config, zmq::PUB, "publisher")?;
+    connect(&publisher, &config.incoming_endpoint())?;
+
+    // start plugins in their own thread
+    let shared = PluginShared {
+        statuses: PluginStatuses::default(),
+        pauses: PluginPauses::default(),
+        stopping: Arc::new(AtomicBool::new(false)),
+        #[cfg(feature = "prometheus")]
+        metrics: config
+            .metrics_addr
+            .as_ref()
+            .map(|_| Arc::new(EngineMetrics::new(&plugins))),
+    };
+    let plugin_liveness = PluginLivenessMap::default();
+    let proxy_running = Arc::new(AtomicBool::new(true));
+    let stats = Arc::new(ForwardingStats::new());
+    let admin_state = AdminState::new(
+        &plugins,
+        &proxy_running,
+        &shared.statuses,
+        &plugin_liveness,
+        &stats,
+    );
+    #[cfg(feature = "prometheus")]
+    let sync_started = Instant::now();
+    let (plugin_threads, resync, skipped) = start_plugins(
+        &context,
+        config,
+        plugins,
+        sync_sockets,
+        &shared,
+        &incoming,
+        &outgoing,
+    )?;
+    #[cfg(feature = "prometheus")]
+    if let Some(metrics) = &shared.metrics {
+        metrics.set_sync_duration(sync_started.elapsed());
+    }
+    let heartbeat = start_heartbeats(&context, config, &plugin_liveness)?;
+    let admin = start_admin(&context, config, admin_state)?;
+    // the proxy sends a copy of every event it forwards to the capture socket, if any
+    let capture = get_capture_socket(&context, config, capture_config.is_some())?;
+    #[cfg(feature = "prometheus")]
+    let exporter = match &shared.metrics {
+        Some(metrics) => start_exporter(&context, config, metrics)?,
+        None => None,
+    };
+    let event_log = start_event_log(&context, config)?;
+    let capture_consumer = start_capture(&context, config, capture_config)?;
+    if let Some(capture) = &capture {
+        // attach the subscribers now, so that the first events the proxy forwards are captured
+        capture.get_events().map_err(|source| EngineError::Socket {
+            socket: "capture".to_string(),
+            source,
+        })?;
+    }
+
+    // proxy from incoming to outgoing sockets;
+    // this call blocks until the proxy is terminated
+    let forwarding_stats = Arc::clone(&stats);
+    let proxy_thread = thread::spawn(move || {
+        info!("Engine starting main proxy");
+        let proxied = match capture {
+            capture if forwarding_loop => forward_events(
+                &incoming,
+                &outgoing,
+                capture.as_ref(),
+                &proxy_control,
+                limit.as_ref(),
+                &mut middlewares,
+                cache.as_mut(),
+                retry_buffer.as_mut(),
+                &forwarding_stats,
+            ),
+            // the proxy keeps no state of its own, so it is started again once interrupted
+            Some(mut capture) => retry_on_eintr(|| {
+                zmq::proxy_steerable_with_capture(
+                    &mut incoming,
+                    &mut outgoing,
+                    &mut capture,
+                    &mut proxy_control,
+                )
+            }),
+            None => retry_on_eintr(|| {
+                zmq::proxy_steerable(&mut incoming, &mut outgoing, &mut proxy_control)
+            }),
+        };
+        proxy_running.store(false, Ordering::SeqCst);
+        proxied.map_err(|source| EngineError::Proxy { source })?;
+        info!("Engine proxy terminated, publishing plugin terminate event");
+        let mut bldr = FlatBufferBuilder::new();
+        send_plugin_terminate_event(&mut outgoing, &mut bldr)?;
+        Ok(())
+    });
+
+    let handle = EngineHandle {
+        context,
+        owns_context,
+        config: config.clone(),
+        control,
+        publisher: Mutex::new((publisher, FlatBufferBuilder::new())),
+        proxy_thread,
+        plugin_threads: Mutex::new(plugin_threads),
+        plugin_statuses: shared.statuses,
+        plugin_pauses: shared.pauses,
+        stopping: shared.stopping,
+        resync: resync.map(Mutex::new),
+        plugin_liveness,
+        heartbeat: heartbeat.map(Mutex::new),
+        admin: admin.map(Mutex::new),
+        zap: zap.map(Mutex::new),
+        #[cfg(feature = "prometheus")]
+        exporter: exporter.map(Mutex::new),
+        event_log,
+        capture: capture_consumer,
+        rejected_events,
+        stats,
+        stop_thread,
+        #[cfg(unix)]
+        ipc_paths,
+    };
+    for (plugin_id, reason) in skipped {
+        let skipped = Event::PluginSkipped(PluginSkipped { plugin_id, reason });
+        if let Err(e) = handle.publish(&skipped) {
+            error!(plugin_id; "could not publish skip of plugin {}: {}", plugin_id, e);
+        }
+    }
+    Ok(handle)
+}
+
+// The socket files of the ipc endpoints, none of which may exist yet: zmq would take over the
+// file of a running engine without a word. Files left behind by an engine that did not shut
+// down are removed if the engine is configured to.
+#[cfg(unix)]
+pub(crate) fn claim_ipc_paths(config: &EngineConfig) -> Result<Vec<PathBuf>, EngineError> {
+    let paths: Vec<PathBuf> = ["incoming", "outgoing", "sync"]
+        .iter()
+        .filter_map(|name| config.ipc_path(name))
+        .collect();
+    for path in paths.iter().filter(|path| path.symlink_metadata().is_ok()) {
+        let stale = || EngineError::IpcPathExists { path: path.clone() };
+        if !config.unlink_stale_ipc {
+            return Err(stale());
+        }
+        warn!("Engine removing stale ipc socket file {}", path.display());
+        fs::remove_file(path).map_err(|e| {
+            error!("could not remove {}: {}", path.display(), e);
+            stale()
+        })?;
+    }
+    Ok(paths)
+}
+
+// Remove the socket files of the ipc endpoints once their sockets are closed; zmq removes them
+// too, but only when its I/O thread gets to it.
+#[cfg(unix)]
+pub(crate) fn remove_ipc_files(paths: &[PathBuf]) {
+    for path in paths {
+        match fs::remove_file(path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                warn!("could not remove {}: {}", path.display(), e)
+            }
+            _ => {}
+        }
+    }
+}
+
+// Start the thread stopping the proxy once it has forwarded the events of `config.stop_after`,
+// or an event `stop_when` returns true for, if either is set. The thread also exits on the
+// PluginTerminateEvent of an engine shut down before then.
+fn start_stop_thread(
+    context: &zmq::Context,
+    config: &EngineConfig,
+    control: &Arc<Mutex<ProxyControl>>,
+    mut stop_when: Option<StopPredicate>,
+) -> Result<Option<JoinHandle<()>>, EngineError> {
+    if config.stop_after.is_none() && stop_when.is_none() {
+        return Ok(None);
+    }
+    let mut stop_after = match &config.stop_after {
+        Some((event_type, count)) => {
+            let event_type =
+                known_event_type(event_type).ok_or_else(|| EngineError::UnknownEventType {
+                    event_type: event_type.to_string(),
+                })?;
+            Some((event_type, *count, 0))
+        }
+        None => None,
+    };
+    let sub_name = "stop sub";
+    let sub_socket = create_socket(context, config, zmq::SUB, sub_name)?;
+    // the predicate sees every event
+    let subscriptions = match (&stop_after, &stop_when) {
+        (_, Some(_)) => vec![Vec::new()],
+        (Some((event_type, _, _)), None) => [event_type, "PluginTerminateEvent"]
+            .iter()
+            .map(|subscribed| event_type_header(subscribed))
+            .collect(),
+        (None, None) => unreachable!("checked above"),
+    };
+    for filter_bytes in subscriptions {
+        sub_socket
+            .set_subscribe(&filter_bytes)
+            .map_err(|source| EngineError::Socket {
+                socket: sub_name.to_string(),
+                source,
+            })?;
+    }
+    connect(&sub_socket, &config.outgoing_endpoint())?;
+    let control = control.clone();
+    let max_payload_size = config.max_payload_size;
+    let stop_thread = thread::spawn(move || {
+        loop {
+            // this fails once the engine terminates the context
+            let frames = match recv_event_frames(&sub_socket, 0) {
+                Ok(frames) => frames,
+                Err(_) => return,
+            };
+            let (event_type, mut meta, payload) = match parse_event_messages(frames) {
+                Some(("PluginTerminateEvent", _, _)) => return,
+                // published by the engine itself while the plugins sync
+                Some(("SyncProbeEvent", _, _)) => continue,
+                Some(event) => event,
+                None => continue,
+            };
+            if let Some((stop_type, count, seen)) = &mut stop_after {
+                if event_type == *stop_type {
+                    *seen += 1;
+                    if seen == count {
+                        info!(
+                            "Engine forwarded {} {}s, stopping the proxy",
+                            count, event_type
+                        );
+                        break;
+                    }
+                }
+            }
+            if let Some(predicate) = &mut stop_when {
+                let decoded =
+                    decompressed(&mut meta, payload, max_payload_size).and_then(|payload| {
+                        Event::decode_with_meta(event_type, meta.as_ref(), &payload)
+                    });
+                match decoded {
+                    Ok(event) if predicate(&event) => {
+                        info!(
+                            "Engine forwarded the {} to stop after, stopping the proxy",
+                            event_type
+                        );
+                        break;
+                    }
+                    Ok(_) => {}
+                    Err(e) => {
+                        error!(event_type; "stop condition could not decode {}: {}", event_type, e)
+                    }
+                }
+            }
+        }
+        let terminated = control.lock().expect("control lock poisoned").terminate();
+        if let Err(e) = terminated {
+            error!("Engine could not stop the proxy: {}", e);
+        }
+    });
+    Ok(Some(stop_thread))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::events::{
+        bytes_to_event, recv_event_msg, Event, EventMeta, ImageStored, PluginFailed,
+        PluginRestarted,
+    };
+    use crate::settings::PluginSettings;
+    use crate::storage::{InMemoryStore, StorageBackend};
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::mpsc::Receiver;
+
+    // Connect as the external plugin (id 3) of an engine running with `config`, subscribe to
+    // `subscriptions` and sync with the engine.
+    fn connect_external_observer(
+        config: &EngineConfig,
+        subscriptions: &[&str],
+    ) -> (zmq::Context, Socket) {
+        let context = zmq::Context::new();
+        let sub_socket = context.socket(zmq::SUB).unwrap();
+        sub_socket
+            .connect(&format!("tcp://localhost:{}", config.outgoing_port))
+            .unwrap();
+        for sub in subscriptions {
+            sub_socket
+                .set_subscribe(&get_event_type_bytes_filter(sub).unwrap())
+                .unwrap();
+        }
+        sub_socket.set_rcvtimeo(10000).unwrap();
+        // give the subscriptions time to reach the engine before the pipeline starts
+        thread::sleep(Duration::from_millis(200));
+
+        let sync = context.socket(zmq::REQ).unwrap();
+        sync.connect(&format!("tcp://localhost:{}", config.sync_port))
+            .unwrap();
+        sync.send("ready 3", 0).unwrap();
+        sync.recv_msg(0).unwrap();
+        (context, sub_socket)
+    }
+
+    // Receive events on `sub_socket` until all 5 images are accounted for.
+    fn count_image_events(sub_socket: &Socket) -> usize {
+        let mut count = 0;
+        while count < 5 {
+            let (_, payload) = recv_event_msg(sub_socket).expect("timed out waiting for events");
+            bytes_to_event(&payload).unwrap();
+            count += 1;
+        }
+        count
+    }
+
+    // Act as the external plugin (id 3) of an engine running with `config`: subscribe to the
+    // stored and deleted events, sync, and count events until all 5 images are accounted for.
+    fn run_external_observer(config: &EngineConfig) -> usize {
+        let (_context, sub_socket) =
+            connect_external_observer(config, &["ImageStoredEvent", "ImageDeletedEvent"]);
+        count_image_events(&sub_socket)
+    }
+
+    #[test]
+    fn test_config_endpoints() {
+        let config = EventEngineBuilder::new()
+            .incoming_port(6559)
+            .outgoing_inproc("events-b")
+            .sync_port(6000)
+            .build();
+        assert_eq!(config.incoming_tcp_endpoint(), "tcp://127.0.0.1:6559");
+        assert_eq!(config.outgoing_tcp_endpoint(), "tcp://127.0.0.1:5560");
+        assert_eq!(config.incoming_inproc_endpoint(), "inproc://messages");
+        assert_eq!(config.outgoing_inproc_endpoint(), "inproc://events-b");
+        assert_eq!(config.sync_tcp_endpoint(), "tcp://127.0.0.1:6000");
+        assert_eq!(config.sync_inproc_endpoint(), "inproc://events-b-sync");
+        assert_eq!(config.incoming_endpoint(), "inproc://messages");
+        assert_eq!(config.outgoing_endpoint(), "inproc://events-b");
+        assert_eq!(config.sync_endpoint(), "inproc://events-b-sync");
+
+        let config = EventEngineBuilder::new()
+            .incoming_port(6559)
+            .transport(Transport::Tcp)
+            .build();
+        assert_eq!(config.incoming_tcp_endpoint(), "tcp://127.0.0.1:6559");
+        assert_eq!(config.incoming_endpoint(), "tcp://127.0.0.1:6559");
+        assert_eq!(config.outgoing_endpoint(), "tcp://127.0.0.1:5560");
+        assert_eq!(config.sync_endpoint(), "tcp://127.0.0.1:5000");
+
+        // the sockets in the engine's process connect to an engine on all interfaces over the
+        // loopback interface
+        let config = EventEngineBuilder::new()
+            .transport(Transport::Tcp)
+            .expose_external(true)
+            .build();
+        assert_eq!(config.incoming_tcp_endpoint(), "tcp://*:5559");
+        assert_eq!(config.incoming_endpoint(), "tcp://127.0.0.1:5559");
+        let config = EventEngineBuilder::new()
+            .transport(Transport::Tcp)
+            .bind_address("[::]")
+            .build();
+        assert_eq!(config.sync_tcp_endpoint(), "tcp://[::]:5000");
+        assert_eq!(config.sync_endpoint(), "tcp://[::1]:5000");
+        let config = EventEngineBuilder::new()
+            .bind_address("192.168.1.10")
+            .build();
+        assert_eq!(config.outgoing_tcp_endpoint(), "tcp://192.168.1.10:5560");
+        assert_eq!(config.outgoing_endpoint(), "inproc://events");
+    }
+
+    #[test]
+    fn test_bind_error_names_endpoint() {
+        let config = EventEngineBuilder::new()
+            .incoming_port(55559)
+            .outgoing_port(55560)
+            .sync_port(55000)
+            .build();
+        // take the incoming port before the engine gets to it
+        let context = zmq::Context::new();
+        let squatter = context.socket(zmq::SUB).unwrap();
+        squatter.bind(&config.incoming_tcp_endpoint()).unwrap();
+
+        match start_event_engine(&config) {
+            Err(EngineError::Bind { endpoint, .. }) => {
+                assert_eq!(endpoint, "tcp://127.0.0.1:55559")
+            }
+            Err(e) => panic!("expected a bind error, got: {}", e),
+            Ok(_) => panic!("engine started on a port that is in use"),
+        }
+    }
+
+    #[test]
+    fn test_host_context_receives_events_over_inproc() {
+        let context = zmq::Context::new();
+        let builder = EventEngineBuilder::new()
+            .incoming_port(16559)
+            .outgoing_port(16560)
+            .incoming_inproc("messages-host")
+            .outgoing_inproc("events-host")
+            .sync_port(16000)
+            .context(context.clone());
+        let config = builder.config().clone();
+
+        // the host acts as the external plugin (id 3) using only inproc sockets on its context
+        let sub_socket = context.socket(zmq::SUB).unwrap();
+        sub_socket
+            .connect(&config.outgoing_inproc_endpoint())
+            .unwrap();
+        for sub in ["ImageStoredEvent", "ImageDeletedEvent"] {
+            sub_socket
+                .set_subscribe(&get_event_type_bytes_filter(sub).unwrap())
+                .unwrap();
+        }
+        sub_socket.set_rcvtimeo(10000).unwrap();
+        let sync = context.socket(zmq::REQ).unwrap();
+        sync.connect(&config.sync_inproc_endpoint()).unwrap();
+
+        let engine = thread::spawn(move || builder.start().unwrap());
+        sync.send("ready 3", 0).unwrap();
+        sync.recv_msg(0).unwrap();
+        let engine = engine.join().unwrap();
+
+        assert_eq!(count_image_events(&sub_socket), 5);
+        engine.shutdown().unwrap();
+        // the host context is still usable after the engine has shut down
+        assert!(context.socket(zmq::PUB).is_ok());
+    }
+
+    #[test]
+    fn test_sync_timeout_names_missing_plugin() {
+        // nothing plays the external plugin, so plugin 3 never syncs
+        let config = EventEngineBuilder::new()
+            .incoming_port(17559)
+            .outgoing_port(17560)
+            .sync_port(17000)
+            .sync_timeout(Duration::from_millis(500))
+            .build();
+        match start_event_engine(&config) {
+            Err(EngineError::SyncTimeout { plugin_ids }) => assert_eq!(plugin_ids, vec![3]),
+            Err(e) => panic!("expected a sync timeout, got: {}", e),
+            Ok(_) => panic!("engine started without plugin 3"),
+        }
+    }
+
+    // A log record: its level, message and key-value fields.
+    type CapturedRecord = (log::Level, String, Vec<(String, String)>);
+
+    // Keeps every error record of the test process, for the tests to look for theirs.
+    struct CaptureLogger {
+        records: Mutex<Vec<CapturedRecord>>,
+    }
+
+    impl log::Log for CaptureLogger {
+        fn enabled(&self, metadata: &log::Metadata) -> bool {
+            metadata.level() <= log::Level::Error
+        }
+
+        fn log(&self, record: &log::Record) {
+            struct Fields(Vec<(String, String)>);
+            impl<'kvs> log::kv::VisitSource<'kvs> for Fields {
+                fn visit_pair(
+                    &mut self,
+                    key: log::kv::Key<'kvs>,
+                    value: log::kv::Value<'kvs>,
+                ) -> Result<(), log::kv::Error> {
+                    self.0.push((key.to_string(), value.to_string()));
+                    Ok(())
+                }
+            }
+            if !self.enabled(record.metadata()) {
+                return;
+            }
+            let mut fields = Fields(Vec::new());
+            record.key_values().visit(&mut fields).unwrap();
+            self.records.lock().unwrap().push((
+                record.level(),
+                record.args().to_string(),
+                fields.0,
+            ));
+        }
+
+        fn flush(&self) {}
+    }
+
+    static LOGGER: CaptureLogger = CaptureLogger {
+        records: Mutex::new(Vec::new()),
+    };
+
+    #[test]
+    fn test_sync_timeout_is_logged_as_error() {
+        // the logger is installed once for the whole test process
+        if log::set_logger(&LOGGER).is_ok() {
+            log::set_max_level(log::LevelFilter::Error);
+        }
+        // nothing plays external plugin 27, so it never syncs
+        let mut plugins = PluginRegistry::new();
+        plugins.register_external(27).unwrap();
+        let result = EventEngineBuilder::new()
+            .incoming_port(2559)
+            .outgoing_port(2560)
+            .sync_port(2000)
+            .sync_timeout(Duration::from_millis(200))
+            .plugins(plugins)
+            .start();
+        assert!(matches!(result, Err(EngineError::SyncTimeout { .. })));
+        let records = LOGGER.records.lock().unwrap();
+        let field = ("plugin_ids".to_string(), "[27]".to_string());
+        assert!(
+            records
+                .iter()
+                .any(|(level, _, fields)| *level == log::Level::Error && fields.contains(&field)),
+            "no error record for plugin 27 in {:?}",
+            records
+        );
+    }
+
+    #[test]
+    fn test_skip_missing_external_plugins() {
+        let config = EventEngineBuilder::new()
+            .incoming_port(18559)
+            .outgoing_port(18560)
+            .sync_port(18000)
+            .sync_timeout(Duration::from_millis(500))
+            .skip_missing_external_plugins(true)
+            .build();
+        let engine = start_event_engine(&config).unwrap();
+        assert!(engine.is_running());
+        engine.shutdown().unwrap();
+    }
+
+    // A REQ socket connected to the TCP sync socket of an engine running with `config`.
+    fn connect_sync_socket(context: &zmq::Context, config: &EngineConfig) -> Socket {
+        let sync = context.socket(zmq::REQ).unwrap();
+        sync.connect(&format!("tcp://localhost:{}", config.sync_port))
+            .unwrap();
+        sync.set_rcvtimeo(5000).unwrap();
+        sync
+    }
+
+    #[test]
+    fn test_plugins_sync_out_of_order() {
+        let mut plugins = PluginRegistry::new();
+        for plugin_id in [5, 2, 9] {
+            plugins.register_external(plugin_id).unwrap();
+        }
+        let builder = EventEngineBuilder::new()
+            .incoming_port(21559)
+            .outgoing_port(21560)
+            .sync_port(21000)
+            .plugins(plugins);
+        let config = builder.config().clone();
+        let engine = thread::spawn(move || builder.start());
+
+        let context = zmq::Context::new();
+        let sync_sockets: Vec<Socket> = [9, 5, 2]
+            .iter()
+            .map(|plugin_id| {
+                let sync = connect_sync_socket(&context, &config);
+                sync.send(format!("ready {}", plugin_id).as_str(), 0)
+                    .unwrap();
+                sync
+            })
+            .collect();
+        for sync in &sync_sockets {
+            assert_eq!(sync.recv_string(0).unwrap().unwrap(), "ok");
+        }
+        engine.join().unwrap().unwrap().shutdown().unwrap();
+    }
+
+    #[test]
+    fn test_duplicate_ready_rejected() {
+        let mut plugins = PluginRegistry::new();
+        plugins
+            .register(1, &[], |_, _, _| Ok(()))
+            .unwrap()
+            .register_external(4)
+            .unwrap()
+            .register_external(5)
+            .unwrap();
+        let builder = EventEngineBuilder::new()
+            .incoming_port(22559)
+            .outgoing_port(22560)
+            .sync_port(22000)
+            .plugins(plugins);
+        let config = builder.config().clone();
+        let engine = thread::spawn(move || builder.start());
+
+        // two sockets claim to be plugin 4; whichever the engine hears from second is rejected
+        let context = zmq::Context::new();
+        let first = connect_sync_socket(&context, &config);
+        let second = connect_sync_socket(&context, &config);
+        first.send("ready 4", 0).unwrap();
+        second.send("ready 4", 0).unwrap();
+        let mut items = [
+            first.as_poll_item(zmq::POLLIN),
+            second.as_poll_item(zmq::POLLIN),
+        ];
+        assert_eq!(zmq::poll(&mut items, 5000).unwrap(), 1);
+        let (rejected, accepted) = if items[0].is_readable() {
+            (first, second)
+        } else {
+            (second, first)
+        };
+        assert_eq!(
+            rejected.recv_string(0).unwrap().unwrap(),
+            "rejected: plugin 4 already synced"
+        );
+
+        // ids that are not registered, plugins started by the engine and malformed messages are
+        // rejected too
+        for (msg, reply) in [
+            ("ready 6", "rejected: plugin 6 is not registered"),
+            ("ready 1", "rejected: plugin 1 syncs over inproc"),
+            (
+                "ready",
+                "rejected: expected \"ready <plugin_id>\", got \"ready\"",
+            ),
+            // the engine could not decode the events of a plugin of a newer schema version
+            (
+                "ready 5 3-3",
+                "rejected: plugin 5 decodes schema versions 3-3, the engine 1-2",
+            ),
+            (
+                "ready 5 latest",
+                "rejected: plugin 5 decodes schema versions latest, the engine 1-2",
+            ),
+        ] {
+            let sync = connect_sync_socket(&context, &config);
+            sync.send(msg, 0).unwrap();
+            assert_eq!(sync.recv_string(0).unwrap().unwrap(), reply);
+        }
+
+        let last = connect_sync_socket(&context, &config);
+        last.send("ready 5", 0).unwrap();
+        assert_eq!(accepted.recv_string(0).unwrap().unwrap(), "ok");
+        assert_eq!(last.recv_string(0).unwrap().unwrap(), "ok");
+        engine.join().unwrap().unwrap().shutdown().unwrap();
+    }
+
+    #[test]
+    fn test_plugin_asking_for_a_probe_is_released_once_it_confirms_one() {
+        let mut plugins = PluginRegistry::new();
+        plugins.register_external(4).unwrap();
+        let builder = EventEngineBuilder::new()
+            .incoming_port(22659)
+            .outgoing_port(22660)
+            .sync_port(22100)
+            .plugins(plugins);
+        let config = builder.config().clone();
+        let engine = thread::spawn(move || builder.start());
+
+        let context = zmq::Context::new();
+        let sub = context.socket(zmq::SUB).unwrap();
+        let probe = get_event_type_bytes_filter("SyncProbeEvent").unwrap();
+        sub.set_subscribe(&probe).unwrap();
+        sub.set_rcvtimeo(5000).unwrap();
+        sub.connect(&format!("tcp://localhost:{}", config.outgoing_port))
+            .unwrap();
+        let sync = connect_sync_socket(&context, &config);
+        sync.send("ready 4 1-2 probe", 0).unwrap();
+        assert_eq!(sync.recv_string(0).unwrap().unwrap(), SYNC_PROBE);
+
+        // the probes keep coming until the plugin confirms one
+        for _ in 0..2 {
+            let frames = recv_event_frames(&sub, 0).unwrap();
+            match parse_event_messages(frames) {
+                Some(("SyncProbeEvent", _, payload)) => {
+                    assert!(matches!(Event::decode(&payload), Ok(Event::SyncProbe(_))))
+                }
+                event => panic!("expected a probe, got {:?}", event),
+            }
+        }
+        sync.send("probed 4", 0).unwrap();
+        assert_eq!(sync.recv_string(0).unwrap().unwrap(), "ok");
+        engine.join().unwrap().unwrap().shutdown().unwrap();
+    }
+
+    #[test]
+    fn test_confirms_probe() {
+        assert!(confirms_probe(b"ready 4 probe"));
+        assert!(confirms_probe(b"ready 4 1-6 probe"));
+        assert!(!confirms_probe(b"ready 4"));
+        assert!(!confirms_probe(b"ready 4 1-6"));
+        assert!(!confirms_probe(b"readyprobe"));
+        assert_eq!(parse_sync_msg(b"ready 4 1-6 probe"), Some((4, Some("1-6"))));
+        assert_eq!(parse_sync_msg(b"ready 4 probe"), Some((4, None)));
+    }
+
+    #[test]
+    fn test_internal_plugins_do_not_bind_tcp_sync_ports() {
+        // something else holds the default sync port and the ones after it
+        let context = zmq::Context::new();
+        let squatters: Vec<Socket> = (5000..5003)
+            .map(|port| {
+                let squatter = context.socket(zmq::REP).unwrap();
+                // the port may just as well be taken by another process already
+                let _ = squatter.bind(&format!("tcp://*:{}", port));
+                squatter
+            })
+            .collect();
+
+        let mut plugins = PluginRegistry::new();
+        plugins
+            .register_plugin(Box::new(crate::new_image_plugin::NewImagePlugin::new(0)))
+            .unwrap()
+            .register_plugin(Box::new(crate::image_score_plugin::ImageScorePlugin::new(
+                1,
+            )))
+            .unwrap()
+            .register_plugin(Box::new(crate::image_store_plugin::ImageStorePlugin::new(
+                2,
+            )))
+            .unwrap();
+        let engine = EventEngineBuilder::new()
+            .incoming_port(23559)
+            .outgoing_port(23560)
+            .plugins(plugins)
+            .start()
+            .unwrap();
+        assert!(engine.is_running());
+        engine.shutdown().unwrap();
+        drop(squatters);
+    }
+
+    #[test]
+    fn test_closure_plugin_with_captured_sender() {
+        let (uuid_tx, uuid_rx) = std::sync::mpsc::channel();
+        let mut plugins = PluginRegistry::new();
+        plugins
+            .register(0, &[], crate::new_image_plugin::start)
+            .unwrap()
+            .register(
+                1,
+                &["NewImageEvent"],
+                move |_pub_socket, sub_socket, _bldr| {
+                    let (_, payload) = recv_event_msg(sub_socket)?;
+                    let event = bytes_to_event(&payload)?;
+                    let image_uuid = event
+                        .event_as_new_image_event()
+                        .and_then(|e| e.image_uuid())
+                        .map(|uuid| uuid.to_string());
+                    uuid_tx.send(image_uuid).unwrap();
+                    Ok(())
+                },
+            )
+            .unwrap();
+        let engine = EventEngineBuilder::new()
+            .incoming_port(19559)
+            .outgoing_port(19560)
+            .sync_port(19000)
+            .plugins(plugins)
+            .start()
+            .unwrap();
+
+        let image_uuid = uuid_rx
+            .recv_timeout(Duration::from_secs(10))
+            .expect("closure plugin did not forward an event")
+            .expect("closure plugin got an event without an image uuid");
+        assert_eq!(image_uuid.len(), 36);
+        engine.shutdown().unwrap();
+    }
+
+    // An engine running `plugins` on its own inproc endpoints, named after `name`.
+    fn start_inproc_engine(name: &str, plugins: PluginRegistry) -> EngineHandle {
+        EventEngineBuilder::new()
+            .incoming_inproc(&format!("messages-{}", name))
+            .outgoing_inproc(&format!("events-{}", name))
+            .transport(Transport::InprocOnly)
+            .plugins(plugins)
+            .start()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_plugin_thread_is_named_and_sized() {
+        let (name_tx, name_rx) = std::sync::mpsc::channel();
+        let mut plugins = PluginRegistry::new();
+        plugins
+            .register(1, &[], move |_pub_socket, _sub_socket, _bldr| {
+                // more than the default stack of 2 MiB
+                let buffer = [1u8; 4 << 20];
+                std::hint::black_box(&buffer);
+                let name = thread::current().name().map(str::to_string);
+                name_tx.send(name).unwrap();
+                Ok(())
+            })
+            .unwrap()
+            .stack_size(1, 8 << 20)
+            .unwrap();
+        let engine = start_inproc_engine("stack", plugins);
+
+        let name = name_rx
+            .recv_timeout(Duration::from_secs(10))
+            .expect("plugin did not run");
+        assert_eq!(name.as_deref(), Some("plugin-1"));
+        let results = engine.wait_for_plugins(Duration::from_secs(5));
+        assert!(matches!(results.get(&1), Some(Ok(()))), "{:?}", results);
+        engine.shutdown().unwrap();
+    }
+
+    #[test]
+    fn test_plugins_start_after_their_dependencies() {
+        let started = Arc::new(Mutex::new(Vec::new()));
+        let mut plugins = PluginRegistry::new();
+        // registered the other way round from how they depend on each other
+        for plugin_id in [3, 2, 1] {
+            let started = Arc::clone(&started);
+            plugins
+                .register(plugin_id, &[], move |_pub_socket, _sub_socket, _bldr| {
+                    started.lock().unwrap().push(plugin_id);
+                    Ok(())
+                })
+                .unwrap();
+        }
+        plugins
+            .depends_on(3, &[2])
+            .unwrap()
+            .depends_on(2, &["plugin-1"])
+            .unwrap();
+        let engine = start_inproc_engine("chain", plugins);
+
+        let results = engine.wait_for_plugins(Duration::from_secs(5));
+        assert_eq!(results.len(), 3, "plugins did not exit: {:?}", results);
+        assert_eq!(*started.lock().unwrap(), [1, 2, 3]);
+        engine.shutdown().unwrap();
+    }
+
+    #[test]
+    fn test_dependency_cycle_is_refused() {
+        let mut plugins = PluginRegistry::new();
+        plugins
+            .register(1, &[], crate::new_image_plugin::start)
+            .unwrap()
+            .register(2, &[], crate::new_image_plugin::start)
+            .unwrap()
+            .depends_on(1, &[2])
+            .unwrap()
+            .depends_on(2, &[1])
+            .unwrap();
+        let started = EventEngineBuilder::new()
+            .incoming_inproc("messages-cycle")
+            .outgoing_inproc("events-cycle")
+            .transport(Transport::InprocOnly)
+            .plugins(plugins)
+            .start();
+        match started {
+            Err(EngineError::DependencyCycle { cycle }) => {
+                assert_eq!(cycle, ["plugin-1 (1)", "plugin-2 (2)"])
+            }
+            Err(e) => panic!("expected a dependency cycle, got: {}", e),
+            Ok(_) => panic!("started plugins depending on each other"),
+        }
+    }
+
+    #[test]
+    fn test_misspelled_subscription_is_refused() {
+        let misspelled = || {
+            let mut plugins = PluginRegistry::new();
+            plugins
+                .register(1, &["ImageScorredEvent"], |_pub_socket, _sub_socket, _bldr| Ok(()))
+                .unwrap();
+            plugins
+        };
+        let started = EventEngineBuilder::new()
+            .incoming_inproc("messages-misspelled")
+            .outgoing_inproc("events-misspelled")
+            .transport(Transport::InprocOnly)
+            .plugins(misspelled())
+            .start();
+        match started {
+            Err(e @ EngineError::UnknownSubscriptions { .. }) => assert!(
+                e.to_string().contains("did you mean ImageScoredEvent?"),
+                "{}",
+                e
+            ),
+            Err(e) => panic!("expected unknown subscriptions, got: {}", e),
+            Ok(_) => panic!("started a plugin subscribing to an unknown event type"),
+        }
+
+        // unless unknown subscriptions are allowed
+        let engine = EventEngineBuilder::new()
+            .incoming_inproc("messages-unknown-allowed")
+            .outgoing_inproc("events-unknown-allowed")
+            .transport(Transport::InprocOnly)
+            .allow_unknown_subscriptions(true)
+            .plugins(misspelled())
+            .start()
+            .unwrap();
+        let results = engine.wait_for_plugins(Duration::from_secs(5));
+        assert!(matches!(results.get(&1), Some(Ok(()))), "{:?}", results);
+        engine.shutdown().unwrap();
+    }
+
+    // Records the type of every event it gets, until the engine shuts down.
+    struct TypeRecorder {
+        id: i32,
+        subscriptions: &'static [&'static str],
+        types_tx: std::sync::mpsc::Sender<String>,
+    }
+
+    impl Plugin for TypeRecorder {
+        fn id(&self) -> i32 {
+            self.id
+        }
+
+        fn name(&self) -> &str {
+            "type-recorder"
+        }
+
+        fn subscriptions(&self) -> &[&str] {
+            self.subscriptions
+        }
+
+        fn start(
+            self: Box<Self>,
+            mut ctx: PluginContext,
+        ) -> Result<(), crate::plugin::PluginError> {
+            loop {
+                let msg = ctx.next_event()?;
+                if msg.event_type == "PluginTerminateEvent" {
+                    return Ok(());
+                }
+                let _ = self.types_tx.send(msg.event_type);
+            }
+        }
+    }
+
+    // A NewImageEvent, an ImageScoredEvent and an ImageStoredEvent of one image.
+    fn image_events() -> [Event; 3] {
+        let image_uuid = uuid::Uuid::new_v4().to_string();
+        [
+            Event::NewImage(crate::events::NewImage {
+                image_uuid: image_uuid.clone(),
+                image_format: "png".to_string(),
+                image: vec![1, 2, 3],
+                location: None,
+            }),
+            Event::ImageScored(crate::events::ImageScored {
+                image_uuid: image_uuid.clone(),
+                scores: Vec::new(),
+            }),
+            Event::ImageStored(ImageStored {
+                image_uuid,
+                path: "/tmp/image.png".to_string(),
+                deduplicated: false,
+            }),
+        ]
+    }
+
+    // The types a TypeRecorder records, up to the first of type `last`. The events of a publisher
+    // arrive in order, so none published before that one is missed.
+    fn received_until(types_rx: &Receiver<String>, last: &str) -> Vec<String> {
+        let mut types = Vec::new();
+        while let Ok(event_type) = types_rx.recv_timeout(Duration::from_secs(5)) {
+            let done = event_type == last;
+            types.push(event_type);
+            if done {
+                break;
+            }
+        }
+        types
+    }
+
+    #[test]
+    fn test_wildcard_subscriptions() {
+        let (prefix_tx, prefix_rx) = std::sync::mpsc::channel();
+        let (all_tx, all_rx) = std::sync::mpsc::channel();
+        let mut plugins = PluginRegistry::new();
+        plugins
+            .register_plugin(Box::new(TypeRecorder {
+                id: 1,
+                subscriptions: &["Image*"],
+                types_tx: prefix_tx,
+            }))
+            .unwrap()
+            .register_plugin(Box::new(TypeRecorder {
+                id: 2,
+                subscriptions: &["*"],
+                types_tx: all_tx,
+            }))
+            .unwrap();
+        let engine = start_inproc_engine("wildcards", plugins);
+
+        for event in image_events() {
+            engine.publish(&event).unwrap();
+        }
+        assert_eq!(
+            received_until(&prefix_rx, "ImageStoredEvent"),
+            ["ImageScoredEvent", "ImageStoredEvent"]
+        );
+        assert_eq!(
+            received_until(&all_rx, "ImageStoredEvent"),
+            [
+                "EngineStartedEvent",
+                "NewImageEvent",
+                "ImageScoredEvent",
+                "ImageStoredEvent"
+            ]
+        );
+        engine.shutdown().unwrap();
+    }
+
+    #[test]
+    fn test_exclusion_subscriptions() {
+        let (all_but_tx, all_but_rx) = std::sync::mpsc::channel();
+        let (prefix_but_tx, prefix_but_rx) = std::sync::mpsc::channel();
+        let mut plugins = PluginRegistry::new();
+        plugins
+            .register_plugin(Box::new(TypeRecorder {
+                id: 1,
+                subscriptions: &["!ImageScoredEvent"],
+                types_tx: all_but_tx,
+            }))
+            .unwrap()
+            .register_plugin(Box::new(TypeRecorder {
+                id: 2,
+                subscriptions: &["Image*", "!ImageStoredEvent"],
+                types_tx: prefix_but_tx,
+            }))
+            .unwrap();
+        let engine = start_inproc_engine("exclusions", plugins);
+
+        // a type registered after startup was not excluded, so it is received
+        let audit = crate::event_type_registry::EventTypeRegistry::global()
+            .register("ExclusionAuditEvent")
+            .unwrap();
+        let mut events = image_events().to_vec();
+        events.push(audit.event(vec![1]));
+        events.push(Event::ImageDeleted(crate::events::ImageDeleted {
+            image_uuid: uuid::Uuid::new_v4().to_string(),
+            existed: true,
+        }));
+        for event in &events {
+            engine.publish(event).unwrap();
+        }
+        assert_eq!(
+            received_until(&all_but_rx, "ImageDeletedEvent"),
+            [
+                "EngineStartedEvent",
+                "NewImageEvent",
+                "ImageStoredEvent",
+                "ExclusionAuditEvent",
+                "ImageDeletedEvent"
+            ]
+        );
+        assert_eq!(
+            received_until(&prefix_but_rx, "ImageDeletedEvent"),
+            ["ImageScoredEvent", "ImageDeletedEvent"]
+        );
+        engine.shutdown().unwrap();
+    }
+
+    #[test]
+    fn test_engine_started_comes_first_and_engine_stopping_last() {
+        // the images of a plugin released before the EngineStartedEvent would come first now
+        // and then, so over a few runs
+        for run in 0..20 {
+            let (lifecycle_tx, lifecycle_rx) = std::sync::mpsc::channel();
+            let (all_tx, all_rx) = std::sync::mpsc::channel();
+            let (started_tx, started_rx) = std::sync::mpsc::channel();
+            let mut plugins = PluginRegistry::new();
+            plugins
+                .register(0, &[], crate::new_image_plugin::start)
+                .unwrap()
+                .register_plugin(Box::new(TypeRecorder {
+                    id: 1,
+                    subscriptions: &["EngineStartedEvent", "NewImageEvent", "EngineStoppingEvent"],
+                    types_tx: lifecycle_tx,
+                }))
+                .unwrap()
+                .register_plugin(Box::new(TypeRecorder {
+                    id: 2,
+                    subscriptions: &["*"],
+                    types_tx: all_tx,
+                }))
+                .unwrap()
+                .register(
+                    3,
+                    &["EngineStartedEvent"],
+                    move |_pub_socket, sub_socket, _bldr| loop {
+                        // probes published while the plugins synced may still be queued
+                        let (event_type, payload) = recv_event_msg(sub_socket)?;
+                        if event_type == "EngineStartedEvent" {
+                            started_tx.send(Event::decode(&payload).unwrap()).unwrap();
+                            return Ok(());
+                        }
+                    },
+                )
+                .unwrap();
+            let engine = EventEngineBuilder::new()
+                .incoming_inproc(&format!("messages-lifecycle-{}", run))
+                .outgoing_inproc(&format!("events-lifecycle-{}", run))
+                .transport(Transport::InprocOnly)
+                .engine_id(7)
+                .plugins(plugins)
+                .start()
+                .unwrap();
+            match started_rx.recv_timeout(Duration::from_secs(5)).unwrap() {
+                Event::EngineStarted(started) => {
+                    assert_eq!(started.engine_id, 7);
+                    assert_eq!(started.protocol_version, SCHEMA_VERSION);
+                    assert!(started.started_at_ms <= now_ms());
+                }
+                event => panic!("expected an EngineStartedEvent, got {:?}", event),
+            }
+            // the images are forwarded by the time the plugin publishing them is finished
+            let published = Instant::now();
+            while engine.plugin_status(0) != Some(PluginStatus::Finished)
+                && published.elapsed() < Duration::from_secs(5)
+            {
+                thread::sleep(Duration::from_millis(1));
+            }
+            thread::sleep(Duration::from_millis(10));
+            engine.shutdown_with_reason("end of test").unwrap();
+
+            let images = ["NewImageEvent"; crate::new_image_plugin::DEFAULT_IMAGES];
+            let expected: Vec<&str> = std::iter::once("EngineStartedEvent")
+                .chain(images)
+                .chain(std::iter::once("EngineStoppingEvent"))
+                .collect();
+            assert_eq!(lifecycle_rx.try_iter().collect::<Vec<_>>(), expected);
+            assert_eq!(all_rx.try_iter().collect::<Vec<_>>(), expected);
+        }
+    }
+
+    // Publish a NewImageEvent, and an ImageScoredEvent that gets it stored, of the image
+    // `image_uuid`.
+    fn publish_labrador(engine: &EngineHandle, image_uuid: &str) {
+        let image = Event::NewImage(crate::events::NewImage {
+            image_uuid: image_uuid.to_string(),
+            image_format: "png".to_string(),
+            image: vec![1, 2, 3],
+            location: None,
+        });
+        let scored = Event::ImageScored(crate::events::ImageScored {
+            image_uuid: image_uuid.to_string(),
+            scores: vec![crate::events::ImageScore {
+                label: "labrador".to_string(),
+                probability: 0.9,
+            }],
+        });
+        engine.publish(&image).unwrap();
+        engine.publish(&scored).unwrap();
+    }
+
+    // Start an engine running an ImageStorePlugin (id 2) storing `images` images in `storage`,
+    // whose events are held or dropped as `policy` says while it is paused.
+    fn start_paused_store(
+        name: &str,
+        policy: PausePolicy,
+        images: usize,
+        storage: &InMemoryStore,
+    ) -> EngineHandle {
+        let mut plugins = PluginRegistry::new();
+        let store = crate::image_store_plugin::ImageStorePlugin::new(2)
+            .images(images)
+            .storage(Box::new(storage.clone()));
+        plugins.register_plugin(Box::new(store)).unwrap();
+        let engine = EventEngineBuilder::new()
+            .incoming_inproc(&format!("messages-{}", name))
+            .outgoing_inproc(&format!("events-{}", name))
+            .transport(Transport::InprocOnly)
+            .pause_policy(policy)
+            .plugins(plugins)
+            .start()
+            .unwrap();
+        engine.pause_plugin(2).unwrap();
+        engine
+    }
+
+    // Wait up to 5 s for the plugin `plugin_id` to have dropped `dropped` events while paused.
+    fn wait_for_paused_dropped(engine: &EngineHandle, plugin_id: i32, dropped: u64) -> u64 {
+        let started = Instant::now();
+        while engine.paused_dropped(plugin_id) < dropped
+            && started.elapsed() < Duration::from_secs(5)
+        {
+            thread::sleep(Duration::from_millis(1));
+        }
+        engine.paused_dropped(plugin_id)
+    }
+
+    #[test]
+    fn test_only_the_plugins_of_the_profile_start() {
+        let mut plugins = PluginRegistry::new();
+        plugins
+            .register_plugin(Box::new(crate::new_image_plugin::NewImagePlugin::new(0)))
+            .unwrap()
+            .register_plugin(Box::new(crate::image_score_plugin::ImageScorePlugin::new(
+                1,
+            )))
+            .unwrap()
+            .register_plugin(Box::new(crate::image_store_plugin::ImageStorePlugin::new(
+                2,
+            )))
+            .unwrap()
+            .register_plugin(Box::new(crate::metrics_plugin::MetricsPlugin::new(
+                4, 24571,
+            )))
+            .unwrap()
+            .depends_on(0, &[1])
+            .unwrap()
+            .profile("ingest-only", &[0, 4])
+            .unwrap()
+            .profile("scoring", &[1, 4])
+            .unwrap();
+        let engine = EventEngineBuilder::new()
+            .incoming_inproc("messages-profile")
+            .outgoing_inproc("events-profile")
+            .transport(Transport::InprocOnly)
+            .with_profile("ingest-only")
+            .plugins(plugins)
+            .start()
+            .unwrap();
+        let scored = engine.subscribe(&["ImageScoredEvent"]).unwrap();
+        let mut threads: Vec<i32> = engine
+            .plugin_threads
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(plugin_id, _)| *plugin_id)
+            .collect();
+        threads.sort();
+        assert_eq!(threads, [0, 4]);
+        assert_eq!(engine.plugin_status(1), None);
+        assert_eq!(engine.plugin_status(2), None);
+
+        // the new image plugin publishes its images, and nothing scores them
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while engine.plugin_status(0) != Some(PluginStatus::Finished) && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(engine.plugin_status(0), Some(PluginStatus::Finished));
+        assert!(scored.recv_timeout(Duration::from_millis(200)).is_err());
+        engine.shutdown().unwrap();
+
+        assert!(matches!(
+            EventEngineBuilder::new()
+                .incoming_inproc("messages-no-profile")
+                .outgoing_inproc("events-no-profile")
+                .transport(Transport::InprocOnly)
+                .with_profile("full")
+                .plugins(PluginRegistry::new())
+                .start(),
+            Err(EngineError::UnknownProfile { .. })
+        ));
+    }
+
+    #[test]
+    fn test_a_paused_plugin_gets_the_events_it_held_once_resumed() {
+        let storage = InMemoryStore::new();
+        // room for the events of one image
+        let engine = start_paused_store("paused-buffer", PausePolicy::Buffer(2), 1, &storage);
+        publish_labrador(&engine, "first");
+        publish_labrador(&engine, "second");
+        // those of the second image do not fit
+        assert_eq!(wait_for_paused_dropped(&engine, 2, 2), 2);
+        assert!(storage.is_empty());
+
+        engine.resume_plugin(2).unwrap();
+        let results = engine.wait_for_plugins(Duration::from_secs(5));
+        assert!(matches!(results.get(&2), Some(Ok(()))), "{:?}", results);
+        assert_eq!(storage.get("first").unwrap(), Some(vec![1, 2, 3]));
+        assert_eq!(storage.len(), 1);
+        assert_eq!(engine.paused_dropped(2), 2);
+        assert!(matches!(
+            engine.pause_plugin(7),
+            Err(EngineError::UnknownPluginId { plugin_id: 7 })
+        ));
+        engine.shutdown().unwrap();
+    }
+
+    #[test]
+    fn test_a_paused_plugin_dropping_its_events_gets_those_after_it_is_resumed() {
+        let storage = InMemoryStore::new();
+        let engine = start_paused_store("paused-drop", PausePolicy::Drop, 1, &storage);
+        publish_labrador(&engine, "first");
+        publish_labrador(&engine, "second");
+        assert_eq!(wait_for_paused_dropped(&engine, 2, 4), 4);
+
+        engine.resume_plugin(2).unwrap();
+        publish_labrador(&engine, "third");
+        let results = engine.wait_for_plugins(Duration::from_secs(5));
+        assert!(matches!(results.get(&2), Some(Ok(()))), "{:?}", results);
+        assert_eq!(storage.get("third").unwrap(), Some(vec![1, 2, 3]));
+        assert_eq!(storage.len(), 1);
+        assert_eq!(engine.paused_dropped(2), 4);
+        engine.shutdown().unwrap();
+    }
+
+    #[test]
+    fn test_independent_plugins_start_together() {
+        // each plugin only finishes once it has heard from the other, which it cannot if they
+        // are started one after the other
+        let (tx_1, rx_1) = std::sync::mpsc::channel();
+        let (tx_2, rx_2) = std::sync::mpsc::channel();
+        let mut plugins = PluginRegistry::new();
+        for (plugin_id, tx, rx) in [(1, tx_2, rx_1), (2, tx_1, rx_2)] {
+            plugins
+                .register(plugin_id, &[], move |_pub_socket, _sub_socket, _bldr| {
+                    tx.send(plugin_id).unwrap();
+                    rx.recv_timeout(Duration::from_secs(5))
+                        .map(drop)
+                        .map_err(std::io::Error::other)
+                })
+                .unwrap();
+        }
+        let engine = start_inproc_engine("independent", plugins);
+
+        let results = engine.wait_for_plugins(Duration::from_secs(10));
+        assert_eq!(results.len(), 2, "plugins did not exit: {:?}", results);
+        assert!(results.values().all(|result| result.is_ok()), "{:?}", results);
+        engine.shutdown().unwrap();
+    }
+
+    // A plugin with its own state: counts the scored images it sees and reports the count
+    struct ScoreCounter {
+        expected: usize,
+        count_tx: std::sync::mpsc::Sender<usize>,
+    }
+
+    impl Plugin for ScoreCounter {
+        fn id(&self) -> i32 {
+            3
+        }
+
+        fn name(&self) -> &str {
+            "score-counter"
+        }
+
+        fn subscriptions(&self) -> &[&str] {
+            &["ImageScoredEvent"]
+        }
+
+        fn start(
+            self: Box<Self>,
+            mut ctx: PluginContext,
+        ) -> Result<(), crate::plugin::PluginError> {
+            let mut count = 0;
+            while count < self.expected {
+                if ctx.next_event()?.event_type == "ImageScoredEvent" {
+                    count += 1;
+                }
+            }
+            self.count_tx.send(count).unwrap();
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_trait_plugin_in_image_pipeline() {
+        let (count_tx, count_rx) = std::sync::mpsc::channel();
+        let mut plugins = PluginRegistry::new();
+        plugins
+            .register_plugin(Box::new(crate::new_image_plugin::NewImagePlugin::new(0)))
+            .unwrap()
+            .register_plugin(Box::new(crate::image_score_plugin::ImageScorePlugin::new(
+                1,
+            )))
+            .unwrap()
+            .register_plugin(Box::new(crate::image_store_plugin::ImageStorePlugin::new(
+                2,
+            )))
+            .unwrap()
+            .register_plugin(Box::new(ScoreCounter {
+                expected: 5,
+                count_tx,
+            }))
+            .unwrap();
+        let engine = EventEngineBuilder::new()
+            .incoming_port(20559)
+            .outgoing_port(20560)
+            .sync_port(20000)
+            .plugins(plugins)
+            .start()
+            .unwrap();
+        assert_eq!(count_rx.recv_timeout(Duration::from_secs(10)).unwrap(), 5);
+        engine.shutdown().unwrap();
+    }
+
+    #[test]
+    fn test_panicking_plugin_is_reported() {
+        let (failed_tx, failed_rx) = std::sync::mpsc::channel();
+        let mut plugins = PluginRegistry::new();
+        plugins
+            .register(0, &[], crate::new_image_plugin::start)
+            .unwrap()
+            .register(1, &["NewImageEvent"], |_pub_socket, sub_socket, _bldr| {
+                recv_event_msg(sub_socket)?;
+                recv_event_msg(sub_socket)?;
+                panic!("cannot handle a second image");
+            })
+            .unwrap()
+            .register(
+                2,
+                &["PluginFailedEvent"],
+                move |_pub_socket, sub_socket, _bldr| {
+                    let (_, payload) = recv_event_msg(sub_socket)?;
+                    failed_tx
+                        .send(crate::events::Event::decode(&payload).unwrap())
+                        .unwrap();
+                    Ok(())
+                },
+            )
+            .unwrap();
+        let engine = EventEngineBuilder::new()
+            .incoming_port(24559)
+            .outgoing_port(24560)
+            .plugins(plugins)
+            .start()
+            .unwrap();
+
+        let failed = failed_rx.recv_timeout(Duration::from_secs(10)).unwrap();
+        assert_eq!(
+            failed,
+            crate::events::Event::PluginFailed(crate::events::PluginFailed {
+                plugin_id: 1,
+                message: "cannot handle a second image".to_string(),
+            })
+        );
+        assert_eq!(
+            engine.plugin_status(1),
+            Some(PluginStatus::Failed(
+                "cannot handle a second image".to_string()
+            ))
+        );
+        assert_eq!(engine.plugin_status(42), None);
+        engine.shutdown().unwrap();
+    }
+
+    // Fails the first `failures` times it is started, then returns straight away.
+    struct FlakyPlugin {
+        failures: usize,
+        runs: Arc<AtomicUsize>,
+    }
+
+    impl Plugin for FlakyPlugin {
+        fn id(&self) -> i32 {
+            1
+        }
+
+        fn name(&self) -> &str {
+            "flaky"
+        }
+
+        fn subscriptions(&self) -> &[&str] {
+            &[]
+        }
+
+        fn start(self: Box<Self>, _ctx: PluginContext) -> Result<(), PluginError> {
+            let run = self.runs.fetch_add(1, Ordering::SeqCst) + 1;
+            if run <= self.failures {
+                return Err(PluginError::Other(format!("run {} fails", run)));
+            }
+            Ok(())
+        }
+    }
+
+    // Start an engine running the flaky plugin (1) with the given restart policy, and an
+    // observer (2) that forwards the engine's failed and restarted events. Returns the number
+    // of times the flaky plugin was started so far, and the forwarded events.
+    fn start_flaky_engine(
+        incoming_port: u16,
+        failures: usize,
+        restart_policy: RestartPolicy,
+    ) -> (EngineHandle, Arc<AtomicUsize>, Receiver<Event>) {
+        let runs = Arc::new(AtomicUsize::new(0));
+        let factory_runs = Arc::clone(&runs);
+        let (event_tx, event_rx) = std::sync::mpsc::channel();
+        let mut plugins = PluginRegistry::new();
+        plugins
+            .register_restartable(restart_policy, move || {
+                Box::new(FlakyPlugin {
+                    failures,
+                    runs: Arc::clone(&factory_runs),
+                })
+            })
+            .unwrap()
+            .register(
+                2,
+                &["PluginFailedEvent", "PluginRestartedEvent"],
+                move |_pub_socket, sub_socket, _bldr| loop {
+                    let (event_type, payload) = recv_event_msg(sub_socket)?;
+                    if event_type == "PluginTerminateEvent" {
+                        return Ok(());
+                    }
+                    let _ = event_tx.send(Event::decode(&payload).unwrap());
+                },
+            )
+            .unwrap();
+        let engine = EventEngineBuilder::new()
+            .incoming_port(incoming_port)
+            .outgoing_port(incoming_port + 1)
+            .plugins(plugins)
+            .start()
+            .unwrap();
+        (engine, runs, event_rx)
+    }
+
+    fn failed(message: &str) -> Event {
+        Event::PluginFailed(PluginFailed {
+            plugin_id: 1,
+            message: message.to_string(),
+        })
+    }
+
+    fn restarted(restart_count: u32) -> Event {
+        Event::PluginRestarted(PluginRestarted {
+            plugin_id: 1,
+            restart_count,
+        })
+    }
+
+    #[test]
+    fn test_failed_plugin_is_restarted() {
+        let policy = RestartPolicy::Always {
+            max_retries: 3,
+            backoff: Duration::from_millis(50),
+        };
+        let (engine, runs, event_rx) = start_flaky_engine(26559, 1, policy);
+
+        let timeout = Duration::from_secs(10);
+        assert_eq!(
+            event_rx.recv_timeout(timeout).unwrap(),
+            failed("run 1 fails")
+        );
+        assert_eq!(event_rx.recv_timeout(timeout).unwrap(), restarted(1));
+        let deadline = Instant::now() + timeout;
+        while engine.plugin_status(1) != Some(PluginStatus::Finished) && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(engine.plugin_status(1), Some(PluginStatus::Finished));
+        assert_eq!(runs.load(Ordering::SeqCst), 2);
+        engine.shutdown().unwrap();
+    }
+
+    #[test]
+    fn test_restarts_are_capped() {
+        let policy = RestartPolicy::Always {
+            max_retries: 2,
+            backoff: Duration::from_millis(50),
+        };
+        let (engine, runs, event_rx) = start_flaky_engine(27559, usize::MAX, policy);
+
+        let timeout = Duration::from_secs(10);
+        let events: Vec<Event> = (0..5)
+            .map(|_| event_rx.recv_timeout(timeout).unwrap())
+            .collect();
+        assert_eq!(
+            events,
+            vec![
+                failed("run 1 fails"),
+                restarted(1),
+                failed("run 2 fails"),
+                restarted(2),
+                failed("run 3 fails"),
+            ]
+        );
+        // no third restart
+        assert!(event_rx.recv_timeout(Duration::from_millis(500)).is_err());
+        assert_eq!(runs.load(Ordering::SeqCst), 3);
+        assert_eq!(
+            engine.plugin_status(1),
+            Some(PluginStatus::Failed("run 3 fails".to_string()))
+        );
+        engine.shutdown().unwrap();
+    }
+
+    #[test]
+    fn test_host_publishes_new_image_events() {
+        let (scored_tx, scored_rx) = std::sync::mpsc::channel();
+        let mut plugins = PluginRegistry::new();
+        plugins
+            .register_plugin(Box::new(crate::image_score_plugin::ImageScorePlugin::new(
+                1,
+            )))
+            .unwrap()
+            .register(
+                2,
+                &["ImageScoredEvent"],
+                move |_pub_socket, sub_socket, _bldr| loop {
+                    let (event_type, payload) = recv_event_msg(sub_socket)?;
+                    if event_type == "PluginTerminateEvent" {
+                        return Ok(());
+                    }
+                    let _ = scored_tx.send(Event::decode(&payload).unwrap());
+                },
+            )
+            .unwrap();
+        let engine = EventEngineBuilder::new()
+            .incoming_port(28559)
+            .outgoing_port(28560)
+            .plugins(plugins)
+            .start()
+            .unwrap();
+
+        let new_image = |image_uuid: &str| {
+            Event::NewImage(crate::events::NewImage {
+                image_uuid: image_uuid.to_string(),
+                image_format: "png".to_string(),
+                image: vec![1, 2, 3],
+                location: None,
+            })
+        };
+        engine.publish(&new_image("from-main")).unwrap();
+        // the handle can be shared with other threads, which publish on the same socket
+        thread::scope(|s| {
+            s.spawn(|| engine.publish(&new_image("from-thread")).unwrap());
+        });
+
+        let mut image_uuids: Vec<String> = (0..2)
+            .map(
+                |_| match scored_rx.recv_timeout(Duration::from_secs(10)).unwrap() {
+                    Event::ImageScored(scored) => scored.image_uuid,
+                    event => panic!("expected an ImageScoredEvent, got {:?}", event),
+                },
+            )
+            .collect();
+        image_uuids.sort();
+        assert_eq!(image_uuids, vec!["from-main", "from-thread"]);
+        engine.shutdown().unwrap();
+    }
+
+    #[test]
+    fn test_host_subscribes_to_stored_images() {
+        let mut plugins = PluginRegistry::new();
+        plugins
+            .register_plugin(Box::new(crate::image_score_plugin::ImageScorePlugin::new(
+                1,
+            )))
+            .unwrap()
+            .register_plugin(Box::new(crate::image_store_plugin::ImageStorePlugin::new(
+                2,
+            )))
+            .unwrap();
+        let engine = EventEngineBuilder::new()
+            .incoming_port(29559)
+            .outgoing_port(29560)
+            .plugins(plugins)
+            .start()
+            .unwrap();
+
+        assert!(matches!(
+            engine.subscribe(&["ImageStoredEvent", "ImageLostEvent"]),
+            Err(EngineError::UnknownEventType { event_type }) if event_type == "ImageLostEvent"
+        ));
+        let stored_rx = engine.subscribe(&["ImageStoredEvent"]).unwrap();
+        // whether an image is stored or deleted depends on its random score
+        let outcome_rx = engine
+            .subscribe(&["ImageStoredEvent", "ImageDeletedEvent"])
+            .unwrap();
+        // give the subscriptions time to reach the engine
+        thread::sleep(Duration::from_millis(200));
+        for i in 0..3 {
+            engine
+                .publish(&Event::NewImage(crate::events::NewImage {
+                    image_uuid: format!("image-{}", i),
+                    image_format: "png".to_string(),
+                    image: vec![1, 2, 3],
+                    location: None,
+                }))
+                .unwrap();
+        }
+
+        let timeout = Duration::from_secs(10);
+        let expected: Vec<Event> = (0..3)
+            .map(|_| outcome_rx.recv_timeout(timeout).unwrap())
+            .filter(|event| matches!(event, Event::ImageStored(_)))
+            .collect();
+        let stored: Vec<Event> = expected
+            .iter()
+            .map(|_| stored_rx.recv_timeout(timeout).unwrap())
+            .collect();
+        assert_eq!(stored, expected);
+        assert!(stored_rx.recv_timeout(Duration::from_millis(500)).is_err());
+        engine.shutdown().unwrap();
+    }
+
+    #[test]
+    fn test_wait_for_plugins_of_finite_pipeline() {
+        let mut plugins = PluginRegistry::new();
+        plugins
+            .register_plugin(Box::new(crate::new_image_plugin::NewImagePlugin::new(0)))
+            .unwrap()
+            .register_plugin(Box::new(crate::image_score_plugin::ImageScorePlugin::new(
+                1,
+            )))
+            .unwrap()
+            .register_plugin(Box::new(crate::image_store_plugin::ImageStorePlugin::new(
+                2,
+            )))
+            .unwrap()
+            .register(3, &[], |_pub_socket, _sub_socket, _bldr| {
+                Err(std::io::Error::other("no space left for images"))
+            })
+            .unwrap();
+        let engine = EventEngineBuilder::new()
+            .incoming_port(30559)
+            .outgoing_port(30560)
+            .plugins(plugins)
+            .start()
+            .unwrap();
+
+        // every plugin returns once the five images have been generated, scored and stored
+        let results = engine.wait_for_plugins(Duration::from_secs(10));
+        assert_eq!(results.len(), 4);
+        for plugin_id in 0..3 {
+            assert!(results[&plugin_id].is_ok(), "plugin {} failed", plugin_id);
+        }
+        match &results[&3] {
+            Err(PluginError::Io(e)) => assert_eq!(e.to_string(), "no space left for images"),
+            result => panic!("expected plugin 3 to fail, got {:?}", result),
+        }
+        // the results are only returned once
+        assert!(engine.wait_for_plugins(Duration::ZERO).is_empty());
+        engine.shutdown().unwrap();
+    }
+
+    #[test]
+    fn test_an_optional_plugin_failing_to_start_leaves_the_pipeline_running() {
+        let mut plugins = PluginRegistry::new();
+        plugins
+            .register_plugin(Box::new(crate::new_image_plugin::NewImagePlugin::new(0)))
+            .unwrap()
+            .register_plugin(Box::new(crate::image_score_plugin::ImageScorePlugin::new(
+                1,
+            )))
+            .unwrap()
+            .register_plugin(Box::new(crate::image_store_plugin::ImageStorePlugin::new(
+                2,
+            )))
+            .unwrap()
+            // the webhooks, say, with the network down
+            .register(
+                3,
+                &["ImageStoredEvent"],
+                |_pub_socket, _sub_socket, _bldr| {
+                    Err(std::io::Error::other("network is unreachable"))
+                },
+            )
+            .unwrap()
+            .optional(3, true)
+            .unwrap();
+        let engine = start_inproc_engine("optional-failing", plugins);
+
+        // the five images are generated, scored and stored all the same
+        let results = engine.wait_for_plugins(Duration::from_secs(10));
+        assert_eq!(results.len(), 4);
+        for plugin_id in 0..3 {
+            assert!(results[&plugin_id].is_ok(), "plugin {} failed", plugin_id);
+        }
+        assert!(results[&3].is_err());
+        assert_eq!(
+            engine.plugin_status(3),
+            Some(PluginStatus::Failed("network is unreachable".to_string()))
+        );
+        engine.shutdown().unwrap();
+    }
+
+    #[test]
+    fn test_an_optional_plugin_that_cannot_be_spawned_is_skipped() {
+        // no thread gets a stack this large
+        let unspawnable = 1 << 62;
+        let (types_tx, types_rx) = std::sync::mpsc::channel();
+        let mut plugins = PluginRegistry::new();
+        plugins
+            .register_plugin(Box::new(TypeRecorder {
+                id: 1,
+                subscriptions: &["PluginSkippedEvent"],
+                types_tx,
+            }))
+            .unwrap()
+            .register(2, &[], |_pub_socket, _sub_socket, _bldr| Ok(()))
+            .unwrap()
+            .stack_size(2, unspawnable)
+            .unwrap()
+            .optional(2, true)
+            .unwrap()
+            // depending on the skipped plugin does not hold the recorder up
+            .depends_on(1, &[2])
+            .unwrap();
+        let engine = start_inproc_engine("optional-unspawnable", plugins);
+        assert_eq!(
+            types_rx.recv_timeout(Duration::from_secs(5)).unwrap(),
+            "PluginSkippedEvent"
+        );
+        assert!(matches!(
+            engine.plugin_status(2),
+            Some(PluginStatus::Failed(_))
+        ));
+        assert_eq!(engine.plugin_status(1), Some(PluginStatus::Running));
+        engine.shutdown().unwrap();
+
+        // a required plugin that cannot be spawned still keeps the engine from starting
+        let mut plugins = PluginRegistry::new();
+        plugins
+            .register(2, &[], |_pub_socket, _sub_socket, _bldr| Ok(()))
+            .unwrap()
+            .stack_size(2, unspawnable)
+            .unwrap();
+        let started = EventEngineBuilder::new()
+            .incoming_inproc("messages-required-unspawnable")
+            .outgoing_inproc("events-required-unspawnable")
+            .transport(Transport::InprocOnly)
+            .plugins(plugins)
+            .start();
+        assert!(matches!(
+            started,
+            Err(EngineError::PluginSpawn { plugin_id: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn test_inproc_only_engine_stops_after_the_batch() {
+        // every TCP port of the engine is taken, which an inproc-only engine does not mind
+        let context = zmq::Context::new();
+        let _squatters: Vec<Socket> = [2959, 2960, 2961]
+            .iter()
+            .map(|port| {
+                let squatter = context.socket(zmq::SUB).unwrap();
+                squatter.bind(&format!("tcp://*:{}", port)).unwrap();
+                squatter
+            })
+            .collect();
+        let labrador =
+            crate::image_score_plugin::FixedScorer::new(vec![("labrador".to_string(), 0.9)]);
+        let mut plugins = PluginRegistry::new();
+        plugins
+            .register_plugin(Box::new(
+                crate::new_image_plugin::NewImagePlugin::new(0)
+                    .images(20)
+                    .image_size(64 * 1024),
+            ))
+            .unwrap()
+            .register_plugin(Box::new(
+                crate::image_score_plugin::ImageScorePlugin::with_scorer(1, Box::new(labrador))
+                    .images(20),
+            ))
+            .unwrap()
+            .register_plugin(Box::new(
+                crate::image_store_plugin::ImageStorePlugin::new(2).images(20),
+            ))
+            .unwrap();
+        let engine = EventEngineBuilder::new()
+            .incoming_port(2959)
+            .outgoing_port(2960)
+            .sync_port(2961)
+            .incoming_inproc("messages-batch")
+            .outgoing_inproc("events-batch")
+            .transport(Transport::InprocOnly)
+            .stop_after("ImageStoredEvent", 20)
+            .plugins(plugins)
+            .start()
+            .unwrap();
+
+        assert!(engine.wait_for_stop(Duration::from_secs(10)));
+        assert!(!engine.is_running());
+        let results = engine.wait_for_plugins(Duration::from_secs(5));
+        assert_eq!(results.len(), 3, "plugins did not exit: {:?}", results);
+        assert!(results.values().all(|result| result.is_ok()));
+        engine.shutdown().unwrap();
+    }
+
+    #[test]
+    fn test_engine_stops_when_the_predicate_holds() {
+        let storage = InMemoryStore::new();
+        // images scored low are deleted rather than stored
+        let labrador =
+            crate::image_score_plugin::FixedScorer::new(vec![("labrador".to_string(), 0.9)]);
+        let mut plugins = PluginRegistry::new();
+        plugins
+            .register_plugin(Box::new(
+                crate::new_image_plugin::NewImagePlugin::new(0).images(10),
+            ))
+            .unwrap()
+            .register_plugin(Box::new(
+                crate::image_score_plugin::ImageScorePlugin::with_scorer(1, Box::new(labrador))
+                    .images(10),
+            ))
+            .unwrap()
+            .register_plugin(Box::new(
+                crate::image_store_plugin::ImageStorePlugin::new(2)
+                    .images(10)
+                    .storage(Box::new(storage.clone())),
+            ))
+            .unwrap();
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let predicate_seen = Arc::clone(&seen);
+        let engine = EventEngineBuilder::new()
+            .incoming_inproc("messages-stop-when")
+            .outgoing_inproc("events-stop-when")
+            .transport(Transport::InprocOnly)
+            .stop_when(move |event| {
+                let mut seen = predicate_seen.lock().unwrap();
+                seen.push(event.type_name());
+                seen.iter().filter(|t| **t == "ImageStoredEvent").count() == 10
+            })
+            .plugins(plugins)
+            .start()
+            .unwrap();
+
+        assert!(engine.wait_for_stop(Duration::from_secs(10)));
+        let results = engine.wait_for_plugins(Duration::from_secs(5));
+        assert_eq!(results.len(), 3, "plugins did not exit: {:?}", results);
+        assert!(results.values().all(|result| result.is_ok()));
+        engine.shutdown().unwrap();
+        assert_eq!(storage.len(), 10);
+        // the predicate saw every event, decoded, up to the one it stopped on
+        let seen = seen.lock().unwrap();
+        let count = |event_type| seen.iter().filter(|t| **t == event_type).count();
+        assert_eq!(count("NewImageEvent"), 10);
+        assert_eq!(count("ImageScoredEvent"), 10);
+        assert_eq!(seen.last(), Some(&"ImageStoredEvent"));
+    }
+
+    // Records the name of the thread scoring each image.
+    struct ThreadScorer {
+        threads: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl crate::image_score_plugin::ImageScorer for ThreadScorer {
+        fn score(
+            &mut self,
+            _image: &[u8],
+            _format: &str,
+        ) -> Result<Vec<(String, f32)>, crate::image_score_plugin::ScoreError> {
+            let name = thread::current().name().unwrap_or_default().to_string();
+            self.threads.lock().unwrap().push(name);
+            Ok(vec![("labrador".to_string(), 0.9)])
+        }
+    }
+
+    #[test]
+    fn test_pool_workers_share_the_events() {
+        let threads = Arc::new(Mutex::new(Vec::new()));
+        let storage = InMemoryStore::new();
+        let scorer_threads = Arc::clone(&threads);
+        let mut plugins = PluginRegistry::new();
+        plugins
+            .register_plugin(Box::new(
+                crate::new_image_plugin::NewImagePlugin::new(0).images(4),
+            ))
+            .unwrap()
+            .register_pool(2, move || {
+                let scorer = ThreadScorer {
+                    threads: Arc::clone(&scorer_threads),
+                };
+                Box::new(crate::image_score_plugin::ImageScorePlugin::with_scorer(
+                    1,
+                    Box::new(scorer),
+                ))
+            })
+            .unwrap()
+            .register_plugin(Box::new(
+                crate::image_store_plugin::ImageStorePlugin::new(2)
+                    .images(4)
+                    .storage(Box::new(storage.clone())),
+            ))
+            .unwrap();
+        let engine = EventEngineBuilder::new()
+            .incoming_inproc("messages-pool")
+            .outgoing_inproc("events-pool")
+            .transport(Transport::InprocOnly)
+            .stop_after("ImageStoredEvent", 4)
+            .plugins(plugins)
+            .start()
+            .unwrap();
+
+        assert!(engine.wait_for_stop(Duration::from_secs(10)));
+        let results = engine.wait_for_plugins(Duration::from_secs(5));
+        assert_eq!(results.len(), 3, "plugins did not exit: {:?}", results);
+        assert!(results.values().all(|result| result.is_ok()));
+        assert_eq!(engine.plugin_status(1), Some(PluginStatus::Finished));
+        engine.shutdown().unwrap();
+        assert_eq!(storage.len(), 4);
+        // the events are handed to the workers in turn
+        let mut threads = threads.lock().unwrap().clone();
+        threads.sort();
+        assert_eq!(
+            threads,
+            [
+                "image-score worker 0",
+                "image-score worker 0",
+                "image-score worker 1",
+                "image-score worker 1"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tcp_only_engine_runs_the_pipeline_over_tcp() {
+        let context = zmq::Context::new();
+        let mut plugins = PluginRegistry::new();
+        plugins
+            .register_plugin(Box::new(crate::new_image_plugin::NewImagePlugin::new(0)))
+            .unwrap()
+            .register_plugin(Box::new(crate::image_score_plugin::ImageScorePlugin::new(
+                1,
+            )))
+            .unwrap()
+            .register_plugin(Box::new(crate::image_store_plugin::ImageStorePlugin::new(
+                2,
+            )))
+            .unwrap();
+        let engine = EventEngineBuilder::new()
+            .incoming_port(2970)
+            .outgoing_port(2971)
+            .sync_port(2972)
+            .incoming_inproc("messages-tcp-only")
+            .outgoing_inproc("events-tcp-only")
+            .transport(Transport::Tcp)
+            .context(context.clone())
+            .plugins(plugins)
+            .start()
+            .unwrap();
+
+        let results = engine.wait_for_plugins(Duration::from_secs(10));
+        assert_eq!(results.len(), 3, "plugins did not exit: {:?}", results);
+        assert!(results.values().all(|result| result.is_ok()));
+        // the inproc endpoints were left alone
+        for name in [
+            "messages-tcp-only",
+            "events-tcp-only",
+            "events-tcp-only-sync",
+        ] {
+            let socket = context.socket(zmq::PUB).unwrap();
+            socket.bind(&format!("inproc://{}", name)).unwrap();
+        }
+        engine.shutdown().unwrap();
+    }
+
+    #[test]
+    fn test_ports_set_to_0_are_picked_by_the_system() {
+        let mut plugins = PluginRegistry::new();
+        plugins
+            .register_plugin(Box::new(crate::new_image_plugin::NewImagePlugin::new(0)))
+            .unwrap();
+        let engine = EventEngineBuilder::new()
+            .incoming_port(0)
+            .outgoing_port(0)
+            .sync_port(0)
+            .transport(Transport::Tcp)
+            .plugins(plugins)
+            .start()
+            .unwrap();
+        // the plugin synced and published on the ports bound
+        let results = engine.wait_for_plugins(Duration::from_secs(10));
+        assert!(matches!(results.get(&0), Some(Ok(()))), "{:?}", results);
+        let config = engine.config();
+        let ports = [config.incoming_port, config.outgoing_port, config.sync_port];
+        assert!(!ports.contains(&0), "{:?}", ports);
+        let distinct: std::collections::HashSet<_> = ports.iter().collect();
+        assert_eq!(distinct.len(), 3, "{:?}", ports);
+        let squatter = zmq::Context::new().socket(zmq::PUB).unwrap();
+        assert_eq!(
+            squatter.bind(&config.outgoing_tcp_endpoint()),
+            Err(zmq::Error::EADDRINUSE)
+        );
+        engine.shutdown().unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_stale_ipc_socket_file_is_only_removed_if_asked() {
+        let dir = std::env::temp_dir().join(format!("plyoreacto-ipc-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir(&dir).unwrap();
+        // left behind by an engine that crashed
+        std::fs::write(dir.join("outgoing.sock"), b"").unwrap();
+        let builder = || {
+            EventEngineBuilder::new()
+                .incoming_inproc("messages-stale-ipc")
+                .outgoing_inproc("events-stale-ipc")
+                .transport(Transport::Ipc { dir: dir.clone() })
+                .plugins(PluginRegistry::new())
+        };
+
+        match builder().start() {
+            Err(e @ EngineError::IpcPathExists { .. }) => assert_eq!(
+                e.to_string(),
+                format!(
+                    "ipc socket file {} already exists; is another engine using it?",
+                    dir.join("outgoing.sock").display()
+                )
+            ),
+            Err(e) => panic!("expected a stale ipc file error, got: {}", e),
+            Ok(_) => panic!("engine took over the socket file of another engine"),
+        }
+
+        let engine = builder().unlink_stale_ipc(true).start().unwrap();
+        assert!(dir.join("incoming.sock").exists());
+        assert!(dir.join("outgoing.sock").exists());
+        engine.shutdown().unwrap();
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+        std::fs::remove_dir(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_inproc_only_engine_rejects_external_plugins() {
+        let mut plugins = PluginRegistry::new();
+        plugins.register_optional_external(3).unwrap();
+        match EventEngineBuilder::new()
+            .transport(Transport::InprocOnly)
+            .plugins(plugins)
+            .start()
+        {
+            Err(e @ EngineError::ExternalPluginsInprocOnly) => assert_eq!(
+                e.to_string(),
+                "external plugins cannot reach an inproc-only engine"
+            ),
+            Err(e) => panic!("expected an inproc-only error, got: {}", e),
+            Ok(_) => panic!("inproc-only engine started with an external plugin"),
+        }
+        assert!(matches!(
+            EventEngineBuilder::new()
+                .transport(Transport::InprocOnly)
+                .registration_window(Duration::from_secs(1))
+                .plugins(PluginRegistry::new())
+                .start(),
+            Err(EngineError::ExternalPluginsInprocOnly)
+        ));
+    }
+
+    #[test]
+    fn test_invalid_plugin_ids_are_refused_before_binding() {
+        let mut plugins = PluginRegistry::new();
+        plugins
+            .register(-2, &[], crate::new_image_plugin::start)
+            .unwrap();
+        // the port is taken, so the engine would fail to bind it first
+        let taken = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        match EventEngineBuilder::new()
+            .incoming_port(taken.local_addr().unwrap().port())
+            .plugins(plugins)
+            .start()
+        {
+            Err(e @ EngineError::InvalidPluginConfig { .. }) => {
+                assert_eq!(e.to_string(), "invalid plugin ids: -2 (negative)")
+            }
+            Err(e) => panic!("expected an invalid plugin config error, got: {}", e),
+            Ok(_) => panic!("engine started with a negative plugin id"),
+        }
+    }
+
+    #[test]
+    fn test_zero_io_threads_are_refused() {
+        // the ports are taken, so the engine would fail to bind them first
+        let taken = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        for builder in [
+            EventEngineBuilder::new(),
+            // even on a context of the host's, which the engine does not size
+            EventEngineBuilder::new().context(zmq::Context::new()),
+        ] {
+            match builder
+                .incoming_port(taken.local_addr().unwrap().port())
+                .io_threads(0)
+                .plugins(PluginRegistry::new())
+                .start()
+            {
+                Err(e @ EngineError::InvalidIoThreads { io_threads: 0 }) => assert_eq!(
+                    e.to_string(),
+                    "the zmq context needs at least one I/O thread, not 0"
+                ),
+                Err(e) => panic!("expected an invalid I/O threads error, got: {}", e),
+                Ok(_) => panic!("engine started without I/O threads"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_io_threads_are_only_set_on_the_engines_context() {
+        let config = EventEngineBuilder::new().io_threads(2).build();
+        let context = zmq::Context::new();
+        set_io_threads(&context, &config, true).unwrap();
+        assert_eq!(context.get_io_threads().unwrap(), 2);
+        let host_context = zmq::Context::new();
+        set_io_threads(&host_context, &config, false).unwrap();
+        assert_eq!(host_context.get_io_threads().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_tcp_keepalive_is_set_on_the_engine_sockets() {
+        let context = zmq::Context::new();
+        let socket = context.socket(zmq::PUB).unwrap();
+        set_tcp_keepalive(&socket, "outgoing", None).unwrap();
+        // the OS default
+        assert_eq!(socket.get_tcp_keepalive().unwrap(), -1);
+        let keepalive = TcpKeepalive {
+            idle: Some(Duration::from_secs(30)),
+            interval: Some(Duration::from_secs(5)),
+            count: Some(4),
+        };
+        set_tcp_keepalive(&socket, "outgoing", Some(keepalive)).unwrap();
+        assert_eq!(socket.get_tcp_keepalive().unwrap(), 1);
+        assert_eq!(socket.get_tcp_keepalive_idle().unwrap(), 30);
+        assert_eq!(socket.get_tcp_keepalive_intvl().unwrap(), 5);
+        assert_eq!(socket.get_tcp_keepalive_cnt().unwrap(), 4);
+
+        // and an engine with keepalive runs as before
+        let engine = EventEngineBuilder::new()
+            .incoming_port(24659)
+            .outgoing_port(24660)
+            .tcp_keepalive(TcpKeepalive {
+                count: Some(4),
+                ..TcpKeepalive::default()
+            })
+            .io_threads(2)
+            .plugins(PluginRegistry::new())
+            .start()
+            .unwrap();
+        engine.shutdown().unwrap();
+    }
+
+    // Publishes an ImageStoredEvent and reports the envelope it was sent in.
+    struct EnvelopePublisher {
+        meta_tx: std::sync::mpsc::Sender<EventMeta>,
+    }
+
+    impl Plugin for EnvelopePublisher {
+        fn id(&self) -> i32 {
+            1
+        }
+
+        fn name(&self) -> &str {
+            "envelope-publisher"
+        }
+
+        fn subscriptions(&self) -> &[&str] {
+            &[]
+        }
+
+        fn start(self: Box<Self>, mut ctx: PluginContext) -> Result<(), PluginError> {
+            let meta = ctx.publish(&crate::events::ImageStored {
+                image_uuid: "enveloped".to_string(),
+                path: String::new(),
+                deduplicated: false,
+            })?;
+            self.meta_tx.send(meta).unwrap();
+            Ok(())
+        }
+    }
+
+    // Reports the envelope of the first ImageStoredEvent it receives.
+    struct EnvelopeObserver {
+        meta_tx: std::sync::mpsc::Sender<Option<EventMeta>>,
+    }
+
+    impl Plugin for EnvelopeObserver {
+        fn id(&self) -> i32 {
+            2
+        }
+
+        fn name(&self) -> &str {
+            "envelope-observer"
+        }
+
+        fn subscriptions(&self) -> &[&str] {
+            &["ImageStoredEvent"]
+        }
+
+        fn start(self: Box<Self>, mut ctx: PluginContext) -> Result<(), PluginError> {
+            let msg = ctx.next_event()?;
+            if msg.event_type == "ImageStoredEvent" {
+                self.meta_tx.send(msg.meta).unwrap();
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_event_envelope_survives_proxy() {
+        let (published_tx, published_rx) = std::sync::mpsc::channel();
+        let (received_tx, received_rx) = std::sync::mpsc::channel();
+        let mut plugins = PluginRegistry::new();
+        plugins
+            .register_plugin(Box::new(EnvelopePublisher {
+                meta_tx: published_tx,
+            }))
+            .unwrap()
+            .register_plugin(Box::new(EnvelopeObserver {
+                meta_tx: received_tx,
+            }))
+            .unwrap();
+        let before_ms = EventMeta::new(0).timestamp_ms;
+        let engine = EventEngineBuilder::new()
+            .incoming_port(31559)
+            .outgoing_port(31560)
+            .plugins(plugins)
+            .start()
+            .unwrap();
+
+        let timeout = Duration::from_secs(10);
+        let published = published_rx.recv_timeout(timeout).unwrap();
+        assert_eq!(published.source_plugin_id, 1);
+        assert!(published.timestamp_ms >= before_ms);
+        assert_eq!(received_rx.recv_timeout(timeout).unwrap(), Some(published));
+        engine.shutdown().unwrap();
+    }
+
+    // Publishes `count` new images as fast as it can, then one more after `pause`, so that the
+    // images dropped at the end of the flood are detected too.
+    struct ImageFlood {
+        count: u64,
+        pause: Duration,
+    }
+
+    impl Plugin for ImageFlood {
+        fn id(&self) -> i32 {
+            0
+        }
+
+        fn name(&self) -> &str {
+            "image-flood"
+        }
+
+        fn subscriptions(&self) -> &[&str] {
+            &[]
+        }
+
+        fn start(self: Box<Self>, mut ctx: PluginContext) -> Result<(), PluginError> {
+            for i in 0..=self.count {
+                if i == self.count {
+                    thread::sleep(self.pause);
+                }
+                ctx.publish(&crate::events::NewImage {
+                    image_uuid: format!("image-{}", i),
+                    image_format: "png".to_string(),
+                    image: vec![0; 1024],
+                    location: None,
+                })?;
+            }
+            Ok(())
+        }
+    }
+
+    // Sleeps through the flood, then receives images until the one numbered `last` and reports
+    // how many it received and how many it missed.
+    struct SleepyObserver {
+        sleep: Duration,
+        last: u64,
+        report_tx: std::sync::mpsc::Sender<(u64, u64)>,
+    }
+
+    impl Plugin for SleepyObserver {
+        fn id(&self) -> i32 {
+            1
+        }
+
+        fn name(&self) -> &str {
+            "sleepy-observer"
+        }
+
+        fn subscriptions(&self) -> &[&str] {
+            &["NewImageEvent"]
+        }
+
+        fn start(self: Box<Self>, mut ctx: PluginContext) -> Result<(), PluginError> {
+            ctx.publish_dropped_events();
+            thread::sleep(self.sleep);
+            let mut received = 0;
+            loop {
+                let msg = ctx.next_event()?;
+                if msg.event_type != "NewImageEvent" {
+                    continue;
+                }
+                received += 1;
+                if msg.meta.map(|meta| meta.seq) == Some(self.last) {
+                    break;
+                }
+            }
+            self.report_tx
+                .send((received, ctx.dropped_events()))
+                .unwrap();
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_events_dropped_past_the_hwm_are_detected() {
+        let (report_tx, report_rx) = std::sync::mpsc::channel();
+        let mut plugins = PluginRegistry::new();
+        plugins
+            .register_plugin(Box::new(ImageFlood {
+                count: 100,
+                pause: Duration::from_secs(1),
+            }))
+            .unwrap()
+            .register_plugin(Box::new(SleepyObserver {
+                sleep: Duration::from_millis(300),
+                last: 101,
+                report_tx,
+            }))
+            .unwrap()
+            .hwm(0, Hwm::new(2))
+            .unwrap()
+            .hwm(1, Hwm::new(2))
+            .unwrap();
+        let engine = EventEngineBuilder::new()
+            .incoming_port(2859)
+            .outgoing_port(2860)
+            .sync_port(2800)
+            .incoming_hwm(Hwm::new(2))
+            .outgoing_hwm(Hwm::new(2))
+            .plugins(plugins)
+            .start()
+            .unwrap();
+        let dropped_rx = engine.subscribe(&["EventsDroppedEvent"]).unwrap();
+
+        let (received, dropped) = report_rx.recv_timeout(Duration::from_secs(10)).unwrap();
+        // the sockets on the way hold a handful of images at most
+        assert!(received < 20, "received {} of the 101 images", received);
+        assert_eq!(received + dropped, 101);
+        let mut reported = 0;
+        while reported < dropped {
+            match dropped_rx.recv_timeout(Duration::from_secs(5)).unwrap() {
+                Event::EventsDropped(e) => {
+                    assert_eq!(e.plugin_id, 1);
+                    reported += e.missed;
+                }
+                event => panic!("expected an EventsDroppedEvent, got {:?}", event),
+            }
+        }
+        assert_eq!(reported, dropped);
+        engine.shutdown().unwrap();
+    }
+
+    // Publishes three new images back to back and reports the envelope of each one.
+    struct ImageSource {
+        new_image_tx: std::sync::mpsc::Sender<(String, EventMeta)>,
+    }
+
+    impl Plugin for ImageSource {
+        fn id(&self) -> i32 {
+            0
+        }
+
+        fn name(&self) -> &str {
+            "image-source"
+        }
+
+        fn subscriptions(&self) -> &[&str] {
+            &[]
+        }
+
+        fn start(self: Box<Self>, mut ctx: PluginContext) -> Result<(), PluginError> {
+            for i in 0..3 {
+                let image_uuid = format!("image-{}", i);
+                let meta = ctx.publish(&crate::events::NewImage {
+                    image_uuid: image_uuid.clone(),
+                    image_format: "png".to_string(),
+                    image: vec![1, 2, 3],
+                    location: None,
+                })?;
+                self.new_image_tx.send((image_uuid, meta)).unwrap();
+            }
+            Ok(())
+        }
+    }
+
+    // Reports the image uuid and envelope of the first three stored or deleted images.
+    struct OutcomeObserver {
+        outcome_tx: std::sync::mpsc::Sender<(String, Option<EventMeta>)>,
+    }
+
+    impl Plugin for OutcomeObserver {
+        fn id(&self) -> i32 {
+            3
+        }
+
+        fn name(&self) -> &str {
+            "outcome-observer"
+        }
+
+        fn subscriptions(&self) -> &[&str] {
+            &["ImageStoredEvent", "ImageDeletedEvent"]
+        }
+
+        fn start(self: Box<Self>, mut ctx: PluginContext) -> Result<(), PluginError> {
+            for _ in 0..3 {
+                let msg = ctx.next_event()?;
+                let image_uuid = match msg.decode()? {
+                    Event::ImageStored(e) => e.image_uuid,
+                    Event::ImageDeleted(e) => e.image_uuid,
+                    _ => return Ok(()),
+                };
+                self.outcome_tx.send((image_uuid, msg.meta)).unwrap();
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_correlation_id_follows_each_image() {
+        let (new_image_tx, new_image_rx) = std::sync::mpsc::channel();
+        let (outcome_tx, outcome_rx) = std::sync::mpsc::channel();
+        let mut plugins = PluginRegistry::new();
+        plugins
+            .register_plugin(Box::new(ImageSource { new_image_tx }))
+            .unwrap()
+            .register_plugin(Box::new(crate::image_score_plugin::ImageScorePlugin::new(
+                1,
+            )))
+            .unwrap()
+            .register_plugin(Box::new(crate::image_store_plugin::ImageStorePlugin::new(
+                2,
+            )))
+            .unwrap()
+            .register_plugin(Box::new(OutcomeObserver { outcome_tx }))
+            .unwrap();
+        let engine = EventEngineBuilder::new()
+            .incoming_port(32559)
+            .outgoing_port(32560)
+            .plugins(plugins)
+            .start()
+            .unwrap();
+
+        let timeout = Duration::from_secs(10);
+        let new_images: HashMap<String, EventMeta> = (0..3)
+            .map(|_| new_image_rx.recv_timeout(timeout).unwrap())
+            .collect();
+        for _ in 0..3 {
+            let (image_uuid, meta) = outcome_rx.recv_timeout(timeout).unwrap();
+            let meta = meta.expect("outcome published without an envelope");
+            assert_eq!(meta.correlation_id, new_images[&image_uuid].event_id);
+            assert_eq!(meta.source_plugin_id, 2);
+        }
+        engine.shutdown().unwrap();
+    }
+
+    // Scores every new image as a labrador, so that the store plugin stores all of them.
+    struct LabradorScorer;
+
+    impl Plugin for LabradorScorer {
+        fn id(&self) -> i32 {
+            1
+        }
+
+        fn name(&self) -> &str {
+            "labrador-scorer"
+        }
+
+        fn subscriptions(&self) -> &[&str] {
+            &["NewImageEvent"]
+        }
+
+        fn start(self: Box<Self>, mut ctx: PluginContext) -> Result<(), PluginError> {
+            loop {
+                let msg = ctx.next_event()?;
+                let image_uuid = match msg.decode()? {
+                    Event::NewImage(e) => e.image_uuid,
+                    _ => return Ok(()),
+                };
+                ctx.publish(&crate::events::ImageScored {
+                    image_uuid,
+                    scores: vec![crate::events::ImageScore {
+                        label: "labrador".to_string(),
+                        probability: 1.0,
+                    }],
+                })?;
+            }
+        }
+    }
+
+    #[test]
+    fn test_watched_directory_images_are_stored() {
+        let dir = std::env::temp_dir().join(format!("plyoreacto-watch-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir(&dir).unwrap();
+        let mut plugins = PluginRegistry::new();
+        plugins
+            .register_plugin(Box::new(
+                crate::new_image_plugin::NewImagePlugin::watch(0, &dir)
+                    .poll_interval(Duration::from_millis(50)),
+            ))
+            .unwrap()
+            .register_plugin(Box::new(LabradorScorer))
+            .unwrap()
+            .register_plugin(Box::new(crate::image_store_plugin::ImageStorePlugin::new(
+                2,
+            )))
+            .unwrap();
+        let engine = EventEngineBuilder::new()
+            .incoming_port(33559)
+            .outgoing_port(33560)
+            .plugins(plugins)
+            .start()
+            .unwrap();
+        let event_rx = engine
+            .subscribe(&["NewImageEvent", "ImageStoredEvent"])
+            .unwrap();
+        // give the subscription time to reach the engine
+        thread::sleep(Duration::from_millis(200));
+
+        let png = b"\x89PNG\r\n\x1a\n".to_vec();
+        // one file is written under a temporary name and renamed, the other one in place
+        std::fs::write(dir.join("first.png.tmp"), &png).unwrap();
+        std::fs::rename(dir.join("first.png.tmp"), dir.join("first.png")).unwrap();
+        std::fs::write(dir.join("second.PNG"), &png).unwrap();
+
+        let timeout = Duration::from_secs(10);
+        let mut new_images = Vec::new();
+        let mut stored = Vec::new();
+        while stored.len() < 2 {
+            match event_rx.recv_timeout(timeout).unwrap() {
+                Event::NewImage(e) => {
+                    assert_eq!((e.image_format.as_str(), &e.image), ("png", &png));
+                    new_images.push(e.image_uuid);
+                }
+                Event::ImageStored(e) => stored.push(e.image_uuid),
+                event => panic!("unexpected event {:?}", event),
+            }
+        }
+        new_images.sort();
+        new_images.dedup();
+        stored.sort();
+        assert_eq!(stored, new_images);
+        // files are published once
+        assert!(event_rx.recv_timeout(Duration::from_millis(500)).is_err());
+        engine.shutdown().unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_the_built_in_plugins_read_their_settings() {
+        let dir =
+            std::env::temp_dir().join(format!("plyoreacto-settings-{}", uuid::Uuid::new_v4()));
+        let (images, root) = (dir.join("images"), dir.join("stored"));
+        std::fs::create_dir_all(&images).unwrap();
+        let mut plugins = PluginRegistry::new();
+        plugins
+            .register_plugin(Box::new(crate::new_image_plugin::NewImagePlugin::new(0)))
+            .unwrap()
+            .register_plugin(Box::new(
+                crate::image_score_plugin::ImageScorePlugin::with_scorer(
+                    1,
+                    Box::new(FirstByteScorer),
+                ),
+            ))
+            .unwrap()
+            .register_plugin(Box::new(crate::image_store_plugin::ImageStorePlugin::new(
+                2,
+            )))
+            .unwrap()
+            .settings(
+                0,
+                PluginSettings::new().set("image_dir", images.to_str().unwrap()),
+            )
+            .unwrap()
+            .settings(1, PluginSettings::new().set("threshold", 0.5))
+            .unwrap()
+            .settings(
+                2,
+                PluginSettings::new().set("storage_root", root.to_str().unwrap()),
+            )
+            .unwrap();
+        let engine = start_inproc_engine("settings", plugins);
+        let event_rx = engine
+            .subscribe(&["ImageStoredEvent", "ImageRejectedEvent"])
+            .unwrap();
+        // give the subscription time to reach the engine
+        thread::sleep(Duration::from_millis(200));
+
+        // scored 0.9 and 0.1
+        std::fs::write(images.join("kept.png"), [90, 1, 2]).unwrap();
+        std::fs::write(images.join("rejected.png"), [10, 1, 2]).unwrap();
+        let timeout = Duration::from_secs(10);
+        let (mut stored, mut rejected) = (None, None);
+        while stored.is_none() || rejected.is_none() {
+            match event_rx.recv_timeout(timeout).unwrap() {
+                Event::ImageStored(e) => stored = Some(e),
+                Event::ImageRejected(e) => rejected = Some(e),
+                event => panic!("unexpected event {:?}", event),
+            }
+        }
+        engine.shutdown().unwrap();
+        assert_eq!(rejected.unwrap().probability, 0.1);
+        let stored = stored.unwrap();
+        assert!(std::path::Path::new(&stored.path).starts_with(&root));
+        assert_eq!(std::fs::read(&stored.path).unwrap(), [90, 1, 2]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_a_setting_of_the_wrong_type_keeps_the_engine_from_starting() {
+        let mut plugins = PluginRegistry::new();
+        plugins
+            .register_plugin(Box::new(crate::image_store_plugin::ImageStorePlugin::new(
+                2,
+            )))
+            .unwrap()
+            .settings(2, PluginSettings::new().set("storage_root", 12))
+            .unwrap();
+        let started = EventEngineBuilder::new()
+            .incoming_inproc("messages-settings-wrong-type")
+            .outgoing_inproc("events-settings-wrong-type")
+            .transport(Transport::InprocOnly)
+            .plugins(plugins)
+            .start();
+        match started {
+            Err(e @ EngineError::PluginSettings { plugin_id: 2, .. }) => assert_eq!(
+                e.to_string(),
+                "could not configure plugin 2: setting \"storage_root\" should be a path, got 12"
+            ),
+            Err(e) => panic!("expected a settings error, got {}", e),
+            Ok(_) => panic!("the engine started"),
+        }
+    }
+
+    #[test]
+    fn test_the_plugin_entries_of_a_configuration_file_apply_when_the_engine_starts() {
+        let (type_tx, type_rx) = std::sync::mpsc::channel();
+        let mut plugins = PluginRegistry::new();
+        plugins
+            .register_plugin(Box::new(
+                crate::image_score_plugin::ImageScorePlugin::with_scorer(
+                    1,
+                    Box::new(FirstByteScorer),
+                ),
+            ))
+            .unwrap()
+            .register_plugin(Box::new(crate::image_store_plugin::ImageStorePlugin::new(
+                2,
+            )))
+            .unwrap()
+            .register(
+                5,
+                &["NewImageEvent"],
+                move |_pub_socket, sub_socket, _bldr| {
+                    let (event_type, _payload) = recv_event_msg(sub_socket)?;
+                    type_tx.send(event_type).unwrap();
+                    Ok(())
+                },
+            )
+            .unwrap();
+        let config = EngineConfig::from_toml(
+            r#"
+            [engine]
+            incoming_inproc = "messages-config-file"
+            outgoing_inproc = "events-config-file"
+            transport = "inproc-only"
+
+            [[plugin]]
+            name = "image-score"
+            settings = { threshold = 0.5 }
+
+            [[plugin]]
+            name = "image-store"
+            enabled = false
+
+            [[plugin]]
+            name = "plugin-5"
+            subscriptions = ["ImageRejectedEvent"]
+            "#,
+        )
+        .unwrap();
+        let engine = EventEngineBuilder::from_config(config)
+            .plugins(plugins)
+            .start()
+            .unwrap();
+        assert_eq!(engine.plugin_status(2), None);
+
+        // scored 0.1, under the threshold of the file
+        engine
+            .publish(&Event::NewImage(crate::events::NewImage {
+                image_uuid: "rejected".to_string(),
+                image_format: "png".to_string(),
+                image: vec![10, 1, 2],
+                location: None,
+            }))
+            .unwrap();
+        let event_type = type_rx.recv_timeout(Duration::from_secs(10));
+        engine.shutdown().unwrap();
+        assert_eq!(event_type.unwrap(), "ImageRejectedEvent");
+    }
+
+    // Scores every image as a labrador, with the first byte of the image as the percentage.
+    struct FirstByteScorer;
+
+    impl crate::image_score_plugin::ImageScorer for FirstByteScorer {
+        fn score(
+            &mut self,
+            image: &[u8],
+            _format: &str,
+        ) -> Result<Vec<(String, f32)>, crate::image_score_plugin::ScoreError> {
+            let probability = image.first().copied().unwrap_or_default() as f32 / 100.0;
+            Ok(vec![("labrador".to_string(), probability)])
+        }
+    }
+
+    #[test]
+    fn test_rejected_images_are_not_stored() {
+        let mut plugins = PluginRegistry::new();
+        plugins
+            .register_plugin(Box::new(
+                crate::image_score_plugin::ImageScorePlugin::with_scorer(
+                    1,
+                    Box::new(FirstByteScorer),
+                )
+                .threshold(0.25),
+            ))
+            .unwrap()
+            .register_plugin(Box::new(crate::image_store_plugin::ImageStorePlugin::new(
+                2,
+            )))
+            .unwrap();
+        let engine = EventEngineBuilder::new()
+            .incoming_port(37559)
+            .outgoing_port(37560)
+            .plugins(plugins)
+            .start()
+            .unwrap();
+        let event_rx = engine
+            .subscribe(&[
+                "ImageRejectedEvent",
+                "ImageStoredEvent",
+                "ImageDeletedEvent",
+            ])
+            .unwrap();
+        // give the subscription time to reach the engine
+        thread::sleep(Duration::from_millis(200));
+        for (image_uuid, percentage) in [("confident", 90), ("doubtful", 10)] {
+            engine
+                .publish(&Event::NewImage(crate::events::NewImage {
+                    image_uuid: image_uuid.to_string(),
+                    image_format: "png".to_string(),
+                    image: vec![percentage, 1, 2],
+                    location: None,
+                }))
+                .unwrap();
+        }
+
+        let timeout = Duration::from_secs(10);
+        let mut outcomes = vec![
+            event_rx.recv_timeout(timeout).unwrap(),
+            event_rx.recv_timeout(timeout).unwrap(),
+        ];
+        outcomes.sort_by_key(|event| event.type_name());
+        assert_eq!(
+            outcomes,
+            vec![
+                Event::ImageRejected(crate::events::ImageRejected {
+                    image_uuid: "doubtful".to_string(),
+                    top_label: "labrador".to_string(),
+                    probability: 0.1,
+                }),
+                Event::ImageStored(crate::events::ImageStored {
+                    image_uuid: "confident".to_string(),
+                    path: String::new(),
+                    deduplicated: false,
+                }),
+            ]
+        );
+        // the rejected image is neither stored nor deleted
+        assert!(event_rx.recv_timeout(Duration::from_millis(500)).is_err());
+        engine.shutdown().unwrap();
+    }
+
+    // Scores every image as a labrador and puts it in `storage`.
+    fn start_storing_engine(
+        store: crate::image_store_plugin::ImageStorePlugin,
+        incoming_port: u16,
+    ) -> EngineHandle {
+        EventEngineBuilder::new()
+            .incoming_port(incoming_port)
+            .outgoing_port(incoming_port + 1)
+            .plugins(storing_plugins(store))
+            .start()
+            .unwrap()
+    }
+
+    // A scorer scoring every image a labrador, and `store`.
+    fn storing_plugins(store: crate::image_store_plugin::ImageStorePlugin) -> PluginRegistry {
+        let scorer =
+            crate::image_score_plugin::FixedScorer::new(vec![("labrador".to_string(), 1.0)]);
+        let mut plugins = PluginRegistry::new();
+        plugins
+            .register_plugin(Box::new(
+                crate::image_score_plugin::ImageScorePlugin::with_scorer(1, Box::new(scorer)),
+            ))
+            .unwrap()
+            .register_plugin(Box::new(store))
+            .unwrap();
+        plugins
+    }
+
+    fn publish_image(engine: &EngineHandle, image_uuid: &str, image: Vec<u8>) {
+        engine
+            .publish(&Event::NewImage(crate::events::NewImage {
+                image_uuid: image_uuid.to_string(),
+                image_format: "png".to_string(),
+                image,
+                location: None,
+            }))
+            .unwrap();
+    }
+
+    // Run three images through a pipeline storing them in a clone of `storage`, check that
+    // `storage` then holds the bytes of each one and return their ImageStoredEvents.
+    fn check_pipeline_stores_images<S>(storage: S, incoming_port: u16) -> Vec<ImageStored>
+    where
+        S: crate::storage::StorageBackend + Clone + Send + 'static,
+    {
+        let plugin = crate::image_store_plugin::ImageStorePlugin::new(2);
+        let engine = start_storing_engine(plugin.storage(Box::new(storage.clone())), incoming_port);
+        let stored_rx = engine.subscribe(&["ImageStoredEvent"]).unwrap();
+        // give the subscription time to reach the engine
+        thread::sleep(Duration::from_millis(200));
+        let images: HashMap<String, Vec<u8>> = (0..3)
+            .map(|i| (uuid::Uuid::new_v4().to_string(), vec![i, 1, 2, 3]))
+            .collect();
+        for (image_uuid, image) in &images {
+            publish_image(&engine, image_uuid, image.clone());
+        }
+
+        let timeout = Duration::from_secs(10);
+        let stored: Vec<ImageStored> = (0..images.len())
+            .map(|_| match stored_rx.recv_timeout(timeout).unwrap() {
+                Event::ImageStored(stored) => stored,
+                event => panic!("expected an ImageStoredEvent, got {:?}", event),
+            })
+            .collect();
+        engine.shutdown().unwrap();
+        for ImageStored { image_uuid, .. } in &stored {
+            assert_eq!(
+                storage.get(image_uuid).unwrap().as_ref(),
+                images.get(image_uuid)
+            );
+        }
+        stored
+    }
+
+    #[test]
+    fn test_pipeline_stores_images_in_memory() {
+        let storage = InMemoryStore::new();
+        for stored in check_pipeline_stores_images(storage.clone(), 38559) {
+            assert_eq!(stored.path, format!("memory:{}.png", stored.image_uuid));
+        }
+        assert_eq!(storage.len(), 3);
+    }
+
+    #[test]
+    fn test_pipeline_stores_images_on_the_filesystem() {
+        let root = std::env::temp_dir().join(format!("plyoreacto-store-{}", uuid::Uuid::new_v4()));
+        let storage = crate::storage::FilesystemStore::new(&root).fsync(true);
+        for stored in check_pipeline_stores_images(storage, 40559) {
+            let image_uuid = &stored.image_uuid;
+            let expected_path = root
+                .join(&image_uuid[0..2])
+                .join(&image_uuid[2..4])
+                .join(format!("{}.png", image_uuid));
+            assert_eq!(stored.path, expected_path.display().to_string());
+        }
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_pipeline_stores_images_passed_by_reference() {
+        let dir = std::env::temp_dir().join(format!("plyoreacto-refs-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir(&dir).unwrap();
+        let storage = InMemoryStore::new();
+        let plugin = crate::image_store_plugin::ImageStorePlugin::new(2);
+        let plugins = storing_plugins(plugin.storage(Box::new(storage.clone())));
+        let engine = start_inproc_engine("refs", plugins);
+        let outcome_rx = engine
+            .subscribe(&["ImageStoredEvent", "ImageScoreFailedEvent"])
+            .unwrap();
+        // give the subscription time to reach the engine
+        thread::sleep(Duration::from_millis(200));
+        std::fs::write(dir.join("path.png"), [1, 2, 3]).unwrap();
+        std::fs::write(dir.join("uri.png"), [4, 5, 6]).unwrap();
+        let path = dir.join("path.png").display().to_string();
+        let uri = format!("file://{}", dir.join("uri.png").display());
+        let missing = dir.join("missing.png").display().to_string();
+        // images passed by reference and inline share the pipeline
+        publish_image(&engine, "inline", vec![7, 8, 9]);
+        for (image_uuid, location) in [("path", &path), ("uri", &uri), ("missing", &missing)] {
+            engine
+                .publish(&Event::NewImage(crate::events::NewImage {
+                    image_uuid: image_uuid.to_string(),
+                    image_format: "png".to_string(),
+                    image: Vec::new(),
+                    location: Some(location.clone()),
+                }))
+                .unwrap();
+        }
+
+        let timeout = Duration::from_secs(10);
+        let mut stored = Vec::new();
+        let mut failed = Vec::new();
+        while stored.len() + failed.len() < 4 {
+            match outcome_rx.recv_timeout(timeout).unwrap() {
+                Event::ImageStored(e) => stored.push(e.image_uuid),
+                Event::ImageScoreFailed(e) => failed.push(e),
+                event => panic!("unexpected event {:?}", event),
+            }
+        }
+        engine.shutdown().unwrap();
+        stored.sort();
+        assert_eq!(stored, ["inline", "path", "uri"]);
+        for (image_uuid, image) in [
+            ("inline", [7, 8, 9]),
+            ("path", [1, 2, 3]),
+            ("uri", [4, 5, 6]),
+        ] {
+            let stored_image = crate::storage::StorageBackend::get(&storage, image_uuid);
+            assert_eq!(stored_image.unwrap(), Some(image.to_vec()));
+        }
+        // the file of an image that is gone by the time it is scored fails the image
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].image_uuid, "missing");
+        let cannot_read = format!("could not read the image at {}: ", missing);
+        assert!(
+            failed[0].error.starts_with(&cannot_read),
+            "{}",
+            failed[0].error
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_same_bytes_are_stored_once() {
+        let root = std::env::temp_dir().join(format!("plyoreacto-store-{}", uuid::Uuid::new_v4()));
+        let storage = crate::storage::FilesystemStore::new(root.join("images"));
+        std::fs::create_dir(&root).unwrap();
+        let index = crate::storage::DedupIndex::open(root.join("dedup.idx")).unwrap();
+        let plugin = crate::image_store_plugin::ImageStorePlugin::new(2)
+            .storage(Box::new(storage))
+            .deduplicate(index);
+        let engine = start_storing_engine(plugin, 41559);
+        let stored_rx = engine.subscribe(&["ImageStoredEvent"]).unwrap();
+        // give the subscription time to reach the engine
+        thread::sleep(Duration::from_millis(200));
+        publish_image(&engine, "first", vec![1, 2, 3]);
+        publish_image(&engine, "second", vec![1, 2, 3]);
+
+        let timeout = Duration::from_secs(10);
+        let mut stored: Vec<ImageStored> = (0..2)
+            .map(|_| match stored_rx.recv_timeout(timeout).unwrap() {
+                Event::ImageStored(stored) => stored,
+                event => panic!("expected an ImageStoredEvent, got {:?}", event),
+            })
+            .collect();
+        engine.shutdown().unwrap();
+        stored.sort_by(|a, b| a.image_uuid.cmp(&b.image_uuid));
+        let path = root.join("images/fi/rs/first.png").display().to_string();
+        assert_eq!(
+            stored,
+            vec![
+                ImageStored {
+                    image_uuid: "first".to_string(),
+                    path: path.clone(),
+                    deduplicated: false,
+                },
+                ImageStored {
+                    image_uuid: "second".to_string(),
+                    path,
+                    deduplicated: true,
+                },
+            ]
+        );
+        // only the first image was written
+        let entries = |dir: &str| std::fs::read_dir(root.join(dir)).unwrap().count();
+        assert_eq!(entries("images"), 1);
+        assert_eq!(entries("images/fi/rs"), 1);
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_stored_images_can_be_deleted() {
+        let root = std::env::temp_dir().join(format!("plyoreacto-store-{}", uuid::Uuid::new_v4()));
+        let storage = crate::storage::FilesystemStore::new(&root);
+        let plugin = crate::image_store_plugin::ImageStorePlugin::new(2).storage(Box::new(storage));
+        let engine = start_storing_engine(plugin, 42559);
+        let outcome_rx = engine
+            .subscribe(&["ImageStoredEvent", "ImageDeletedEvent"])
+            .unwrap();
+        // give the subscription time to reach the engine
+        thread::sleep(Duration::from_millis(200));
+        let timeout = Duration::from_secs(10);
+        publish_image(&engine, "1234abcd", vec![1, 2, 3]);
+        let path = match outcome_rx.recv_timeout(timeout).unwrap() {
+            Event::ImageStored(stored) => std::path::PathBuf::from(stored.path),
+            event => panic!("expected an ImageStoredEvent, got {:?}", event),
+        };
+        assert!(path.exists());
+
+        for image_uuid in ["1234abcd", "1234abcd", "never-stored"] {
+            engine
+                .publish(&Event::ImageDeletedRequest(
+                    crate::events::ImageDeletedRequest {
+                        image_uuid: image_uuid.to_string(),
+                    },
+                ))
+                .unwrap();
+        }
+        let deleted: Vec<Event> = (0..3)
+            .map(|_| outcome_rx.recv_timeout(timeout).unwrap())
+            .collect();
+        engine.shutdown().unwrap();
+        let deleted_event = |image_uuid: &str, existed| {
+            Event::ImageDeleted(crate::events::ImageDeleted {
+                image_uuid: image_uuid.to_string(),
+                existed,
+            })
+        };
+        assert_eq!(
+            deleted,
+            vec![
+                deleted_event("1234abcd", true),
+                // deleting again, or deleting an image that was never stored, is not an error
+                deleted_event("1234abcd", false),
+                deleted_event("never-stored", false),
+            ]
+        );
+        assert!(!path.exists());
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    // An in-memory store that is full for images larger than `max_size` bytes.
+    struct SmallImageStore {
+        store: InMemoryStore,
+        max_size: usize,
+    }
+
+    impl crate::storage::StorageBackend for SmallImageStore {
+        fn put(
+            &mut self,
+            image_uuid: &str,
+            image_format: &str,
+            image: &[u8],
+        ) -> Result<crate::storage::StoredLocation, crate::storage::StorageError> {
+            if image.len() > self.max_size {
+                return Err(crate::storage::StorageError::Other(
+                    "no space left for the image".to_string(),
+                ));
+            }
+            self.store.put(image_uuid, image_format, image)
+        }
+
+        fn get(&self, image_uuid: &str) -> Result<Option<Vec<u8>>, crate::storage::StorageError> {
+            self.store.get(image_uuid)
+        }
+
+        fn delete(&mut self, image_uuid: &str) -> Result<bool, crate::storage::StorageError> {
+            self.store.delete(image_uuid)
+        }
+    }
+
+    #[test]
+    fn test_store_failures_are_published() {
+        let store = InMemoryStore::new();
+        let storage = SmallImageStore {
+            store: store.clone(),
+            max_size: 3,
+        };
+        let plugin = crate::image_store_plugin::ImageStorePlugin::new(2).storage(Box::new(storage));
+        let engine = start_storing_engine(plugin, 39559);
+        let outcome_rx = engine
+            .subscribe(&["ImageStoredEvent", "ImageStoreFailedEvent"])
+            .unwrap();
+        // give the subscription time to reach the engine
+        thread::sleep(Duration::from_millis(200));
+        publish_image(&engine, "too-large", vec![1, 2, 3, 4]);
+        publish_image(&engine, "kept", vec![4, 5, 6]);
+
+        let timeout = Duration::from_secs(10);
+        assert_eq!(
+            outcome_rx.recv_timeout(timeout).unwrap(),
+            Event::ImageStoreFailed(crate::events::ImageStoreFailed {
+                image_uuid: "too-large".to_string(),
+                error: "no space left for the image".to_string(),
+            })
+        );
+        // the plugin goes on with the next image
+        assert_eq!(
+            outcome_rx.recv_timeout(timeout).unwrap(),
+            Event::ImageStored(ImageStored {
+                image_uuid: "kept".to_string(),
+                path: "memory:kept.png".to_string(),
+                deduplicated: false,
+            })
+        );
+        engine.shutdown().unwrap();
+        assert_eq!(store.len(), 1);
+    }
+
+    // A plugin answering the engine heartbeats; with `release`, it blocks on the first image it
+    // gets until `release` receives.
+    struct HeartbeatPlugin {
+        plugin_id: i32,
+        release: Option<std::sync::mpsc::Receiver<()>>,
+    }
+
+    impl Plugin for HeartbeatPlugin {
+        fn id(&self) -> i32 {
+            self.plugin_id
+        }
+
+        fn name(&self) -> &str {
+            "heartbeat"
+        }
+
+        fn subscriptions(&self) -> &[&str] {
+            &["NewImageEvent"]
+        }
+
+        fn start(
+            self: Box<Self>,
+            mut ctx: PluginContext,
+        ) -> Result<(), crate::plugin::PluginError> {
+            ctx.answer_heartbeats()?;
+            loop {
+                let msg = ctx.next_event()?;
+                if msg.event_type == "PluginTerminateEvent" {
+                    return Ok(());
+                }
+                if let Some(release) = &self.release {
+                    release.recv().unwrap();
+                }
+            }
+        }
+    }
+
+    // Poll the liveness of the plugins of `engine` until `done` holds, for up to five seconds.
+    fn wait_for_liveness(
+        engine: &EngineHandle,
+        done: impl Fn(&HashMap<i32, PluginLiveness>) -> bool,
+    ) -> HashMap<i32, PluginLiveness> {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        loop {
+            let liveness = engine.plugin_liveness();
+            if done(&liveness) || Instant::now() > deadline {
+                return liveness;
+            }
+            thread::sleep(Duration::from_millis(20));
+        }
+    }
+
+    #[test]
+    fn test_stuck_plugin_is_flagged_unresponsive() {
+        let (release_tx, release_rx) = std::sync::mpsc::channel();
+        let mut plugins = PluginRegistry::new();
+        plugins
+            .register_plugin(Box::new(HeartbeatPlugin {
+                plugin_id: 1,
+                release: None,
+            }))
+            .unwrap()
+            .register_plugin(Box::new(HeartbeatPlugin {
+                plugin_id: 2,
+                release: Some(release_rx),
+            }))
+            .unwrap();
+        let engine = EventEngineBuilder::new()
+            .incoming_port(4559)
+            .outgoing_port(4560)
+            .sync_port(4000)
+            .heartbeat_interval(Duration::from_millis(100))
+            .heartbeat_missed_beats(3)
+            .plugins(plugins)
+            .start()
+            .unwrap();
+        let healthy = |liveness: &HashMap<i32, PluginLiveness>| {
+            [1, 2]
+                .iter()
+                .all(|id| liveness.get(id).is_some_and(|l| !l.unresponsive))
+        };
+        assert!(healthy(&wait_for_liveness(&engine, healthy)));
+
+        // plugin 2 gets stuck on the image, plugin 1 carries on
+        engine
+            .publish(&Event::NewImage(crate::events::NewImage {
+                image_uuid: uuid::Uuid::new_v4().to_string(),
+                image_format: "png".to_string(),
+                image: vec![1, 2, 3],
+                location: None,
+            }))
+            .unwrap();
+        let liveness = wait_for_liveness(&engine, |liveness| liveness[&2].unresponsive);
+        assert!(liveness[&2].unresponsive);
+        assert!(liveness[&2].missed_beats >= 3);
+        assert!(!liveness[&1].unresponsive);
+
+        // once released, it answers again
+        release_tx.send(()).unwrap();
+        assert!(healthy(&wait_for_liveness(&engine, healthy)));
+        engine.shutdown().unwrap();
+    }
+
+    #[test]
+    fn test_two_engines_in_one_process() {
+        let config_a = EventEngineBuilder::new()
+            .incoming_port(15559)
+            .outgoing_port(15560)
+            .sync_port(15000)
+            .build();
+        let config_b = EventEngineBuilder::new()
+            .incoming_port(25559)
+            .outgoing_port(25560)
+            .incoming_inproc("messages-b")
+            .outgoing_inproc("events-b")
+            .sync_port(25000)
+            .build();
+
+        for config in [config_a.clone(), config_b.clone()] {
+            thread::spawn(move || event_engine_with_config(&config));
+        }
+        let observer_a = thread::spawn(move || run_external_observer(&config_a));
+        let observer_b = thread::spawn(move || run_external_observer(&config_b));
+        assert_eq!(observer_a.join().unwrap(), 5);
+        assert_eq!(observer_b.join().unwrap(), 5);
+    }
+
+    #[test]
+    fn test_engine_handle_shutdown() {
+        let config = EventEngineBuilder::new()
+            .incoming_port(35559)
+            .outgoing_port(35560)
+            .sync_port(35000)
+            .build();
+        let observer_config = config.clone();
+        let observer = thread::spawn(move || run_external_observer(&observer_config));
+
+        // returns once all plugins, including the observer, have synced
+        let engine = start_event_engine(&config).unwrap();
+        assert!(engine.is_running());
+        assert_eq!(observer.join().unwrap(), 5);
+
+        let (done_tx, done_rx) = std::sync::mpsc::channel();
+        thread::spawn(move || {
+            done_tx.send(engine.shutdown()).unwrap();
+        });
+        done_rx
+            .recv_timeout(Duration::from_secs(5))
+            .expect("engine threads did not exit within the timeout")
+            .unwrap();
+    }
+
+    #[test]
+    fn test_shutdown_publishes_plugin_terminate_event() {
+        let config = EventEngineBuilder::new()
+            .incoming_port(45559)
+            .outgoing_port(45560)
+            .sync_port(45000)
+            .build();
+        let observer_config = config.clone();
+        let (count_tx, count_rx) = std::sync::mpsc::channel();
+        let observer = thread::spawn(move || {
+            let (_context, sub_socket) = connect_external_observer(
+                &observer_config,
+                &[
+                    "ImageStoredEvent",
+                    "ImageDeletedEvent",
+                    "PluginTerminateEvent",
+                ],
+            );
+            count_tx.send(count_image_events(&sub_socket)).unwrap();
+            let (_, payload) =
+                recv_event_msg(&sub_socket).expect("timed out waiting for terminate event");
+            bytes_to_event(&payload)
+                .unwrap()
+                .event_type()
+                .variant_name()
+                .unwrap()
+                .to_string()
+        });
+
+        let engine = start_event_engine(&config).unwrap();
+        assert_eq!(count_rx.recv().unwrap(), 5);
+        engine.shutdown().unwrap();
+        assert_eq!(observer.join().unwrap(), "PluginTerminateEvent");
+    }
+
+    // Ignores every event it gets, including the PluginTerminateEvent, like a plugin that missed
+    // it; it only returns when `next_event` fails.
+    struct DeafPlugin(i32);
+
+    impl Plugin for DeafPlugin {
+        fn id(&self) -> i32 {
+            self.0
+        }
+
+        fn name(&self) -> &str {
+            "deaf-plugin"
+        }
+
+        fn subscriptions(&self) -> &[&str] {
+            &[]
+        }
+
+        fn start(
+            self: Box<Self>,
+            mut ctx: PluginContext,
+        ) -> Result<(), crate::plugin::PluginError> {
+            loop {
+                ctx.next_event()?;
+            }
+        }
+    }
+
+    // The number of threads of this process named `name`.
+    #[cfg(target_os = "linux")]
+    fn threads_named(name: &str) -> usize {
+        std::fs::read_dir("/proc/self/task")
+            .unwrap()
+            .filter_map(|task| std::fs::read_to_string(task.ok()?.path().join("comm")).ok())
+            .filter(|comm| comm.trim_end() == name)
+            .count()
+    }
+
+    #[test]
+    fn test_engine_starts_and_stops_repeatedly() {
+        let started = Instant::now();
+        for i in 0..20 {
+            let mut plugins = PluginRegistry::new();
+            plugins
+                .register_plugin(Box::new(DeafPlugin(0)))
+                .unwrap()
+                .register_plugin(Box::new(DeafPlugin(1)))
+                .unwrap();
+            let builder = EventEngineBuilder::new()
+                .incoming_inproc("messages-start-stop")
+                .outgoing_inproc("events-start-stop")
+                .transport(Transport::InprocOnly)
+                .plugins(plugins);
+            // every other engine runs on a context of the host's, which shutdown leaves alone
+            let builder = if i % 2 == 0 {
+                builder
+            } else {
+                builder.context(zmq::Context::new())
+            };
+            let engine = builder.start().unwrap();
+            engine.shutdown().unwrap();
+            // the plugins returned on their own instead of being detached
+            #[cfg(target_os = "linux")]
+            assert_eq!(threads_named("deaf-plugin"), 0);
+        }
+        // rather than each shutdown waiting out the grace period for the plugins
+        assert!(
+            started.elapsed() < PLUGIN_EXIT_GRACE_PERIOD * 4,
+            "20 engines took {:?} to start and stop",
+            started.elapsed()
+        );
+    }
+
+    #[test]
+    fn test_first_image_reaches_every_plugin_of_a_fresh_pipeline() {
+        // whichever plugin starts first, the first image it publishes reaches the others, as
+        // the pipeline would not store it otherwise
+        for run in 0..100 {
+            let labrador =
+                crate::image_score_plugin::FixedScorer::new(vec![("labrador".to_string(), 0.9)]);
+            let mut plugins = PluginRegistry::new();
+            plugins
+                .register_plugin(Box::new(
+                    crate::new_image_plugin::NewImagePlugin::new(0).images(1),
+                ))
+                .unwrap()
+                .register_plugin(Box::new(
+                    crate::image_score_plugin::ImageScorePlugin::with_scorer(1, Box::new(labrador))
+                        .images(1),
+                ))
+                .unwrap()
+                .register_plugin(Box::new(
+                    crate::image_store_plugin::ImageStorePlugin::new(2).images(1),
+                ))
+                .unwrap();
+            let engine = EventEngineBuilder::new()
+                .incoming_inproc("messages-first-image")
+                .outgoing_inproc("events-first-image")
+                .transport(Transport::InprocOnly)
+                .stop_after("ImageStoredEvent", 1)
+                .plugins(plugins)
+                .start()
+                .unwrap();
+            assert!(
+                engine.wait_for_stop(Duration::from_secs(10)),
+                "the first image was lost in run {}",
+                run
+            );
+            engine.shutdown().unwrap();
+        }
+    }
 }
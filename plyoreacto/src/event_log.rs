@@ -0,0 +1,846 @@
+//! A durable log of the events the engine forwards. An engine started with
+//! `EventEngineBuilder::event_log(EventLogConfig::new(dir))` appends every event its proxy
+//! forwards, with its type, its envelope (if it was sent with one) and its payload, to segment
+//! files in `dir`: `events-00000000.log`, `events-00000001.log`, ..., starting a new one once a
+//! segment reaches the configured size. An engine restarted on the same directory carries on
+//! with a new segment after the last one, so that nothing is written after a record torn by a
+//! crash. `EventLogReader` reads the segments back, oldest first.
+//!
+//! Each record is a big-endian u32 giving the length of the rest of it, then the length of the
+//! type name (one byte) and the name, the length of the meta frame (one byte, 0 for an event
+//! without an envelope) and the frame, and finally the flatbuffer of the event.
+//!
+//! Like the metrics exporter, the log reads the copies of the events the proxy sends to its
+//! capture socket, and hands them to a writer thread through a bounded buffer, so that writing
+//! never holds the proxy up: events arriving while the buffer is full are dropped, and counted in
+//! `EngineHandle::event_log_dropped`. The capture socket itself drops copies once its high-water
+//! mark is reached, which that count would miss.
+//!
+//! `EngineHandle::replay` publishes the events of a log to an engine again, as fast as it can or
+//! paced like the original run. Their envelopes are marked `replayed`; see `EventMeta`.
+//!
+
+use std::borrow::Cow;
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use flatbuffers::FlatBufferBuilder;
+use log::{error, info, warn};
+use zmq::Socket;
+
+use crate::compression::decompressed;
+use crate::event_engine::{bind, connect, create_socket, subscribe_to_capture};
+use crate::event_engine::{EngineConfig, EngineError};
+use crate::events::{
+    known_event_type, parse_event_messages, recv_event_frames, retry_on_eintr,
+    send_event_msg_with_meta, Event, EventError, EventMeta, Frame,
+};
+
+const DEFAULT_SEGMENT_SIZE: u64 = 64 * 1024 * 1024;
+const DEFAULT_BUFFER: usize = 10_000;
+
+// The source plugin id of the envelopes given on replay to the events logged without one, which
+// no plugin published.
+const REPLAY_SOURCE_PLUGIN_ID: i32 = -1;
+
+// Segment files are named after their number, padded so that they sort in order.
+const SEGMENT_PREFIX: &str = "events-";
+const SEGMENT_SUFFIX: &str = ".log";
+
+/// Where and how an engine logs the events it forwards; see the module documentation.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EventLogConfig {
+    // directory of the segment files, created if it does not exist
+    pub dir: PathBuf,
+    // size in bytes past which a new segment is started; a record is never split, so a segment
+    // holding a single big event may be larger
+    pub segment_size: u64,
+    // how many events may wait for the writer before new ones are dropped
+    pub buffer: usize,
+}
+
+impl EventLogConfig {
+    /// Log to segments of 64 MiB in `dir`, buffering up to 10000 events for the writer.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        EventLogConfig {
+            dir: dir.into(),
+            segment_size: DEFAULT_SEGMENT_SIZE,
+            buffer: DEFAULT_BUFFER,
+        }
+    }
+
+    /// Start a new segment once the current one has reached `bytes`.
+    pub fn segment_size(mut self, bytes: u64) -> Self {
+        self.segment_size = bytes;
+        self
+    }
+
+    /// Buffer up to `events` events for the writer.
+    pub fn buffer(mut self, events: usize) -> Self {
+        self.buffer = events;
+        self
+    }
+}
+
+// An event on its way to the writer (or the capture consumer), its payload still in the message
+// it was captured in.
+pub(crate) type Captured = (&'static str, Option<EventMeta>, Frame);
+
+// Control socket and threads of the event log, and how many events it dropped.
+pub(crate) struct EventLog {
+    control: Mutex<Socket>,
+    reader_thread: JoinHandle<()>,
+    writer_thread: JoinHandle<()>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl EventLog {
+    // How many events were dropped because the writer did not keep up, or had stopped.
+    pub(crate) fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    // Log the events captured so far and join the threads; called once the proxy has stopped,
+    // so that these are all the events it forwarded.
+    pub(crate) fn stop(self) -> Result<(), EngineError> {
+        self.control
+            .into_inner()
+            .expect("event log lock poisoned")
+            .send("TERMINATE", 0)
+            .map_err(|source| EngineError::Shutdown { source })?;
+        self.reader_thread
+            .join()
+            .expect("Engine event log reader thread panicked");
+        self.writer_thread
+            .join()
+            .expect("Engine event log writer thread panicked");
+        Ok(())
+    }
+}
+
+// Start logging the events the proxy captures, if the engine has an event log. The first
+// segment is opened here, so that a directory the engine cannot write to fails the start.
+pub(crate) fn start_event_log(
+    context: &zmq::Context,
+    config: &EngineConfig,
+) -> Result<Option<EventLog>, EngineError> {
+    let log_config = match &config.event_log {
+        Some(log_config) => log_config.clone(),
+        None => return Ok(None),
+    };
+    let log_error = |source| EngineError::EventLog {
+        dir: log_config.dir.clone(),
+        source,
+    };
+    let writer =
+        SegmentWriter::open(&log_config.dir, log_config.segment_size).map_err(log_error)?;
+    let captured = subscribe_to_capture(context, config, "logged events")?;
+
+    let control_endpoint = format!("inproc://{}-event-log-control", config.outgoing_inproc);
+    let event_log_control = create_socket(context, config, zmq::PAIR, "event log control")?;
+    bind(&event_log_control, &control_endpoint)?;
+    let control = create_socket(context, config, zmq::PAIR, "event log")?;
+    connect(&control, &control_endpoint)?;
+    info!(
+        "Engine logging events to {}, starting with {}",
+        log_config.dir.display(),
+        writer.path().display()
+    );
+
+    let (records, to_write) = mpsc::sync_channel(log_config.buffer);
+    let dropped = Arc::new(AtomicU64::new(0));
+    let reader_dropped = Arc::clone(&dropped);
+    let reader_thread = thread::spawn(move || {
+        read_capture(
+            captured,
+            event_log_control,
+            records,
+            reader_dropped,
+            "event log",
+        )
+    });
+    let writer_thread = thread::spawn(move || {
+        let dir = log_config.dir;
+        if let Err(e) = write_events(writer, to_write) {
+            error!("Engine stopped logging events to {}: {}", dir.display(), e);
+        }
+    });
+    Ok(Some(EventLog {
+        control: Mutex::new(control),
+        reader_thread,
+        writer_thread,
+        dropped,
+    }))
+}
+
+// Hand the events captured on `captured` to the `reader` named in the logs (the writer, or the
+// capture consumer) until TERMINATE is received on `control`, dropping those that do not fit in
+// the buffer.
+pub(crate) fn read_capture(
+    captured: Socket,
+    control: Socket,
+    records: SyncSender<Captured>,
+    dropped: Arc<AtomicU64>,
+    reader: &str,
+) {
+    loop {
+        let mut items = [
+            captured.as_poll_item(zmq::POLLIN),
+            control.as_poll_item(zmq::POLLIN),
+        ];
+        if retry_on_eintr(|| zmq::poll(&mut items, -1)).is_err() {
+            return;
+        }
+        let stopping = items[1].is_readable();
+        // take every event captured so far; when stopping, these are the last the proxy forwarded
+        while let Ok(frames) = recv_event_frames(&captured, zmq::DONTWAIT) {
+            // the capture also sees the subscriptions going the other way, which are not events
+            let Some(record) = parse_event_messages(frames) else {
+                continue;
+            };
+            if records.try_send(record).is_err() && dropped.fetch_add(1, Ordering::Relaxed) == 0 {
+                warn!("Engine {} is not keeping up, dropping events", reader);
+            }
+        }
+        if stopping {
+            return;
+        }
+    }
+}
+
+// Append the events received on `records` until the reader is done with them; the segment is
+// flushed whenever the buffer runs empty, and synced to disk when it is closed.
+fn write_events(mut writer: SegmentWriter, records: Receiver<Captured>) -> io::Result<()> {
+    while let Ok(record) = records.recv() {
+        writer.append(&record)?;
+        // write out a burst before flushing it
+        while let Ok(record) = records.try_recv() {
+            writer.append(&record)?;
+        }
+        writer.file.flush()?;
+    }
+    writer.sync()
+}
+
+// The path of segment `number` in `dir`.
+fn segment_path(dir: &Path, number: u64) -> PathBuf {
+    dir.join(format!("{}{:08}{}", SEGMENT_PREFIX, number, SEGMENT_SUFFIX))
+}
+
+// The number of the segment named `name`, if it is one.
+fn segment_number(name: &str) -> Option<u64> {
+    name.strip_prefix(SEGMENT_PREFIX)?
+        .strip_suffix(SEGMENT_SUFFIX)?
+        .parse()
+        .ok()
+}
+
+// The numbers and paths of the segments in `dir`, oldest first.
+fn segments(dir: &Path) -> io::Result<Vec<(u64, PathBuf)>> {
+    let mut segments = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        if let Some(number) = entry.file_name().to_str().and_then(segment_number) {
+            segments.push((number, entry.path()));
+        }
+    }
+    segments.sort();
+    Ok(segments)
+}
+
+// Appends records to the current segment, starting a new one when it is full.
+struct SegmentWriter {
+    dir: PathBuf,
+    number: u64,
+    file: BufWriter<File>,
+    written: u64,
+    segment_size: u64,
+}
+
+impl SegmentWriter {
+    // Open a new segment after the last one in `dir`.
+    fn open(dir: &Path, segment_size: u64) -> io::Result<Self> {
+        fs::create_dir_all(dir)?;
+        let number = match segments(dir)?.last() {
+            Some((last, _)) => last + 1,
+            None => 0,
+        };
+        Ok(SegmentWriter {
+            dir: dir.to_path_buf(),
+            number,
+            file: create_segment(&segment_path(dir, number))?,
+            written: 0,
+            segment_size,
+        })
+    }
+
+    fn path(&self) -> PathBuf {
+        segment_path(&self.dir, self.number)
+    }
+
+    fn append(&mut self, (event_type, meta, payload): &Captured) -> io::Result<()> {
+        let meta = meta.map(|meta| meta.to_bytes()).unwrap_or_default();
+        let len = 1 + event_type.len() + 1 + meta.len() + payload.len();
+        let len = u32::try_from(len).map_err(|_| {
+            io::Error::new(io::ErrorKind::InvalidInput, "event too big for the log")
+        })?;
+        let record_size = 4 + u64::from(len);
+        if self.written > 0 && self.written + record_size > self.segment_size {
+            self.rotate()?;
+        }
+        self.file.write_all(&len.to_be_bytes())?;
+        // event type names are far shorter than 256 bytes, and the meta frame is 69
+        self.file.write_all(&[event_type.len() as u8])?;
+        self.file.write_all(event_type.as_bytes())?;
+        self.file.write_all(&[meta.len() as u8])?;
+        self.file.write_all(&meta)?;
+        self.file.write_all(payload)?;
+        self.written += record_size;
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.sync()?;
+        self.number += 1;
+        self.file = create_segment(&self.path())?;
+        self.written = 0;
+        Ok(())
+    }
+
+    fn sync(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        self.file.get_ref().sync_data()
+    }
+}
+
+fn create_segment(path: &Path) -> io::Result<BufWriter<File>> {
+    let file = OpenOptions::new().write(true).create_new(true).open(path)?;
+    Ok(BufWriter::new(file))
+}
+
+/// An error reading an event log.
+#[derive(Debug)]
+pub enum EventLogError {
+    /// The directory or a segment could not be read.
+    Io(io::Error),
+    /// The segment ends in the middle of the record at `offset`, e.g., because the engine
+    /// writing it crashed; the rest of the segment is skipped.
+    Truncated { segment: PathBuf, offset: u64 },
+    /// The record at `offset` is not one the engine writes; the rest of the segment is skipped.
+    Malformed { segment: PathBuf, offset: u64 },
+    /// The event of the record at `offset` could not be decoded.
+    Event {
+        segment: PathBuf,
+        offset: u64,
+        source: EventError,
+    },
+}
+
+impl fmt::Display for EventLogError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EventLogError::Io(source) => write!(f, "could not read the event log: {}", source),
+            EventLogError::Truncated { segment, offset } => write!(
+                f,
+                "{} ends in the record at offset {}",
+                segment.display(),
+                offset
+            ),
+            EventLogError::Malformed { segment, offset } => write!(
+                f,
+                "malformed record at offset {} of {}",
+                offset,
+                segment.display()
+            ),
+            EventLogError::Event {
+                segment,
+                offset,
+                source,
+            } => write!(
+                f,
+                "could not decode the event at offset {} of {}: {}",
+                offset,
+                segment.display(),
+                source
+            ),
+        }
+    }
+}
+
+impl std::error::Error for EventLogError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            EventLogError::Io(source) => Some(source),
+            EventLogError::Event { source, .. } => Some(source),
+            EventLogError::Truncated { .. } | EventLogError::Malformed { .. } => None,
+        }
+    }
+}
+
+impl From<io::Error> for EventLogError {
+    fn from(error: io::Error) -> Self {
+        EventLogError::Io(error)
+    }
+}
+
+/// An event read back from the log.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LoggedEvent {
+    pub event_type: &'static str,
+    // None for an event that was sent without an envelope
+    pub meta: Option<EventMeta>,
+    pub event: Event,
+}
+
+/// Reads the events an engine logged in a directory, in the order the proxy forwarded them.
+///
+/// ```no_run
+/// use plyoreacto::event_log::EventLogReader;
+///
+/// for logged in EventLogReader::open("events").unwrap().iter() {
+///     let logged = logged.unwrap();
+///     println!("{} {:?}", logged.event_type, logged.event);
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct EventLogReader {
+    segments: Vec<PathBuf>,
+}
+
+impl EventLogReader {
+    /// A reader of the segments in `dir` as they are now; segments started later are not read.
+    pub fn open(dir: impl AsRef<Path>) -> Result<Self, EventLogError> {
+        let segments = segments(dir.as_ref())?;
+        Ok(EventLogReader {
+            segments: segments.into_iter().map(|(_, path)| path).collect(),
+        })
+    }
+
+    /// The segment files, oldest first.
+    pub fn segments(&self) -> &[PathBuf] {
+        &self.segments
+    }
+
+    /// The logged events, oldest first. A segment that cannot be read past an error yields the
+    /// error and no more events; those of the next segment follow.
+    pub fn iter(&self) -> EventLogIter {
+        EventLogIter {
+            segments: self.segments.clone().into_iter(),
+            current: None,
+        }
+    }
+}
+
+/// The iterator returned by `EventLogReader::iter`.
+#[derive(Debug)]
+pub struct EventLogIter {
+    segments: std::vec::IntoIter<PathBuf>,
+    current: Option<SegmentReader>,
+}
+
+impl Iterator for EventLogIter {
+    type Item = Result<LoggedEvent, EventLogError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let reader = match &mut self.current {
+                Some(reader) => reader,
+                None => {
+                    let path = self.segments.next()?;
+                    let file = match File::open(&path) {
+                        Ok(file) => file,
+                        Err(e) => return Some(Err(e.into())),
+                    };
+                    self.current.insert(SegmentReader {
+                        path,
+                        file: BufReader::new(file),
+                        offset: 0,
+                    })
+                }
+            };
+            match reader.next_record() {
+                Ok(Some(logged)) => return Some(Ok(logged)),
+                Ok(None) => self.current = None,
+                Err(e) => {
+                    self.current = None;
+                    return Some(Err(e));
+                }
+            }
+        }
+    }
+}
+
+// Reads the records of one segment.
+#[derive(Debug)]
+struct SegmentReader {
+    path: PathBuf,
+    file: BufReader<File>,
+    offset: u64,
+}
+
+impl SegmentReader {
+    // The next record, or None at the end of the segment.
+    fn next_record(&mut self) -> Result<Option<LoggedEvent>, EventLogError> {
+        let mut len = [0; 4];
+        let read = read_fully(&mut self.file, &mut len)?;
+        if read == 0 {
+            return Ok(None);
+        }
+        let mut record = vec![0; u32::from_be_bytes(len) as usize];
+        if read < len.len() || read_fully(&mut self.file, &mut record)? < record.len() {
+            return Err(EventLogError::Truncated {
+                segment: self.path.clone(),
+                offset: self.offset,
+            });
+        }
+        let logged = self.parse_record(&record)?;
+        self.offset += (len.len() + record.len()) as u64;
+        Ok(Some(logged))
+    }
+
+    fn parse_record(&self, record: &[u8]) -> Result<LoggedEvent, EventLogError> {
+        let malformed = || EventLogError::Malformed {
+            segment: self.path.clone(),
+            offset: self.offset,
+        };
+        let (event_type, rest) = split_field(record).ok_or_else(malformed)?;
+        let (meta, payload) = split_field(rest).ok_or_else(malformed)?;
+        let event_type = std::str::from_utf8(event_type).map_err(|_| malformed())?;
+        let event_type = known_event_type(event_type).ok_or_else(malformed)?;
+        let mut meta = match meta {
+            [] => None,
+            meta => Some(EventMeta::from_bytes(meta).ok_or_else(malformed)?),
+        };
+        // the log holds the payloads as they were forwarded, compressed or not
+        let event = decompressed(&mut meta, Cow::Borrowed(payload), 0)
+            .and_then(|payload| Event::decode_with_meta(event_type, meta.as_ref(), &payload))
+            .map_err(|source| EventLogError::Event {
+                segment: self.path.clone(),
+                offset: self.offset,
+                source,
+            })?;
+        Ok(LoggedEvent {
+            event_type,
+            meta,
+            event,
+        })
+    }
+}
+
+// Split a field prefixed with its one byte length off `bytes`.
+fn split_field(bytes: &[u8]) -> Option<(&[u8], &[u8])> {
+    let (len, rest) = bytes.split_first()?;
+    (rest.len() >= *len as usize).then(|| rest.split_at(*len as usize))
+}
+
+// Fill `buf` from `reader`, returning fewer bytes than it holds only at the end of the file.
+fn read_fully(reader: &mut impl Read, buf: &mut [u8]) -> io::Result<usize> {
+    let mut read = 0;
+    while read < buf.len() {
+        match reader.read(&mut buf[read..]) {
+            Ok(0) => break,
+            Ok(n) => read += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(read)
+}
+
+/// How fast `EngineHandle::replay` publishes the events of a log.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ReplaySpeed {
+    /// One right after the other.
+    Unpaced,
+    /// With the time between them in the original run, as taken from their envelopes, divided by
+    /// this factor: 2.0 replays twice as fast. An event without an envelope follows the one
+    /// before it right away.
+    Paced(f64),
+}
+
+// Publish the events logged in `dir` on `socket`, marked as replayed, and return how many were
+// published. Records that cannot be read, such as one torn at the end of a segment, are skipped
+// with a warning.
+pub(crate) fn replay(
+    socket: &Socket,
+    bldr: &mut FlatBufferBuilder,
+    dir: &Path,
+    speed: ReplaySpeed,
+) -> Result<u64, EngineError> {
+    let reader = EventLogReader::open(dir).map_err(|source| EngineError::Replay {
+        dir: dir.to_path_buf(),
+        source,
+    })?;
+    info!(
+        "Engine replaying {} event log segments from {}",
+        reader.segments().len(),
+        dir.display()
+    );
+    let started = Instant::now();
+    let mut first_us = None;
+    let mut replayed = 0;
+    for logged in reader.iter() {
+        let logged = match logged {
+            Ok(logged) => logged,
+            Err(e) => {
+                warn!("Engine skipping unreadable event log records: {}", e);
+                continue;
+            }
+        };
+        if let (ReplaySpeed::Paced(speed), Some(meta)) = (speed, &logged.meta) {
+            let first_us = *first_us.get_or_insert(meta.monotonic_us);
+            let offset = Duration::from_micros(meta.monotonic_us.saturating_sub(first_us));
+            let due = started + offset.div_f64(speed);
+            thread::sleep(due.saturating_duration_since(Instant::now()));
+        }
+        // replayed events are not numbered, so that they are not taken for dropped ones; they
+        // are published as they were decoded, uncompressed
+        let meta = EventMeta {
+            seq: 0,
+            replayed: true,
+            compressed: false,
+            ..logged
+                .meta
+                .unwrap_or_else(|| EventMeta::new(REPLAY_SOURCE_PLUGIN_ID))
+        };
+        let payload = logged.event.encode(bldr);
+        send_event_msg_with_meta(socket, logged.event_type, &meta, payload)
+            .map_err(|source| EngineError::Io(source.into()))?;
+        replayed += 1;
+    }
+    info!("Engine replayed {} events", replayed);
+    Ok(replayed)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::event_engine::{EventEngineBuilder, Transport};
+    use crate::events::PluginHeartbeat;
+    use crate::image_score_plugin::{FixedScorer, ImageScorePlugin};
+    use crate::image_store_plugin::ImageStorePlugin;
+    use crate::new_image_plugin::NewImagePlugin;
+    use crate::plugin_registry::PluginRegistry;
+    use crate::storage::InMemoryStore;
+
+    fn scratch_dir() -> PathBuf {
+        std::env::temp_dir().join(format!("plyoreacto-log-{}", uuid::Uuid::new_v4()))
+    }
+
+    #[test]
+    fn test_pipeline_round_trips_through_the_log() {
+        let dir = scratch_dir();
+        let labrador = FixedScorer::new(vec![("labrador".to_string(), 0.9)]);
+        let mut plugins = PluginRegistry::new();
+        plugins
+            .register_plugin(Box::new(NewImagePlugin::new(0).image_size(4096)))
+            .unwrap()
+            .register_plugin(Box::new(ImageScorePlugin::with_scorer(
+                1,
+                Box::new(labrador),
+            )))
+            .unwrap()
+            .register_plugin(Box::new(ImageStorePlugin::new(2)))
+            .unwrap();
+        let engine = EventEngineBuilder::new()
+            .incoming_inproc("messages-event-log")
+            .outgoing_inproc("events-event-log")
+            .transport(Transport::InprocOnly)
+            // two images to a segment
+            .event_log(EventLogConfig::new(&dir).segment_size(10_000))
+            .stop_after("ImageStoredEvent", 5)
+            .plugins(plugins)
+            .start()
+            .unwrap();
+        assert!(engine.wait_for_stop(Duration::from_secs(10)));
+        assert_eq!(engine.event_log_dropped(), 0);
+        engine.shutdown().unwrap();
+
+        let reader = EventLogReader::open(&dir).unwrap();
+        assert!(reader.segments().len() >= 3, "{:?}", reader.segments());
+        let logged: Vec<LoggedEvent> = reader.iter().map(Result::unwrap).collect();
+        let of_type = |event_type| {
+            logged
+                .iter()
+                .filter(|logged| logged.event_type == event_type)
+                .collect::<Vec<_>>()
+        };
+        let new_images = of_type("NewImageEvent");
+        let stored = of_type("ImageStoredEvent");
+        assert_eq!(new_images.len(), 5);
+        assert_eq!(of_type("ImageScoredEvent").len(), 5);
+        assert_eq!(stored.len(), 5);
+        for logged in &logged {
+            assert_eq!(logged.event_type, logged.event.type_name());
+        }
+        match &new_images[0].event {
+            Event::NewImage(image) => assert_eq!(image.image.len(), 4096),
+            event => panic!("unexpected event {:?}", event),
+        }
+        // every stored image continues the chain its new image started
+        let meta = |logged: &LoggedEvent| logged.meta.expect("published with an envelope");
+        let chains: Vec<_> = new_images.iter().map(|l| meta(l).correlation_id).collect();
+        for logged in &stored {
+            assert!(chains.contains(&meta(logged).correlation_id));
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_new_segment_after_the_last_and_torn_record_is_reported() {
+        let dir = scratch_dir();
+        let mut bldr = FlatBufferBuilder::new();
+        let heartbeat = |seq| Event::PluginHeartbeat(PluginHeartbeat { plugin_id: 1, seq });
+        let captured = |bldr: &mut FlatBufferBuilder, event: &Event| {
+            let msg = zmq::Message::from(event.encode(bldr));
+            let frames = vec![zmq::Message::from(&b"PluginHeartbeatEvent\0"[..]), msg];
+            parse_event_messages(frames).unwrap()
+        };
+        let mut writer = SegmentWriter::open(&dir, DEFAULT_SEGMENT_SIZE).unwrap();
+        writer.append(&captured(&mut bldr, &heartbeat(1))).unwrap();
+        writer.sync().unwrap();
+        let first = writer.path();
+
+        // a restarted engine does not append to the segment a crash may have torn
+        let mut writer = SegmentWriter::open(&dir, DEFAULT_SEGMENT_SIZE).unwrap();
+        assert_eq!(writer.path(), segment_path(&dir, 1));
+        writer.append(&captured(&mut bldr, &heartbeat(2))).unwrap();
+        writer.append(&captured(&mut bldr, &heartbeat(3))).unwrap();
+        writer.sync().unwrap();
+        let second = fs::read(writer.path()).unwrap();
+        fs::write(writer.path(), &second[..second.len() - 1]).unwrap();
+
+        let logged: Vec<_> = EventLogReader::open(&dir).unwrap().iter().collect();
+        assert_eq!(logged.len(), 3, "{:?}", logged);
+        assert_eq!(logged[0].as_ref().unwrap().event, heartbeat(1));
+        assert_eq!(logged[0].as_ref().unwrap().meta, None);
+        assert_eq!(logged[1].as_ref().unwrap().event, heartbeat(2));
+        match &logged[2] {
+            Err(EventLogError::Truncated { segment, offset }) => {
+                assert_eq!(*segment, writer.path());
+                assert_eq!(*offset as usize, second.len() / 2);
+            }
+            result => panic!("unexpected result {:?}", result),
+        }
+        assert_ne!(first, writer.path());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_three_image_run_replays_into_a_fresh_engine() {
+        let dir = scratch_dir();
+        let labrador = FixedScorer::new(vec![("labrador".to_string(), 0.9)]);
+        let mut plugins = PluginRegistry::new();
+        plugins
+            .register_plugin(Box::new(NewImagePlugin::new(0).images(3)))
+            .unwrap()
+            .register_plugin(Box::new(
+                ImageScorePlugin::with_scorer(1, Box::new(labrador)).images(3),
+            ))
+            .unwrap()
+            .register_plugin(Box::new(ImageStorePlugin::new(2).images(3)))
+            .unwrap();
+        let engine = EventEngineBuilder::new()
+            .incoming_inproc("messages-captured-run")
+            .outgoing_inproc("events-captured-run")
+            .transport(Transport::InprocOnly)
+            .event_log(EventLogConfig::new(&dir))
+            .stop_after("ImageStoredEvent", 3)
+            .plugins(plugins)
+            .start()
+            .unwrap();
+        assert!(engine.wait_for_stop(Duration::from_secs(10)));
+        engine.shutdown().unwrap();
+        // the engine crashed in the middle of writing one more record
+        let reader = EventLogReader::open(&dir).unwrap();
+        let last = reader.segments().last().unwrap();
+        let mut segment = OpenOptions::new().append(true).open(last).unwrap();
+        segment.write_all(&[0, 0, 1, 0, 16]).unwrap();
+
+        let store = InMemoryStore::new();
+        let mut plugins = PluginRegistry::new();
+        plugins
+            .register_plugin(Box::new(
+                ImageStorePlugin::new(0)
+                    .images(3)
+                    .storage(Box::new(store.clone())),
+            ))
+            .unwrap();
+        let engine = EventEngineBuilder::new()
+            .incoming_inproc("messages-replayed-run")
+            .outgoing_inproc("events-replayed-run")
+            .transport(Transport::InprocOnly)
+            .plugins(plugins)
+            .start()
+            .unwrap();
+        let replayed = engine.replay(&dir, ReplaySpeed::Unpaced).unwrap();
+        let results = engine.wait_for_plugins(Duration::from_secs(10));
+        engine.shutdown().unwrap();
+        assert!(replayed >= 9, "replayed only {} events", replayed);
+        assert!(
+            matches!(results.get(&0), Some(Ok(()))),
+            "store plugin did not store three images: {:?}",
+            results
+        );
+        assert_eq!(store.len(), 3);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_paced_replay_keeps_the_original_timings() {
+        let dir = scratch_dir();
+        let mut bldr = FlatBufferBuilder::new();
+        let meta = EventMeta {
+            seq: 4,
+            ..EventMeta::new(1)
+        };
+        let later = EventMeta {
+            monotonic_us: meta.monotonic_us + 200_000,
+            seq: 5,
+            ..EventMeta::new(1)
+        };
+        let mut writer = SegmentWriter::open(&dir, DEFAULT_SEGMENT_SIZE).unwrap();
+        for (seq, meta) in [(1, meta), (2, later)] {
+            let event = Event::PluginHeartbeat(PluginHeartbeat { plugin_id: 1, seq });
+            let payload = zmq::Message::from(event.encode(&mut bldr));
+            let frames = vec![
+                zmq::Message::from(&b"PluginHeartbeatEvent\0"[..]),
+                zmq::Message::from(&meta.to_bytes()[..]),
+                payload,
+            ];
+            writer
+                .append(&parse_event_messages(frames).unwrap())
+                .unwrap();
+        }
+        writer.sync().unwrap();
+
+        let ctx = zmq::Context::new();
+        let pull = ctx.socket(zmq::PULL).unwrap();
+        pull.bind("inproc://test-paced-replay").unwrap();
+        let push = ctx.socket(zmq::PUSH).unwrap();
+        push.connect("inproc://test-paced-replay").unwrap();
+        let started = Instant::now();
+        let replayed = replay(&push, &mut bldr, &dir, ReplaySpeed::Paced(2.0)).unwrap();
+        let elapsed = started.elapsed();
+        assert_eq!(replayed, 2);
+        assert!(elapsed >= Duration::from_millis(100), "{:?}", elapsed);
+        assert!(elapsed < Duration::from_millis(200), "{:?}", elapsed);
+        for expected in [meta, later] {
+            let frames = recv_event_frames(&pull, 0).unwrap();
+            let (_, received, _) = parse_event_messages(frames).unwrap();
+            let received = received.unwrap();
+            assert!(received.replayed);
+            assert_eq!(received.seq, 0);
+            assert_eq!(received.event_id, expected.event_id);
+            assert_eq!(received.monotonic_us, expected.monotonic_us);
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,322 @@
+//! Event types defined by an application instead of the events schema, so that plugins with
+//! events of their own do not need a fork of the crate. Register the name of each such type
+//! before starting the engine, and in every process whose plugins publish or subscribe to it
+//! (e.g., those of external plugins), and it can be subscribed to, published and received like
+//! the built-in types:
+//!
+//! ```
+//! use plyoreacto::event_type_registry::EventTypeRegistry;
+//! use plyoreacto::events::{get_event_type_bytes_filter, Event};
+//!
+//! let weather = EventTypeRegistry::global().register("WeatherEvent").unwrap();
+//! assert!(get_event_type_bytes_filter("WeatherEvent").is_ok());
+//! let event = weather.event(b"sunny".to_vec());
+//! assert_eq!(event, Event::Custom { type_name: "WeatherEvent", payload: b"sunny".to_vec() });
+//! ```
+//!
+//! The header of a custom event's messages is its name, as for the built-in types, and its
+//! payload frame a `CustomEvent` flatbuffer holding the id of its type and its own payload,
+//! serialized however its publishers and subscribers agree on. The id is derived from the name,
+//! so every process that registers the name gets the same one whatever else it registers, and
+//! `Event::decode` finds the type from it again, giving an `Event::Custom`.
+//!
+
+use std::fmt;
+use std::sync::{OnceLock, RwLock};
+
+use crate::events::{event_type_header, event_type_names, Event};
+
+// The table carrying every custom event; a type of this name could not be told apart from it.
+const CUSTOM_EVENT: &str = "CustomEvent";
+
+/// An event type registered with the `EventTypeRegistry`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct CustomEventType {
+    /// The name of the type, as used for subscriptions.
+    pub name: &'static str,
+    /// The id of the type in the `CustomEvent` flatbuffers of its events.
+    pub type_id: u32,
+}
+
+impl CustomEventType {
+    /// The header frame of the messages of this type, which subscriptions to it filter on.
+    pub fn header(&self) -> Vec<u8> {
+        event_type_header(self.name)
+    }
+
+    /// An event of this type with `payload`, to publish with `PluginContext::publish`.
+    pub fn event(&self, payload: Vec<u8>) -> Event {
+        Event::Custom {
+            type_name: self.name,
+            payload,
+        }
+    }
+}
+
+/// Why an event type could not be registered.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EventTypeError {
+    /// The name is empty, or has a NUL byte, which would end the header of its messages.
+    InvalidName { name: String },
+    /// The name is that of a built-in event type.
+    BuiltIn { name: String },
+    /// The id derived from the name is that of `registered`, a type registered before, so their
+    /// events could not be told apart.
+    TypeIdTaken {
+        name: String,
+        registered: &'static str,
+    },
+}
+
+impl fmt::Display for EventTypeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EventTypeError::InvalidName { name } => {
+                write!(f, "{:?} is not a valid event type name", name)
+            }
+            EventTypeError::BuiltIn { name } => write!(f, "{} is a built-in event type", name),
+            EventTypeError::TypeIdTaken { name, registered } => {
+                write!(
+                    f,
+                    "{} has the same type id as the registered event type {}",
+                    name, registered
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for EventTypeError {}
+
+/// The event types registered in this process, on top of the built-in ones.
+pub struct EventTypeRegistry {
+    types: RwLock<Vec<CustomEventType>>,
+}
+
+impl EventTypeRegistry {
+    /// The registry of the process, which the events module and the engine consult.
+    pub fn global() -> &'static EventTypeRegistry {
+        static REGISTRY: OnceLock<EventTypeRegistry> = OnceLock::new();
+        REGISTRY.get_or_init(|| EventTypeRegistry {
+            types: RwLock::new(Vec::new()),
+        })
+    }
+
+    /// Register the event type `name`, and return it with its type id; registering a name again
+    /// returns the same type. Registered types stay for the life of the process.
+    pub fn register(&self, name: &str) -> Result<CustomEventType, EventTypeError> {
+        if name.is_empty() || name.contains('\0') {
+            return Err(EventTypeError::InvalidName {
+                name: name.to_string(),
+            });
+        }
+        if name == CUSTOM_EVENT || event_type_names().contains(&name) {
+            return Err(EventTypeError::BuiltIn {
+                name: name.to_string(),
+            });
+        }
+        let type_id = custom_type_id(name);
+        let mut types = self.types.write().unwrap();
+        match types
+            .iter()
+            .find(|registered| registered.type_id == type_id)
+        {
+            Some(registered) if registered.name == name => return Ok(*registered),
+            Some(registered) => {
+                return Err(EventTypeError::TypeIdTaken {
+                    name: name.to_string(),
+                    registered: registered.name,
+                })
+            }
+            None => {}
+        }
+        let registered = CustomEventType {
+            name: Box::leak(name.into()),
+            type_id,
+        };
+        types.push(registered);
+        Ok(registered)
+    }
+
+    /// The registered type named `name`, if any.
+    pub fn by_name(&self, name: &str) -> Option<CustomEventType> {
+        let types = self.types.read().unwrap();
+        types
+            .iter()
+            .find(|registered| registered.name == name)
+            .copied()
+    }
+
+    /// The registered type with id `type_id`, if any.
+    pub fn by_type_id(&self, type_id: u32) -> Option<CustomEventType> {
+        let types = self.types.read().unwrap();
+        types
+            .iter()
+            .find(|registered| registered.type_id == type_id)
+            .copied()
+    }
+
+    /// Every registered type, in the order they were registered.
+    pub fn types(&self) -> Vec<CustomEventType> {
+        self.types.read().unwrap().clone()
+    }
+}
+
+// The type id of the custom event type `name`: its 32-bit FNV-1a hash, which every process
+// computes the same.
+pub(crate) fn custom_type_id(name: &str) -> u32 {
+    name.bytes().fold(0x811c_9dc5, |hash, byte| {
+        (hash ^ u32::from(byte)).wrapping_mul(0x0100_0193)
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::event_engine::{EventEngineBuilder, Transport};
+    use crate::events::{get_event_type_bytes_filter, known_event_type, EventError};
+    use crate::plugin::{Plugin, PluginContext, PluginError};
+    use crate::plugin_registry::PluginRegistry;
+    use flatbuffers::FlatBufferBuilder;
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    #[test]
+    fn test_registered_names_must_be_new() {
+        let registry = EventTypeRegistry::global();
+        for name in ["NewImageEvent", "PluginTerminateEvent", "CustomEvent"] {
+            assert_eq!(
+                registry.register(name),
+                Err(EventTypeError::BuiltIn {
+                    name: name.to_string()
+                })
+            );
+        }
+        for name in ["", "Weather\0Event"] {
+            assert!(matches!(
+                registry.register(name),
+                Err(EventTypeError::InvalidName { .. })
+            ));
+        }
+        assert!(get_event_type_bytes_filter("TideEvent").is_err());
+        let tide = registry.register("TideEvent").unwrap();
+        assert_eq!(tide.type_id, custom_type_id("TideEvent"));
+        assert_eq!(registry.register("TideEvent"), Ok(tide));
+        assert_eq!(registry.by_name("TideEvent"), Some(tide));
+        assert_eq!(registry.by_type_id(tide.type_id), Some(tide));
+        assert!(registry.types().contains(&tide));
+        assert_eq!(known_event_type("TideEvent"), Some("TideEvent"));
+        assert_eq!(
+            get_event_type_bytes_filter("TideEvent").unwrap(),
+            tide.header()
+        );
+    }
+
+    #[test]
+    fn test_custom_events_decode_only_as_registered_types() {
+        let registry = EventTypeRegistry::global();
+        let storm = registry.register("StormEvent").unwrap();
+        let mut bldr = FlatBufferBuilder::new();
+        let event = storm.event(vec![1, 2, 3]);
+        let payload = event.encode(&mut bldr).to_vec();
+        assert_eq!(Event::decode(&payload).unwrap(), event);
+        assert_eq!(Event::decode_as("StormEvent", &payload).unwrap(), event);
+        assert!(matches!(
+            Event::decode_as("TideEvent", &payload),
+            Err(EventError::TypeMismatch { .. })
+        ));
+
+        // an id nothing was registered with, as from a process that registered more types
+        let unregistered = Event::Custom {
+            type_name: "DroughtEvent",
+            payload: Vec::new(),
+        };
+        let payload = unregistered.encode(&mut bldr).to_vec();
+        assert!(matches!(
+            Event::decode(&payload),
+            Err(EventError::UnknownType)
+        ));
+    }
+
+    // Publishes a WeatherEvent once synced with the engine.
+    struct WeatherStation {
+        weather: CustomEventType,
+    }
+
+    impl Plugin for WeatherStation {
+        fn id(&self) -> i32 {
+            1
+        }
+
+        fn name(&self) -> &str {
+            "weather-station"
+        }
+
+        fn subscriptions(&self) -> &[&str] {
+            &[]
+        }
+
+        fn start(self: Box<Self>, mut ctx: PluginContext) -> Result<(), PluginError> {
+            ctx.publish(&self.weather.event(b"sunny".to_vec()))?;
+            Ok(())
+        }
+    }
+
+    // Reports the first WeatherEvent it receives.
+    struct WeatherWatcher {
+        event_tx: mpsc::Sender<Event>,
+    }
+
+    impl Plugin for WeatherWatcher {
+        fn id(&self) -> i32 {
+            2
+        }
+
+        fn name(&self) -> &str {
+            "weather-watcher"
+        }
+
+        fn subscriptions(&self) -> &[&str] {
+            &["WeatherEvent"]
+        }
+
+        fn start(self: Box<Self>, mut ctx: PluginContext) -> Result<(), PluginError> {
+            let msg = ctx.next_event()?;
+            if msg.event_type == "WeatherEvent" {
+                self.event_tx.send(msg.decode()?).unwrap();
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_custom_events_reach_their_subscribers() {
+        let weather = EventTypeRegistry::global()
+            .register("WeatherEvent")
+            .unwrap();
+        let (event_tx, event_rx) = mpsc::channel();
+        let mut plugins = PluginRegistry::new();
+        plugins
+            .register_plugin(Box::new(WeatherStation { weather }))
+            .unwrap()
+            .register_plugin(Box::new(WeatherWatcher { event_tx }))
+            .unwrap();
+        let engine = EventEngineBuilder::new()
+            .incoming_inproc("messages-custom-events")
+            .outgoing_inproc("events-custom-events")
+            .transport(Transport::InprocOnly)
+            .plugins(plugins)
+            .start()
+            .unwrap();
+
+        let received = event_rx.recv_timeout(Duration::from_secs(10)).unwrap();
+        assert_eq!(
+            received,
+            Event::Custom {
+                type_name: "WeatherEvent",
+                payload: b"sunny".to_vec(),
+            }
+        );
+        engine.shutdown().unwrap();
+    }
+}
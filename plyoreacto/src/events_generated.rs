@@ -1,3 +1,5 @@
+// this line added to keep clippy happy
+#![allow(clippy::all)]
 // automatically generated by the FlatBuffers compiler, do not modify
 
 
@@ -20,15 +22,39 @@ pub mod events {
 #[deprecated(since = "2.0.0", note = "Use associated constants instead. This will no longer be generated in 2021.")]
 pub const ENUM_MIN_EVENT_TYPE: u8 = 0;
 #[deprecated(since = "2.0.0", note = "Use associated constants instead. This will no longer be generated in 2021.")]
-pub const ENUM_MAX_EVENT_TYPE: u8 = 4;
+pub const ENUM_MAX_EVENT_TYPE: u8 = 28;
 #[deprecated(since = "2.0.0", note = "Use associated constants instead. This will no longer be generated in 2021.")]
 #[allow(non_camel_case_types)]
-pub const ENUM_VALUES_EVENT_TYPE: [EventType; 5] = [
+pub const ENUM_VALUES_EVENT_TYPE: [EventType; 29] = [
   EventType::NONE,
   EventType::NewImageEvent,
   EventType::ImageScoredEvent,
   EventType::ImageStoredEvent,
   EventType::ImageDeletedEvent,
+  EventType::PluginTerminateEvent,
+  EventType::PluginFailedEvent,
+  EventType::PluginRestartedEvent,
+  EventType::ImageScoreFailedEvent,
+  EventType::ImageRejectedEvent,
+  EventType::ImageStoreFailedEvent,
+  EventType::ImageDeletedRequestEvent,
+  EventType::MetricsSnapshotEvent,
+  EventType::WebhookDeliveryFailedEvent,
+  EventType::PluginJoinedEvent,
+  EventType::PluginLeftEvent,
+  EventType::EngineHeartbeatEvent,
+  EventType::PluginHeartbeatEvent,
+  EventType::EventsDroppedEvent,
+  EventType::EventRejectedEvent,
+  EventType::CustomEvent,
+  EventType::BackpressureEvent,
+  EventType::BackpressureRelievedEvent,
+  EventType::DeadLetterEvent,
+  EventType::SyncProbeEvent,
+  EventType::EngineStartedEvent,
+  EventType::EngineStoppingEvent,
+  EventType::PluginSkippedEvent,
+  EventType::TickEvent,
 ];
 
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
@@ -41,15 +67,63 @@ impl EventType {
   pub const ImageScoredEvent: Self = Self(2);
   pub const ImageStoredEvent: Self = Self(3);
   pub const ImageDeletedEvent: Self = Self(4);
+  pub const PluginTerminateEvent: Self = Self(5);
+  pub const PluginFailedEvent: Self = Self(6);
+  pub const PluginRestartedEvent: Self = Self(7);
+  pub const ImageScoreFailedEvent: Self = Self(8);
+  pub const ImageRejectedEvent: Self = Self(9);
+  pub const ImageStoreFailedEvent: Self = Self(10);
+  pub const ImageDeletedRequestEvent: Self = Self(11);
+  pub const MetricsSnapshotEvent: Self = Self(12);
+  pub const WebhookDeliveryFailedEvent: Self = Self(13);
+  pub const PluginJoinedEvent: Self = Self(14);
+  pub const PluginLeftEvent: Self = Self(15);
+  pub const EngineHeartbeatEvent: Self = Self(16);
+  pub const PluginHeartbeatEvent: Self = Self(17);
+  pub const EventsDroppedEvent: Self = Self(18);
+  pub const EventRejectedEvent: Self = Self(19);
+  pub const CustomEvent: Self = Self(20);
+  pub const BackpressureEvent: Self = Self(21);
+  pub const BackpressureRelievedEvent: Self = Self(22);
+  pub const DeadLetterEvent: Self = Self(23);
+  pub const SyncProbeEvent: Self = Self(24);
+  pub const EngineStartedEvent: Self = Self(25);
+  pub const EngineStoppingEvent: Self = Self(26);
+  pub const PluginSkippedEvent: Self = Self(27);
+  pub const TickEvent: Self = Self(28);
 
   pub const ENUM_MIN: u8 = 0;
-  pub const ENUM_MAX: u8 = 4;
+  pub const ENUM_MAX: u8 = 28;
   pub const ENUM_VALUES: &'static [Self] = &[
     Self::NONE,
     Self::NewImageEvent,
     Self::ImageScoredEvent,
     Self::ImageStoredEvent,
     Self::ImageDeletedEvent,
+    Self::PluginTerminateEvent,
+    Self::PluginFailedEvent,
+    Self::PluginRestartedEvent,
+    Self::ImageScoreFailedEvent,
+    Self::ImageRejectedEvent,
+    Self::ImageStoreFailedEvent,
+    Self::ImageDeletedRequestEvent,
+    Self::MetricsSnapshotEvent,
+    Self::WebhookDeliveryFailedEvent,
+    Self::PluginJoinedEvent,
+    Self::PluginLeftEvent,
+    Self::EngineHeartbeatEvent,
+    Self::PluginHeartbeatEvent,
+    Self::EventsDroppedEvent,
+    Self::EventRejectedEvent,
+    Self::CustomEvent,
+    Self::BackpressureEvent,
+    Self::BackpressureRelievedEvent,
+    Self::DeadLetterEvent,
+    Self::SyncProbeEvent,
+    Self::EngineStartedEvent,
+    Self::EngineStoppingEvent,
+    Self::PluginSkippedEvent,
+    Self::TickEvent,
   ];
   /// Returns the variant's name or "" if unknown.
   pub fn variant_name(self) -> Option<&'static str> {
@@ -59,6 +133,30 @@ impl EventType {
       Self::ImageScoredEvent => Some("ImageScoredEvent"),
       Self::ImageStoredEvent => Some("ImageStoredEvent"),
       Self::ImageDeletedEvent => Some("ImageDeletedEvent"),
+      Self::PluginTerminateEvent => Some("PluginTerminateEvent"),
+      Self::PluginFailedEvent => Some("PluginFailedEvent"),
+      Self::PluginRestartedEvent => Some("PluginRestartedEvent"),
+      Self::ImageScoreFailedEvent => Some("ImageScoreFailedEvent"),
+      Self::ImageRejectedEvent => Some("ImageRejectedEvent"),
+      Self::ImageStoreFailedEvent => Some("ImageStoreFailedEvent"),
+      Self::ImageDeletedRequestEvent => Some("ImageDeletedRequestEvent"),
+      Self::MetricsSnapshotEvent => Some("MetricsSnapshotEvent"),
+      Self::WebhookDeliveryFailedEvent => Some("WebhookDeliveryFailedEvent"),
+      Self::PluginJoinedEvent => Some("PluginJoinedEvent"),
+      Self::PluginLeftEvent => Some("PluginLeftEvent"),
+      Self::EngineHeartbeatEvent => Some("EngineHeartbeatEvent"),
+      Self::PluginHeartbeatEvent => Some("PluginHeartbeatEvent"),
+      Self::EventsDroppedEvent => Some("EventsDroppedEvent"),
+      Self::EventRejectedEvent => Some("EventRejectedEvent"),
+      Self::CustomEvent => Some("CustomEvent"),
+      Self::BackpressureEvent => Some("BackpressureEvent"),
+      Self::BackpressureRelievedEvent => Some("BackpressureRelievedEvent"),
+      Self::DeadLetterEvent => Some("DeadLetterEvent"),
+      Self::SyncProbeEvent => Some("SyncProbeEvent"),
+      Self::EngineStartedEvent => Some("EngineStartedEvent"),
+      Self::EngineStoppingEvent => Some("EngineStoppingEvent"),
+      Self::PluginSkippedEvent => Some("PluginSkippedEvent"),
+      Self::TickEvent => Some("TickEvent"),
       _ => None,
     }
   }
@@ -137,6 +235,7 @@ impl<'a> NewImageEvent<'a> {
   pub const VT_IMAGE_UUID: flatbuffers::VOffsetT = 4;
   pub const VT_IMAGE_FORMAT: flatbuffers::VOffsetT = 6;
   pub const VT_IMAGE: flatbuffers::VOffsetT = 8;
+  pub const VT_LOCATION: flatbuffers::VOffsetT = 10;
 
   #[inline]
   pub fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
@@ -148,6 +247,7 @@ impl<'a> NewImageEvent<'a> {
     args: &'args NewImageEventArgs<'args>
   ) -> flatbuffers::WIPOffset<NewImageEvent<'bldr>> {
     let mut builder = NewImageEventBuilder::new(_fbb);
+    if let Some(x) = args.location { builder.add_location(x); }
     if let Some(x) = args.image { builder.add_image(x); }
     if let Some(x) = args.image_format { builder.add_image_format(x); }
     if let Some(x) = args.image_uuid { builder.add_image_uuid(x); }
@@ -167,6 +267,10 @@ impl<'a> NewImageEvent<'a> {
   pub fn image(&self) -> Option<&'a [u8]> {
     self._tab.get::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'a, u8>>>(NewImageEvent::VT_IMAGE, None).map(|v| v.safe_slice())
   }
+  #[inline]
+  pub fn location(&self) -> Option<&'a str> {
+    self._tab.get::<flatbuffers::ForwardsUOffset<&str>>(NewImageEvent::VT_LOCATION, None)
+  }
 }
 
 impl flatbuffers::Verifiable for NewImageEvent<'_> {
@@ -179,6 +283,7 @@ impl flatbuffers::Verifiable for NewImageEvent<'_> {
      .visit_field::<flatbuffers::ForwardsUOffset<&str>>("image_uuid", Self::VT_IMAGE_UUID, false)?
      .visit_field::<flatbuffers::ForwardsUOffset<&str>>("image_format", Self::VT_IMAGE_FORMAT, false)?
      .visit_field::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'_, u8>>>("image", Self::VT_IMAGE, false)?
+     .visit_field::<flatbuffers::ForwardsUOffset<&str>>("location", Self::VT_LOCATION, false)?
      .finish();
     Ok(())
   }
@@ -187,6 +292,7 @@ pub struct NewImageEventArgs<'a> {
     pub image_uuid: Option<flatbuffers::WIPOffset<&'a str>>,
     pub image_format: Option<flatbuffers::WIPOffset<&'a str>>,
     pub image: Option<flatbuffers::WIPOffset<flatbuffers::Vector<'a, u8>>>,
+    pub location: Option<flatbuffers::WIPOffset<&'a str>>,
 }
 impl<'a> Default for NewImageEventArgs<'a> {
   #[inline]
@@ -195,6 +301,7 @@ impl<'a> Default for NewImageEventArgs<'a> {
       image_uuid: None,
       image_format: None,
       image: None,
+      location: None,
     }
   }
 }
@@ -217,6 +324,10 @@ impl<'a: 'b, 'b> NewImageEventBuilder<'a, 'b> {
     self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(NewImageEvent::VT_IMAGE, image);
   }
   #[inline]
+  pub fn add_location(&mut self, location: flatbuffers::WIPOffset<&'b  str>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(NewImageEvent::VT_LOCATION, location);
+  }
+  #[inline]
   pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a>) -> NewImageEventBuilder<'a, 'b> {
     let start = _fbb.start_table();
     NewImageEventBuilder {
@@ -237,6 +348,7 @@ impl core::fmt::Debug for NewImageEvent<'_> {
       ds.field("image_uuid", &self.image_uuid());
       ds.field("image_format", &self.image_format());
       ds.field("image", &self.image());
+      ds.field("location", &self.location());
       ds.finish()
   }
 }
@@ -456,34 +568,36 @@ impl core::fmt::Debug for ImageScoredEvent<'_> {
       ds.finish()
   }
 }
-pub enum ImageStoredEventOffset {}
+pub enum ImageScoreFailedEventOffset {}
 #[derive(Copy, Clone, PartialEq)]
 
-pub struct ImageStoredEvent<'a> {
+pub struct ImageScoreFailedEvent<'a> {
   pub _tab: flatbuffers::Table<'a>,
 }
 
-impl<'a> flatbuffers::Follow<'a> for ImageStoredEvent<'a> {
-  type Inner = ImageStoredEvent<'a>;
+impl<'a> flatbuffers::Follow<'a> for ImageScoreFailedEvent<'a> {
+  type Inner = ImageScoreFailedEvent<'a>;
   #[inline]
   fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
     Self { _tab: flatbuffers::Table { buf, loc } }
   }
 }
 
-impl<'a> ImageStoredEvent<'a> {
+impl<'a> ImageScoreFailedEvent<'a> {
   pub const VT_IMAGE_UUID: flatbuffers::VOffsetT = 4;
+  pub const VT_ERROR: flatbuffers::VOffsetT = 6;
 
   #[inline]
   pub fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
-    ImageStoredEvent { _tab: table }
+    ImageScoreFailedEvent { _tab: table }
   }
   #[allow(unused_mut)]
   pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr>(
     _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr>,
-    args: &'args ImageStoredEventArgs<'args>
-  ) -> flatbuffers::WIPOffset<ImageStoredEvent<'bldr>> {
-    let mut builder = ImageStoredEventBuilder::new(_fbb);
+    args: &'args ImageScoreFailedEventArgs<'args>
+  ) -> flatbuffers::WIPOffset<ImageScoreFailedEvent<'bldr>> {
+    let mut builder = ImageScoreFailedEventBuilder::new(_fbb);
+    if let Some(x) = args.error { builder.add_error(x); }
     if let Some(x) = args.image_uuid { builder.add_image_uuid(x); }
     builder.finish()
   }
@@ -491,11 +605,15 @@ impl<'a> ImageStoredEvent<'a> {
 
   #[inline]
   pub fn image_uuid(&self) -> Option<&'a str> {
-    self._tab.get::<flatbuffers::ForwardsUOffset<&str>>(ImageStoredEvent::VT_IMAGE_UUID, None)
+    self._tab.get::<flatbuffers::ForwardsUOffset<&str>>(ImageScoreFailedEvent::VT_IMAGE_UUID, None)
+  }
+  #[inline]
+  pub fn error(&self) -> Option<&'a str> {
+    self._tab.get::<flatbuffers::ForwardsUOffset<&str>>(ImageScoreFailedEvent::VT_ERROR, None)
   }
 }
 
-impl flatbuffers::Verifiable for ImageStoredEvent<'_> {
+impl flatbuffers::Verifiable for ImageScoreFailedEvent<'_> {
   #[inline]
   fn run_verifier(
     v: &mut flatbuffers::Verifier, pos: usize
@@ -503,81 +621,93 @@ impl flatbuffers::Verifiable for ImageStoredEvent<'_> {
     use self::flatbuffers::Verifiable;
     v.visit_table(pos)?
      .visit_field::<flatbuffers::ForwardsUOffset<&str>>("image_uuid", Self::VT_IMAGE_UUID, false)?
+     .visit_field::<flatbuffers::ForwardsUOffset<&str>>("error", Self::VT_ERROR, false)?
      .finish();
     Ok(())
   }
 }
-pub struct ImageStoredEventArgs<'a> {
+pub struct ImageScoreFailedEventArgs<'a> {
     pub image_uuid: Option<flatbuffers::WIPOffset<&'a str>>,
+    pub error: Option<flatbuffers::WIPOffset<&'a str>>,
 }
-impl<'a> Default for ImageStoredEventArgs<'a> {
+impl<'a> Default for ImageScoreFailedEventArgs<'a> {
   #[inline]
   fn default() -> Self {
-    ImageStoredEventArgs {
+    ImageScoreFailedEventArgs {
       image_uuid: None,
+      error: None,
     }
   }
 }
 
-pub struct ImageStoredEventBuilder<'a: 'b, 'b> {
+pub struct ImageScoreFailedEventBuilder<'a: 'b, 'b> {
   fbb_: &'b mut flatbuffers::FlatBufferBuilder<'a>,
   start_: flatbuffers::WIPOffset<flatbuffers::TableUnfinishedWIPOffset>,
 }
-impl<'a: 'b, 'b> ImageStoredEventBuilder<'a, 'b> {
+impl<'a: 'b, 'b> ImageScoreFailedEventBuilder<'a, 'b> {
   #[inline]
   pub fn add_image_uuid(&mut self, image_uuid: flatbuffers::WIPOffset<&'b  str>) {
-    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(ImageStoredEvent::VT_IMAGE_UUID, image_uuid);
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(ImageScoreFailedEvent::VT_IMAGE_UUID, image_uuid);
   }
   #[inline]
-  pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a>) -> ImageStoredEventBuilder<'a, 'b> {
+  pub fn add_error(&mut self, error: flatbuffers::WIPOffset<&'b  str>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(ImageScoreFailedEvent::VT_ERROR, error);
+  }
+  #[inline]
+  pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a>) -> ImageScoreFailedEventBuilder<'a, 'b> {
     let start = _fbb.start_table();
-    ImageStoredEventBuilder {
+    ImageScoreFailedEventBuilder {
       fbb_: _fbb,
       start_: start,
     }
   }
   #[inline]
-  pub fn finish(self) -> flatbuffers::WIPOffset<ImageStoredEvent<'a>> {
+  pub fn finish(self) -> flatbuffers::WIPOffset<ImageScoreFailedEvent<'a>> {
     let o = self.fbb_.end_table(self.start_);
     flatbuffers::WIPOffset::new(o.value())
   }
 }
 
-impl core::fmt::Debug for ImageStoredEvent<'_> {
+impl core::fmt::Debug for ImageScoreFailedEvent<'_> {
   fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
-    let mut ds = f.debug_struct("ImageStoredEvent");
+    let mut ds = f.debug_struct("ImageScoreFailedEvent");
       ds.field("image_uuid", &self.image_uuid());
+      ds.field("error", &self.error());
       ds.finish()
   }
 }
-pub enum ImageDeletedEventOffset {}
+pub enum ImageRejectedEventOffset {}
 #[derive(Copy, Clone, PartialEq)]
 
-pub struct ImageDeletedEvent<'a> {
+pub struct ImageRejectedEvent<'a> {
   pub _tab: flatbuffers::Table<'a>,
 }
 
-impl<'a> flatbuffers::Follow<'a> for ImageDeletedEvent<'a> {
-  type Inner = ImageDeletedEvent<'a>;
+impl<'a> flatbuffers::Follow<'a> for ImageRejectedEvent<'a> {
+  type Inner = ImageRejectedEvent<'a>;
   #[inline]
   fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
     Self { _tab: flatbuffers::Table { buf, loc } }
   }
 }
 
-impl<'a> ImageDeletedEvent<'a> {
+impl<'a> ImageRejectedEvent<'a> {
   pub const VT_IMAGE_UUID: flatbuffers::VOffsetT = 4;
+  pub const VT_TOP_LABEL: flatbuffers::VOffsetT = 6;
+  pub const VT_PROBABILITY: flatbuffers::VOffsetT = 8;
 
   #[inline]
   pub fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
-    ImageDeletedEvent { _tab: table }
+    ImageRejectedEvent { _tab: table }
   }
   #[allow(unused_mut)]
   pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr>(
     _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr>,
-    args: &'args ImageDeletedEventArgs<'args>
-  ) -> flatbuffers::WIPOffset<ImageDeletedEvent<'bldr>> {
-    let mut builder = ImageDeletedEventBuilder::new(_fbb);
+    args: &'args ImageRejectedEventArgs<'args>
+  ) -> flatbuffers::WIPOffset<ImageRejectedEvent<'bldr>> {
+    let mut builder = ImageRejectedEventBuilder::new(_fbb);
+    builder.add_probability(args.probability);
+    if let Some(x) = args.top_label { builder.add_top_label(x); }
     if let Some(x) = args.image_uuid { builder.add_image_uuid(x); }
     builder.finish()
   }
@@ -585,11 +715,19 @@ impl<'a> ImageDeletedEvent<'a> {
 
   #[inline]
   pub fn image_uuid(&self) -> Option<&'a str> {
-    self._tab.get::<flatbuffers::ForwardsUOffset<&str>>(ImageDeletedEvent::VT_IMAGE_UUID, None)
+    self._tab.get::<flatbuffers::ForwardsUOffset<&str>>(ImageRejectedEvent::VT_IMAGE_UUID, None)
+  }
+  #[inline]
+  pub fn top_label(&self) -> Option<&'a str> {
+    self._tab.get::<flatbuffers::ForwardsUOffset<&str>>(ImageRejectedEvent::VT_TOP_LABEL, None)
+  }
+  #[inline]
+  pub fn probability(&self) -> f32 {
+    self._tab.get::<f32>(ImageRejectedEvent::VT_PROBABILITY, Some(0.0)).unwrap()
   }
 }
 
-impl flatbuffers::Verifiable for ImageDeletedEvent<'_> {
+impl flatbuffers::Verifiable for ImageRejectedEvent<'_> {
   #[inline]
   fn run_verifier(
     v: &mut flatbuffers::Verifier, pos: usize
@@ -597,226 +735,3411 @@ impl flatbuffers::Verifiable for ImageDeletedEvent<'_> {
     use self::flatbuffers::Verifiable;
     v.visit_table(pos)?
      .visit_field::<flatbuffers::ForwardsUOffset<&str>>("image_uuid", Self::VT_IMAGE_UUID, false)?
+     .visit_field::<flatbuffers::ForwardsUOffset<&str>>("top_label", Self::VT_TOP_LABEL, false)?
+     .visit_field::<f32>("probability", Self::VT_PROBABILITY, false)?
      .finish();
     Ok(())
   }
 }
-pub struct ImageDeletedEventArgs<'a> {
+pub struct ImageRejectedEventArgs<'a> {
     pub image_uuid: Option<flatbuffers::WIPOffset<&'a str>>,
+    pub top_label: Option<flatbuffers::WIPOffset<&'a str>>,
+    pub probability: f32,
 }
-impl<'a> Default for ImageDeletedEventArgs<'a> {
+impl<'a> Default for ImageRejectedEventArgs<'a> {
   #[inline]
   fn default() -> Self {
-    ImageDeletedEventArgs {
+    ImageRejectedEventArgs {
       image_uuid: None,
+      top_label: None,
+      probability: 0.0,
     }
   }
 }
 
-pub struct ImageDeletedEventBuilder<'a: 'b, 'b> {
+pub struct ImageRejectedEventBuilder<'a: 'b, 'b> {
   fbb_: &'b mut flatbuffers::FlatBufferBuilder<'a>,
   start_: flatbuffers::WIPOffset<flatbuffers::TableUnfinishedWIPOffset>,
 }
-impl<'a: 'b, 'b> ImageDeletedEventBuilder<'a, 'b> {
+impl<'a: 'b, 'b> ImageRejectedEventBuilder<'a, 'b> {
   #[inline]
   pub fn add_image_uuid(&mut self, image_uuid: flatbuffers::WIPOffset<&'b  str>) {
-    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(ImageDeletedEvent::VT_IMAGE_UUID, image_uuid);
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(ImageRejectedEvent::VT_IMAGE_UUID, image_uuid);
   }
   #[inline]
-  pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a>) -> ImageDeletedEventBuilder<'a, 'b> {
+  pub fn add_top_label(&mut self, top_label: flatbuffers::WIPOffset<&'b  str>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(ImageRejectedEvent::VT_TOP_LABEL, top_label);
+  }
+  #[inline]
+  pub fn add_probability(&mut self, probability: f32) {
+    self.fbb_.push_slot::<f32>(ImageRejectedEvent::VT_PROBABILITY, probability, 0.0);
+  }
+  #[inline]
+  pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a>) -> ImageRejectedEventBuilder<'a, 'b> {
     let start = _fbb.start_table();
-    ImageDeletedEventBuilder {
+    ImageRejectedEventBuilder {
       fbb_: _fbb,
       start_: start,
     }
   }
   #[inline]
-  pub fn finish(self) -> flatbuffers::WIPOffset<ImageDeletedEvent<'a>> {
+  pub fn finish(self) -> flatbuffers::WIPOffset<ImageRejectedEvent<'a>> {
     let o = self.fbb_.end_table(self.start_);
     flatbuffers::WIPOffset::new(o.value())
   }
 }
 
-impl core::fmt::Debug for ImageDeletedEvent<'_> {
+impl core::fmt::Debug for ImageRejectedEvent<'_> {
   fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
-    let mut ds = f.debug_struct("ImageDeletedEvent");
+    let mut ds = f.debug_struct("ImageRejectedEvent");
       ds.field("image_uuid", &self.image_uuid());
+      ds.field("top_label", &self.top_label());
+      ds.field("probability", &self.probability());
       ds.finish()
   }
 }
-pub enum EventOffset {}
+pub enum ImageStoredEventOffset {}
 #[derive(Copy, Clone, PartialEq)]
 
-pub struct Event<'a> {
+pub struct ImageStoredEvent<'a> {
   pub _tab: flatbuffers::Table<'a>,
 }
 
-impl<'a> flatbuffers::Follow<'a> for Event<'a> {
-  type Inner = Event<'a>;
+impl<'a> flatbuffers::Follow<'a> for ImageStoredEvent<'a> {
+  type Inner = ImageStoredEvent<'a>;
   #[inline]
   fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
     Self { _tab: flatbuffers::Table { buf, loc } }
   }
 }
 
-impl<'a> Event<'a> {
-  pub const VT_EVENT_TYPE: flatbuffers::VOffsetT = 4;
-  pub const VT_EVENT: flatbuffers::VOffsetT = 6;
+impl<'a> ImageStoredEvent<'a> {
+  pub const VT_IMAGE_UUID: flatbuffers::VOffsetT = 4;
+  pub const VT_PATH: flatbuffers::VOffsetT = 6;
+  pub const VT_DEDUPLICATED: flatbuffers::VOffsetT = 8;
 
   #[inline]
   pub fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
-    Event { _tab: table }
+    ImageStoredEvent { _tab: table }
   }
   #[allow(unused_mut)]
   pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr>(
     _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr>,
-    args: &'args EventArgs
-  ) -> flatbuffers::WIPOffset<Event<'bldr>> {
-    let mut builder = EventBuilder::new(_fbb);
-    if let Some(x) = args.event { builder.add_event(x); }
-    builder.add_event_type(args.event_type);
+    args: &'args ImageStoredEventArgs<'args>
+  ) -> flatbuffers::WIPOffset<ImageStoredEvent<'bldr>> {
+    let mut builder = ImageStoredEventBuilder::new(_fbb);
+    if let Some(x) = args.path { builder.add_path(x); }
+    if let Some(x) = args.image_uuid { builder.add_image_uuid(x); }
+    builder.add_deduplicated(args.deduplicated);
     builder.finish()
   }
 
 
   #[inline]
-  pub fn event_type(&self) -> EventType {
-    self._tab.get::<EventType>(Event::VT_EVENT_TYPE, Some(EventType::NONE)).unwrap()
+  pub fn image_uuid(&self) -> Option<&'a str> {
+    self._tab.get::<flatbuffers::ForwardsUOffset<&str>>(ImageStoredEvent::VT_IMAGE_UUID, None)
   }
   #[inline]
-  pub fn event(&self) -> Option<flatbuffers::Table<'a>> {
-    self._tab.get::<flatbuffers::ForwardsUOffset<flatbuffers::Table<'a>>>(Event::VT_EVENT, None)
+  pub fn path(&self) -> Option<&'a str> {
+    self._tab.get::<flatbuffers::ForwardsUOffset<&str>>(ImageStoredEvent::VT_PATH, None)
   }
   #[inline]
-  #[allow(non_snake_case)]
-  pub fn event_as_new_image_event(&self) -> Option<NewImageEvent<'a>> {
-    if self.event_type() == EventType::NewImageEvent {
-      self.event().map(NewImageEvent::init_from_table)
-    } else {
-      None
-    }
+  pub fn deduplicated(&self) -> bool {
+    self._tab.get::<bool>(ImageStoredEvent::VT_DEDUPLICATED, Some(false)).unwrap()
   }
+}
 
+impl flatbuffers::Verifiable for ImageStoredEvent<'_> {
   #[inline]
-  #[allow(non_snake_case)]
-  pub fn event_as_image_scored_event(&self) -> Option<ImageScoredEvent<'a>> {
-    if self.event_type() == EventType::ImageScoredEvent {
-      self.event().map(ImageScoredEvent::init_from_table)
-    } else {
-      None
+  fn run_verifier(
+    v: &mut flatbuffers::Verifier, pos: usize
+  ) -> Result<(), flatbuffers::InvalidFlatbuffer> {
+    use self::flatbuffers::Verifiable;
+    v.visit_table(pos)?
+     .visit_field::<flatbuffers::ForwardsUOffset<&str>>("image_uuid", Self::VT_IMAGE_UUID, false)?
+     .visit_field::<flatbuffers::ForwardsUOffset<&str>>("path", Self::VT_PATH, false)?
+     .visit_field::<bool>("deduplicated", Self::VT_DEDUPLICATED, false)?
+     .finish();
+    Ok(())
+  }
+}
+pub struct ImageStoredEventArgs<'a> {
+    pub image_uuid: Option<flatbuffers::WIPOffset<&'a str>>,
+    pub path: Option<flatbuffers::WIPOffset<&'a str>>,
+    pub deduplicated: bool,
+}
+impl<'a> Default for ImageStoredEventArgs<'a> {
+  #[inline]
+  fn default() -> Self {
+    ImageStoredEventArgs {
+      image_uuid: None,
+      path: None,
+      deduplicated: false,
     }
   }
+}
 
+pub struct ImageStoredEventBuilder<'a: 'b, 'b> {
+  fbb_: &'b mut flatbuffers::FlatBufferBuilder<'a>,
+  start_: flatbuffers::WIPOffset<flatbuffers::TableUnfinishedWIPOffset>,
+}
+impl<'a: 'b, 'b> ImageStoredEventBuilder<'a, 'b> {
   #[inline]
-  #[allow(non_snake_case)]
-  pub fn event_as_image_stored_event(&self) -> Option<ImageStoredEvent<'a>> {
-    if self.event_type() == EventType::ImageStoredEvent {
-      self.event().map(ImageStoredEvent::init_from_table)
-    } else {
-      None
+  pub fn add_image_uuid(&mut self, image_uuid: flatbuffers::WIPOffset<&'b  str>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(ImageStoredEvent::VT_IMAGE_UUID, image_uuid);
+  }
+  #[inline]
+  pub fn add_path(&mut self, path: flatbuffers::WIPOffset<&'b  str>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(ImageStoredEvent::VT_PATH, path);
+  }
+  #[inline]
+  pub fn add_deduplicated(&mut self, deduplicated: bool) {
+    self.fbb_.push_slot::<bool>(ImageStoredEvent::VT_DEDUPLICATED, deduplicated, false);
+  }
+  #[inline]
+  pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a>) -> ImageStoredEventBuilder<'a, 'b> {
+    let start = _fbb.start_table();
+    ImageStoredEventBuilder {
+      fbb_: _fbb,
+      start_: start,
     }
   }
+  #[inline]
+  pub fn finish(self) -> flatbuffers::WIPOffset<ImageStoredEvent<'a>> {
+    let o = self.fbb_.end_table(self.start_);
+    flatbuffers::WIPOffset::new(o.value())
+  }
+}
+
+impl core::fmt::Debug for ImageStoredEvent<'_> {
+  fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+    let mut ds = f.debug_struct("ImageStoredEvent");
+      ds.field("image_uuid", &self.image_uuid());
+      ds.field("path", &self.path());
+      ds.field("deduplicated", &self.deduplicated());
+      ds.finish()
+  }
+}
+pub enum ImageStoreFailedEventOffset {}
+#[derive(Copy, Clone, PartialEq)]
+
+pub struct ImageStoreFailedEvent<'a> {
+  pub _tab: flatbuffers::Table<'a>,
+}
 
+impl<'a> flatbuffers::Follow<'a> for ImageStoreFailedEvent<'a> {
+  type Inner = ImageStoreFailedEvent<'a>;
   #[inline]
-  #[allow(non_snake_case)]
-  pub fn event_as_image_deleted_event(&self) -> Option<ImageDeletedEvent<'a>> {
-    if self.event_type() == EventType::ImageDeletedEvent {
-      self.event().map(ImageDeletedEvent::init_from_table)
-    } else {
-      None
-    }
+  fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+    Self { _tab: flatbuffers::Table { buf, loc } }
+  }
+}
+
+impl<'a> ImageStoreFailedEvent<'a> {
+  pub const VT_IMAGE_UUID: flatbuffers::VOffsetT = 4;
+  pub const VT_ERROR: flatbuffers::VOffsetT = 6;
+
+  #[inline]
+  pub fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
+    ImageStoreFailedEvent { _tab: table }
+  }
+  #[allow(unused_mut)]
+  pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr>(
+    _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr>,
+    args: &'args ImageStoreFailedEventArgs<'args>
+  ) -> flatbuffers::WIPOffset<ImageStoreFailedEvent<'bldr>> {
+    let mut builder = ImageStoreFailedEventBuilder::new(_fbb);
+    if let Some(x) = args.error { builder.add_error(x); }
+    if let Some(x) = args.image_uuid { builder.add_image_uuid(x); }
+    builder.finish()
   }
 
+
+  #[inline]
+  pub fn image_uuid(&self) -> Option<&'a str> {
+    self._tab.get::<flatbuffers::ForwardsUOffset<&str>>(ImageStoreFailedEvent::VT_IMAGE_UUID, None)
+  }
+  #[inline]
+  pub fn error(&self) -> Option<&'a str> {
+    self._tab.get::<flatbuffers::ForwardsUOffset<&str>>(ImageStoreFailedEvent::VT_ERROR, None)
+  }
 }
 
-impl flatbuffers::Verifiable for Event<'_> {
+impl flatbuffers::Verifiable for ImageStoreFailedEvent<'_> {
   #[inline]
   fn run_verifier(
     v: &mut flatbuffers::Verifier, pos: usize
   ) -> Result<(), flatbuffers::InvalidFlatbuffer> {
     use self::flatbuffers::Verifiable;
     v.visit_table(pos)?
-     .visit_union::<EventType, _>("event_type", Self::VT_EVENT_TYPE, "event", Self::VT_EVENT, false, |key, v, pos| {
-        match key {
-          EventType::NewImageEvent => v.verify_union_variant::<flatbuffers::ForwardsUOffset<NewImageEvent>>("EventType::NewImageEvent", pos),
-          EventType::ImageScoredEvent => v.verify_union_variant::<flatbuffers::ForwardsUOffset<ImageScoredEvent>>("EventType::ImageScoredEvent", pos),
-          EventType::ImageStoredEvent => v.verify_union_variant::<flatbuffers::ForwardsUOffset<ImageStoredEvent>>("EventType::ImageStoredEvent", pos),
-          EventType::ImageDeletedEvent => v.verify_union_variant::<flatbuffers::ForwardsUOffset<ImageDeletedEvent>>("EventType::ImageDeletedEvent", pos),
-          _ => Ok(()),
-        }
-     })?
+     .visit_field::<flatbuffers::ForwardsUOffset<&str>>("image_uuid", Self::VT_IMAGE_UUID, false)?
+     .visit_field::<flatbuffers::ForwardsUOffset<&str>>("error", Self::VT_ERROR, false)?
      .finish();
     Ok(())
   }
 }
-pub struct EventArgs {
-    pub event_type: EventType,
-    pub event: Option<flatbuffers::WIPOffset<flatbuffers::UnionWIPOffset>>,
+pub struct ImageStoreFailedEventArgs<'a> {
+    pub image_uuid: Option<flatbuffers::WIPOffset<&'a str>>,
+    pub error: Option<flatbuffers::WIPOffset<&'a str>>,
 }
-impl<'a> Default for EventArgs {
+impl<'a> Default for ImageStoreFailedEventArgs<'a> {
   #[inline]
   fn default() -> Self {
-    EventArgs {
-      event_type: EventType::NONE,
-      event: None,
+    ImageStoreFailedEventArgs {
+      image_uuid: None,
+      error: None,
     }
   }
 }
 
-pub struct EventBuilder<'a: 'b, 'b> {
+pub struct ImageStoreFailedEventBuilder<'a: 'b, 'b> {
   fbb_: &'b mut flatbuffers::FlatBufferBuilder<'a>,
   start_: flatbuffers::WIPOffset<flatbuffers::TableUnfinishedWIPOffset>,
 }
-impl<'a: 'b, 'b> EventBuilder<'a, 'b> {
+impl<'a: 'b, 'b> ImageStoreFailedEventBuilder<'a, 'b> {
   #[inline]
-  pub fn add_event_type(&mut self, event_type: EventType) {
-    self.fbb_.push_slot::<EventType>(Event::VT_EVENT_TYPE, event_type, EventType::NONE);
+  pub fn add_image_uuid(&mut self, image_uuid: flatbuffers::WIPOffset<&'b  str>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(ImageStoreFailedEvent::VT_IMAGE_UUID, image_uuid);
   }
   #[inline]
-  pub fn add_event(&mut self, event: flatbuffers::WIPOffset<flatbuffers::UnionWIPOffset>) {
-    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(Event::VT_EVENT, event);
+  pub fn add_error(&mut self, error: flatbuffers::WIPOffset<&'b  str>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(ImageStoreFailedEvent::VT_ERROR, error);
   }
   #[inline]
-  pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a>) -> EventBuilder<'a, 'b> {
+  pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a>) -> ImageStoreFailedEventBuilder<'a, 'b> {
     let start = _fbb.start_table();
-    EventBuilder {
+    ImageStoreFailedEventBuilder {
       fbb_: _fbb,
       start_: start,
     }
   }
   #[inline]
-  pub fn finish(self) -> flatbuffers::WIPOffset<Event<'a>> {
+  pub fn finish(self) -> flatbuffers::WIPOffset<ImageStoreFailedEvent<'a>> {
     let o = self.fbb_.end_table(self.start_);
     flatbuffers::WIPOffset::new(o.value())
   }
 }
 
-impl core::fmt::Debug for Event<'_> {
+impl core::fmt::Debug for ImageStoreFailedEvent<'_> {
   fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
-    let mut ds = f.debug_struct("Event");
-      ds.field("event_type", &self.event_type());
-      match self.event_type() {
-        EventType::NewImageEvent => {
-          if let Some(x) = self.event_as_new_image_event() {
-            ds.field("event", &x)
-          } else {
-            ds.field("event", &"InvalidFlatbuffer: Union discriminant does not match value.")
-          }
-        },
-        EventType::ImageScoredEvent => {
-          if let Some(x) = self.event_as_image_scored_event() {
-            ds.field("event", &x)
-          } else {
-            ds.field("event", &"InvalidFlatbuffer: Union discriminant does not match value.")
-          }
-        },
-        EventType::ImageStoredEvent => {
-          if let Some(x) = self.event_as_image_stored_event() {
-            ds.field("event", &x)
-          } else {
-            ds.field("event", &"InvalidFlatbuffer: Union discriminant does not match value.")
-          }
-        },
+    let mut ds = f.debug_struct("ImageStoreFailedEvent");
+      ds.field("image_uuid", &self.image_uuid());
+      ds.field("error", &self.error());
+      ds.finish()
+  }
+}
+pub enum ImageDeletedEventOffset {}
+#[derive(Copy, Clone, PartialEq)]
+
+pub struct ImageDeletedEvent<'a> {
+  pub _tab: flatbuffers::Table<'a>,
+}
+
+impl<'a> flatbuffers::Follow<'a> for ImageDeletedEvent<'a> {
+  type Inner = ImageDeletedEvent<'a>;
+  #[inline]
+  fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+    Self { _tab: flatbuffers::Table { buf, loc } }
+  }
+}
+
+impl<'a> ImageDeletedEvent<'a> {
+  pub const VT_IMAGE_UUID: flatbuffers::VOffsetT = 4;
+  pub const VT_EXISTED: flatbuffers::VOffsetT = 6;
+
+  #[inline]
+  pub fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
+    ImageDeletedEvent { _tab: table }
+  }
+  #[allow(unused_mut)]
+  pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr>(
+    _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr>,
+    args: &'args ImageDeletedEventArgs<'args>
+  ) -> flatbuffers::WIPOffset<ImageDeletedEvent<'bldr>> {
+    let mut builder = ImageDeletedEventBuilder::new(_fbb);
+    if let Some(x) = args.image_uuid { builder.add_image_uuid(x); }
+    builder.add_existed(args.existed);
+    builder.finish()
+  }
+
+
+  #[inline]
+  pub fn image_uuid(&self) -> Option<&'a str> {
+    self._tab.get::<flatbuffers::ForwardsUOffset<&str>>(ImageDeletedEvent::VT_IMAGE_UUID, None)
+  }
+  #[inline]
+  pub fn existed(&self) -> bool {
+    self._tab.get::<bool>(ImageDeletedEvent::VT_EXISTED, Some(false)).unwrap()
+  }
+}
+
+impl flatbuffers::Verifiable for ImageDeletedEvent<'_> {
+  #[inline]
+  fn run_verifier(
+    v: &mut flatbuffers::Verifier, pos: usize
+  ) -> Result<(), flatbuffers::InvalidFlatbuffer> {
+    use self::flatbuffers::Verifiable;
+    v.visit_table(pos)?
+     .visit_field::<flatbuffers::ForwardsUOffset<&str>>("image_uuid", Self::VT_IMAGE_UUID, false)?
+     .visit_field::<bool>("existed", Self::VT_EXISTED, false)?
+     .finish();
+    Ok(())
+  }
+}
+pub struct ImageDeletedEventArgs<'a> {
+    pub image_uuid: Option<flatbuffers::WIPOffset<&'a str>>,
+    pub existed: bool,
+}
+impl<'a> Default for ImageDeletedEventArgs<'a> {
+  #[inline]
+  fn default() -> Self {
+    ImageDeletedEventArgs {
+      image_uuid: None,
+      existed: false,
+    }
+  }
+}
+
+pub struct ImageDeletedEventBuilder<'a: 'b, 'b> {
+  fbb_: &'b mut flatbuffers::FlatBufferBuilder<'a>,
+  start_: flatbuffers::WIPOffset<flatbuffers::TableUnfinishedWIPOffset>,
+}
+impl<'a: 'b, 'b> ImageDeletedEventBuilder<'a, 'b> {
+  #[inline]
+  pub fn add_image_uuid(&mut self, image_uuid: flatbuffers::WIPOffset<&'b  str>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(ImageDeletedEvent::VT_IMAGE_UUID, image_uuid);
+  }
+  #[inline]
+  pub fn add_existed(&mut self, existed: bool) {
+    self.fbb_.push_slot::<bool>(ImageDeletedEvent::VT_EXISTED, existed, false);
+  }
+  #[inline]
+  pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a>) -> ImageDeletedEventBuilder<'a, 'b> {
+    let start = _fbb.start_table();
+    ImageDeletedEventBuilder {
+      fbb_: _fbb,
+      start_: start,
+    }
+  }
+  #[inline]
+  pub fn finish(self) -> flatbuffers::WIPOffset<ImageDeletedEvent<'a>> {
+    let o = self.fbb_.end_table(self.start_);
+    flatbuffers::WIPOffset::new(o.value())
+  }
+}
+
+impl core::fmt::Debug for ImageDeletedEvent<'_> {
+  fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+    let mut ds = f.debug_struct("ImageDeletedEvent");
+      ds.field("image_uuid", &self.image_uuid());
+      ds.field("existed", &self.existed());
+      ds.finish()
+  }
+}
+pub enum ImageDeletedRequestEventOffset {}
+#[derive(Copy, Clone, PartialEq)]
+
+pub struct ImageDeletedRequestEvent<'a> {
+  pub _tab: flatbuffers::Table<'a>,
+}
+
+impl<'a> flatbuffers::Follow<'a> for ImageDeletedRequestEvent<'a> {
+  type Inner = ImageDeletedRequestEvent<'a>;
+  #[inline]
+  fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+    Self { _tab: flatbuffers::Table { buf, loc } }
+  }
+}
+
+impl<'a> ImageDeletedRequestEvent<'a> {
+  pub const VT_IMAGE_UUID: flatbuffers::VOffsetT = 4;
+
+  #[inline]
+  pub fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
+    ImageDeletedRequestEvent { _tab: table }
+  }
+  #[allow(unused_mut)]
+  pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr>(
+    _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr>,
+    args: &'args ImageDeletedRequestEventArgs<'args>
+  ) -> flatbuffers::WIPOffset<ImageDeletedRequestEvent<'bldr>> {
+    let mut builder = ImageDeletedRequestEventBuilder::new(_fbb);
+    if let Some(x) = args.image_uuid { builder.add_image_uuid(x); }
+    builder.finish()
+  }
+
+
+  #[inline]
+  pub fn image_uuid(&self) -> Option<&'a str> {
+    self._tab.get::<flatbuffers::ForwardsUOffset<&str>>(ImageDeletedRequestEvent::VT_IMAGE_UUID, None)
+  }
+}
+
+impl flatbuffers::Verifiable for ImageDeletedRequestEvent<'_> {
+  #[inline]
+  fn run_verifier(
+    v: &mut flatbuffers::Verifier, pos: usize
+  ) -> Result<(), flatbuffers::InvalidFlatbuffer> {
+    use self::flatbuffers::Verifiable;
+    v.visit_table(pos)?
+     .visit_field::<flatbuffers::ForwardsUOffset<&str>>("image_uuid", Self::VT_IMAGE_UUID, false)?
+     .finish();
+    Ok(())
+  }
+}
+pub struct ImageDeletedRequestEventArgs<'a> {
+    pub image_uuid: Option<flatbuffers::WIPOffset<&'a str>>,
+}
+impl<'a> Default for ImageDeletedRequestEventArgs<'a> {
+  #[inline]
+  fn default() -> Self {
+    ImageDeletedRequestEventArgs {
+      image_uuid: None,
+    }
+  }
+}
+
+pub struct ImageDeletedRequestEventBuilder<'a: 'b, 'b> {
+  fbb_: &'b mut flatbuffers::FlatBufferBuilder<'a>,
+  start_: flatbuffers::WIPOffset<flatbuffers::TableUnfinishedWIPOffset>,
+}
+impl<'a: 'b, 'b> ImageDeletedRequestEventBuilder<'a, 'b> {
+  #[inline]
+  pub fn add_image_uuid(&mut self, image_uuid: flatbuffers::WIPOffset<&'b  str>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(ImageDeletedRequestEvent::VT_IMAGE_UUID, image_uuid);
+  }
+  #[inline]
+  pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a>) -> ImageDeletedRequestEventBuilder<'a, 'b> {
+    let start = _fbb.start_table();
+    ImageDeletedRequestEventBuilder {
+      fbb_: _fbb,
+      start_: start,
+    }
+  }
+  #[inline]
+  pub fn finish(self) -> flatbuffers::WIPOffset<ImageDeletedRequestEvent<'a>> {
+    let o = self.fbb_.end_table(self.start_);
+    flatbuffers::WIPOffset::new(o.value())
+  }
+}
+
+impl core::fmt::Debug for ImageDeletedRequestEvent<'_> {
+  fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+    let mut ds = f.debug_struct("ImageDeletedRequestEvent");
+      ds.field("image_uuid", &self.image_uuid());
+      ds.finish()
+  }
+}
+pub enum EventTypeCountOffset {}
+#[derive(Copy, Clone, PartialEq)]
+
+pub struct EventTypeCount<'a> {
+  pub _tab: flatbuffers::Table<'a>,
+}
+
+impl<'a> flatbuffers::Follow<'a> for EventTypeCount<'a> {
+  type Inner = EventTypeCount<'a>;
+  #[inline]
+  fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+    Self { _tab: flatbuffers::Table { buf, loc } }
+  }
+}
+
+impl<'a> EventTypeCount<'a> {
+  pub const VT_EVENT_TYPE: flatbuffers::VOffsetT = 4;
+  pub const VT_COUNT: flatbuffers::VOffsetT = 6;
+  pub const VT_BYTES: flatbuffers::VOffsetT = 8;
+
+  #[inline]
+  pub fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
+    EventTypeCount { _tab: table }
+  }
+  #[allow(unused_mut)]
+  pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr>(
+    _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr>,
+    args: &'args EventTypeCountArgs<'args>
+  ) -> flatbuffers::WIPOffset<EventTypeCount<'bldr>> {
+    let mut builder = EventTypeCountBuilder::new(_fbb);
+    builder.add_bytes(args.bytes);
+    builder.add_count(args.count);
+    if let Some(x) = args.event_type { builder.add_event_type(x); }
+    builder.finish()
+  }
+
+
+  #[inline]
+  pub fn event_type(&self) -> Option<&'a str> {
+    self._tab.get::<flatbuffers::ForwardsUOffset<&str>>(EventTypeCount::VT_EVENT_TYPE, None)
+  }
+  #[inline]
+  pub fn count(&self) -> u64 {
+    self._tab.get::<u64>(EventTypeCount::VT_COUNT, Some(0)).unwrap()
+  }
+  #[inline]
+  pub fn bytes(&self) -> u64 {
+    self._tab.get::<u64>(EventTypeCount::VT_BYTES, Some(0)).unwrap()
+  }
+}
+
+impl flatbuffers::Verifiable for EventTypeCount<'_> {
+  #[inline]
+  fn run_verifier(
+    v: &mut flatbuffers::Verifier, pos: usize
+  ) -> Result<(), flatbuffers::InvalidFlatbuffer> {
+    use self::flatbuffers::Verifiable;
+    v.visit_table(pos)?
+     .visit_field::<flatbuffers::ForwardsUOffset<&str>>("event_type", Self::VT_EVENT_TYPE, false)?
+     .visit_field::<u64>("count", Self::VT_COUNT, false)?
+     .visit_field::<u64>("bytes", Self::VT_BYTES, false)?
+     .finish();
+    Ok(())
+  }
+}
+pub struct EventTypeCountArgs<'a> {
+    pub event_type: Option<flatbuffers::WIPOffset<&'a str>>,
+    pub count: u64,
+    pub bytes: u64,
+}
+impl<'a> Default for EventTypeCountArgs<'a> {
+  #[inline]
+  fn default() -> Self {
+    EventTypeCountArgs {
+      event_type: None,
+      count: 0,
+      bytes: 0,
+    }
+  }
+}
+
+pub struct EventTypeCountBuilder<'a: 'b, 'b> {
+  fbb_: &'b mut flatbuffers::FlatBufferBuilder<'a>,
+  start_: flatbuffers::WIPOffset<flatbuffers::TableUnfinishedWIPOffset>,
+}
+impl<'a: 'b, 'b> EventTypeCountBuilder<'a, 'b> {
+  #[inline]
+  pub fn add_event_type(&mut self, event_type: flatbuffers::WIPOffset<&'b  str>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(EventTypeCount::VT_EVENT_TYPE, event_type);
+  }
+  #[inline]
+  pub fn add_count(&mut self, count: u64) {
+    self.fbb_.push_slot::<u64>(EventTypeCount::VT_COUNT, count, 0);
+  }
+  #[inline]
+  pub fn add_bytes(&mut self, bytes: u64) {
+    self.fbb_.push_slot::<u64>(EventTypeCount::VT_BYTES, bytes, 0);
+  }
+  #[inline]
+  pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a>) -> EventTypeCountBuilder<'a, 'b> {
+    let start = _fbb.start_table();
+    EventTypeCountBuilder {
+      fbb_: _fbb,
+      start_: start,
+    }
+  }
+  #[inline]
+  pub fn finish(self) -> flatbuffers::WIPOffset<EventTypeCount<'a>> {
+    let o = self.fbb_.end_table(self.start_);
+    flatbuffers::WIPOffset::new(o.value())
+  }
+}
+
+impl core::fmt::Debug for EventTypeCount<'_> {
+  fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+    let mut ds = f.debug_struct("EventTypeCount");
+      ds.field("event_type", &self.event_type());
+      ds.field("count", &self.count());
+      ds.field("bytes", &self.bytes());
+      ds.finish()
+  }
+}
+pub enum EventTypeLatencyOffset {}
+#[derive(Copy, Clone, PartialEq)]
+
+pub struct EventTypeLatency<'a> {
+  pub _tab: flatbuffers::Table<'a>,
+}
+
+impl<'a> flatbuffers::Follow<'a> for EventTypeLatency<'a> {
+  type Inner = EventTypeLatency<'a>;
+  #[inline]
+  fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+    Self { _tab: flatbuffers::Table { buf, loc } }
+  }
+}
+
+impl<'a> EventTypeLatency<'a> {
+  pub const VT_EVENT_TYPE: flatbuffers::VOffsetT = 4;
+  pub const VT_COUNT: flatbuffers::VOffsetT = 6;
+  pub const VT_P50_US: flatbuffers::VOffsetT = 8;
+  pub const VT_P95_US: flatbuffers::VOffsetT = 10;
+  pub const VT_P99_US: flatbuffers::VOffsetT = 12;
+  pub const VT_MAX_US: flatbuffers::VOffsetT = 14;
+
+  #[inline]
+  pub fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
+    EventTypeLatency { _tab: table }
+  }
+  #[allow(unused_mut)]
+  pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr>(
+    _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr>,
+    args: &'args EventTypeLatencyArgs<'args>
+  ) -> flatbuffers::WIPOffset<EventTypeLatency<'bldr>> {
+    let mut builder = EventTypeLatencyBuilder::new(_fbb);
+    builder.add_max_us(args.max_us);
+    builder.add_p99_us(args.p99_us);
+    builder.add_p95_us(args.p95_us);
+    builder.add_p50_us(args.p50_us);
+    builder.add_count(args.count);
+    if let Some(x) = args.event_type { builder.add_event_type(x); }
+    builder.finish()
+  }
+
+
+  #[inline]
+  pub fn event_type(&self) -> Option<&'a str> {
+    self._tab.get::<flatbuffers::ForwardsUOffset<&str>>(EventTypeLatency::VT_EVENT_TYPE, None)
+  }
+  #[inline]
+  pub fn count(&self) -> u64 {
+    self._tab.get::<u64>(EventTypeLatency::VT_COUNT, Some(0)).unwrap()
+  }
+  #[inline]
+  pub fn p50_us(&self) -> u64 {
+    self._tab.get::<u64>(EventTypeLatency::VT_P50_US, Some(0)).unwrap()
+  }
+  #[inline]
+  pub fn p95_us(&self) -> u64 {
+    self._tab.get::<u64>(EventTypeLatency::VT_P95_US, Some(0)).unwrap()
+  }
+  #[inline]
+  pub fn p99_us(&self) -> u64 {
+    self._tab.get::<u64>(EventTypeLatency::VT_P99_US, Some(0)).unwrap()
+  }
+  #[inline]
+  pub fn max_us(&self) -> u64 {
+    self._tab.get::<u64>(EventTypeLatency::VT_MAX_US, Some(0)).unwrap()
+  }
+}
+
+impl flatbuffers::Verifiable for EventTypeLatency<'_> {
+  #[inline]
+  fn run_verifier(
+    v: &mut flatbuffers::Verifier, pos: usize
+  ) -> Result<(), flatbuffers::InvalidFlatbuffer> {
+    use self::flatbuffers::Verifiable;
+    v.visit_table(pos)?
+     .visit_field::<flatbuffers::ForwardsUOffset<&str>>("event_type", Self::VT_EVENT_TYPE, false)?
+     .visit_field::<u64>("count", Self::VT_COUNT, false)?
+     .visit_field::<u64>("p50_us", Self::VT_P50_US, false)?
+     .visit_field::<u64>("p95_us", Self::VT_P95_US, false)?
+     .visit_field::<u64>("p99_us", Self::VT_P99_US, false)?
+     .visit_field::<u64>("max_us", Self::VT_MAX_US, false)?
+     .finish();
+    Ok(())
+  }
+}
+pub struct EventTypeLatencyArgs<'a> {
+    pub event_type: Option<flatbuffers::WIPOffset<&'a str>>,
+    pub count: u64,
+    pub p50_us: u64,
+    pub p95_us: u64,
+    pub p99_us: u64,
+    pub max_us: u64,
+}
+impl<'a> Default for EventTypeLatencyArgs<'a> {
+  #[inline]
+  fn default() -> Self {
+    EventTypeLatencyArgs {
+      event_type: None,
+      count: 0,
+      p50_us: 0,
+      p95_us: 0,
+      p99_us: 0,
+      max_us: 0,
+    }
+  }
+}
+
+pub struct EventTypeLatencyBuilder<'a: 'b, 'b> {
+  fbb_: &'b mut flatbuffers::FlatBufferBuilder<'a>,
+  start_: flatbuffers::WIPOffset<flatbuffers::TableUnfinishedWIPOffset>,
+}
+impl<'a: 'b, 'b> EventTypeLatencyBuilder<'a, 'b> {
+  #[inline]
+  pub fn add_event_type(&mut self, event_type: flatbuffers::WIPOffset<&'b  str>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(EventTypeLatency::VT_EVENT_TYPE, event_type);
+  }
+  #[inline]
+  pub fn add_count(&mut self, count: u64) {
+    self.fbb_.push_slot::<u64>(EventTypeLatency::VT_COUNT, count, 0);
+  }
+  #[inline]
+  pub fn add_p50_us(&mut self, p50_us: u64) {
+    self.fbb_.push_slot::<u64>(EventTypeLatency::VT_P50_US, p50_us, 0);
+  }
+  #[inline]
+  pub fn add_p95_us(&mut self, p95_us: u64) {
+    self.fbb_.push_slot::<u64>(EventTypeLatency::VT_P95_US, p95_us, 0);
+  }
+  #[inline]
+  pub fn add_p99_us(&mut self, p99_us: u64) {
+    self.fbb_.push_slot::<u64>(EventTypeLatency::VT_P99_US, p99_us, 0);
+  }
+  #[inline]
+  pub fn add_max_us(&mut self, max_us: u64) {
+    self.fbb_.push_slot::<u64>(EventTypeLatency::VT_MAX_US, max_us, 0);
+  }
+  #[inline]
+  pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a>) -> EventTypeLatencyBuilder<'a, 'b> {
+    let start = _fbb.start_table();
+    EventTypeLatencyBuilder {
+      fbb_: _fbb,
+      start_: start,
+    }
+  }
+  #[inline]
+  pub fn finish(self) -> flatbuffers::WIPOffset<EventTypeLatency<'a>> {
+    let o = self.fbb_.end_table(self.start_);
+    flatbuffers::WIPOffset::new(o.value())
+  }
+}
+
+impl core::fmt::Debug for EventTypeLatency<'_> {
+  fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+    let mut ds = f.debug_struct("EventTypeLatency");
+      ds.field("event_type", &self.event_type());
+      ds.field("count", &self.count());
+      ds.field("p50_us", &self.p50_us());
+      ds.field("p95_us", &self.p95_us());
+      ds.field("p99_us", &self.p99_us());
+      ds.field("max_us", &self.max_us());
+      ds.finish()
+  }
+}
+pub enum MetricsSnapshotEventOffset {}
+#[derive(Copy, Clone, PartialEq)]
+
+pub struct MetricsSnapshotEvent<'a> {
+  pub _tab: flatbuffers::Table<'a>,
+}
+
+impl<'a> flatbuffers::Follow<'a> for MetricsSnapshotEvent<'a> {
+  type Inner = MetricsSnapshotEvent<'a>;
+  #[inline]
+  fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+    Self { _tab: flatbuffers::Table { buf, loc } }
+  }
+}
+
+impl<'a> MetricsSnapshotEvent<'a> {
+  pub const VT_COUNTS: flatbuffers::VOffsetT = 4;
+  pub const VT_LATENCIES: flatbuffers::VOffsetT = 6;
+
+  #[inline]
+  pub fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
+    MetricsSnapshotEvent { _tab: table }
+  }
+  #[allow(unused_mut)]
+  pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr>(
+    _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr>,
+    args: &'args MetricsSnapshotEventArgs<'args>
+  ) -> flatbuffers::WIPOffset<MetricsSnapshotEvent<'bldr>> {
+    let mut builder = MetricsSnapshotEventBuilder::new(_fbb);
+    if let Some(x) = args.latencies { builder.add_latencies(x); }
+    if let Some(x) = args.counts { builder.add_counts(x); }
+    builder.finish()
+  }
+
+
+  #[inline]
+  pub fn counts(&self) -> Option<flatbuffers::Vector<'a, flatbuffers::ForwardsUOffset<EventTypeCount<'a>>>> {
+    self._tab.get::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'a, flatbuffers::ForwardsUOffset<EventTypeCount>>>>(MetricsSnapshotEvent::VT_COUNTS, None)
+  }
+  #[inline]
+  pub fn latencies(&self) -> Option<flatbuffers::Vector<'a, flatbuffers::ForwardsUOffset<EventTypeLatency<'a>>>> {
+    self._tab.get::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'a, flatbuffers::ForwardsUOffset<EventTypeLatency>>>>(MetricsSnapshotEvent::VT_LATENCIES, None)
+  }
+}
+
+impl flatbuffers::Verifiable for MetricsSnapshotEvent<'_> {
+  #[inline]
+  fn run_verifier(
+    v: &mut flatbuffers::Verifier, pos: usize
+  ) -> Result<(), flatbuffers::InvalidFlatbuffer> {
+    use self::flatbuffers::Verifiable;
+    v.visit_table(pos)?
+     .visit_field::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'_, flatbuffers::ForwardsUOffset<EventTypeCount>>>>("counts", Self::VT_COUNTS, false)?
+     .visit_field::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'_, flatbuffers::ForwardsUOffset<EventTypeLatency>>>>("latencies", Self::VT_LATENCIES, false)?
+     .finish();
+    Ok(())
+  }
+}
+pub struct MetricsSnapshotEventArgs<'a> {
+    pub counts: Option<flatbuffers::WIPOffset<flatbuffers::Vector<'a, flatbuffers::ForwardsUOffset<EventTypeCount<'a>>>>>,
+    pub latencies: Option<flatbuffers::WIPOffset<flatbuffers::Vector<'a, flatbuffers::ForwardsUOffset<EventTypeLatency<'a>>>>>,
+}
+impl<'a> Default for MetricsSnapshotEventArgs<'a> {
+  #[inline]
+  fn default() -> Self {
+    MetricsSnapshotEventArgs {
+      counts: None,
+      latencies: None,
+    }
+  }
+}
+
+pub struct MetricsSnapshotEventBuilder<'a: 'b, 'b> {
+  fbb_: &'b mut flatbuffers::FlatBufferBuilder<'a>,
+  start_: flatbuffers::WIPOffset<flatbuffers::TableUnfinishedWIPOffset>,
+}
+impl<'a: 'b, 'b> MetricsSnapshotEventBuilder<'a, 'b> {
+  #[inline]
+  pub fn add_counts(&mut self, counts: flatbuffers::WIPOffset<flatbuffers::Vector<'b , flatbuffers::ForwardsUOffset<EventTypeCount<'b >>>>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(MetricsSnapshotEvent::VT_COUNTS, counts);
+  }
+  #[inline]
+  pub fn add_latencies(&mut self, latencies: flatbuffers::WIPOffset<flatbuffers::Vector<'b , flatbuffers::ForwardsUOffset<EventTypeLatency<'b >>>>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(MetricsSnapshotEvent::VT_LATENCIES, latencies);
+  }
+  #[inline]
+  pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a>) -> MetricsSnapshotEventBuilder<'a, 'b> {
+    let start = _fbb.start_table();
+    MetricsSnapshotEventBuilder {
+      fbb_: _fbb,
+      start_: start,
+    }
+  }
+  #[inline]
+  pub fn finish(self) -> flatbuffers::WIPOffset<MetricsSnapshotEvent<'a>> {
+    let o = self.fbb_.end_table(self.start_);
+    flatbuffers::WIPOffset::new(o.value())
+  }
+}
+
+impl core::fmt::Debug for MetricsSnapshotEvent<'_> {
+  fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+    let mut ds = f.debug_struct("MetricsSnapshotEvent");
+      ds.field("counts", &self.counts());
+      ds.field("latencies", &self.latencies());
+      ds.finish()
+  }
+}
+pub enum WebhookDeliveryFailedEventOffset {}
+#[derive(Copy, Clone, PartialEq)]
+
+pub struct WebhookDeliveryFailedEvent<'a> {
+  pub _tab: flatbuffers::Table<'a>,
+}
+
+impl<'a> flatbuffers::Follow<'a> for WebhookDeliveryFailedEvent<'a> {
+  type Inner = WebhookDeliveryFailedEvent<'a>;
+  #[inline]
+  fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+    Self { _tab: flatbuffers::Table { buf, loc } }
+  }
+}
+
+impl<'a> WebhookDeliveryFailedEvent<'a> {
+  pub const VT_IMAGE_UUID: flatbuffers::VOffsetT = 4;
+  pub const VT_EVENT_TYPE: flatbuffers::VOffsetT = 6;
+  pub const VT_URL: flatbuffers::VOffsetT = 8;
+  pub const VT_STATUS_CODE: flatbuffers::VOffsetT = 10;
+  pub const VT_ERROR: flatbuffers::VOffsetT = 12;
+
+  #[inline]
+  pub fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
+    WebhookDeliveryFailedEvent { _tab: table }
+  }
+  #[allow(unused_mut)]
+  pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr>(
+    _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr>,
+    args: &'args WebhookDeliveryFailedEventArgs<'args>
+  ) -> flatbuffers::WIPOffset<WebhookDeliveryFailedEvent<'bldr>> {
+    let mut builder = WebhookDeliveryFailedEventBuilder::new(_fbb);
+    if let Some(x) = args.error { builder.add_error(x); }
+    if let Some(x) = args.url { builder.add_url(x); }
+    if let Some(x) = args.event_type { builder.add_event_type(x); }
+    if let Some(x) = args.image_uuid { builder.add_image_uuid(x); }
+    builder.add_status_code(args.status_code);
+    builder.finish()
+  }
+
+
+  #[inline]
+  pub fn image_uuid(&self) -> Option<&'a str> {
+    self._tab.get::<flatbuffers::ForwardsUOffset<&str>>(WebhookDeliveryFailedEvent::VT_IMAGE_UUID, None)
+  }
+  #[inline]
+  pub fn event_type(&self) -> Option<&'a str> {
+    self._tab.get::<flatbuffers::ForwardsUOffset<&str>>(WebhookDeliveryFailedEvent::VT_EVENT_TYPE, None)
+  }
+  #[inline]
+  pub fn url(&self) -> Option<&'a str> {
+    self._tab.get::<flatbuffers::ForwardsUOffset<&str>>(WebhookDeliveryFailedEvent::VT_URL, None)
+  }
+  #[inline]
+  pub fn status_code(&self) -> u16 {
+    self._tab.get::<u16>(WebhookDeliveryFailedEvent::VT_STATUS_CODE, Some(0)).unwrap()
+  }
+  #[inline]
+  pub fn error(&self) -> Option<&'a str> {
+    self._tab.get::<flatbuffers::ForwardsUOffset<&str>>(WebhookDeliveryFailedEvent::VT_ERROR, None)
+  }
+}
+
+impl flatbuffers::Verifiable for WebhookDeliveryFailedEvent<'_> {
+  #[inline]
+  fn run_verifier(
+    v: &mut flatbuffers::Verifier, pos: usize
+  ) -> Result<(), flatbuffers::InvalidFlatbuffer> {
+    use self::flatbuffers::Verifiable;
+    v.visit_table(pos)?
+     .visit_field::<flatbuffers::ForwardsUOffset<&str>>("image_uuid", Self::VT_IMAGE_UUID, false)?
+     .visit_field::<flatbuffers::ForwardsUOffset<&str>>("event_type", Self::VT_EVENT_TYPE, false)?
+     .visit_field::<flatbuffers::ForwardsUOffset<&str>>("url", Self::VT_URL, false)?
+     .visit_field::<u16>("status_code", Self::VT_STATUS_CODE, false)?
+     .visit_field::<flatbuffers::ForwardsUOffset<&str>>("error", Self::VT_ERROR, false)?
+     .finish();
+    Ok(())
+  }
+}
+pub struct WebhookDeliveryFailedEventArgs<'a> {
+    pub image_uuid: Option<flatbuffers::WIPOffset<&'a str>>,
+    pub event_type: Option<flatbuffers::WIPOffset<&'a str>>,
+    pub url: Option<flatbuffers::WIPOffset<&'a str>>,
+    pub status_code: u16,
+    pub error: Option<flatbuffers::WIPOffset<&'a str>>,
+}
+impl<'a> Default for WebhookDeliveryFailedEventArgs<'a> {
+  #[inline]
+  fn default() -> Self {
+    WebhookDeliveryFailedEventArgs {
+      image_uuid: None,
+      event_type: None,
+      url: None,
+      status_code: 0,
+      error: None,
+    }
+  }
+}
+
+pub struct WebhookDeliveryFailedEventBuilder<'a: 'b, 'b> {
+  fbb_: &'b mut flatbuffers::FlatBufferBuilder<'a>,
+  start_: flatbuffers::WIPOffset<flatbuffers::TableUnfinishedWIPOffset>,
+}
+impl<'a: 'b, 'b> WebhookDeliveryFailedEventBuilder<'a, 'b> {
+  #[inline]
+  pub fn add_image_uuid(&mut self, image_uuid: flatbuffers::WIPOffset<&'b  str>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(WebhookDeliveryFailedEvent::VT_IMAGE_UUID, image_uuid);
+  }
+  #[inline]
+  pub fn add_event_type(&mut self, event_type: flatbuffers::WIPOffset<&'b  str>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(WebhookDeliveryFailedEvent::VT_EVENT_TYPE, event_type);
+  }
+  #[inline]
+  pub fn add_url(&mut self, url: flatbuffers::WIPOffset<&'b  str>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(WebhookDeliveryFailedEvent::VT_URL, url);
+  }
+  #[inline]
+  pub fn add_status_code(&mut self, status_code: u16) {
+    self.fbb_.push_slot::<u16>(WebhookDeliveryFailedEvent::VT_STATUS_CODE, status_code, 0);
+  }
+  #[inline]
+  pub fn add_error(&mut self, error: flatbuffers::WIPOffset<&'b  str>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(WebhookDeliveryFailedEvent::VT_ERROR, error);
+  }
+  #[inline]
+  pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a>) -> WebhookDeliveryFailedEventBuilder<'a, 'b> {
+    let start = _fbb.start_table();
+    WebhookDeliveryFailedEventBuilder {
+      fbb_: _fbb,
+      start_: start,
+    }
+  }
+  #[inline]
+  pub fn finish(self) -> flatbuffers::WIPOffset<WebhookDeliveryFailedEvent<'a>> {
+    let o = self.fbb_.end_table(self.start_);
+    flatbuffers::WIPOffset::new(o.value())
+  }
+}
+
+impl core::fmt::Debug for WebhookDeliveryFailedEvent<'_> {
+  fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+    let mut ds = f.debug_struct("WebhookDeliveryFailedEvent");
+      ds.field("image_uuid", &self.image_uuid());
+      ds.field("event_type", &self.event_type());
+      ds.field("url", &self.url());
+      ds.field("status_code", &self.status_code());
+      ds.field("error", &self.error());
+      ds.finish()
+  }
+}
+pub enum PluginTerminateEventOffset {}
+#[derive(Copy, Clone, PartialEq)]
+
+pub struct PluginTerminateEvent<'a> {
+  pub _tab: flatbuffers::Table<'a>,
+}
+
+impl<'a> flatbuffers::Follow<'a> for PluginTerminateEvent<'a> {
+  type Inner = PluginTerminateEvent<'a>;
+  #[inline]
+  fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+    Self { _tab: flatbuffers::Table { buf, loc } }
+  }
+}
+
+impl<'a> PluginTerminateEvent<'a> {
+  #[inline]
+  pub fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
+    PluginTerminateEvent { _tab: table }
+  }
+  #[allow(unused_mut)]
+  pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr>(
+    _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr>,
+    _args: &'args PluginTerminateEventArgs
+  ) -> flatbuffers::WIPOffset<PluginTerminateEvent<'bldr>> {
+    let mut builder = PluginTerminateEventBuilder::new(_fbb);
+    builder.finish()
+  }
+
+
+}
+
+impl flatbuffers::Verifiable for PluginTerminateEvent<'_> {
+  #[inline]
+  fn run_verifier(
+    v: &mut flatbuffers::Verifier, pos: usize
+  ) -> Result<(), flatbuffers::InvalidFlatbuffer> {
+    use self::flatbuffers::Verifiable;
+    v.visit_table(pos)?
+     .finish();
+    Ok(())
+  }
+}
+pub struct PluginTerminateEventArgs {
+}
+impl<'a> Default for PluginTerminateEventArgs {
+  #[inline]
+  fn default() -> Self {
+    PluginTerminateEventArgs {
+    }
+  }
+}
+
+pub struct PluginTerminateEventBuilder<'a: 'b, 'b> {
+  fbb_: &'b mut flatbuffers::FlatBufferBuilder<'a>,
+  start_: flatbuffers::WIPOffset<flatbuffers::TableUnfinishedWIPOffset>,
+}
+impl<'a: 'b, 'b> PluginTerminateEventBuilder<'a, 'b> {
+  #[inline]
+  pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a>) -> PluginTerminateEventBuilder<'a, 'b> {
+    let start = _fbb.start_table();
+    PluginTerminateEventBuilder {
+      fbb_: _fbb,
+      start_: start,
+    }
+  }
+  #[inline]
+  pub fn finish(self) -> flatbuffers::WIPOffset<PluginTerminateEvent<'a>> {
+    let o = self.fbb_.end_table(self.start_);
+    flatbuffers::WIPOffset::new(o.value())
+  }
+}
+
+impl core::fmt::Debug for PluginTerminateEvent<'_> {
+  fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+    let mut ds = f.debug_struct("PluginTerminateEvent");
+      ds.finish()
+  }
+}
+pub enum PluginFailedEventOffset {}
+#[derive(Copy, Clone, PartialEq)]
+
+pub struct PluginFailedEvent<'a> {
+  pub _tab: flatbuffers::Table<'a>,
+}
+
+impl<'a> flatbuffers::Follow<'a> for PluginFailedEvent<'a> {
+  type Inner = PluginFailedEvent<'a>;
+  #[inline]
+  fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+    Self { _tab: flatbuffers::Table { buf, loc } }
+  }
+}
+
+impl<'a> PluginFailedEvent<'a> {
+  pub const VT_PLUGIN_ID: flatbuffers::VOffsetT = 4;
+  pub const VT_MESSAGE: flatbuffers::VOffsetT = 6;
+
+  #[inline]
+  pub fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
+    PluginFailedEvent { _tab: table }
+  }
+  #[allow(unused_mut)]
+  pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr>(
+    _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr>,
+    args: &'args PluginFailedEventArgs<'args>
+  ) -> flatbuffers::WIPOffset<PluginFailedEvent<'bldr>> {
+    let mut builder = PluginFailedEventBuilder::new(_fbb);
+    if let Some(x) = args.message { builder.add_message(x); }
+    builder.add_plugin_id(args.plugin_id);
+    builder.finish()
+  }
+
+
+  #[inline]
+  pub fn plugin_id(&self) -> i32 {
+    self._tab.get::<i32>(PluginFailedEvent::VT_PLUGIN_ID, Some(0)).unwrap()
+  }
+  #[inline]
+  pub fn message(&self) -> Option<&'a str> {
+    self._tab.get::<flatbuffers::ForwardsUOffset<&str>>(PluginFailedEvent::VT_MESSAGE, None)
+  }
+}
+
+impl flatbuffers::Verifiable for PluginFailedEvent<'_> {
+  #[inline]
+  fn run_verifier(
+    v: &mut flatbuffers::Verifier, pos: usize
+  ) -> Result<(), flatbuffers::InvalidFlatbuffer> {
+    use self::flatbuffers::Verifiable;
+    v.visit_table(pos)?
+     .visit_field::<i32>("plugin_id", Self::VT_PLUGIN_ID, false)?
+     .visit_field::<flatbuffers::ForwardsUOffset<&str>>("message", Self::VT_MESSAGE, false)?
+     .finish();
+    Ok(())
+  }
+}
+pub struct PluginFailedEventArgs<'a> {
+    pub plugin_id: i32,
+    pub message: Option<flatbuffers::WIPOffset<&'a str>>,
+}
+impl<'a> Default for PluginFailedEventArgs<'a> {
+  #[inline]
+  fn default() -> Self {
+    PluginFailedEventArgs {
+      plugin_id: 0,
+      message: None,
+    }
+  }
+}
+
+pub struct PluginFailedEventBuilder<'a: 'b, 'b> {
+  fbb_: &'b mut flatbuffers::FlatBufferBuilder<'a>,
+  start_: flatbuffers::WIPOffset<flatbuffers::TableUnfinishedWIPOffset>,
+}
+impl<'a: 'b, 'b> PluginFailedEventBuilder<'a, 'b> {
+  #[inline]
+  pub fn add_plugin_id(&mut self, plugin_id: i32) {
+    self.fbb_.push_slot::<i32>(PluginFailedEvent::VT_PLUGIN_ID, plugin_id, 0);
+  }
+  #[inline]
+  pub fn add_message(&mut self, message: flatbuffers::WIPOffset<&'b  str>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(PluginFailedEvent::VT_MESSAGE, message);
+  }
+  #[inline]
+  pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a>) -> PluginFailedEventBuilder<'a, 'b> {
+    let start = _fbb.start_table();
+    PluginFailedEventBuilder {
+      fbb_: _fbb,
+      start_: start,
+    }
+  }
+  #[inline]
+  pub fn finish(self) -> flatbuffers::WIPOffset<PluginFailedEvent<'a>> {
+    let o = self.fbb_.end_table(self.start_);
+    flatbuffers::WIPOffset::new(o.value())
+  }
+}
+
+impl core::fmt::Debug for PluginFailedEvent<'_> {
+  fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+    let mut ds = f.debug_struct("PluginFailedEvent");
+      ds.field("plugin_id", &self.plugin_id());
+      ds.field("message", &self.message());
+      ds.finish()
+  }
+}
+pub enum PluginRestartedEventOffset {}
+#[derive(Copy, Clone, PartialEq)]
+
+pub struct PluginRestartedEvent<'a> {
+  pub _tab: flatbuffers::Table<'a>,
+}
+
+impl<'a> flatbuffers::Follow<'a> for PluginRestartedEvent<'a> {
+  type Inner = PluginRestartedEvent<'a>;
+  #[inline]
+  fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+    Self { _tab: flatbuffers::Table { buf, loc } }
+  }
+}
+
+impl<'a> PluginRestartedEvent<'a> {
+  pub const VT_PLUGIN_ID: flatbuffers::VOffsetT = 4;
+  pub const VT_RESTART_COUNT: flatbuffers::VOffsetT = 6;
+
+  #[inline]
+  pub fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
+    PluginRestartedEvent { _tab: table }
+  }
+  #[allow(unused_mut)]
+  pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr>(
+    _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr>,
+    args: &'args PluginRestartedEventArgs
+  ) -> flatbuffers::WIPOffset<PluginRestartedEvent<'bldr>> {
+    let mut builder = PluginRestartedEventBuilder::new(_fbb);
+    builder.add_restart_count(args.restart_count);
+    builder.add_plugin_id(args.plugin_id);
+    builder.finish()
+  }
+
+
+  #[inline]
+  pub fn plugin_id(&self) -> i32 {
+    self._tab.get::<i32>(PluginRestartedEvent::VT_PLUGIN_ID, Some(0)).unwrap()
+  }
+  #[inline]
+  pub fn restart_count(&self) -> u32 {
+    self._tab.get::<u32>(PluginRestartedEvent::VT_RESTART_COUNT, Some(0)).unwrap()
+  }
+}
+
+impl flatbuffers::Verifiable for PluginRestartedEvent<'_> {
+  #[inline]
+  fn run_verifier(
+    v: &mut flatbuffers::Verifier, pos: usize
+  ) -> Result<(), flatbuffers::InvalidFlatbuffer> {
+    use self::flatbuffers::Verifiable;
+    v.visit_table(pos)?
+     .visit_field::<i32>("plugin_id", Self::VT_PLUGIN_ID, false)?
+     .visit_field::<u32>("restart_count", Self::VT_RESTART_COUNT, false)?
+     .finish();
+    Ok(())
+  }
+}
+pub struct PluginRestartedEventArgs {
+    pub plugin_id: i32,
+    pub restart_count: u32,
+}
+impl<'a> Default for PluginRestartedEventArgs {
+  #[inline]
+  fn default() -> Self {
+    PluginRestartedEventArgs {
+      plugin_id: 0,
+      restart_count: 0,
+    }
+  }
+}
+
+pub struct PluginRestartedEventBuilder<'a: 'b, 'b> {
+  fbb_: &'b mut flatbuffers::FlatBufferBuilder<'a>,
+  start_: flatbuffers::WIPOffset<flatbuffers::TableUnfinishedWIPOffset>,
+}
+impl<'a: 'b, 'b> PluginRestartedEventBuilder<'a, 'b> {
+  #[inline]
+  pub fn add_plugin_id(&mut self, plugin_id: i32) {
+    self.fbb_.push_slot::<i32>(PluginRestartedEvent::VT_PLUGIN_ID, plugin_id, 0);
+  }
+  #[inline]
+  pub fn add_restart_count(&mut self, restart_count: u32) {
+    self.fbb_.push_slot::<u32>(PluginRestartedEvent::VT_RESTART_COUNT, restart_count, 0);
+  }
+  #[inline]
+  pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a>) -> PluginRestartedEventBuilder<'a, 'b> {
+    let start = _fbb.start_table();
+    PluginRestartedEventBuilder {
+      fbb_: _fbb,
+      start_: start,
+    }
+  }
+  #[inline]
+  pub fn finish(self) -> flatbuffers::WIPOffset<PluginRestartedEvent<'a>> {
+    let o = self.fbb_.end_table(self.start_);
+    flatbuffers::WIPOffset::new(o.value())
+  }
+}
+
+impl core::fmt::Debug for PluginRestartedEvent<'_> {
+  fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+    let mut ds = f.debug_struct("PluginRestartedEvent");
+      ds.field("plugin_id", &self.plugin_id());
+      ds.field("restart_count", &self.restart_count());
+      ds.finish()
+  }
+}
+pub enum PluginJoinedEventOffset {}
+#[derive(Copy, Clone, PartialEq)]
+
+pub struct PluginJoinedEvent<'a> {
+  pub _tab: flatbuffers::Table<'a>,
+}
+
+impl<'a> flatbuffers::Follow<'a> for PluginJoinedEvent<'a> {
+  type Inner = PluginJoinedEvent<'a>;
+  #[inline]
+  fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+    Self { _tab: flatbuffers::Table { buf, loc } }
+  }
+}
+
+impl<'a> PluginJoinedEvent<'a> {
+  pub const VT_PLUGIN_ID: flatbuffers::VOffsetT = 4;
+
+  #[inline]
+  pub fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
+    PluginJoinedEvent { _tab: table }
+  }
+  #[allow(unused_mut)]
+  pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr>(
+    _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr>,
+    args: &'args PluginJoinedEventArgs
+  ) -> flatbuffers::WIPOffset<PluginJoinedEvent<'bldr>> {
+    let mut builder = PluginJoinedEventBuilder::new(_fbb);
+    builder.add_plugin_id(args.plugin_id);
+    builder.finish()
+  }
+
+
+  #[inline]
+  pub fn plugin_id(&self) -> i32 {
+    self._tab.get::<i32>(PluginJoinedEvent::VT_PLUGIN_ID, Some(0)).unwrap()
+  }
+}
+
+impl flatbuffers::Verifiable for PluginJoinedEvent<'_> {
+  #[inline]
+  fn run_verifier(
+    v: &mut flatbuffers::Verifier, pos: usize
+  ) -> Result<(), flatbuffers::InvalidFlatbuffer> {
+    use self::flatbuffers::Verifiable;
+    v.visit_table(pos)?
+     .visit_field::<i32>("plugin_id", Self::VT_PLUGIN_ID, false)?
+     .finish();
+    Ok(())
+  }
+}
+pub struct PluginJoinedEventArgs {
+    pub plugin_id: i32,
+}
+impl<'a> Default for PluginJoinedEventArgs {
+  #[inline]
+  fn default() -> Self {
+    PluginJoinedEventArgs {
+      plugin_id: 0,
+    }
+  }
+}
+
+pub struct PluginJoinedEventBuilder<'a: 'b, 'b> {
+  fbb_: &'b mut flatbuffers::FlatBufferBuilder<'a>,
+  start_: flatbuffers::WIPOffset<flatbuffers::TableUnfinishedWIPOffset>,
+}
+impl<'a: 'b, 'b> PluginJoinedEventBuilder<'a, 'b> {
+  #[inline]
+  pub fn add_plugin_id(&mut self, plugin_id: i32) {
+    self.fbb_.push_slot::<i32>(PluginJoinedEvent::VT_PLUGIN_ID, plugin_id, 0);
+  }
+  #[inline]
+  pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a>) -> PluginJoinedEventBuilder<'a, 'b> {
+    let start = _fbb.start_table();
+    PluginJoinedEventBuilder {
+      fbb_: _fbb,
+      start_: start,
+    }
+  }
+  #[inline]
+  pub fn finish(self) -> flatbuffers::WIPOffset<PluginJoinedEvent<'a>> {
+    let o = self.fbb_.end_table(self.start_);
+    flatbuffers::WIPOffset::new(o.value())
+  }
+}
+
+impl core::fmt::Debug for PluginJoinedEvent<'_> {
+  fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+    let mut ds = f.debug_struct("PluginJoinedEvent");
+      ds.field("plugin_id", &self.plugin_id());
+      ds.finish()
+  }
+}
+pub enum PluginLeftEventOffset {}
+#[derive(Copy, Clone, PartialEq)]
+
+pub struct PluginLeftEvent<'a> {
+  pub _tab: flatbuffers::Table<'a>,
+}
+
+impl<'a> flatbuffers::Follow<'a> for PluginLeftEvent<'a> {
+  type Inner = PluginLeftEvent<'a>;
+  #[inline]
+  fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+    Self { _tab: flatbuffers::Table { buf, loc } }
+  }
+}
+
+impl<'a> PluginLeftEvent<'a> {
+  pub const VT_PLUGIN_ID: flatbuffers::VOffsetT = 4;
+  pub const VT_REASON: flatbuffers::VOffsetT = 6;
+
+  #[inline]
+  pub fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
+    PluginLeftEvent { _tab: table }
+  }
+  #[allow(unused_mut)]
+  pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr>(
+    _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr>,
+    args: &'args PluginLeftEventArgs<'args>
+  ) -> flatbuffers::WIPOffset<PluginLeftEvent<'bldr>> {
+    let mut builder = PluginLeftEventBuilder::new(_fbb);
+    if let Some(x) = args.reason { builder.add_reason(x); }
+    builder.add_plugin_id(args.plugin_id);
+    builder.finish()
+  }
+
+
+  #[inline]
+  pub fn plugin_id(&self) -> i32 {
+    self._tab.get::<i32>(PluginLeftEvent::VT_PLUGIN_ID, Some(0)).unwrap()
+  }
+  #[inline]
+  pub fn reason(&self) -> Option<&'a str> {
+    self._tab.get::<flatbuffers::ForwardsUOffset<&str>>(PluginLeftEvent::VT_REASON, None)
+  }
+}
+
+impl flatbuffers::Verifiable for PluginLeftEvent<'_> {
+  #[inline]
+  fn run_verifier(
+    v: &mut flatbuffers::Verifier, pos: usize
+  ) -> Result<(), flatbuffers::InvalidFlatbuffer> {
+    use self::flatbuffers::Verifiable;
+    v.visit_table(pos)?
+     .visit_field::<i32>("plugin_id", Self::VT_PLUGIN_ID, false)?
+     .visit_field::<flatbuffers::ForwardsUOffset<&str>>("reason", Self::VT_REASON, false)?
+     .finish();
+    Ok(())
+  }
+}
+pub struct PluginLeftEventArgs<'a> {
+    pub plugin_id: i32,
+    pub reason: Option<flatbuffers::WIPOffset<&'a str>>,
+}
+impl<'a> Default for PluginLeftEventArgs<'a> {
+  #[inline]
+  fn default() -> Self {
+    PluginLeftEventArgs {
+      plugin_id: 0,
+      reason: None,
+    }
+  }
+}
+
+pub struct PluginLeftEventBuilder<'a: 'b, 'b> {
+  fbb_: &'b mut flatbuffers::FlatBufferBuilder<'a>,
+  start_: flatbuffers::WIPOffset<flatbuffers::TableUnfinishedWIPOffset>,
+}
+impl<'a: 'b, 'b> PluginLeftEventBuilder<'a, 'b> {
+  #[inline]
+  pub fn add_plugin_id(&mut self, plugin_id: i32) {
+    self.fbb_.push_slot::<i32>(PluginLeftEvent::VT_PLUGIN_ID, plugin_id, 0);
+  }
+  #[inline]
+  pub fn add_reason(&mut self, reason: flatbuffers::WIPOffset<&'b  str>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(PluginLeftEvent::VT_REASON, reason);
+  }
+  #[inline]
+  pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a>) -> PluginLeftEventBuilder<'a, 'b> {
+    let start = _fbb.start_table();
+    PluginLeftEventBuilder {
+      fbb_: _fbb,
+      start_: start,
+    }
+  }
+  #[inline]
+  pub fn finish(self) -> flatbuffers::WIPOffset<PluginLeftEvent<'a>> {
+    let o = self.fbb_.end_table(self.start_);
+    flatbuffers::WIPOffset::new(o.value())
+  }
+}
+
+impl core::fmt::Debug for PluginLeftEvent<'_> {
+  fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+    let mut ds = f.debug_struct("PluginLeftEvent");
+      ds.field("plugin_id", &self.plugin_id());
+      ds.field("reason", &self.reason());
+      ds.finish()
+  }
+}
+pub enum EngineHeartbeatEventOffset {}
+#[derive(Copy, Clone, PartialEq)]
+
+pub struct EngineHeartbeatEvent<'a> {
+  pub _tab: flatbuffers::Table<'a>,
+}
+
+impl<'a> flatbuffers::Follow<'a> for EngineHeartbeatEvent<'a> {
+  type Inner = EngineHeartbeatEvent<'a>;
+  #[inline]
+  fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+    Self { _tab: flatbuffers::Table { buf, loc } }
+  }
+}
+
+impl<'a> EngineHeartbeatEvent<'a> {
+  pub const VT_SEQ: flatbuffers::VOffsetT = 4;
+  pub const VT_UPTIME_MS: flatbuffers::VOffsetT = 6;
+
+  #[inline]
+  pub fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
+    EngineHeartbeatEvent { _tab: table }
+  }
+  #[allow(unused_mut)]
+  pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr>(
+    _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr>,
+    args: &'args EngineHeartbeatEventArgs
+  ) -> flatbuffers::WIPOffset<EngineHeartbeatEvent<'bldr>> {
+    let mut builder = EngineHeartbeatEventBuilder::new(_fbb);
+    builder.add_uptime_ms(args.uptime_ms);
+    builder.add_seq(args.seq);
+    builder.finish()
+  }
+
+
+  #[inline]
+  pub fn seq(&self) -> u64 {
+    self._tab.get::<u64>(EngineHeartbeatEvent::VT_SEQ, Some(0)).unwrap()
+  }
+  #[inline]
+  pub fn uptime_ms(&self) -> u64 {
+    self._tab.get::<u64>(EngineHeartbeatEvent::VT_UPTIME_MS, Some(0)).unwrap()
+  }
+}
+
+impl flatbuffers::Verifiable for EngineHeartbeatEvent<'_> {
+  #[inline]
+  fn run_verifier(
+    v: &mut flatbuffers::Verifier, pos: usize
+  ) -> Result<(), flatbuffers::InvalidFlatbuffer> {
+    use self::flatbuffers::Verifiable;
+    v.visit_table(pos)?
+     .visit_field::<u64>("seq", Self::VT_SEQ, false)?
+     .visit_field::<u64>("uptime_ms", Self::VT_UPTIME_MS, false)?
+     .finish();
+    Ok(())
+  }
+}
+pub struct EngineHeartbeatEventArgs {
+    pub seq: u64,
+    pub uptime_ms: u64,
+}
+impl<'a> Default for EngineHeartbeatEventArgs {
+  #[inline]
+  fn default() -> Self {
+    EngineHeartbeatEventArgs {
+      seq: 0,
+      uptime_ms: 0,
+    }
+  }
+}
+
+pub struct EngineHeartbeatEventBuilder<'a: 'b, 'b> {
+  fbb_: &'b mut flatbuffers::FlatBufferBuilder<'a>,
+  start_: flatbuffers::WIPOffset<flatbuffers::TableUnfinishedWIPOffset>,
+}
+impl<'a: 'b, 'b> EngineHeartbeatEventBuilder<'a, 'b> {
+  #[inline]
+  pub fn add_seq(&mut self, seq: u64) {
+    self.fbb_.push_slot::<u64>(EngineHeartbeatEvent::VT_SEQ, seq, 0);
+  }
+  #[inline]
+  pub fn add_uptime_ms(&mut self, uptime_ms: u64) {
+    self.fbb_.push_slot::<u64>(EngineHeartbeatEvent::VT_UPTIME_MS, uptime_ms, 0);
+  }
+  #[inline]
+  pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a>) -> EngineHeartbeatEventBuilder<'a, 'b> {
+    let start = _fbb.start_table();
+    EngineHeartbeatEventBuilder {
+      fbb_: _fbb,
+      start_: start,
+    }
+  }
+  #[inline]
+  pub fn finish(self) -> flatbuffers::WIPOffset<EngineHeartbeatEvent<'a>> {
+    let o = self.fbb_.end_table(self.start_);
+    flatbuffers::WIPOffset::new(o.value())
+  }
+}
+
+impl core::fmt::Debug for EngineHeartbeatEvent<'_> {
+  fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+    let mut ds = f.debug_struct("EngineHeartbeatEvent");
+      ds.field("seq", &self.seq());
+      ds.field("uptime_ms", &self.uptime_ms());
+      ds.finish()
+  }
+}
+pub enum PluginHeartbeatEventOffset {}
+#[derive(Copy, Clone, PartialEq)]
+
+pub struct PluginHeartbeatEvent<'a> {
+  pub _tab: flatbuffers::Table<'a>,
+}
+
+impl<'a> flatbuffers::Follow<'a> for PluginHeartbeatEvent<'a> {
+  type Inner = PluginHeartbeatEvent<'a>;
+  #[inline]
+  fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+    Self { _tab: flatbuffers::Table { buf, loc } }
+  }
+}
+
+impl<'a> PluginHeartbeatEvent<'a> {
+  pub const VT_PLUGIN_ID: flatbuffers::VOffsetT = 4;
+  pub const VT_SEQ: flatbuffers::VOffsetT = 6;
+
+  #[inline]
+  pub fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
+    PluginHeartbeatEvent { _tab: table }
+  }
+  #[allow(unused_mut)]
+  pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr>(
+    _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr>,
+    args: &'args PluginHeartbeatEventArgs
+  ) -> flatbuffers::WIPOffset<PluginHeartbeatEvent<'bldr>> {
+    let mut builder = PluginHeartbeatEventBuilder::new(_fbb);
+    builder.add_seq(args.seq);
+    builder.add_plugin_id(args.plugin_id);
+    builder.finish()
+  }
+
+
+  #[inline]
+  pub fn plugin_id(&self) -> i32 {
+    self._tab.get::<i32>(PluginHeartbeatEvent::VT_PLUGIN_ID, Some(0)).unwrap()
+  }
+  #[inline]
+  pub fn seq(&self) -> u64 {
+    self._tab.get::<u64>(PluginHeartbeatEvent::VT_SEQ, Some(0)).unwrap()
+  }
+}
+
+impl flatbuffers::Verifiable for PluginHeartbeatEvent<'_> {
+  #[inline]
+  fn run_verifier(
+    v: &mut flatbuffers::Verifier, pos: usize
+  ) -> Result<(), flatbuffers::InvalidFlatbuffer> {
+    use self::flatbuffers::Verifiable;
+    v.visit_table(pos)?
+     .visit_field::<i32>("plugin_id", Self::VT_PLUGIN_ID, false)?
+     .visit_field::<u64>("seq", Self::VT_SEQ, false)?
+     .finish();
+    Ok(())
+  }
+}
+pub struct PluginHeartbeatEventArgs {
+    pub plugin_id: i32,
+    pub seq: u64,
+}
+impl<'a> Default for PluginHeartbeatEventArgs {
+  #[inline]
+  fn default() -> Self {
+    PluginHeartbeatEventArgs {
+      plugin_id: 0,
+      seq: 0,
+    }
+  }
+}
+
+pub struct PluginHeartbeatEventBuilder<'a: 'b, 'b> {
+  fbb_: &'b mut flatbuffers::FlatBufferBuilder<'a>,
+  start_: flatbuffers::WIPOffset<flatbuffers::TableUnfinishedWIPOffset>,
+}
+impl<'a: 'b, 'b> PluginHeartbeatEventBuilder<'a, 'b> {
+  #[inline]
+  pub fn add_plugin_id(&mut self, plugin_id: i32) {
+    self.fbb_.push_slot::<i32>(PluginHeartbeatEvent::VT_PLUGIN_ID, plugin_id, 0);
+  }
+  #[inline]
+  pub fn add_seq(&mut self, seq: u64) {
+    self.fbb_.push_slot::<u64>(PluginHeartbeatEvent::VT_SEQ, seq, 0);
+  }
+  #[inline]
+  pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a>) -> PluginHeartbeatEventBuilder<'a, 'b> {
+    let start = _fbb.start_table();
+    PluginHeartbeatEventBuilder {
+      fbb_: _fbb,
+      start_: start,
+    }
+  }
+  #[inline]
+  pub fn finish(self) -> flatbuffers::WIPOffset<PluginHeartbeatEvent<'a>> {
+    let o = self.fbb_.end_table(self.start_);
+    flatbuffers::WIPOffset::new(o.value())
+  }
+}
+
+impl core::fmt::Debug for PluginHeartbeatEvent<'_> {
+  fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+    let mut ds = f.debug_struct("PluginHeartbeatEvent");
+      ds.field("plugin_id", &self.plugin_id());
+      ds.field("seq", &self.seq());
+      ds.finish()
+  }
+}
+pub enum EventsDroppedEventOffset {}
+#[derive(Copy, Clone, PartialEq)]
+
+pub struct EventsDroppedEvent<'a> {
+  pub _tab: flatbuffers::Table<'a>,
+}
+
+impl<'a> flatbuffers::Follow<'a> for EventsDroppedEvent<'a> {
+  type Inner = EventsDroppedEvent<'a>;
+  #[inline]
+  fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+    Self { _tab: flatbuffers::Table { buf, loc } }
+  }
+}
+
+impl<'a> EventsDroppedEvent<'a> {
+  pub const VT_PLUGIN_ID: flatbuffers::VOffsetT = 4;
+  pub const VT_MISSED: flatbuffers::VOffsetT = 6;
+
+  #[inline]
+  pub fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
+    EventsDroppedEvent { _tab: table }
+  }
+  #[allow(unused_mut)]
+  pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr>(
+    _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr>,
+    args: &'args EventsDroppedEventArgs
+  ) -> flatbuffers::WIPOffset<EventsDroppedEvent<'bldr>> {
+    let mut builder = EventsDroppedEventBuilder::new(_fbb);
+    builder.add_missed(args.missed);
+    builder.add_plugin_id(args.plugin_id);
+    builder.finish()
+  }
+
+
+  #[inline]
+  pub fn plugin_id(&self) -> i32 {
+    self._tab.get::<i32>(EventsDroppedEvent::VT_PLUGIN_ID, Some(0)).unwrap()
+  }
+  #[inline]
+  pub fn missed(&self) -> u64 {
+    self._tab.get::<u64>(EventsDroppedEvent::VT_MISSED, Some(0)).unwrap()
+  }
+}
+
+impl flatbuffers::Verifiable for EventsDroppedEvent<'_> {
+  #[inline]
+  fn run_verifier(
+    v: &mut flatbuffers::Verifier, pos: usize
+  ) -> Result<(), flatbuffers::InvalidFlatbuffer> {
+    use self::flatbuffers::Verifiable;
+    v.visit_table(pos)?
+     .visit_field::<i32>("plugin_id", Self::VT_PLUGIN_ID, false)?
+     .visit_field::<u64>("missed", Self::VT_MISSED, false)?
+     .finish();
+    Ok(())
+  }
+}
+pub struct EventsDroppedEventArgs {
+    pub plugin_id: i32,
+    pub missed: u64,
+}
+impl<'a> Default for EventsDroppedEventArgs {
+  #[inline]
+  fn default() -> Self {
+    EventsDroppedEventArgs {
+      plugin_id: 0,
+      missed: 0,
+    }
+  }
+}
+
+pub struct EventsDroppedEventBuilder<'a: 'b, 'b> {
+  fbb_: &'b mut flatbuffers::FlatBufferBuilder<'a>,
+  start_: flatbuffers::WIPOffset<flatbuffers::TableUnfinishedWIPOffset>,
+}
+impl<'a: 'b, 'b> EventsDroppedEventBuilder<'a, 'b> {
+  #[inline]
+  pub fn add_plugin_id(&mut self, plugin_id: i32) {
+    self.fbb_.push_slot::<i32>(EventsDroppedEvent::VT_PLUGIN_ID, plugin_id, 0);
+  }
+  #[inline]
+  pub fn add_missed(&mut self, missed: u64) {
+    self.fbb_.push_slot::<u64>(EventsDroppedEvent::VT_MISSED, missed, 0);
+  }
+  #[inline]
+  pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a>) -> EventsDroppedEventBuilder<'a, 'b> {
+    let start = _fbb.start_table();
+    EventsDroppedEventBuilder {
+      fbb_: _fbb,
+      start_: start,
+    }
+  }
+  #[inline]
+  pub fn finish(self) -> flatbuffers::WIPOffset<EventsDroppedEvent<'a>> {
+    let o = self.fbb_.end_table(self.start_);
+    flatbuffers::WIPOffset::new(o.value())
+  }
+}
+
+impl core::fmt::Debug for EventsDroppedEvent<'_> {
+  fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+    let mut ds = f.debug_struct("EventsDroppedEvent");
+      ds.field("plugin_id", &self.plugin_id());
+      ds.field("missed", &self.missed());
+      ds.finish()
+  }
+}
+pub enum EventRejectedEventOffset {}
+#[derive(Copy, Clone, PartialEq)]
+
+pub struct EventRejectedEvent<'a> {
+  pub _tab: flatbuffers::Table<'a>,
+}
+
+impl<'a> flatbuffers::Follow<'a> for EventRejectedEvent<'a> {
+  type Inner = EventRejectedEvent<'a>;
+  #[inline]
+  fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+    Self { _tab: flatbuffers::Table { buf, loc } }
+  }
+}
+
+impl<'a> EventRejectedEvent<'a> {
+  pub const VT_REASON: flatbuffers::VOffsetT = 4;
+  pub const VT_SIZE: flatbuffers::VOffsetT = 6;
+
+  #[inline]
+  pub fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
+    EventRejectedEvent { _tab: table }
+  }
+  #[allow(unused_mut)]
+  pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr>(
+    _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr>,
+    args: &'args EventRejectedEventArgs<'args>
+  ) -> flatbuffers::WIPOffset<EventRejectedEvent<'bldr>> {
+    let mut builder = EventRejectedEventBuilder::new(_fbb);
+    builder.add_size(args.size);
+    if let Some(x) = args.reason { builder.add_reason(x); }
+    builder.finish()
+  }
+
+
+  #[inline]
+  pub fn reason(&self) -> Option<&'a str> {
+    self._tab.get::<flatbuffers::ForwardsUOffset<&str>>(EventRejectedEvent::VT_REASON, None)
+  }
+  #[inline]
+  pub fn size(&self) -> u64 {
+    self._tab.get::<u64>(EventRejectedEvent::VT_SIZE, Some(0)).unwrap()
+  }
+}
+
+impl flatbuffers::Verifiable for EventRejectedEvent<'_> {
+  #[inline]
+  fn run_verifier(
+    v: &mut flatbuffers::Verifier, pos: usize
+  ) -> Result<(), flatbuffers::InvalidFlatbuffer> {
+    use self::flatbuffers::Verifiable;
+    v.visit_table(pos)?
+     .visit_field::<flatbuffers::ForwardsUOffset<&str>>("reason", Self::VT_REASON, false)?
+     .visit_field::<u64>("size", Self::VT_SIZE, false)?
+     .finish();
+    Ok(())
+  }
+}
+pub struct EventRejectedEventArgs<'a> {
+    pub reason: Option<flatbuffers::WIPOffset<&'a str>>,
+    pub size: u64,
+}
+impl<'a> Default for EventRejectedEventArgs<'a> {
+  #[inline]
+  fn default() -> Self {
+    EventRejectedEventArgs {
+      reason: None,
+      size: 0,
+    }
+  }
+}
+
+pub struct EventRejectedEventBuilder<'a: 'b, 'b> {
+  fbb_: &'b mut flatbuffers::FlatBufferBuilder<'a>,
+  start_: flatbuffers::WIPOffset<flatbuffers::TableUnfinishedWIPOffset>,
+}
+impl<'a: 'b, 'b> EventRejectedEventBuilder<'a, 'b> {
+  #[inline]
+  pub fn add_reason(&mut self, reason: flatbuffers::WIPOffset<&'b  str>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(EventRejectedEvent::VT_REASON, reason);
+  }
+  #[inline]
+  pub fn add_size(&mut self, size: u64) {
+    self.fbb_.push_slot::<u64>(EventRejectedEvent::VT_SIZE, size, 0);
+  }
+  #[inline]
+  pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a>) -> EventRejectedEventBuilder<'a, 'b> {
+    let start = _fbb.start_table();
+    EventRejectedEventBuilder {
+      fbb_: _fbb,
+      start_: start,
+    }
+  }
+  #[inline]
+  pub fn finish(self) -> flatbuffers::WIPOffset<EventRejectedEvent<'a>> {
+    let o = self.fbb_.end_table(self.start_);
+    flatbuffers::WIPOffset::new(o.value())
+  }
+}
+
+impl core::fmt::Debug for EventRejectedEvent<'_> {
+  fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+    let mut ds = f.debug_struct("EventRejectedEvent");
+      ds.field("reason", &self.reason());
+      ds.field("size", &self.size());
+      ds.finish()
+  }
+}
+pub enum CustomEventOffset {}
+#[derive(Copy, Clone, PartialEq)]
+
+pub struct CustomEvent<'a> {
+  pub _tab: flatbuffers::Table<'a>,
+}
+
+impl<'a> flatbuffers::Follow<'a> for CustomEvent<'a> {
+  type Inner = CustomEvent<'a>;
+  #[inline]
+  fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+    Self { _tab: flatbuffers::Table { buf, loc } }
+  }
+}
+
+impl<'a> CustomEvent<'a> {
+  pub const VT_TYPE_ID: flatbuffers::VOffsetT = 4;
+  pub const VT_PAYLOAD: flatbuffers::VOffsetT = 6;
+
+  #[inline]
+  pub fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
+    CustomEvent { _tab: table }
+  }
+  #[allow(unused_mut)]
+  pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr>(
+    _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr>,
+    args: &'args CustomEventArgs<'args>
+  ) -> flatbuffers::WIPOffset<CustomEvent<'bldr>> {
+    let mut builder = CustomEventBuilder::new(_fbb);
+    if let Some(x) = args.payload { builder.add_payload(x); }
+    builder.add_type_id(args.type_id);
+    builder.finish()
+  }
+
+
+  #[inline]
+  pub fn type_id(&self) -> u32 {
+    self._tab.get::<u32>(CustomEvent::VT_TYPE_ID, Some(0)).unwrap()
+  }
+  #[inline]
+  pub fn payload(&self) -> Option<&'a [u8]> {
+    self._tab.get::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'a, u8>>>(CustomEvent::VT_PAYLOAD, None).map(|v| v.safe_slice())
+  }
+}
+
+impl flatbuffers::Verifiable for CustomEvent<'_> {
+  #[inline]
+  fn run_verifier(
+    v: &mut flatbuffers::Verifier, pos: usize
+  ) -> Result<(), flatbuffers::InvalidFlatbuffer> {
+    use self::flatbuffers::Verifiable;
+    v.visit_table(pos)?
+     .visit_field::<u32>("type_id", Self::VT_TYPE_ID, false)?
+     .visit_field::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'_, u8>>>("payload", Self::VT_PAYLOAD, false)?
+     .finish();
+    Ok(())
+  }
+}
+pub struct CustomEventArgs<'a> {
+    pub type_id: u32,
+    pub payload: Option<flatbuffers::WIPOffset<flatbuffers::Vector<'a, u8>>>,
+}
+impl<'a> Default for CustomEventArgs<'a> {
+  #[inline]
+  fn default() -> Self {
+    CustomEventArgs {
+      type_id: 0,
+      payload: None,
+    }
+  }
+}
+
+pub struct CustomEventBuilder<'a: 'b, 'b> {
+  fbb_: &'b mut flatbuffers::FlatBufferBuilder<'a>,
+  start_: flatbuffers::WIPOffset<flatbuffers::TableUnfinishedWIPOffset>,
+}
+impl<'a: 'b, 'b> CustomEventBuilder<'a, 'b> {
+  #[inline]
+  pub fn add_type_id(&mut self, type_id: u32) {
+    self.fbb_.push_slot::<u32>(CustomEvent::VT_TYPE_ID, type_id, 0);
+  }
+  #[inline]
+  pub fn add_payload(&mut self, payload: flatbuffers::WIPOffset<flatbuffers::Vector<'b , u8>>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(CustomEvent::VT_PAYLOAD, payload);
+  }
+  #[inline]
+  pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a>) -> CustomEventBuilder<'a, 'b> {
+    let start = _fbb.start_table();
+    CustomEventBuilder {
+      fbb_: _fbb,
+      start_: start,
+    }
+  }
+  #[inline]
+  pub fn finish(self) -> flatbuffers::WIPOffset<CustomEvent<'a>> {
+    let o = self.fbb_.end_table(self.start_);
+    flatbuffers::WIPOffset::new(o.value())
+  }
+}
+
+impl core::fmt::Debug for CustomEvent<'_> {
+  fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+    let mut ds = f.debug_struct("CustomEvent");
+      ds.field("type_id", &self.type_id());
+      ds.field("payload", &self.payload());
+      ds.finish()
+  }
+}
+pub enum BackpressureEventOffset {}
+#[derive(Copy, Clone, PartialEq)]
+
+pub struct BackpressureEvent<'a> {
+  pub _tab: flatbuffers::Table<'a>,
+}
+
+impl<'a> flatbuffers::Follow<'a> for BackpressureEvent<'a> {
+  type Inner = BackpressureEvent<'a>;
+  #[inline]
+  fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+    Self { _tab: flatbuffers::Table { buf, loc } }
+  }
+}
+
+impl<'a> BackpressureEvent<'a> {
+  pub const VT_PLUGIN_ID: flatbuffers::VOffsetT = 4;
+  pub const VT_BACKLOG: flatbuffers::VOffsetT = 6;
+
+  #[inline]
+  pub fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
+    BackpressureEvent { _tab: table }
+  }
+  #[allow(unused_mut)]
+  pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr>(
+    _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr>,
+    args: &'args BackpressureEventArgs
+  ) -> flatbuffers::WIPOffset<BackpressureEvent<'bldr>> {
+    let mut builder = BackpressureEventBuilder::new(_fbb);
+    builder.add_backlog(args.backlog);
+    builder.add_plugin_id(args.plugin_id);
+    builder.finish()
+  }
+
+
+  #[inline]
+  pub fn plugin_id(&self) -> i32 {
+    self._tab.get::<i32>(BackpressureEvent::VT_PLUGIN_ID, Some(0)).unwrap()
+  }
+  #[inline]
+  pub fn backlog(&self) -> u64 {
+    self._tab.get::<u64>(BackpressureEvent::VT_BACKLOG, Some(0)).unwrap()
+  }
+}
+
+impl flatbuffers::Verifiable for BackpressureEvent<'_> {
+  #[inline]
+  fn run_verifier(
+    v: &mut flatbuffers::Verifier, pos: usize
+  ) -> Result<(), flatbuffers::InvalidFlatbuffer> {
+    use self::flatbuffers::Verifiable;
+    v.visit_table(pos)?
+     .visit_field::<i32>("plugin_id", Self::VT_PLUGIN_ID, false)?
+     .visit_field::<u64>("backlog", Self::VT_BACKLOG, false)?
+     .finish();
+    Ok(())
+  }
+}
+pub struct BackpressureEventArgs {
+    pub plugin_id: i32,
+    pub backlog: u64,
+}
+impl<'a> Default for BackpressureEventArgs {
+  #[inline]
+  fn default() -> Self {
+    BackpressureEventArgs {
+      plugin_id: 0,
+      backlog: 0,
+    }
+  }
+}
+
+pub struct BackpressureEventBuilder<'a: 'b, 'b> {
+  fbb_: &'b mut flatbuffers::FlatBufferBuilder<'a>,
+  start_: flatbuffers::WIPOffset<flatbuffers::TableUnfinishedWIPOffset>,
+}
+impl<'a: 'b, 'b> BackpressureEventBuilder<'a, 'b> {
+  #[inline]
+  pub fn add_plugin_id(&mut self, plugin_id: i32) {
+    self.fbb_.push_slot::<i32>(BackpressureEvent::VT_PLUGIN_ID, plugin_id, 0);
+  }
+  #[inline]
+  pub fn add_backlog(&mut self, backlog: u64) {
+    self.fbb_.push_slot::<u64>(BackpressureEvent::VT_BACKLOG, backlog, 0);
+  }
+  #[inline]
+  pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a>) -> BackpressureEventBuilder<'a, 'b> {
+    let start = _fbb.start_table();
+    BackpressureEventBuilder {
+      fbb_: _fbb,
+      start_: start,
+    }
+  }
+  #[inline]
+  pub fn finish(self) -> flatbuffers::WIPOffset<BackpressureEvent<'a>> {
+    let o = self.fbb_.end_table(self.start_);
+    flatbuffers::WIPOffset::new(o.value())
+  }
+}
+
+impl core::fmt::Debug for BackpressureEvent<'_> {
+  fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+    let mut ds = f.debug_struct("BackpressureEvent");
+      ds.field("plugin_id", &self.plugin_id());
+      ds.field("backlog", &self.backlog());
+      ds.finish()
+  }
+}
+pub enum BackpressureRelievedEventOffset {}
+#[derive(Copy, Clone, PartialEq)]
+
+pub struct BackpressureRelievedEvent<'a> {
+  pub _tab: flatbuffers::Table<'a>,
+}
+
+impl<'a> flatbuffers::Follow<'a> for BackpressureRelievedEvent<'a> {
+  type Inner = BackpressureRelievedEvent<'a>;
+  #[inline]
+  fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+    Self { _tab: flatbuffers::Table { buf, loc } }
+  }
+}
+
+impl<'a> BackpressureRelievedEvent<'a> {
+  pub const VT_PLUGIN_ID: flatbuffers::VOffsetT = 4;
+  pub const VT_BACKLOG: flatbuffers::VOffsetT = 6;
+
+  #[inline]
+  pub fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
+    BackpressureRelievedEvent { _tab: table }
+  }
+  #[allow(unused_mut)]
+  pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr>(
+    _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr>,
+    args: &'args BackpressureRelievedEventArgs
+  ) -> flatbuffers::WIPOffset<BackpressureRelievedEvent<'bldr>> {
+    let mut builder = BackpressureRelievedEventBuilder::new(_fbb);
+    builder.add_backlog(args.backlog);
+    builder.add_plugin_id(args.plugin_id);
+    builder.finish()
+  }
+
+
+  #[inline]
+  pub fn plugin_id(&self) -> i32 {
+    self._tab.get::<i32>(BackpressureRelievedEvent::VT_PLUGIN_ID, Some(0)).unwrap()
+  }
+  #[inline]
+  pub fn backlog(&self) -> u64 {
+    self._tab.get::<u64>(BackpressureRelievedEvent::VT_BACKLOG, Some(0)).unwrap()
+  }
+}
+
+impl flatbuffers::Verifiable for BackpressureRelievedEvent<'_> {
+  #[inline]
+  fn run_verifier(
+    v: &mut flatbuffers::Verifier, pos: usize
+  ) -> Result<(), flatbuffers::InvalidFlatbuffer> {
+    use self::flatbuffers::Verifiable;
+    v.visit_table(pos)?
+     .visit_field::<i32>("plugin_id", Self::VT_PLUGIN_ID, false)?
+     .visit_field::<u64>("backlog", Self::VT_BACKLOG, false)?
+     .finish();
+    Ok(())
+  }
+}
+pub struct BackpressureRelievedEventArgs {
+    pub plugin_id: i32,
+    pub backlog: u64,
+}
+impl<'a> Default for BackpressureRelievedEventArgs {
+  #[inline]
+  fn default() -> Self {
+    BackpressureRelievedEventArgs {
+      plugin_id: 0,
+      backlog: 0,
+    }
+  }
+}
+
+pub struct BackpressureRelievedEventBuilder<'a: 'b, 'b> {
+  fbb_: &'b mut flatbuffers::FlatBufferBuilder<'a>,
+  start_: flatbuffers::WIPOffset<flatbuffers::TableUnfinishedWIPOffset>,
+}
+impl<'a: 'b, 'b> BackpressureRelievedEventBuilder<'a, 'b> {
+  #[inline]
+  pub fn add_plugin_id(&mut self, plugin_id: i32) {
+    self.fbb_.push_slot::<i32>(BackpressureRelievedEvent::VT_PLUGIN_ID, plugin_id, 0);
+  }
+  #[inline]
+  pub fn add_backlog(&mut self, backlog: u64) {
+    self.fbb_.push_slot::<u64>(BackpressureRelievedEvent::VT_BACKLOG, backlog, 0);
+  }
+  #[inline]
+  pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a>) -> BackpressureRelievedEventBuilder<'a, 'b> {
+    let start = _fbb.start_table();
+    BackpressureRelievedEventBuilder {
+      fbb_: _fbb,
+      start_: start,
+    }
+  }
+  #[inline]
+  pub fn finish(self) -> flatbuffers::WIPOffset<BackpressureRelievedEvent<'a>> {
+    let o = self.fbb_.end_table(self.start_);
+    flatbuffers::WIPOffset::new(o.value())
+  }
+}
+
+impl core::fmt::Debug for BackpressureRelievedEvent<'_> {
+  fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+    let mut ds = f.debug_struct("BackpressureRelievedEvent");
+      ds.field("plugin_id", &self.plugin_id());
+      ds.field("backlog", &self.backlog());
+      ds.finish()
+  }
+}
+pub enum DeadLetterEventOffset {}
+#[derive(Copy, Clone, PartialEq)]
+
+pub struct DeadLetterEvent<'a> {
+  pub _tab: flatbuffers::Table<'a>,
+}
+
+impl<'a> flatbuffers::Follow<'a> for DeadLetterEvent<'a> {
+  type Inner = DeadLetterEvent<'a>;
+  #[inline]
+  fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+    Self { _tab: flatbuffers::Table { buf, loc } }
+  }
+}
+
+impl<'a> DeadLetterEvent<'a> {
+  pub const VT_PLUGIN_ID: flatbuffers::VOffsetT = 4;
+  pub const VT_EVENT_TYPE: flatbuffers::VOffsetT = 6;
+  pub const VT_ENVELOPE: flatbuffers::VOffsetT = 8;
+  pub const VT_PAYLOAD: flatbuffers::VOffsetT = 10;
+  pub const VT_REASON: flatbuffers::VOffsetT = 12;
+
+  #[inline]
+  pub fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
+    DeadLetterEvent { _tab: table }
+  }
+  #[allow(unused_mut)]
+  pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr>(
+    _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr>,
+    args: &'args DeadLetterEventArgs<'args>
+  ) -> flatbuffers::WIPOffset<DeadLetterEvent<'bldr>> {
+    let mut builder = DeadLetterEventBuilder::new(_fbb);
+    if let Some(x) = args.reason { builder.add_reason(x); }
+    if let Some(x) = args.payload { builder.add_payload(x); }
+    if let Some(x) = args.envelope { builder.add_envelope(x); }
+    if let Some(x) = args.event_type { builder.add_event_type(x); }
+    builder.add_plugin_id(args.plugin_id);
+    builder.finish()
+  }
+
+
+  #[inline]
+  pub fn plugin_id(&self) -> i32 {
+    self._tab.get::<i32>(DeadLetterEvent::VT_PLUGIN_ID, Some(0)).unwrap()
+  }
+  #[inline]
+  pub fn event_type(&self) -> Option<&'a str> {
+    self._tab.get::<flatbuffers::ForwardsUOffset<&str>>(DeadLetterEvent::VT_EVENT_TYPE, None)
+  }
+  #[inline]
+  pub fn envelope(&self) -> Option<&'a [u8]> {
+    self._tab.get::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'a, u8>>>(DeadLetterEvent::VT_ENVELOPE, None).map(|v| v.safe_slice())
+  }
+  #[inline]
+  pub fn payload(&self) -> Option<&'a [u8]> {
+    self._tab.get::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'a, u8>>>(DeadLetterEvent::VT_PAYLOAD, None).map(|v| v.safe_slice())
+  }
+  #[inline]
+  pub fn reason(&self) -> Option<&'a str> {
+    self._tab.get::<flatbuffers::ForwardsUOffset<&str>>(DeadLetterEvent::VT_REASON, None)
+  }
+}
+
+impl flatbuffers::Verifiable for DeadLetterEvent<'_> {
+  #[inline]
+  fn run_verifier(
+    v: &mut flatbuffers::Verifier, pos: usize
+  ) -> Result<(), flatbuffers::InvalidFlatbuffer> {
+    use self::flatbuffers::Verifiable;
+    v.visit_table(pos)?
+     .visit_field::<i32>("plugin_id", Self::VT_PLUGIN_ID, false)?
+     .visit_field::<flatbuffers::ForwardsUOffset<&str>>("event_type", Self::VT_EVENT_TYPE, false)?
+     .visit_field::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'_, u8>>>("envelope", Self::VT_ENVELOPE, false)?
+     .visit_field::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'_, u8>>>("payload", Self::VT_PAYLOAD, false)?
+     .visit_field::<flatbuffers::ForwardsUOffset<&str>>("reason", Self::VT_REASON, false)?
+     .finish();
+    Ok(())
+  }
+}
+pub struct DeadLetterEventArgs<'a> {
+    pub plugin_id: i32,
+    pub event_type: Option<flatbuffers::WIPOffset<&'a str>>,
+    pub envelope: Option<flatbuffers::WIPOffset<flatbuffers::Vector<'a, u8>>>,
+    pub payload: Option<flatbuffers::WIPOffset<flatbuffers::Vector<'a, u8>>>,
+    pub reason: Option<flatbuffers::WIPOffset<&'a str>>,
+}
+impl<'a> Default for DeadLetterEventArgs<'a> {
+  #[inline]
+  fn default() -> Self {
+    DeadLetterEventArgs {
+      plugin_id: 0,
+      event_type: None,
+      envelope: None,
+      payload: None,
+      reason: None,
+    }
+  }
+}
+
+pub struct DeadLetterEventBuilder<'a: 'b, 'b> {
+  fbb_: &'b mut flatbuffers::FlatBufferBuilder<'a>,
+  start_: flatbuffers::WIPOffset<flatbuffers::TableUnfinishedWIPOffset>,
+}
+impl<'a: 'b, 'b> DeadLetterEventBuilder<'a, 'b> {
+  #[inline]
+  pub fn add_plugin_id(&mut self, plugin_id: i32) {
+    self.fbb_.push_slot::<i32>(DeadLetterEvent::VT_PLUGIN_ID, plugin_id, 0);
+  }
+  #[inline]
+  pub fn add_event_type(&mut self, event_type: flatbuffers::WIPOffset<&'b  str>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(DeadLetterEvent::VT_EVENT_TYPE, event_type);
+  }
+  #[inline]
+  pub fn add_envelope(&mut self, envelope: flatbuffers::WIPOffset<flatbuffers::Vector<'b , u8>>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(DeadLetterEvent::VT_ENVELOPE, envelope);
+  }
+  #[inline]
+  pub fn add_payload(&mut self, payload: flatbuffers::WIPOffset<flatbuffers::Vector<'b , u8>>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(DeadLetterEvent::VT_PAYLOAD, payload);
+  }
+  #[inline]
+  pub fn add_reason(&mut self, reason: flatbuffers::WIPOffset<&'b  str>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(DeadLetterEvent::VT_REASON, reason);
+  }
+  #[inline]
+  pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a>) -> DeadLetterEventBuilder<'a, 'b> {
+    let start = _fbb.start_table();
+    DeadLetterEventBuilder {
+      fbb_: _fbb,
+      start_: start,
+    }
+  }
+  #[inline]
+  pub fn finish(self) -> flatbuffers::WIPOffset<DeadLetterEvent<'a>> {
+    let o = self.fbb_.end_table(self.start_);
+    flatbuffers::WIPOffset::new(o.value())
+  }
+}
+
+impl core::fmt::Debug for DeadLetterEvent<'_> {
+  fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+    let mut ds = f.debug_struct("DeadLetterEvent");
+      ds.field("plugin_id", &self.plugin_id());
+      ds.field("event_type", &self.event_type());
+      ds.field("envelope", &self.envelope());
+      ds.field("payload", &self.payload());
+      ds.field("reason", &self.reason());
+      ds.finish()
+  }
+}
+pub enum SyncProbeEventOffset {}
+#[derive(Copy, Clone, PartialEq)]
+
+pub struct SyncProbeEvent<'a> {
+  pub _tab: flatbuffers::Table<'a>,
+}
+
+impl<'a> flatbuffers::Follow<'a> for SyncProbeEvent<'a> {
+  type Inner = SyncProbeEvent<'a>;
+  #[inline]
+  fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+    Self { _tab: flatbuffers::Table { buf, loc } }
+  }
+}
+
+impl<'a> SyncProbeEvent<'a> {
+  pub const VT_SEQ: flatbuffers::VOffsetT = 4;
+
+  #[inline]
+  pub fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
+    SyncProbeEvent { _tab: table }
+  }
+  #[allow(unused_mut)]
+  pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr>(
+    _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr>,
+    args: &'args SyncProbeEventArgs
+  ) -> flatbuffers::WIPOffset<SyncProbeEvent<'bldr>> {
+    let mut builder = SyncProbeEventBuilder::new(_fbb);
+    builder.add_seq(args.seq);
+    builder.finish()
+  }
+
+
+  #[inline]
+  pub fn seq(&self) -> u64 {
+    self._tab.get::<u64>(SyncProbeEvent::VT_SEQ, Some(0)).unwrap()
+  }
+}
+
+impl flatbuffers::Verifiable for SyncProbeEvent<'_> {
+  #[inline]
+  fn run_verifier(
+    v: &mut flatbuffers::Verifier, pos: usize
+  ) -> Result<(), flatbuffers::InvalidFlatbuffer> {
+    use self::flatbuffers::Verifiable;
+    v.visit_table(pos)?
+     .visit_field::<u64>("seq", Self::VT_SEQ, false)?
+     .finish();
+    Ok(())
+  }
+}
+pub struct SyncProbeEventArgs {
+    pub seq: u64,
+}
+impl<'a> Default for SyncProbeEventArgs {
+  #[inline]
+  fn default() -> Self {
+    SyncProbeEventArgs {
+      seq: 0,
+    }
+  }
+}
+
+pub struct SyncProbeEventBuilder<'a: 'b, 'b> {
+  fbb_: &'b mut flatbuffers::FlatBufferBuilder<'a>,
+  start_: flatbuffers::WIPOffset<flatbuffers::TableUnfinishedWIPOffset>,
+}
+impl<'a: 'b, 'b> SyncProbeEventBuilder<'a, 'b> {
+  #[inline]
+  pub fn add_seq(&mut self, seq: u64) {
+    self.fbb_.push_slot::<u64>(SyncProbeEvent::VT_SEQ, seq, 0);
+  }
+  #[inline]
+  pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a>) -> SyncProbeEventBuilder<'a, 'b> {
+    let start = _fbb.start_table();
+    SyncProbeEventBuilder {
+      fbb_: _fbb,
+      start_: start,
+    }
+  }
+  #[inline]
+  pub fn finish(self) -> flatbuffers::WIPOffset<SyncProbeEvent<'a>> {
+    let o = self.fbb_.end_table(self.start_);
+    flatbuffers::WIPOffset::new(o.value())
+  }
+}
+
+impl core::fmt::Debug for SyncProbeEvent<'_> {
+  fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+    let mut ds = f.debug_struct("SyncProbeEvent");
+      ds.field("seq", &self.seq());
+      ds.finish()
+  }
+}
+pub enum EngineStartedEventOffset {}
+#[derive(Copy, Clone, PartialEq)]
+
+pub struct EngineStartedEvent<'a> {
+  pub _tab: flatbuffers::Table<'a>,
+}
+
+impl<'a> flatbuffers::Follow<'a> for EngineStartedEvent<'a> {
+  type Inner = EngineStartedEvent<'a>;
+  #[inline]
+  fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+    Self { _tab: flatbuffers::Table { buf, loc } }
+  }
+}
+
+impl<'a> EngineStartedEvent<'a> {
+  pub const VT_ENGINE_ID: flatbuffers::VOffsetT = 4;
+  pub const VT_PROTOCOL_VERSION: flatbuffers::VOffsetT = 6;
+  pub const VT_STARTED_AT_MS: flatbuffers::VOffsetT = 8;
+
+  #[inline]
+  pub fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
+    EngineStartedEvent { _tab: table }
+  }
+  #[allow(unused_mut)]
+  pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr>(
+    _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr>,
+    args: &'args EngineStartedEventArgs
+  ) -> flatbuffers::WIPOffset<EngineStartedEvent<'bldr>> {
+    let mut builder = EngineStartedEventBuilder::new(_fbb);
+    builder.add_started_at_ms(args.started_at_ms);
+    builder.add_engine_id(args.engine_id);
+    builder.add_protocol_version(args.protocol_version);
+    builder.finish()
+  }
+
+
+  #[inline]
+  pub fn engine_id(&self) -> u32 {
+    self._tab.get::<u32>(EngineStartedEvent::VT_ENGINE_ID, Some(0)).unwrap()
+  }
+  #[inline]
+  pub fn protocol_version(&self) -> u16 {
+    self._tab.get::<u16>(EngineStartedEvent::VT_PROTOCOL_VERSION, Some(0)).unwrap()
+  }
+  #[inline]
+  pub fn started_at_ms(&self) -> u64 {
+    self._tab.get::<u64>(EngineStartedEvent::VT_STARTED_AT_MS, Some(0)).unwrap()
+  }
+}
+
+impl flatbuffers::Verifiable for EngineStartedEvent<'_> {
+  #[inline]
+  fn run_verifier(
+    v: &mut flatbuffers::Verifier, pos: usize
+  ) -> Result<(), flatbuffers::InvalidFlatbuffer> {
+    use self::flatbuffers::Verifiable;
+    v.visit_table(pos)?
+     .visit_field::<u32>("engine_id", Self::VT_ENGINE_ID, false)?
+     .visit_field::<u16>("protocol_version", Self::VT_PROTOCOL_VERSION, false)?
+     .visit_field::<u64>("started_at_ms", Self::VT_STARTED_AT_MS, false)?
+     .finish();
+    Ok(())
+  }
+}
+pub struct EngineStartedEventArgs {
+    pub engine_id: u32,
+    pub protocol_version: u16,
+    pub started_at_ms: u64,
+}
+impl<'a> Default for EngineStartedEventArgs {
+  #[inline]
+  fn default() -> Self {
+    EngineStartedEventArgs {
+      engine_id: 0,
+      protocol_version: 0,
+      started_at_ms: 0,
+    }
+  }
+}
+
+pub struct EngineStartedEventBuilder<'a: 'b, 'b> {
+  fbb_: &'b mut flatbuffers::FlatBufferBuilder<'a>,
+  start_: flatbuffers::WIPOffset<flatbuffers::TableUnfinishedWIPOffset>,
+}
+impl<'a: 'b, 'b> EngineStartedEventBuilder<'a, 'b> {
+  #[inline]
+  pub fn add_engine_id(&mut self, engine_id: u32) {
+    self.fbb_.push_slot::<u32>(EngineStartedEvent::VT_ENGINE_ID, engine_id, 0);
+  }
+  #[inline]
+  pub fn add_protocol_version(&mut self, protocol_version: u16) {
+    self.fbb_.push_slot::<u16>(EngineStartedEvent::VT_PROTOCOL_VERSION, protocol_version, 0);
+  }
+  #[inline]
+  pub fn add_started_at_ms(&mut self, started_at_ms: u64) {
+    self.fbb_.push_slot::<u64>(EngineStartedEvent::VT_STARTED_AT_MS, started_at_ms, 0);
+  }
+  #[inline]
+  pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a>) -> EngineStartedEventBuilder<'a, 'b> {
+    let start = _fbb.start_table();
+    EngineStartedEventBuilder {
+      fbb_: _fbb,
+      start_: start,
+    }
+  }
+  #[inline]
+  pub fn finish(self) -> flatbuffers::WIPOffset<EngineStartedEvent<'a>> {
+    let o = self.fbb_.end_table(self.start_);
+    flatbuffers::WIPOffset::new(o.value())
+  }
+}
+
+impl core::fmt::Debug for EngineStartedEvent<'_> {
+  fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+    let mut ds = f.debug_struct("EngineStartedEvent");
+      ds.field("engine_id", &self.engine_id());
+      ds.field("protocol_version", &self.protocol_version());
+      ds.field("started_at_ms", &self.started_at_ms());
+      ds.finish()
+  }
+}
+pub enum EngineStoppingEventOffset {}
+#[derive(Copy, Clone, PartialEq)]
+
+pub struct EngineStoppingEvent<'a> {
+  pub _tab: flatbuffers::Table<'a>,
+}
+
+impl<'a> flatbuffers::Follow<'a> for EngineStoppingEvent<'a> {
+  type Inner = EngineStoppingEvent<'a>;
+  #[inline]
+  fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+    Self { _tab: flatbuffers::Table { buf, loc } }
+  }
+}
+
+impl<'a> EngineStoppingEvent<'a> {
+  pub const VT_REASON: flatbuffers::VOffsetT = 4;
+
+  #[inline]
+  pub fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
+    EngineStoppingEvent { _tab: table }
+  }
+  #[allow(unused_mut)]
+  pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr>(
+    _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr>,
+    args: &'args EngineStoppingEventArgs<'args>
+  ) -> flatbuffers::WIPOffset<EngineStoppingEvent<'bldr>> {
+    let mut builder = EngineStoppingEventBuilder::new(_fbb);
+    if let Some(x) = args.reason { builder.add_reason(x); }
+    builder.finish()
+  }
+
+
+  #[inline]
+  pub fn reason(&self) -> Option<&'a str> {
+    self._tab.get::<flatbuffers::ForwardsUOffset<&str>>(EngineStoppingEvent::VT_REASON, None)
+  }
+}
+
+impl flatbuffers::Verifiable for EngineStoppingEvent<'_> {
+  #[inline]
+  fn run_verifier(
+    v: &mut flatbuffers::Verifier, pos: usize
+  ) -> Result<(), flatbuffers::InvalidFlatbuffer> {
+    use self::flatbuffers::Verifiable;
+    v.visit_table(pos)?
+     .visit_field::<flatbuffers::ForwardsUOffset<&str>>("reason", Self::VT_REASON, false)?
+     .finish();
+    Ok(())
+  }
+}
+pub struct EngineStoppingEventArgs<'a> {
+    pub reason: Option<flatbuffers::WIPOffset<&'a str>>,
+}
+impl<'a> Default for EngineStoppingEventArgs<'a> {
+  #[inline]
+  fn default() -> Self {
+    EngineStoppingEventArgs {
+      reason: None,
+    }
+  }
+}
+
+pub struct EngineStoppingEventBuilder<'a: 'b, 'b> {
+  fbb_: &'b mut flatbuffers::FlatBufferBuilder<'a>,
+  start_: flatbuffers::WIPOffset<flatbuffers::TableUnfinishedWIPOffset>,
+}
+impl<'a: 'b, 'b> EngineStoppingEventBuilder<'a, 'b> {
+  #[inline]
+  pub fn add_reason(&mut self, reason: flatbuffers::WIPOffset<&'b  str>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(EngineStoppingEvent::VT_REASON, reason);
+  }
+  #[inline]
+  pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a>) -> EngineStoppingEventBuilder<'a, 'b> {
+    let start = _fbb.start_table();
+    EngineStoppingEventBuilder {
+      fbb_: _fbb,
+      start_: start,
+    }
+  }
+  #[inline]
+  pub fn finish(self) -> flatbuffers::WIPOffset<EngineStoppingEvent<'a>> {
+    let o = self.fbb_.end_table(self.start_);
+    flatbuffers::WIPOffset::new(o.value())
+  }
+}
+
+impl core::fmt::Debug for EngineStoppingEvent<'_> {
+  fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+    let mut ds = f.debug_struct("EngineStoppingEvent");
+      ds.field("reason", &self.reason());
+      ds.finish()
+  }
+}
+pub enum PluginSkippedEventOffset {}
+#[derive(Copy, Clone, PartialEq)]
+
+pub struct PluginSkippedEvent<'a> {
+  pub _tab: flatbuffers::Table<'a>,
+}
+
+impl<'a> flatbuffers::Follow<'a> for PluginSkippedEvent<'a> {
+  type Inner = PluginSkippedEvent<'a>;
+  #[inline]
+  fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+    Self { _tab: flatbuffers::Table { buf, loc } }
+  }
+}
+
+impl<'a> PluginSkippedEvent<'a> {
+  pub const VT_PLUGIN_ID: flatbuffers::VOffsetT = 4;
+  pub const VT_REASON: flatbuffers::VOffsetT = 6;
+
+  #[inline]
+  pub fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
+    PluginSkippedEvent { _tab: table }
+  }
+  #[allow(unused_mut)]
+  pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr>(
+    _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr>,
+    args: &'args PluginSkippedEventArgs<'args>
+  ) -> flatbuffers::WIPOffset<PluginSkippedEvent<'bldr>> {
+    let mut builder = PluginSkippedEventBuilder::new(_fbb);
+    if let Some(x) = args.reason { builder.add_reason(x); }
+    builder.add_plugin_id(args.plugin_id);
+    builder.finish()
+  }
+
+
+  #[inline]
+  pub fn plugin_id(&self) -> i32 {
+    self._tab.get::<i32>(PluginSkippedEvent::VT_PLUGIN_ID, Some(0)).unwrap()
+  }
+  #[inline]
+  pub fn reason(&self) -> Option<&'a str> {
+    self._tab.get::<flatbuffers::ForwardsUOffset<&str>>(PluginSkippedEvent::VT_REASON, None)
+  }
+}
+
+impl flatbuffers::Verifiable for PluginSkippedEvent<'_> {
+  #[inline]
+  fn run_verifier(
+    v: &mut flatbuffers::Verifier, pos: usize
+  ) -> Result<(), flatbuffers::InvalidFlatbuffer> {
+    use self::flatbuffers::Verifiable;
+    v.visit_table(pos)?
+     .visit_field::<i32>("plugin_id", Self::VT_PLUGIN_ID, false)?
+     .visit_field::<flatbuffers::ForwardsUOffset<&str>>("reason", Self::VT_REASON, false)?
+     .finish();
+    Ok(())
+  }
+}
+pub struct PluginSkippedEventArgs<'a> {
+    pub plugin_id: i32,
+    pub reason: Option<flatbuffers::WIPOffset<&'a str>>,
+}
+impl<'a> Default for PluginSkippedEventArgs<'a> {
+  #[inline]
+  fn default() -> Self {
+    PluginSkippedEventArgs {
+      plugin_id: 0,
+      reason: None,
+    }
+  }
+}
+
+pub struct PluginSkippedEventBuilder<'a: 'b, 'b> {
+  fbb_: &'b mut flatbuffers::FlatBufferBuilder<'a>,
+  start_: flatbuffers::WIPOffset<flatbuffers::TableUnfinishedWIPOffset>,
+}
+impl<'a: 'b, 'b> PluginSkippedEventBuilder<'a, 'b> {
+  #[inline]
+  pub fn add_plugin_id(&mut self, plugin_id: i32) {
+    self.fbb_.push_slot::<i32>(PluginSkippedEvent::VT_PLUGIN_ID, plugin_id, 0);
+  }
+  #[inline]
+  pub fn add_reason(&mut self, reason: flatbuffers::WIPOffset<&'b  str>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(PluginSkippedEvent::VT_REASON, reason);
+  }
+  #[inline]
+  pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a>) -> PluginSkippedEventBuilder<'a, 'b> {
+    let start = _fbb.start_table();
+    PluginSkippedEventBuilder {
+      fbb_: _fbb,
+      start_: start,
+    }
+  }
+  #[inline]
+  pub fn finish(self) -> flatbuffers::WIPOffset<PluginSkippedEvent<'a>> {
+    let o = self.fbb_.end_table(self.start_);
+    flatbuffers::WIPOffset::new(o.value())
+  }
+}
+
+impl core::fmt::Debug for PluginSkippedEvent<'_> {
+  fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+    let mut ds = f.debug_struct("PluginSkippedEvent");
+      ds.field("plugin_id", &self.plugin_id());
+      ds.field("reason", &self.reason());
+      ds.finish()
+  }
+}
+pub enum TickEventOffset {}
+#[derive(Copy, Clone, PartialEq)]
+
+pub struct TickEvent<'a> {
+  pub _tab: flatbuffers::Table<'a>,
+}
+
+impl<'a> flatbuffers::Follow<'a> for TickEvent<'a> {
+  type Inner = TickEvent<'a>;
+  #[inline]
+  fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+    Self { _tab: flatbuffers::Table { buf, loc } }
+  }
+}
+
+impl<'a> TickEvent<'a> {
+  pub const VT_SEQ: flatbuffers::VOffsetT = 4;
+  pub const VT_SCHEDULED_AT_MS: flatbuffers::VOffsetT = 6;
+
+  #[inline]
+  pub fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
+    TickEvent { _tab: table }
+  }
+  #[allow(unused_mut)]
+  pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr>(
+    _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr>,
+    args: &'args TickEventArgs
+  ) -> flatbuffers::WIPOffset<TickEvent<'bldr>> {
+    let mut builder = TickEventBuilder::new(_fbb);
+    builder.add_scheduled_at_ms(args.scheduled_at_ms);
+    builder.add_seq(args.seq);
+    builder.finish()
+  }
+
+
+  #[inline]
+  pub fn seq(&self) -> u64 {
+    self._tab.get::<u64>(TickEvent::VT_SEQ, Some(0)).unwrap()
+  }
+  #[inline]
+  pub fn scheduled_at_ms(&self) -> u64 {
+    self._tab.get::<u64>(TickEvent::VT_SCHEDULED_AT_MS, Some(0)).unwrap()
+  }
+}
+
+impl flatbuffers::Verifiable for TickEvent<'_> {
+  #[inline]
+  fn run_verifier(
+    v: &mut flatbuffers::Verifier, pos: usize
+  ) -> Result<(), flatbuffers::InvalidFlatbuffer> {
+    use self::flatbuffers::Verifiable;
+    v.visit_table(pos)?
+     .visit_field::<u64>("seq", Self::VT_SEQ, false)?
+     .visit_field::<u64>("scheduled_at_ms", Self::VT_SCHEDULED_AT_MS, false)?
+     .finish();
+    Ok(())
+  }
+}
+pub struct TickEventArgs {
+    pub seq: u64,
+    pub scheduled_at_ms: u64,
+}
+impl<'a> Default for TickEventArgs {
+  #[inline]
+  fn default() -> Self {
+    TickEventArgs {
+      seq: 0,
+      scheduled_at_ms: 0,
+    }
+  }
+}
+
+pub struct TickEventBuilder<'a: 'b, 'b> {
+  fbb_: &'b mut flatbuffers::FlatBufferBuilder<'a>,
+  start_: flatbuffers::WIPOffset<flatbuffers::TableUnfinishedWIPOffset>,
+}
+impl<'a: 'b, 'b> TickEventBuilder<'a, 'b> {
+  #[inline]
+  pub fn add_seq(&mut self, seq: u64) {
+    self.fbb_.push_slot::<u64>(TickEvent::VT_SEQ, seq, 0);
+  }
+  #[inline]
+  pub fn add_scheduled_at_ms(&mut self, scheduled_at_ms: u64) {
+    self.fbb_.push_slot::<u64>(TickEvent::VT_SCHEDULED_AT_MS, scheduled_at_ms, 0);
+  }
+  #[inline]
+  pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a>) -> TickEventBuilder<'a, 'b> {
+    let start = _fbb.start_table();
+    TickEventBuilder {
+      fbb_: _fbb,
+      start_: start,
+    }
+  }
+  #[inline]
+  pub fn finish(self) -> flatbuffers::WIPOffset<TickEvent<'a>> {
+    let o = self.fbb_.end_table(self.start_);
+    flatbuffers::WIPOffset::new(o.value())
+  }
+}
+
+impl core::fmt::Debug for TickEvent<'_> {
+  fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+    let mut ds = f.debug_struct("TickEvent");
+      ds.field("seq", &self.seq());
+      ds.field("scheduled_at_ms", &self.scheduled_at_ms());
+      ds.finish()
+  }
+}
+pub enum EventOffset {}
+#[derive(Copy, Clone, PartialEq)]
+
+pub struct Event<'a> {
+  pub _tab: flatbuffers::Table<'a>,
+}
+
+impl<'a> flatbuffers::Follow<'a> for Event<'a> {
+  type Inner = Event<'a>;
+  #[inline]
+  fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+    Self { _tab: flatbuffers::Table { buf, loc } }
+  }
+}
+
+impl<'a> Event<'a> {
+  pub const VT_EVENT_TYPE: flatbuffers::VOffsetT = 4;
+  pub const VT_EVENT: flatbuffers::VOffsetT = 6;
+
+  #[inline]
+  pub fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
+    Event { _tab: table }
+  }
+  #[allow(unused_mut)]
+  pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr>(
+    _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr>,
+    args: &'args EventArgs
+  ) -> flatbuffers::WIPOffset<Event<'bldr>> {
+    let mut builder = EventBuilder::new(_fbb);
+    if let Some(x) = args.event { builder.add_event(x); }
+    builder.add_event_type(args.event_type);
+    builder.finish()
+  }
+
+
+  #[inline]
+  pub fn event_type(&self) -> EventType {
+    self._tab.get::<EventType>(Event::VT_EVENT_TYPE, Some(EventType::NONE)).unwrap()
+  }
+  #[inline]
+  pub fn event(&self) -> Option<flatbuffers::Table<'a>> {
+    self._tab.get::<flatbuffers::ForwardsUOffset<flatbuffers::Table<'a>>>(Event::VT_EVENT, None)
+  }
+  #[inline]
+  #[allow(non_snake_case)]
+  pub fn event_as_new_image_event(&self) -> Option<NewImageEvent<'a>> {
+    if self.event_type() == EventType::NewImageEvent {
+      self.event().map(NewImageEvent::init_from_table)
+    } else {
+      None
+    }
+  }
+
+  #[inline]
+  #[allow(non_snake_case)]
+  pub fn event_as_image_scored_event(&self) -> Option<ImageScoredEvent<'a>> {
+    if self.event_type() == EventType::ImageScoredEvent {
+      self.event().map(ImageScoredEvent::init_from_table)
+    } else {
+      None
+    }
+  }
+
+  #[inline]
+  #[allow(non_snake_case)]
+  pub fn event_as_image_stored_event(&self) -> Option<ImageStoredEvent<'a>> {
+    if self.event_type() == EventType::ImageStoredEvent {
+      self.event().map(ImageStoredEvent::init_from_table)
+    } else {
+      None
+    }
+  }
+
+  #[inline]
+  #[allow(non_snake_case)]
+  pub fn event_as_image_deleted_event(&self) -> Option<ImageDeletedEvent<'a>> {
+    if self.event_type() == EventType::ImageDeletedEvent {
+      self.event().map(ImageDeletedEvent::init_from_table)
+    } else {
+      None
+    }
+  }
+
+  #[inline]
+  #[allow(non_snake_case)]
+  pub fn event_as_plugin_terminate_event(&self) -> Option<PluginTerminateEvent<'a>> {
+    if self.event_type() == EventType::PluginTerminateEvent {
+      self.event().map(PluginTerminateEvent::init_from_table)
+    } else {
+      None
+    }
+  }
+
+  #[inline]
+  #[allow(non_snake_case)]
+  pub fn event_as_plugin_failed_event(&self) -> Option<PluginFailedEvent<'a>> {
+    if self.event_type() == EventType::PluginFailedEvent {
+      self.event().map(PluginFailedEvent::init_from_table)
+    } else {
+      None
+    }
+  }
+
+  #[inline]
+  #[allow(non_snake_case)]
+  pub fn event_as_plugin_restarted_event(&self) -> Option<PluginRestartedEvent<'a>> {
+    if self.event_type() == EventType::PluginRestartedEvent {
+      self.event().map(PluginRestartedEvent::init_from_table)
+    } else {
+      None
+    }
+  }
+
+  #[inline]
+  #[allow(non_snake_case)]
+  pub fn event_as_image_score_failed_event(&self) -> Option<ImageScoreFailedEvent<'a>> {
+    if self.event_type() == EventType::ImageScoreFailedEvent {
+      self.event().map(ImageScoreFailedEvent::init_from_table)
+    } else {
+      None
+    }
+  }
+
+  #[inline]
+  #[allow(non_snake_case)]
+  pub fn event_as_image_rejected_event(&self) -> Option<ImageRejectedEvent<'a>> {
+    if self.event_type() == EventType::ImageRejectedEvent {
+      self.event().map(ImageRejectedEvent::init_from_table)
+    } else {
+      None
+    }
+  }
+
+  #[inline]
+  #[allow(non_snake_case)]
+  pub fn event_as_image_store_failed_event(&self) -> Option<ImageStoreFailedEvent<'a>> {
+    if self.event_type() == EventType::ImageStoreFailedEvent {
+      self.event().map(ImageStoreFailedEvent::init_from_table)
+    } else {
+      None
+    }
+  }
+
+  #[inline]
+  #[allow(non_snake_case)]
+  pub fn event_as_image_deleted_request_event(&self) -> Option<ImageDeletedRequestEvent<'a>> {
+    if self.event_type() == EventType::ImageDeletedRequestEvent {
+      self.event().map(ImageDeletedRequestEvent::init_from_table)
+    } else {
+      None
+    }
+  }
+
+  #[inline]
+  #[allow(non_snake_case)]
+  pub fn event_as_metrics_snapshot_event(&self) -> Option<MetricsSnapshotEvent<'a>> {
+    if self.event_type() == EventType::MetricsSnapshotEvent {
+      self.event().map(MetricsSnapshotEvent::init_from_table)
+    } else {
+      None
+    }
+  }
+
+  #[inline]
+  #[allow(non_snake_case)]
+  pub fn event_as_webhook_delivery_failed_event(&self) -> Option<WebhookDeliveryFailedEvent<'a>> {
+    if self.event_type() == EventType::WebhookDeliveryFailedEvent {
+      self.event().map(WebhookDeliveryFailedEvent::init_from_table)
+    } else {
+      None
+    }
+  }
+
+  #[inline]
+  #[allow(non_snake_case)]
+  pub fn event_as_plugin_joined_event(&self) -> Option<PluginJoinedEvent<'a>> {
+    if self.event_type() == EventType::PluginJoinedEvent {
+      self.event().map(PluginJoinedEvent::init_from_table)
+    } else {
+      None
+    }
+  }
+
+  #[inline]
+  #[allow(non_snake_case)]
+  pub fn event_as_plugin_left_event(&self) -> Option<PluginLeftEvent<'a>> {
+    if self.event_type() == EventType::PluginLeftEvent {
+      self.event().map(PluginLeftEvent::init_from_table)
+    } else {
+      None
+    }
+  }
+
+  #[inline]
+  #[allow(non_snake_case)]
+  pub fn event_as_engine_heartbeat_event(&self) -> Option<EngineHeartbeatEvent<'a>> {
+    if self.event_type() == EventType::EngineHeartbeatEvent {
+      self.event().map(EngineHeartbeatEvent::init_from_table)
+    } else {
+      None
+    }
+  }
+
+  #[inline]
+  #[allow(non_snake_case)]
+  pub fn event_as_plugin_heartbeat_event(&self) -> Option<PluginHeartbeatEvent<'a>> {
+    if self.event_type() == EventType::PluginHeartbeatEvent {
+      self.event().map(PluginHeartbeatEvent::init_from_table)
+    } else {
+      None
+    }
+  }
+
+  #[inline]
+  #[allow(non_snake_case)]
+  pub fn event_as_events_dropped_event(&self) -> Option<EventsDroppedEvent<'a>> {
+    if self.event_type() == EventType::EventsDroppedEvent {
+      self.event().map(EventsDroppedEvent::init_from_table)
+    } else {
+      None
+    }
+  }
+
+  #[inline]
+  #[allow(non_snake_case)]
+  pub fn event_as_event_rejected_event(&self) -> Option<EventRejectedEvent<'a>> {
+    if self.event_type() == EventType::EventRejectedEvent {
+      self.event().map(EventRejectedEvent::init_from_table)
+    } else {
+      None
+    }
+  }
+
+  #[inline]
+  #[allow(non_snake_case)]
+  pub fn event_as_custom_event(&self) -> Option<CustomEvent<'a>> {
+    if self.event_type() == EventType::CustomEvent {
+      self.event().map(CustomEvent::init_from_table)
+    } else {
+      None
+    }
+  }
+
+  #[inline]
+  #[allow(non_snake_case)]
+  pub fn event_as_backpressure_event(&self) -> Option<BackpressureEvent<'a>> {
+    if self.event_type() == EventType::BackpressureEvent {
+      self.event().map(BackpressureEvent::init_from_table)
+    } else {
+      None
+    }
+  }
+
+  #[inline]
+  #[allow(non_snake_case)]
+  pub fn event_as_backpressure_relieved_event(&self) -> Option<BackpressureRelievedEvent<'a>> {
+    if self.event_type() == EventType::BackpressureRelievedEvent {
+      self.event().map(BackpressureRelievedEvent::init_from_table)
+    } else {
+      None
+    }
+  }
+
+  #[inline]
+  #[allow(non_snake_case)]
+  pub fn event_as_dead_letter_event(&self) -> Option<DeadLetterEvent<'a>> {
+    if self.event_type() == EventType::DeadLetterEvent {
+      self.event().map(DeadLetterEvent::init_from_table)
+    } else {
+      None
+    }
+  }
+
+  #[inline]
+  #[allow(non_snake_case)]
+  pub fn event_as_sync_probe_event(&self) -> Option<SyncProbeEvent<'a>> {
+    if self.event_type() == EventType::SyncProbeEvent {
+      self.event().map(SyncProbeEvent::init_from_table)
+    } else {
+      None
+    }
+  }
+
+  #[inline]
+  #[allow(non_snake_case)]
+  pub fn event_as_engine_started_event(&self) -> Option<EngineStartedEvent<'a>> {
+    if self.event_type() == EventType::EngineStartedEvent {
+      self.event().map(EngineStartedEvent::init_from_table)
+    } else {
+      None
+    }
+  }
+
+  #[inline]
+  #[allow(non_snake_case)]
+  pub fn event_as_engine_stopping_event(&self) -> Option<EngineStoppingEvent<'a>> {
+    if self.event_type() == EventType::EngineStoppingEvent {
+      self.event().map(EngineStoppingEvent::init_from_table)
+    } else {
+      None
+    }
+  }
+
+  #[inline]
+  #[allow(non_snake_case)]
+  pub fn event_as_plugin_skipped_event(&self) -> Option<PluginSkippedEvent<'a>> {
+    if self.event_type() == EventType::PluginSkippedEvent {
+      self.event().map(PluginSkippedEvent::init_from_table)
+    } else {
+      None
+    }
+  }
+
+  #[inline]
+  #[allow(non_snake_case)]
+  pub fn event_as_tick_event(&self) -> Option<TickEvent<'a>> {
+    if self.event_type() == EventType::TickEvent {
+      self.event().map(TickEvent::init_from_table)
+    } else {
+      None
+    }
+  }
+
+}
+
+impl flatbuffers::Verifiable for Event<'_> {
+  #[inline]
+  fn run_verifier(
+    v: &mut flatbuffers::Verifier, pos: usize
+  ) -> Result<(), flatbuffers::InvalidFlatbuffer> {
+    use self::flatbuffers::Verifiable;
+    v.visit_table(pos)?
+     .visit_union::<EventType, _>("event_type", Self::VT_EVENT_TYPE, "event", Self::VT_EVENT, false, |key, v, pos| {
+        match key {
+          EventType::NewImageEvent => v.verify_union_variant::<flatbuffers::ForwardsUOffset<NewImageEvent>>("EventType::NewImageEvent", pos),
+          EventType::ImageScoredEvent => v.verify_union_variant::<flatbuffers::ForwardsUOffset<ImageScoredEvent>>("EventType::ImageScoredEvent", pos),
+          EventType::ImageStoredEvent => v.verify_union_variant::<flatbuffers::ForwardsUOffset<ImageStoredEvent>>("EventType::ImageStoredEvent", pos),
+          EventType::ImageDeletedEvent => v.verify_union_variant::<flatbuffers::ForwardsUOffset<ImageDeletedEvent>>("EventType::ImageDeletedEvent", pos),
+          EventType::PluginTerminateEvent => v.verify_union_variant::<flatbuffers::ForwardsUOffset<PluginTerminateEvent>>("EventType::PluginTerminateEvent", pos),
+          EventType::PluginFailedEvent => v.verify_union_variant::<flatbuffers::ForwardsUOffset<PluginFailedEvent>>("EventType::PluginFailedEvent", pos),
+          EventType::PluginRestartedEvent => v.verify_union_variant::<flatbuffers::ForwardsUOffset<PluginRestartedEvent>>("EventType::PluginRestartedEvent", pos),
+          EventType::ImageScoreFailedEvent => v.verify_union_variant::<flatbuffers::ForwardsUOffset<ImageScoreFailedEvent>>("EventType::ImageScoreFailedEvent", pos),
+          EventType::ImageRejectedEvent => v.verify_union_variant::<flatbuffers::ForwardsUOffset<ImageRejectedEvent>>("EventType::ImageRejectedEvent", pos),
+          EventType::ImageStoreFailedEvent => v.verify_union_variant::<flatbuffers::ForwardsUOffset<ImageStoreFailedEvent>>("EventType::ImageStoreFailedEvent", pos),
+          EventType::ImageDeletedRequestEvent => v.verify_union_variant::<flatbuffers::ForwardsUOffset<ImageDeletedRequestEvent>>("EventType::ImageDeletedRequestEvent", pos),
+          EventType::MetricsSnapshotEvent => v.verify_union_variant::<flatbuffers::ForwardsUOffset<MetricsSnapshotEvent>>("EventType::MetricsSnapshotEvent", pos),
+          EventType::WebhookDeliveryFailedEvent => v.verify_union_variant::<flatbuffers::ForwardsUOffset<WebhookDeliveryFailedEvent>>("EventType::WebhookDeliveryFailedEvent", pos),
+          EventType::PluginJoinedEvent => v.verify_union_variant::<flatbuffers::ForwardsUOffset<PluginJoinedEvent>>("EventType::PluginJoinedEvent", pos),
+          EventType::PluginLeftEvent => v.verify_union_variant::<flatbuffers::ForwardsUOffset<PluginLeftEvent>>("EventType::PluginLeftEvent", pos),
+          EventType::EngineHeartbeatEvent => v.verify_union_variant::<flatbuffers::ForwardsUOffset<EngineHeartbeatEvent>>("EventType::EngineHeartbeatEvent", pos),
+          EventType::PluginHeartbeatEvent => v.verify_union_variant::<flatbuffers::ForwardsUOffset<PluginHeartbeatEvent>>("EventType::PluginHeartbeatEvent", pos),
+          EventType::EventsDroppedEvent => v.verify_union_variant::<flatbuffers::ForwardsUOffset<EventsDroppedEvent>>("EventType::EventsDroppedEvent", pos),
+          EventType::EventRejectedEvent => v.verify_union_variant::<flatbuffers::ForwardsUOffset<EventRejectedEvent>>("EventType::EventRejectedEvent", pos),
+          EventType::CustomEvent => v.verify_union_variant::<flatbuffers::ForwardsUOffset<CustomEvent>>("EventType::CustomEvent", pos),
+          EventType::BackpressureEvent => v.verify_union_variant::<flatbuffers::ForwardsUOffset<BackpressureEvent>>("EventType::BackpressureEvent", pos),
+          EventType::BackpressureRelievedEvent => v.verify_union_variant::<flatbuffers::ForwardsUOffset<BackpressureRelievedEvent>>("EventType::BackpressureRelievedEvent", pos),
+          EventType::DeadLetterEvent => v.verify_union_variant::<flatbuffers::ForwardsUOffset<DeadLetterEvent>>("EventType::DeadLetterEvent", pos),
+          EventType::SyncProbeEvent => v.verify_union_variant::<flatbuffers::ForwardsUOffset<SyncProbeEvent>>("EventType::SyncProbeEvent", pos),
+          EventType::EngineStartedEvent => v.verify_union_variant::<flatbuffers::ForwardsUOffset<EngineStartedEvent>>("EventType::EngineStartedEvent", pos),
+          EventType::EngineStoppingEvent => v.verify_union_variant::<flatbuffers::ForwardsUOffset<EngineStoppingEvent>>("EventType::EngineStoppingEvent", pos),
+          EventType::PluginSkippedEvent => v.verify_union_variant::<flatbuffers::ForwardsUOffset<PluginSkippedEvent>>("EventType::PluginSkippedEvent", pos),
+          EventType::TickEvent => v.verify_union_variant::<flatbuffers::ForwardsUOffset<TickEvent>>("EventType::TickEvent", pos),
+          _ => Ok(()),
+        }
+     })?
+     .finish();
+    Ok(())
+  }
+}
+pub struct EventArgs {
+    pub event_type: EventType,
+    pub event: Option<flatbuffers::WIPOffset<flatbuffers::UnionWIPOffset>>,
+}
+impl<'a> Default for EventArgs {
+  #[inline]
+  fn default() -> Self {
+    EventArgs {
+      event_type: EventType::NONE,
+      event: None,
+    }
+  }
+}
+
+pub struct EventBuilder<'a: 'b, 'b> {
+  fbb_: &'b mut flatbuffers::FlatBufferBuilder<'a>,
+  start_: flatbuffers::WIPOffset<flatbuffers::TableUnfinishedWIPOffset>,
+}
+impl<'a: 'b, 'b> EventBuilder<'a, 'b> {
+  #[inline]
+  pub fn add_event_type(&mut self, event_type: EventType) {
+    self.fbb_.push_slot::<EventType>(Event::VT_EVENT_TYPE, event_type, EventType::NONE);
+  }
+  #[inline]
+  pub fn add_event(&mut self, event: flatbuffers::WIPOffset<flatbuffers::UnionWIPOffset>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(Event::VT_EVENT, event);
+  }
+  #[inline]
+  pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a>) -> EventBuilder<'a, 'b> {
+    let start = _fbb.start_table();
+    EventBuilder {
+      fbb_: _fbb,
+      start_: start,
+    }
+  }
+  #[inline]
+  pub fn finish(self) -> flatbuffers::WIPOffset<Event<'a>> {
+    let o = self.fbb_.end_table(self.start_);
+    flatbuffers::WIPOffset::new(o.value())
+  }
+}
+
+impl core::fmt::Debug for Event<'_> {
+  fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+    let mut ds = f.debug_struct("Event");
+      ds.field("event_type", &self.event_type());
+      match self.event_type() {
+        EventType::NewImageEvent => {
+          if let Some(x) = self.event_as_new_image_event() {
+            ds.field("event", &x)
+          } else {
+            ds.field("event", &"InvalidFlatbuffer: Union discriminant does not match value.")
+          }
+        },
+        EventType::ImageScoredEvent => {
+          if let Some(x) = self.event_as_image_scored_event() {
+            ds.field("event", &x)
+          } else {
+            ds.field("event", &"InvalidFlatbuffer: Union discriminant does not match value.")
+          }
+        },
+        EventType::ImageStoredEvent => {
+          if let Some(x) = self.event_as_image_stored_event() {
+            ds.field("event", &x)
+          } else {
+            ds.field("event", &"InvalidFlatbuffer: Union discriminant does not match value.")
+          }
+        },
         EventType::ImageDeletedEvent => {
           if let Some(x) = self.event_as_image_deleted_event() {
             ds.field("event", &x)
@@ -824,6 +4147,174 @@ impl core::fmt::Debug for Event<'_> {
             ds.field("event", &"InvalidFlatbuffer: Union discriminant does not match value.")
           }
         },
+        EventType::PluginTerminateEvent => {
+          if let Some(x) = self.event_as_plugin_terminate_event() {
+            ds.field("event", &x)
+          } else {
+            ds.field("event", &"InvalidFlatbuffer: Union discriminant does not match value.")
+          }
+        },
+        EventType::PluginFailedEvent => {
+          if let Some(x) = self.event_as_plugin_failed_event() {
+            ds.field("event", &x)
+          } else {
+            ds.field("event", &"InvalidFlatbuffer: Union discriminant does not match value.")
+          }
+        },
+        EventType::PluginRestartedEvent => {
+          if let Some(x) = self.event_as_plugin_restarted_event() {
+            ds.field("event", &x)
+          } else {
+            ds.field("event", &"InvalidFlatbuffer: Union discriminant does not match value.")
+          }
+        },
+        EventType::ImageScoreFailedEvent => {
+          if let Some(x) = self.event_as_image_score_failed_event() {
+            ds.field("event", &x)
+          } else {
+            ds.field("event", &"InvalidFlatbuffer: Union discriminant does not match value.")
+          }
+        },
+        EventType::ImageRejectedEvent => {
+          if let Some(x) = self.event_as_image_rejected_event() {
+            ds.field("event", &x)
+          } else {
+            ds.field("event", &"InvalidFlatbuffer: Union discriminant does not match value.")
+          }
+        },
+        EventType::ImageStoreFailedEvent => {
+          if let Some(x) = self.event_as_image_store_failed_event() {
+            ds.field("event", &x)
+          } else {
+            ds.field("event", &"InvalidFlatbuffer: Union discriminant does not match value.")
+          }
+        },
+        EventType::ImageDeletedRequestEvent => {
+          if let Some(x) = self.event_as_image_deleted_request_event() {
+            ds.field("event", &x)
+          } else {
+            ds.field("event", &"InvalidFlatbuffer: Union discriminant does not match value.")
+          }
+        },
+        EventType::MetricsSnapshotEvent => {
+          if let Some(x) = self.event_as_metrics_snapshot_event() {
+            ds.field("event", &x)
+          } else {
+            ds.field("event", &"InvalidFlatbuffer: Union discriminant does not match value.")
+          }
+        },
+        EventType::WebhookDeliveryFailedEvent => {
+          if let Some(x) = self.event_as_webhook_delivery_failed_event() {
+            ds.field("event", &x)
+          } else {
+            ds.field("event", &"InvalidFlatbuffer: Union discriminant does not match value.")
+          }
+        },
+        EventType::PluginJoinedEvent => {
+          if let Some(x) = self.event_as_plugin_joined_event() {
+            ds.field("event", &x)
+          } else {
+            ds.field("event", &"InvalidFlatbuffer: Union discriminant does not match value.")
+          }
+        },
+        EventType::PluginLeftEvent => {
+          if let Some(x) = self.event_as_plugin_left_event() {
+            ds.field("event", &x)
+          } else {
+            ds.field("event", &"InvalidFlatbuffer: Union discriminant does not match value.")
+          }
+        },
+        EventType::EngineHeartbeatEvent => {
+          if let Some(x) = self.event_as_engine_heartbeat_event() {
+            ds.field("event", &x)
+          } else {
+            ds.field("event", &"InvalidFlatbuffer: Union discriminant does not match value.")
+          }
+        },
+        EventType::PluginHeartbeatEvent => {
+          if let Some(x) = self.event_as_plugin_heartbeat_event() {
+            ds.field("event", &x)
+          } else {
+            ds.field("event", &"InvalidFlatbuffer: Union discriminant does not match value.")
+          }
+        },
+        EventType::EventsDroppedEvent => {
+          if let Some(x) = self.event_as_events_dropped_event() {
+            ds.field("event", &x)
+          } else {
+            ds.field("event", &"InvalidFlatbuffer: Union discriminant does not match value.")
+          }
+        },
+        EventType::EventRejectedEvent => {
+          if let Some(x) = self.event_as_event_rejected_event() {
+            ds.field("event", &x)
+          } else {
+            ds.field("event", &"InvalidFlatbuffer: Union discriminant does not match value.")
+          }
+        },
+        EventType::CustomEvent => {
+          if let Some(x) = self.event_as_custom_event() {
+            ds.field("event", &x)
+          } else {
+            ds.field("event", &"InvalidFlatbuffer: Union discriminant does not match value.")
+          }
+        },
+        EventType::BackpressureEvent => {
+          if let Some(x) = self.event_as_backpressure_event() {
+            ds.field("event", &x)
+          } else {
+            ds.field("event", &"InvalidFlatbuffer: Union discriminant does not match value.")
+          }
+        },
+        EventType::BackpressureRelievedEvent => {
+          if let Some(x) = self.event_as_backpressure_relieved_event() {
+            ds.field("event", &x)
+          } else {
+            ds.field("event", &"InvalidFlatbuffer: Union discriminant does not match value.")
+          }
+        },
+        EventType::DeadLetterEvent => {
+          if let Some(x) = self.event_as_dead_letter_event() {
+            ds.field("event", &x)
+          } else {
+            ds.field("event", &"InvalidFlatbuffer: Union discriminant does not match value.")
+          }
+        },
+        EventType::SyncProbeEvent => {
+          if let Some(x) = self.event_as_sync_probe_event() {
+            ds.field("event", &x)
+          } else {
+            ds.field("event", &"InvalidFlatbuffer: Union discriminant does not match value.")
+          }
+        },
+        EventType::EngineStartedEvent => {
+          if let Some(x) = self.event_as_engine_started_event() {
+            ds.field("event", &x)
+          } else {
+            ds.field("event", &"InvalidFlatbuffer: Union discriminant does not match value.")
+          }
+        },
+        EventType::EngineStoppingEvent => {
+          if let Some(x) = self.event_as_engine_stopping_event() {
+            ds.field("event", &x)
+          } else {
+            ds.field("event", &"InvalidFlatbuffer: Union discriminant does not match value.")
+          }
+        },
+        EventType::PluginSkippedEvent => {
+          if let Some(x) = self.event_as_plugin_skipped_event() {
+            ds.field("event", &x)
+          } else {
+            ds.field("event", &"InvalidFlatbuffer: Union discriminant does not match value.")
+          }
+        },
+        EventType::TickEvent => {
+          if let Some(x) = self.event_as_tick_event() {
+            ds.field("event", &x)
+          } else {
+            ds.field("event", &"InvalidFlatbuffer: Union discriminant does not match value.")
+          }
+        },
         _ => {
           let x: Option<()> = None;
           ds.field("event", &x)
@@ -0,0 +1,843 @@
+//! Client for external plugins: plugins running in a process of their own, registered with
+//! `PluginRegistry::register_external`.
+//! `ExternalPluginClient::connect` does what the engine does for the plugins it starts: it
+//! connects a PUB socket to the engine's incoming TCP port and a SUB socket, subscribed to the
+//! plugin's events and the PluginTerminateEvent, to its outgoing TCP port, and syncs with the
+//! engine on its sync port. Events are then published and received with the same framing as
+//! in a `PluginContext`. On the same host, an engine on `Transport::Ipc` is reached over its Unix
+//! domain sockets instead of TCP. An engine binds its TCP ports on the loopback interface unless
+//! configured with `EventEngineBuilder::bind_address` or `expose_external`, so that a client on
+//! another host only reaches one that is; `engine_host` may be an IPv6 literal such as "[::1]".
+//! An engine started with a registration window also takes plugins it has no id for:
+//! `ExternalPluginClient::register` sends the plugin's name and subscriptions, and the engine
+//! checks them and answers with an id and the endpoints to connect to before the plugin syncs.
+//! Once synced, the client sends heartbeats on the sync socket from a thread of its own, until
+//! it is dropped; the engine publishes a PluginLeftEvent for a plugin whose heartbeats stop,
+//! e.g., because its process died.
+//!
+
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use log::{info, warn};
+
+use crate::curve::{make_client, CurveClient};
+use crate::event_engine::{
+    check_subscriptions, confirm_probe, connect, create_socket, is_all_interfaces, subscribed,
+    subscription_filter, EngineConfig, EngineError, UnknownSubscription, SYNC_HEARTBEAT,
+    SYNC_PROBE, SYNC_PROBED, SYNC_READY, SYNC_REGISTER, SYNC_REGISTERED,
+};
+use crate::events::{retry_on_eintr, schema_versions, EventMeta, EventPayload};
+use crate::plugin::{EventMsg, PluginContext, PluginError};
+
+// How long the subscriptions get to reach the engine before the plugin syncs; the engine starts
+// publishing as soon as every plugin has synced, so events would be missed otherwise.
+pub(crate) const SUBSCRIPTION_SETTLE_TIME: Duration = Duration::from_millis(200);
+
+/// The sockets of an external plugin, connected and synced with the engine.
+pub struct ExternalPluginClient {
+    // owns the sockets of `ctx`, so it lives as long as the client
+    _context: zmq::Context,
+    ctx: PluginContext,
+    // the heartbeat thread, which owns the sync socket, and the sender that stops it; both are
+    // taken on drop, so that every socket of the client is closed once it is dropped
+    heartbeats: Option<(Sender<()>, JoinHandle<()>)>,
+}
+
+impl ExternalPluginClient {
+    /// Connect as the external plugin `plugin_id` to an engine on `engine_host` listening on the
+    /// default ports, subscribe to `subscriptions` (e.g., "NewImageEvent") and sync. While the
+    /// engine starts, this blocks until it has synced all its plugins (or for the default sync
+    /// timeout); an optional plugin joining a running engine is answered right away.
+    pub fn connect(
+        engine_host: &str,
+        plugin_id: i32,
+        subscriptions: &[&str],
+    ) -> Result<Self, EngineError> {
+        ExternalPluginClient::connect_with_config(
+            engine_host,
+            &EngineConfig::default(),
+            plugin_id,
+            subscriptions,
+        )
+    }
+
+    /// Like `connect`, for an engine listening on the TCP ports of `config` (or, on
+    /// `Transport::Ipc`, on its ipc sockets, whatever `engine_host` is); the client waits for the
+    /// sync reply for the `sync_timeout` of `config`.
+    pub fn connect_with_config(
+        engine_host: &str,
+        config: &EngineConfig,
+        plugin_id: i32,
+        subscriptions: &[&str],
+    ) -> Result<Self, EngineError> {
+        ExternalPluginClient::connect_as(engine_host, config, None, plugin_id, subscriptions)
+    }
+
+    /// Like `connect_with_config`, for an engine configured for CURVE: the client's sockets are
+    /// CURVE clients with the keys of `curve`, which the engine only lets in if it allows their
+    /// public key.
+    pub fn connect_with_curve(
+        engine_host: &str,
+        config: &EngineConfig,
+        curve: &CurveClient,
+        plugin_id: i32,
+        subscriptions: &[&str],
+    ) -> Result<Self, EngineError> {
+        ExternalPluginClient::connect_as(engine_host, config, Some(curve), plugin_id, subscriptions)
+    }
+
+    fn connect_as(
+        engine_host: &str,
+        config: &EngineConfig,
+        curve: Option<&CurveClient>,
+        plugin_id: i32,
+        subscriptions: &[&str],
+    ) -> Result<Self, EngineError> {
+        // the engine never learns what an external plugin subscribes to, so it is checked here
+        check_subscriptions(config, UnknownSubscription::find(plugin_id, subscriptions))?;
+        let endpoint = |name: &str, port: u16| engine_endpoint(engine_host, config, name, port);
+        let context = zmq::Context::new();
+        let sync = connect_sync_socket(
+            &context,
+            config,
+            curve,
+            &endpoint("sync", config.sync_port),
+            plugin_id,
+        )?;
+        ExternalPluginClient::sync(
+            context,
+            config,
+            curve,
+            sync,
+            plugin_id,
+            &endpoint("incoming", config.incoming_port),
+            &endpoint("outgoing", config.outgoing_port),
+            subscriptions,
+        )
+    }
+
+    /// Register as an external plugin called `name` (a single word, used in the engine's log
+    /// output) with an engine on `engine_host` started with a registration window, using the
+    /// default sync port, while it starts or once it runs. The engine checks `subscriptions`
+    /// and assigns the plugin an id (see `plugin_id`); the client then connects to the
+    /// endpoints the engine gave and syncs.
+    pub fn register(
+        engine_host: &str,
+        name: &str,
+        subscriptions: &[&str],
+    ) -> Result<Self, EngineError> {
+        ExternalPluginClient::register_with_config(
+            engine_host,
+            &EngineConfig::default(),
+            name,
+            subscriptions,
+        )
+    }
+
+    /// Like `register`, for an engine whose sync socket is on the sync port (or, on
+    /// `Transport::Ipc`, the sync ipc socket) of `config`; the client sends heartbeats every
+    /// quarter of its `external_heartbeat_timeout`.
+    pub fn register_with_config(
+        engine_host: &str,
+        config: &EngineConfig,
+        name: &str,
+        subscriptions: &[&str],
+    ) -> Result<Self, EngineError> {
+        ExternalPluginClient::register_as(engine_host, config, None, name, subscriptions)
+    }
+
+    /// Like `register_with_config`, for an engine configured for CURVE; see
+    /// `connect_with_curve`.
+    pub fn register_with_curve(
+        engine_host: &str,
+        config: &EngineConfig,
+        curve: &CurveClient,
+        name: &str,
+        subscriptions: &[&str],
+    ) -> Result<Self, EngineError> {
+        ExternalPluginClient::register_as(engine_host, config, Some(curve), name, subscriptions)
+    }
+
+    fn register_as(
+        engine_host: &str,
+        config: &EngineConfig,
+        curve: Option<&CurveClient>,
+        name: &str,
+        subscriptions: &[&str],
+    ) -> Result<Self, EngineError> {
+        // the plugin has no id until the engine replies; -1 stands for it in errors
+        let endpoint = engine_endpoint(engine_host, config, "sync", config.sync_port);
+        let context = zmq::Context::new();
+        let sync = connect_sync_socket(&context, config, curve, &endpoint, -1)?;
+        let msg = std::iter::once(SYNC_REGISTER)
+            .chain([name])
+            .chain(subscriptions.iter().copied())
+            .collect::<Vec<&str>>()
+            .join(" ");
+        let reply = request(&sync, -1, &msg)?;
+        // "registered <plugin_id> <incoming endpoint> <outgoing endpoint>", with the endpoints
+        // as the engine bound them
+        let registration = match reply.split_whitespace().collect::<Vec<&str>>().as_slice() {
+            [registered, plugin_id, incoming, outgoing] if *registered == SYNC_REGISTERED => {
+                plugin_id.parse::<i32>().ok().map(|plugin_id| {
+                    let on_host = |endpoint: &str| on_engine_host(endpoint, engine_host);
+                    (plugin_id, on_host(incoming), on_host(outgoing))
+                })
+            }
+            _ => None,
+        };
+        let (plugin_id, incoming, outgoing) = registration.ok_or(EngineError::SyncRejected {
+            plugin_id: -1,
+            reply: reply.clone(),
+        })?;
+        info!(plugin_id; "external plugin {} registered as plugin {}", name, plugin_id);
+        ExternalPluginClient::sync(
+            context,
+            config,
+            curve,
+            sync,
+            plugin_id,
+            &incoming,
+            &outgoing,
+            subscriptions,
+        )
+    }
+
+    // Connect the pub and sub sockets of the plugin to the engine's incoming and outgoing
+    // endpoints, sync on `sync` and start sending heartbeats on it.
+    #[allow(clippy::too_many_arguments)]
+    fn sync(
+        context: zmq::Context,
+        config: &EngineConfig,
+        curve: Option<&CurveClient>,
+        sync: zmq::Socket,
+        plugin_id: i32,
+        incoming_endpoint: &str,
+        outgoing_endpoint: &str,
+        subscriptions: &[&str],
+    ) -> Result<Self, EngineError> {
+        let pub_name = "external plugin pub";
+        let pub_socket = create_socket(&context, config, zmq::PUB, pub_name)?;
+        if let Some(curve) = curve {
+            make_client(&pub_socket, pub_name, curve)?;
+        }
+        connect(&pub_socket, incoming_endpoint)?;
+
+        let sub_name = "external plugin sub";
+        let sub_socket = create_socket(&context, config, zmq::SUB, sub_name)?;
+        if let Some(curve) = curve {
+            make_client(&sub_socket, sub_name, curve)?;
+        }
+        // the probes the engine publishes while the plugin syncs go last, as for its own plugins
+        let probe = std::iter::once("SyncProbeEvent");
+        for event_type in std::iter::once("PluginTerminateEvent")
+            .chain(subscribed(subscriptions))
+            .chain(probe)
+        {
+            let filter_bytes = subscription_filter(config, event_type).ok_or_else(|| {
+                EngineError::UnknownEventType {
+                    event_type: event_type.to_string(),
+                }
+            })?;
+            sub_socket
+                .set_subscribe(&filter_bytes)
+                .map_err(|source| EngineError::Socket {
+                    socket: sub_name.to_string(),
+                    source,
+                })?;
+        }
+        connect(&sub_socket, outgoing_endpoint)?;
+        thread::sleep(SUBSCRIPTION_SETTLE_TIME);
+
+        let ready = format!(
+            "{} {} {} {}",
+            SYNC_READY,
+            plugin_id,
+            schema_versions(),
+            SYNC_PROBE
+        );
+        let mut reply = request(&sync, plugin_id, &ready)?;
+        if reply == SYNC_PROBE {
+            let deadline = Instant::now() + config.sync_timeout;
+            confirm_probe(plugin_id, &sub_socket, deadline)
+                .map_err(|source| EngineError::Sync { plugin_id, source })?;
+            reply = request(&sync, plugin_id, &format!("{} {}", SYNC_PROBED, plugin_id))?;
+        }
+        if reply != "ok" {
+            return Err(EngineError::SyncRejected { plugin_id, reply });
+        }
+        info!(plugin_id; "external plugin {} synced with the engine", plugin_id);
+
+        let interval = config.external_heartbeat_timeout / 4;
+        let (stop, stopped) = mpsc::channel();
+        let heartbeat_thread =
+            thread::spawn(move || send_heartbeats(sync, plugin_id, interval, stopped));
+
+        let mut ctx =
+            PluginContext::new(plugin_id, pub_socket, sub_socket).with_subscriptions(subscriptions);
+        ctx.set_max_payload_size(config.max_payload_size);
+        ctx.set_compression(config.compression);
+        ctx.set_codec(config.codec);
+        ctx.set_engine_id(config.engine_id);
+        Ok(ExternalPluginClient {
+            _context: context,
+            ctx,
+            heartbeats: Some((stop, heartbeat_thread)),
+        })
+    }
+
+    /// The id of the plugin, as given to `connect` or assigned by the engine on `register`.
+    pub fn plugin_id(&self) -> i32 {
+        self.ctx.plugin_id
+    }
+
+    /// Publish `event` to the engine; see `PluginContext::publish`.
+    pub fn publish(&mut self, event: &impl EventPayload) -> Result<EventMeta, PluginError> {
+        self.ctx.publish(event)
+    }
+
+    /// Publish `event` in response to the event with envelope `to`; see
+    /// `PluginContext::publish_reply`.
+    pub fn publish_reply(
+        &mut self,
+        to: &EventMeta,
+        event: &impl EventPayload,
+    ) -> Result<EventMeta, PluginError> {
+        self.ctx.publish_reply(to, event)
+    }
+
+    /// Answer the engine heartbeats; see `PluginContext::answer_heartbeats`.
+    pub fn answer_heartbeats(&mut self) -> Result<(), PluginError> {
+        self.ctx.answer_heartbeats()
+    }
+
+    /// Block until the next subscribed event (or the PluginTerminateEvent) arrives.
+    pub fn next_event(&mut self) -> Result<EventMsg, PluginError> {
+        self.ctx.next_event()
+    }
+}
+
+impl Drop for ExternalPluginClient {
+    fn drop(&mut self) {
+        if let Some((stop, heartbeat_thread)) = self.heartbeats.take() {
+            drop(stop);
+            let _ = heartbeat_thread.join();
+        }
+    }
+}
+
+// Send a heartbeat on `sync` every `interval` until the sender of `stopped` is dropped or the
+// engine does not answer a heartbeat with "ok" within the interval.
+fn send_heartbeats(sync: zmq::Socket, plugin_id: i32, interval: Duration, stopped: Receiver<()>) {
+    let _ = sync.set_rcvtimeo(interval.as_millis() as i32);
+    let msg = format!("{} {}", SYNC_HEARTBEAT, plugin_id);
+    while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(interval) {
+        match request(&sync, plugin_id, &msg) {
+            Ok(reply) if reply == "ok" => {}
+            Ok(reply) => {
+                warn!(plugin_id; "external plugin {} stopping heartbeats: {}", plugin_id, reply);
+                return;
+            }
+            Err(e) => {
+                warn!(plugin_id; "external plugin {} stopping heartbeats: {}", plugin_id, e);
+                return;
+            }
+        }
+    }
+}
+
+// The endpoint of the engine socket `name` ("incoming", "outgoing" or "sync"): its ipc socket on
+// `Transport::Ipc`, whatever the host, and otherwise `port` on `engine_host`.
+#[cfg_attr(not(unix), allow(unused_variables))]
+fn engine_endpoint(engine_host: &str, config: &EngineConfig, name: &str, port: u16) -> String {
+    #[cfg(unix)]
+    if let Some(endpoint) = config.ipc_endpoint(name) {
+        return endpoint;
+    }
+    format!("tcp://{}:{}", engine_host, port)
+}
+
+// The endpoint `endpoint` of the engine reached on `engine_host`, if the engine bound it on all
+// interfaces (e.g., "tcp://*:5559"); any other endpoint is on the address it was bound on.
+fn on_engine_host(endpoint: &str, engine_host: &str) -> String {
+    match endpoint
+        .strip_prefix("tcp://")
+        .and_then(|address| address.rsplit_once(':'))
+    {
+        Some((address, port)) if is_all_interfaces(address) => {
+            format!("tcp://{}:{}", engine_host, port)
+        }
+        _ => endpoint.to_string(),
+    }
+}
+
+// Connect a REQ socket to the engine's sync socket at `endpoint`, waiting for the engine to take
+// requests and reply to them for the sync timeout of `config`. An engine that rejected the
+// client's CURVE key never does, and the requests left must not keep the socket from closing,
+// so it does not linger.
+fn connect_sync_socket(
+    context: &zmq::Context,
+    config: &EngineConfig,
+    curve: Option<&CurveClient>,
+    endpoint: &str,
+    plugin_id: i32,
+) -> Result<zmq::Socket, EngineError> {
+    let sync_name = "external plugin sync";
+    let sync = create_socket(context, config, zmq::REQ, sync_name)?;
+    let sync_timeout = config.sync_timeout.as_millis() as i32;
+    sync.set_sndtimeo(sync_timeout)
+        .map_err(|source| EngineError::Sync { plugin_id, source })?;
+    sync.set_rcvtimeo(sync_timeout)
+        .map_err(|source| EngineError::Sync { plugin_id, source })?;
+    sync.set_linger(0)
+        .map_err(|source| EngineError::Sync { plugin_id, source })?;
+    if let Some(curve) = curve {
+        make_client(&sync, sync_name, curve)?;
+    }
+    connect(&sync, endpoint)?;
+    Ok(sync)
+}
+
+// Send `msg` on the sync socket and return the engine's reply.
+fn request(sync: &zmq::Socket, plugin_id: i32, msg: &str) -> Result<String, EngineError> {
+    let sync_error = |source| EngineError::Sync { plugin_id, source };
+    retry_on_eintr(|| sync.send(msg, 0)).map_err(sync_error)?;
+    let reply = retry_on_eintr(|| sync.recv_msg(0)).map_err(sync_error)?;
+    Ok(reply.as_str().unwrap_or_default().to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::curve::{curve_supported, CurveKeyPair, CurveServer};
+    use crate::event_engine::EventEngineBuilder;
+    use crate::events::{Event, ImageScore, ImageScored, NewImage, PluginJoined, PluginLeft};
+    use crate::image_store_plugin::ImageStorePlugin;
+    use crate::plugin_registry::PluginRegistry;
+
+    // Score every image with a labrador, as an external plugin would.
+    fn run_external_scorer(mut client: ExternalPluginClient) -> Result<usize, PluginError> {
+        let mut scored = 0;
+        loop {
+            let msg = client.next_event()?;
+            let image = match msg.decode()? {
+                Event::NewImage(image) => image,
+                Event::PluginTerminate => return Ok(scored),
+                event => panic!("unexpected event {:?}", event),
+            };
+            let event = ImageScored {
+                image_uuid: image.image_uuid,
+                scores: vec![ImageScore {
+                    label: "labrador".to_string(),
+                    probability: 1.0,
+                }],
+            };
+            match &msg.meta {
+                Some(meta) => client.publish_reply(meta, &event)?,
+                None => client.publish(&event)?,
+            };
+            scored += 1;
+        }
+    }
+
+    // Run an engine storing the images that external plugin 3 scores, connecting to
+    // `engine_host` with the keys of `curve` if any, and check that an image the engine
+    // publishes gets stored.
+    fn check_external_plugin_scores_image(
+        builder: EventEngineBuilder,
+        engine_host: &'static str,
+        curve: Option<CurveClient>,
+    ) {
+        let config = builder.config().clone();
+        // the client syncs while the engine starts, so it runs in a thread of its own
+        let scorer = thread::spawn(move || {
+            let client = ExternalPluginClient::connect_as(
+                engine_host,
+                &config,
+                curve.as_ref(),
+                3,
+                &["NewImageEvent"],
+            )
+            .unwrap();
+            run_external_scorer(client)
+        });
+        let mut plugins = PluginRegistry::new();
+        plugins
+            .register_plugin(Box::new(ImageStorePlugin::new(2)))
+            .unwrap()
+            .register_external(3)
+            .unwrap();
+        let engine = builder.plugins(plugins).start().unwrap();
+        let rx = engine.subscribe(&["ImageStoredEvent"]).unwrap();
+        // give the subscription time to reach the engine
+        thread::sleep(Duration::from_millis(200));
+        let image_uuid = uuid::Uuid::new_v4().to_string();
+        engine
+            .publish(&Event::NewImage(NewImage {
+                image_uuid: image_uuid.clone(),
+                image_format: "png".to_string(),
+                image: vec![1, 2, 3],
+                location: None,
+            }))
+            .unwrap();
+
+        match rx.recv_timeout(Duration::from_secs(10)).unwrap() {
+            Event::ImageStored(e) => assert_eq!(e.image_uuid, image_uuid),
+            event => panic!("expected an ImageStoredEvent, got {:?}", event),
+        }
+        engine.shutdown().unwrap();
+        assert_eq!(scorer.join().unwrap().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_external_plugin_scores_images_over_tcp() {
+        check_external_plugin_scores_image(
+            EventEngineBuilder::new()
+                .incoming_port(11559)
+                .outgoing_port(11560)
+                .sync_port(11000)
+                .sync_timeout(Duration::from_secs(10)),
+            "localhost",
+            None,
+        );
+    }
+
+    #[test]
+    fn test_external_plugin_scores_images_over_ipv6() {
+        check_external_plugin_scores_image(
+            EventEngineBuilder::new()
+                .incoming_port(3100)
+                .outgoing_port(3101)
+                .sync_port(3102)
+                .bind_address("[::1]")
+                .sync_timeout(Duration::from_secs(10)),
+            "[::1]",
+            None,
+        );
+    }
+
+    #[test]
+    fn test_wildcard_endpoints_are_on_the_engine_host() {
+        assert_eq!(
+            on_engine_host("tcp://*:5559", "engine.example.com"),
+            "tcp://engine.example.com:5559"
+        );
+        assert_eq!(
+            on_engine_host("tcp://[::]:5559", "[::1]"),
+            "tcp://[::1]:5559"
+        );
+        assert_eq!(
+            on_engine_host("tcp://192.168.1.10:5559", "engine.example.com"),
+            "tcp://192.168.1.10:5559"
+        );
+    }
+
+    #[test]
+    fn test_external_plugin_scores_images_over_curve() {
+        if !curve_supported() {
+            return;
+        }
+        let server = CurveKeyPair::generate().unwrap();
+        let client = CurveKeyPair::generate().unwrap();
+        check_external_plugin_scores_image(
+            EventEngineBuilder::new()
+                .incoming_port(2980)
+                .outgoing_port(2981)
+                .sync_port(2982)
+                .sync_timeout(Duration::from_secs(10))
+                .curve(CurveServer {
+                    keys: server.clone(),
+                    allowed_clients: vec![client.public_key],
+                }),
+            "localhost",
+            Some(CurveClient {
+                keys: client,
+                server_public_key: server.public_key,
+            }),
+        );
+    }
+
+    #[test]
+    fn test_unknown_curve_client_is_rejected() {
+        if !curve_supported() {
+            return;
+        }
+        let server = CurveKeyPair::generate().unwrap();
+        let client = CurveKeyPair::generate().unwrap();
+        let mut plugins = PluginRegistry::new();
+        plugins.register_optional_external(3).unwrap();
+        let builder = EventEngineBuilder::new()
+            .incoming_port(2990)
+            .outgoing_port(2991)
+            .sync_port(2992)
+            .sync_timeout(Duration::from_millis(500))
+            .curve(CurveServer {
+                keys: server.clone(),
+                allowed_clients: vec![client.public_key],
+            })
+            .plugins(plugins);
+        let config = builder.config().clone();
+        let engine = builder.start().unwrap();
+
+        // neither a client with a key the engine does not know nor one without CURVE gets an
+        // answer to its sync message
+        let stranger = CurveClient {
+            keys: CurveKeyPair::generate().unwrap(),
+            server_public_key: server.public_key,
+        };
+        match ExternalPluginClient::connect_with_curve("localhost", &config, &stranger, 3, &[]) {
+            Err(EngineError::Sync { plugin_id: 3, .. }) => {}
+            Err(e) => panic!("expected no sync reply, got: {}", e),
+            Ok(_) => panic!("the engine let in a client it does not know"),
+        }
+        match ExternalPluginClient::connect_with_config("localhost", &config, 3, &[]) {
+            Err(EngineError::Sync { plugin_id: 3, .. }) => {}
+            Err(e) => panic!("expected no sync reply, got: {}", e),
+            Ok(_) => panic!("the engine let in a client without CURVE"),
+        }
+        // the allowed client still gets in
+        let allowed = CurveClient {
+            keys: client,
+            server_public_key: server.public_key,
+        };
+        assert!(
+            ExternalPluginClient::connect_with_curve("localhost", &config, &allowed, 3, &[])
+                .is_ok()
+        );
+        engine.shutdown().unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_external_plugin_scores_images_over_ipc() {
+        let dir = std::env::temp_dir().join(format!("plyoreacto-ipc-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir(&dir).unwrap();
+        check_external_plugin_scores_image(
+            EventEngineBuilder::new()
+                .incoming_inproc("messages-ipc")
+                .outgoing_inproc("events-ipc")
+                .transport(crate::event_engine::Transport::Ipc { dir: dir.clone() })
+                .sync_timeout(Duration::from_secs(10)),
+            "localhost",
+            None,
+        );
+        // the socket files went with the engine
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+        std::fs::remove_dir(&dir).unwrap();
+    }
+
+    // Start an engine accepting registrations, register plugins called `names` with it one
+    // after the other and have each receive an image; returns the id each plugin was assigned,
+    // by name.
+    fn register_plugins(names: [&'static str; 2], incoming_port: u16) -> Vec<(&'static str, i32)> {
+        let builder = EventEngineBuilder::new()
+            .incoming_port(incoming_port)
+            .outgoing_port(incoming_port + 1)
+            .sync_port(incoming_port - 59)
+            .sync_timeout(Duration::from_secs(10))
+            .registration_window(Duration::from_secs(1));
+        let config = builder.config().clone();
+        // the engine waits for the registration window to close before start returns
+        let engine = thread::spawn(move || {
+            let mut plugins = PluginRegistry::new();
+            plugins
+                .register_plugin(Box::new(ImageStorePlugin::new(0)))
+                .unwrap();
+            builder.plugins(plugins).start().unwrap()
+        });
+        let mut clients = Vec::new();
+        for name in names {
+            // register once the engine is up, and after the plugin before
+            thread::sleep(Duration::from_millis(200));
+            let config = config.clone();
+            clients.push(thread::spawn(move || {
+                let mut client = ExternalPluginClient::register_with_config(
+                    "localhost",
+                    &config,
+                    name,
+                    &["NewImageEvent"],
+                )
+                .unwrap();
+                let msg = client.next_event().unwrap();
+                assert_eq!(msg.event_type, "NewImageEvent");
+                (name, client.plugin_id())
+            }));
+        }
+        let engine = engine.join().unwrap();
+        engine
+            .publish(&Event::NewImage(NewImage {
+                image_uuid: uuid::Uuid::new_v4().to_string(),
+                image_format: "png".to_string(),
+                image: vec![1, 2, 3],
+                location: None,
+            }))
+            .unwrap();
+        let ids = clients.into_iter().map(|c| c.join().unwrap()).collect();
+        engine.shutdown().unwrap();
+        ids
+    }
+
+    #[test]
+    fn test_plugins_register_in_either_order() {
+        assert_eq!(
+            register_plugins(["scorer", "observer"], 13559),
+            [("scorer", 1), ("observer", 2)]
+        );
+        assert_eq!(
+            register_plugins(["observer", "scorer"], 14559),
+            [("observer", 1), ("scorer", 2)]
+        );
+    }
+
+    #[test]
+    fn test_registration_with_unknown_event_type_is_rejected() {
+        let builder = EventEngineBuilder::new()
+            .incoming_port(8559)
+            .outgoing_port(8560)
+            .sync_port(8500)
+            .registration_window(Duration::from_millis(500));
+        let config = builder.config().clone();
+        let lost_config = config.clone();
+        let client = thread::spawn(move || {
+            ExternalPluginClient::register_with_config(
+                "localhost",
+                &lost_config,
+                "lost",
+                &["LostEvent"],
+            )
+            .map(|_| ())
+        });
+        // a rejected plugin is not waited for
+        let engine = builder.plugins(PluginRegistry::new()).start().unwrap();
+        match client.join().unwrap() {
+            Err(EngineError::SyncRejected { reply, .. }) => {
+                assert_eq!(reply, "rejected: unknown event type \"LostEvent\"")
+            }
+            result => panic!("expected the registration to be rejected, got {:?}", result),
+        }
+        // the plugins registering once the engine runs are checked too, with a suggestion
+        let subscriptions = ["ImageScorredEvent"];
+        let registered = ExternalPluginClient::register_with_config(
+            "localhost",
+            &config,
+            "scorer",
+            &subscriptions,
+        );
+        match registered.map(|_| ()) {
+            Err(EngineError::SyncRejected { reply, .. }) => assert_eq!(
+                reply,
+                "rejected: unknown event type \"ImageScorredEvent\" (did you mean \
+                 ImageScoredEvent?)"
+            ),
+            result => panic!("expected the registration to be rejected, got {:?}", result),
+        }
+        engine.shutdown().unwrap();
+    }
+
+    #[test]
+    fn test_unknown_subscriptions_are_refused_before_connecting() {
+        // no engine listens on these ports
+        let config = EventEngineBuilder::new().sync_port(8600).config().clone();
+        let subscriptions = ["NewImageEvent", "ImageScorredEvent", "LostEvent"];
+        match ExternalPluginClient::connect_with_config("localhost", &config, 3, &subscriptions) {
+            Err(e @ EngineError::UnknownSubscriptions { .. }) => assert_eq!(
+                e.to_string(),
+                "subscriptions to unknown event types: \"ImageScorredEvent\" (plugin 3, did you \
+                 mean ImageScoredEvent?), \"LostEvent\" (plugin 3)"
+            ),
+            result => panic!(
+                "expected unknown subscriptions, got {:?}",
+                result.map(|_| ())
+            ),
+        }
+    }
+
+    #[test]
+    fn test_late_plugin_receives_subsequent_events() {
+        let builder = EventEngineBuilder::new()
+            .incoming_port(9559)
+            .outgoing_port(9560)
+            .sync_port(9500);
+        let config = builder.config().clone();
+        let mut plugins = PluginRegistry::new();
+        plugins.register_optional_external(3).unwrap();
+        // the engine does not wait for plugin 3
+        let engine = builder.plugins(plugins).start().unwrap();
+        let joined_rx = engine.subscribe(&["PluginJoinedEvent"]).unwrap();
+        let new_image = |image_uuid: &str| {
+            Event::NewImage(NewImage {
+                image_uuid: image_uuid.to_string(),
+                image_format: "png".to_string(),
+                image: vec![1, 2, 3],
+                location: None,
+            })
+        };
+        engine.publish(&new_image("before")).unwrap();
+
+        let mut client =
+            ExternalPluginClient::connect_with_config("localhost", &config, 3, &["NewImageEvent"])
+                .unwrap();
+        assert_eq!(
+            joined_rx.recv_timeout(Duration::from_secs(10)).unwrap(),
+            Event::PluginJoined(PluginJoined { plugin_id: 3 })
+        );
+        engine.publish(&new_image("after")).unwrap();
+        match client.next_event().unwrap().decode().unwrap() {
+            Event::NewImage(image) => assert_eq!(image.image_uuid, "after"),
+            event => panic!("expected a NewImageEvent, got {:?}", event),
+        }
+        engine.shutdown().unwrap();
+    }
+
+    #[test]
+    fn test_plugin_whose_heartbeats_stop_has_left() {
+        let builder = EventEngineBuilder::new()
+            .incoming_port(10559)
+            .outgoing_port(10560)
+            .sync_port(10500)
+            .external_heartbeat_timeout(Duration::from_millis(500));
+        let config = builder.config().clone();
+        let mut plugins = PluginRegistry::new();
+        plugins.register_optional_external(3).unwrap();
+        let engine = builder.plugins(plugins).start().unwrap();
+        let rx = engine.subscribe(&["PluginLeftEvent"]).unwrap();
+
+        let client =
+            ExternalPluginClient::connect_with_config("localhost", &config, 3, &["NewImageEvent"])
+                .unwrap();
+        // the heartbeats keep the plugin in
+        assert!(rx.recv_timeout(Duration::from_secs(1)).is_err());
+        drop(client);
+        assert_eq!(
+            rx.recv_timeout(Duration::from_secs(5)).unwrap(),
+            Event::PluginLeft(PluginLeft {
+                plugin_id: 3,
+                reason: "no heartbeat for 500ms".to_string(),
+            })
+        );
+        engine.shutdown().unwrap();
+    }
+
+    #[test]
+    fn test_unregistered_plugin_is_rejected() {
+        let builder = EventEngineBuilder::new()
+            .incoming_port(12559)
+            .outgoing_port(12560)
+            .sync_port(12000)
+            .sync_timeout(Duration::from_secs(2));
+        let config = builder.config().clone();
+        let client = thread::spawn(move || {
+            ExternalPluginClient::connect_with_config("localhost", &config, 4, &[]).map(|_| ())
+        });
+        let mut plugins = PluginRegistry::new();
+        plugins.register_external(3).unwrap();
+        // plugin 3 never syncs, so the engine gives up
+        assert!(builder.plugins(plugins).start().is_err());
+        assert!(matches!(
+            client.join().unwrap(),
+            Err(EngineError::SyncRejected { plugin_id: 4, .. })
+        ));
+    }
+}
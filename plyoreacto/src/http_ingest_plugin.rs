@@ -0,0 +1,288 @@
+//! HTTP ingest plugin, built with the `http-ingest` feature.
+//! This plugin runs a small HTTP server and publishes a NewImageEvent for every image POSTed to
+//! `/images`, so that images can be fed into the pipeline without speaking ZeroMQ. It does not
+//! subscribe to any messages, unless it pauses on backpressure: then it subscribes to the
+//! BackpressureEvents and BackpressureRelievedEvents, and turns the uploads away while a plugin
+//! reports backpressure.
+//!
+
+use std::io::Read;
+use std::time::Duration;
+
+use log::{error, info};
+use tiny_http::{Method, Request, Response, Server};
+
+use crate::events::NewImage;
+use crate::plugin::{Plugin, PluginContext, PluginError, BACKPRESSURE_EVENTS};
+
+// Largest image accepted, unless set with `HttpIngestPlugin::max_body_size`.
+const DEFAULT_MAX_BODY_SIZE: usize = 10 * 1024 * 1024;
+
+// How often the plugin checks for the terminate event while no requests arrive.
+const TERMINATE_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The HTTP ingest plugin, for registering with a `PluginRegistry`. A `POST /images` request
+/// with the image as its body and an `image/*` content type (e.g., `image/png`) is published as
+/// a `NewImageEvent` with a new uuid, which is returned in the response body. Bodies larger than
+/// the maximum body size are rejected with a 413 and never published.
+pub struct HttpIngestPlugin {
+    plugin_id: i32,
+    port: u16,
+    max_body_size: usize,
+    // whether to turn the uploads away while a plugin reports backpressure
+    pause_on_backpressure: bool,
+}
+
+impl HttpIngestPlugin {
+    /// A plugin serving HTTP on `port`; the port is bound when the plugin starts.
+    pub fn new(plugin_id: i32, port: u16) -> Self {
+        HttpIngestPlugin {
+            plugin_id,
+            port,
+            max_body_size: DEFAULT_MAX_BODY_SIZE,
+            pause_on_backpressure: false,
+        }
+    }
+
+    /// Largest image, in bytes, the plugin accepts.
+    pub fn max_body_size(mut self, max_body_size: usize) -> Self {
+        self.max_body_size = max_body_size;
+        self
+    }
+
+    /// Answer uploads with a 503 while a plugin reports backpressure (see
+    /// `PluginContext::report_backpressure`), instead of publishing more images than the
+    /// pipeline keeps up with; clients retry them later.
+    pub fn pause_on_backpressure(mut self) -> Self {
+        self.pause_on_backpressure = true;
+        self
+    }
+
+    // Publish the image of a `POST /images` request; returns the status code and body of the
+    // response.
+    fn ingest(&self, ctx: &mut PluginContext, request: &mut Request) -> (u16, String) {
+        if request.url() != "/images" {
+            return (404, "not found\n".to_string());
+        }
+        if *request.method() != Method::Post {
+            return (405, "use POST to upload an image\n".to_string());
+        }
+        if ctx.is_backpressured() {
+            return (503, "the pipeline is behind, retry later\n".to_string());
+        }
+        let content_type = request
+            .headers()
+            .iter()
+            .find(|header| header.field.equiv("Content-Type"))
+            .map(|header| header.value.as_str().to_string());
+        let image_format = match content_type.as_deref().and_then(image_format) {
+            Some(image_format) => image_format,
+            None => return (415, "expected an image/* content type\n".to_string()),
+        };
+        if request.body_length().unwrap_or(0) > self.max_body_size {
+            return (413, "image too large\n".to_string());
+        }
+        // the body length is unknown for chunked requests, so read at most one byte too many
+        let mut image = Vec::new();
+        let limit = self.max_body_size as u64 + 1;
+        if let Err(e) = request.as_reader().take(limit).read_to_end(&mut image) {
+            return (400, format!("could not read the image: {}\n", e));
+        }
+        if image.len() > self.max_body_size {
+            return (413, "image too large\n".to_string());
+        }
+
+        let image_uuid = uuid::Uuid::new_v4().to_string();
+        let new_image = NewImage {
+            image_uuid: image_uuid.clone(),
+            image_format,
+            image,
+            location: None,
+        };
+        if let Err(e) = ctx.publish(&new_image) {
+            return (500, format!("could not publish the image: {}\n", e));
+        }
+        info!(
+            plugin_id = ctx.plugin_id;
+            "(NEW IMAGE -- {}) HTTP ingest plugin sent message for an uploaded image",
+            image_uuid
+        );
+        (201, format!("{}\n", image_uuid))
+    }
+}
+
+// The image format of a content type (e.g., "png" for "image/png"), ignoring any parameters.
+fn image_format(content_type: &str) -> Option<String> {
+    let media_type = content_type.split(';').next()?.trim().to_lowercase();
+    let subtype = media_type.strip_prefix("image/")?;
+    if subtype.is_empty() {
+        return None;
+    }
+    Some(subtype.to_string())
+}
+
+impl Plugin for HttpIngestPlugin {
+    fn id(&self) -> i32 {
+        self.plugin_id
+    }
+
+    fn name(&self) -> &str {
+        "http-ingest"
+    }
+
+    fn subscriptions(&self) -> &[&str] {
+        if self.pause_on_backpressure {
+            &BACKPRESSURE_EVENTS
+        } else {
+            &[]
+        }
+    }
+
+    fn start(self: Box<Self>, mut ctx: PluginContext) -> Result<(), PluginError> {
+        let server = Server::http(("0.0.0.0", self.port)).map_err(|e| {
+            PluginError::Other(format!(
+                "could not start HTTP server on port {}: {}",
+                self.port, e
+            ))
+        })?;
+        if self.pause_on_backpressure {
+            ctx.pause_on_backpressure()?;
+        }
+        info!(plugin_id = ctx.plugin_id; "HTTP ingest plugin listening on port {}", self.port);
+        loop {
+            if let Some(mut request) = server.recv_timeout(TERMINATE_POLL_INTERVAL)? {
+                let (status, body) = self.ingest(&mut ctx, &mut request);
+                let response = Response::from_string(body).with_status_code(status);
+                if let Err(e) = request.respond(response) {
+                    error!(
+                        plugin_id = ctx.plugin_id;
+                        "HTTP ingest plugin could not send response: {}",
+                        e
+                    );
+                }
+            }
+            // the backpressure events are tracked by the context
+            while ctx.sub_socket.poll(zmq::POLLIN, 0)? > 0 {
+                if ctx.next_event()?.event_type == "PluginTerminateEvent" {
+                    info!(
+                        plugin_id = ctx.plugin_id;
+                        "HTTP ingest plugin got terminate event, exiting"
+                    );
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::event_engine::EventEngineBuilder;
+    use crate::events::Event;
+    use crate::image_score_plugin::ImageScorePlugin;
+    use crate::plugin_registry::PluginRegistry;
+    use std::io::Write;
+    use std::net::TcpStream;
+    use std::time::Instant;
+
+    // A small JPEG: the start of image, an empty JFIF segment and the end of image markers.
+    const JPEG: &[u8] = &[
+        0xff, 0xd8, 0xff, 0xe0, 0x00, 0x10, b'J', b'F', b'I', b'F', 0x00, 0x01, 0x01, 0x00, 0x00,
+        0x01, 0x00, 0x01, 0x00, 0x00, 0xff, 0xd9,
+    ];
+
+    // POST `body` to /images on `port` and return the status code and body of the response.
+    // The plugin binds its port after it has synced, so connecting is retried for a while.
+    fn post_image(port: u16, content_type: &str, body: &[u8]) -> (u16, String) {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        let mut stream = loop {
+            match TcpStream::connect(("127.0.0.1", port)) {
+                Ok(stream) => break stream,
+                Err(e) if Instant::now() > deadline => panic!("could not connect: {}", e),
+                Err(_) => std::thread::sleep(Duration::from_millis(20)),
+            }
+        };
+        write!(
+            stream,
+            "POST /images HTTP/1.1\r\nHost: localhost\r\nContent-Type: {}\r\n\
+             Content-Length: {}\r\nConnection: close\r\n\r\n",
+            content_type,
+            body.len()
+        )
+        .unwrap();
+        stream.write_all(body).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        let status = response
+            .split(' ')
+            .nth(1)
+            .and_then(|status| status.parse().ok())
+            .expect("response without a status code");
+        let (_, body) = response.split_once("\r\n\r\n").unwrap_or_default();
+        (status, body.to_string())
+    }
+
+    fn start_ingest_engine(
+        ingest: HttpIngestPlugin,
+        incoming_port: u16,
+    ) -> crate::event_engine::EngineHandle {
+        let mut plugins = PluginRegistry::new();
+        plugins
+            .register_plugin(Box::new(ingest))
+            .unwrap()
+            .register_plugin(Box::new(ImageScorePlugin::new(1)))
+            .unwrap();
+        EventEngineBuilder::new()
+            .incoming_port(incoming_port)
+            .outgoing_port(incoming_port + 1)
+            .plugins(plugins)
+            .start()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_posted_image_is_scored() {
+        let engine = start_ingest_engine(HttpIngestPlugin::new(0, 34580), 34559);
+        let scored_rx = engine.subscribe(&["ImageScoredEvent"]).unwrap();
+        // give the subscription time to reach the engine
+        std::thread::sleep(Duration::from_millis(200));
+
+        let (status, body) = post_image(34580, "image/jpeg", JPEG);
+        assert_eq!(status, 201);
+        let image_uuid = body.trim();
+        match scored_rx.recv_timeout(Duration::from_secs(10)).unwrap() {
+            Event::ImageScored(scored) => assert_eq!(scored.image_uuid, image_uuid),
+            event => panic!("expected an ImageScoredEvent, got {:?}", event),
+        }
+        assert_eq!(post_image(34580, "text/plain", b"not an image").0, 415);
+        engine.shutdown().unwrap();
+    }
+
+    #[test]
+    fn test_oversized_image_is_rejected() {
+        let ingest = HttpIngestPlugin::new(0, 36580).max_body_size(JPEG.len() - 1);
+        let engine = start_ingest_engine(ingest, 36559);
+        let new_image_rx = engine.subscribe(&["NewImageEvent"]).unwrap();
+        std::thread::sleep(Duration::from_millis(200));
+
+        assert_eq!(post_image(36580, "image/jpeg", JPEG).0, 413);
+        assert!(new_image_rx
+            .recv_timeout(Duration::from_millis(500))
+            .is_err());
+        assert_eq!(post_image(36580, "image/jpeg", &JPEG[1..]).0, 201);
+        assert!(new_image_rx.recv_timeout(Duration::from_secs(10)).is_ok());
+        engine.shutdown().unwrap();
+    }
+
+    #[test]
+    fn test_image_format_from_content_type() {
+        assert_eq!(image_format("image/png"), Some("png".to_string()));
+        assert_eq!(
+            image_format("Image/JPEG; charset=binary"),
+            Some("jpeg".to_string())
+        );
+        assert_eq!(image_format("image/"), None);
+        assert_eq!(image_format("application/octet-stream"), None);
+    }
+}
@@ -0,0 +1,191 @@
+//! The image of a NewImageEvent, which the event either carries or passes by reference. Passing
+//! a 20 MB TIFF through the two PUB/SUB hops between its publisher and each subscriber is a
+//! waste when they all share a filesystem: a NewImageEvent with a `location` (a file path, or a
+//! `file://` URI) instead of image bytes has its subscribers read the file themselves, and an
+//! `ImagePayload` gives them the bytes either way:
+//!
+//! ```no_run
+//! use plyoreacto::events::NewImage;
+//! use plyoreacto::image_payload::ImagePayload;
+//!
+//! let new_image = NewImage {
+//!     image_uuid: "1234".to_string(),
+//!     image_format: "tiff".to_string(),
+//!     image: Vec::new(),
+//!     location: Some("/shared/images/1234.tiff".to_string()),
+//! };
+//! let bytes = ImagePayload::from(&new_image).bytes().expect("Image is gone");
+//! ```
+//!
+//! The file is only read when the bytes are asked for, so it has to stay where it is until every
+//! subscriber has read it; the image scoring plugin publishes an ImageScoreFailedEvent, and the
+//! image storing plugin an ImageStoreFailedEvent, for an image whose file is gone by then.
+//!
+
+use std::borrow::Cow;
+use std::fmt;
+use std::fs;
+
+use crate::events::NewImage;
+use crate::events_generated::events::NewImageEvent;
+
+// The prefix of the URIs of local files, the only ones a location can be besides a path.
+const FILE_URI: &str = "file://";
+
+/// The image of a NewImageEvent: its bytes, or where to read them from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImagePayload<'a> {
+    /// The bytes the event carries.
+    Inline(&'a [u8]),
+    /// The path, or `file://` URI, of the file holding the bytes.
+    Location(&'a str),
+}
+
+impl<'a> ImagePayload<'a> {
+    /// The image of a NewImageEvent table, as read with `events::read_new_image`.
+    pub fn of(new_image: &NewImageEvent<'a>) -> Self {
+        match new_image.location() {
+            Some(location) => ImagePayload::Location(location),
+            None => ImagePayload::Inline(new_image.image().unwrap_or_default()),
+        }
+    }
+
+    /// Where the image is read from, if it is passed by reference.
+    pub fn location(&self) -> Option<&'a str> {
+        match self {
+            ImagePayload::Inline(_) => None,
+            ImagePayload::Location(location) => Some(location),
+        }
+    }
+
+    /// The bytes of the image: those of the event itself, borrowed, or those read from its
+    /// location.
+    pub fn bytes(&self) -> Result<Cow<'a, [u8]>, ImagePayloadError> {
+        let location = match self {
+            ImagePayload::Inline(image) => return Ok(Cow::Borrowed(image)),
+            ImagePayload::Location(location) => *location,
+        };
+        let path = match location.strip_prefix(FILE_URI) {
+            Some(path) => path,
+            None if has_scheme(location) => {
+                return Err(ImagePayloadError::UnsupportedLocation {
+                    location: location.to_string(),
+                })
+            }
+            None => location,
+        };
+        fs::read(path)
+            .map(Cow::Owned)
+            .map_err(|source| ImagePayloadError::Unreadable {
+                location: location.to_string(),
+                source,
+            })
+    }
+}
+
+impl<'a> From<&'a NewImage> for ImagePayload<'a> {
+    fn from(new_image: &'a NewImage) -> Self {
+        match &new_image.location {
+            Some(location) => ImagePayload::Location(location),
+            None => ImagePayload::Inline(&new_image.image),
+        }
+    }
+}
+
+// Whether `location` is a URI, `scheme://...`, rather than a path.
+fn has_scheme(location: &str) -> bool {
+    match location.split_once("://") {
+        Some((scheme, _)) => {
+            scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+                && scheme
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c))
+        }
+        None => false,
+    }
+}
+
+/// Why the bytes of an image passed by reference could not be had.
+#[derive(Debug)]
+pub enum ImagePayloadError {
+    /// The file at the location could not be read, e.g., because it was deleted since the
+    /// event was published.
+    Unreadable {
+        location: String,
+        source: std::io::Error,
+    },
+    /// The location is a URI of another scheme than `file`.
+    UnsupportedLocation { location: String },
+}
+
+impl fmt::Display for ImagePayloadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImagePayloadError::Unreadable { location, source } => {
+                write!(f, "could not read the image at {}: {}", location, source)
+            }
+            ImagePayloadError::UnsupportedLocation { location } => {
+                write!(f, "unsupported image location {}", location)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ImagePayloadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ImagePayloadError::Unreadable { source, .. } => Some(source),
+            ImagePayloadError::UnsupportedLocation { .. } => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::events::{make_new_image_at_msg, make_new_image_msg, read_new_image};
+    use flatbuffers::FlatBufferBuilder;
+
+    #[test]
+    fn test_inline_images_are_borrowed() {
+        let mut bldr = FlatBufferBuilder::new();
+        let payload = make_new_image_msg(&mut bldr, "1234", "png", &[1, 2, 3])
+            .unwrap()
+            .to_vec();
+        let image = ImagePayload::of(&read_new_image(&payload).unwrap());
+        assert_eq!(image.location(), None);
+        assert!(matches!(image.bytes().unwrap(), Cow::Borrowed(&[1, 2, 3])));
+    }
+
+    #[test]
+    fn test_images_are_read_from_their_location() {
+        let path = std::env::temp_dir().join(format!("plyoreacto-{}.png", uuid::Uuid::new_v4()));
+        fs::write(&path, [4, 5, 6]).unwrap();
+        let path = path.display().to_string();
+        let uri = format!("file://{}", path);
+        let mut bldr = FlatBufferBuilder::new();
+        for location in [&path, &uri] {
+            let payload = make_new_image_at_msg(&mut bldr, "1234", "png", location)
+                .unwrap()
+                .to_vec();
+            let image = ImagePayload::of(&read_new_image(&payload).unwrap());
+            assert_eq!(image.location(), Some(location.as_str()));
+            assert_eq!(image.bytes().unwrap().as_ref(), &[4, 5, 6]);
+        }
+
+        fs::remove_file(&path).unwrap();
+        let new_image = NewImage {
+            image_uuid: "1234".to_string(),
+            image_format: "png".to_string(),
+            image: Vec::new(),
+            location: Some(uri.clone()),
+        };
+        let error = ImagePayload::from(&new_image).bytes().unwrap_err();
+        assert!(matches!(&error, ImagePayloadError::Unreadable { location, .. } if *location == uri));
+        assert!(error.to_string().starts_with(&format!("could not read the image at {}", uri)));
+        assert!(matches!(
+            ImagePayload::Location("s3://bucket/1234.png").bytes(),
+            Err(ImagePayloadError::UnsupportedLocation { .. })
+        ));
+    }
+}
@@ -0,0 +1,335 @@
+//! Image retention plugin.
+//! This plugin purges old images from a storage backend: every period it lists the stored
+//! images, deletes the ones its retention policy expires and publishes an ImageDeletedEvent for
+//! each. It does not subscribe to any messages.
+//! Give it a backend sharing the images of the image storing plugin, e.g., a `FilesystemStore`
+//! with the same root or a clone of an `InMemoryStore`.
+//!
+
+use std::time::{Duration, SystemTime};
+
+use log::{error, info};
+
+use crate::events::ImageDeleted;
+use crate::plugin::{Plugin, PluginContext, PluginError};
+use crate::storage::{StorageBackend, StorageError, StoredImage};
+
+/// Which stored images to keep. An image is kept while it is younger than the maximum age and
+/// it and the images stored after it fit in the maximum count and total size; every limit is
+/// optional, and the default policy keeps everything.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RetentionPolicy {
+    max_age: Option<Duration>,
+    max_count: Option<usize>,
+    max_total_bytes: Option<u64>,
+}
+
+impl RetentionPolicy {
+    pub fn new() -> Self {
+        RetentionPolicy::default()
+    }
+
+    /// Purge the images stored longer than `max_age` ago.
+    pub fn max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    /// Keep only the `max_count` most recently stored images.
+    pub fn max_count(mut self, max_count: usize) -> Self {
+        self.max_count = Some(max_count);
+        self
+    }
+
+    /// Keep only the most recently stored images that take up `max_total_bytes` together.
+    pub fn max_total_bytes(mut self, max_total_bytes: u64) -> Self {
+        self.max_total_bytes = Some(max_total_bytes);
+        self
+    }
+
+    /// The images of `images` that expire at `now`, oldest first.
+    pub fn expired(&self, mut images: Vec<StoredImage>, now: SystemTime) -> Vec<StoredImage> {
+        // newest first, so that the images kept are the ones stored last
+        images.sort_by_key(|image| std::cmp::Reverse(image.stored_at));
+        let mut total_bytes = 0;
+        let kept = images
+            .iter()
+            .enumerate()
+            .take_while(|(count, image)| {
+                total_bytes += image.size;
+                let age = now.duration_since(image.stored_at).unwrap_or_default();
+                self.max_age.is_none_or(|max_age| age <= max_age)
+                    && self.max_count.is_none_or(|max_count| *count < max_count)
+                    && self.max_total_bytes.is_none_or(|max| total_bytes <= max)
+            })
+            .count();
+        let mut expired = images.split_off(kept);
+        expired.reverse();
+        expired
+    }
+}
+
+/// The image retention plugin, for registering with a `PluginRegistry`.
+pub struct ImageRetentionPlugin {
+    plugin_id: i32,
+    storage: Box<dyn StorageBackend + Send>,
+    period: Duration,
+    policy: RetentionPolicy,
+}
+
+impl ImageRetentionPlugin {
+    /// A plugin purging the images of `storage` that `policy` expires, every `period`.
+    pub fn new(
+        plugin_id: i32,
+        storage: Box<dyn StorageBackend + Send>,
+        period: Duration,
+        policy: RetentionPolicy,
+    ) -> Self {
+        ImageRetentionPlugin {
+            plugin_id,
+            storage,
+            period,
+            policy,
+        }
+    }
+
+    // Delete the expired images; returns the uuids of the images deleted. Only the images
+    // listed at the start of the pass are considered, so an image stored while the pass runs
+    // is never deleted by it.
+    fn purge(&mut self) -> Result<Vec<String>, StorageError> {
+        let started = SystemTime::now();
+        let images = self.storage.list()?;
+        let mut deleted = Vec::new();
+        for image in self.policy.expired(images, started) {
+            if self.storage.delete(&image.image_uuid)? {
+                deleted.push(image.image_uuid);
+            }
+        }
+        Ok(deleted)
+    }
+}
+
+impl Plugin for ImageRetentionPlugin {
+    fn id(&self) -> i32 {
+        self.plugin_id
+    }
+
+    fn name(&self) -> &str {
+        "image-retention"
+    }
+
+    fn subscriptions(&self) -> &[&str] {
+        &[]
+    }
+
+    fn start(mut self: Box<Self>, mut ctx: PluginContext) -> Result<(), PluginError> {
+        loop {
+            // wait for the next pass, unless the engine terminates the plugin first
+            let timeout = self.period.as_millis() as i64;
+            if ctx.sub_socket.poll(zmq::POLLIN, timeout)? > 0
+                && ctx.next_event()?.event_type == "PluginTerminateEvent"
+            {
+                info!(
+                    plugin_id = ctx.plugin_id;
+                    "Image retention plugin got terminate event, exiting"
+                );
+                return Ok(());
+            }
+            // a failing pass is retried at the next period
+            let deleted = match self.purge() {
+                Ok(deleted) => deleted,
+                Err(e) => {
+                    error!(
+                        plugin_id = ctx.plugin_id;
+                        "Image retention plugin could not purge images: {}",
+                        e
+                    );
+                    continue;
+                }
+            };
+            for image_uuid in deleted {
+                ctx.publish(&ImageDeleted {
+                    image_uuid: image_uuid.clone(),
+                    existed: true,
+                })?;
+                info!(
+                    plugin_id = ctx.plugin_id;
+                    "(IMAGE DELETED -- {}) Image retention plugin purged image {}",
+                    image_uuid, image_uuid
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::event_engine::EventEngineBuilder;
+    use crate::events::{Event, NewImage};
+    use crate::image_score_plugin::{FixedScorer, ImageScorePlugin};
+    use crate::image_store_plugin::ImageStorePlugin;
+    use crate::plugin_registry::PluginRegistry;
+    use crate::storage::{InMemoryStore, StoredLocation};
+    use std::cell::Cell;
+
+    fn stored(image_uuid: &str, size: u64, age: u64, now: SystemTime) -> StoredImage {
+        StoredImage {
+            image_uuid: image_uuid.to_string(),
+            size,
+            stored_at: now - Duration::from_secs(age),
+        }
+    }
+
+    fn expired_uuids(
+        policy: RetentionPolicy,
+        images: &[StoredImage],
+        now: SystemTime,
+    ) -> Vec<String> {
+        policy
+            .expired(images.to_vec(), now)
+            .into_iter()
+            .map(|image| image.image_uuid)
+            .collect()
+    }
+
+    #[test]
+    fn test_policy_expires_the_oldest_images() {
+        let now = SystemTime::now();
+        let images = [
+            stored("b", 10, 20, now),
+            stored("a", 10, 30, now),
+            stored("d", 10, 0, now),
+            stored("c", 10, 10, now),
+        ];
+        assert!(expired_uuids(RetentionPolicy::new(), &images, now).is_empty());
+        let max_age = RetentionPolicy::new().max_age(Duration::from_secs(15));
+        assert_eq!(expired_uuids(max_age, &images, now), ["a", "b"]);
+        let max_count = RetentionPolicy::new().max_count(1);
+        assert_eq!(expired_uuids(max_count, &images, now), ["a", "b", "c"]);
+        let max_total_bytes = RetentionPolicy::new().max_total_bytes(25);
+        assert_eq!(expired_uuids(max_total_bytes, &images, now), ["a", "b"]);
+        // the strictest limit wins
+        let policy = RetentionPolicy::new()
+            .max_age(Duration::from_secs(25))
+            .max_count(3)
+            .max_total_bytes(15);
+        assert_eq!(expired_uuids(policy, &images, now), ["a", "b", "c"]);
+    }
+
+    // An in-memory store where an image arrives each time the images are listed, as if it were
+    // stored while a purge pass runs.
+    struct ArrivingStore {
+        store: InMemoryStore,
+        arrivals: Cell<usize>,
+    }
+
+    impl StorageBackend for ArrivingStore {
+        fn put(
+            &mut self,
+            image_uuid: &str,
+            image_format: &str,
+            image: &[u8],
+        ) -> Result<StoredLocation, StorageError> {
+            self.store.put(image_uuid, image_format, image)
+        }
+
+        fn get(&self, image_uuid: &str) -> Result<Option<Vec<u8>>, StorageError> {
+            self.store.get(image_uuid)
+        }
+
+        fn delete(&mut self, image_uuid: &str) -> Result<bool, StorageError> {
+            self.store.delete(image_uuid)
+        }
+
+        fn list(&self) -> Result<Vec<StoredImage>, StorageError> {
+            let images = self.store.list()?;
+            let arrival = format!("arrival-{}", self.arrivals.get());
+            self.arrivals.set(self.arrivals.get() + 1);
+            self.store.clone().put(&arrival, "png", &[1])?;
+            Ok(images)
+        }
+    }
+
+    #[test]
+    fn test_images_stored_during_a_pass_are_kept() {
+        let store = InMemoryStore::new();
+        let mut storage = ArrivingStore {
+            store: store.clone(),
+            arrivals: Cell::new(0),
+        };
+        storage.put("old", "png", &[1]).unwrap();
+        let policy = RetentionPolicy::new().max_count(0);
+        let mut plugin = ImageRetentionPlugin::new(0, Box::new(storage), Duration::ZERO, policy);
+        assert_eq!(plugin.purge().unwrap(), ["old"]);
+        assert_eq!(store.get("old").unwrap(), None);
+        assert_eq!(store.get("arrival-0").unwrap(), Some(vec![1]));
+        // the next pass gets it, but not the image arriving then
+        assert_eq!(plugin.purge().unwrap(), ["arrival-0"]);
+        assert_eq!(store.len(), 1);
+    }
+
+    #[test]
+    fn test_pipeline_purges_old_images() {
+        let store = InMemoryStore::new();
+        let scorer = FixedScorer::new(vec![("labrador".to_string(), 1.0)]);
+        let policy = RetentionPolicy::new().max_age(Duration::from_millis(300));
+        let retention = ImageRetentionPlugin::new(
+            3,
+            Box::new(store.clone()),
+            Duration::from_millis(100),
+            policy,
+        );
+        let mut plugins = PluginRegistry::new();
+        plugins
+            .register_plugin(Box::new(ImageScorePlugin::with_scorer(1, Box::new(scorer))))
+            .unwrap()
+            .register_plugin(Box::new(
+                ImageStorePlugin::new(2).storage(Box::new(store.clone())),
+            ))
+            .unwrap()
+            .register_plugin(Box::new(retention))
+            .unwrap();
+        let engine = EventEngineBuilder::new()
+            .incoming_port(43559)
+            .outgoing_port(43560)
+            .plugins(plugins)
+            .start()
+            .unwrap();
+        let rx = engine
+            .subscribe(&["ImageStoredEvent", "ImageDeletedEvent"])
+            .unwrap();
+        // give the subscription time to reach the engine
+        std::thread::sleep(Duration::from_millis(200));
+        for image_uuid in ["first", "second", "third"] {
+            engine
+                .publish(&Event::NewImage(NewImage {
+                    image_uuid: image_uuid.to_string(),
+                    image_format: "png".to_string(),
+                    image: vec![1, 2, 3],
+                    location: None,
+                }))
+                .unwrap();
+        }
+
+        let timeout = Duration::from_secs(10);
+        let mut deleted = Vec::new();
+        let mut stored = 0;
+        while deleted.len() < 3 {
+            match rx.recv_timeout(timeout).unwrap() {
+                Event::ImageStored(_) => stored += 1,
+                Event::ImageDeleted(e) => {
+                    assert!(e.existed);
+                    deleted.push(e.image_uuid);
+                }
+                event => panic!("unexpected event {:?}", event),
+            }
+        }
+        engine.shutdown().unwrap();
+        // every image is stored before it is old enough to be purged
+        assert_eq!(stored, 3);
+        deleted.sort();
+        assert_eq!(deleted, ["first", "second", "third"]);
+        assert!(store.is_empty());
+    }
+}
@@ -1,63 +1,613 @@
 //! Image scoring plugin. *Plugin 2*
 //! This plugin subscribes to NewImageEvent messages and published ImageScoredEvent messages.
+//! The scores come from an `ImageScorer`; when the scorer fails for an image, the plugin
+//! publishes an ImageScoreFailedEvent for it instead and goes on with the next image. Images
+//! whose top score is below the plugin's threshold, if it has one, get an ImageRejectedEvent
+//! instead of an ImageScoredEvent, so the image storing plugin never sees them. Images passed
+//! by reference are read from their location, and get an ImageScoreFailedEvent if it cannot be.
+//! A NewImageEvent the plugin cannot read at all, e.g., without an image uuid, is dead-lettered
+//! (see `PluginContext::dead_letter`) and the plugin goes on with the next one.
 //!
 
-use crate::events::bytes_to_event;
-use flatbuffers::FlatBufferBuilder;
+use std::fmt;
+
+use log::{debug, error, info, warn};
 use rand::Rng;
-use zmq::Socket;
-
-use super::events::{send_image_scored_event, ImageScore};
-
-pub fn start(
-    pub_socket: &mut Socket,
-    sub_socket: &mut Socket,
-    bldr: &mut FlatBufferBuilder,
-) -> std::io::Result<()> {
-    // process 5 new image events
-    let mut count = 0;
-    // for generating random probabilities
-    let mut rng = rand::thread_rng();
-
-    while count < 5 {
-        let msg_bytes = sub_socket.recv_bytes(0).expect("Error receiving message");
-        let event = bytes_to_event(&msg_bytes).expect("Error getting event");
-        // check type of event -- TODO: remove this when subscriptions work
-        let event_type = event
-            .event_type()
-            .variant_name()
-            .expect("could not get event type");
-        if event_type != "NewImageEvent" {
-            println!("*********** Image score plugin got unexpected message!!! ***********");
-            println!("Message variant: {}", event_type);
-            println!("Message bytes: {:?}", &msg_bytes);
-            println!("**********                                               ************");
-            continue;
+
+use super::events::{
+    read_new_image, Event, ImageRejected, ImageScore, ImageScoreFailed, ImageScored,
+};
+use crate::image_payload::ImagePayload;
+use crate::new_image_plugin::DEFAULT_IMAGES;
+use crate::plugin::{EventMsg, EventSink, EventSource, Plugin, PluginContext, PluginError};
+
+/// Scores images for the image scoring plugin; implement it to plug in a model.
+pub trait ImageScorer: Send {
+    /// The labels of `image` with the probability of each; `format` is the image format of the
+    /// NewImageEvent (e.g., "png").
+    fn score(&mut self, image: &[u8], format: &str) -> Result<Vec<(String, f32)>, ScoreError>;
+}
+
+/// Errors returned by an `ImageScorer`.
+#[derive(Debug)]
+pub enum ScoreError {
+    /// The scorer does not handle images of this format.
+    UnsupportedFormat(String),
+    /// Any other failure, described by the scorer.
+    Other(String),
+}
+
+impl fmt::Display for ScoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScoreError::UnsupportedFormat(format) => {
+                write!(f, "unsupported image format: {}", format)
+            }
+            ScoreError::Other(reason) => write!(f, "{}", reason),
+        }
+    }
+}
+
+impl std::error::Error for ScoreError {}
+
+/// The default scorer: a random probability for the label "labrador", whatever the image.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RandomScorer;
+
+impl ImageScorer for RandomScorer {
+    fn score(&mut self, _image: &[u8], _format: &str) -> Result<Vec<(String, f32)>, ScoreError> {
+        // generate a random probability:
+        let prob = rand::thread_rng().gen::<f32>();
+        Ok(vec![("labrador".to_string(), prob)])
+    }
+}
+
+/// A scorer that gives every image the same scores, for tests.
+#[derive(Clone, Debug, Default)]
+pub struct FixedScorer {
+    scores: Vec<(String, f32)>,
+}
+
+impl FixedScorer {
+    pub fn new(scores: Vec<(String, f32)>) -> Self {
+        FixedScorer { scores }
+    }
+}
+
+impl ImageScorer for FixedScorer {
+    fn score(&mut self, _image: &[u8], _format: &str) -> Result<Vec<(String, f32)>, ScoreError> {
+        Ok(self.scores.clone())
+    }
+}
+
+/// The image scoring plugin, for registering with a `PluginRegistry`. A `threshold` setting (a
+/// number; see `PluginRegistry::settings`) replaces the one it was made with.
+pub struct ImageScorePlugin {
+    plugin_id: i32,
+    scorer: Box<dyn ImageScorer>,
+    threshold: Option<f32>,
+    // how many images to score before returning
+    images: usize,
+}
+
+impl ImageScorePlugin {
+    /// A plugin scoring images with the `RandomScorer`.
+    pub fn new(plugin_id: i32) -> Self {
+        ImageScorePlugin::with_scorer(plugin_id, Box::new(RandomScorer))
+    }
+
+    /// A plugin scoring images with `scorer`.
+    pub fn with_scorer(plugin_id: i32, scorer: Box<dyn ImageScorer>) -> Self {
+        ImageScorePlugin {
+            plugin_id,
+            scorer,
+            threshold: None,
+            images: DEFAULT_IMAGES,
+        }
+    }
+
+    /// How many images to score before the plugin returns; 5 by default, like the images the
+    /// `NewImagePlugin` generates.
+    pub fn images(mut self, images: usize) -> Self {
+        self.images = images;
+        self
+    }
+
+    /// Reject the images whose most probable label has a probability below `threshold`. By
+    /// default no image is rejected.
+    pub fn threshold(mut self, threshold: f32) -> Self {
+        self.threshold = Some(threshold);
+        self
+    }
+
+    // The event published for an image with `scores`: an ImageRejectedEvent when the top score
+    // is below the threshold, an ImageScoredEvent otherwise.
+    fn outcome(&self, image_uuid: String, scores: Vec<(String, f32)>) -> Event {
+        if let Some(threshold) = self.threshold {
+            let (top_label, probability) = scores
+                .iter()
+                .max_by(|a, b| a.1.total_cmp(&b.1))
+                .cloned()
+                .unwrap_or_default();
+            if probability < threshold {
+                return Event::ImageRejected(ImageRejected {
+                    image_uuid,
+                    top_label,
+                    probability,
+                });
+            }
+        }
+        Event::ImageScored(ImageScored {
+            image_uuid,
+            scores: scores
+                .into_iter()
+                .map(|(label, probability)| ImageScore { label, probability })
+                .collect(),
+        })
+    }
+}
+
+impl Plugin for ImageScorePlugin {
+    fn id(&self) -> i32 {
+        self.plugin_id
+    }
+
+    fn name(&self) -> &str {
+        "image-score"
+    }
+
+    fn subscriptions(&self) -> &[&str] {
+        &["NewImageEvent"]
+    }
+
+    fn publications(&self) -> &[&str] {
+        &[
+            "ImageScoredEvent",
+            "ImageRejectedEvent",
+            "ImageScoreFailedEvent",
+        ]
+    }
+
+    fn start(mut self: Box<Self>, mut ctx: PluginContext) -> Result<(), PluginError> {
+        if ctx.settings().contains("threshold") {
+            self.threshold = Some(ctx.settings().get_f64("threshold")? as f32);
+        }
+        self.run(&mut ctx)
+    }
+}
+
+impl ImageScorePlugin {
+    /// Score the images of the NewImageEvents `ctx` receives and publish the outcomes, as the
+    /// plugin does once the engine starts it, until `ctx` receives a PluginTerminateEvent or
+    /// the plugin has handled its `images`; with a `MemoryContext`, for testing the plugin
+    /// without an engine.
+    pub fn run(&mut self, ctx: &mut (impl EventSource + EventSink)) -> Result<(), PluginError> {
+        // process the new image events
+        let mut count = 0;
+
+        while count < self.images {
+            let msg = ctx.next_event()?;
+            if msg.event_type == "PluginTerminateEvent" {
+                info!(
+                    plugin_id = ctx.plugin_id();
+                    "Image score plugin got terminate event, exiting"
+                );
+                break;
+            }
+            let outcome = match self.score_event(ctx.plugin_id(), &msg) {
+                Ok(Some(outcome)) => outcome,
+                Ok(None) => continue,
+                Err(e) => {
+                    let reason = e.to_string();
+                    ctx.dead_letter(&msg.event_type, msg.meta.as_ref(), &msg.payload, &reason)?;
+                    count += 1;
+                    continue;
+                }
+            };
+            // tie the outcome to the new image event, if it came in an envelope
+            match &msg.meta {
+                Some(meta) => ctx.publish_reply(meta, &outcome)?,
+                None => ctx.publish(&outcome)?,
+            };
+            count += 1;
+            log_outcome(ctx.plugin_id(), &outcome);
+        }
+        Ok(())
+    }
+
+    // The event to publish for the NewImageEvent `msg`, received by the plugin `plugin_id`, or
+    // None for an event of another type; fails for an event the plugin cannot read, to be
+    // dead-lettered.
+    fn score_event(
+        &mut self,
+        plugin_id: i32,
+        msg: &EventMsg,
+    ) -> Result<Option<Event>, PluginError> {
+        if msg.event_type != "NewImageEvent" {
+            warn!(
+                plugin_id, event_type = msg.event_type.as_str();
+                "Image score plugin got unexpected message {}: {:?}",
+                msg.event_type, &msg.payload
+            );
+            return Ok(None);
         };
 
-        let image_uuid = event
-            .event_as_new_image_event()
-            .unwrap()
+        let new_image = read_new_image(&msg.payload)?;
+        let image_uuid = new_image
             .image_uuid()
-            .unwrap();
-        println!(
+            .ok_or_else(|| PluginError::Other("NewImageEvent without image_uuid".to_string()))?
+            .to_string();
+        debug!(
+            plugin_id;
             "Image scored plugin got New Image event for image {}",
             image_uuid
         );
-        // generate an image scored event
-        // generate a random probability:
-        let prob = rng.gen::<f32>();
-        let scores = vec![ImageScore {
-            label: "labrador".to_string(),
-            probability: prob,
-        }];
-        send_image_scored_event(pub_socket, bldr, image_uuid, scores)
-            .expect("Could not send image scored event");
-        count += 1;
-        println!(
-            "(IMAGE SCORED -- {}) Image scored plugin sent Image Scored event for image: {}; prob: {}", image_uuid, 
-            image_uuid, prob
+        let image_format = new_image.image_format().unwrap_or_default();
+        let scores = ImagePayload::of(&new_image)
+            .bytes()
+            .map_err(|e| e.to_string())
+            .and_then(|image| {
+                self.scorer
+                    .score(&image, image_format)
+                    .map_err(|e| e.to_string())
+            });
+        Ok(Some(match scores {
+            Ok(scores) => self.outcome(image_uuid, scores),
+            Err(error) => Event::ImageScoreFailed(ImageScoreFailed { image_uuid, error }),
+        }))
+    }
+}
+
+// Log the `outcome` of an image, as published by the plugin `plugin_id`.
+fn log_outcome(plugin_id: i32, outcome: &Event) {
+    match outcome {
+        Event::ImageScored(e) => info!(
+            plugin_id;
+            "(IMAGE SCORED -- {}) Image scored plugin sent Image Scored event for image: {}; scores: {:?}",
+            e.image_uuid, e.image_uuid, e.scores
+        ),
+        Event::ImageRejected(e) => info!(
+            plugin_id;
+            "(IMAGE REJECTED -- {}) Image scored plugin rejected image: {}; {}: {}",
+            e.image_uuid, e.image_uuid, e.top_label, e.probability
+        ),
+        Event::ImageScoreFailed(e) => error!(
+            plugin_id;
+            "(IMAGE SCORE FAILED -- {}) Image scored plugin could not score image: {}",
+            e.image_uuid, e.error
+        ),
+        _ => {}
+    }
+}
+
+// On an async engine the plugin runs as a task, scoring the images on the runtime's thread: a
+// scorer that takes long to score an image is better registered as a blocking plugin.
+#[cfg(all(feature = "async", unix))]
+impl crate::async_engine::AsyncPlugin for ImageScorePlugin {
+    fn id(&self) -> i32 {
+        self.plugin_id
+    }
+
+    fn name(&self) -> &str {
+        "image-score"
+    }
+
+    fn subscriptions(&self) -> &[&str] {
+        &["NewImageEvent"]
+    }
+
+    fn start(
+        mut self: Box<Self>,
+        mut ctx: crate::async_engine::AsyncPluginContext,
+    ) -> crate::async_engine::PluginFuture {
+        Box::pin(async move {
+            let plugin_id = ctx.plugin_id();
+            let mut count = 0;
+            while count < self.images {
+                let msg = ctx.next_event().await?;
+                if msg.event_type == "PluginTerminateEvent" {
+                    info!(plugin_id; "Image score plugin got terminate event, exiting");
+                    break;
+                }
+                let outcome = match self.score_event(plugin_id, &msg) {
+                    Ok(Some(outcome)) => outcome,
+                    Ok(None) => continue,
+                    Err(e) => {
+                        let reason = e.to_string();
+                        let meta = msg.meta.as_ref();
+                        ctx.context()
+                            .dead_letter(&msg.event_type, meta, &msg.payload, &reason)?;
+                        count += 1;
+                        continue;
+                    }
+                };
+                match &msg.meta {
+                    Some(meta) => ctx.publish_reply(meta, &outcome).await?,
+                    None => ctx.publish(&outcome).await?,
+                };
+                count += 1;
+                log_outcome(plugin_id, &outcome);
+            }
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::events::{
+        frame_event_msg, get_event_type_bytes_filter, make_new_image_msg, recv_event_msg,
+        send_new_image_event, send_plugin_terminate_event, DeadLetter, Event, NewImage,
+        PluginTerminate,
+    };
+    use crate::events_generated::events::{
+        Event as FbEvent, EventArgs, EventType, NewImageEvent, NewImageEventArgs,
+    };
+    use crate::memory_context::MemoryContext;
+    use flatbuffers::FlatBufferBuilder;
+    use std::thread::JoinHandle;
+    use zmq::Socket;
+
+    // Start `plugin` on sockets connected to stand-ins for the engine's outgoing and incoming
+    // sockets, which are returned along with the plugin's thread; the incoming stand-in is
+    // subscribed to `subscriptions`.
+    fn start_score_plugin(
+        context: &zmq::Context,
+        name: &str,
+        plugin: ImageScorePlugin,
+        subscriptions: &[&str],
+    ) -> (Socket, Socket, JoinHandle<Result<(), PluginError>>) {
+        let events_endpoint = format!("inproc://test-{}-events", name);
+        let messages_endpoint = format!("inproc://test-{}-messages", name);
+        let events = context.socket(zmq::PUB).unwrap();
+        events.bind(&events_endpoint).unwrap();
+        let messages = context.socket(zmq::SUB).unwrap();
+        messages.bind(&messages_endpoint).unwrap();
+        for sub in subscriptions {
+            messages
+                .set_subscribe(&get_event_type_bytes_filter(sub).unwrap())
+                .unwrap();
+        }
+        messages.set_rcvtimeo(5000).unwrap();
+
+        let pub_socket = context.socket(zmq::PUB).unwrap();
+        pub_socket.connect(&messages_endpoint).unwrap();
+        let sub_socket = context.socket(zmq::SUB).unwrap();
+        sub_socket.connect(&events_endpoint).unwrap();
+        for sub in ["NewImageEvent", "PluginTerminateEvent"] {
+            sub_socket
+                .set_subscribe(&get_event_type_bytes_filter(sub).unwrap())
+                .unwrap();
+        }
+        // give the subscriptions time to reach the pub sockets
+        std::thread::sleep(std::time::Duration::from_millis(100));
+        let plugin = std::thread::spawn(move || {
+            Box::new(plugin).start(PluginContext::new(1, pub_socket, sub_socket))
+        });
+        (events, messages, plugin)
+    }
+
+    #[test]
+    fn test_scores_new_image_events_in_both_framings() {
+        let context = zmq::Context::new();
+        let (mut events, messages, plugin) = start_score_plugin(
+            &context,
+            "score",
+            ImageScorePlugin::new(1),
+            &["ImageScoredEvent"],
         );
+
+        let mut bldr = FlatBufferBuilder::new();
+        send_new_image_event(&mut events, &mut bldr, "multipart", "png", &[1, 2, 3]).unwrap();
+        // a single frame message, as published before events were split into two frames
+        let payload = make_new_image_msg(&mut bldr, "single-frame", "png", &[1, 2, 3])
+            .unwrap()
+            .to_vec();
+        events
+            .send(frame_event_msg("NewImageEvent", &payload), 0)
+            .unwrap();
+
+        for expected_uuid in ["multipart", "single-frame"] {
+            let (event_type, payload) = recv_event_msg(&messages).unwrap();
+            assert_eq!(event_type, "ImageScoredEvent");
+            match Event::decode(&payload).unwrap() {
+                Event::ImageScored(scored) => assert_eq!(scored.image_uuid, expected_uuid),
+                event => panic!("expected an ImageScoredEvent, got {:?}", event),
+            }
+        }
+        send_plugin_terminate_event(&mut events, &mut bldr).unwrap();
+        plugin.join().unwrap().unwrap();
+    }
+
+    // A NewImageEvent of a three byte image in `image_format`.
+    fn new_image(image_uuid: &str, image_format: &str) -> NewImage {
+        NewImage {
+            image_uuid: image_uuid.to_string(),
+            image_format: image_format.to_string(),
+            image: vec![1, 2, 3],
+            location: None,
+        }
+    }
+
+    #[test]
+    fn test_scores_come_from_the_scorer() {
+        let scorer = FixedScorer::new(vec![
+            ("poodle".to_string(), 0.75),
+            ("beagle".to_string(), 0.25),
+        ]);
+        let mut ctx = MemoryContext::new(1);
+        let meta = ctx.push(&new_image("1234", "png"));
+        ctx.push(&PluginTerminate);
+        ImageScorePlugin::with_scorer(1, Box::new(scorer))
+            .run(&mut ctx)
+            .unwrap();
+
+        let expected = ImageScored {
+            image_uuid: "1234".to_string(),
+            scores: vec![
+                ImageScore {
+                    label: "poodle".to_string(),
+                    probability: 0.75,
+                },
+                ImageScore {
+                    label: "beagle".to_string(),
+                    probability: 0.25,
+                },
+            ],
+        };
+        let (scored, scored_meta) = &ctx.published()[0];
+        assert_eq!(scored, &Event::ImageScored(expected));
+        // a reply to the NewImageEvent
+        assert_eq!(scored_meta.correlation_id, meta.correlation_id);
+        assert_eq!(ctx.published().len(), 1);
+    }
+
+    #[test]
+    fn test_images_below_threshold_are_rejected() {
+        let scores = vec![("poodle".to_string(), 0.25), ("beagle".to_string(), 0.5)];
+        let plugin = ImageScorePlugin::new(1).threshold(0.75);
+        assert_eq!(
+            plugin.outcome("1234".to_string(), scores.clone()),
+            Event::ImageRejected(ImageRejected {
+                image_uuid: "1234".to_string(),
+                top_label: "beagle".to_string(),
+                probability: 0.5,
+            })
+        );
+        let plugin = plugin.threshold(0.5);
+        assert!(matches!(
+            plugin.outcome("1234".to_string(), scores.clone()),
+            Event::ImageScored(scored) if scored.scores.len() == 2
+        ));
+        // without a threshold nothing is rejected, not even an image without scores
+        let plugin = ImageScorePlugin::new(1);
+        assert!(matches!(
+            plugin.outcome("1234".to_string(), vec![]),
+            Event::ImageScored(_)
+        ));
+    }
+
+    // Scores png images only.
+    struct PngScorer;
+
+    impl ImageScorer for PngScorer {
+        fn score(&mut self, _image: &[u8], format: &str) -> Result<Vec<(String, f32)>, ScoreError> {
+            if format != "png" {
+                return Err(ScoreError::UnsupportedFormat(format.to_string()));
+            }
+            Ok(vec![("labrador".to_string(), 1.0)])
+        }
+    }
+
+    #[test]
+    fn test_scorer_errors_are_published() {
+        let mut ctx = MemoryContext::new(1);
+        ctx.push(&new_image("gif", "gif"));
+        ctx.push(&new_image("png", "png"));
+        ctx.push(&PluginTerminate);
+        ImageScorePlugin::with_scorer(1, Box::new(PngScorer))
+            .run(&mut ctx)
+            .unwrap();
+
+        assert_eq!(
+            ctx.published()[0].0,
+            Event::ImageScoreFailed(ImageScoreFailed {
+                image_uuid: "gif".to_string(),
+                error: "unsupported image format: gif".to_string(),
+            })
+        );
+        // the plugin goes on with the next image
+        match &ctx.published()[1].0 {
+            Event::ImageScored(scored) => assert_eq!(scored.image_uuid, "png"),
+            event => panic!("expected an ImageScoredEvent, got {:?}", event),
+        }
+    }
+
+    #[test]
+    fn test_unreadable_images_are_dead_lettered() {
+        let mut bldr = FlatBufferBuilder::new();
+        let image = make_new_image_msg(&mut bldr, "1234", "png", &[1, 2, 3])
+            .unwrap()
+            .to_vec();
+        let corrupt = &image[..image.len() / 2];
+        // a valid NewImageEvent, but without an image uuid
+        bldr.reset();
+        let image_format = bldr.create_string("png");
+        let new_image_event = NewImageEvent::create(
+            &mut bldr,
+            &NewImageEventArgs {
+                image_format: Some(image_format),
+                ..Default::default()
+            },
+        );
+        let event = FbEvent::create(
+            &mut bldr,
+            &EventArgs {
+                event_type: EventType::NewImageEvent,
+                event: Some(new_image_event.as_union_value()),
+            },
+        );
+        bldr.finish(event, None);
+        let anonymous = bldr.finished_data().to_vec();
+        let mut ctx = MemoryContext::new(1);
+        for payload in [corrupt, &anonymous[..]] {
+            // as a publisher on a raw socket would send them, without an envelope
+            ctx.push_msg(EventMsg {
+                event_type: "NewImageEvent".to_string(),
+                meta: None,
+                payload: payload.into(),
+            });
+        }
+        ctx.push(&new_image("good", "png"));
+        ctx.push(&PluginTerminate);
+        ImageScorePlugin::new(1).run(&mut ctx).unwrap();
+
+        for ((published, _), (payload, reason)) in ctx.published().iter().zip([
+            (corrupt, "malformed NewImageEvent payload"),
+            (&anonymous[..], "NewImageEvent without image_uuid"),
+        ]) {
+            let dead_letter = match published {
+                Event::DeadLetter(dead_letter) => dead_letter,
+                event => panic!("expected a DeadLetterEvent, got {:?}", event),
+            };
+            assert!(
+                dead_letter.reason.starts_with(reason),
+                "{}",
+                dead_letter.reason
+            );
+            assert_eq!(
+                dead_letter,
+                &DeadLetter {
+                    plugin_id: 1,
+                    event_type: "NewImageEvent".to_string(),
+                    envelope: Vec::new(),
+                    payload: payload.to_vec(),
+                    reason: dead_letter.reason.clone(),
+                }
+            );
+        }
+        // the plugin goes on with the next image
+        match &ctx.published()[2].0 {
+            Event::ImageScored(scored) => assert_eq!(scored.image_uuid, "good"),
+            event => panic!("expected an ImageScoredEvent, got {:?}", event),
+        }
+    }
+
+    #[test]
+    fn test_plugin_returns_after_its_images() {
+        let mut ctx = MemoryContext::new(1);
+        for image_uuid in ["1", "2", "3"] {
+            ctx.push(&new_image(image_uuid, "png"));
+        }
+        ImageScorePlugin::new(1).images(2).run(&mut ctx).unwrap();
+        assert_eq!(ctx.published().len(), 2);
+        assert_eq!(ctx.pending(), 1);
+        // without a PluginTerminateEvent, the plugin stops once there is no event left
+        assert!(matches!(
+            ImageScorePlugin::new(1).run(&mut ctx),
+            Err(PluginError::Stopped)
+        ));
+        assert_eq!(ctx.published().len(), 3);
     }
-    Ok(())
 }
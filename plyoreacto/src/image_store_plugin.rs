@@ -1,67 +1,557 @@
 //! Image storing plugin. *Plugin 3*
 //! This plugin subscribes to ImageScoredEvent messages and published ImageStoredEvent and
-//! ImageDeletedEvent messages.
+//! ImageDeletedEvent messages. It also subscribes to ImageDeletedRequestEvent messages, removes
+//! the image from its storage and answers with an ImageDeletedEvent telling whether there was one.
+//! With a storage backend, it also subscribes to NewImageEvent messages and keeps each image
+//! until the image is scored (or rejected), so that it can put the images it stores in the
+//! backend. Images that cannot be stored get an ImageStoreFailedEvent instead of an ImageStoredEvent.
+//! With a dedup index, an image whose bytes were already stored is not put in the backend again:
+//! its ImageStoredEvent points at the earlier location and is marked as deduplicated. Deleting
+//! the image that was written forgets its bytes, so the next image with them is written again.
+//! The bytes of an image passed by reference are read from its location when it is stored.
+//! It can report backpressure, for the plugins producing images to pause while its storage does
+//! not keep up. With its events delivered at least once (see the `acks` module), it acknowledges
+//! the events of an image once the image is stored (or not), and skips those delivered again
+//! for an image its backend already holds; a scored image whose NewImageEvent has not come yet
+//! waits for it. An event the plugin cannot read is dead-lettered (see
+//! `PluginContext::dead_letter`) and the plugin goes on with the next one. When the engine
+//! starts shutting down (an EngineStoppingEvent), it flushes its storage backend and dedup index.
 //!
 
-use flatbuffers::FlatBufferBuilder;
-use zmq::Socket;
-
-use crate::events::{bytes_to_event, send_image_deleted_event, send_image_stored_event};
-
-pub fn start(
-    pub_socket: &mut Socket,
-    sub_socket: &mut Socket,
-    bldr: &mut FlatBufferBuilder,
-) -> std::io::Result<()> {
-    // process 5 events
-    let mut count = 0;
-    while count < 5 {
-        let msg_bytes = sub_socket.recv_bytes(0).expect("Error receiving message");
-        let event = bytes_to_event(&msg_bytes).expect("Error getting event");
-        // check type of event -- TODO: remove this when subscriptions work
-        let event_type = event
-            .event_type()
-            .variant_name()
-            .expect("could not get event type");
-        if event_type != "ImageScoredEvent" {
-            println!("******** Image store plugin got unexpected message!!!**********");
-            continue;
-        }
-
-        let image_scored_event = event
-            .event_as_image_scored_event()
-            .expect("could not cast event to ImageScoredEvent");
-        let image_uuid = image_scored_event.image_uuid().unwrap();
-        println!(
+use std::collections::HashMap;
+
+use log::{debug, error, info, warn};
+
+use crate::events::{
+    read_image_scored, Event, EventMeta, ImageDeleted, ImageStoreFailed, ImageStored, NewImage,
+};
+use crate::image_payload::ImagePayload;
+use crate::new_image_plugin::DEFAULT_IMAGES;
+use crate::plugin::{EventMsg, EventSink, EventSource, Plugin, PluginContext, PluginError};
+use crate::settings::{PluginSettings, SettingsError};
+use crate::storage::{DedupIndex, FilesystemStore, StorageBackend, StorageError};
+
+/// The image storing plugin, for registering with a `PluginRegistry`. A `storage_root` setting
+/// (a path; see `PluginRegistry::settings`) has it store the images in a `FilesystemStore` at
+/// that root instead of the storage it was made with, if any.
+pub struct ImageStorePlugin {
+    plugin_id: i32,
+    storage: Option<Box<dyn StorageBackend + Send>>,
+    dedup: Option<DedupIndex>,
+    // how many scored images to handle before returning
+    images: usize,
+    // the backlog over which the plugin reports backpressure, if it does
+    backpressure: Option<usize>,
+}
+
+impl ImageStorePlugin {
+    /// A plugin without a storage backend: it publishes whether each image is kept, but does
+    /// not write it anywhere.
+    pub fn new(plugin_id: i32) -> Self {
+        ImageStorePlugin {
+            plugin_id,
+            storage: None,
+            dedup: None,
+            images: DEFAULT_IMAGES,
+            backpressure: None,
+        }
+    }
+
+    /// How many scored images to store (or not) before the plugin returns; 5 by default, like
+    /// the images the `NewImagePlugin` generates.
+    pub fn images(mut self, images: usize) -> Self {
+        self.images = images;
+        self
+    }
+
+    /// Put the images the plugin stores in `storage`.
+    pub fn storage(mut self, storage: Box<dyn StorageBackend + Send>) -> Self {
+        self.storage = Some(storage);
+        self
+    }
+
+    /// Store the same bytes only once, remembering where they were stored in `index`. Only
+    /// applies with a storage backend.
+    pub fn deduplicate(mut self, index: DedupIndex) -> Self {
+        self.dedup = Some(index);
+        self
+    }
+
+    /// Report backpressure once more than `threshold` events wait to be handled (see
+    /// `PluginContext::report_backpressure`), for producers such as a `NewImagePlugin` that
+    /// pauses on backpressure to wait for the storage to catch up.
+    pub fn backpressure(mut self, threshold: usize) -> Self {
+        self.backpressure = Some(threshold);
+        self
+    }
+
+    // Whether the backend holds the image `image_uuid` already, which only matters when the
+    // NewImageEvents are delivered at least once and may come again.
+    fn has_stored(&self, ctx: &impl EventSink, image_uuid: &str) -> bool {
+        let stored = match &self.storage {
+            Some(storage) if ctx.acknowledges("NewImageEvent") => storage.get(image_uuid),
+            _ => return false,
+        };
+        matches!(stored, Ok(Some(_)))
+    }
+
+    // Store (or not) the image scored in `msg` and publish the outcome; false if the image was
+    // stored before, or waits in `early` for its NewImageEvent. Both events of an image are
+    // acknowledged once it is handled.
+    fn scored(
+        &mut self,
+        ctx: &mut impl EventSink,
+        msg: &EventMsg,
+        pending: &mut Pending,
+        early: &mut HashMap<String, EventMsg>,
+    ) -> Result<bool, PluginError> {
+        let image_scored_event = match read_image_scored(&msg.payload) {
+            Ok(image_scored_event) => image_scored_event,
+            Err(e) => {
+                let reason = e.to_string();
+                ctx.dead_letter(&msg.event_type, msg.meta.as_ref(), &msg.payload, &reason)?;
+                return Ok(true);
+            }
+        };
+        let image_uuid = image_scored_event.image_uuid().unwrap_or_default();
+        debug!(
+            plugin_id = ctx.plugin_id();
             "Image stored plugin got ImageScored event for image {}",
             image_uuid
         );
+        let (mut image, image_meta) = match pending.remove(image_uuid) {
+            Some((image, meta)) => (Some(image), meta),
+            None => (None, None),
+        };
+        if image.is_none() && self.storage.is_some() && ctx.acknowledges("NewImageEvent") {
+            if self.has_stored(ctx, image_uuid) {
+                // stored before the plugin was restarted, and delivered again
+                ctx.ack(&msg.event_type, msg.meta.as_ref())?;
+            } else {
+                early.insert(image_uuid.to_string(), msg.clone());
+            }
+            return Ok(false);
+        }
         // If the probability of the image containing a laborador is >= 0.5, we keep the image
-        let scores = image_scored_event
-            .scores()
-            .expect("could not get image scores");
+        let scores = image_scored_event.scores().into_iter().flatten();
         for score in scores {
-            if score.label().expect("could not get score label") == "labrador" {
+            if score.label() == Some("labrador") {
                 // found the labrador score, check the probability
-                if score.probability() < 0.5 {
-                    send_image_deleted_event(pub_socket, bldr, image_uuid)
-                        .expect("could not sent image deleted event");
-                    println!(
-                        "(IMAGE DELETED -- {}) Image stored plugin sent an image deleted event for image {}", image_uuid,
-                        image_uuid
-                    );
+                let outcome = if score.probability() < 0.5 {
+                    Event::ImageDeleted(ImageDeleted {
+                        image_uuid: image_uuid.to_string(),
+                        existed: false,
+                    })
                 } else {
-                    send_image_stored_event(pub_socket, bldr, image_uuid)
-                        .expect("could not sent image deleted event");
-                    println!(
-                        "(IMAGE STORED -- {}) Image stored plugin sent an image stored event for image {}", image_uuid, 
-                        image_uuid
+                    self.store(image_uuid, image.take())
+                };
+                // tie the outcome to the image's earlier events, if they came in an envelope
+                match &msg.meta {
+                    Some(meta) => ctx.publish_reply(meta, &outcome)?,
+                    None => ctx.publish(&outcome)?,
+                };
+                match outcome {
+                    Event::ImageDeleted(_) => info!(
+                        plugin_id = ctx.plugin_id();
+                        "(IMAGE DELETED -- {}) Image stored plugin sent an image deleted event for image {}",
+                        image_uuid, image_uuid
+                    ),
+                    Event::ImageStoreFailed(e) => error!(
+                        plugin_id = ctx.plugin_id();
+                        "(IMAGE STORE FAILED -- {}) Image stored plugin could not store image {}: {}",
+                        image_uuid, image_uuid, e.error
+                    ),
+                    _ => info!(
+                        plugin_id = ctx.plugin_id();
+                        "(IMAGE STORED -- {}) Image stored plugin sent an image stored event for image {}",
+                        image_uuid, image_uuid
+                    ),
+                }
+            }
+        }
+        ctx.ack("NewImageEvent", image_meta.as_ref())?;
+        ctx.ack(&msg.event_type, msg.meta.as_ref())?;
+        Ok(true)
+    }
+
+    // Write out what the storage backend and the dedup index hold back, as the engine is about to
+    // stop; failures are only logged, there being nothing else to do about them now.
+    fn flush(&mut self, plugin_id: i32) {
+        let flushed = match (&mut self.storage, &mut self.dedup) {
+            (None, _) => return,
+            (Some(storage), dedup) => storage
+                .flush()
+                .and_then(|()| dedup.as_mut().map_or(Ok(()), DedupIndex::flush)),
+        };
+        match flushed {
+            Ok(()) => info!(plugin_id; "Image store plugin flushed its storage"),
+            Err(e) => error!(plugin_id; "Image store plugin could not flush its storage: {}", e),
+        }
+    }
+
+    // The event published for a delete request: whether the image was stored, or why it could not
+    // be deleted.
+    fn delete(&mut self, image_uuid: &str) -> Event {
+        let deleted = match &mut self.storage {
+            None => Ok(false),
+            Some(storage) => delete_stored(storage.as_mut(), self.dedup.as_mut(), image_uuid),
+        };
+        match deleted {
+            Ok(existed) => Event::ImageDeleted(ImageDeleted {
+                image_uuid: image_uuid.to_string(),
+                existed,
+            }),
+            Err(e) => Event::ImageStoreFailed(ImageStoreFailed {
+                image_uuid: image_uuid.to_string(),
+                error: format!("could not delete the image: {}", e),
+            }),
+        }
+    }
+
+    // The event published for a stored image: where it was stored, or why it could not be.
+    fn store(&mut self, image_uuid: &str, image: Option<NewImage>) -> Event {
+        let stored = match (&mut self.storage, image) {
+            (None, _) => Ok((String::new(), false)),
+            (_, None) => Err("the image was not received".to_string()),
+            (Some(storage), Some(image)) => ImagePayload::from(&image)
+                .bytes()
+                .map_err(|e| e.to_string())
+                .and_then(|bytes| {
+                    put_stored(
+                        storage.as_mut(),
+                        self.dedup.as_mut(),
+                        image_uuid,
+                        &image.image_format,
+                        &bytes,
+                    )
+                    .map_err(|e| e.to_string())
+                }),
+        };
+        match stored {
+            Ok((location, deduplicated)) => Event::ImageStored(ImageStored {
+                image_uuid: image_uuid.to_string(),
+                path: location,
+                deduplicated,
+            }),
+            Err(error) => Event::ImageStoreFailed(ImageStoreFailed {
+                image_uuid: image_uuid.to_string(),
+                error,
+            }),
+        }
+    }
+}
+
+// The images waiting for their score, by uuid, with the envelope of their NewImageEvent.
+type Pending = HashMap<String, (NewImage, Option<EventMeta>)>;
+
+// Put an image in `storage`, unless the dedup index knows where its bytes already are; where it
+// is, and whether it was there already.
+fn put_stored(
+    storage: &mut (dyn StorageBackend + Send),
+    dedup: Option<&mut DedupIndex>,
+    image_uuid: &str,
+    image_format: &str,
+    image: &[u8],
+) -> Result<(String, bool), StorageError> {
+    let index = match dedup {
+        None => {
+            return storage
+                .put(image_uuid, image_format, image)
+                .map(|location| (location, false))
+        }
+        Some(index) => index,
+    };
+    let hash = DedupIndex::hash(image);
+    if let Some(location) = index.get(&hash) {
+        return Ok((location.clone(), true));
+    }
+    let location = storage.put(image_uuid, image_format, image)?;
+    index.insert(&hash, &location)?;
+    Ok((location, false))
+}
+
+// Delete an image from `storage`, first forgetting its bytes in the dedup index.
+fn delete_stored(
+    storage: &mut (dyn StorageBackend + Send),
+    dedup: Option<&mut DedupIndex>,
+    image_uuid: &str,
+) -> Result<bool, StorageError> {
+    if let Some(index) = dedup {
+        if let Some(image) = storage.get(image_uuid)? {
+            index.remove(&DedupIndex::hash(&image))?;
+        }
+    }
+    storage.delete(image_uuid)
+}
+
+impl Plugin for ImageStorePlugin {
+    fn id(&self) -> i32 {
+        self.plugin_id
+    }
+
+    fn name(&self) -> &str {
+        "image-store"
+    }
+
+    fn subscriptions(&self) -> &[&str] {
+        match self.storage {
+            None => &["ImageScoredEvent", "ImageDeletedRequestEvent"],
+            Some(_) => &[
+                "NewImageEvent",
+                "ImageScoredEvent",
+                "ImageRejectedEvent",
+                "ImageScoreFailedEvent",
+                "ImageDeletedRequestEvent",
+                "EngineStoppingEvent",
+            ],
+        }
+    }
+
+    fn publications(&self) -> &[&str] {
+        &["ImageStoredEvent", "ImageDeletedEvent"]
+    }
+
+    // a plugin with storage subscribes to more events
+    fn configure(&mut self, settings: &PluginSettings) -> Result<(), SettingsError> {
+        if settings.contains("storage_root") {
+            let root = settings.get_path("storage_root")?;
+            self.storage = Some(Box::new(FilesystemStore::new(root)));
+        }
+        Ok(())
+    }
+
+    fn start(mut self: Box<Self>, mut ctx: PluginContext) -> Result<(), PluginError> {
+        if let Some(threshold) = self.backpressure {
+            ctx.report_backpressure(threshold);
+        }
+        self.run(&mut ctx)
+    }
+}
+
+impl ImageStorePlugin {
+    /// Store (or not) the images scored in the events `ctx` receives, answer the delete
+    /// requests and publish the outcomes, as the plugin does once the engine starts it, until
+    /// `ctx` receives a PluginTerminateEvent or the plugin has handled its `images`; with a
+    /// `MemoryContext`, for testing the plugin without an engine. Backpressure is only
+    /// reported with the context the engine starts the plugin with.
+    pub fn run(&mut self, ctx: &mut (impl EventSource + EventSink)) -> Result<(), PluginError> {
+        let mut pending = Pending::new();
+        // with NewImageEvents delivered at least once, the scored images that came before their
+        // NewImageEvent, by uuid
+        let mut early = HashMap::<String, EventMsg>::new();
+        // process the scored images
+        let mut count = 0;
+        while count < self.images {
+            // the event of the previous round, if any, is handled
+            ctx.done()?;
+            let msg = ctx.next_event()?;
+            match msg.event_type.as_str() {
+                "PluginTerminateEvent" => {
+                    info!(
+                        plugin_id = ctx.plugin_id();
+                        "Image store plugin got terminate event, exiting"
+                    );
+                    break;
+                }
+                "NewImageEvent" => {
+                    let Some(Event::NewImage(image)) = ctx.decode_or_dead_letter(&msg)? else {
+                        continue;
+                    };
+                    if self.has_stored(ctx, &image.image_uuid) {
+                        // stored before the plugin was restarted, and delivered again
+                        ctx.ack(&msg.event_type, msg.meta.as_ref())?;
+                        continue;
+                    }
+                    let image_uuid = image.image_uuid.clone();
+                    pending.insert(image_uuid.clone(), (image, msg.meta));
+                    if let Some(scored) = early.remove(&image_uuid) {
+                        if self.scored(ctx, &scored, &mut pending, &mut early)? {
+                            count += 1;
+                        }
+                    }
+                    continue;
+                }
+                // these images are never scored, so they are not kept either
+                "ImageRejectedEvent" | "ImageScoreFailedEvent" => {
+                    let image = match ctx.decode_or_dead_letter(&msg)? {
+                        Some(Event::ImageRejected(e)) => pending.remove(&e.image_uuid),
+                        Some(Event::ImageScoreFailed(e)) => pending.remove(&e.image_uuid),
+                        _ => None,
+                    };
+                    if let Some((_, meta)) = image {
+                        ctx.ack("NewImageEvent", meta.as_ref())?;
+                    }
+                    ctx.ack(&msg.event_type, msg.meta.as_ref())?;
+                    continue;
+                }
+                "ImageDeletedRequestEvent" => {
+                    if let Some(Event::ImageDeletedRequest(request)) =
+                        ctx.decode_or_dead_letter(&msg)?
+                    {
+                        let outcome = self.delete(&request.image_uuid);
+                        match &msg.meta {
+                            Some(meta) => ctx.publish_reply(meta, &outcome)?,
+                            None => ctx.publish(&outcome)?,
+                        };
+                        info!(
+                            plugin_id = ctx.plugin_id();
+                            "Image store plugin answered the delete request for image {}",
+                            request.image_uuid
+                        );
+                        ctx.ack(&msg.event_type, msg.meta.as_ref())?;
+                    }
+                    continue;
+                }
+                "EngineStoppingEvent" => {
+                    self.flush(ctx.plugin_id());
+                    continue;
+                }
+                "ImageScoredEvent" => {}
+                _ => {
+                    warn!(
+                        plugin_id = ctx.plugin_id(), event_type = msg.event_type.as_str();
+                        "Image store plugin got unexpected message {}",
+                        msg.event_type
                     );
+                    continue;
                 }
             }
+            if self.scored(ctx, &msg, &mut pending, &mut early)? {
+                count += 1;
+            }
         }
-        count += 1;
+
+        Ok(())
     }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::events::{
+        EngineStopping, ImageDeletedRequest, ImageScore, ImageScored, PluginTerminate,
+    };
+    use crate::memory_context::MemoryContext;
+    use crate::storage::InMemoryStore;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
 
-    Ok(())
+    // An ImageScoredEvent with a labrador score of `probability`.
+    fn scored(image_uuid: &str, probability: f32) -> ImageScored {
+        ImageScored {
+            image_uuid: image_uuid.to_string(),
+            scores: vec![ImageScore {
+                label: "labrador".to_string(),
+                probability,
+            }],
+        }
+    }
+
+    #[test]
+    fn test_scored_images_are_stored_or_deleted() {
+        let storage = InMemoryStore::new();
+        let mut ctx = MemoryContext::new(2).acknowledge(&["NewImageEvent", "ImageScoredEvent"]);
+        for image_uuid in ["labrador", "poodle"] {
+            ctx.push(&NewImage {
+                image_uuid: image_uuid.to_string(),
+                image_format: "png".to_string(),
+                image: vec![1, 2, 3],
+                location: None,
+            });
+        }
+        let scored_meta = ctx.push(&scored("labrador", 0.9));
+        ctx.push(&scored("poodle", 0.1));
+        ctx.push(&ImageDeletedRequest {
+            image_uuid: "labrador".to_string(),
+        });
+        ctx.push(&PluginTerminate);
+        ImageStorePlugin::new(2)
+            .storage(Box::new(storage.clone()))
+            .run(&mut ctx)
+            .unwrap();
+
+        let published: Vec<_> = ctx.published().iter().map(|(event, _)| event).collect();
+        match published[0] {
+            Event::ImageStored(stored) => assert_eq!(stored.image_uuid, "labrador"),
+            event => panic!("expected an ImageStoredEvent, got {:?}", event),
+        }
+        assert_eq!(
+            ctx.published()[0].1.correlation_id,
+            scored_meta.correlation_id
+        );
+        assert_eq!(
+            published[1..],
+            [
+                &Event::ImageDeleted(ImageDeleted {
+                    image_uuid: "poodle".to_string(),
+                    existed: false,
+                }),
+                &Event::ImageDeleted(ImageDeleted {
+                    image_uuid: "labrador".to_string(),
+                    existed: true,
+                }),
+            ]
+        );
+        assert!(storage.is_empty());
+        // both events of each image are acknowledged once it is handled
+        assert_eq!(ctx.acked().len(), 4);
+    }
+
+    // An InMemoryStore counting how many times it was flushed.
+    struct FlushCounter {
+        store: InMemoryStore,
+        flushes: Arc<AtomicUsize>,
+    }
+
+    impl StorageBackend for FlushCounter {
+        fn put(
+            &mut self,
+            image_uuid: &str,
+            image_format: &str,
+            image: &[u8],
+        ) -> Result<String, StorageError> {
+            self.store.put(image_uuid, image_format, image)
+        }
+
+        fn get(&self, image_uuid: &str) -> Result<Option<Vec<u8>>, StorageError> {
+            self.store.get(image_uuid)
+        }
+
+        fn delete(&mut self, image_uuid: &str) -> Result<bool, StorageError> {
+            self.store.delete(image_uuid)
+        }
+
+        fn flush(&mut self) -> Result<(), StorageError> {
+            self.flushes.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_storage_is_flushed_when_the_engine_is_stopping() {
+        let flushes = Arc::new(AtomicUsize::new(0));
+        let storage = FlushCounter {
+            store: InMemoryStore::new(),
+            flushes: Arc::clone(&flushes),
+        };
+        let mut ctx = MemoryContext::new(2);
+        ctx.push(&NewImage {
+            image_uuid: "labrador".to_string(),
+            image_format: "png".to_string(),
+            image: vec![1, 2, 3],
+            location: None,
+        });
+        ctx.push(&scored("labrador", 0.9));
+        ctx.push(&EngineStopping {
+            reason: "shutdown requested".to_string(),
+        });
+        ctx.push(&PluginTerminate);
+        ImageStorePlugin::new(2)
+            .storage(Box::new(storage))
+            .run(&mut ctx)
+            .unwrap();
+
+        assert_eq!(flushes.load(Ordering::SeqCst), 1);
+        assert!(matches!(ctx.published()[0].0, Event::ImageStored(_)));
+    }
 }
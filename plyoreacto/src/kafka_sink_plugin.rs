@@ -0,0 +1,721 @@
+//! Kafka sink plugin, built with the `kafka` feature.
+//! This plugin mirrors events to Kafka for the systems downstream of the engine: it subscribes
+//! to the event types it is configured with and produces each event as a record, to a topic of
+//! its type or to a single topic, with the type in the `event_type` header either way. The value
+//! of a record is the flatbuffer of the event, or its JSON (see `Event::to_json`); its key is
+//! the uuid of the image of the event, so that the records of an image go to the same partition
+//! in the order they were published.
+//!
+//! Records are produced in batches of up to `KafkaConfig::batch_size`, sent once full or once
+//! the oldest record waited for `KafkaConfig::linger`, one batch at a time. A batch failing to
+//! be produced is sent again, with exponential backoff; past `KafkaConfig::max_retries`, its
+//! events are dead-lettered (see `PluginContext::dead_letter`). A batch sent again may be
+//! written twice if the brokers wrote it before failing.
+//!
+//! The plugin produces with librdkafka, through the rdkafka crate, over TCP without TLS or SASL:
+//! it fetches the partitions of the topics and their leaders from the bootstrap servers, and
+//! produces to the leaders, partitioning the records by key as the Java client does.
+//!
+
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use log::{debug, info, warn};
+use rdkafka::config::ClientConfig;
+use rdkafka::error::{KafkaResult, RDKafkaErrorCode};
+use rdkafka::message::{DeliveryResult, Header, OwnedHeaders};
+use rdkafka::producer::{BaseProducer, BaseRecord, Producer as _, ProducerContext};
+use rdkafka::ClientContext;
+
+use crate::event_engine::EngineError;
+use crate::events::{known_event_type, now_ms, EventMeta};
+use crate::plugin::{EventMsg, Plugin, PluginContext, PluginError};
+
+// Used unless set in the `KafkaConfig`.
+const DEFAULT_CLIENT_ID: &str = "plyoreacto";
+const DEFAULT_BATCH_SIZE: usize = 100;
+const DEFAULT_LINGER: Duration = Duration::from_millis(10);
+const DEFAULT_MAX_RETRIES: u32 = 5;
+const DEFAULT_RETRY_BACKOFF: Duration = Duration::from_millis(100);
+const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+// How long a send waits for librdkafka to make room in its full queue before trying again.
+const QUEUE_FULL_WAIT: Duration = Duration::from_millis(100);
+
+/// The topics a `KafkaSinkPlugin` produces the events to.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum KafkaTopics {
+    /// A topic for each event type, named after it with a prefix, e.g.,
+    /// "plyoreacto.ImageStoredEvent" for the prefix "plyoreacto.".
+    PerEventType { prefix: String },
+    /// A single topic for all the events.
+    Single(String),
+}
+
+/// How a `KafkaSinkPlugin` serializes the events into the values of the records.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum KafkaFormat {
+    /// The flatbuffer of the event, as described by events.fbs.
+    #[default]
+    Flatbuffers,
+    /// The JSON object of `Event::to_json`.
+    Json,
+}
+
+/// Where a `KafkaSinkPlugin` produces the events, and how.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KafkaConfig {
+    // host:port of the brokers the metadata of the topics is fetched from, e.g., "kafka:9092";
+    // they are tried in order
+    pub bootstrap_servers: Vec<String>,
+    pub topics: KafkaTopics,
+    pub format: KafkaFormat,
+    // the client id the brokers log the requests under
+    pub client_id: String,
+    // the most records produced in one batch
+    pub batch_size: usize,
+    // how long a record waits for others to fill its batch
+    pub linger: Duration,
+    // how many times a batch failing to be produced is sent again before its events are
+    // dead-lettered
+    pub max_retries: u32,
+    // wait before the first retry, doubled before each further one
+    pub retry_backoff: Duration,
+    // how long the brokers may take to answer a request, the replication of a batch included
+    pub request_timeout: Duration,
+}
+
+impl KafkaConfig {
+    /// A config producing flatbuffers to `topics` of the brokers `bootstrap_servers` with the
+    /// default batching and retries.
+    pub fn new(bootstrap_servers: &[&str], topics: KafkaTopics) -> Self {
+        KafkaConfig {
+            bootstrap_servers: bootstrap_servers.iter().map(|s| s.to_string()).collect(),
+            topics,
+            format: KafkaFormat::Flatbuffers,
+            client_id: DEFAULT_CLIENT_ID.to_string(),
+            batch_size: DEFAULT_BATCH_SIZE,
+            linger: DEFAULT_LINGER,
+            max_retries: DEFAULT_MAX_RETRIES,
+            retry_backoff: DEFAULT_RETRY_BACKOFF,
+            request_timeout: DEFAULT_REQUEST_TIMEOUT,
+        }
+    }
+}
+
+// A record to produce.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct KafkaRecord {
+    pub(crate) topic: String,
+    pub(crate) key: Option<Vec<u8>>,
+    pub(crate) value: Vec<u8>,
+    pub(crate) headers: Vec<(String, Vec<u8>)>,
+    pub(crate) timestamp_ms: u64,
+}
+
+// Produces the records of a `KafkaSinkPlugin`; a trait so that tests can stand in for the
+// brokers. An `Err` means the batch may not have been written.
+pub(crate) trait Producer: Send {
+    fn produce(&mut self, records: &[KafkaRecord]) -> Result<(), String>;
+}
+
+/// The Kafka sink plugin, for registering with a `PluginRegistry`.
+pub struct KafkaSinkPlugin {
+    plugin_id: i32,
+    config: KafkaConfig,
+    subscriptions: Vec<&'static str>,
+    producer: Box<dyn Producer>,
+}
+
+impl KafkaSinkPlugin {
+    /// A plugin producing the events of `event_types` as `config` says. Fails if one of the
+    /// types is not an event type, or if `config` is not one librdkafka takes.
+    pub fn new(
+        plugin_id: i32,
+        config: KafkaConfig,
+        event_types: &[&str],
+    ) -> Result<Self, EngineError> {
+        let producer = BrokerProducer::new(&config).map_err(|e| EngineError::PluginSpawn {
+            plugin_id,
+            reason: format!("could not make a Kafka producer: {}", e),
+        })?;
+        KafkaSinkPlugin::with_producer(plugin_id, config, event_types, Box::new(producer))
+    }
+
+    pub(crate) fn with_producer(
+        plugin_id: i32,
+        config: KafkaConfig,
+        event_types: &[&str],
+        producer: Box<dyn Producer>,
+    ) -> Result<Self, EngineError> {
+        let mut subscriptions = Vec::new();
+        for event_type in event_types {
+            let name =
+                known_event_type(event_type).ok_or_else(|| EngineError::UnknownEventType {
+                    event_type: event_type.to_string(),
+                })?;
+            if !subscriptions.contains(&name) {
+                subscriptions.push(name);
+            }
+        }
+        Ok(KafkaSinkPlugin {
+            plugin_id,
+            config,
+            subscriptions,
+            producer,
+        })
+    }
+
+    // The record of the event `msg`.
+    fn record(&self, msg: &EventMsg) -> Result<KafkaRecord, PluginError> {
+        let event = msg.decode()?;
+        let topic = match &self.config.topics {
+            KafkaTopics::PerEventType { prefix } => format!("{}{}", prefix, msg.event_type),
+            KafkaTopics::Single(topic) => topic.clone(),
+        };
+        let value = match self.config.format {
+            KafkaFormat::Flatbuffers => msg.payload.to_vec(),
+            KafkaFormat::Json => event.to_json(),
+        };
+        let mut headers = vec![(
+            "event_type".to_string(),
+            msg.event_type.clone().into_bytes(),
+        )];
+        if let Some(meta) = &msg.meta {
+            for (name, id) in [
+                ("event_id", meta.event_id),
+                ("correlation_id", meta.correlation_id),
+            ] {
+                headers.push((name.to_string(), id.to_string().into_bytes()));
+            }
+        }
+        Ok(KafkaRecord {
+            topic,
+            key: event.image_uuid().map(|uuid| uuid.as_bytes().to_vec()),
+            value,
+            headers,
+            timestamp_ms: msg
+                .meta
+                .as_ref()
+                .map_or_else(now_ms, |meta| meta.timestamp_ms),
+        })
+    }
+
+    // Produce the records of `batch`, retrying with backoff, and dead-letter its events if they
+    // still could not be.
+    fn flush(&mut self, ctx: &mut PluginContext, batch: &mut Batch) -> Result<(), PluginError> {
+        if batch.records.is_empty() {
+            return Ok(());
+        }
+        let mut backoff = self.config.retry_backoff;
+        let mut attempt = 0;
+        let error = loop {
+            match self.producer.produce(&batch.records) {
+                Ok(()) => {
+                    debug!(
+                        plugin_id = self.plugin_id;
+                        "Kafka sink plugin produced {} records", batch.records.len()
+                    );
+                    for (event_type, meta, _) in &batch.events {
+                        ctx.ack(event_type, meta.as_ref())?;
+                    }
+                    batch.clear();
+                    return Ok(());
+                }
+                Err(e) if attempt == self.config.max_retries => break e,
+                Err(e) => {
+                    attempt += 1;
+                    warn!(
+                        plugin_id = self.plugin_id;
+                        "Kafka sink plugin retrying ({} of {}) after: {}",
+                        attempt, self.config.max_retries, e
+                    );
+                    thread::sleep(backoff);
+                    backoff *= 2;
+                }
+            }
+        };
+        let reason = format!(
+            "could not produce it to Kafka after {} attempts: {}",
+            attempt + 1,
+            error
+        );
+        for (event_type, meta, payload) in &batch.events {
+            ctx.dead_letter(event_type, meta.as_ref(), payload, &reason)?;
+        }
+        batch.clear();
+        Ok(())
+    }
+}
+
+impl Plugin for KafkaSinkPlugin {
+    fn id(&self) -> i32 {
+        self.plugin_id
+    }
+
+    fn name(&self) -> &str {
+        "kafka-sink"
+    }
+
+    fn subscriptions(&self) -> &[&str] {
+        &self.subscriptions
+    }
+
+    fn start(mut self: Box<Self>, mut ctx: PluginContext) -> Result<(), PluginError> {
+        info!(
+            plugin_id = self.plugin_id;
+            "Kafka sink plugin producing {:?} to {:?}", self.subscriptions, self.config.topics
+        );
+        let mut batch = Batch::default();
+        loop {
+            // with a batch waiting, only until its linger time is up
+            let next = match batch.started {
+                Some(started) => {
+                    ctx.wait_for_event(self.config.linger.saturating_sub(started.elapsed()))
+                }
+                None => ctx.next_event().map(Some),
+            };
+            match next {
+                Ok(Some(msg)) if msg.event_type == "PluginTerminateEvent" => {
+                    info!(
+                        plugin_id = self.plugin_id;
+                        "Kafka sink plugin got terminate event, exiting"
+                    );
+                    return self.flush(&mut ctx, &mut batch);
+                }
+                Ok(Some(msg)) => {
+                    match self.record(&msg) {
+                        Ok(record) => batch.push(record, &msg),
+                        Err(e) => {
+                            let reason = format!("could not make a Kafka record of it: {}", e);
+                            ctx.dead_letter(
+                                &msg.event_type,
+                                msg.meta.as_ref(),
+                                &msg.payload,
+                                &reason,
+                            )?;
+                        }
+                    }
+                    if batch.records.len() >= self.config.batch_size {
+                        self.flush(&mut ctx, &mut batch)?;
+                    }
+                }
+                Ok(None) => {}
+                Err(PluginError::Stopped) => {
+                    self.flush(&mut ctx, &mut batch)?;
+                    return Err(PluginError::Stopped);
+                }
+                Err(e) => return Err(e),
+            }
+            if batch
+                .started
+                .is_some_and(|started| started.elapsed() >= self.config.linger)
+            {
+                self.flush(&mut ctx, &mut batch)?;
+            }
+        }
+    }
+}
+
+// The records waiting to be produced, and the events they were made of, as received, for
+// dead-lettering them.
+#[derive(Default)]
+struct Batch {
+    records: Vec<KafkaRecord>,
+    events: Vec<(String, Option<EventMeta>, Vec<u8>)>,
+    // when the first record was added
+    started: Option<Instant>,
+}
+
+impl Batch {
+    fn push(&mut self, record: KafkaRecord, msg: &EventMsg) {
+        self.started.get_or_insert_with(Instant::now);
+        self.records.push(record);
+        self.events
+            .push((msg.event_type.clone(), msg.meta, msg.payload.to_vec()));
+    }
+
+    fn clear(&mut self) {
+        self.records.clear();
+        self.events.clear();
+        self.started = None;
+    }
+}
+
+// Produces records to the brokers of a `KafkaConfig` with librdkafka, which fetches the
+// metadata of the topics and keeps a connection to each broker; it neither batches nor retries on
+// its own, the plugin's batches and retries taking the place of those.
+struct BrokerProducer {
+    producer: BaseProducer<DeliveryErrors>,
+    request_timeout: Duration,
+}
+
+impl BrokerProducer {
+    fn new(config: &KafkaConfig) -> KafkaResult<Self> {
+        let timeout_ms = config.request_timeout.as_millis().to_string();
+        let producer = ClientConfig::new()
+            .set("bootstrap.servers", config.bootstrap_servers.join(","))
+            .set("client.id", &config.client_id)
+            // every replica in sync has to have written a record for it to be delivered
+            .set("acks", "all")
+            .set("linger.ms", "0")
+            .set("message.send.max.retries", "0")
+            // the partitions of the keys are those of the Java client
+            .set("partitioner", "murmur2_random")
+            .set("request.timeout.ms", &timeout_ms)
+            .set("message.timeout.ms", &timeout_ms)
+            .create_with_context(DeliveryErrors::default())?;
+        Ok(BrokerProducer {
+            producer,
+            request_timeout: config.request_timeout,
+        })
+    }
+
+    // Queue the records for librdkafka to send, making room in a full queue by sending.
+    fn send(&self, records: &[KafkaRecord]) -> KafkaResult<()> {
+        for record in records {
+            let mut headers = OwnedHeaders::new_with_capacity(record.headers.len());
+            for (key, value) in &record.headers {
+                headers = headers.insert(Header {
+                    key,
+                    value: Some(value),
+                });
+            }
+            let mut message = BaseRecord::<Vec<u8>, _>::to(&record.topic)
+                .payload(&record.value)
+                .headers(headers)
+                .timestamp(record.timestamp_ms as i64);
+            if let Some(key) = &record.key {
+                message = message.key(key);
+            }
+            while let Err((e, returned)) = self.producer.send(message) {
+                if e.rdkafka_error_code() != Some(RDKafkaErrorCode::QueueFull) {
+                    return Err(e);
+                }
+                message = returned;
+                self.producer.poll(QUEUE_FULL_WAIT);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Producer for BrokerProducer {
+    fn produce(&mut self, records: &[KafkaRecord]) -> Result<(), String> {
+        let errors = &self.producer.context().0;
+        errors
+            .lock()
+            .expect("delivery errors lock poisoned")
+            .clear();
+        let sent = self.send(records);
+        // the records queued are sent, or time out, before the batch may be sent again
+        let flushed = self.producer.flush(self.request_timeout);
+        sent.and(flushed).map_err(|e| e.to_string())?;
+        let errors = errors.lock().expect("delivery errors lock poisoned");
+        match errors.first() {
+            Some(e) => Err(format!(
+                "{} of {} records not delivered: {}",
+                errors.len(),
+                records.len(),
+                e
+            )),
+            None => Ok(()),
+        }
+    }
+}
+
+// The errors of the records librdkafka could not deliver, since the batch they were sent in.
+#[derive(Default)]
+struct DeliveryErrors(Mutex<Vec<String>>);
+
+impl ClientContext for DeliveryErrors {}
+
+impl ProducerContext for DeliveryErrors {
+    type DeliveryOpaque = ();
+
+    fn delivery(&self, result: &DeliveryResult<'_>, _: ()) {
+        if let Err((e, _)) = result {
+            self.0
+                .lock()
+                .expect("delivery errors lock poisoned")
+                .push(e.to_string());
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::events::{
+        get_event_type_bytes_filter, recv_event_msg, send_event_msg_with_meta,
+        send_plugin_terminate_event, Event, EventPayload, ImageStored,
+    };
+    use flatbuffers::FlatBufferBuilder;
+    use std::sync::Arc;
+    use std::thread::JoinHandle;
+
+    // Answers the batches with the given results, in order, then produces them; records the
+    // batches it was given.
+    struct ScriptedProducer {
+        results: Vec<Result<(), String>>,
+        batches: Arc<Mutex<Vec<Vec<KafkaRecord>>>>,
+    }
+
+    impl Producer for ScriptedProducer {
+        fn produce(&mut self, records: &[KafkaRecord]) -> Result<(), String> {
+            self.batches.lock().unwrap().push(records.to_vec());
+            match self.results.is_empty() {
+                true => Ok(()),
+                false => self.results.remove(0),
+            }
+        }
+    }
+
+    fn test_config() -> KafkaConfig {
+        KafkaConfig {
+            batch_size: 2,
+            linger: Duration::from_millis(50),
+            max_retries: 2,
+            retry_backoff: Duration::from_millis(1),
+            ..KafkaConfig::new(
+                &["localhost:9092"],
+                KafkaTopics::PerEventType {
+                    prefix: "plyoreacto.".to_string(),
+                },
+            )
+        }
+    }
+
+    // Start `plugin` on sockets connected to stand-ins for the engine's outgoing and incoming
+    // sockets, which are returned along with its thread; the incoming stand-in is subscribed
+    // to the DeadLetterEvents.
+    fn start_sink(
+        context: &zmq::Context,
+        name: &str,
+        plugin: KafkaSinkPlugin,
+    ) -> (
+        zmq::Socket,
+        zmq::Socket,
+        JoinHandle<Result<(), PluginError>>,
+    ) {
+        let events = context.socket(zmq::PUB).unwrap();
+        events.bind(&format!("inproc://{}-events", name)).unwrap();
+        let messages = context.socket(zmq::SUB).unwrap();
+        messages
+            .bind(&format!("inproc://{}-messages", name))
+            .unwrap();
+        messages
+            .set_subscribe(&get_event_type_bytes_filter("DeadLetterEvent").unwrap())
+            .unwrap();
+        messages.set_rcvtimeo(5000).unwrap();
+        let sub_socket = context.socket(zmq::SUB).unwrap();
+        sub_socket
+            .connect(&format!("inproc://{}-events", name))
+            .unwrap();
+        for sub in ["ImageStoredEvent", "PluginTerminateEvent"] {
+            sub_socket
+                .set_subscribe(&get_event_type_bytes_filter(sub).unwrap())
+                .unwrap();
+        }
+        let pub_socket = context.socket(zmq::PUB).unwrap();
+        pub_socket
+            .connect(&format!("inproc://{}-messages", name))
+            .unwrap();
+        let ctx = PluginContext::new(7, pub_socket, sub_socket);
+        // give the subscriptions time to reach the pub sockets
+        std::thread::sleep(Duration::from_millis(100));
+        let plugin = std::thread::spawn(move || Box::new(plugin).start(ctx));
+        (events, messages, plugin)
+    }
+
+    fn stored(image_uuid: &str) -> ImageStored {
+        ImageStored {
+            image_uuid: image_uuid.to_string(),
+            path: format!("/images/{}.png", image_uuid),
+            deduplicated: false,
+        }
+    }
+
+    // Publish the ImageStoredEvents of `image_uuids` on `events`, returning their envelopes.
+    fn publish_stored(events: &zmq::Socket, image_uuids: &[&str]) -> Vec<EventMeta> {
+        let mut bldr = FlatBufferBuilder::new();
+        image_uuids
+            .iter()
+            .map(|image_uuid| {
+                let meta = EventMeta::new(1);
+                let payload = stored(image_uuid).build(&mut bldr).unwrap().to_vec();
+                send_event_msg_with_meta(events, "ImageStoredEvent", &meta, &payload).unwrap();
+                meta
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_events_are_produced_in_batches() {
+        let batches = Arc::new(Mutex::new(Vec::new()));
+        let producer = ScriptedProducer {
+            results: Vec::new(),
+            batches: batches.clone(),
+        };
+        let plugin = KafkaSinkPlugin::with_producer(
+            7,
+            KafkaConfig {
+                format: KafkaFormat::Json,
+                ..test_config()
+            },
+            &["ImageStoredEvent"],
+            Box::new(producer),
+        )
+        .unwrap();
+        let context = zmq::Context::new();
+        let (mut events, _messages, plugin) = start_sink(&context, "kafka-batches", plugin);
+
+        let metas = publish_stored(&events, &["1", "2", "3"]);
+        // the third waits for the linger to be produced
+        std::thread::sleep(Duration::from_millis(200));
+        send_plugin_terminate_event(&mut events, &mut FlatBufferBuilder::new()).unwrap();
+        plugin.join().unwrap().unwrap();
+
+        let batches = batches.lock().unwrap();
+        let sizes: Vec<_> = batches.iter().map(Vec::len).collect();
+        assert_eq!(sizes, [2, 1]);
+        let records: Vec<_> = batches.iter().flatten().collect();
+        for ((record, meta), image_uuid) in records.iter().zip(&metas).zip(["1", "2", "3"]) {
+            assert_eq!(record.topic, "plyoreacto.ImageStoredEvent");
+            assert_eq!(record.key.as_deref(), Some(image_uuid.as_bytes()));
+            assert_eq!(
+                Event::from_json("ImageStoredEvent", &record.value).unwrap(),
+                Event::ImageStored(stored(image_uuid))
+            );
+            assert_eq!(record.timestamp_ms, meta.timestamp_ms);
+            assert_eq!(
+                record.headers,
+                [
+                    ("event_type".to_string(), b"ImageStoredEvent".to_vec()),
+                    (
+                        "event_id".to_string(),
+                        meta.event_id.to_string().into_bytes()
+                    ),
+                    (
+                        "correlation_id".to_string(),
+                        meta.correlation_id.to_string().into_bytes()
+                    ),
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn test_failed_batches_are_retried_then_dead_lettered() {
+        let batches = Arc::new(Mutex::new(Vec::new()));
+        // the first batch goes through on its second attempt, the second never does
+        let results = [Some("leader moved"), None]
+            .into_iter()
+            .chain([Some("broker down"); 3])
+            .map(|error| error.map_or(Ok(()), |e| Err(e.to_string())))
+            .collect();
+        let producer = ScriptedProducer {
+            results,
+            batches: batches.clone(),
+        };
+        let config = KafkaConfig {
+            topics: KafkaTopics::Single("events".to_string()),
+            ..test_config()
+        };
+        let plugin =
+            KafkaSinkPlugin::with_producer(7, config, &["ImageStoredEvent"], Box::new(producer))
+                .unwrap();
+        let context = zmq::Context::new();
+        let (mut events, messages, plugin) = start_sink(&context, "kafka-retries", plugin);
+
+        publish_stored(&events, &["1", "2"]);
+        let metas = publish_stored(&events, &["3", "4"]);
+        let mut dead_letters = Vec::new();
+        for _ in 0..2 {
+            let (event_type, payload) = recv_event_msg(&messages).unwrap();
+            assert_eq!(event_type, "DeadLetterEvent");
+            match Event::decode(&payload).unwrap() {
+                Event::DeadLetter(dead_letter) => dead_letters.push(dead_letter),
+                event => panic!("expected a DeadLetterEvent, got {:?}", event),
+            }
+        }
+        send_plugin_terminate_event(&mut events, &mut FlatBufferBuilder::new()).unwrap();
+        plugin.join().unwrap().unwrap();
+
+        // each batch was sent until it went through or ran out of retries
+        let batches = batches.lock().unwrap();
+        assert_eq!(batches.len(), 2 + 3);
+        assert!(batches.iter().all(|batch| batch[0].topic == "events"));
+        let keys: Vec<_> = batches.iter().map(|batch| batch[0].key.clone()).collect();
+        let first = Some(b"1".to_vec());
+        let third = Some(b"3".to_vec());
+        assert_eq!(
+            keys,
+            [first.clone(), first, third.clone(), third.clone(), third]
+        );
+        for (dead_letter, meta) in dead_letters.iter().zip(&metas) {
+            assert_eq!(dead_letter.plugin_id, 7);
+            assert_eq!(dead_letter.event_type, "ImageStoredEvent");
+            assert_eq!(dead_letter.meta(), Some(*meta));
+            assert_eq!(
+                dead_letter.reason,
+                "could not produce it to Kafka after 3 attempts: broker down"
+            );
+        }
+        assert_eq!(
+            dead_letters[1].event().unwrap(),
+            Event::ImageStored(stored("4"))
+        );
+    }
+
+    #[test]
+    fn test_batches_fail_when_no_broker_answers() {
+        let config = KafkaConfig {
+            request_timeout: Duration::from_secs(1),
+            ..KafkaConfig::new(&["127.0.0.1:1"], KafkaTopics::Single("events".into()))
+        };
+        let mut producer = BrokerProducer::new(&config).unwrap();
+        let record = KafkaRecord {
+            topic: "events".to_string(),
+            key: Some(b"1".to_vec()),
+            value: b"value".to_vec(),
+            headers: vec![("event_type".to_string(), b"ImageStoredEvent".to_vec())],
+            timestamp_ms: now_ms(),
+        };
+        let started = Instant::now();
+        assert!(producer.produce(&[record]).is_err());
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    // Run against a real broker when PLYOREACTO_TEST_KAFKA_BROKERS is set, e.g. to
+    // "localhost:9092"; the topic (PLYOREACTO_TEST_KAFKA_TOPIC, "plyoreacto-test" by default)
+    // must exist or the brokers must create topics on their own.
+    #[test]
+    fn test_produce_against_broker() {
+        let brokers = match std::env::var("PLYOREACTO_TEST_KAFKA_BROKERS") {
+            Ok(brokers) => brokers,
+            Err(_) => return,
+        };
+        let topic = std::env::var("PLYOREACTO_TEST_KAFKA_TOPIC")
+            .unwrap_or_else(|_| "plyoreacto-test".to_string());
+        let brokers: Vec<&str> = brokers.split(',').collect();
+        let config = KafkaConfig::new(&brokers, KafkaTopics::Single(topic.clone()));
+        let mut producer = BrokerProducer::new(&config).unwrap();
+        let records: Vec<_> = (0..10)
+            .map(|i| KafkaRecord {
+                topic: topic.clone(),
+                key: (i % 2 == 0).then(|| format!("image-{}", i).into_bytes()),
+                value: vec![i; 100],
+                headers: vec![("event_type".to_string(), b"ImageStoredEvent".to_vec())],
+                timestamp_ms: now_ms(),
+            })
+            .collect();
+        // a topic created by the request has no leader at first
+        let deadline = Instant::now() + Duration::from_secs(30);
+        while let Err(e) = producer.produce(&records) {
+            assert!(Instant::now() < deadline, "{}", e);
+            std::thread::sleep(Duration::from_millis(500));
+        }
+    }
+}
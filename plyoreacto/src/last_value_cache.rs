@@ -0,0 +1,192 @@
+//! A last-value cache for subscribers that connect after the events they want were published,
+//! such as a dashboard connecting to a running engine. An engine started with
+//! `EventEngineBuilder::last_value_cache(&["MetricsSnapshotEvent"])` remembers the most recent
+//! event of each of these types, and publishes it again whenever a subscription to its type
+//! reaches the outgoing socket: the new subscriber gets the cached event right away instead of
+//! waiting for the next one.
+//!
+//! For this the outgoing socket is an XPUB socket, which passes every subscription on to the
+//! engine, and the engine forwards events with a loop of its own instead of `zmq::proxy` (see
+//! the `middleware` module). Like anything
+//! published, the cached event goes to every subscriber of its type, so those that were already
+//! subscribed receive it a second time; only cache the types whose subscribers take that in
+//! their stride, such as snapshots of state.
+//!
+
+use crate::events::{event_type_header, event_type_names};
+
+// Marks a subscription message of an XPUB socket; unsubscriptions start with 0.
+const SUBSCRIBE: u8 = 1;
+
+// The frames of the most recent event of each cached type, indexed like `event_type_names()`.
+pub(crate) struct LastValueCache {
+    // the header frame of each type, None for the types that are not cached
+    headers: Vec<Option<Vec<u8>>>,
+    last_values: Vec<Option<Vec<Vec<u8>>>>,
+}
+
+impl LastValueCache {
+    // A cache of the events of `event_types`, all of which are event type names.
+    pub(crate) fn new(event_types: &[String]) -> Self {
+        let headers: Vec<_> = event_type_names()
+            .iter()
+            .map(|name| {
+                event_types
+                    .iter()
+                    .any(|cached| cached == name)
+                    .then(|| event_type_header(name))
+            })
+            .collect();
+        LastValueCache {
+            last_values: vec![None; headers.len()],
+            headers,
+        }
+    }
+
+    // Remember the message `frames` if it is an event of a cached type.
+    pub(crate) fn remember(&mut self, frames: &[zmq::Message]) {
+        let Some(header) = frames.first() else {
+            return;
+        };
+        // a single frame message starts with the header just as well
+        let cached = self.headers.iter().position(|cached| {
+            cached
+                .as_ref()
+                .is_some_and(|cached| header.starts_with(cached))
+        });
+        if let Some(i) = cached {
+            self.last_values[i] = Some(frames.iter().map(|frame| frame.to_vec()).collect());
+        }
+    }
+
+    // The cached events to send in answer to the message `subscription` received on the XPUB
+    // socket: none for an unsubscription.
+    pub(crate) fn answer<'a>(
+        &'a self,
+        subscription: &'a [u8],
+    ) -> impl Iterator<Item = &'a Vec<Vec<u8>>> + 'a {
+        let topic = match subscription.split_first() {
+            Some((&SUBSCRIBE, topic)) => Some(topic),
+            _ => None,
+        };
+        topic.into_iter().flat_map(|topic| self.matching(topic))
+    }
+
+    // The cached events that a subscription to `topic` receives: those whose header starts with
+    // it, i.e., all of them for an empty topic.
+    fn matching<'a>(&'a self, topic: &'a [u8]) -> impl Iterator<Item = &'a Vec<Vec<u8>>> + 'a {
+        self.headers
+            .iter()
+            .zip(&self.last_values)
+            .filter_map(move |(header, last_value)| match (header, last_value) {
+                (Some(header), Some(last_value)) if header.starts_with(topic) => Some(last_value),
+                _ => None,
+            })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::event_engine::{EventEngineBuilder, Transport};
+    use crate::events::{event_type_header, parse_event_frames, Event, EventMeta};
+    use crate::events::{EventTypeCount, MetricsSnapshot};
+    use crate::plugin_registry::PluginRegistry;
+
+    // A subscriber to the snapshots published by the engine on `context`.
+    fn subscribe_to_snapshots(context: &zmq::Context) -> zmq::Socket {
+        let subscriber = context.socket(zmq::SUB).unwrap();
+        subscriber
+            .set_subscribe(&event_type_header("MetricsSnapshotEvent"))
+            .unwrap();
+        subscriber.set_rcvtimeo(5000).unwrap();
+        subscriber.connect("inproc://events-last-value").unwrap();
+        subscriber
+    }
+
+    fn message(frames: &[&[u8]]) -> Vec<zmq::Message> {
+        frames
+            .iter()
+            .map(|frame| zmq::Message::from(*frame))
+            .collect()
+    }
+
+    #[test]
+    fn test_only_the_last_event_of_cached_types_is_kept() {
+        let mut cache = LastValueCache::new(&["MetricsSnapshotEvent".to_string()]);
+        let snapshot = event_type_header("MetricsSnapshotEvent");
+        let meta = EventMeta::new(1).to_bytes();
+        cache.remember(&message(&[&snapshot, &meta, b"first"]));
+        cache.remember(&message(&[&snapshot, &meta, b"second"]));
+        cache.remember(&message(&[&event_type_header("NewImageEvent"), b"image"]));
+
+        let cached: Vec<_> = cache.matching(&snapshot).collect();
+        assert_eq!(cached, [&vec![snapshot.clone(), meta, b"second".to_vec()]]);
+        assert_eq!(cache.matching(b"").count(), 1);
+        assert_eq!(
+            cache.matching(&event_type_header("NewImageEvent")).count(),
+            0
+        );
+        // a subscription to a type whose name only starts like a cached one
+        assert_eq!(cache.matching(b"MetricsSnapshotEventX").count(), 0);
+        let mut unsubscription = vec![0];
+        unsubscription.extend_from_slice(&snapshot);
+        assert_eq!(cache.answer(&unsubscription).count(), 0);
+        unsubscription[0] = SUBSCRIBE;
+        assert_eq!(cache.answer(&unsubscription).count(), 1);
+    }
+
+    #[test]
+    fn test_late_subscriber_gets_the_cached_snapshot() {
+        let context = zmq::Context::new();
+        let engine = EventEngineBuilder::new()
+            .incoming_inproc("messages-last-value")
+            .outgoing_inproc("events-last-value")
+            .transport(Transport::InprocOnly)
+            .last_value_cache(&["MetricsSnapshotEvent"])
+            .context(context.clone())
+            .plugins(PluginRegistry::new())
+            .start()
+            .unwrap();
+        let snapshot = Event::MetricsSnapshot(MetricsSnapshot {
+            counts: vec![EventTypeCount {
+                event_type: "NewImageEvent".to_string(),
+                count: 3,
+                bytes: 300,
+            }],
+            latencies: Vec::new(),
+        });
+        // once the first subscriber has it, the proxy has forwarded it; its subscription takes
+        // a moment to reach the proxy, so probe until it has
+        let first = subscribe_to_snapshots(&context);
+        first.set_rcvtimeo(100).unwrap();
+        loop {
+            engine.publish(&snapshot).unwrap();
+            if first.recv_multipart(0).is_ok() {
+                break;
+            }
+        }
+
+        let late = subscribe_to_snapshots(&context);
+        let frames = late.recv_multipart(0).unwrap();
+        let (event_type, payload) = parse_event_frames(frames).unwrap();
+        assert_eq!(event_type, "MetricsSnapshotEvent");
+        assert_eq!(Event::decode(&payload).unwrap(), snapshot);
+        drop((first, late));
+        engine.shutdown().unwrap();
+    }
+
+    #[test]
+    fn test_unknown_cached_types_are_rejected() {
+        let started = EventEngineBuilder::new()
+            .transport(Transport::InprocOnly)
+            .last_value_cache(&["SnapshotEvent"])
+            .plugins(PluginRegistry::new())
+            .start();
+        assert!(matches!(
+            started,
+            Err(crate::event_engine::EngineError::UnknownEventType { event_type })
+                if event_type == "SnapshotEvent"
+        ));
+    }
+}
@@ -0,0 +1,57 @@
+//! plyoreacto -- an event engine for building event-driven applications out of plugins.
+//!
+//! The engine proxies events between an `incoming` and an `outgoing` socket; plugins publish
+//! to the former and subscribe to the latter, either in-process (inproc) or over TCP.
+//!
+
+pub mod acks;
+pub mod admin;
+#[cfg(all(feature = "async", unix))]
+pub mod async_engine;
+pub mod bridge_plugin;
+pub mod capture;
+pub mod compression;
+pub mod config_file;
+pub mod curve;
+pub mod dead_letter_plugin;
+pub mod event_engine;
+pub mod event_log;
+pub mod event_type_registry;
+pub mod events;
+
+#[allow(dead_code, unused_imports, mismatched_lifetime_syntaxes)]
+pub mod events_generated;
+pub mod external;
+#[cfg(feature = "http-ingest")]
+pub mod http_ingest_plugin;
+pub mod image_payload;
+pub mod image_retention_plugin;
+pub mod image_score_plugin;
+pub mod image_store_plugin;
+#[cfg(feature = "kafka")]
+pub mod kafka_sink_plugin;
+pub mod last_value_cache;
+pub mod logger_plugin;
+pub mod memory_context;
+pub mod metrics_plugin;
+pub mod middleware;
+#[cfg(feature = "mqtt")]
+pub mod mqtt_bridge_plugin;
+pub mod new_image_plugin;
+pub mod plugin;
+pub mod plugin_registry;
+#[cfg(feature = "prometheus")]
+pub mod prometheus;
+#[cfg(feature = "s3")]
+pub mod s3_store;
+pub mod settings;
+#[cfg(feature = "tracing")]
+pub mod spans;
+pub mod stats;
+pub mod storage;
+#[cfg(feature = "test-util")]
+pub mod testing;
+#[cfg(feature = "webhook")]
+pub mod webhook_plugin;
+#[cfg(feature = "ws-gateway")]
+pub mod ws_gateway_plugin;
@@ -0,0 +1,320 @@
+//! Event logger plugin.
+//! This plugin subscribes to every event type and appends one JSON object per event to a log
+//! file: its type, the uuid of its image (if any), its timestamp, selected fields of its payload
+//! and the size of the payload, but never the bytes of an image. The file is rotated when it
+//! would grow past a maximum size.
+//! Failing to write the log does not stop the plugin: the entry is dropped, the file is opened
+//! again for the next entry, and once writing works again the number of entries dropped is
+//! logged.
+//!
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use log::{error, info};
+use serde_json::{json, Value};
+
+use crate::events::{event_type_names, now_ms};
+use crate::plugin::{Plugin, PluginContext, PluginError};
+
+// Size at which the log file is rotated, unless set with `LoggerPlugin::max_file_size`.
+const DEFAULT_MAX_FILE_SIZE: u64 = 10 * 1024 * 1024;
+
+// Number of rotated files kept, unless set with `LoggerPlugin::rotated_files`.
+const DEFAULT_ROTATED_FILES: usize = 3;
+
+/// The event logger plugin, for registering with a `PluginRegistry`. When the log file at
+/// `path` would grow past the maximum file size it is renamed to `<path>.1`, the file that was
+/// `<path>.1` to `<path>.2`, and so on, keeping the configured number of rotated files.
+pub struct LoggerPlugin {
+    plugin_id: i32,
+    log: LogFile,
+}
+
+impl LoggerPlugin {
+    /// A plugin logging the events to the file at `path`, which is created if needed.
+    pub fn new(plugin_id: i32, path: impl Into<PathBuf>) -> Self {
+        LoggerPlugin {
+            plugin_id,
+            log: LogFile::new(path.into()),
+        }
+    }
+
+    /// Size, in bytes, past which the log file is rotated.
+    pub fn max_file_size(mut self, max_file_size: u64) -> Self {
+        self.log.max_file_size = max_file_size;
+        self
+    }
+
+    /// Number of rotated log files kept; with 0 the log file is emptied instead.
+    pub fn rotated_files(mut self, rotated_files: usize) -> Self {
+        self.log.rotated_files = rotated_files;
+        self
+    }
+}
+
+// The log file, opened on the first entry and again after a failure.
+struct LogFile {
+    path: PathBuf,
+    max_file_size: u64,
+    rotated_files: usize,
+    file: Option<File>,
+    // bytes in the current file
+    size: u64,
+    // entries dropped since the last one written
+    dropped: u64,
+}
+
+impl LogFile {
+    fn new(path: PathBuf) -> Self {
+        LogFile {
+            path,
+            max_file_size: DEFAULT_MAX_FILE_SIZE,
+            rotated_files: DEFAULT_ROTATED_FILES,
+            file: None,
+            size: 0,
+            dropped: 0,
+        }
+    }
+
+    // Append `entry`, or count it as dropped.
+    fn append(&mut self, entry: &Value) {
+        if let Err(e) = self.try_append(entry) {
+            self.file = None;
+            self.dropped += 1;
+            error!(
+                "Logger plugin dropped an entry ({} so far): could not write {}: {}",
+                self.dropped,
+                self.path.display(),
+                e
+            );
+        }
+    }
+
+    fn try_append(&mut self, entry: &Value) -> io::Result<()> {
+        let mut lines = String::new();
+        if self.dropped > 0 {
+            let dropped = json!({"timestamp_ms": now_ms(), "dropped_entries": self.dropped});
+            lines.push_str(&format!("{}\n", dropped));
+        }
+        lines.push_str(&format!("{}\n", entry));
+        let len = lines.len() as u64;
+
+        if self.file.is_none() {
+            self.open()?;
+        }
+        if self.size > 0 && self.size + len > self.max_file_size {
+            self.file = None;
+            self.rotate()?;
+            self.open()?;
+        }
+        let file = self.file.as_mut().expect("the log file was opened");
+        file.write_all(lines.as_bytes())?;
+        self.size += len;
+        self.dropped = 0;
+        Ok(())
+    }
+
+    fn open(&mut self) -> io::Result<()> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        self.size = file.metadata()?.len();
+        self.file = Some(file);
+        Ok(())
+    }
+
+    fn rotate(&self) -> io::Result<()> {
+        if self.rotated_files == 0 {
+            return fs::remove_file(&self.path);
+        }
+        for n in (1..self.rotated_files).rev() {
+            let from = rotated_path(&self.path, n);
+            if from.exists() {
+                fs::rename(from, rotated_path(&self.path, n + 1))?;
+            }
+        }
+        fs::rename(&self.path, rotated_path(&self.path, 1))
+    }
+}
+
+// The path of the `n`th rotated log file, e.g., "events.log.1".
+fn rotated_path(path: &Path, n: usize) -> PathBuf {
+    let mut rotated = path.as_os_str().to_owned();
+    rotated.push(format!(".{}", n));
+    PathBuf::from(rotated)
+}
+
+impl Plugin for LoggerPlugin {
+    fn id(&self) -> i32 {
+        self.plugin_id
+    }
+
+    fn name(&self) -> &str {
+        "logger"
+    }
+
+    fn subscriptions(&self) -> &[&str] {
+        event_type_names()
+    }
+
+    fn start(mut self: Box<Self>, mut ctx: PluginContext) -> Result<(), PluginError> {
+        loop {
+            let msg = ctx.next_event()?;
+            self.log.append(&msg.to_json());
+            if msg.event_type == "PluginTerminateEvent" {
+                info!(plugin_id = ctx.plugin_id; "Logger plugin got terminate event, exiting");
+                return Ok(());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::event_engine::{EventEngineBuilder, Transport};
+    use crate::events::{Event, NewImage};
+    use crate::image_score_plugin::{FixedScorer, ImageScorePlugin};
+    use crate::image_store_plugin::ImageStorePlugin;
+    use crate::plugin_registry::PluginRegistry;
+    use flatbuffers::FlatBufferBuilder;
+    use std::time::Duration;
+
+    fn temp_dir() -> PathBuf {
+        std::env::temp_dir().join(format!("plyoreacto-log-{}", uuid::Uuid::new_v4()))
+    }
+
+    fn read_lines(path: &Path) -> Vec<Value> {
+        fs::read_to_string(path)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_log_file_is_rotated() {
+        let dir = temp_dir();
+        fs::create_dir(&dir).unwrap();
+        let path = dir.join("events.log");
+        let mut log = LogFile::new(path.clone());
+        log.max_file_size = 50;
+        log.rotated_files = 2;
+        for n in 0..4 {
+            // 23 bytes per line, so two fit in a file
+            log.append(&json!({"entry": format!("{:010}", n)}));
+        }
+        log.append(&json!({"entry": "last"}));
+        let entries = |path: &Path| -> Vec<Value> {
+            read_lines(path)
+                .into_iter()
+                .map(|e| e["entry"].clone())
+                .collect()
+        };
+        assert_eq!(entries(&path), [json!("last")]);
+        assert_eq!(
+            entries(&rotated_path(&path, 1)),
+            [json!("0000000002"), json!("0000000003")]
+        );
+        assert_eq!(
+            entries(&rotated_path(&path, 2)),
+            [json!("0000000000"), json!("0000000001")]
+        );
+        assert!(!rotated_path(&path, 3).exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_entries_are_dropped_while_the_file_cannot_be_written() {
+        let dir = temp_dir();
+        let path = dir.join("events.log");
+        let mut log = LogFile::new(path.clone());
+        // the directory of the log does not exist yet
+        log.append(&json!({"entry": 1}));
+        log.append(&json!({"entry": 2}));
+        assert_eq!(log.dropped, 2);
+        fs::create_dir(&dir).unwrap();
+        log.append(&json!({"entry": 3}));
+        let lines = read_lines(&path);
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["dropped_entries"], json!(2));
+        assert_eq!(lines[1]["entry"], json!(3));
+        assert_eq!(log.dropped, 0);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_pipeline_events_are_logged_in_order() {
+        let dir = temp_dir();
+        fs::create_dir(&dir).unwrap();
+        let path = dir.join("events.log");
+        let scorer = FixedScorer::new(vec![("labrador".to_string(), 1.0)]);
+        let mut plugins = PluginRegistry::new();
+        plugins
+            .register_plugin(Box::new(ImageScorePlugin::with_scorer(1, Box::new(scorer))))
+            .unwrap()
+            .register_plugin(Box::new(ImageStorePlugin::new(2)))
+            .unwrap()
+            .register_plugin(Box::new(LoggerPlugin::new(3, &path)))
+            .unwrap();
+        let engine = EventEngineBuilder::new()
+            .incoming_inproc("messages-logged-in-order")
+            .outgoing_inproc("events-logged-in-order")
+            .transport(Transport::InprocOnly)
+            .plugins(plugins)
+            .start()
+            .unwrap();
+        // the events as the engine sends them to its subscribers, the logger included
+        let event_types: Vec<&str> = event_type_names()
+            .iter()
+            .copied()
+            .filter(|t| *t != "PluginTerminateEvent")
+            .collect();
+        let rx = engine.subscribe(&event_types).unwrap();
+        // give the subscription time to reach the engine
+        std::thread::sleep(Duration::from_millis(200));
+        for _ in 0..3 {
+            engine
+                .publish(&Event::NewImage(NewImage {
+                    image_uuid: uuid::Uuid::new_v4().to_string(),
+                    image_format: "png".to_string(),
+                    image: vec![1, 2, 3],
+                    location: None,
+                }))
+                .unwrap();
+        }
+        let timeout = Duration::from_secs(10);
+        let published: Vec<Event> = (0..9).map(|_| rx.recv_timeout(timeout).unwrap()).collect();
+        engine.shutdown().unwrap();
+
+        let lines = read_lines(&path);
+        let logged: Vec<(String, String)> = lines
+            .iter()
+            .map(|line| {
+                let uuid = line["uuid"].as_str().unwrap_or_default().to_string();
+                (line["type"].as_str().unwrap().to_string(), uuid)
+            })
+            .collect();
+        // between the engine's own events, which the plugin gets before and after the others
+        let engine_event = |event_type: &str| (event_type.to_string(), String::new());
+        let expected: Vec<(String, String)> = std::iter::once(engine_event("EngineStartedEvent"))
+            .chain(published.iter().map(|event| {
+                let uuid = event.image_uuid().unwrap_or_default().to_string();
+                (event.type_name().to_string(), uuid)
+            }))
+            .chain([
+                engine_event("EngineStoppingEvent"),
+                engine_event("PluginTerminateEvent"),
+            ])
+            .collect();
+        assert_eq!(logged, expected);
+        // no image bytes, just their size
+        assert_eq!(lines[1]["fields"]["image_size"], json!(3));
+        let payload_size = published[0].encode(&mut FlatBufferBuilder::new()).len();
+        assert_eq!(lines[1]["payload_size"], json!(payload_size));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
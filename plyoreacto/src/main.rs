@@ -1,13 +1,76 @@
 use std::thread;
 
-mod event_engine;
-mod events;
+use plyoreacto::event_engine::{EngineConfig, EventEngineBuilder};
+use plyoreacto::metrics_plugin::MetricsPlugin;
+use plyoreacto::plugin_registry::{default_plugins, PluginRegistry};
 
-#[allow(dead_code, unused_imports)]
-mod events_generated;
-mod image_score_plugin;
-mod image_store_plugin;
-mod new_image_plugin;
+const USAGE: &str = "usage: plyoreacto [--config <file>] [--profile ingest-only|scoring|full]";
+
+// Port the metrics plugin of the example engine answers queries on.
+const METRICS_QUERY_PORT: u16 = 5570;
+
+// Prints the log records of the engine and plugins to stdout, at the level given by RUST_LOG
+// (e.g., "debug"); info by default.
+struct StdoutLogger;
+
+impl log::Log for StdoutLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &log::Record) {
+        if self.enabled(record.metadata()) {
+            println!("[{}] {}", record.level(), record.args());
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+static LOGGER: StdoutLogger = StdoutLogger;
+
+// The plugins of the example engine: the image pipeline of `default_plugins` and a metrics
+// plugin (4), with a profile for each role the binary runs in.
+fn plugins() -> PluginRegistry {
+    let mut plugins = default_plugins();
+    plugins
+        .register_plugin(Box::new(MetricsPlugin::new(4, METRICS_QUERY_PORT)))
+        .and_then(|p| p.profile("ingest-only", &[0, 4]))
+        .and_then(|p| p.profile("scoring", &[1, 4]))
+        .and_then(|p| p.profile("full", &[0, 1, 2, 3, 4]))
+        .expect("the example plugins are registered once");
+    plugins
+}
+
+// How to run the example engine, as given on the command line.
+#[derive(Debug, Default, PartialEq)]
+struct Options {
+    // the configuration file of the engine and plugins (see the config_file module); the
+    // defaults if None
+    config: Option<String>,
+    // the profile of the plugins to start, over that of the configuration file; all of them if
+    // neither gives one
+    profile: Option<String>,
+}
+
+// The options of the arguments `args` (without the name of the program); Ok(None) for --help.
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Option<Options>, String> {
+    let mut options = Options::default();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "--config" => {
+                options.config = Some(args.next().ok_or("--config takes a file")?);
+            }
+            "--profile" => {
+                options.profile = Some(args.next().ok_or("--profile takes a profile name")?);
+            }
+            _ => return Err(format!("unknown option {}", arg)),
+        }
+    }
+    Ok(Some(options))
+}
 
 fn plugin_c(ctx: &mut zmq::Context) {
     let new_events = ctx
@@ -53,6 +116,23 @@ fn plugin_c(ctx: &mut zmq::Context) {
 }
 
 fn main() {
+    let level = std::env::var("RUST_LOG")
+        .ok()
+        .and_then(|level| level.parse().ok())
+        .unwrap_or(log::LevelFilter::Info);
+    log::set_logger(&LOGGER).expect("no other logger is installed");
+    log::set_max_level(level);
+    let options = match parse_args(std::env::args().skip(1)) {
+        Ok(Some(options)) => options,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return;
+        }
+        Err(e) => {
+            eprintln!("plyoreacto: {}\n{}", e, USAGE);
+            std::process::exit(2);
+        }
+    };
     println!("Starting main engine");
 
     // * --------------------------------------------
@@ -61,7 +141,22 @@ fn main() {
     // *
     // * Comment the line below to run the demo code
     // * -----------------------------------
-    event_engine::event_engine().expect("Error from engine");
+    let config = match &options.config {
+        Some(path) => match EngineConfig::from_toml_file(path) {
+            Ok(config) => config,
+            Err(e) => {
+                eprintln!("plyoreacto: {}", e);
+                std::process::exit(2);
+            }
+        },
+        None => EngineConfig::default(),
+    };
+    let builder = EventEngineBuilder::from_config(config).plugins(plugins());
+    let builder = match &options.profile {
+        Some(profile) => builder.with_profile(profile),
+        None => builder,
+    };
+    builder.run().expect("Error from engine");
 
     // *---------------------------------------------
     // *
@@ -144,6 +239,35 @@ fn main() {
 
     // start the zmq proxy
     println!("Engine starting the proxy...");
-    let _result = zmq::proxy(&incoming, &outgoing)
-        .expect("Engine got error running proxy; socket was closed?");
+    zmq::proxy(&incoming, &outgoing).expect("Engine got error running proxy; socket was closed?");
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Option<Options>, String> {
+        parse_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn test_parse_args() {
+        assert_eq!(parse(&[]), Ok(Some(Options::default())));
+        let options = parse(&["--profile", "scoring"]).unwrap().unwrap();
+        assert_eq!(options.profile.as_deref(), Some("scoring"));
+        let options = parse(&["--config", "engine.toml", "--profile", "full"])
+            .unwrap()
+            .unwrap();
+        assert_eq!(options.config.as_deref(), Some("engine.toml"));
+        assert_eq!(options.profile.as_deref(), Some("full"));
+        assert!(parse(&["--config"]).is_err());
+        assert_eq!(parse(&["--help"]), Ok(None));
+        assert!(parse(&["--profile"]).is_err());
+        assert!(parse(&["--verbose"]).is_err());
+    }
+
+    #[test]
+    fn test_every_role_has_a_profile() {
+        assert_eq!(plugins().profiles(), ["ingest-only", "scoring", "full"]);
+    }
 }
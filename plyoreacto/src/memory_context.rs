@@ -0,0 +1,223 @@
+//! A stand-in for the `PluginContext` of a plugin, keeping its events in memory, for testing the
+//! logic of a plugin with plain function calls instead of sockets and an engine. Events pushed
+//! with `push` are what `next_event` returns, in order, and the events the plugin publishes are
+//! kept, decoded, for `published` to return:
+//!
+//! ```
+//! use plyoreacto::events::{Event, NewImage, PluginTerminate};
+//! use plyoreacto::image_score_plugin::ImageScorePlugin;
+//! use plyoreacto::memory_context::MemoryContext;
+//!
+//! let mut ctx = MemoryContext::new(1);
+//! ctx.push(&NewImage {
+//!     image_uuid: "5b0c".to_string(),
+//!     image_format: "png".to_string(),
+//!     image: vec![1, 2, 3],
+//!     location: None,
+//! });
+//! ctx.push(&PluginTerminate);
+//! ImageScorePlugin::new(1).run(&mut ctx).unwrap();
+//! match &ctx.published()[0].0 {
+//!     Event::ImageScored(scored) => assert_eq!(scored.image_uuid, "5b0c"),
+//!     event => panic!("expected an ImageScoredEvent, got {:?}", event),
+//! }
+//! ```
+//!
+//! The plugin has to be written against `EventSource + EventSink` for that (see
+//! `plugin::EventSource`). Only the events it handles come through: the heartbeats, gap detection,
+//! compression and backpressure of a `PluginContext` are left out.
+//!
+
+use std::collections::{HashSet, VecDeque};
+
+use flatbuffers::FlatBufferBuilder;
+
+use crate::events::{get_event_type_bytes_filter, Event, EventMeta, EventPayload};
+use crate::plugin::{EventMsg, EventSink, EventSource, PluginError};
+
+/// The source plugin id of the envelopes of the events pushed, which no plugin published.
+pub const MEMORY_SOURCE_PLUGIN_ID: i32 = -1;
+
+/// The events of a plugin under test: those it is to receive and those it published.
+#[derive(Debug)]
+pub struct MemoryContext {
+    plugin_id: i32,
+    // what `next_event` returns next
+    incoming: VecDeque<EventMsg>,
+    published: Vec<(Event, EventMeta)>,
+    acked: Vec<(String, EventMeta)>,
+    // the event types delivered at least once
+    acknowledged: HashSet<String>,
+}
+
+impl MemoryContext {
+    /// The context of the plugin `plugin_id`, without any event.
+    pub fn new(plugin_id: i32) -> Self {
+        MemoryContext {
+            plugin_id,
+            incoming: VecDeque::new(),
+            published: Vec::new(),
+            acked: Vec::new(),
+            acknowledged: HashSet::new(),
+        }
+    }
+
+    /// Deliver the events of `event_types` at least once, as the engine does with
+    /// `EventEngineBuilder::acknowledged_delivery`, so that the plugin acknowledges them (see
+    /// `acked`).
+    pub fn acknowledge(mut self, event_types: &[&str]) -> Self {
+        self.acknowledged
+            .extend(event_types.iter().map(|event_type| event_type.to_string()));
+        self
+    }
+
+    /// Queue `event` for the plugin to receive, in an envelope from `MEMORY_SOURCE_PLUGIN_ID`,
+    /// which is returned.
+    pub fn push(&mut self, event: &impl EventPayload) -> EventMeta {
+        let mut bldr = FlatBufferBuilder::new();
+        let payload = event
+            .build(&mut bldr)
+            .expect("could not serialize the event pushed");
+        let meta = EventMeta::new(MEMORY_SOURCE_PLUGIN_ID);
+        self.push_msg(EventMsg {
+            event_type: event.event_type().to_string(),
+            meta: Some(meta),
+            payload: payload.into(),
+        });
+        meta
+    }
+
+    /// Queue `msg` for the plugin to receive as it is, e.g., an event without an envelope or
+    /// with a malformed payload.
+    pub fn push_msg(&mut self, msg: EventMsg) {
+        self.incoming.push_back(msg);
+    }
+
+    /// The events the plugin published, in order, along with their envelopes.
+    pub fn published(&self) -> &[(Event, EventMeta)] {
+        &self.published
+    }
+
+    /// The events the plugin acknowledged, in order, by type; see `acknowledge`.
+    pub fn acked(&self) -> &[(String, EventMeta)] {
+        &self.acked
+    }
+
+    /// How many pushed events the plugin has not received yet.
+    pub fn pending(&self) -> usize {
+        self.incoming.len()
+    }
+
+    fn publish_with_meta(
+        &mut self,
+        meta: EventMeta,
+        event: &impl EventPayload,
+    ) -> Result<EventMeta, PluginError> {
+        let event_type = event.event_type();
+        get_event_type_bytes_filter(event_type).map_err(PluginError::Other)?;
+        let mut bldr = FlatBufferBuilder::new();
+        let event = Event::decode_as(event_type, event.build(&mut bldr)?)?;
+        self.published.push((event, meta));
+        Ok(meta)
+    }
+}
+
+impl EventSource for MemoryContext {
+    /// The event pushed first of those left, or `PluginError::Stopped` once none is left, as a
+    /// `PluginContext` returns once the engine is shutting down.
+    fn next_event(&mut self) -> Result<EventMsg, PluginError> {
+        self.incoming.pop_front().ok_or(PluginError::Stopped)
+    }
+
+    fn done(&mut self) -> Result<(), PluginError> {
+        Ok(())
+    }
+}
+
+impl EventSink for MemoryContext {
+    fn plugin_id(&self) -> i32 {
+        self.plugin_id
+    }
+
+    fn publish(&mut self, event: &impl EventPayload) -> Result<EventMeta, PluginError> {
+        self.publish_with_meta(EventMeta::new(self.plugin_id), event)
+    }
+
+    fn publish_reply(
+        &mut self,
+        to: &EventMeta,
+        event: &impl EventPayload,
+    ) -> Result<EventMeta, PluginError> {
+        self.publish_with_meta(EventMeta::reply(to, self.plugin_id), event)
+    }
+
+    fn acknowledges(&self, event_type: &str) -> bool {
+        self.acknowledged.contains(event_type)
+    }
+
+    fn ack(&mut self, event_type: &str, meta: Option<&EventMeta>) -> Result<(), PluginError> {
+        if let Some(meta) = meta.filter(|_| self.acknowledges(event_type)) {
+            self.acked.push((event_type.to_string(), *meta));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::events::{ImageDeletedRequest, PluginTerminate};
+
+    #[test]
+    fn test_events_are_received_and_published_in_memory() {
+        let mut ctx = MemoryContext::new(3).acknowledge(&["ImageDeletedRequestEvent"]);
+        let request = ImageDeletedRequest {
+            image_uuid: "5b0c".to_string(),
+        };
+        let meta = ctx.push(&request);
+        ctx.push(&PluginTerminate);
+        assert_eq!(ctx.pending(), 2);
+
+        let msg = ctx.next_event().unwrap();
+        assert_eq!(msg.meta, Some(meta));
+        assert_eq!(
+            msg.decode().unwrap(),
+            Event::ImageDeletedRequest(request.clone())
+        );
+        let reply = ctx.publish_reply(&meta, &Event::ImageDeletedRequest(request.clone()));
+        assert_eq!(reply.unwrap().correlation_id, meta.correlation_id);
+        ctx.ack(&msg.event_type, msg.meta.as_ref()).unwrap();
+        assert_eq!(ctx.next_event().unwrap().event_type, "PluginTerminateEvent");
+        // as a plugin's context does once the engine is shutting down
+        assert!(matches!(ctx.next_event(), Err(PluginError::Stopped)));
+
+        assert_eq!(ctx.published().len(), 1);
+        assert_eq!(ctx.published()[0].1.source_plugin_id, 3);
+        assert_eq!(
+            ctx.acked(),
+            &[("ImageDeletedRequestEvent".to_string(), meta)]
+        );
+        // only the events delivered at least once are acknowledged
+        ctx.ack("ImageScoredEvent", Some(&meta)).unwrap();
+        assert_eq!(ctx.acked().len(), 1);
+    }
+
+    #[test]
+    fn test_unreadable_events_are_dead_lettered() {
+        let mut ctx = MemoryContext::new(3);
+        ctx.push_msg(EventMsg {
+            event_type: "ImageScoredEvent".to_string(),
+            meta: None,
+            payload: vec![1, 2, 3].into(),
+        });
+        let msg = ctx.next_event().unwrap();
+        assert_eq!(ctx.decode_or_dead_letter(&msg).unwrap(), None);
+        match &ctx.published()[0].0 {
+            Event::DeadLetter(dead_letter) => {
+                assert_eq!(dead_letter.event_type, "ImageScoredEvent");
+                assert_eq!(dead_letter.payload, vec![1, 2, 3]);
+            }
+            event => panic!("expected a DeadLetterEvent, got {:?}", event),
+        }
+    }
+}
@@ -0,0 +1,498 @@
+//! Metrics plugin.
+//! This plugin subscribes to every event type and counts the events of each type and the bytes
+//! of their payloads. For the events published in reply to another one, it also keeps a
+//! histogram of how long after the start of their chain they were published, from the monotonic
+//! timestamps of their envelopes (see `EventMeta::chain_latency`); for ImageStoredEvents, that is
+//! how long images take from their NewImageEvent to being stored.
+//!
+//! The plugin answers every request on a REP socket bound to `tcp://*:<query port>` with a
+//! snapshot of the counters, serialized like the payload of a MetricsSnapshotEvent (decode it
+//! with `Event::decode`), and publishes a MetricsSnapshotEvent every snapshot interval.
+//!
+
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+use flatbuffers::FlatBufferBuilder;
+use log::info;
+
+use crate::events::{
+    event_type_names, EventPayload, EventTypeCount, EventTypeLatency, MetricsSnapshot,
+};
+use crate::plugin::{EventMsg, Plugin, PluginContext, PluginError};
+
+// How often a MetricsSnapshotEvent is published, unless set with
+// `MetricsPlugin::snapshot_interval`.
+const DEFAULT_SNAPSHOT_INTERVAL: Duration = Duration::from_secs(10);
+
+/// The metrics plugin, for registering with a `PluginRegistry`.
+pub struct MetricsPlugin {
+    plugin_id: i32,
+    query_port: u16,
+    snapshot_interval: Duration,
+}
+
+impl MetricsPlugin {
+    /// A plugin answering queries on `query_port`; the port is bound when the plugin starts.
+    pub fn new(plugin_id: i32, query_port: u16) -> Self {
+        MetricsPlugin {
+            plugin_id,
+            query_port,
+            snapshot_interval: DEFAULT_SNAPSHOT_INTERVAL,
+        }
+    }
+
+    /// How often the plugin publishes a MetricsSnapshotEvent.
+    pub fn snapshot_interval(mut self, snapshot_interval: Duration) -> Self {
+        self.snapshot_interval = snapshot_interval;
+        self
+    }
+}
+
+// Latencies up to this many microseconds have a bucket each in a `LatencyHistogram`; it is a
+// power of two.
+const LATENCY_SUB_BUCKETS: u64 = 32;
+
+// A histogram of latencies in microseconds, HDR style: past LATENCY_SUB_BUCKETS, every power of
+// two is split into LATENCY_SUB_BUCKETS buckets of equal width, so that the quantiles are within
+// about 3% of the latencies recorded, however large, with a few hundred buckets.
+#[derive(Debug, Default)]
+struct LatencyHistogram {
+    // number of latencies recorded in each bucket; grown up to the largest bucket used
+    buckets: Vec<u64>,
+    count: u64,
+    max_us: u64,
+}
+
+impl LatencyHistogram {
+    // The bucket of `latency_us`.
+    fn bucket(latency_us: u64) -> usize {
+        if latency_us < LATENCY_SUB_BUCKETS {
+            return latency_us as usize;
+        }
+        // the position of the highest bit above those of the sub-buckets
+        let shift =
+            u64::from(63 - latency_us.leading_zeros() - LATENCY_SUB_BUCKETS.trailing_zeros());
+        let sub_bucket = (latency_us >> shift) - LATENCY_SUB_BUCKETS;
+        (LATENCY_SUB_BUCKETS * (shift + 1) + sub_bucket) as usize
+    }
+
+    // The largest latency in `bucket`.
+    fn bucket_max(bucket: usize) -> u64 {
+        let bucket = bucket as u64;
+        if bucket < LATENCY_SUB_BUCKETS {
+            return bucket;
+        }
+        let shift = bucket / LATENCY_SUB_BUCKETS - 1;
+        let sub_bucket = bucket % LATENCY_SUB_BUCKETS;
+        ((LATENCY_SUB_BUCKETS + sub_bucket) << shift) + ((1 << shift) - 1)
+    }
+
+    fn record(&mut self, latency_us: u64) {
+        let bucket = Self::bucket(latency_us);
+        if bucket >= self.buckets.len() {
+            self.buckets.resize(bucket + 1, 0);
+        }
+        self.buckets[bucket] = self.buckets[bucket].saturating_add(1);
+        self.count = self.count.saturating_add(1);
+        self.max_us = self.max_us.max(latency_us);
+    }
+
+    // The latency that `quantile` (between 0 and 1) of the recorded latencies are at most, up to
+    // the width of its bucket.
+    fn quantile(&self, quantile: f64) -> u64 {
+        let rank = ((quantile * self.count as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (bucket, count) in self.buckets.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return Self::bucket_max(bucket).min(self.max_us);
+            }
+        }
+        self.max_us
+    }
+}
+
+// The counters of every event type seen, by type name.
+#[derive(Debug, Default)]
+struct Counters {
+    counts: BTreeMap<String, EventTypeCount>,
+    latencies: BTreeMap<String, LatencyHistogram>,
+}
+
+impl Counters {
+    fn record(&mut self, msg: &EventMsg) {
+        let count = self
+            .counts
+            .entry(msg.event_type.clone())
+            .or_insert_with(|| EventTypeCount {
+                event_type: msg.event_type.clone(),
+                count: 0,
+                bytes: 0,
+            });
+        // counters never go down, even if they could overflow
+        count.count = count.count.saturating_add(1);
+        count.bytes = count.bytes.saturating_add(msg.payload.len() as u64);
+        // the event that starts a chain has no latency
+        if let Some(meta) = msg.meta.filter(|meta| meta.event_id != meta.correlation_id) {
+            self.latencies
+                .entry(msg.event_type.clone())
+                .or_default()
+                .record(meta.chain_latency().as_micros() as u64);
+        }
+    }
+
+    fn snapshot(&self) -> MetricsSnapshot {
+        MetricsSnapshot {
+            counts: self.counts.values().cloned().collect(),
+            latencies: self
+                .latencies
+                .iter()
+                .map(|(event_type, histogram)| EventTypeLatency {
+                    event_type: event_type.clone(),
+                    count: histogram.count,
+                    p50_us: histogram.quantile(0.5),
+                    p95_us: histogram.quantile(0.95),
+                    p99_us: histogram.quantile(0.99),
+                    max_us: histogram.max_us,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Plugin for MetricsPlugin {
+    fn id(&self) -> i32 {
+        self.plugin_id
+    }
+
+    fn name(&self) -> &str {
+        "metrics"
+    }
+
+    fn subscriptions(&self) -> &[&str] {
+        event_type_names()
+    }
+
+    fn publications(&self) -> &[&str] {
+        &["MetricsSnapshotEvent"]
+    }
+
+    fn start(self: Box<Self>, mut ctx: PluginContext) -> Result<(), PluginError> {
+        let context = zmq::Context::new();
+        let query_socket = context.socket(zmq::REP)?;
+        query_socket
+            .bind(&format!("tcp://*:{}", self.query_port))
+            .map_err(|e| {
+                PluginError::Other(format!(
+                    "could not bind the query socket on port {}: {}",
+                    self.query_port, e
+                ))
+            })?;
+        info!(
+            plugin_id = ctx.plugin_id;
+            "Metrics plugin answering queries on port {}",
+            self.query_port
+        );
+
+        let mut counters = Counters::default();
+        let mut bldr = FlatBufferBuilder::new();
+        let mut next_snapshot = Instant::now() + self.snapshot_interval;
+        loop {
+            let timeout = next_snapshot.saturating_duration_since(Instant::now());
+            let (events_ready, query_ready) = {
+                let mut items = [
+                    ctx.sub_socket.as_poll_item(zmq::POLLIN),
+                    query_socket.as_poll_item(zmq::POLLIN),
+                ];
+                zmq::poll(&mut items, timeout.as_millis() as i64)?;
+                (items[0].is_readable(), items[1].is_readable())
+            };
+            if events_ready {
+                // take every event that arrived, so that a burst is counted in one go
+                while ctx.sub_socket.poll(zmq::POLLIN, 0)? > 0 {
+                    let msg = ctx.next_event()?;
+                    counters.record(&msg);
+                    if msg.event_type == "PluginTerminateEvent" {
+                        info!(
+                            plugin_id = ctx.plugin_id;
+                            "Metrics plugin got terminate event, exiting"
+                        );
+                        return Ok(());
+                    }
+                }
+            }
+            if query_ready {
+                // the request body is ignored: every request gets the current counters
+                query_socket.recv_bytes(0)?;
+                let snapshot = counters.snapshot();
+                query_socket.send(snapshot.build(&mut bldr)?, 0)?;
+            }
+            if Instant::now() >= next_snapshot {
+                ctx.publish(&counters.snapshot())?;
+                next_snapshot = Instant::now() + self.snapshot_interval;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::event_engine::EventEngineBuilder;
+    use crate::events::{Event, EventMeta, NewImage};
+    use crate::image_score_plugin::{FixedScorer, ImageScorePlugin, ImageScorer, ScoreError};
+    use crate::image_store_plugin::ImageStorePlugin;
+    use crate::plugin_registry::PluginRegistry;
+
+    // Ask the metrics plugin on `port` for its counters.
+    fn query(port: u16) -> MetricsSnapshot {
+        let context = zmq::Context::new();
+        let socket = context.socket(zmq::REQ).unwrap();
+        socket.set_rcvtimeo(5000).unwrap();
+        socket
+            .connect(&format!("tcp://127.0.0.1:{}", port))
+            .unwrap();
+        socket.send("", 0).unwrap();
+        let reply = socket
+            .recv_bytes(0)
+            .expect("no reply from the metrics plugin");
+        match Event::decode(&reply).unwrap() {
+            Event::MetricsSnapshot(snapshot) => snapshot,
+            event => panic!("expected a MetricsSnapshotEvent, got {:?}", event),
+        }
+    }
+
+    fn count(snapshot: &MetricsSnapshot, event_type: &str) -> u64 {
+        snapshot
+            .counts
+            .iter()
+            .find(|count| count.event_type == event_type)
+            .map_or(0, |count| count.count)
+    }
+
+    #[test]
+    fn test_counters_only_go_up() {
+        let mut counters = Counters::default();
+        let msg = EventMsg {
+            event_type: "NewImageEvent".to_string(),
+            meta: None,
+            payload: vec![0; 10].into(),
+        };
+        counters.record(&msg);
+        counters.record(&msg);
+        assert_eq!(
+            counters.snapshot().counts,
+            vec![EventTypeCount {
+                event_type: "NewImageEvent".to_string(),
+                count: 2,
+                bytes: 20,
+            }]
+        );
+        counters.counts.get_mut("NewImageEvent").unwrap().count = u64::MAX;
+        counters.record(&msg);
+        assert_eq!(count(&counters.snapshot(), "NewImageEvent"), u64::MAX);
+    }
+
+    #[test]
+    fn test_latency_quantiles() {
+        // every bucket holds the latencies it is the bucket of
+        for latency_us in (0..100_000).chain([u64::MAX / 3, u64::MAX]) {
+            let bucket = LatencyHistogram::bucket(latency_us);
+            assert!(LatencyHistogram::bucket_max(bucket) >= latency_us);
+            assert!(bucket == 0 || LatencyHistogram::bucket_max(bucket - 1) < latency_us);
+        }
+        let mut histogram = LatencyHistogram::default();
+        for latency_us in 1..=1000 {
+            histogram.record(latency_us * 100);
+        }
+        let within = |expected: u64, quantile: f64| {
+            let latency_us = histogram.quantile(quantile);
+            assert!(
+                latency_us >= expected && latency_us <= expected + expected / 32,
+                "p{} is {}, expected {}",
+                quantile * 100.0,
+                latency_us,
+                expected
+            );
+        };
+        within(50_000, 0.5);
+        within(95_000, 0.95);
+        within(99_000, 0.99);
+        assert_eq!(histogram.quantile(1.0), 100_000);
+        assert_eq!(histogram.max_us, 100_000);
+
+        // the events starting a chain have no latency
+        let mut counters = Counters::default();
+        let meta = EventMeta::new(1);
+        let mut msg = EventMsg {
+            event_type: "ImageStoredEvent".to_string(),
+            meta: Some(meta),
+            payload: vec![].into(),
+        };
+        counters.record(&msg);
+        assert_eq!(counters.snapshot().latencies, vec![]);
+        msg.meta = Some(EventMeta {
+            monotonic_us: meta.monotonic_us + 2500,
+            ..EventMeta::reply(&meta, 2)
+        });
+        counters.record(&msg);
+        let latencies = counters.snapshot().latencies;
+        assert_eq!(latencies.len(), 1);
+        assert_eq!((latencies[0].count, latencies[0].max_us), (1, 2500));
+    }
+
+    #[test]
+    fn test_pipeline_events_are_counted() {
+        let scorer = FixedScorer::new(vec![("labrador".to_string(), 1.0)]);
+        let mut plugins = PluginRegistry::new();
+        plugins
+            .register_plugin(Box::new(ImageScorePlugin::with_scorer(1, Box::new(scorer))))
+            .unwrap()
+            .register_plugin(Box::new(ImageStorePlugin::new(2)))
+            .unwrap()
+            .register_plugin(Box::new(
+                MetricsPlugin::new(3, 3112).snapshot_interval(Duration::from_millis(100)),
+            ))
+            .unwrap();
+        let engine = EventEngineBuilder::new()
+            .incoming_port(3110)
+            .outgoing_port(3111)
+            .plugins(plugins)
+            .start()
+            .unwrap();
+        let snapshot_rx = engine.subscribe(&["MetricsSnapshotEvent"]).unwrap();
+        for _ in 0..3 {
+            engine
+                .publish(&Event::NewImage(NewImage {
+                    image_uuid: uuid::Uuid::new_v4().to_string(),
+                    image_format: "png".to_string(),
+                    image: vec![1, 2, 3],
+                    location: None,
+                }))
+                .unwrap();
+        }
+
+        // the snapshot may be taken before the last events reach the plugin
+        let event_types = ["NewImageEvent", "ImageScoredEvent", "ImageStoredEvent"];
+        let deadline = Instant::now() + Duration::from_secs(10);
+        let snapshot = loop {
+            let snapshot = query(3112);
+            let done = event_types.iter().all(|t| count(&snapshot, t) >= 3);
+            if done || Instant::now() > deadline {
+                break snapshot;
+            }
+            std::thread::sleep(Duration::from_millis(50));
+        };
+        for event_type in event_types {
+            assert_eq!(count(&snapshot, event_type), 3, "{}", event_type);
+        }
+        assert!(matches!(
+            snapshot_rx.recv_timeout(Duration::from_secs(10)).unwrap(),
+            Event::MetricsSnapshot(_)
+        ));
+        engine.shutdown().unwrap();
+    }
+
+    // Scores every image after a delay, as a slow model would.
+    struct SlowScorer(Duration);
+
+    impl ImageScorer for SlowScorer {
+        fn score(
+            &mut self,
+            _image: &[u8],
+            _format: &str,
+        ) -> Result<Vec<(String, f32)>, ScoreError> {
+            std::thread::sleep(self.0);
+            Ok(vec![("labrador".to_string(), 0.9)])
+        }
+    }
+
+    // Publishes `count` images, one every `interval`, so that they do not queue up at the scorer.
+    struct PacedImages {
+        count: usize,
+        interval: Duration,
+    }
+
+    impl Plugin for PacedImages {
+        fn id(&self) -> i32 {
+            0
+        }
+
+        fn name(&self) -> &str {
+            "paced images"
+        }
+
+        fn subscriptions(&self) -> &[&str] {
+            &[]
+        }
+
+        fn start(self: Box<Self>, mut ctx: PluginContext) -> Result<(), PluginError> {
+            for _ in 0..self.count {
+                ctx.publish(&NewImage {
+                    image_uuid: uuid::Uuid::new_v4().to_string(),
+                    image_format: "png".to_string(),
+                    image: vec![1, 2, 3],
+                    location: None,
+                })?;
+                std::thread::sleep(self.interval);
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_store_latency_reflects_scoring_delay() {
+        let delay = Duration::from_millis(50);
+        let mut plugins = PluginRegistry::new();
+        plugins
+            .register_plugin(Box::new(PacedImages {
+                count: 5,
+                interval: Duration::from_millis(150),
+            }))
+            .unwrap()
+            .register_plugin(Box::new(ImageScorePlugin::with_scorer(
+                1,
+                Box::new(SlowScorer(delay)),
+            )))
+            .unwrap()
+            .register_plugin(Box::new(ImageStorePlugin::new(2)))
+            .unwrap()
+            .register_plugin(Box::new(MetricsPlugin::new(3, 2780)))
+            .unwrap();
+        let engine = EventEngineBuilder::new()
+            .incoming_port(2759)
+            .outgoing_port(2760)
+            .sync_port(2700)
+            .plugins(plugins)
+            .start()
+            .unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(10);
+        let stored = loop {
+            let snapshot = query(2780);
+            let stored = snapshot
+                .latencies
+                .into_iter()
+                .find(|latency| latency.event_type == "ImageStoredEvent");
+            match stored {
+                Some(stored) if stored.count == 5 => break stored,
+                _ if Instant::now() > deadline => {
+                    panic!("expected 5 stored images, got {:?}", stored)
+                }
+                _ => std::thread::sleep(Duration::from_millis(50)),
+            }
+        };
+        engine.shutdown().unwrap();
+
+        // each image waits for its score, and little else
+        let delay_us = delay.as_micros() as u64;
+        assert!(
+            stored.p50_us >= delay_us && stored.p50_us < delay_us + 25_000,
+            "p50 of {}us for a scoring delay of {}us",
+            stored.p50_us,
+            delay_us
+        );
+        assert!(stored.p50_us <= stored.p99_us && stored.p99_us <= stored.max_us);
+    }
+}
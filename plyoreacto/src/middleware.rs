@@ -0,0 +1,486 @@
+//! Middleware looking at, changing or dropping the events the engine forwards. An engine
+//! started with `EventEngineBuilder::middleware(..)` forwards events with a loop of its own
+//! instead of `zmq::proxy`, as it does to enforce its maximum payload size (see
+//! `EventEngineBuilder::max_payload_size`): every event received on the incoming socket is
+//! handed to each
+//! middleware in the order they were added, which decides whether it is forwarded as it is,
+//! dropped, or forwarded with another payload (which the middlewares after it then see).
+//! Messages that are not events are forwarded untouched.
+//!
+//! ```no_run
+//! use plyoreacto::event_engine::EventEngineBuilder;
+//! use plyoreacto::middleware::PayloadSizeLimit;
+//!
+//! EventEngineBuilder::new()
+//!     .middleware(Box::new(PayloadSizeLimit::new(16 * 1024 * 1024)))
+//!     .run()
+//!     .expect("Error from engine");
+//! ```
+//!
+//! The middlewares run on the proxy thread, so a slow one holds up every event behind it. With
+//! none, the loop forwards as fast as `zmq::proxy` does (see `cargo bench --bench engine`), and
+//! `EventEngineBuilder::forwarding_loop` uses it without any, even with no payload size limit.
+//!
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use flatbuffers::FlatBufferBuilder;
+use log::{debug, warn};
+use zmq::Socket;
+
+use crate::acks::RetryBuffer;
+use crate::events::{
+    check_schema_version, event_type_header, peek_event_messages, recv_event_frames,
+    retry_on_eintr, send_event_msg, verify_event, Codec, Event, EventMeta, EventPayload,
+    EventRejected,
+};
+use crate::last_value_cache::LastValueCache;
+use crate::stats::ForwardingStats;
+
+// How many events the loop forwards before it polls the control socket again.
+pub(crate) const FORWARD_BATCH: usize = 1000;
+
+/// What to do with an event, as decided by a `Middleware`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Action {
+    /// Forward the event as it is.
+    Forward,
+    /// Drop the event; the middlewares after this one do not see it.
+    Drop,
+    /// Forward the event with this payload instead, keeping its type and envelope.
+    Replace(Vec<u8>),
+}
+
+/// Looks at each event the engine forwards, in transit from the incoming to the outgoing socket.
+pub trait Middleware: Send {
+    /// Decide what to do with an event of type `event_type`, with envelope `meta` (None for
+    /// events published without one, such as those of the engine itself) and serialized event
+    /// `payload`.
+    fn on_event(&mut self, event_type: &str, meta: Option<&EventMeta>, payload: &[u8]) -> Action;
+}
+
+/// Drops the events whose payload is bigger than a limit, with a warning, e.g., to keep
+/// oversized images from reaching the plugins.
+#[derive(Clone, Debug)]
+pub struct PayloadSizeLimit {
+    max_bytes: usize,
+    dropped: u64,
+}
+
+impl PayloadSizeLimit {
+    pub fn new(max_bytes: usize) -> Self {
+        PayloadSizeLimit {
+            max_bytes,
+            dropped: 0,
+        }
+    }
+}
+
+impl Middleware for PayloadSizeLimit {
+    fn on_event(&mut self, event_type: &str, _meta: Option<&EventMeta>, payload: &[u8]) -> Action {
+        if payload.len() <= self.max_bytes {
+            return Action::Forward;
+        }
+        self.dropped += 1;
+        warn!(
+            event_type = event_type;
+            "Engine dropped a {} of {} bytes, over the limit of {} ({} dropped so far)",
+            event_type, payload.len(), self.max_bytes, self.dropped
+        );
+        Action::Drop
+    }
+}
+
+/// Drops the events whose payload the flatbuffers verifier rejects, or that hold another type of
+/// event than their header announces, with a warning, e.g., those of a faulty external
+/// publisher. The Rust plugins skip such events themselves (see `PluginContext::next_event`), but
+/// the Python plugins do not verify what they receive. JSON payloads are parsed instead, and
+/// events of a newer schema version than the engine's are dropped too.
+/// Compressed payloads are forwarded unchecked, as decompressing them all would hold up every
+/// event behind them.
+#[derive(Clone, Debug, Default)]
+pub struct VerifyEvents {
+    dropped: u64,
+}
+
+impl VerifyEvents {
+    pub fn new() -> Self {
+        VerifyEvents::default()
+    }
+}
+
+impl Middleware for VerifyEvents {
+    fn on_event(&mut self, event_type: &str, meta: Option<&EventMeta>, payload: &[u8]) -> Action {
+        let verified = check_schema_version(meta).and_then(|()| {
+            match meta {
+                Some(meta) if meta.compressed => Ok(()),
+                Some(meta) if meta.codec == Codec::Json => {
+                    Event::from_json(event_type, payload).map(|_| ())
+                }
+                _ => verify_event(event_type, payload).map(|_| ()),
+            }
+        });
+        let Err(e) = verified else {
+            return Action::Forward;
+        };
+        self.dropped += 1;
+        warn!(
+            event_type = event_type;
+            "Engine dropped an event: {} ({} dropped so far)", e, self.dropped
+        );
+        Action::Drop
+    }
+}
+
+// The maximum payload size the engine forwards, and the count of the events it dropped for
+// being over it, which the engine handle reads.
+pub(crate) struct PayloadLimit {
+    pub(crate) max_bytes: usize,
+    pub(crate) rejected: Arc<AtomicU64>,
+}
+
+impl PayloadLimit {
+    // The EventRejectedEvent to publish instead of the message `frames` if its payload is over
+    // the limit; messages that are not events are measured whole.
+    pub(crate) fn reject(&self, frames: &[zmq::Message]) -> Option<EventRejected> {
+        let (what, size) = match peek_event_messages(frames) {
+            Some((event_type, _, payload)) => (event_type, payload.len()),
+            None => ("message", frames.iter().map(|frame| frame.len()).sum()),
+        };
+        if size <= self.max_bytes {
+            return None;
+        }
+        let rejected = self.rejected.fetch_add(1, Ordering::Relaxed) + 1;
+        let reason = format!(
+            "{} payload of {} bytes is over the limit of {} bytes",
+            what, size, self.max_bytes
+        );
+        warn!("Engine dropped a {} ({} rejected so far)", reason, rejected);
+        Some(EventRejected {
+            reason,
+            size: size as u64,
+        })
+    }
+}
+
+// Forward the events received on `incoming` to `outgoing`, copying them to `capture` if there is
+// one, until TERMINATE is received on `control`, as `zmq::proxy_steerable_with_capture` does,
+// but passing each on to the middlewares first. Events with a payload over `limit` are dropped
+// before the middlewares see them, and an EventRejectedEvent is published in their place. With
+// a last-value cache, `outgoing` is an XPUB socket and the cached event of a type is sent again
+// for every subscription to it. With a retry buffer, the events delivered at least once are
+// held until acknowledged, and sent again to their plugins when they are not.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn forward_events(
+    incoming: &Socket,
+    outgoing: &Socket,
+    capture: Option<&Socket>,
+    control: &Socket,
+    limit: Option<&PayloadLimit>,
+    middlewares: &mut [Box<dyn Middleware>],
+    mut cache: Option<&mut LastValueCache>,
+    mut acks: Option<&mut RetryBuffer>,
+    stats: &ForwardingStats,
+) -> zmq::Result<()> {
+    let mut bldr = FlatBufferBuilder::new();
+    // where the engine publishes its own events
+    let sinks: Vec<&Socket> = capture.into_iter().chain([outgoing]).collect();
+    loop {
+        let mut items = vec![
+            control.as_poll_item(zmq::POLLIN),
+            incoming.as_poll_item(zmq::POLLIN),
+        ];
+        // a PUB socket never has anything to receive
+        if cache.is_some() {
+            items.push(outgoing.as_poll_item(zmq::POLLIN));
+        }
+        // woken up by the acknowledgements, and for the events to send again
+        if let Some(acks) = acks.as_deref() {
+            items.push(acks.router().as_poll_item(zmq::POLLIN));
+        }
+        let timeout = acks.as_deref().map_or(-1, RetryBuffer::poll_timeout);
+        retry_on_eintr(|| zmq::poll(&mut items, timeout))?;
+        let readable: Vec<bool> = items.iter().map(zmq::PollItem::is_readable).collect();
+        // the stop thread and the engine handle only ever send TERMINATE
+        if readable[0] && retry_on_eintr(|| control.recv_bytes(0))? == b"TERMINATE" {
+            return Ok(());
+        }
+        if readable[1] {
+            // take a burst in one go, as zmq::proxy does
+            for _ in 0..FORWARD_BATCH {
+                let frames = match recv_event_frames(incoming, zmq::DONTWAIT) {
+                    Ok(frames) => frames,
+                    Err(zmq::Error::EAGAIN) => break,
+                    Err(e) => return Err(e),
+                };
+                if let Some(rejected) = limit.and_then(|limit| limit.reject(&frames)) {
+                    let data = rejected
+                        .build(&mut bldr)
+                        .expect("building an event does not fail");
+                    for socket in &sinks {
+                        send_event_msg(socket, rejected.event_type(), data)?;
+                    }
+                    continue;
+                }
+                let Some(frames) = apply_middlewares(middlewares, frames) else {
+                    continue;
+                };
+                stats.count(&frames, SystemTime::now());
+                if let Some(cache) = cache.as_deref_mut() {
+                    cache.remember(&frames);
+                }
+                if let Some(acks) = acks.as_deref_mut() {
+                    acks.hold(&frames, &sinks)?;
+                }
+                if let Some(capture) = capture {
+                    send_frames(capture, frames.iter().map(|frame| &frame[..]))?;
+                }
+                let last = frames.len() - 1;
+                for (i, frame) in frames.into_iter().enumerate() {
+                    outgoing.send(frame, if i < last { zmq::SNDMORE } else { 0 })?;
+                }
+            }
+        }
+        if let (Some(cache), Some(&true)) = (cache.as_deref(), readable.get(2)) {
+            let subscription = retry_on_eintr(|| outgoing.recv_bytes(0))?;
+            for last_value in cache.answer(&subscription) {
+                send_frames(outgoing, last_value.iter().map(Vec::as_slice))?;
+            }
+        }
+        if let Some(acks) = acks.as_deref_mut() {
+            acks.acknowledged()?;
+            acks.retransmit(&sinks)?;
+        }
+    }
+}
+
+// The frames to forward for the message `frames` once every middleware has had its say, or None
+// if one of them dropped it.
+fn apply_middlewares(
+    middlewares: &mut [Box<dyn Middleware>],
+    frames: Vec<zmq::Message>,
+) -> Option<Vec<zmq::Message>> {
+    if middlewares.is_empty() {
+        return Some(frames);
+    }
+    let Some((event_type, meta, payload)) = peek_event_messages(&frames) else {
+        return Some(frames);
+    };
+    let mut replaced: Option<Vec<u8>> = None;
+    for middleware in middlewares.iter_mut() {
+        let payload = replaced.as_deref().unwrap_or(payload);
+        match middleware.on_event(event_type, meta.as_ref(), payload) {
+            Action::Forward => {}
+            Action::Drop => {
+                debug!(event_type = event_type; "Engine middleware dropped a {}", event_type);
+                return None;
+            }
+            Action::Replace(payload) => replaced = Some(payload),
+        }
+    }
+    let Some(payload) = replaced else {
+        return Some(frames);
+    };
+    let mut frames = vec![zmq::Message::from(event_type_header(event_type))];
+    if let Some(meta) = meta {
+        frames.push(zmq::Message::from(meta.to_bytes()));
+    }
+    frames.push(zmq::Message::from(payload));
+    Some(frames)
+}
+
+// Send `frames` on `socket` as one message.
+pub(crate) fn send_frames<'a>(
+    socket: &Socket,
+    frames: impl ExactSizeIterator<Item = &'a [u8]>,
+) -> zmq::Result<()> {
+    let last = frames.len() - 1;
+    for (i, frame) in frames.enumerate() {
+        socket.send(frame, if i < last { zmq::SNDMORE } else { 0 })?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::event_engine::{EngineHandle, EventEngineBuilder, Transport};
+    use crate::events::{Event, EventRejected, NewImage, PluginHeartbeat};
+    use crate::plugin_registry::PluginRegistry;
+    use flatbuffers::FlatBufferBuilder;
+    use std::sync::mpsc::Receiver;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    fn start_engine(name: &str, middlewares: Vec<Box<dyn Middleware>>) -> EngineHandle {
+        start_engine_with(name, EventEngineBuilder::new(), middlewares)
+    }
+
+    fn start_engine_with(
+        name: &str,
+        builder: EventEngineBuilder,
+        middlewares: Vec<Box<dyn Middleware>>,
+    ) -> EngineHandle {
+        let mut builder = builder
+            .incoming_inproc(&format!("messages-{}", name))
+            .outgoing_inproc(&format!("events-{}", name))
+            .transport(Transport::InprocOnly)
+            .plugins(PluginRegistry::new());
+        for middleware in middlewares {
+            builder = builder.middleware(middleware);
+        }
+        builder.start().unwrap()
+    }
+
+    // Subscribe to the events of type `event_type`, publishing `probe` until the subscription
+    // has reached the engine.
+    fn subscribe(engine: &EngineHandle, event_type: &str, probe: &Event) -> Receiver<Event> {
+        let rx = engine.subscribe(&[event_type]).unwrap();
+        loop {
+            engine.publish(probe).unwrap();
+            if rx.recv_timeout(Duration::from_millis(100)).is_ok() {
+                break;
+            }
+        }
+        while rx.recv_timeout(Duration::from_millis(50)).is_ok() {}
+        rx
+    }
+
+    fn image(size: usize) -> Event {
+        Event::NewImage(NewImage {
+            image_uuid: format!("image-{}", size),
+            image_format: "png".to_string(),
+            image: vec![7; size],
+            location: None,
+        })
+    }
+
+    #[test]
+    fn test_events_over_the_size_limit_are_dropped() {
+        let engine = start_engine("size-limit", vec![Box::new(PayloadSizeLimit::new(1024))]);
+        let rx = subscribe(&engine, "NewImageEvent", &image(10));
+        engine.publish(&image(4096)).unwrap();
+        engine.publish(&image(512)).unwrap();
+        let received = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        engine.shutdown().unwrap();
+        assert_eq!(received, image(512));
+    }
+
+    #[test]
+    fn test_events_over_the_max_payload_size_are_rejected() {
+        let builder = EventEngineBuilder::new().max_payload_size(1024);
+        let engine = start_engine_with("max-payload", builder, Vec::new());
+        let rx = subscribe(&engine, "NewImageEvent", &image(10));
+        let rejected_rx = engine.subscribe(&["EventRejectedEvent"]).unwrap();
+        // the host's events reach the engine unchecked, as those of an external plugin do
+        let oversized = image(4096);
+        let size = oversized.encode(&mut FlatBufferBuilder::new()).len();
+        loop {
+            engine.publish(&oversized).unwrap();
+            if let Ok(rejected) = rejected_rx.recv_timeout(Duration::from_millis(100)) {
+                let reason = format!(
+                    "NewImageEvent payload of {} bytes is over the limit of 1024 bytes",
+                    size
+                );
+                let size = size as u64;
+                assert_eq!(
+                    rejected,
+                    Event::EventRejected(EventRejected { reason, size })
+                );
+                break;
+            }
+        }
+        engine.publish(&image(512)).unwrap();
+        let received = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(received, image(512));
+        assert!(engine.rejected_events() >= 1);
+        engine.shutdown().unwrap();
+    }
+
+    #[test]
+    fn test_malformed_events_are_dropped() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let context = zmq::Context::new();
+        let builder = EventEngineBuilder::new().context(context.clone());
+        let middlewares: Vec<Box<dyn Middleware>> = vec![
+            Box::new(VerifyEvents::new()),
+            Box::new(Record(Arc::clone(&seen))),
+        ];
+        let engine = start_engine_with("verify", builder, middlewares);
+        let rx = subscribe(&engine, "NewImageEvent", &image(10));
+        // as a faulty external publisher would send them
+        let publisher = context.socket(zmq::PUB).unwrap();
+        publisher.connect("inproc://messages-verify").unwrap();
+        let mut bldr = FlatBufferBuilder::new();
+        let payload = image(512).encode(&mut bldr).to_vec();
+        let heartbeat = Event::PluginHeartbeat(PluginHeartbeat {
+            plugin_id: 3,
+            seq: 1,
+        });
+        let heartbeat = heartbeat.encode(&mut bldr).to_vec();
+        loop {
+            send_event_msg(&publisher, "NewImageEvent", &payload[..payload.len() / 2]).unwrap();
+            send_event_msg(&publisher, "NewImageEvent", &heartbeat).unwrap();
+            send_event_msg(&publisher, "NewImageEvent", &payload).unwrap();
+            // the first messages are lost until the connection is up
+            if rx.recv_timeout(Duration::from_millis(100)).is_ok() {
+                break;
+            }
+        }
+        engine.shutdown().unwrap();
+        // the recorder only ever saw events it could decode, the last before the engine's
+        // EngineStoppingEvent being the complete image
+        let seen = seen.lock().unwrap();
+        assert!(matches!(seen.last(), Some(Event::EngineStopping(_))));
+        assert_eq!(seen.iter().rev().nth(1), Some(&image(512)));
+        assert!(rx.try_recv().is_err());
+    }
+
+    // Adds 100 to the seq of every heartbeat.
+    struct Renumber;
+
+    impl Middleware for Renumber {
+        fn on_event(&mut self, event_type: &str, _: Option<&EventMeta>, payload: &[u8]) -> Action {
+            match Event::decode(payload) {
+                Ok(Event::PluginHeartbeat(heartbeat)) if event_type == "PluginHeartbeatEvent" => {
+                    let renumbered = Event::PluginHeartbeat(PluginHeartbeat {
+                        seq: heartbeat.seq + 100,
+                        ..heartbeat
+                    });
+                    Action::Replace(renumbered.encode(&mut FlatBufferBuilder::new()).to_vec())
+                }
+                _ => Action::Forward,
+            }
+        }
+    }
+
+    // Records the events it sees.
+    struct Record(Arc<Mutex<Vec<Event>>>);
+
+    impl Middleware for Record {
+        fn on_event(&mut self, _: &str, _: Option<&EventMeta>, payload: &[u8]) -> Action {
+            self.0.lock().unwrap().push(Event::decode(payload).unwrap());
+            Action::Forward
+        }
+    }
+
+    #[test]
+    fn test_replaced_payloads_are_forwarded_and_seen_by_later_middlewares() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let engine = start_engine(
+            "replace",
+            vec![Box::new(Renumber), Box::new(Record(Arc::clone(&seen)))],
+        );
+        let heartbeat = |seq| Event::PluginHeartbeat(PluginHeartbeat { plugin_id: 3, seq });
+        let rx = subscribe(&engine, "PluginHeartbeatEvent", &heartbeat(0));
+        engine.publish(&heartbeat(1)).unwrap();
+        let received = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        engine.shutdown().unwrap();
+        assert_eq!(received, heartbeat(101));
+        let seen = seen.lock().unwrap();
+        assert!(matches!(seen.last(), Some(Event::EngineStopping(_))));
+        assert_eq!(seen.iter().rev().nth(1), Some(&heartbeat(101)));
+    }
+}
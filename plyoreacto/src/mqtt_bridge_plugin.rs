@@ -0,0 +1,697 @@
+//! MQTT bridge plugin, built with the `mqtt` feature.
+//! This plugin feeds the messages published on an MQTT broker into the pipeline, e.g., the
+//! frames of edge cameras: it subscribes to the topics it is configured with and publishes each
+//! message as an event, a NewImageEvent with a new uuid for the image topics, or the event whose
+//! JSON the message holds for the others. It speaks MQTT 3.1.1 over TCP, with the rumqttc client.
+//!
+//! Messages are received with QoS 1, in a session the broker keeps while the plugin is
+//! disconnected, and acknowledged once published, so that the broker holds the messages
+//! published in between and sends those not acknowledged again. A message sent again with the
+//! packet id and the content of one received within the dedup window is only acknowledged. When
+//! the connection is lost, the plugin connects again, waiting longer after each failed attempt
+//! (see `MqttBridgePlugin::reconnect_backoff`).
+//!
+
+use std::collections::{HashSet, VecDeque};
+use std::time::{Duration, Instant};
+
+use log::{debug, error, info, warn};
+use rumqttc::{
+    Client, Connection, ConnectionError, Event as MqttEvent, MqttOptions, Outgoing, Packet,
+    Publish, QoS, RecvTimeoutError, SubscribeFilter, SubscribeReasonCode,
+};
+use sha2::{Digest, Sha256};
+
+use crate::event_engine::EngineError;
+use crate::events::{known_event_type, Event, NewImage};
+use crate::plugin::{Plugin, PluginContext, PluginError};
+
+// Used unless set with the builder methods of `MqttBridgePlugin`.
+const DEFAULT_KEEP_ALIVE: Duration = Duration::from_secs(30);
+const DEFAULT_RECONNECT_INTERVAL: Duration = Duration::from_millis(100);
+const DEFAULT_MAX_RECONNECT_INTERVAL: Duration = Duration::from_secs(10);
+const DEFAULT_DEDUP_WINDOW: Duration = Duration::from_secs(60);
+const DEFAULT_PORT: u16 = 1883;
+
+// How long the DISCONNECT may take to be sent on the way out.
+const DISCONNECT_TIMEOUT: Duration = Duration::from_secs(1);
+// How often the plugin checks for the terminate event while no message arrives.
+const TERMINATE_POLL_INTERVAL: Duration = Duration::from_millis(100);
+// The largest packet MQTT allows, so that frames are not refused for their size; rumqttc
+// takes no more than 10 KiB unless told otherwise.
+const MAX_PACKET_SIZE: usize = 268_435_455 + 5;
+// How many acks the plugin may queue for the client before it polls the connection again.
+const REQUEST_CAPACITY: usize = 16;
+
+/// What the messages of an MQTT topic are published as.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TopicEvent {
+    /// A NewImageEvent with a new uuid, holding the message as an image of the format, e.g.,
+    /// "jpeg".
+    NewImage { image_format: String },
+    /// An event of the type, whose JSON the message holds (see `Event::from_json`).
+    Json { event_type: String },
+}
+
+/// The MQTT bridge plugin, for registering with a `PluginRegistry`.
+pub struct MqttBridgePlugin {
+    plugin_id: i32,
+    // host and port of the broker
+    broker: String,
+    // (topic filter, event) pairs, in the order they were given
+    topics: Vec<(String, TopicEvent)>,
+    client_id: String,
+    credentials: Option<(String, String)>,
+    keep_alive: Duration,
+    reconnect_interval: Duration,
+    max_reconnect_interval: Duration,
+    dedup_window: Duration,
+    // the session with the broker, made once the plugin starts unless a test stands in for it
+    session: Option<Box<dyn MqttSession>>,
+}
+
+impl MqttBridgePlugin {
+    /// A plugin publishing the messages of the topics of `topics`, each a topic filter, e.g.,
+    /// "cameras/+/jpeg", and what its messages are published as, received from the broker at
+    /// `broker`, e.g., "mqtt://broker:1883" (the port defaults to 1883). A message is published
+    /// as the event of the first filter it matches. Fails if one of the events is of a type that
+    /// does not exist.
+    pub fn new(
+        plugin_id: i32,
+        broker: &str,
+        topics: Vec<(String, TopicEvent)>,
+    ) -> Result<Self, EngineError> {
+        for (_, event) in &topics {
+            if let TopicEvent::Json { event_type } = event {
+                known_event_type(event_type).ok_or_else(|| EngineError::UnknownEventType {
+                    event_type: event_type.clone(),
+                })?;
+            }
+        }
+        let broker = broker
+            .strip_prefix("mqtt://")
+            .or_else(|| broker.strip_prefix("tcp://"))
+            .unwrap_or(broker);
+        let broker = match broker.rsplit_once(':') {
+            Some((_, port)) if port.parse::<u16>().is_ok() => broker.to_string(),
+            _ => format!("{}:{}", broker, DEFAULT_PORT),
+        };
+        Ok(MqttBridgePlugin {
+            plugin_id,
+            broker,
+            topics,
+            client_id: format!("plyoreacto-{}", plugin_id),
+            credentials: None,
+            keep_alive: DEFAULT_KEEP_ALIVE,
+            reconnect_interval: DEFAULT_RECONNECT_INTERVAL,
+            max_reconnect_interval: DEFAULT_MAX_RECONNECT_INTERVAL,
+            dedup_window: DEFAULT_DEDUP_WINDOW,
+            session: None,
+        })
+    }
+
+    /// The client id the broker keeps the session of the plugin under, "plyoreacto-<id>"
+    /// unless set; it must be unique among the clients of the broker.
+    pub fn client_id(mut self, client_id: impl Into<String>) -> Self {
+        self.client_id = client_id.into();
+        self
+    }
+
+    /// Log in to the broker as `username`.
+    pub fn credentials(mut self, username: impl Into<String>, password: impl Into<String>) -> Self {
+        self.credentials = Some((username.into(), password.into()));
+        self
+    }
+
+    /// Ping the broker when nothing was sent to it for `keep_alive`, in whole seconds, and
+    /// connect again when it did not answer by the next ping.
+    pub fn keep_alive(mut self, keep_alive: Duration) -> Self {
+        self.keep_alive = keep_alive;
+        self
+    }
+
+    /// Wait `interval` before connecting again to a broker that went away, doubled after each
+    /// failed attempt up to `max_interval`.
+    pub fn reconnect_backoff(mut self, interval: Duration, max_interval: Duration) -> Self {
+        self.reconnect_interval = interval;
+        self.max_reconnect_interval = max_interval;
+        self
+    }
+
+    /// How long the messages received are remembered for, to tell the messages the broker
+    /// sends again from new ones.
+    pub fn dedup_window(mut self, dedup_window: Duration) -> Self {
+        self.dedup_window = dedup_window;
+        self
+    }
+
+    // Publish the next message of the broker, if one arrives soon, and acknowledge it.
+    fn receive(
+        &self,
+        ctx: &mut PluginContext,
+        session: &mut dyn MqttSession,
+        dedup: &mut DedupWindow,
+    ) -> Result<(), SessionEnd> {
+        if let Some(message) = session.next_message(TERMINATE_POLL_INTERVAL)? {
+            self.ingest(ctx, dedup, &message)?;
+            session.ack(&message)?;
+        }
+        Ok(())
+    }
+
+    // Publish `message` as the event of its topic, unless it was received already.
+    fn ingest(
+        &self,
+        ctx: &mut PluginContext,
+        dedup: &mut DedupWindow,
+        message: &Publish,
+    ) -> Result<(), PluginError> {
+        let topic = &message.topic;
+        // QoS 0 messages have no packet id, and are never sent again
+        if message.qos != QoS::AtMostOnce
+            && !dedup.insert(self.dedup_window, message.pkid, topic, &message.payload)
+        {
+            debug!(
+                plugin_id = self.plugin_id;
+                "MQTT bridge plugin skipped message {} of {}, received already", message.pkid, topic
+            );
+            return Ok(());
+        }
+        let Some((_, event)) = self
+            .topics
+            .iter()
+            .find(|(filter, _)| topic_matches(filter, topic))
+        else {
+            warn!(
+                plugin_id = self.plugin_id;
+                "MQTT bridge plugin skipped a message of {}, which no topic filter matches", topic
+            );
+            return Ok(());
+        };
+        let published = match event {
+            TopicEvent::NewImage { image_format } => {
+                let image_uuid = uuid::Uuid::new_v4().to_string();
+                let new_image = NewImage {
+                    image_uuid: image_uuid.clone(),
+                    image_format: image_format.clone(),
+                    image: message.payload.to_vec(),
+                    location: None,
+                };
+                ctx.publish(&new_image).map(|_| {
+                    info!(
+                        plugin_id = self.plugin_id;
+                        "(NEW IMAGE -- {}) MQTT bridge plugin sent message for a frame of {}",
+                        image_uuid, topic
+                    )
+                })
+            }
+            TopicEvent::Json { event_type } => Event::from_json(event_type, &message.payload)
+                .map_err(PluginError::from)
+                .and_then(|event| ctx.publish(&event).map(|_| ())),
+        };
+        match published {
+            // the message is acknowledged all the same, or the broker would send it forever
+            Err(PluginError::Event(e)) => {
+                error!(
+                    plugin_id = self.plugin_id;
+                    "MQTT bridge plugin dropped a message of {}: {}", topic, e
+                );
+                Ok(())
+            }
+            result => result,
+        }
+    }
+}
+
+impl Plugin for MqttBridgePlugin {
+    fn id(&self) -> i32 {
+        self.plugin_id
+    }
+
+    fn name(&self) -> &str {
+        "mqtt-bridge"
+    }
+
+    fn subscriptions(&self) -> &[&str] {
+        &[]
+    }
+
+    fn start(mut self: Box<Self>, mut ctx: PluginContext) -> Result<(), PluginError> {
+        let mut session = match self.session.take() {
+            Some(session) => session,
+            None => Box::new(BrokerSession::new(&self)?),
+        };
+        let mut dedup = DedupWindow::default();
+        let mut backoff = self.reconnect_interval;
+        loop {
+            if terminated(&mut ctx, Duration::ZERO)? {
+                info!(plugin_id = self.plugin_id; "MQTT bridge plugin got terminate event, exiting");
+                // the session is kept, so the broker holds the messages until the plugin is back
+                session.disconnect();
+                return Ok(());
+            }
+            match self.receive(&mut ctx, session.as_mut(), &mut dedup) {
+                Ok(()) => backoff = self.reconnect_interval,
+                Err(SessionEnd::Failed(e)) => return Err(e),
+                Err(SessionEnd::Lost(e)) => {
+                    warn!(
+                        plugin_id = self.plugin_id;
+                        "MQTT bridge plugin lost {}: {}; connecting again in {:?}",
+                        self.broker, e, backoff
+                    );
+                    // waiting before connecting again
+                    if terminated(&mut ctx, backoff)? {
+                        info!(
+                            plugin_id = self.plugin_id;
+                            "MQTT bridge plugin got terminate event, exiting"
+                        );
+                        return Ok(());
+                    }
+                    backoff = (backoff * 2).min(self.max_reconnect_interval);
+                }
+            }
+        }
+    }
+}
+
+// Why receiving from the broker stopped, other than the plugin being terminated.
+enum SessionEnd {
+    // the connection was lost, or could not be made, and is made again
+    Lost(String),
+    // the plugin cannot go on, e.g., the broker refused its credentials
+    Failed(PluginError),
+}
+
+impl From<PluginError> for SessionEnd {
+    fn from(e: PluginError) -> Self {
+        SessionEnd::Failed(e)
+    }
+}
+
+// The session of the plugin with its broker; a trait so that tests can stand in for the
+// broker.
+trait MqttSession: Send {
+    // The next message of the topics subscribed to, if one arrives within `timeout`, connecting
+    // first, for as long as that takes, if the connection was lost.
+    fn next_message(&mut self, timeout: Duration) -> Result<Option<Publish>, SessionEnd>;
+
+    // Acknowledge `message`, once published.
+    fn ack(&mut self, message: &Publish) -> Result<(), SessionEnd>;
+
+    // Leave the broker, which keeps the session.
+    fn disconnect(&mut self);
+}
+
+// The session with a broker over rumqttc, whose connection connects again when polled once
+// lost, resuming the session.
+struct BrokerSession {
+    plugin_id: i32,
+    broker: String,
+    filters: Vec<String>,
+    client: Client,
+    connection: Connection,
+    connected: bool,
+}
+
+impl BrokerSession {
+    fn new(plugin: &MqttBridgePlugin) -> Result<Self, PluginError> {
+        // the broker only keeps the sessions of clients with an id
+        if plugin.client_id.is_empty() {
+            return Err(PluginError::Other(
+                "MQTT bridge plugin needs a client id".to_string(),
+            ));
+        }
+        let (host, port) = plugin
+            .broker
+            .rsplit_once(':')
+            .and_then(|(host, port)| Some((host, port.parse().ok()?)))
+            .unwrap_or((&plugin.broker, DEFAULT_PORT));
+        let mut options = MqttOptions::new(&plugin.client_id, host, port);
+        options
+            .set_keep_alive(Duration::from_secs(plugin.keep_alive.as_secs()))
+            .set_clean_session(false)
+            .set_manual_acks(true)
+            .set_max_packet_size(MAX_PACKET_SIZE, MAX_PACKET_SIZE);
+        if let Some((username, password)) = &plugin.credentials {
+            options.set_credentials(username, password);
+        }
+        let (client, connection) = Client::new(options, REQUEST_CAPACITY);
+        Ok(BrokerSession {
+            plugin_id: plugin.plugin_id,
+            broker: plugin.broker.clone(),
+            filters: plugin
+                .topics
+                .iter()
+                .map(|(filter, _)| filter.clone())
+                .collect(),
+            client,
+            connection,
+            connected: false,
+        })
+    }
+
+    fn subscribe(&mut self) -> Result<(), SessionEnd> {
+        if self.filters.is_empty() {
+            return Ok(());
+        }
+        let filters = self
+            .filters
+            .iter()
+            .map(|filter| SubscribeFilter::new(filter.clone(), QoS::AtLeastOnce));
+        self.client
+            .try_subscribe_many(filters)
+            .map_err(|e| SessionEnd::Lost(e.to_string()))
+    }
+}
+
+impl MqttSession for BrokerSession {
+    fn next_message(&mut self, timeout: Duration) -> Result<Option<Publish>, SessionEnd> {
+        let event = if self.connected {
+            match self.connection.recv_timeout(timeout) {
+                Ok(event) => event,
+                Err(RecvTimeoutError::Timeout) => return Ok(None),
+                Err(RecvTimeoutError::Disconnected) => {
+                    return Err(SessionEnd::Lost("the MQTT client went away".to_string()))
+                }
+            }
+        } else {
+            // connecting, within the 5 s rumqttc gives it, is not cut short, or it would start
+            // over on each call
+            self.connection
+                .recv()
+                .map_err(|_| SessionEnd::Lost("the MQTT client went away".to_string()))?
+        };
+        match event {
+            Ok(MqttEvent::Incoming(Packet::ConnAck(connack))) => {
+                self.connected = true;
+                info!(
+                    plugin_id = self.plugin_id;
+                    "MQTT bridge plugin connected to {} (session present: {})",
+                    self.broker, connack.session_present
+                );
+                // the subscriptions of a session kept by the broker are made again anyway, in
+                // case the topics changed
+                self.subscribe()?;
+                Ok(None)
+            }
+            Ok(MqttEvent::Incoming(Packet::Publish(message))) => Ok(Some(message)),
+            Ok(MqttEvent::Incoming(Packet::SubAck(suback))) => {
+                for (filter, code) in self.filters.iter().zip(suback.return_codes) {
+                    if code == SubscribeReasonCode::Failure {
+                        return Err(SessionEnd::Failed(PluginError::Other(format!(
+                            "MQTT broker {} refused the subscription to {}",
+                            self.broker, filter
+                        ))));
+                    }
+                }
+                Ok(None)
+            }
+            Ok(_) => Ok(None),
+            Err(ConnectionError::ConnectionRefused(code)) => {
+                Err(SessionEnd::Failed(PluginError::Other(format!(
+                    "MQTT broker {} refused the connection: {:?}",
+                    self.broker, code
+                ))))
+            }
+            Err(e) => {
+                self.connected = false;
+                Err(SessionEnd::Lost(e.to_string()))
+            }
+        }
+    }
+
+    fn ack(&mut self, message: &Publish) -> Result<(), SessionEnd> {
+        self.client
+            .try_ack(message)
+            .map_err(|e| SessionEnd::Lost(e.to_string()))
+    }
+
+    fn disconnect(&mut self) {
+        if !self.connected || self.client.try_disconnect().is_err() {
+            return;
+        }
+        // the acks queued, then the DISCONNECT, are only sent as the connection is polled
+        let deadline = Instant::now() + DISCONNECT_TIMEOUT;
+        while let Some(left) = deadline.checked_duration_since(Instant::now()) {
+            match self.connection.recv_timeout(left) {
+                Ok(Ok(MqttEvent::Outgoing(Outgoing::Disconnect))) | Ok(Err(_)) | Err(_) => return,
+                Ok(Ok(_)) => {}
+            }
+        }
+    }
+}
+
+// Take the events for the plugin that arrive within `timeout`, if any; true if one was the
+// PluginTerminateEvent.
+fn terminated(ctx: &mut PluginContext, timeout: Duration) -> Result<bool, PluginError> {
+    let deadline = Instant::now() + timeout;
+    while let Some(msg) = ctx.wait_for_event(deadline.saturating_duration_since(Instant::now()))? {
+        if msg.event_type == "PluginTerminateEvent" {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+// The messages received within the dedup window, by packet id and digest of their topic and
+// payload: the broker only reuses the id of a message once it is acknowledged, so a message
+// with the id of a recent one is only that one again if it also has its content.
+#[derive(Default)]
+struct DedupWindow {
+    received: VecDeque<(Instant, (u16, [u8; 32]))>,
+    keys: HashSet<(u16, [u8; 32])>,
+}
+
+impl DedupWindow {
+    // Remember the message, forgetting those older than `window`; false if it was received
+    // within the window already.
+    fn insert(&mut self, window: Duration, packet_id: u16, topic: &str, payload: &[u8]) -> bool {
+        while let Some((at, key)) = self.received.front() {
+            if at.elapsed() <= window {
+                break;
+            }
+            self.keys.remove(key);
+            self.received.pop_front();
+        }
+        let mut digest = Sha256::new();
+        digest.update(topic.as_bytes());
+        digest.update([0]);
+        digest.update(payload);
+        let key = (packet_id, digest.finalize().into());
+        if !self.keys.insert(key) {
+            return false;
+        }
+        self.received.push_back((Instant::now(), key));
+        true
+    }
+}
+
+// Whether the MQTT topic filter `filter` matches `topic`: "+" matches a level of the topic and
+// "#", last, the levels left, if any. Topics starting with "$" are only matched by filters
+// starting with it.
+fn topic_matches(filter: &str, topic: &str) -> bool {
+    if topic.starts_with('$') != filter.starts_with('$') {
+        return false;
+    }
+    let mut levels = topic.split('/');
+    for filter_level in filter.split('/') {
+        if filter_level == "#" {
+            return true;
+        }
+        match levels.next() {
+            Some(level) if filter_level == "+" || filter_level == level => {}
+            _ => return false,
+        }
+    }
+    levels.next().is_none()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::event_engine::{EventEngineBuilder, Transport};
+    use crate::image_score_plugin::{FixedScorer, ImageScorePlugin};
+    use crate::image_store_plugin::ImageStorePlugin;
+    use crate::plugin_registry::PluginRegistry;
+    use crate::storage::{InMemoryStore, StorageBackend};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    // Hands the plugin the messages of its script, in order, once `started` is set, a `None`
+    // standing for a lost connection, then nothing; records the packet ids it acknowledged, and
+    // whether it left.
+    struct ScriptedSession {
+        started: Arc<AtomicBool>,
+        script: VecDeque<Option<Publish>>,
+        acks: Arc<Mutex<Vec<u16>>>,
+        disconnected: Arc<Mutex<bool>>,
+    }
+
+    impl MqttSession for ScriptedSession {
+        fn next_message(&mut self, timeout: Duration) -> Result<Option<Publish>, SessionEnd> {
+            if !self.started.load(Ordering::SeqCst) {
+                std::thread::sleep(timeout);
+                return Ok(None);
+            }
+            match self.script.pop_front() {
+                Some(Some(message)) => Ok(Some(message)),
+                Some(None) => Err(SessionEnd::Lost("broker went away".to_string())),
+                None => {
+                    std::thread::sleep(timeout);
+                    Ok(None)
+                }
+            }
+        }
+
+        fn ack(&mut self, message: &Publish) -> Result<(), SessionEnd> {
+            self.acks.lock().unwrap().push(message.pkid);
+            Ok(())
+        }
+
+        fn disconnect(&mut self) {
+            *self.disconnected.lock().unwrap() = true;
+        }
+    }
+
+    // The frame numbered `frame`, as message `packet_id`.
+    fn frame(packet_id: u16, frame: u16, dup: bool) -> Option<Publish> {
+        let mut message = Publish::new(
+            format!("cameras/{}/jpeg", frame % 2),
+            QoS::AtLeastOnce,
+            format!("frame {}", frame),
+        );
+        message.pkid = packet_id;
+        message.dup = dup;
+        Some(message)
+    }
+
+    #[test]
+    fn test_frames_are_stored_once_across_reconnects() {
+        let started = Arc::new(AtomicBool::new(false));
+        let acks = Arc::new(Mutex::new(Vec::new()));
+        let disconnected = Arc::new(Mutex::new(false));
+        let script = [frame(1, 1, false), frame(2, 2, false), frame(3, 3, false)]
+            .into_iter()
+            // the broker goes away, and takes the plugin back having lost the ack of 3, which
+            // it sends again
+            .chain([None, frame(3, 3, true)])
+            // the ids of acknowledged messages are reused for new ones
+            .chain([frame(1, 101, false), frame(4, 104, false)])
+            .collect();
+        let mut mqtt = MqttBridgePlugin::new(
+            1,
+            "mqtt://broker",
+            vec![(
+                "cameras/+/jpeg".to_string(),
+                TopicEvent::NewImage {
+                    image_format: "jpeg".to_string(),
+                },
+            )],
+        )
+        .unwrap()
+        .reconnect_backoff(Duration::from_millis(10), Duration::from_millis(100));
+        mqtt.session = Some(Box::new(ScriptedSession {
+            started: started.clone(),
+            script,
+            acks: acks.clone(),
+            disconnected: disconnected.clone(),
+        }));
+        let store = InMemoryStore::new();
+        let labrador = FixedScorer::new(vec![("labrador".to_string(), 1.0)]);
+        let mut plugins = PluginRegistry::new();
+        plugins
+            .register_plugin(Box::new(mqtt))
+            .unwrap()
+            .register_plugin(Box::new(ImageScorePlugin::with_scorer(
+                2,
+                Box::new(labrador),
+            )))
+            .unwrap()
+            .register_plugin(Box::new(
+                ImageStorePlugin::new(3).storage(Box::new(store.clone())),
+            ))
+            .unwrap();
+        let engine = EventEngineBuilder::new()
+            .incoming_inproc("messages-mqtt")
+            .outgoing_inproc("events-mqtt")
+            .transport(Transport::InprocOnly)
+            .plugins(plugins)
+            .start()
+            .unwrap();
+        let stored_rx = engine.subscribe(&["ImageStoredEvent"]).unwrap();
+        // the broker only sends the frames once the subscription is there to see them stored
+        std::thread::sleep(Duration::from_millis(200));
+        started.store(true, Ordering::SeqCst);
+
+        let mut stored = Vec::new();
+        while let Ok(event) = stored_rx.recv_timeout(Duration::from_secs(2)) {
+            if let Event::ImageStored(image) = event {
+                stored.push(store.get(&image.image_uuid).unwrap().unwrap());
+            }
+        }
+        stored.sort();
+        let frames: Vec<_> = ["frame 1", "frame 101", "frame 104", "frame 2", "frame 3"]
+            .map(|frame| frame.as_bytes().to_vec())
+            .into();
+        assert_eq!(stored, frames);
+        // every message was acknowledged, the one sent again too
+        assert_eq!(*acks.lock().unwrap(), [1, 2, 3, 3, 1, 4]);
+        engine.shutdown().unwrap();
+        // the plugin ends its session on the way out
+        assert!(*disconnected.lock().unwrap());
+    }
+
+    #[test]
+    fn test_unreachable_brokers_are_lost() {
+        let plugin = MqttBridgePlugin::new(1, "127.0.0.1:1", Vec::new()).unwrap();
+        let mut session = BrokerSession::new(&plugin).unwrap();
+        assert!(matches!(
+            session.next_message(TERMINATE_POLL_INTERVAL),
+            Err(SessionEnd::Lost(_))
+        ));
+        // the plugin cannot resume a session without a client id
+        let plugin = plugin.client_id("");
+        assert!(BrokerSession::new(&plugin).is_err());
+    }
+
+    #[test]
+    fn test_topic_filters() {
+        assert!(topic_matches("cameras/+/jpeg", "cameras/1/jpeg"));
+        assert!(!topic_matches("cameras/+/jpeg", "cameras/1/2/jpeg"));
+        assert!(!topic_matches("cameras/+/jpeg", "cameras/1/png"));
+        assert!(topic_matches("cameras/#", "cameras/1/jpeg"));
+        assert!(topic_matches("cameras/#", "cameras"));
+        assert!(!topic_matches("cameras/1", "cameras/1/jpeg"));
+        assert!(!topic_matches("#", "$SYS/uptime"));
+        assert!(topic_matches("$SYS/#", "$SYS/uptime"));
+    }
+
+    #[test]
+    fn test_messages_are_deduplicated_within_the_window() {
+        let mut dedup = DedupWindow::default();
+        let window = Duration::from_millis(50);
+        assert!(dedup.insert(window, 1, "cameras/1/jpeg", b"frame"));
+        assert!(!dedup.insert(window, 1, "cameras/1/jpeg", b"frame"));
+        assert!(dedup.insert(window, 1, "cameras/1/jpeg", b"another frame"));
+        assert!(dedup.insert(window, 2, "cameras/1/jpeg", b"frame"));
+        std::thread::sleep(window * 2);
+        assert!(dedup.insert(window, 1, "cameras/1/jpeg", b"frame"));
+        assert_eq!(dedup.keys.len(), 1);
+    }
+
+    #[test]
+    fn test_json_events_are_checked() {
+        let topics = vec![(
+            "cameras/+/deletes".to_string(),
+            TopicEvent::Json {
+                event_type: "ImageDeletedEvnt".to_string(),
+            },
+        )];
+        assert!(matches!(
+            MqttBridgePlugin::new(1, "broker", topics),
+            Err(EngineError::UnknownEventType { event_type }) if event_type == "ImageDeletedEvnt"
+        ));
+        let plugin = MqttBridgePlugin::new(1, "mqtt://broker", Vec::new()).unwrap();
+        assert_eq!(plugin.broker, "broker:1883");
+    }
+}
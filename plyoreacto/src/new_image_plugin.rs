@@ -1,9 +1,19 @@
 //! New Image plugin. *Plugin 1*
-//! This plugin publishes NewImageEvent messages. It does not subscribe to any messages.
+//! This plugin publishes NewImageEvent messages, for generated images or for the image files that
+//! appear in a watched directory. It does not subscribe to any messages, unless it pauses on
+//! backpressure: then it subscribes to the BackpressureEvents and BackpressureRelievedEvents, and
+//! publishes nothing while a plugin reports backpressure.
 //!
 
-use super::events::send_new_image_event;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use super::events::{send_new_image_event, NewImage};
+use crate::plugin::{Plugin, PluginContext, PluginError, BACKPRESSURE_EVENTS};
 use flatbuffers::FlatBufferBuilder;
+use log::{error, info};
 use zmq::Socket;
 
 pub fn start(
@@ -15,16 +25,10 @@ pub fn start(
     let mut count = 0;
     while count < 5 {
         let uuid = uuid::Uuid::new_v4().to_string();
-        send_new_image_event(
-            pub_socket,
-            bldr,
-            &uuid,
-            &"png".to_string(),
-            &Vec::<u8>::new(),
-        )
-        .expect("Could not send a new message event");
-
-        println!(
+        send_new_image_event(pub_socket, bldr, &uuid, "png", &Vec::<u8>::new())
+            .expect("Could not send a new message event");
+
+        info!(
             "(NEW IMAGE -- {}) New Image plugin sent message {:?}",
             uuid, uuid
         );
@@ -32,3 +36,421 @@ pub fn start(
     }
     Ok(())
 }
+
+/// The new image plugin, for registering with a `PluginRegistry`. It generates five empty
+/// images (see `images` and `image_size` for more, or bigger, ones), or, when made with
+/// `NewImagePlugin::watch` or given an `image_dir` setting (a path; see
+/// `PluginRegistry::settings`), publishes the image files that appear in a directory.
+pub struct NewImagePlugin {
+    plugin_id: i32,
+    // how many images to generate, and how many bytes each
+    images: usize,
+    image_size: usize,
+    // the directory to take the images from, if any
+    watch: Option<DirectoryWatch>,
+    // whether to publish nothing while a plugin reports backpressure
+    pause_on_backpressure: bool,
+}
+
+// How many images are generated, unless set with `NewImagePlugin::images`; the score and store
+// plugins handle as many by default, so that the example pipeline runs its course.
+pub(crate) const DEFAULT_IMAGES: usize = 5;
+
+// How often a watched directory is listed, unless set with `NewImagePlugin::poll_interval`.
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+struct DirectoryWatch {
+    dir: PathBuf,
+    poll_interval: Duration,
+    // whether the files are published by path rather than with their bytes
+    by_reference: bool,
+}
+
+impl DirectoryWatch {
+    fn new(dir: PathBuf) -> Self {
+        DirectoryWatch {
+            dir,
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            by_reference: false,
+        }
+    }
+}
+
+// What was seen of a file in the watched directory at the last poll.
+struct FileState {
+    len: u64,
+    modified: Option<SystemTime>,
+    published: bool,
+}
+
+impl NewImagePlugin {
+    pub fn new(plugin_id: i32) -> Self {
+        NewImagePlugin {
+            plugin_id,
+            images: DEFAULT_IMAGES,
+            image_size: 0,
+            watch: None,
+            pause_on_backpressure: false,
+        }
+    }
+
+    /// How many images to generate before the plugin returns.
+    pub fn images(mut self, images: usize) -> Self {
+        self.images = images;
+        self
+    }
+
+    /// How many bytes each generated image has; they are all zero.
+    pub fn image_size(mut self, image_size: usize) -> Self {
+        self.image_size = image_size;
+        self
+    }
+
+    /// A plugin that watches `dir` and publishes a `NewImageEvent`, with a new uuid, for every
+    /// image file that appears in it, until the plugin is terminated. The image format is taken
+    /// from the file extension. A file is only published once its size and modification time
+    /// have not changed between two polls, and files ending in `.tmp` are ignored, so files can
+    /// also be written under a `.tmp` name and renamed when complete. A file that is replaced
+    /// under the same name is published again, as a new image.
+    pub fn watch(plugin_id: i32, dir: impl Into<PathBuf>) -> Self {
+        NewImagePlugin {
+            watch: Some(DirectoryWatch::new(dir.into())),
+            ..NewImagePlugin::new(plugin_id)
+        }
+    }
+
+    /// How often the watched directory is checked for new files.
+    pub fn poll_interval(mut self, poll_interval: Duration) -> Self {
+        if let Some(watch) = &mut self.watch {
+            watch.poll_interval = poll_interval;
+        }
+        self
+    }
+
+    /// Publish nothing while a plugin reports backpressure (see
+    /// `PluginContext::report_backpressure`), e.g., an image store plugin whose disk cannot keep
+    /// up: the plugin waits for its BackpressureRelievedEvent before publishing the next image.
+    pub fn pause_on_backpressure(mut self) -> Self {
+        self.pause_on_backpressure = true;
+        self
+    }
+
+    /// Publish the path of each file of the watched directory, as the `location` of its
+    /// NewImageEvent, instead of its bytes, for subscribers that share the filesystem (see
+    /// `ImagePayload`). The files have to stay until the subscribers have read them.
+    pub fn by_reference(mut self) -> Self {
+        if let Some(watch) = &mut self.watch {
+            watch.by_reference = true;
+        }
+        self
+    }
+}
+
+// The image format of a file, from its extension (e.g., "png" or "jpeg").
+fn image_format(path: &Path) -> Option<String> {
+    let extension = path.extension()?.to_str()?.to_lowercase();
+    Some(match extension.as_str() {
+        "jpg" => "jpeg".to_string(),
+        "tif" => "tiff".to_string(),
+        _ => extension,
+    })
+}
+
+impl DirectoryWatch {
+    // List the directory and return the files that are ready to be published: those that have
+    // not changed since the last poll and have not been published yet.
+    fn poll(&self, files: &mut HashMap<PathBuf, FileState>) -> std::io::Result<Vec<PathBuf>> {
+        let mut seen = HashMap::new();
+        let mut ready = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            if name.starts_with('.') || name.ends_with(".tmp") || image_format(&path).is_none() {
+                continue;
+            }
+            // the file may be gone already, or be a directory
+            let metadata = match entry.metadata() {
+                Ok(metadata) if metadata.is_file() => metadata,
+                _ => continue,
+            };
+            let mut state = FileState {
+                len: metadata.len(),
+                modified: metadata.modified().ok(),
+                published: false,
+            };
+            if let Some(previous) = files.remove(&path) {
+                let unchanged = previous.len == state.len && previous.modified == state.modified;
+                if unchanged {
+                    // empty files are most likely still being written
+                    if !previous.published && state.len > 0 {
+                        ready.push(path.clone());
+                    }
+                    state.published = previous.published || state.len > 0;
+                }
+            }
+            seen.insert(path, state);
+        }
+        // files that disappeared are forgotten, so a new file with the same name is published
+        *files = seen;
+        Ok(ready)
+    }
+}
+
+// Publish the image files that appear in the watched directory until the plugin is terminated,
+// waiting before each one while a plugin reports backpressure if `pause_on_backpressure`.
+fn watch_directory(
+    mut ctx: PluginContext,
+    watch: &DirectoryWatch,
+    pause_on_backpressure: bool,
+) -> Result<(), PluginError> {
+    let mut files = HashMap::new();
+    loop {
+        for path in watch.poll(&mut files)? {
+            if pause_on_backpressure && !ctx.wait_for_relief()? {
+                info!(plugin_id = ctx.plugin_id; "New Image plugin got terminate event, exiting");
+                return Ok(());
+            }
+            let read = if watch.by_reference {
+                fs::canonicalize(&path).map(|path| (Vec::new(), Some(path.display().to_string())))
+            } else {
+                fs::read(&path).map(|image| (image, None))
+            };
+            let (image, location) = match read {
+                Ok(read) => read,
+                Err(e) => {
+                    error!(
+                        plugin_id = ctx.plugin_id;
+                        "New Image plugin could not read {}: {}",
+                        path.display(), e
+                    );
+                    files.remove(&path);
+                    continue;
+                }
+            };
+            let uuid = uuid::Uuid::new_v4().to_string();
+            ctx.publish(&NewImage {
+                image_uuid: uuid.clone(),
+                image_format: image_format(&path).unwrap_or_default(),
+                image,
+                location,
+            })?;
+            info!(
+                plugin_id = ctx.plugin_id;
+                "(NEW IMAGE -- {}) New Image plugin sent message for file {}",
+                uuid, path.display()
+            );
+        }
+        // wait for the next poll, unless the engine terminates the plugin first
+        let timeout = watch.poll_interval.as_millis() as i64;
+        if ctx.sub_socket.poll(zmq::POLLIN, timeout)? > 0
+            && ctx.next_event()?.event_type == "PluginTerminateEvent"
+        {
+            info!(plugin_id = ctx.plugin_id; "New Image plugin got terminate event, exiting");
+            return Ok(());
+        }
+    }
+}
+
+impl Plugin for NewImagePlugin {
+    fn id(&self) -> i32 {
+        self.plugin_id
+    }
+
+    fn name(&self) -> &str {
+        "new-image"
+    }
+
+    fn subscriptions(&self) -> &[&str] {
+        if self.pause_on_backpressure {
+            &BACKPRESSURE_EVENTS
+        } else {
+            &[]
+        }
+    }
+
+    fn publications(&self) -> &[&str] {
+        &["NewImageEvent"]
+    }
+
+    fn start(mut self: Box<Self>, mut ctx: PluginContext) -> Result<(), PluginError> {
+        // the directory of the settings replaces the one the plugin was made with
+        if ctx.settings().contains("image_dir") {
+            let dir = ctx.settings().get_path("image_dir")?;
+            match &mut self.watch {
+                Some(watch) => watch.dir = dir,
+                None => self.watch = Some(DirectoryWatch::new(dir)),
+            }
+        }
+        if self.pause_on_backpressure {
+            ctx.pause_on_backpressure()?;
+        }
+        if let Some(watch) = &self.watch {
+            return watch_directory(ctx, watch, self.pause_on_backpressure);
+        }
+        // send the New Image events as fast as we can; each one starts the chain of events of
+        // its image
+        let mut new_image = NewImage {
+            image_uuid: String::new(),
+            image_format: "png".to_string(),
+            image: vec![0; self.image_size],
+            location: None,
+        };
+        for _ in 0..self.images {
+            if self.pause_on_backpressure && !ctx.wait_for_relief()? {
+                info!(plugin_id = ctx.plugin_id; "New Image plugin got terminate event, exiting");
+                return Ok(());
+            }
+            let uuid = uuid::Uuid::new_v4().to_string();
+            new_image.image_uuid = uuid.clone();
+            ctx.publish(&new_image)?;
+            info!(
+                plugin_id = ctx.plugin_id;
+                "(NEW IMAGE -- {}) New Image plugin sent message {:?}",
+                uuid, uuid
+            );
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::event_engine::EventEngineBuilder;
+    use crate::external::ExternalPluginClient;
+    use crate::image_score_plugin::{FixedScorer, ImageScorePlugin};
+    use crate::image_store_plugin::ImageStorePlugin;
+    use crate::plugin_registry::PluginRegistry;
+    use crate::storage::{InMemoryStore, StorageBackend, StorageError, StoredLocation};
+
+    #[test]
+    fn test_image_format_from_extension() {
+        assert_eq!(image_format(Path::new("a/b.png")), Some("png".to_string()));
+        assert_eq!(image_format(Path::new("b.JPG")), Some("jpeg".to_string()));
+        assert_eq!(image_format(Path::new("b.tif")), Some("tiff".to_string()));
+        assert_eq!(image_format(Path::new("README")), None);
+    }
+
+    #[test]
+    fn test_files_are_ready_once_unchanged() {
+        let dir = std::env::temp_dir().join(format!("plyoreacto-poll-{}", uuid::Uuid::new_v4()));
+        fs::create_dir(&dir).unwrap();
+        let watch = DirectoryWatch {
+            dir: dir.clone(),
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            by_reference: false,
+        };
+        let mut files = HashMap::new();
+        let image = dir.join("image.png");
+        fs::write(&image, [1, 2]).unwrap();
+        fs::write(dir.join("partial.png.tmp"), [1]).unwrap();
+        fs::write(dir.join("empty.png"), []).unwrap();
+
+        assert!(watch.poll(&mut files).unwrap().is_empty());
+        assert_eq!(watch.poll(&mut files).unwrap(), vec![image.clone()]);
+        // a file is only ready once
+        assert!(watch.poll(&mut files).unwrap().is_empty());
+
+        // a file replaced under the same name is a new image
+        fs::remove_file(&image).unwrap();
+        assert!(watch.poll(&mut files).unwrap().is_empty());
+        fs::write(&image, [3, 4, 5]).unwrap();
+        assert!(watch.poll(&mut files).unwrap().is_empty());
+        assert_eq!(watch.poll(&mut files).unwrap(), vec![image]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    // An in-memory store taking 20 ms to put an image, like a slow disk.
+    struct SlowStore(InMemoryStore);
+
+    impl StorageBackend for SlowStore {
+        fn put(
+            &mut self,
+            image_uuid: &str,
+            image_format: &str,
+            image: &[u8],
+        ) -> Result<StoredLocation, StorageError> {
+            std::thread::sleep(Duration::from_millis(20));
+            self.0.put(image_uuid, image_format, image)
+        }
+
+        fn get(&self, image_uuid: &str) -> Result<Option<Vec<u8>>, StorageError> {
+            self.0.get(image_uuid)
+        }
+
+        fn delete(&mut self, image_uuid: &str) -> Result<bool, StorageError> {
+            self.0.delete(image_uuid)
+        }
+    }
+
+    #[test]
+    fn test_generated_images_pause_on_backpressure() {
+        const IMAGES: usize = 60;
+        let builder = EventEngineBuilder::new()
+            .incoming_port(27659)
+            .outgoing_port(27660)
+            .sync_port(27100)
+            .sync_timeout(Duration::from_secs(10));
+        let config = builder.config().clone();
+        // the observer syncs while the engine starts, so that it sees every event
+        let (types_tx, types_rx) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            let subscriptions = [
+                "NewImageEvent",
+                "BackpressureRelievedEvent",
+                "ImageStoredEvent",
+            ];
+            let mut client =
+                ExternalPluginClient::connect_with_config("localhost", &config, 9, &subscriptions)
+                    .unwrap();
+            while let Ok(msg) = client.next_event() {
+                if types_tx.send(msg.event_type).is_err() {
+                    break;
+                }
+            }
+        });
+        let labrador = FixedScorer::new(vec![("labrador".to_string(), 1.0)]);
+        let mut plugins = PluginRegistry::new();
+        plugins
+            .register_plugin(Box::new(
+                NewImagePlugin::new(1)
+                    .images(IMAGES)
+                    .image_size(64 * 1024)
+                    .pause_on_backpressure(),
+            ))
+            .unwrap()
+            .register_plugin(Box::new(
+                ImageScorePlugin::with_scorer(2, Box::new(labrador)).images(IMAGES),
+            ))
+            .unwrap()
+            .register_plugin(Box::new(
+                ImageStorePlugin::new(3)
+                    .images(IMAGES)
+                    .storage(Box::new(SlowStore(InMemoryStore::new())))
+                    .backpressure(4),
+            ))
+            .unwrap()
+            .register_external(9)
+            .unwrap();
+        let engine = builder.plugins(plugins).start().unwrap();
+
+        // every image is stored: none was dropped on the way
+        let mut types = Vec::new();
+        while types.iter().filter(|t| *t == "ImageStoredEvent").count() < IMAGES {
+            match types_rx.recv_timeout(Duration::from_secs(10)) {
+                Ok(event_type) => types.push(event_type),
+                Err(_) => panic!("not every image was stored: {:?}", types),
+            }
+        }
+        let position = |event_type: &str| types.iter().position(|t| t == event_type);
+        let images = types.iter().filter(|t| *t == "NewImageEvent").count();
+        assert_eq!(images, IMAGES);
+        // the producer paused: the store caught up before the last image was published
+        let relieved = position("BackpressureRelievedEvent").unwrap_or_else(|| panic!("{:?}", types));
+        let last_image = types.iter().rposition(|t| t == "NewImageEvent").unwrap();
+        assert!(relieved < last_image, "{:?}", types);
+        engine.shutdown().unwrap();
+    }
+}
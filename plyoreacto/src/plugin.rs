@@ -0,0 +1,2381 @@
+//! The `Plugin` trait implemented by everything the engine runs in a plugin thread.
+//! A plugin declares its id, a name used in log output, and the events it subscribes to; the
+//! engine creates and syncs its sockets and then hands them to `start` in a `PluginContext`.
+//!
+
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use flatbuffers::FlatBufferBuilder;
+use log::{debug, info, warn};
+use serde_json::{json, Map, Value};
+use zmq::Socket;
+
+use crate::acks::AckChannel;
+use crate::compression::{decompressed, Compression};
+use crate::event_engine::{subscribed, DEFAULT_MAX_PAYLOAD_SIZE, DEFAULT_PLUGIN_RECV_TIMEOUT};
+use crate::events::{
+    check_schema_version, closest_event_type, event_type_matches, excluded_event_type,
+    flatbuffers_payload, get_event_type_bytes_filter, now_ms, parse_event_messages,
+    recv_event_frames, retry_on_eintr, send_event_msg_with_meta, verify_event, wildcard_prefix,
+    Backpressure, BackpressureRelieved, Codec, DeadLetter, Event, EventError, EventMeta,
+    EventPayload, EventsDropped, Frame, PluginHeartbeat, Tick,
+};
+use crate::events_generated::events::Event as FbEvent;
+use crate::plugin_registry::PluginStartFn;
+use crate::settings::{PluginSettings, SettingsError};
+
+// How many events a paused plugin holds by default.
+const DEFAULT_PAUSE_BUFFER: usize = 1000;
+
+/// The event types a plugin pausing on backpressure subscribes to (see
+/// `PluginContext::pause_on_backpressure`), for plugins to list in their `subscriptions`.
+pub const BACKPRESSURE_EVENTS: [&str; 2] = ["BackpressureEvent", "BackpressureRelievedEvent"];
+
+/// The gaps in the sequence numbers of the events `PluginContext::next_event` received from one
+/// publisher, over all the event types it publishes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct GapStats {
+    /// How many times events were missing between two received events of a type.
+    pub gaps: u64,
+    /// How many events were missing in all.
+    pub missed: u64,
+    /// How many times the sequence numbers went backwards, as they do when the publisher
+    /// restarts and numbers its events from 1 again; these are not gaps.
+    pub resets: u64,
+}
+
+/// A gap in the sequence numbers of the events of type `event_type` from the plugin
+/// `publisher_id`: the events numbered after `last_seq` and before `seq` were not received.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SeqGap {
+    pub publisher_id: i32,
+    pub event_type: &'static str,
+    pub last_seq: u64,
+    pub seq: u64,
+}
+
+impl SeqGap {
+    /// How many events are missing.
+    pub fn missed(&self) -> u64 {
+        self.seq - self.last_seq - 1
+    }
+}
+
+/// What `next_event` does with the events of a plugin paused with `EngineHandle::pause_plugin`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PausePolicy {
+    /// Hold up to this many events, returned in order once the plugin is resumed; those arriving
+    /// while that many are held are dropped.
+    Buffer(usize),
+    /// Drop every event.
+    Drop,
+}
+
+impl Default for PausePolicy {
+    fn default() -> Self {
+        PausePolicy::Buffer(DEFAULT_PAUSE_BUFFER)
+    }
+}
+
+// Whether a plugin started by the engine is paused, and how many of its events were dropped
+// while it was; shared by the engine handle and the contexts of the plugin (one per start, or per
+// worker of its pool).
+#[derive(Debug, Default)]
+pub(crate) struct PauseState {
+    paused: AtomicBool,
+    dropped: AtomicU64,
+}
+
+impl PauseState {
+    // Pause or resume the plugin, returning whether it was paused.
+    pub(crate) fn set_paused(&self, paused: bool) -> bool {
+        self.paused.swap(paused, Ordering::SeqCst)
+    }
+
+    fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+
+    pub(crate) fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
+
+// What `PluginContext::on_gap` calls.
+type GapCallback = Box<dyn FnMut(&SeqGap) + Send>;
+
+/// Sockets and builder handed to a plugin when it starts. Plugins publish and receive events
+/// through `publish` and `next_event`, which take care of the message framing.
+pub struct PluginContext {
+    // id of the plugin, recorded as the source of the events it publishes
+    pub(crate) plugin_id: i32,
+    // socket to publish new events on; connected to the engine's incoming socket
+    pub(crate) pub_socket: Socket,
+    // socket the plugin's subscribed events (and the PluginTerminateEvent) arrive on
+    pub(crate) sub_socket: Socket,
+    // builder for serializing events
+    pub(crate) bldr: FlatBufferBuilder<'static>,
+    // whether `next_event` answers the engine heartbeats
+    answer_heartbeats: bool,
+    // sequence number of the last event of each type the plugin published
+    published_seqs: HashMap<&'static str, u64>,
+    // sequence number of the last event of each type received from each publisher, by plugin id
+    received_seqs: HashMap<(i32, &'static str), u64>,
+    dropped_events: u64,
+    // the gaps in the sequence numbers received from each publisher, by plugin id
+    gap_stats: HashMap<i32, GapStats>,
+    // called with every gap `next_event` detects
+    on_gap: Option<GapCallback>,
+    // how many events `next_event` skipped because their message or payload was malformed
+    malformed_events: u64,
+    // whether `next_event` publishes an EventsDroppedEvent when events were dropped
+    publish_dropped_events: bool,
+    // whether `next_event` checks the sequence numbers of the events for gaps; not for the
+    // workers of a pool, which only get some of each publisher's events
+    detect_gaps: bool,
+    // the event types and wildcards the sub socket is subscribed to, besides the
+    // PluginTerminateEvent; `subscribe` and `unsubscribe` keep it up to date
+    subscriptions: HashSet<String>,
+    // the event types and wildcards the subscriptions of the plugin exclude
+    exclusions: Vec<String>,
+    // the backlog of the plugin, tracked once it reports backpressure
+    backpressure: Option<BacklogState>,
+    // the plugins that reported backpressure, and no relief since; tracked once the plugin
+    // pauses on backpressure
+    backpressured: Option<HashSet<i32>>,
+    // the biggest serialized event `publish` sends, in bytes; zero sends events of any size
+    max_payload_size: usize,
+    // when `publish` compresses the payloads; None (the default) never does
+    compression: Option<Compression>,
+    // how `publish` serializes the events
+    codec: Codec,
+    // id of the engine the plugin runs on, recorded as the origin of the events it publishes
+    engine_id: u32,
+    // set when the engine that started the plugin is shutting down; `next_event` checks it
+    // whenever the sub socket times out
+    stopping: Option<Arc<AtomicBool>>,
+    // whether `next_event` gave the PluginTerminateEvent its last chance to arrive, once
+    // `stopping` was set
+    stop_seen: bool,
+    // the back-channel to the engine for the events delivered at least once to the plugin; only
+    // set by the engine
+    acks: Option<AckChannel>,
+    // whether the plugin is paused, and what `next_event` does with its events while it is;
+    // only set by the engine
+    pause: Option<(Arc<PauseState>, PausePolicy)>,
+    // the events held while the plugin was paused, returned before any other
+    held: VecDeque<EventMsg>,
+    // the configuration values the plugin was registered with
+    settings: PluginSettings,
+    // the timer `next_event` returns the TickEvents of, if set
+    timer: Option<Timer>,
+    // counters of the events the plugin publishes and receives; only set by the engine
+    #[cfg(feature = "prometheus")]
+    pub(crate) counters: Option<std::sync::Arc<crate::prometheus::PluginCounters>>,
+}
+
+/// An event received by a plugin: the name of its type (from the header frame), its envelope
+/// (from the meta frame) and the serialized event (the payload frame). Events published without
+/// `PluginContext::publish`, e.g., on a raw socket, have no envelope. The payload stays in the
+/// message it was received in, and `event()` parses it there.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EventMsg {
+    pub event_type: String,
+    pub meta: Option<EventMeta>,
+    pub payload: Frame,
+}
+
+impl EventMsg {
+    /// Parse the payload as an `Event` flatbuffer, verified to hold an event of the announced
+    /// type (see `verify_event`). The payloads of the events returned by
+    /// `PluginContext::next_event` are flatbuffers whatever codec they were published with.
+    pub fn event(&self) -> Result<FbEvent<'_>, PluginError> {
+        Ok(verify_event(&self.event_type, &self.payload)?)
+    }
+
+    /// Decode the payload into an owned `Event`, checking that it is of the announced type.
+    pub fn decode(&self) -> Result<Event, PluginError> {
+        Ok(Event::decode_with_meta(
+            &self.event_type,
+            self.meta.as_ref(),
+            &self.payload,
+        )?)
+    }
+
+    /// Whether the event was replayed from an event log, or published in reply to one that was;
+    /// see `EventMeta`.
+    pub fn is_replayed(&self) -> bool {
+        self.meta.as_ref().is_some_and(|meta| meta.replayed)
+    }
+
+    /// The event as a JSON object, as logged by the logger plugin and posted by the webhook
+    /// plugin: its type, timestamp, envelope ids (if it came in an envelope), payload size, the
+    /// uuid of its image (if any) and the fields of its payload worth reading, but never the
+    /// bytes of an image.
+    pub fn to_json(&self) -> Value {
+        let mut entry = Map::new();
+        entry.insert("type".to_string(), json!(self.event_type));
+        let timestamp_ms = self.meta.as_ref().map_or_else(now_ms, |m| m.timestamp_ms);
+        entry.insert("timestamp_ms".to_string(), json!(timestamp_ms));
+        if let Some(meta) = &self.meta {
+            entry.insert("event_id".to_string(), json!(meta.event_id.to_string()));
+            let correlation_id = meta.correlation_id.to_string();
+            entry.insert("correlation_id".to_string(), json!(correlation_id));
+            if meta.replayed {
+                entry.insert("replayed".to_string(), json!(true));
+            }
+            if meta.via_bridge {
+                entry.insert("via_bridge".to_string(), json!(true));
+            }
+            if meta.origin_engine_id != 0 {
+                entry.insert("origin_engine_id".to_string(), json!(meta.origin_engine_id));
+            }
+        }
+        entry.insert("payload_size".to_string(), json!(self.payload.len()));
+        match self.decode() {
+            Ok(event) => {
+                if let Some(image_uuid) = event.image_uuid() {
+                    entry.insert("uuid".to_string(), json!(image_uuid));
+                }
+                entry.insert("fields".to_string(), event_fields(&event));
+            }
+            Err(e) => {
+                entry.insert("decode_error".to_string(), json!(e.to_string()));
+            }
+        }
+        Value::Object(entry)
+    }
+}
+
+// The backlog of a plugin reporting backpressure.
+struct BacklogState {
+    // the backlog over which the plugin reports backpressure
+    threshold: usize,
+    // messages taken off the sub socket that `next_event` has not returned yet
+    queue: VecDeque<Vec<zmq::Message>>,
+    // events `next_event` returned that the plugin is not done with
+    outstanding: usize,
+    // whether a BackpressureEvent was published, and no BackpressureRelievedEvent since
+    reported: bool,
+}
+
+// The timer of a plugin, ticking every `interval`.
+struct Timer {
+    interval: Duration,
+    // when the next tick is due
+    next_at: Instant,
+    // the ticks returned so far
+    seq: u64,
+}
+
+impl BacklogState {
+    fn backlog(&self) -> usize {
+        self.queue.len() + self.outstanding
+    }
+}
+
+impl PluginContext {
+    /// Wrap an already connected (and subscribed) pair of sockets of the plugin `plugin_id`.
+    pub fn new(plugin_id: i32, pub_socket: Socket, sub_socket: Socket) -> Self {
+        PluginContext {
+            plugin_id,
+            pub_socket,
+            sub_socket,
+            bldr: FlatBufferBuilder::new(),
+            answer_heartbeats: false,
+            published_seqs: HashMap::new(),
+            received_seqs: HashMap::new(),
+            dropped_events: 0,
+            gap_stats: HashMap::new(),
+            on_gap: None,
+            malformed_events: 0,
+            publish_dropped_events: false,
+            detect_gaps: true,
+            subscriptions: HashSet::new(),
+            exclusions: Vec::new(),
+            backpressure: None,
+            backpressured: None,
+            max_payload_size: DEFAULT_MAX_PAYLOAD_SIZE,
+            compression: None,
+            codec: Codec::Flatbuffers,
+            engine_id: 0,
+            stopping: None,
+            stop_seen: false,
+            acks: None,
+            pause: None,
+            held: VecDeque::new(),
+            settings: PluginSettings::new(),
+            timer: None,
+            #[cfg(feature = "prometheus")]
+            counters: None,
+        }
+    }
+
+    // Return `PluginError::Stopped` from `next_event` once `stopping` is set and the sub socket
+    // times out.
+    pub(crate) fn with_stopping(mut self, stopping: Arc<AtomicBool>) -> Self {
+        self.stopping = Some(stopping);
+        self
+    }
+
+    // Do not look for gaps in the sequence numbers of the events received.
+    pub(crate) fn without_gap_detection(mut self) -> Self {
+        self.detect_gaps = false;
+        self
+    }
+
+    // Record the subscriptions the sub socket was subscribed with, and skip the events of the
+    // types the exclusions among them (see `events::excluded_event_type`) exclude, which the sub
+    // socket cannot filter out.
+    pub(crate) fn with_subscriptions(mut self, subscriptions: &[&str]) -> Self {
+        self.subscriptions = subscribed(subscriptions)
+            .into_iter()
+            .map(str::to_string)
+            .collect();
+        self.exclusions = subscriptions
+            .iter()
+            .filter_map(|subscription| excluded_event_type(subscription))
+            .map(str::to_string)
+            .collect();
+        self
+    }
+
+    // Acknowledge the events delivered at least once on `acks`, and receive them again on it.
+    pub(crate) fn with_acks(mut self, acks: Option<AckChannel>) -> Self {
+        self.acks = acks;
+        self
+    }
+
+    // Hold or drop the events received while `pause` says the plugin is paused, as `policy`
+    // says.
+    pub(crate) fn with_pause(mut self, pause: Arc<PauseState>, policy: PausePolicy) -> Self {
+        self.pause = Some((pause, policy));
+        self
+    }
+
+    // Count the events published and received with this context on `counters`.
+    #[cfg(feature = "prometheus")]
+    pub(crate) fn with_counters(
+        mut self,
+        counters: Option<std::sync::Arc<crate::prometheus::PluginCounters>>,
+    ) -> Self {
+        self.counters = counters;
+        self
+    }
+
+    /// Answer the engine heartbeats from now on: `next_event` no longer returns the
+    /// EngineHeartbeatEvents, it publishes a PluginHeartbeatEvent with the same seq for each
+    /// instead. A plugin that stops calling `next_event`, e.g., because it is stuck handling an
+    /// event, stops answering, which `EngineHandle::plugin_liveness` reports.
+    pub fn answer_heartbeats(&mut self) -> Result<(), PluginError> {
+        let filter_bytes =
+            get_event_type_bytes_filter("EngineHeartbeatEvent").map_err(PluginError::Other)?;
+        self.sub_socket.set_subscribe(&filter_bytes)?;
+        self.answer_heartbeats = true;
+        Ok(())
+    }
+
+    /// Subscribe to the events of `event_type`, a known event type or a wildcard such as
+    /// `"Image*"`, on top of the plugin's subscriptions; subscribing again to one the plugin is
+    /// subscribed to does nothing. The subscription has to reach the engine before it sends the
+    /// plugin the events, which takes a moment: events the engine publishes right after
+    /// `subscribe` returns may not be received. Exclusions among the plugin's subscriptions still
+    /// apply.
+    pub fn subscribe(&mut self, event_type: &str) -> Result<(), PluginError> {
+        let filter_bytes = subscription_filter(event_type)?;
+        if self.subscriptions.insert(event_type.to_string()) {
+            self.sub_socket.set_subscribe(&filter_bytes)?;
+        }
+        Ok(())
+    }
+
+    /// Stop receiving the events of `event_type`, a subscription of the plugin or one made with
+    /// `subscribe`, until subscribing to it again; unsubscribing from one the plugin is not
+    /// subscribed to does nothing. Only the subscription itself goes: unsubscribing from
+    /// `"NewImageEvent"` leaves a `"*"` subscription getting the NewImageEvents. The events of the
+    /// type already on their way are dropped too, but the engine learns of the change a moment
+    /// later, and keeps sending them until then. The PluginTerminateEvent cannot be unsubscribed
+    /// from.
+    pub fn unsubscribe(&mut self, event_type: &str) -> Result<(), PluginError> {
+        if event_type == "PluginTerminateEvent" {
+            return Err(PluginError::Other(
+                "cannot unsubscribe from the PluginTerminateEvent".to_string(),
+            ));
+        }
+        let filter_bytes = subscription_filter(event_type)?;
+        if self.subscriptions.remove(event_type) {
+            self.sub_socket.set_unsubscribe(&filter_bytes)?;
+        }
+        Ok(())
+    }
+
+    /// Report backpressure from now on: once the plugin's backlog (see `backlog`) goes over
+    /// `threshold`, `next_event` publishes a BackpressureEvent, and once it is down to half of
+    /// `threshold` again, a BackpressureRelievedEvent, for the plugins producing the events to
+    /// pause in between (see `pause_on_backpressure`). The plugin calls `done` for every event
+    /// it has handled. To see the backlog, and keep it from being dropped past the high-water
+    /// mark of the sub socket, `next_event` takes every message waiting on the sub socket off it
+    /// and queues them; producers only hear of the backpressure after a while, so the socket
+    /// still has to hold the events they publish until then.
+    pub fn report_backpressure(&mut self, threshold: usize) {
+        self.backpressure = Some(BacklogState {
+            threshold,
+            queue: VecDeque::new(),
+            outstanding: 0,
+            reported: false,
+        });
+    }
+
+    /// Tell the context the plugin has handled one more of the events `next_event` returned, for
+    /// the backlog of a plugin reporting backpressure; publishes the BackpressureRelievedEvent
+    /// if the backlog is down far enough. Does nothing for other plugins.
+    pub fn done(&mut self) -> Result<(), PluginError> {
+        if let Some(backpressure) = &mut self.backpressure {
+            backpressure.outstanding = backpressure.outstanding.saturating_sub(1);
+        }
+        self.report_backlog()
+    }
+
+    /// How many events the plugin received and has not handled yet: those `next_event` returned
+    /// and the plugin did not call `done` for, and those `next_event` took off the sub socket and
+    /// has not returned yet. Zero unless the plugin reports backpressure.
+    pub fn backlog(&self) -> usize {
+        self.backpressure
+            .as_ref()
+            .map_or(0, |backpressure| backpressure.backlog())
+    }
+
+    /// Pause on backpressure from now on: subscribe to the BackpressureEvents and
+    /// BackpressureRelievedEvents (see `report_backpressure`), if the plugin's subscriptions do
+    /// not have them already, and track which plugins report backpressure, for
+    /// `is_backpressured` and `wait_for_relief`. The events are still returned by `next_event`.
+    pub fn pause_on_backpressure(&mut self) -> Result<(), PluginError> {
+        for event_type in BACKPRESSURE_EVENTS {
+            self.subscribe(event_type)?;
+        }
+        self.backpressured.get_or_insert_with(HashSet::new);
+        Ok(())
+    }
+
+    /// Whether a plugin reported backpressure, and no relief since, among the events
+    /// `next_event` returned; always false unless the plugin pauses on backpressure.
+    pub fn is_backpressured(&self) -> bool {
+        self.backpressured
+            .as_ref()
+            .is_some_and(|backpressured| !backpressured.is_empty())
+    }
+
+    /// Take the events waiting on the sub socket, and then wait for the events that arrive
+    /// until no plugin reports backpressure; a producer calls it before publishing. The events
+    /// are skipped, and returns false for a PluginTerminateEvent, which ends the wait.
+    pub fn wait_for_relief(&mut self) -> Result<bool, PluginError> {
+        loop {
+            let queued = self
+                .backpressure
+                .as_ref()
+                .is_some_and(|backpressure| !backpressure.queue.is_empty());
+            if !queued && !self.is_backpressured() && self.sub_socket.poll(zmq::POLLIN, 0)? == 0 {
+                return Ok(true);
+            }
+            if self.next_event()?.event_type == "PluginTerminateEvent" {
+                return Ok(false);
+            }
+        }
+    }
+
+    /// How many events from plugins publishing with `publish` this context did not receive, e.g.,
+    /// because a socket on their way was past its high-water mark (see `Hwm`). `next_event`
+    /// counts them from the gaps in the sequence numbers of each publisher's events of each
+    /// type, so events dropped after the last one received are only counted once the publisher
+    /// publishes another event of the type.
+    pub fn dropped_events(&self) -> u64 {
+        self.dropped_events
+    }
+
+    /// The gaps `next_event` detected in the sequence numbers of each publisher's events since
+    /// the context was made, by plugin id; see `dropped_events`. Only publishers whose events
+    /// were received are listed.
+    pub fn gap_stats(&self) -> &HashMap<i32, GapStats> {
+        &self.gap_stats
+    }
+
+    /// Call `callback` with every gap `next_event` detects from now on, e.g., to log or count it
+    /// elsewhere; it runs on the plugin's thread before `next_event` returns.
+    pub fn on_gap(&mut self, callback: impl FnMut(&SeqGap) + Send + 'static) {
+        self.on_gap = Some(Box::new(callback));
+    }
+
+    /// How many events `next_event` skipped, with a warning, because their payload was not a
+    /// valid event of the type their header announced, or because their message could not be
+    /// taken apart into a header of a known type, an envelope and a payload, e.g., as sent by a
+    /// faulty external publisher.
+    pub fn malformed_events(&self) -> u64 {
+        self.malformed_events
+    }
+
+    /// Publish a DeadLetterEvent for an event the plugin failed to process because of `reason`,
+    /// instead of failing: the event of type `event_type` with envelope `meta` and `payload`, as
+    /// received, for a `DeadLetterPlugin` to keep for inspecting or replaying it. The dead letter
+    /// is a reply to the event if it had an envelope, and an event delivered at least once is
+    /// acknowledged (see `ack`). `next_event` dead-letters the malformed events it skips itself.
+    pub fn dead_letter(
+        &mut self,
+        event_type: &str,
+        meta: Option<&EventMeta>,
+        payload: &[u8],
+        reason: &str,
+    ) -> Result<EventMeta, PluginError> {
+        EventSink::dead_letter(self, event_type, meta, payload, reason)
+    }
+
+    /// Whether the events of type `event_type` are delivered at least once to the plugin, which
+    /// then acknowledges each one with `ack`; see the `acks` module.
+    pub fn acknowledges(&self, event_type: &str) -> bool {
+        self.acks
+            .as_ref()
+            .is_some_and(|acks| acks.delivers(event_type))
+    }
+
+    /// Tell the engine that the plugin is done with the event of type `event_type` with
+    /// envelope `meta`, so that it is not delivered again. Does nothing for the events that are
+    /// not delivered at least once (see `acknowledges`), so a plugin may acknowledge every event
+    /// it handles. `next_event` skips the events acknowledged lately that are delivered again.
+    pub fn ack(&mut self, event_type: &str, meta: Option<&EventMeta>) -> Result<(), PluginError> {
+        let Some(acks) = self.acks.as_mut().filter(|acks| acks.delivers(event_type)) else {
+            return Ok(());
+        };
+        if let Some(meta) = meta {
+            acks.ack(meta)?;
+        }
+        Ok(())
+    }
+
+    /// The event of `msg` decoded, or None once it is dead-lettered (see `dead_letter`) because
+    /// it cannot be, e.g., for a missing field.
+    pub fn decode_or_dead_letter(&mut self, msg: &EventMsg) -> Result<Option<Event>, PluginError> {
+        EventSink::decode_or_dead_letter(self, msg)
+    }
+
+    /// Publish an EventsDroppedEvent from now on whenever `next_event` detects dropped events.
+    pub fn publish_dropped_events(&mut self) {
+        self.publish_dropped_events = true;
+    }
+
+    /// Refuse to publish events bigger than `max_bytes` once serialized, as the engine does not
+    /// forward them; zero publishes events of any size. Defaults to 64 MiB, and an engine sets
+    /// the contexts of its plugins to its `EventEngineBuilder::max_payload_size`.
+    pub fn set_max_payload_size(&mut self, max_bytes: usize) {
+        self.max_payload_size = max_bytes;
+    }
+
+    /// Compress the payloads of the events published from now on as `compression` says, or
+    /// none of them for None; see the `compression` module. An engine sets the contexts of its
+    /// plugins to its `EventEngineBuilder::compression`. Compressed or not, `next_event`
+    /// decompresses what it receives.
+    pub fn set_compression(&mut self, compression: Option<Compression>) {
+        self.compression = compression;
+    }
+
+    /// Serialize the events published from now on with `codec`, e.g., as JSON for external
+    /// plugins without flatbuffers; the envelope of each records its codec. An engine sets the
+    /// contexts of its plugins to its `EventEngineBuilder::codec`. Whatever the codec,
+    /// `next_event` returns flatbuffers.
+    pub fn set_codec(&mut self, codec: Codec) {
+        self.codec = codec;
+    }
+
+    /// Record `engine_id` as the `origin_engine_id` of the events published from now on, for
+    /// engines bridged together to tell them apart. An engine sets the contexts of its plugins
+    /// to its `EventEngineBuilder::engine_id`; it is 0 otherwise.
+    pub fn set_engine_id(&mut self, engine_id: u32) {
+        self.engine_id = engine_id;
+    }
+
+    /// Give the plugin `settings`, returned by `settings`. An engine sets the contexts of its
+    /// plugins to the settings they were registered with (see `PluginRegistry::settings`).
+    pub fn set_settings(&mut self, settings: PluginSettings) {
+        self.settings = settings;
+    }
+
+    /// The configuration values of the plugin; none unless set with `set_settings`.
+    pub fn settings(&self) -> &PluginSettings {
+        &self.settings
+    }
+
+    /// Have `next_event` return a TickEvent (see `events::Tick`) every `interval` from now on,
+    /// even if no event arrives, for the plugin to do periodic work between the events it
+    /// receives; a zero `interval` stops the timer. `next_event` waits on the sub socket until
+    /// the next tick is due, rather than another thread waking it. The ticks a busy plugin
+    /// missed are skipped, not returned one after the other, and those due while the engine has
+    /// the plugin paused too. A plugin has one timer: setting it again replaces it.
+    pub fn set_timer(&mut self, interval: Duration) {
+        self.timer = (!interval.is_zero()).then(|| Timer {
+            interval,
+            next_at: Instant::now() + interval,
+            seq: 0,
+        });
+    }
+
+    /// Serialize `event` and publish it on the pub socket, in an envelope recording the plugin
+    /// and the time it was published. Returns the envelope, or `EventError::TooLarge` without
+    /// publishing anything if the event is over the maximum payload size.
+    pub fn publish(&mut self, event: &impl EventPayload) -> Result<EventMeta, PluginError> {
+        self.publish_with_meta(EventMeta::new(self.plugin_id), event)
+    }
+
+    /// Like `publish`, for an event published in response to the event with envelope `to`: the
+    /// envelope of `event` keeps the correlation id of `to`.
+    pub fn publish_reply(
+        &mut self,
+        to: &EventMeta,
+        event: &impl EventPayload,
+    ) -> Result<EventMeta, PluginError> {
+        self.publish_with_meta(EventMeta::reply(to, self.plugin_id), event)
+    }
+
+    fn publish_with_meta(
+        &mut self,
+        mut meta: EventMeta,
+        event: &impl EventPayload,
+    ) -> Result<EventMeta, PluginError> {
+        let event_type = event.event_type();
+        // subscribers filter on the header frame, so an event of an unknown type would never
+        // be delivered
+        get_event_type_bytes_filter(event_type).map_err(PluginError::Other)?;
+        let mut data = event.build(&mut self.bldr)?;
+        // events are always built as flatbuffers; the JSON is made from the decoded flatbuffer
+        let json = match self.codec {
+            Codec::Json => Some(Event::decode_as(event_type, data)?.to_json()),
+            Codec::Flatbuffers => None,
+        };
+        if let Some(json) = &json {
+            data = json;
+        }
+        meta.codec = self.codec;
+        meta.origin_engine_id = self.engine_id;
+        if self.max_payload_size > 0 && data.len() > self.max_payload_size {
+            return Err(EventError::TooLarge {
+                size: data.len(),
+                limit: self.max_payload_size,
+            }
+            .into());
+        }
+        let seq = self.published_seqs.entry(event_type).or_default();
+        *seq += 1;
+        meta.seq = *seq;
+        // recorded first, so that the chain has its root span by the time the event is received
+        let compressed = self.compression.and_then(|c| c.compress(data));
+        meta.compressed = compressed.is_some();
+        #[cfg(feature = "tracing")]
+        crate::spans::published(event_type, &meta, self.plugin_id);
+        let payload = compressed.as_deref().unwrap_or(data);
+        send_event_msg_with_meta(&self.pub_socket, event_type, &meta, payload)?;
+        #[cfg(feature = "prometheus")]
+        if let Some(counters) = &self.counters {
+            counters.published.fetch_add(1, Ordering::Relaxed);
+        }
+        Ok(meta)
+    }
+
+    /// Publish an event received from another engine, e.g., by a `BridgePlugin`, as it was
+    /// serialized: the `payload` is sent untouched, compressed or JSON as `meta` says, and the
+    /// envelope keeps the ids, timestamps and origin engine id of `meta`, but is `via_bridge`
+    /// and records the plugin as its source, numbered as the events the plugin publishes.
+    /// Returns the envelope, or `EventError::TooLarge` without publishing anything if the
+    /// payload is over the maximum payload size.
+    pub fn publish_bridged(
+        &mut self,
+        event_type: &'static str,
+        meta: &EventMeta,
+        payload: &[u8],
+    ) -> Result<EventMeta, PluginError> {
+        if self.max_payload_size > 0 && payload.len() > self.max_payload_size {
+            return Err(EventError::TooLarge {
+                size: payload.len(),
+                limit: self.max_payload_size,
+            }
+            .into());
+        }
+        let seq = self.published_seqs.entry(event_type).or_default();
+        *seq += 1;
+        let meta = EventMeta {
+            source_plugin_id: self.plugin_id,
+            seq: *seq,
+            via_bridge: true,
+            ..*meta
+        };
+        send_event_msg_with_meta(&self.pub_socket, event_type, &meta, payload)?;
+        #[cfg(feature = "prometheus")]
+        if let Some(counters) = &self.counters {
+            counters.published.fetch_add(1, Ordering::Relaxed);
+        }
+        Ok(meta)
+    }
+
+    /// Block until the next event arrives on the sub socket. A plugin started by the engine gets
+    /// `PluginError::Stopped` instead once the engine is shutting down and no event is left.
+    /// Events whose payload the flatbuffers verifier rejects are skipped (see
+    /// `malformed_events`) and dead-lettered (see `dead_letter`), so the `event()` and
+    /// `decode()` of those returned only fail for a missing field. JSON payloads are turned into
+    /// flatbuffers first, and skipped likewise if they do not hold an event of the announced
+    /// type, as are the events of a newer schema version (see `SCHEMA_VERSION`). Events
+    /// delivered at least once (see `acks`) also come when the engine sends them again. While
+    /// the engine has the plugin paused (see `EngineHandle::pause_plugin`), only the
+    /// PluginTerminateEvent comes; the events held meanwhile come first once it resumes it.
+    /// With a timer set (see `set_timer`), a TickEvent comes whenever a tick is due.
+    pub fn next_event(&mut self) -> Result<EventMsg, PluginError> {
+        loop {
+            if let Some(msg) = self.event_until(None)? {
+                return Ok(msg);
+            }
+        }
+    }
+
+    /// Like `next_event`, but Ok(None) once `timeout` passed without an event, for plugins that
+    /// also wait on something else, e.g., a broker. The PluginTerminateEvent, ticks, pauses and
+    /// `PluginError::Stopped` come as they do from `next_event`.
+    pub fn wait_for_event(&mut self, timeout: Duration) -> Result<Option<EventMsg>, PluginError> {
+        self.event_until(Some(Instant::now() + timeout))
+    }
+
+    // The next event, or None once `deadline`, if any, passed without one.
+    fn event_until(&mut self, deadline: Option<Instant>) -> Result<Option<EventMsg>, PluginError> {
+        loop {
+            if let Some(msg) = self.resumed() {
+                self.handed_out(&msg)?;
+                return Ok(Some(msg));
+            }
+            if let Some(msg) = self.tick()? {
+                self.handed_out(&msg)?;
+                return Ok(Some(msg));
+            }
+            let frames = match self.frames_until(deadline) {
+                Ok(frames) => frames,
+                Err(zmq::Error::EAGAIN)
+                    if self.stopping.is_some() || self.tick_due() || deadline.is_some() =>
+                {
+                    if self.is_stopping() && !self.terminate_may_come()? {
+                        return Err(PluginError::Stopped);
+                    }
+                    if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                        return Ok(None);
+                    }
+                    continue;
+                }
+                Err(e) => return Err(e.into()),
+            };
+            let msg = self.received(frames)?;
+            if let Some(msg) = msg.and_then(|msg| self.unless_paused(msg)) {
+                self.handed_out(&msg)?;
+                return Ok(Some(msg));
+            }
+        }
+    }
+
+    // Like `next_event`, without waiting: None once no message is left on the sub socket.
+    pub(crate) fn try_next_event(&mut self) -> Result<Option<EventMsg>, PluginError> {
+        loop {
+            if let Some(msg) = self.resumed() {
+                self.handed_out(&msg)?;
+                return Ok(Some(msg));
+            }
+            if let Some(msg) = self.tick()? {
+                self.handed_out(&msg)?;
+                return Ok(Some(msg));
+            }
+            let frames = match self.next_frames(zmq::DONTWAIT) {
+                Ok(frames) => frames,
+                Err(zmq::Error::EAGAIN) => return Ok(None),
+                Err(e) => return Err(e.into()),
+            };
+            let msg = self.received(frames)?;
+            if let Some(msg) = msg.and_then(|msg| self.unless_paused(msg)) {
+                self.handed_out(&msg)?;
+                return Ok(Some(msg));
+            }
+        }
+    }
+
+    // The first of the events held while the plugin was paused, once it no longer is.
+    fn resumed(&mut self) -> Option<EventMsg> {
+        match &self.pause {
+            Some((pause, _)) if !pause.is_paused() => self.held.pop_front(),
+            _ => None,
+        }
+    }
+
+    // The TickEvent to return if the next tick of the timer is due, which is then scheduled an
+    // interval later, or an interval from now if that is past already; None if the plugin is
+    // paused.
+    fn tick(&mut self) -> Result<Option<EventMsg>, PluginError> {
+        let Some(timer) = self.timer.as_mut() else {
+            return Ok(None);
+        };
+        let now = Instant::now();
+        if now < timer.next_at {
+            return Ok(None);
+        }
+        let late = now - timer.next_at;
+        timer.next_at += timer.interval;
+        if timer.next_at <= now {
+            timer.next_at = now + timer.interval;
+        }
+        if matches!(&self.pause, Some((pause, _)) if pause.is_paused()) {
+            return Ok(None);
+        }
+        timer.seq += 1;
+        let tick = Tick {
+            seq: timer.seq,
+            scheduled_at_ms: now_ms().saturating_sub(late.as_millis() as u64),
+        };
+        Ok(Some(EventMsg {
+            event_type: tick.event_type().to_string(),
+            meta: None,
+            payload: tick.build(&mut self.bldr)?.into(),
+        }))
+    }
+
+    fn tick_due(&self) -> bool {
+        self.timer
+            .as_ref()
+            .is_some_and(|timer| Instant::now() >= timer.next_at)
+    }
+
+    // Like `next_frames(0)`, but EAGAIN once the next tick of the timer, if set, is due, or at
+    // `deadline`, if the sub socket does not time out first.
+    fn frames_until(&mut self, deadline: Option<Instant>) -> zmq::Result<Vec<zmq::Message>> {
+        let tick_at = self.timer.as_ref().map(|timer| timer.next_at);
+        let Some(wake_at) = tick_at.into_iter().chain(deadline).min() else {
+            return self.next_frames(0);
+        };
+        loop {
+            let queued = self
+                .backpressure
+                .as_ref()
+                .is_some_and(|backpressure| !backpressure.queue.is_empty());
+            if !queued {
+                // rounded up, not to wake just before the tick is due
+                let until_wake = wake_at.saturating_duration_since(Instant::now());
+                let mut timeout = until_wake.as_micros().div_ceil(1000) as i64;
+                let rcvtimeo = i64::from(self.sub_socket.get_rcvtimeo()?);
+                if rcvtimeo >= 0 {
+                    timeout = timeout.min(rcvtimeo);
+                }
+                let mut items = vec![self.sub_socket.as_poll_item(zmq::POLLIN)];
+                if let Some(acks) = &self.acks {
+                    items.push(acks.socket.as_poll_item(zmq::POLLIN));
+                }
+                if retry_on_eintr(|| zmq::poll(&mut items, timeout))? == 0 {
+                    return Err(zmq::Error::EAGAIN);
+                }
+            }
+            match self.next_frames(zmq::DONTWAIT) {
+                Err(zmq::Error::EAGAIN) => continue,
+                received => return received,
+            }
+        }
+    }
+
+    // The event `msg` to return, unless the plugin is paused: then it is held or dropped, as the
+    // pause policy says, except for the PluginTerminateEvent. Once the plugin is resumed, it is
+    // held behind those still held.
+    fn unless_paused(&mut self, msg: EventMsg) -> Option<EventMsg> {
+        let Some((pause, policy)) = &self.pause else {
+            return Some(msg);
+        };
+        if !pause.is_paused() {
+            if self.held.is_empty() {
+                return Some(msg);
+            }
+            self.held.push_back(msg);
+            return None;
+        }
+        if msg.event_type == "PluginTerminateEvent" {
+            return Some(msg);
+        }
+        match policy {
+            PausePolicy::Buffer(capacity) if self.held.len() < *capacity => {
+                self.held.push_back(msg)
+            }
+            _ => {
+                let dropped = pause.dropped.fetch_add(1, Ordering::Relaxed) + 1;
+                debug!(
+                    plugin_id = self.plugin_id;
+                    "plugin {} is paused, dropped a {} ({} dropped so far)",
+                    self.plugin_id, msg.event_type, dropped
+                );
+            }
+        }
+        None
+    }
+
+    // The frames of the next message received with `flags`; for a plugin reporting
+    // backpressure, the first of those it queued, after queueing all those waiting.
+    fn next_frames(&mut self, flags: i32) -> zmq::Result<Vec<zmq::Message>> {
+        if let Some(backpressure) = &mut self.backpressure {
+            loop {
+                match recv_frames(&self.sub_socket, self.acks.as_ref(), zmq::DONTWAIT) {
+                    Ok(frames) => backpressure.queue.push_back(frames),
+                    Err(zmq::Error::EAGAIN) => break,
+                    Err(e) => return Err(e),
+                }
+            }
+            if let Some(frames) = backpressure.queue.pop_front() {
+                return Ok(frames);
+            }
+        }
+        recv_frames(&self.sub_socket, self.acks.as_ref(), flags)
+    }
+
+    // Account for the event `msg` that `next_event` returns: in the backlog, and, for a
+    // BackpressureEvent or BackpressureRelievedEvent, in the plugins reporting backpressure.
+    fn handed_out(&mut self, msg: &EventMsg) -> Result<(), PluginError> {
+        if let Some(backpressure) = &mut self.backpressure {
+            backpressure.outstanding += 1;
+        }
+        let is_backpressure = BACKPRESSURE_EVENTS.contains(&msg.event_type.as_str());
+        if let Some(backpressured) = self.backpressured.as_mut().filter(|_| is_backpressure) {
+            let was_backpressured = !backpressured.is_empty();
+            match msg.decode()? {
+                Event::Backpressure(e) => backpressured.insert(e.plugin_id),
+                Event::BackpressureRelieved(e) => backpressured.remove(&e.plugin_id),
+                _ => false,
+            };
+            match (was_backpressured, backpressured.is_empty()) {
+                (false, false) => info!(
+                    plugin_id = self.plugin_id;
+                    "plugin {} pauses: plugin(s) {:?} report backpressure",
+                    self.plugin_id, backpressured
+                ),
+                (true, true) => info!(
+                    plugin_id = self.plugin_id;
+                    "plugin {} resumes: no plugin reports backpressure",
+                    self.plugin_id
+                ),
+                _ => {}
+            }
+        }
+        self.report_backlog()
+    }
+
+    // Publish a BackpressureEvent if the backlog went over the threshold, or a
+    // BackpressureRelievedEvent if it went back down to half of it since.
+    fn report_backlog(&mut self) -> Result<(), PluginError> {
+        let (backlog, relieved) = match &mut self.backpressure {
+            Some(b) if !b.reported && b.backlog() > b.threshold => {
+                b.reported = true;
+                (b.backlog() as u64, false)
+            }
+            Some(b) if b.reported && b.backlog() <= b.threshold / 2 => {
+                b.reported = false;
+                (b.backlog() as u64, true)
+            }
+            _ => return Ok(()),
+        };
+        let plugin_id = self.plugin_id;
+        if relieved {
+            self.publish(&BackpressureRelieved { plugin_id, backlog })?;
+        } else {
+            self.publish(&Backpressure { plugin_id, backlog })?;
+        }
+        Ok(())
+    }
+
+    // The event to return for the message `frames` received on the sub socket, or None if it is
+    // skipped (as malformed, excluded or a sync probe) or answered (as an engine heartbeat).
+    fn received(&mut self, frames: Vec<zmq::Message>) -> Result<Option<EventMsg>, PluginError> {
+        let frame_count = frames.len();
+        // a message that cannot even be taken apart has no event type to dead-letter it as
+        let Some((event_type, mut meta, payload)) = parse_event_messages(frames) else {
+            self.malformed_events += 1;
+            warn!(
+                plugin_id = self.plugin_id;
+                "plugin {} skipped a message of {} frame(s) that is not an event of a known type \
+                 ({} skipped so far)",
+                self.plugin_id, frame_count, self.malformed_events
+            );
+            return Ok(None);
+        };
+        let excluded = |pattern: &String| event_type_matches(pattern, event_type);
+        if event_type == "SyncProbeEvent" {
+            return Ok(None);
+        }
+        if event_type != "PluginTerminateEvent" && self.exclusions.iter().any(excluded) {
+            return Ok(None);
+        }
+        // an event delivered again once acknowledged is not handled twice
+        let delivered = self.acks.as_ref().filter(|acks| acks.delivers(event_type));
+        if let (Some(acks), Some(meta)) = (delivered, &meta) {
+            if acks.is_acknowledged(&meta.event_id) {
+                debug!(
+                    plugin_id = self.plugin_id, event_type;
+                    "plugin {} skipped a {} it already acknowledged",
+                    self.plugin_id, event_type
+                );
+                return Ok(None);
+            }
+        }
+        // those sent again are out of sequence, and those missed are sent again
+        let detect_gaps = self.detect_gaps && delivered.is_none();
+        let payload = decompressed(&mut meta, payload, self.max_payload_size)?;
+        if let Some(meta) = meta.as_ref().filter(|_| detect_gaps) {
+            self.check_seq(event_type, meta)?;
+        }
+        // the payload is borrowed until it is verified, for a malformed one to be dead-lettered
+        let original_meta = meta;
+        let verified = check_schema_version(meta.as_ref())
+            .and_then(|()| {
+                let payload = Cow::Borrowed(&*payload);
+                flatbuffers_payload(event_type, &mut meta, payload, &mut self.bldr)
+            })
+            .and_then(|converted| {
+                verify_event(event_type, &converted)?;
+                Ok(match converted {
+                    Cow::Owned(converted) => Some(converted),
+                    Cow::Borrowed(_) => None,
+                })
+            });
+        let payload = match verified {
+            Ok(Some(converted)) => converted.into(),
+            Ok(None) => payload,
+            Err(e) => {
+                self.malformed_events += 1;
+                warn!(
+                    plugin_id = self.plugin_id, event_type;
+                    "plugin {} skipped an event: {} ({} skipped so far)",
+                    self.plugin_id, e, self.malformed_events
+                );
+                let reason = e.to_string();
+                self.dead_letter(event_type, original_meta.as_ref(), &payload, &reason)?;
+                return Ok(None);
+            }
+        };
+        let msg = EventMsg {
+            event_type: event_type.to_string(),
+            meta,
+            payload,
+        };
+        if !self.answer_heartbeats || msg.event_type != "EngineHeartbeatEvent" {
+            #[cfg(feature = "tracing")]
+            if let Some(meta) = &msg.meta {
+                crate::spans::received(&msg.event_type, meta, self.plugin_id);
+            }
+            #[cfg(feature = "prometheus")]
+            if let Some(counters) = &self.counters {
+                counters.received.fetch_add(1, Ordering::Relaxed);
+            }
+            return Ok(Some(msg));
+        }
+        if let Event::EngineHeartbeat(heartbeat) = msg.decode()? {
+            let answer = PluginHeartbeat {
+                plugin_id: self.plugin_id,
+                seq: heartbeat.seq,
+            };
+            self.publish(&answer)?;
+        }
+        Ok(None)
+    }
+
+    // Whether a message arrives within a receive timeout, the first time the engine is found
+    // stopping: the PluginTerminateEvent is published before `stopping` is set, so unless it
+    // was lost it comes within one more receive, even to a plugin whose receive timed out just
+    // as the engine stopped.
+    fn terminate_may_come(&mut self) -> Result<bool, PluginError> {
+        if self.stop_seen {
+            return Ok(false);
+        }
+        self.stop_seen = true;
+        let timeout = match self.sub_socket.get_rcvtimeo()? {
+            timeout if timeout < 0 => DEFAULT_PLUGIN_RECV_TIMEOUT.as_millis() as i64,
+            timeout => i64::from(timeout),
+        };
+        Ok(retry_on_eintr(|| self.sub_socket.poll(zmq::POLLIN, timeout))? > 0)
+    }
+
+    pub(crate) fn is_stopping(&self) -> bool {
+        self.stopping
+            .as_ref()
+            .is_some_and(|stopping| stopping.load(Ordering::SeqCst))
+    }
+
+    // Count the events missed between the last event of type `event_type` received from the
+    // publisher of the one with envelope `meta` and that one.
+    fn check_seq(&mut self, event_type: &'static str, meta: &EventMeta) -> Result<(), PluginError> {
+        // unnumbered events cannot be checked
+        if meta.seq == 0 {
+            return Ok(());
+        }
+        let publisher_id = meta.source_plugin_id;
+        let last_seq = self
+            .received_seqs
+            .insert((publisher_id, event_type), meta.seq);
+        let stats = self.gap_stats.entry(publisher_id).or_default();
+        let gap = match last_seq {
+            Some(last_seq) if meta.seq > last_seq + 1 => SeqGap {
+                publisher_id,
+                event_type,
+                last_seq,
+                seq: meta.seq,
+            },
+            // a publisher that starts over, e.g., once restarted, numbers its events from 1 again
+            Some(last_seq) if meta.seq <= last_seq => {
+                stats.resets += 1;
+                return Ok(());
+            }
+            _ => return Ok(()),
+        };
+        let missed = gap.missed();
+        stats.gaps += 1;
+        stats.missed += missed;
+        if let Some(on_gap) = &mut self.on_gap {
+            on_gap(&gap);
+        }
+        self.dropped_events += missed;
+        warn!(
+            plugin_id = self.plugin_id, event_type;
+            "plugin {} missed {} {}(s) from plugin {}",
+            self.plugin_id, missed, event_type, meta.source_plugin_id
+        );
+        #[cfg(feature = "prometheus")]
+        if let Some(counters) = &self.counters {
+            counters.dropped.fetch_add(missed, Ordering::Relaxed);
+        }
+        if self.publish_dropped_events {
+            let dropped = EventsDropped {
+                plugin_id: self.plugin_id,
+                missed,
+            };
+            self.publish(&dropped)?;
+        }
+        Ok(())
+    }
+}
+
+// The filter of the sub socket subscribing to `event_type`, a known event type or a wildcard,
+// computed as when the engine subscribes the plugin (see `event_engine::subscription_filter`).
+pub(crate) fn subscription_filter(event_type: &str) -> Result<Vec<u8>, PluginError> {
+    if let Some(prefix) = wildcard_prefix(event_type) {
+        return Ok(prefix.as_bytes().to_vec());
+    }
+    get_event_type_bytes_filter(event_type).map_err(|_| {
+        let suggestion = closest_event_type(event_type)
+            .map(|suggestion| format!(" (did you mean {}?)", suggestion))
+            .unwrap_or_default();
+        PluginError::Other(format!(
+            "unknown event type \"{}\"{}",
+            event_type, suggestion
+        ))
+    })
+}
+
+// The next message received with `flags` on the sub socket or, for a plugin with events
+// delivered at least once, on the back-channel the engine sends them again on; the sub socket
+// times out as it does on its own.
+fn recv_frames(
+    sub_socket: &Socket,
+    acks: Option<&AckChannel>,
+    flags: i32,
+) -> zmq::Result<Vec<zmq::Message>> {
+    let Some(acks) = acks else {
+        return recv_event_frames(sub_socket, flags);
+    };
+    let timeout = match flags & zmq::DONTWAIT {
+        0 => i64::from(sub_socket.get_rcvtimeo()?),
+        _ => 0,
+    };
+    loop {
+        for socket in [&acks.socket, sub_socket] {
+            match recv_event_frames(socket, zmq::DONTWAIT) {
+                Err(zmq::Error::EAGAIN) => {}
+                received => return received,
+            }
+        }
+        let mut items = [
+            acks.socket.as_poll_item(zmq::POLLIN),
+            sub_socket.as_poll_item(zmq::POLLIN),
+        ];
+        if retry_on_eintr(|| zmq::poll(&mut items, timeout))? == 0 {
+            return Err(zmq::Error::EAGAIN);
+        }
+    }
+}
+
+// The fields of the payload of an event worth reading, for `EventMsg::to_json`.
+pub(crate) fn event_fields(event: &Event) -> Value {
+    match event {
+        Event::NewImage(e) => json!({
+            "image_format": e.image_format,
+            "image_size": e.image.len(),
+            "location": e.location,
+        }),
+        Event::ImageScored(e) => {
+            let scores: Map<String, Value> = e
+                .scores
+                .iter()
+                .map(|score| (score.label.clone(), json!(score.probability)))
+                .collect();
+            json!({ "scores": scores })
+        }
+        Event::ImageStored(e) => json!({"path": e.path, "deduplicated": e.deduplicated}),
+        Event::ImageDeleted(e) => json!({"existed": e.existed}),
+        Event::PluginTerminate | Event::ImageDeletedRequest(_) => json!({}),
+        Event::PluginFailed(e) => json!({"plugin_id": e.plugin_id, "message": e.message}),
+        Event::PluginRestarted(e) => {
+            json!({"plugin_id": e.plugin_id, "restart_count": e.restart_count})
+        }
+        Event::PluginJoined(e) => json!({"plugin_id": e.plugin_id}),
+        Event::PluginLeft(e) => json!({"plugin_id": e.plugin_id, "reason": e.reason}),
+        Event::EngineHeartbeat(e) => json!({"seq": e.seq, "uptime_ms": e.uptime_ms}),
+        Event::PluginHeartbeat(e) => json!({"plugin_id": e.plugin_id, "seq": e.seq}),
+        Event::EventsDropped(e) => json!({"plugin_id": e.plugin_id, "missed": e.missed}),
+        Event::EventRejected(e) => json!({"reason": e.reason, "size": e.size}),
+        Event::Backpressure(e) => json!({"plugin_id": e.plugin_id, "backlog": e.backlog}),
+        Event::BackpressureRelieved(e) => {
+            json!({"plugin_id": e.plugin_id, "backlog": e.backlog})
+        }
+        Event::DeadLetter(e) => json!({
+            "plugin_id": e.plugin_id,
+            "event_type": e.event_type,
+            "payload_size": e.payload.len(),
+            "reason": e.reason,
+        }),
+        Event::SyncProbe(e) => json!({"seq": e.seq}),
+        Event::EngineStarted(e) => json!({
+            "engine_id": e.engine_id,
+            "protocol_version": e.protocol_version,
+            "started_at_ms": e.started_at_ms,
+        }),
+        Event::EngineStopping(e) => json!({"reason": e.reason}),
+        Event::PluginSkipped(e) => json!({"plugin_id": e.plugin_id, "reason": e.reason}),
+        Event::Tick(e) => json!({"seq": e.seq, "scheduled_at_ms": e.scheduled_at_ms}),
+        Event::ImageScoreFailed(e) => json!({"error": e.error}),
+        Event::ImageRejected(e) => json!({"top_label": e.top_label, "probability": e.probability}),
+        Event::ImageStoreFailed(e) => json!({"error": e.error}),
+        Event::MetricsSnapshot(e) => {
+            let counts: Map<String, Value> = e
+                .counts
+                .iter()
+                .map(|c| {
+                    (
+                        c.event_type.clone(),
+                        json!({"count": c.count, "bytes": c.bytes}),
+                    )
+                })
+                .collect();
+            let latencies: Map<String, Value> = e
+                .latencies
+                .iter()
+                .map(|l| {
+                    (
+                        l.event_type.clone(),
+                        json!({
+                            "count": l.count,
+                            "p50_us": l.p50_us,
+                            "p95_us": l.p95_us,
+                            "p99_us": l.p99_us,
+                            "max_us": l.max_us,
+                        }),
+                    )
+                })
+                .collect();
+            json!({ "counts": counts, "latencies": latencies })
+        }
+        Event::WebhookDeliveryFailed(e) => json!({
+            "event_type": e.event_type,
+            "url": e.url,
+            "status_code": e.status_code,
+            "error": e.error,
+        }),
+        // the payload of a custom event is only known to its publishers and subscribers
+        Event::Custom { payload, .. } => json!({"payload_size": payload.len()}),
+    }
+}
+
+/// Errors returned by a plugin's `start`.
+#[derive(Debug)]
+pub enum PluginError {
+    /// A socket operation failed.
+    Socket(zmq::Error),
+    /// An event could not be built, sent or read.
+    Io(std::io::Error),
+    /// A received message could not be decoded.
+    Event(EventError),
+    /// A setting of the plugin is missing or of the wrong type.
+    Settings(SettingsError),
+    /// Any other failure, described by the plugin.
+    Other(String),
+    /// The engine is shutting down; a plugin returning it from `start` has finished.
+    Stopped,
+}
+
+impl fmt::Display for PluginError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PluginError::Socket(source) => write!(f, "socket error: {}", source),
+            PluginError::Io(source) => write!(f, "{}", source),
+            PluginError::Event(source) => write!(f, "{}", source),
+            PluginError::Settings(source) => write!(f, "{}", source),
+            PluginError::Other(reason) => write!(f, "{}", reason),
+            PluginError::Stopped => write!(f, "the engine is shutting down"),
+        }
+    }
+}
+
+impl std::error::Error for PluginError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PluginError::Socket(source) => Some(source),
+            PluginError::Io(source) => Some(source),
+            PluginError::Event(source) => Some(source),
+            PluginError::Settings(source) => Some(source),
+            PluginError::Other(_) | PluginError::Stopped => None,
+        }
+    }
+}
+
+impl From<zmq::Error> for PluginError {
+    fn from(error: zmq::Error) -> Self {
+        PluginError::Socket(error)
+    }
+}
+
+impl From<EventError> for PluginError {
+    fn from(error: EventError) -> Self {
+        PluginError::Event(error)
+    }
+}
+
+impl From<std::io::Error> for PluginError {
+    fn from(error: std::io::Error) -> Self {
+        PluginError::Io(error)
+    }
+}
+
+impl From<SettingsError> for PluginError {
+    fn from(error: SettingsError) -> Self {
+        PluginError::Settings(error)
+    }
+}
+
+/// A plugin run by the engine in its own thread.
+pub trait Plugin: Send {
+    /// Unique id of the plugin; the plugin announces itself with it when syncing.
+    fn id(&self) -> i32;
+
+    /// Name of the plugin, used in log output and as the name of its thread.
+    fn name(&self) -> &str;
+
+    /// Event types (e.g., "NewImageEvent") the plugin subscribes to.
+    fn subscriptions(&self) -> &[&str];
+
+    /// Event types the plugin publishes, for the engine to warn about the subscriptions to
+    /// types only the plugins left out of its profile publish (see `PluginRegistry::profile`);
+    /// none by default.
+    fn publications(&self) -> &[&str] {
+        &[]
+    }
+
+    /// Apply the settings the plugin was registered with (see `PluginRegistry::settings`) that
+    /// its subscriptions depend on, e.g., the `storage_root` of the `ImageStorePlugin`; called
+    /// by the engine before it subscribes the plugin, for every instance of it. The other
+    /// settings are better read from `PluginContext::settings` once the plugin starts. Does
+    /// nothing by default.
+    fn configure(&mut self, _settings: &PluginSettings) -> Result<(), SettingsError> {
+        Ok(())
+    }
+
+    /// Run the plugin; called once, in the plugin's thread, after the plugin has synced with
+    /// the engine. Plugins should return when they receive a `PluginTerminateEvent`.
+    fn start(self: Box<Self>, ctx: PluginContext) -> Result<(), PluginError>;
+}
+
+/// Where a plugin's events come from: the `PluginContext` the engine hands it, or, for running
+/// the plugin's logic in a test without sockets or an engine, a `MemoryContext`. A plugin whose
+/// `start` hands its context to a function taking an `EventSource + EventSink`, as the
+/// `ImageScorePlugin` and `ImageStorePlugin` do, can be tested by calling that function.
+pub trait EventSource {
+    /// The next event the plugin receives; see `PluginContext::next_event`.
+    fn next_event(&mut self) -> Result<EventMsg, PluginError>;
+
+    /// Tell that the plugin is done with the event received last; see `PluginContext::done`.
+    fn done(&mut self) -> Result<(), PluginError>;
+}
+
+/// Where a plugin's events go, along with the acknowledgements of those it received; see
+/// `EventSource`.
+pub trait EventSink {
+    /// The id of the plugin, recorded as the source of the events it publishes.
+    fn plugin_id(&self) -> i32;
+
+    /// Publish `event`; see `PluginContext::publish`.
+    fn publish(&mut self, event: &impl EventPayload) -> Result<EventMeta, PluginError>;
+
+    /// Publish `event` in response to the event with envelope `to`; see
+    /// `PluginContext::publish_reply`.
+    fn publish_reply(
+        &mut self,
+        to: &EventMeta,
+        event: &impl EventPayload,
+    ) -> Result<EventMeta, PluginError>;
+
+    /// Whether the events of type `event_type` are delivered at least once; see
+    /// `PluginContext::acknowledges`.
+    fn acknowledges(&self, event_type: &str) -> bool;
+
+    /// Acknowledge the event of type `event_type` with envelope `meta`; see
+    /// `PluginContext::ack`.
+    fn ack(&mut self, event_type: &str, meta: Option<&EventMeta>) -> Result<(), PluginError>;
+
+    /// Publish a DeadLetterEvent for an event the plugin failed to process, and acknowledge
+    /// the event; see `PluginContext::dead_letter`.
+    fn dead_letter(
+        &mut self,
+        event_type: &str,
+        meta: Option<&EventMeta>,
+        payload: &[u8],
+        reason: &str,
+    ) -> Result<EventMeta, PluginError> {
+        let plugin_id = self.plugin_id();
+        warn!(
+            plugin_id, event_type;
+            "plugin {} dead-letters a {}: {}",
+            plugin_id, event_type, reason
+        );
+        let dead_letter = DeadLetter {
+            plugin_id,
+            event_type: event_type.to_string(),
+            envelope: meta.map(EventMeta::to_bytes).unwrap_or_default(),
+            payload: payload.to_vec(),
+            reason: reason.to_string(),
+        };
+        let published = match meta {
+            Some(meta) => self.publish_reply(meta, &dead_letter)?,
+            None => self.publish(&dead_letter)?,
+        };
+        self.ack(event_type, meta)?;
+        Ok(published)
+    }
+
+    /// The event of `msg` decoded, or None once it is dead-lettered; see
+    /// `PluginContext::decode_or_dead_letter`.
+    fn decode_or_dead_letter(&mut self, msg: &EventMsg) -> Result<Option<Event>, PluginError> {
+        match msg.decode() {
+            Ok(event) => Ok(Some(event)),
+            Err(e) => {
+                let reason = e.to_string();
+                self.dead_letter(&msg.event_type, msg.meta.as_ref(), &msg.payload, &reason)?;
+                Ok(None)
+            }
+        }
+    }
+}
+
+impl EventSource for PluginContext {
+    fn next_event(&mut self) -> Result<EventMsg, PluginError> {
+        PluginContext::next_event(self)
+    }
+
+    fn done(&mut self) -> Result<(), PluginError> {
+        PluginContext::done(self)
+    }
+}
+
+impl EventSink for PluginContext {
+    fn plugin_id(&self) -> i32 {
+        self.plugin_id
+    }
+
+    fn publish(&mut self, event: &impl EventPayload) -> Result<EventMeta, PluginError> {
+        PluginContext::publish(self, event)
+    }
+
+    fn publish_reply(
+        &mut self,
+        to: &EventMeta,
+        event: &impl EventPayload,
+    ) -> Result<EventMeta, PluginError> {
+        PluginContext::publish_reply(self, to, event)
+    }
+
+    fn acknowledges(&self, event_type: &str) -> bool {
+        PluginContext::acknowledges(self, event_type)
+    }
+
+    fn ack(&mut self, event_type: &str, meta: Option<&EventMeta>) -> Result<(), PluginError> {
+        PluginContext::ack(self, event_type, meta)
+    }
+}
+
+// Adapter for plugins registered as a bare start function.
+pub(crate) struct FnPlugin {
+    plugin_id: i32,
+    name: String,
+    subscriptions: &'static [&'static str],
+    start_function: PluginStartFn,
+}
+
+impl FnPlugin {
+    pub(crate) fn new(
+        plugin_id: i32,
+        subscriptions: &'static [&'static str],
+        start_function: PluginStartFn,
+    ) -> Self {
+        FnPlugin {
+            plugin_id,
+            name: format!("plugin-{}", plugin_id),
+            subscriptions,
+            start_function,
+        }
+    }
+}
+
+impl Plugin for FnPlugin {
+    fn id(&self) -> i32 {
+        self.plugin_id
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn subscriptions(&self) -> &[&str] {
+        self.subscriptions
+    }
+
+    fn start(self: Box<Self>, mut ctx: PluginContext) -> Result<(), PluginError> {
+        // a bare start function receives on the socket itself and cannot check whether the
+        // engine is shutting down, so it blocks until an event arrives as before
+        ctx.sub_socket.set_rcvtimeo(-1)?;
+        (self.start_function)(&mut ctx.pub_socket, &mut ctx.sub_socket, &mut ctx.bldr)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::event_engine::{sync_with_engine, EventEngineBuilder, Transport};
+    use crate::events::{
+        send_event_msg, ImageDeleted, ImageScore, ImageScored, ImageStored, NewImage,
+        SCHEMA_VERSION,
+    };
+    use crate::middleware::{Action, Middleware, VerifyEvents};
+    use crate::plugin_registry::PluginRegistry;
+    use std::collections::HashSet;
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_fn_plugin_adapter() {
+        let plugin = FnPlugin::new(7, &["NewImageEvent"], Box::new(|_, _, _| Ok(())));
+        assert_eq!(plugin.id(), 7);
+        assert_eq!(plugin.name(), "plugin-7");
+        assert_eq!(plugin.subscriptions(), &["NewImageEvent"]);
+
+        let context = zmq::Context::new();
+        let ctx = PluginContext::new(
+            7,
+            context.socket(zmq::PUB).unwrap(),
+            context.socket(zmq::SUB).unwrap(),
+        );
+        assert!(Box::new(plugin).start(ctx).is_ok());
+    }
+
+    // A context whose sub socket receives the given event types from its own pub socket.
+    fn inproc_pair(
+        context: &zmq::Context,
+        endpoint: &str,
+        subscriptions: &[&str],
+    ) -> PluginContext {
+        let pub_socket = context.socket(zmq::PUB).unwrap();
+        pub_socket.bind(endpoint).unwrap();
+        let sub_socket = context.socket(zmq::SUB).unwrap();
+        sub_socket.connect(endpoint).unwrap();
+        for sub in subscriptions {
+            sub_socket
+                .set_subscribe(&get_event_type_bytes_filter(sub).unwrap())
+                .unwrap();
+        }
+        sub_socket.set_rcvtimeo(5000).unwrap();
+        // give the subscriptions time to reach the pub socket
+        std::thread::sleep(std::time::Duration::from_millis(100));
+        PluginContext::new(5, pub_socket, sub_socket)
+    }
+
+    #[test]
+    fn test_publish_next_event_round_trip() {
+        let context = zmq::Context::new();
+        let mut ctx = inproc_pair(
+            &context,
+            "inproc://test-round-trip",
+            &["NewImageEvent", "ImageScoredEvent"],
+        );
+        let meta = ctx
+            .publish(&NewImage {
+                image_uuid: "1234".to_string(),
+                image_format: "png".to_string(),
+                image: vec![1, 2, 3],
+                location: None,
+            })
+            .unwrap();
+        assert_eq!(meta.source_plugin_id, 5);
+        ctx.publish(&ImageScored {
+            image_uuid: "1234".to_string(),
+            scores: vec![ImageScore {
+                label: "labrador".to_string(),
+                probability: 0.25,
+            }],
+        })
+        .unwrap();
+
+        let msg = ctx.next_event().unwrap();
+        assert_eq!(msg.event_type, "NewImageEvent");
+        assert_eq!(msg.meta, Some(meta));
+        let event = msg.event().unwrap();
+        let new_image = event.event_as_new_image_event().unwrap();
+        assert_eq!(new_image.image_uuid(), Some("1234"));
+        assert_eq!(new_image.image_format(), Some("png"));
+        assert_eq!(new_image.image().unwrap().to_vec(), vec![1, 2, 3]);
+
+        let msg = ctx.next_event().unwrap();
+        assert_eq!(msg.event_type, "ImageScoredEvent");
+        let event = msg.event().unwrap();
+        let score = event
+            .event_as_image_scored_event()
+            .unwrap()
+            .scores()
+            .unwrap()
+            .get(0);
+        assert_eq!(score.label(), Some("labrador"));
+        assert_eq!(score.probability(), 0.25);
+    }
+
+    #[test]
+    fn test_events_over_the_max_payload_size_are_not_published() {
+        let context = zmq::Context::new();
+        let mut ctx = inproc_pair(&context, "inproc://test-too-large", &["NewImageEvent"]);
+        ctx.set_max_payload_size(1024);
+        let image = |size| NewImage {
+            image_uuid: format!("image-{}", size),
+            image_format: "png".to_string(),
+            image: vec![7; size],
+            location: None,
+        };
+        assert!(matches!(
+            ctx.publish(&image(4096)),
+            Err(PluginError::Event(EventError::TooLarge { size, limit: 1024 })) if size > 4096
+        ));
+        // the event that was refused did not use up a sequence number
+        assert_eq!(ctx.publish(&image(512)).unwrap().seq, 1);
+        let msg = ctx.next_event().unwrap();
+        assert_eq!(msg.decode().unwrap(), Event::NewImage(image(512)));
+    }
+
+    // Drops the NewImageEvents of plugin 2 with the given sequence numbers.
+    struct DropSeqs(&'static [u64]);
+
+    impl Middleware for DropSeqs {
+        fn on_event(&mut self, event_type: &str, meta: Option<&EventMeta>, _: &[u8]) -> Action {
+            match meta {
+                Some(meta)
+                    if event_type == "NewImageEvent"
+                        && meta.source_plugin_id == 2
+                        && self.0.contains(&meta.seq) =>
+                {
+                    Action::Drop
+                }
+                _ => Action::Forward,
+            }
+        }
+    }
+
+    #[test]
+    fn test_gaps_are_counted_per_publisher() {
+        let context = zmq::Context::new();
+        let engine = EventEngineBuilder::new()
+            .incoming_inproc("messages-gaps")
+            .outgoing_inproc("events-gaps")
+            .transport(Transport::InprocOnly)
+            .context(context.clone())
+            .middleware(Box::new(DropSeqs(&[3, 4, 7])))
+            .plugins(PluginRegistry::new())
+            .start()
+            .unwrap();
+        let publisher = |plugin_id| {
+            let pub_socket = context.socket(zmq::PUB).unwrap();
+            pub_socket.connect("inproc://messages-gaps").unwrap();
+            PluginContext::new(plugin_id, pub_socket, context.socket(zmq::SUB).unwrap())
+        };
+        let (mut dropping, mut intact) = (publisher(2), publisher(3));
+        let sub_socket = context.socket(zmq::SUB).unwrap();
+        sub_socket.connect("inproc://events-gaps").unwrap();
+        let mut ctx = PluginContext::new(5, context.socket(zmq::PUB).unwrap(), sub_socket);
+        for event_type in ["NewImageEvent", "PluginHeartbeatEvent"] {
+            let filter = get_event_type_bytes_filter(event_type).unwrap();
+            ctx.sub_socket.set_subscribe(&filter).unwrap();
+        }
+        let gaps = Arc::new(Mutex::new(Vec::new()));
+        let seen = Arc::clone(&gaps);
+        ctx.on_gap(move |gap| seen.lock().unwrap().push(gap.clone()));
+        // the subscriptions and connections take a moment; probe with heartbeats until they are up
+        ctx.sub_socket.set_rcvtimeo(100).unwrap();
+        let heartbeat = PluginHeartbeat {
+            plugin_id: 2,
+            seq: 0,
+        };
+        let mut probed = HashSet::new();
+        while probed.len() < 2 {
+            dropping.publish(&heartbeat).unwrap();
+            intact.publish(&heartbeat).unwrap();
+            while let Ok(msg) = ctx.next_event() {
+                probed.insert(msg.meta.unwrap().source_plugin_id);
+            }
+        }
+        ctx.sub_socket.set_rcvtimeo(5000).unwrap();
+
+        let image = NewImage {
+            image_uuid: "1234".to_string(),
+            image_format: "png".to_string(),
+            image: vec![1, 2, 3],
+            location: None,
+        };
+        for _ in 0..10 {
+            dropping.publish(&image).unwrap();
+            intact.publish(&image).unwrap();
+        }
+        for _ in 0..17 {
+            assert_eq!(ctx.next_event().unwrap().event_type, "NewImageEvent");
+        }
+        engine.shutdown().unwrap();
+        let expected = GapStats {
+            gaps: 2,
+            missed: 3,
+            resets: 0,
+        };
+        assert_eq!(ctx.gap_stats().get(&2), Some(&expected));
+        assert_eq!(ctx.gap_stats().get(&3), Some(&GapStats::default()));
+        assert_eq!(ctx.dropped_events(), 3);
+        let gap = |last_seq, seq| SeqGap {
+            publisher_id: 2,
+            event_type: "NewImageEvent",
+            last_seq,
+            seq,
+        };
+        assert_eq!(*gaps.lock().unwrap(), [gap(2, 5), gap(6, 8)]);
+    }
+
+    #[test]
+    fn test_restarted_publishers_are_not_gaps() {
+        let context = zmq::Context::new();
+        let mut ctx = inproc_pair(&context, "inproc://test-restarted", &["ImageDeletedEvent"]);
+        let deleted = ImageDeleted {
+            image_uuid: "1234".to_string(),
+            existed: true,
+        };
+        let mut publisher = PluginContext::new(
+            2,
+            context.socket(zmq::PUB).unwrap(),
+            context.socket(zmq::SUB).unwrap(),
+        );
+        // publish on the pub socket of `ctx`, which its sub socket is connected to
+        std::mem::swap(&mut publisher.pub_socket, &mut ctx.pub_socket);
+        for _ in 0..3 {
+            publisher.publish(&deleted).unwrap();
+        }
+        // the same plugin with a new context, as when it is restarted, starts from 1 again
+        let mut restarted =
+            PluginContext::new(2, publisher.pub_socket, context.socket(zmq::SUB).unwrap());
+        for _ in 0..2 {
+            restarted.publish(&deleted).unwrap();
+        }
+        let seqs: Vec<_> = (0..5)
+            .map(|_| ctx.next_event().unwrap().meta.unwrap().seq)
+            .collect();
+        assert_eq!(seqs, [1, 2, 3, 1, 2]);
+        let expected = GapStats {
+            gaps: 0,
+            missed: 0,
+            resets: 1,
+        };
+        assert_eq!(ctx.gap_stats().get(&2), Some(&expected));
+        assert_eq!(ctx.dropped_events(), 0);
+    }
+
+    #[test]
+    fn test_malformed_events_are_skipped() {
+        let context = zmq::Context::new();
+        let mut ctx = inproc_pair(&context, "inproc://test-malformed", &["NewImageEvent"]);
+        let image = Event::NewImage(NewImage {
+            image_uuid: "1234".to_string(),
+            image_format: "png".to_string(),
+            image: vec![1, 2, 3],
+            location: None,
+        });
+        let mut bldr = FlatBufferBuilder::new();
+        let payload = image.encode(&mut bldr).to_vec();
+        let stored = ImageStored {
+            image_uuid: "1234".to_string(),
+            path: String::new(),
+            deduplicated: false,
+        };
+        let stored = Event::ImageStored(stored).encode(&mut bldr).to_vec();
+        // as a faulty publisher on a raw socket would send them
+        let socket = &ctx.pub_socket;
+        send_event_msg(socket, "NewImageEvent", &payload[..payload.len() / 2]).unwrap();
+        send_event_msg(socket, "NewImageEvent", &stored).unwrap();
+        send_event_msg(socket, "NewImageEvent", &payload).unwrap();
+
+        let msg = ctx.next_event().unwrap();
+        assert_eq!(msg.decode().unwrap(), image);
+        assert_eq!(ctx.malformed_events(), 2);
+        let truncated = EventMsg {
+            event_type: "NewImageEvent".to_string(),
+            meta: None,
+            payload: payload[..8].to_vec().into(),
+        };
+        assert!(matches!(
+            truncated.event(),
+            Err(PluginError::Event(EventError::Malformed { event_type, .. })) if event_type == "NewImageEvent"
+        ));
+    }
+
+    #[test]
+    fn test_unparseable_messages_are_skipped() {
+        let context = zmq::Context::new();
+        let mut ctx = inproc_pair(&context, "inproc://test-unparseable", &["NewImageEvent"]);
+        // an external publisher the plugin is subscribed to as well
+        let external = context.socket(zmq::PUB).unwrap();
+        external.bind("inproc://test-unparseable-external").unwrap();
+        ctx.sub_socket
+            .connect("inproc://test-unparseable-external")
+            .unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(100));
+        let image = Event::NewImage(NewImage {
+            image_uuid: "1234".to_string(),
+            image_format: "png".to_string(),
+            image: vec![1, 2, 3],
+            location: None,
+        });
+        let payload = image.encode(&mut FlatBufferBuilder::new()).to_vec();
+        let header = b"NewImageEvent\0";
+        // a garbage envelope, and one frame too many
+        external.send(&header[..], zmq::SNDMORE).unwrap();
+        external.send("not an envelope", zmq::SNDMORE).unwrap();
+        external.send(&payload[..], 0).unwrap();
+        external
+            .send_multipart([&header[..], b"", b"", &payload], 0)
+            .unwrap();
+        send_event_msg(&external, "NewImageEvent", &payload).unwrap();
+
+        let msg = ctx.next_event().unwrap();
+        assert_eq!(msg.decode().unwrap(), image);
+        assert_eq!(ctx.malformed_events(), 2);
+    }
+
+    #[test]
+    fn test_events_with_longer_envelopes_of_a_newer_schema_are_skipped() {
+        let context = zmq::Context::new();
+        let mut ctx = inproc_pair(
+            &context,
+            "inproc://test-newer-envelope",
+            &["ImageDeletedEvent"],
+        );
+        let deleted = ImageDeleted {
+            image_uuid: "1234".to_string(),
+            existed: true,
+        };
+        let payload = Event::ImageDeleted(deleted.clone())
+            .encode(&mut FlatBufferBuilder::new())
+            .to_vec();
+        let meta = EventMeta {
+            schema_version: SCHEMA_VERSION + 1,
+            ..EventMeta::new(9)
+        };
+        let mut envelope = meta.to_bytes();
+        envelope.extend_from_slice(&[0; 8]);
+        let header = b"ImageDeletedEvent\0";
+        ctx.pub_socket
+            .send_multipart([&header[..], &envelope, &payload], 0)
+            .unwrap();
+        ctx.publish(&deleted).unwrap();
+
+        let msg = ctx.next_event().unwrap();
+        assert_eq!(msg.meta.unwrap().schema_version, SCHEMA_VERSION);
+        assert_eq!(ctx.malformed_events(), 1);
+    }
+
+    #[test]
+    fn test_event_msg_decode_checks_type() {
+        let mut bldr = FlatBufferBuilder::new();
+        let event = Event::ImageStored(ImageStored {
+            image_uuid: "1234".to_string(),
+            path: String::new(),
+            deduplicated: false,
+        });
+        let mut msg = EventMsg {
+            event_type: "ImageStoredEvent".to_string(),
+            meta: None,
+            payload: event.encode(&mut bldr).into(),
+        };
+        assert_eq!(msg.decode().unwrap(), event);
+        msg.event_type = "ImageDeletedEvent".to_string();
+        assert!(matches!(
+            msg.decode(),
+            Err(PluginError::Event(EventError::TypeMismatch { expected })) if expected == "ImageDeletedEvent"
+        ));
+    }
+
+    #[test]
+    fn test_next_event_only_returns_subscribed_types() {
+        let context = zmq::Context::new();
+        let mut ctx = inproc_pair(&context, "inproc://test-filtered", &["ImageDeletedEvent"]);
+        ctx.publish(&ImageStored {
+            image_uuid: "stored".to_string(),
+            path: String::new(),
+            deduplicated: false,
+        })
+        .unwrap();
+        ctx.publish(&ImageDeleted {
+            image_uuid: "deleted".to_string(),
+            existed: false,
+        })
+        .unwrap();
+
+        let msg = ctx.next_event().unwrap();
+        assert_eq!(msg.event_type, "ImageDeletedEvent");
+        let event = msg.event().unwrap();
+        assert_eq!(
+            event.event_as_image_deleted_event().unwrap().image_uuid(),
+            Some("deleted")
+        );
+    }
+
+    #[test]
+    fn test_subscriptions_change_at_runtime() {
+        let context = zmq::Context::new();
+        let subscriptions = ["NewImageEvent", "ImageDeletedEvent"];
+        let mut ctx = inproc_pair(&context, "inproc://test-resubscribe", &subscriptions)
+            .with_subscriptions(&subscriptions);
+        let new_image = |image_uuid: &str| NewImage {
+            image_uuid: image_uuid.to_string(),
+            image_format: "png".to_string(),
+            image: vec![1, 2, 3],
+            location: None,
+        };
+        let deleted = |image_uuid: &str| ImageDeleted {
+            image_uuid: image_uuid.to_string(),
+            existed: false,
+        };
+        // the filter changes take a moment to reach the pub socket
+        let settle = || std::thread::sleep(std::time::Duration::from_millis(100));
+
+        let error = ctx.subscribe("NewImgEvent").unwrap_err();
+        assert!(
+            error.to_string().contains("did you mean NewImageEvent?"),
+            "{}",
+            error
+        );
+        assert!(ctx.unsubscribe("PluginTerminateEvent").is_err());
+
+        ctx.unsubscribe("NewImageEvent").unwrap();
+        settle();
+        ctx.publish(&new_image("skipped")).unwrap();
+        ctx.publish(&deleted("first")).unwrap();
+        assert_eq!(
+            ctx.next_event().unwrap().decode().unwrap(),
+            Event::ImageDeleted(deleted("first"))
+        );
+
+        // subscribing twice is subscribing once, so one unsubscribe undoes it
+        ctx.subscribe("NewImageEvent").unwrap();
+        ctx.subscribe("NewImageEvent").unwrap();
+        settle();
+        ctx.publish(&new_image("received")).unwrap();
+        assert_eq!(
+            ctx.next_event().unwrap().decode().unwrap(),
+            Event::NewImage(new_image("received"))
+        );
+        ctx.unsubscribe("NewImageEvent").unwrap();
+        settle();
+        ctx.publish(&new_image("skipped")).unwrap();
+        ctx.publish(&deleted("second")).unwrap();
+        assert_eq!(
+            ctx.next_event().unwrap().decode().unwrap(),
+            Event::ImageDeleted(deleted("second"))
+        );
+
+        ctx.subscribe("ImageSt*").unwrap();
+        settle();
+        let stored = ImageStored {
+            image_uuid: "stored".to_string(),
+            path: String::new(),
+            deduplicated: false,
+        };
+        ctx.publish(&stored).unwrap();
+        assert_eq!(
+            ctx.next_event().unwrap().decode().unwrap(),
+            Event::ImageStored(stored)
+        );
+    }
+
+    #[test]
+    fn test_backlog_is_reported() {
+        let context = zmq::Context::new();
+        let subscriptions = [
+            "NewImageEvent",
+            "BackpressureEvent",
+            "BackpressureRelievedEvent",
+        ];
+        let mut ctx = inproc_pair(&context, "inproc://test-backpressure", &subscriptions)
+            .with_subscriptions(&subscriptions);
+        ctx.report_backpressure(3);
+        ctx.pause_on_backpressure().unwrap();
+        for image_uuid in ["1", "2", "3", "4"] {
+            let new_image = NewImage {
+                image_uuid: image_uuid.to_string(),
+                image_format: "png".to_string(),
+                image: vec![1, 2, 3],
+                location: None,
+            };
+            ctx.publish(&new_image).unwrap();
+        }
+        std::thread::sleep(std::time::Duration::from_millis(100));
+
+        // receiving the first image takes them all off the sub socket: the backlog is over the
+        // threshold, and the context reports it to itself
+        let first = ctx.next_event().unwrap();
+        assert_eq!(first.event_type, "NewImageEvent");
+        assert_eq!(ctx.backlog(), 4);
+        let mut received = vec![first.decode().unwrap()];
+        while received.len() < 6 {
+            ctx.done().unwrap();
+            let event = ctx.next_event().unwrap().decode().unwrap();
+            match &event {
+                Event::Backpressure(_) => assert!(ctx.is_backpressured()),
+                Event::BackpressureRelieved(_) => assert!(!ctx.is_backpressured()),
+                _ => (),
+            }
+            received.push(event);
+        }
+        assert!(received[..4]
+            .iter()
+            .all(|event| matches!(event, Event::NewImage(_))));
+        assert_eq!(
+            received[4],
+            Event::Backpressure(Backpressure {
+                plugin_id: 5,
+                backlog: 4
+            })
+        );
+        // relief comes at half the threshold, when the BackpressureEvent is all that is left
+        assert_eq!(
+            received[5],
+            Event::BackpressureRelieved(BackpressureRelieved {
+                plugin_id: 5,
+                backlog: 1
+            })
+        );
+    }
+
+    #[test]
+    fn test_next_event_stops_with_the_engine() {
+        let context = zmq::Context::new();
+        let stopping = Arc::new(AtomicBool::new(false));
+        let mut ctx = inproc_pair(&context, "inproc://test-stopping", &["ImageDeletedEvent"])
+            .with_stopping(Arc::clone(&stopping));
+        ctx.sub_socket.set_rcvtimeo(10).unwrap();
+        ctx.publish(&ImageDeleted {
+            image_uuid: "deleted".to_string(),
+            existed: false,
+        })
+        .unwrap();
+
+        stopping.store(true, Ordering::SeqCst);
+        // the events already there are still returned
+        assert_eq!(ctx.next_event().unwrap().event_type, "ImageDeletedEvent");
+        assert!(matches!(ctx.next_event(), Err(PluginError::Stopped)));
+    }
+
+    #[test]
+    fn test_wait_for_event_gives_up_at_the_timeout() {
+        let context = zmq::Context::new();
+        let mut ctx = inproc_pair(
+            &context,
+            "inproc://test-wait-for-event",
+            &["ImageDeletedEvent"],
+        );
+        let start = Instant::now();
+        assert!(ctx
+            .wait_for_event(Duration::from_millis(50))
+            .unwrap()
+            .is_none());
+        assert!(start.elapsed() >= Duration::from_millis(50));
+        assert!(start.elapsed() < Duration::from_secs(1));
+
+        ctx.publish(&ImageDeleted {
+            image_uuid: "deleted".to_string(),
+            existed: false,
+        })
+        .unwrap();
+        let msg = ctx.wait_for_event(Duration::from_secs(5)).unwrap().unwrap();
+        assert_eq!(msg.event_type, "ImageDeletedEvent");
+    }
+
+    // SIGUSR1 on Linux, whose handler only counts it, so that the blocking zmq calls of the
+    // thread it reaches fail with EINTR.
+    #[cfg(target_os = "linux")]
+    const INTERRUPT: i32 = 10;
+
+    #[cfg(target_os = "linux")]
+    extern "C" {
+        fn signal(signum: i32, handler: extern "C" fn(i32)) -> usize;
+        fn pthread_kill(thread: std::os::unix::thread::RawPthread, signum: i32) -> i32;
+    }
+
+    #[cfg(target_os = "linux")]
+    thread_local! {
+        static INTERRUPTS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+    }
+
+    #[cfg(target_os = "linux")]
+    extern "C" fn count_interrupt(_: i32) {
+        INTERRUPTS.with(|interrupts| interrupts.set(interrupts.get() + 1));
+    }
+
+    // Make `call` on a thread interrupted by signals for a while, then do `then`, e.g., send
+    // what it waits for; the result of `call` and how many signals interrupted it.
+    #[cfg(target_os = "linux")]
+    fn interrupted<T: Send + 'static>(
+        call: impl FnOnce() -> T + Send + 'static,
+        then: impl FnOnce(),
+    ) -> (T, usize) {
+        use std::os::unix::thread::JoinHandleExt;
+        unsafe { signal(INTERRUPT, count_interrupt) };
+        let caller = std::thread::spawn(move || {
+            let result = call();
+            (result, INTERRUPTS.with(|interrupts| interrupts.get()))
+        });
+        for _ in 0..10 {
+            std::thread::sleep(Duration::from_millis(20));
+            // the thread is not joined yet, so it is still there to signal
+            assert_eq!(unsafe { pthread_kill(caller.as_pthread_t(), INTERRUPT) }, 0);
+        }
+        then();
+        caller.join().unwrap()
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_next_event_goes_on_through_interrupts() {
+        let context = zmq::Context::new();
+        let mut publisher = inproc_pair(&context, "inproc://test-interrupted-next-event", &[]);
+        let sub_socket = context.socket(zmq::SUB).unwrap();
+        sub_socket
+            .connect("inproc://test-interrupted-next-event")
+            .unwrap();
+        sub_socket
+            .set_subscribe(&get_event_type_bytes_filter("ImageDeletedEvent").unwrap())
+            .unwrap();
+        sub_socket.set_rcvtimeo(5000).unwrap();
+        let mut ctx = PluginContext::new(5, context.socket(zmq::PUB).unwrap(), sub_socket);
+        std::thread::sleep(Duration::from_millis(100));
+
+        let (msg, interrupts) = interrupted(
+            move || ctx.next_event().map(|msg| msg.event_type),
+            || {
+                publisher
+                    .publish(&ImageDeleted {
+                        image_uuid: "deleted".to_string(),
+                        existed: false,
+                    })
+                    .unwrap();
+            },
+        );
+        assert_eq!(msg.unwrap(), "ImageDeletedEvent");
+        assert_eq!(interrupts, 10);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_sync_goes_on_through_interrupts() {
+        let context = zmq::Context::new();
+        let engine_sync = context.socket(zmq::REP).unwrap();
+        engine_sync.bind("inproc://test-interrupted-sync").unwrap();
+        let sync = context.socket(zmq::REQ).unwrap();
+        sync.connect("inproc://test-interrupted-sync").unwrap();
+        let sub = context.socket(zmq::SUB).unwrap();
+
+        let (synced, interrupts) = interrupted(
+            move || sync_with_engine(5, &sync, &sub, Duration::from_secs(5)),
+            || {
+                engine_sync.recv_msg(0).unwrap();
+                engine_sync.send("ok", 0).unwrap();
+            },
+        );
+        assert!(synced);
+        assert_eq!(interrupts, 10);
+    }
+
+    fn tick(msg: &EventMsg) -> Option<Tick> {
+        match msg.decode().unwrap() {
+            Event::Tick(tick) => Some(tick),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_ticks_come_without_events() {
+        let context = zmq::Context::new();
+        let mut ctx = inproc_pair(&context, "inproc://test-ticks", &["ImageDeletedEvent"]);
+        let start = Instant::now();
+        ctx.set_timer(Duration::from_millis(50));
+        let mut seqs = Vec::new();
+        let mut scheduled = Vec::new();
+        while start.elapsed() < Duration::from_millis(500) {
+            let msg = ctx.next_event().unwrap();
+            assert_eq!(msg.event_type, "TickEvent");
+            assert_eq!(msg.meta, None);
+            let tick = tick(&msg).unwrap();
+            seqs.push(tick.seq);
+            scheduled.push(tick.scheduled_at_ms);
+        }
+        assert!((8..=11).contains(&seqs.len()), "{} ticks", seqs.len());
+        assert_eq!(seqs, (1..=seqs.len() as u64).collect::<Vec<_>>());
+        assert!(scheduled.windows(2).all(|pair| pair[0] < pair[1]));
+
+        // a zero interval stops the timer
+        ctx.set_timer(Duration::ZERO);
+        ctx.sub_socket.set_rcvtimeo(200).unwrap();
+        assert!(matches!(
+            ctx.next_event(),
+            Err(PluginError::Socket(zmq::Error::EAGAIN))
+        ));
+    }
+
+    #[test]
+    fn test_ticks_interleave_with_events() {
+        let context = zmq::Context::new();
+        let mut ctx = inproc_pair(
+            &context,
+            "inproc://test-tick-events",
+            &["ImageDeletedEvent"],
+        );
+        let deleted = |image_uuid: &str| ImageDeleted {
+            image_uuid: image_uuid.to_string(),
+            existed: true,
+        };
+        ctx.set_timer(Duration::from_millis(50));
+        let next = |ctx: &mut PluginContext| {
+            let msg = ctx.next_event().unwrap();
+            match tick(&msg) {
+                Some(tick) => format!("tick {}", tick.seq),
+                None => match msg.decode().unwrap() {
+                    Event::ImageDeleted(e) => e.image_uuid,
+                    event => panic!("unexpected {:?}", event),
+                },
+            }
+        };
+
+        // the events do not wait for the tick
+        ctx.publish(&deleted("first")).unwrap();
+        assert_eq!(next(&mut ctx), "first");
+        assert_eq!(next(&mut ctx), "tick 1");
+        ctx.publish(&deleted("second")).unwrap();
+        ctx.publish(&deleted("third")).unwrap();
+        // the ticks missed while busy come as one, before the events waiting
+        std::thread::sleep(Duration::from_millis(120));
+        assert_eq!(next(&mut ctx), "tick 2");
+        assert_eq!(next(&mut ctx), "second");
+        assert_eq!(next(&mut ctx), "third");
+        assert_eq!(next(&mut ctx), "tick 3");
+    }
+
+    #[test]
+    fn test_json_and_flatbuffers_publishers_share_an_engine() {
+        let context = zmq::Context::new();
+        let engine = EventEngineBuilder::new()
+            .incoming_inproc("messages-codecs")
+            .outgoing_inproc("events-codecs")
+            .transport(Transport::InprocOnly)
+            .context(context.clone())
+            .middleware(Box::new(VerifyEvents::new()))
+            .plugins(PluginRegistry::new())
+            .start()
+            .unwrap();
+        let publisher = |plugin_id, codec| {
+            let pub_socket = context.socket(zmq::PUB).unwrap();
+            pub_socket.connect("inproc://messages-codecs").unwrap();
+            let mut ctx =
+                PluginContext::new(plugin_id, pub_socket, context.socket(zmq::SUB).unwrap());
+            ctx.set_codec(codec);
+            ctx
+        };
+        let mut json = publisher(2, Codec::Json);
+        let mut flatbuffers = publisher(3, Codec::Flatbuffers);
+        let sub_socket = context.socket(zmq::SUB).unwrap();
+        sub_socket.connect("inproc://events-codecs").unwrap();
+        let raw = context.socket(zmq::SUB).unwrap();
+        raw.connect("inproc://events-codecs").unwrap();
+        for event_type in ["ImageScoredEvent", "PluginHeartbeatEvent"] {
+            let filter = get_event_type_bytes_filter(event_type).unwrap();
+            sub_socket.set_subscribe(&filter).unwrap();
+            raw.set_subscribe(&filter).unwrap();
+        }
+        let mut ctx = PluginContext::new(5, context.socket(zmq::PUB).unwrap(), sub_socket);
+        // the subscriptions and connections take a moment; probe with heartbeats until they are up
+        ctx.sub_socket.set_rcvtimeo(100).unwrap();
+        let heartbeat = PluginHeartbeat {
+            plugin_id: 2,
+            seq: 0,
+        };
+        let mut probed = HashSet::new();
+        while probed.len() < 2 {
+            json.publish(&heartbeat).unwrap();
+            flatbuffers.publish(&heartbeat).unwrap();
+            while let Ok(msg) = ctx.next_event() {
+                probed.insert(msg.meta.unwrap().source_plugin_id);
+            }
+        }
+        ctx.sub_socket.set_rcvtimeo(5000).unwrap();
+        raw.set_rcvtimeo(100).unwrap();
+        while raw.recv_multipart(0).is_ok() {}
+        raw.set_rcvtimeo(5000).unwrap();
+
+        let scored = ImageScored {
+            image_uuid: "1234".to_string(),
+            scores: vec![ImageScore {
+                label: "cat".to_string(),
+                probability: 0.75,
+            }],
+        };
+        assert_eq!(json.publish(&scored).unwrap().codec, Codec::Json);
+        flatbuffers.publish(&scored).unwrap();
+        let mut sent_json = 0;
+        for _ in 0..2 {
+            let msg = ctx.next_event().unwrap();
+            // whatever it was published with, the payload is a flatbuffer now
+            assert_eq!(msg.meta.unwrap().codec, Codec::Flatbuffers);
+            assert_eq!(msg.decode().unwrap(), Event::ImageScored(scored.clone()));
+            let event = msg.event().unwrap();
+            assert_eq!(
+                event.event_as_image_scored_event().unwrap().image_uuid(),
+                Some("1234")
+            );
+
+            let frames = raw.recv_multipart(0).unwrap();
+            let meta = EventMeta::from_bytes(&frames[1]).unwrap();
+            if meta.codec == Codec::Json {
+                sent_json += 1;
+                assert_eq!(meta.source_plugin_id, 2);
+                let sent: Value = serde_json::from_slice(&frames[2]).unwrap();
+                assert_eq!(sent["scores"][0]["label"], "cat");
+            }
+        }
+        assert_eq!(sent_json, 1);
+        engine.shutdown().unwrap();
+    }
+}
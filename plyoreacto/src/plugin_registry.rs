@@ -0,0 +1,1126 @@
+//! The set of plugins an engine starts.
+//! Callers assemble a `PluginRegistry` at runtime and hand it to the `EventEngineBuilder`;
+//! `default_plugins()` returns the image pipeline the engine runs by default.
+//!
+
+use std::collections::HashSet;
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::time::Duration;
+
+use flatbuffers::FlatBufferBuilder;
+use log::{info, warn};
+use zmq::Socket;
+
+use crate::config_file::PluginEntry;
+use crate::event_engine::{EngineError, Hwm, InvalidPluginId};
+use crate::image_score_plugin::ImageScorePlugin;
+use crate::image_store_plugin::ImageStorePlugin;
+use crate::new_image_plugin::NewImagePlugin;
+use crate::plugin::{FnPlugin, Plugin, PluginContext, PluginError};
+use crate::settings::{PluginSettings, SettingsError};
+
+/// Plugin ids kept for plugins the engine may come to run for itself, e.g., metrics or bridge
+/// plugins of its own; an engine refuses to start with a plugin registered with one of them, and
+/// never assigns one to an external plugin registering at runtime.
+pub const RESERVED_PLUGIN_IDS: RangeInclusive<i32> = 1_000_000..=i32::MAX;
+
+/// Signature of a plugin start function: it gets the socket to publish new events on, the
+/// socket to receive its subscribed events on, and a builder for serializing events.
+/// Start functions may be closures that capture their own configuration or state; each one is
+/// called once, in the plugin's thread.
+pub type PluginStartFn =
+    Box<dyn FnOnce(&mut Socket, &mut Socket, &mut FlatBufferBuilder) -> std::io::Result<()> + Send>;
+
+/// Creates a fresh instance of a restartable plugin; see `PluginRegistry::register_restartable`.
+pub type PluginFactory = Box<dyn Fn() -> Box<dyn Plugin> + Send>;
+
+/// What the engine does when a plugin's start function panics or returns an error.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RestartPolicy {
+    /// Leave the plugin failed.
+    #[default]
+    Never,
+    /// Wait for `backoff`, then sync and start a new instance of the plugin; a plugin is
+    /// restarted at most `max_retries` times.
+    Always { max_retries: u32, backoff: Duration },
+}
+
+/// A plugin another one depends on, see `PluginRegistry::depends_on`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Dependency {
+    /// The plugin with this id, run by the engine or a required external plugin.
+    Id(i32),
+    /// Every plugin run by the engine with this name.
+    Name(String),
+}
+
+impl From<i32> for Dependency {
+    fn from(plugin_id: i32) -> Self {
+        Dependency::Id(plugin_id)
+    }
+}
+
+impl From<&str> for Dependency {
+    fn from(name: &str) -> Self {
+        Dependency::Name(name.to_string())
+    }
+}
+
+impl fmt::Display for Dependency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Dependency::Id(plugin_id) => write!(f, "plugin {}", plugin_id),
+            Dependency::Name(name) => write!(f, "plugin {:?}", name),
+        }
+    }
+}
+
+// Plugins run by the engine in their own thread. Restartable plugins keep the factory their
+// first instance was made with, to make a new one each time they are restarted; pooled plugins,
+// to make the instances of their other workers.
+pub(crate) struct PluginConfig {
+    pub(crate) plugin: Box<dyn Plugin>,
+    pub(crate) factory: Option<PluginFactory>,
+    pub(crate) restart_policy: RestartPolicy,
+    // how many instances of the plugin run, each in a thread of its own
+    pub(crate) workers: usize,
+    // high-water marks of the plugin's pub and sub sockets
+    pub(crate) hwm: Hwm,
+    // stack size of the plugin's threads, in bytes; the standard library's default if None
+    pub(crate) stack_size: Option<usize>,
+    // the plugins that must be running before this one gets its sync reply
+    pub(crate) depends_on: Vec<Dependency>,
+    // whether the engine starts without the plugin when it cannot be spawned or does not sync
+    pub(crate) optional: bool,
+    // the configuration values the plugin's context is given
+    pub(crate) settings: PluginSettings,
+}
+
+impl PluginConfig {
+    // The plugin subscribed, with the instances its factory makes, to `subscriptions` instead of
+    // its own. The names live as long as the process, like those of the custom event types; an
+    // engine resubscribes its plugins once, when it starts.
+    fn resubscribed(self, subscriptions: &[String]) -> Self {
+        let subscriptions: Vec<&'static str> = subscriptions
+            .iter()
+            .map(|subscription| &*Box::leak(subscription.clone().into_boxed_str()))
+            .collect();
+        let factory = self.factory.map(|factory| {
+            let subscriptions = subscriptions.clone();
+            Box::new(move || -> Box<dyn Plugin> {
+                Box::new(Resubscribed {
+                    plugin: factory(),
+                    subscriptions: subscriptions.clone(),
+                })
+            }) as PluginFactory
+        });
+        PluginConfig {
+            plugin: Box::new(Resubscribed {
+                plugin: self.plugin,
+                subscriptions,
+            }),
+            factory,
+            ..self
+        }
+    }
+}
+
+// A plugin subscribed to other event types than its own, see `PluginEntry::subscriptions`.
+struct Resubscribed {
+    plugin: Box<dyn Plugin>,
+    subscriptions: Vec<&'static str>,
+}
+
+impl Plugin for Resubscribed {
+    fn id(&self) -> i32 {
+        self.plugin.id()
+    }
+
+    fn name(&self) -> &str {
+        self.plugin.name()
+    }
+
+    fn subscriptions(&self) -> &[&str] {
+        &self.subscriptions
+    }
+
+    fn publications(&self) -> &[&str] {
+        self.plugin.publications()
+    }
+
+    fn configure(&mut self, settings: &PluginSettings) -> Result<(), SettingsError> {
+        self.plugin.configure(settings)
+    }
+
+    fn start(self: Box<Self>, ctx: PluginContext) -> Result<(), PluginError> {
+        self.plugin.start(ctx)
+    }
+}
+
+// External plugins run in their own process and only sync with the engine over TCP.
+pub(crate) struct ExternalPluginConfig {
+    // Every plugin gets a unique id
+    pub(crate) plugin_id: i32,
+    // whether the engine waits for the plugin at startup; others may join at any time
+    pub(crate) required: bool,
+}
+
+/// Plugins to be started by an engine, keyed by their unique plugin id.
+/// Plugins announce themselves with their id when they sync with the engine.
+///
+/// ```
+/// use plyoreacto::event_engine::EventEngineBuilder;
+/// use plyoreacto::events::{bytes_to_event, recv_event_msg};
+/// use plyoreacto::new_image_plugin;
+/// use plyoreacto::plugin_registry::PluginRegistry;
+/// use std::sync::atomic::{AtomicBool, Ordering};
+/// use std::time::{Duration, Instant};
+///
+/// static GOT_NEW_IMAGE: AtomicBool = AtomicBool::new(false);
+///
+/// let mut plugins = PluginRegistry::new();
+/// plugins
+///     .register(0, &[], new_image_plugin::start)?
+///     .register(1, &["NewImageEvent"], |_pub_socket, sub_socket, _bldr| {
+///         let (_event_type, payload) = recv_event_msg(sub_socket)?;
+///         let event = bytes_to_event(&payload)?;
+///         if event.event_as_new_image_event().is_some() {
+///             GOT_NEW_IMAGE.store(true, Ordering::SeqCst);
+///         }
+///         Ok(())
+///     })?;
+///
+/// let engine = EventEngineBuilder::new()
+///     .incoming_port(7559)
+///     .outgoing_port(7560)
+///     .sync_port(7000)
+///     .plugins(plugins)
+///     .start()?;
+/// // give the closure plugin a chance to see the first image before shutting down
+/// let deadline = Instant::now() + Duration::from_secs(10);
+/// while !GOT_NEW_IMAGE.load(Ordering::SeqCst) && Instant::now() < deadline {
+///     std::thread::sleep(Duration::from_millis(10));
+/// }
+/// engine.shutdown()?;
+/// assert!(GOT_NEW_IMAGE.load(Ordering::SeqCst));
+/// # Ok::<(), plyoreacto::event_engine::EngineError>(())
+/// ```
+#[derive(Default)]
+pub struct PluginRegistry {
+    pub(crate) plugins: Vec<PluginConfig>,
+    pub(crate) external_plugins: Vec<ExternalPluginConfig>,
+    // the ids of the plugins of each profile, by name, in the order they were defined
+    profiles: Vec<(String, Vec<i32>)>,
+}
+
+impl PluginRegistry {
+    pub fn new() -> Self {
+        PluginRegistry::default()
+    }
+
+    /// Register a plugin that the engine runs in its own thread.
+    pub fn register_plugin(&mut self, plugin: Box<dyn Plugin>) -> Result<&mut Self, EngineError> {
+        self.check_unique(plugin.id())?;
+        self.plugins.push(PluginConfig {
+            plugin,
+            factory: None,
+            restart_policy: RestartPolicy::Never,
+            workers: 1,
+            hwm: Hwm::default(),
+            stack_size: None,
+            depends_on: Vec::new(),
+            optional: false,
+            settings: PluginSettings::new(),
+        });
+        Ok(self)
+    }
+
+    /// Register a plugin that the engine restarts according to `restart_policy` when it fails.
+    /// `factory` is called once now, and again for every restart; all the plugins it makes must
+    /// have the same id. Events published while the plugin is down are not delivered to it.
+    pub fn register_restartable<F>(
+        &mut self,
+        restart_policy: RestartPolicy,
+        factory: F,
+    ) -> Result<&mut Self, EngineError>
+    where
+        F: Fn() -> Box<dyn Plugin> + Send + 'static,
+    {
+        let plugin = factory();
+        self.check_unique(plugin.id())?;
+        self.plugins.push(PluginConfig {
+            plugin,
+            factory: Some(Box::new(factory)),
+            restart_policy,
+            workers: 1,
+            hwm: Hwm::default(),
+            stack_size: None,
+            depends_on: Vec::new(),
+            optional: false,
+            settings: PluginSettings::new(),
+        });
+        Ok(self)
+    }
+
+    /// Register a plugin run by a pool of `workers` threads, for plugins that cannot keep up
+    /// with their events on one thread (e.g., the image scoring plugin). `factory` is called
+    /// once for each worker, and all the plugins it makes must have the same id and
+    /// subscriptions. Instead of every worker getting every event, as subscribers do, the engine
+    /// hands each subscribed event to a single worker, taking them in turn (and skipping those
+    /// that are behind), and the PluginTerminateEvent to all of them; the events the workers
+    /// publish reach the subscribers like those of any plugin. The pool syncs with the engine
+    /// once all of its workers are up, and is finished once they all are. Pooled plugins are not
+    /// restarted, their workers do not count the events dropped on their way (see
+    /// `PluginContext::dropped_events`), as each one only gets some of them, and they cannot
+    /// answer heartbeats.
+    pub fn register_pool<F>(&mut self, workers: usize, factory: F) -> Result<&mut Self, EngineError>
+    where
+        F: Fn() -> Box<dyn Plugin> + Send + 'static,
+    {
+        let plugin = factory();
+        let plugin_id = plugin.id();
+        self.check_unique(plugin_id)?;
+        if workers == 0 {
+            return Err(EngineError::PluginSpawn {
+                plugin_id,
+                reason: "a worker pool needs at least one worker".to_string(),
+            });
+        }
+        self.plugins.push(PluginConfig {
+            plugin,
+            factory: Some(Box::new(factory)),
+            restart_policy: RestartPolicy::Never,
+            workers,
+            hwm: Hwm::default(),
+            stack_size: None,
+            depends_on: Vec::new(),
+            optional: false,
+            settings: PluginSettings::new(),
+        });
+        Ok(self)
+    }
+
+    /// Register a bare start function as a plugin that the engine runs in its own thread.
+    /// Subscriptions must match event names (e.g., "NewImageEvent"). `start_function` can be a
+    /// plain function, a closure, or an already boxed `PluginStartFn`.
+    pub fn register<F>(
+        &mut self,
+        plugin_id: i32,
+        subscriptions: &'static [&'static str],
+        start_function: F,
+    ) -> Result<&mut Self, EngineError>
+    where
+        F: FnOnce(&mut Socket, &mut Socket, &mut FlatBufferBuilder) -> std::io::Result<()>
+            + Send
+            + 'static,
+    {
+        self.register_plugin(Box::new(FnPlugin::new(
+            plugin_id,
+            subscriptions,
+            Box::new(start_function),
+        )))
+    }
+
+    /// Register a plugin that runs outside of the engine and is required: the engine waits for
+    /// it to sync on the TCP sync port before starting the proxy.
+    pub fn register_external(&mut self, plugin_id: i32) -> Result<&mut Self, EngineError> {
+        self.check_unique(plugin_id)?;
+        self.external_plugins.push(ExternalPluginConfig {
+            plugin_id,
+            required: true,
+        });
+        Ok(self)
+    }
+
+    /// Register a plugin that runs outside of the engine and is not waited for: it syncs
+    /// whenever it starts, before or after the engine, and gets the events published from then
+    /// on.
+    pub fn register_optional_external(&mut self, plugin_id: i32) -> Result<&mut Self, EngineError> {
+        self.check_unique(plugin_id)?;
+        self.external_plugins.push(ExternalPluginConfig {
+            plugin_id,
+            required: false,
+        });
+        Ok(self)
+    }
+
+    /// Set the high-water marks of the pub and sub sockets of the plugin `plugin_id`, registered
+    /// to run in the engine; a restarted plugin gets them too. External plugins create their own
+    /// sockets.
+    pub fn hwm(&mut self, plugin_id: i32, hwm: Hwm) -> Result<&mut Self, EngineError> {
+        let plugin = self
+            .plugins
+            .iter_mut()
+            .find(|p| p.plugin.id() == plugin_id)
+            .ok_or(EngineError::UnknownPluginId { plugin_id })?;
+        plugin.hwm = hwm;
+        Ok(self)
+    }
+
+    /// Set the stack size, in bytes, of the thread of the plugin `plugin_id` (of each of its
+    /// workers for a pool), for plugins that need more than the standard library's default, e.g.,
+    /// to decode large images with deeply recursive code.
+    pub fn stack_size(&mut self, plugin_id: i32, bytes: usize) -> Result<&mut Self, EngineError> {
+        let plugin = self
+            .plugins
+            .iter_mut()
+            .find(|p| p.plugin.id() == plugin_id)
+            .ok_or(EngineError::UnknownPluginId { plugin_id })?;
+        plugin.stack_size = Some(bytes);
+        Ok(self)
+    }
+
+    /// Give the plugin `plugin_id` (each of its workers, for a pool, and each of its instances,
+    /// for a restartable plugin) `settings`, which it reads from `PluginContext::settings` once
+    /// it starts; see the `settings` module.
+    pub fn settings(
+        &mut self,
+        plugin_id: i32,
+        settings: PluginSettings,
+    ) -> Result<&mut Self, EngineError> {
+        let plugin = self
+            .plugins
+            .iter_mut()
+            .find(|p| p.plugin.id() == plugin_id)
+            .ok_or(EngineError::UnknownPluginId { plugin_id })?;
+        plugin.settings = settings;
+        Ok(self)
+    }
+
+    /// Make the plugin `plugin_id` optional, or required again: an optional plugin that cannot be
+    /// spawned or does not sync within the sync timeout is left out, with its status set to
+    /// `PluginStatus::Failed`, and the engine starts without it and publishes a
+    /// `PluginSkippedEvent` once it has started. A required plugin failing to start stops the
+    /// engine from starting, as every plugin does by default.
+    pub fn optional(&mut self, plugin_id: i32, optional: bool) -> Result<&mut Self, EngineError> {
+        let plugin = self
+            .plugins
+            .iter_mut()
+            .find(|p| p.plugin.id() == plugin_id)
+            .ok_or(EngineError::UnknownPluginId { plugin_id })?;
+        plugin.optional = optional;
+        Ok(self)
+    }
+
+    /// Have the plugin `plugin_id`, registered to run in the engine, start after its
+    /// `dependencies`, given by id (`&[1, 2]`) or by name (`&["image-score"]`): the engine
+    /// starts the plugins in dependency order, and only answers the sync of a plugin once its
+    /// dependencies have synced and, for those it runs, are running. Plugins without
+    /// dependencies between them start together. Starting the engine fails if a dependency is
+    /// neither run by the engine nor a required external plugin, or if plugins depend on each
+    /// other in a cycle.
+    pub fn depends_on<D>(
+        &mut self,
+        plugin_id: i32,
+        dependencies: &[D],
+    ) -> Result<&mut Self, EngineError>
+    where
+        D: Clone + Into<Dependency>,
+    {
+        let plugin = self
+            .plugins
+            .iter_mut()
+            .find(|p| p.plugin.id() == plugin_id)
+            .ok_or(EngineError::UnknownPluginId { plugin_id })?;
+        plugin
+            .depends_on
+            .extend(dependencies.iter().cloned().map(Into::into));
+        Ok(self)
+    }
+
+    /// Define the profile `name`: the registered plugins, by id, that an engine started with
+    /// `EventEngineBuilder::with_profile(name)` runs, or waits for if they are external. The
+    /// others are left out as if they had never been registered, but for a warning about the
+    /// subscriptions of those started to event types that only the plugins left out publish
+    /// (see `Plugin::publications`), and their dependencies on the plugins left out, which are
+    /// dropped. Defining a profile again replaces it.
+    pub fn profile(&mut self, name: &str, plugin_ids: &[i32]) -> Result<&mut Self, EngineError> {
+        let registered = self.plugin_ids();
+        if let Some(plugin_id) = plugin_ids.iter().find(|id| !registered.contains(id)) {
+            return Err(EngineError::UnknownPluginId {
+                plugin_id: *plugin_id,
+            });
+        }
+        self.profiles.retain(|(profile, _)| profile != name);
+        self.profiles.push((name.to_string(), plugin_ids.to_vec()));
+        Ok(self)
+    }
+
+    /// Names of the profiles defined, in the order they were.
+    pub fn profiles(&self) -> Vec<&str> {
+        self.profiles
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect()
+    }
+
+    // Leave out the plugins that are not in the profile `name`.
+    pub(crate) fn select_profile(&mut self, name: &str) -> Result<(), EngineError> {
+        let Some((_, selected)) = self.profiles.iter().find(|(profile, _)| profile == name) else {
+            return Err(EngineError::UnknownProfile {
+                profile: name.to_string(),
+                profiles: self.profiles().into_iter().map(str::to_string).collect(),
+            });
+        };
+        let selected = selected.clone();
+        info!("Starting the plugins {:?} of profile {:?}", selected, name);
+        let left_out_ids: HashSet<i32> = self
+            .plugin_ids()
+            .into_iter()
+            .filter(|plugin_id| !selected.contains(plugin_id))
+            .collect();
+        self.leave_out(&left_out_ids, &format!("profile {:?}", name));
+        Ok(())
+    }
+
+    // Apply the `[[plugin]]` entries of a configuration file to the plugins run by the engine of
+    // their name: leave out those disabled, subscribe the others to the event types of their
+    // entry, if it has any, instead of their own, and give them the settings of their entry that
+    // the code did not give them already.
+    pub(crate) fn apply_entries(&mut self, entries: &[PluginEntry]) -> Result<(), EngineError> {
+        let mut names: Vec<String> = Vec::new();
+        for plugin in &self.plugins {
+            if !names.iter().any(|name| name == plugin.plugin.name()) {
+                names.push(plugin.plugin.name().to_string());
+            }
+        }
+        if let Some(entry) = entries.iter().find(|entry| !names.contains(&entry.name)) {
+            return Err(EngineError::UnknownPluginName {
+                name: entry.name.clone(),
+                names,
+            });
+        }
+        self.plugins = std::mem::take(&mut self.plugins)
+            .into_iter()
+            .map(|mut plugin| {
+                let Some(entry) = entries.iter().find(|e| e.name == plugin.plugin.name()) else {
+                    return plugin;
+                };
+                plugin.settings =
+                    std::mem::take(&mut plugin.settings).with_defaults(&entry.settings);
+                match &entry.subscriptions {
+                    Some(subscriptions) => plugin.resubscribed(subscriptions),
+                    None => plugin,
+                }
+            })
+            .collect();
+        let disabled: HashSet<i32> = self
+            .plugins
+            .iter()
+            .filter(|p| {
+                entries
+                    .iter()
+                    .any(|entry| !entry.enabled && entry.name == p.plugin.name())
+            })
+            .map(|p| p.plugin.id())
+            .collect();
+        if !disabled.is_empty() {
+            info!(
+                "Leaving out the plugins {:?}, disabled in the configuration file",
+                disabled
+            );
+            self.leave_out(&disabled, "the configuration file");
+        }
+        Ok(())
+    }
+
+    // Leave out the plugins `left_out_ids`, left out of `what` (e.g., a profile), dropping the
+    // dependencies of the others on them.
+    fn leave_out(&mut self, left_out_ids: &HashSet<i32>, what: &str) {
+        let (left_out, kept): (Vec<_>, Vec<_>) = self
+            .plugins
+            .drain(..)
+            .partition(|p| left_out_ids.contains(&p.plugin.id()));
+        self.plugins = kept;
+        self.external_plugins
+            .retain(|p| !left_out_ids.contains(&p.plugin_id));
+
+        let published = |plugins: &[PluginConfig]| -> HashSet<String> {
+            let publications = plugins.iter().flat_map(|p| p.plugin.publications());
+            publications
+                .map(|event_type| event_type.to_string())
+                .collect()
+        };
+        let published_left_out = published(&left_out);
+        let published_kept = published(&self.plugins);
+        let names = |plugins: &[PluginConfig]| -> HashSet<String> {
+            plugins
+                .iter()
+                .map(|p| p.plugin.name().to_string())
+                .collect()
+        };
+        let left_out_names = &names(&left_out) - &names(&self.plugins);
+        for plugin in &mut self.plugins {
+            let plugin_id = plugin.plugin.id();
+            for event_type in plugin.plugin.subscriptions() {
+                if published_left_out.contains(*event_type) && !published_kept.contains(*event_type)
+                {
+                    warn!(
+                        plugin_id;
+                        "plugin {} ({}) subscribes to {}, which only plugins left out of {} publish",
+                        plugin_id,
+                        plugin.plugin.name(),
+                        event_type,
+                        what
+                    );
+                }
+            }
+            plugin.depends_on.retain(|dependency| {
+                let left_out = match dependency {
+                    Dependency::Id(id) => left_out_ids.contains(id),
+                    Dependency::Name(dependency_name) => left_out_names.contains(dependency_name),
+                };
+                if left_out {
+                    warn!(
+                        plugin_id;
+                        "plugin {} no longer depends on {}, left out of {}",
+                        plugin_id, dependency, what
+                    );
+                }
+                !left_out
+            });
+        }
+    }
+
+    // The ids of the plugins run by the engine and of the required external plugins, in the
+    // order they start: in waves, the plugins of each depending only on those of the waves
+    // before it, in the order they were registered.
+    pub(crate) fn startup_waves(&self) -> Result<Vec<Vec<i32>>, EngineError> {
+        let required_external = self
+            .external_plugins
+            .iter()
+            .filter(|p| p.required)
+            .map(|p| p.plugin_id);
+        let node_ids: Vec<i32> = self
+            .plugins
+            .iter()
+            .map(|p| p.plugin.id())
+            .chain(required_external)
+            .collect();
+        // the ids each plugin depends on; external plugins depend on none
+        let mut pending = Vec::with_capacity(node_ids.len());
+        for plugin_id in &node_ids {
+            let dependencies = match self.plugins.iter().find(|p| p.plugin.id() == *plugin_id) {
+                Some(plugin) => self.resolve(*plugin_id, &plugin.depends_on, &node_ids)?,
+                None => Vec::new(),
+            };
+            pending.push((*plugin_id, dependencies));
+        }
+        let mut started = HashSet::new();
+        let mut waves = Vec::new();
+        while !pending.is_empty() {
+            let (wave, rest): (Vec<_>, Vec<_>) = pending
+                .into_iter()
+                .partition(|(_, dependencies)| dependencies.iter().all(|d| started.contains(d)));
+            if wave.is_empty() {
+                return Err(self.cycle(&rest));
+            }
+            let wave: Vec<i32> = wave.into_iter().map(|(plugin_id, _)| plugin_id).collect();
+            started.extend(wave.iter().copied());
+            waves.push(wave);
+            pending = rest;
+        }
+        Ok(waves)
+    }
+
+    // The ids of the plugins among `node_ids` that the plugin `plugin_id` depends on.
+    fn resolve(
+        &self,
+        plugin_id: i32,
+        dependencies: &[Dependency],
+        node_ids: &[i32],
+    ) -> Result<Vec<i32>, EngineError> {
+        let mut resolved = Vec::new();
+        for dependency in dependencies {
+            let ids: Vec<i32> = match dependency {
+                Dependency::Id(id) => node_ids.iter().filter(|n| *n == id).copied().collect(),
+                Dependency::Name(name) => self
+                    .plugins
+                    .iter()
+                    .filter(|p| p.plugin.name() == name)
+                    .map(|p| p.plugin.id())
+                    .collect(),
+            };
+            if ids.is_empty() {
+                return Err(EngineError::UnknownDependency {
+                    plugin_id,
+                    dependency: dependency.to_string(),
+                });
+            }
+            resolved.extend(ids);
+        }
+        Ok(resolved)
+    }
+
+    // The error naming a cycle among the plugins that cannot start, each of whom depends on
+    // at least one of the others.
+    fn cycle(&self, blocked: &[(i32, Vec<i32>)]) -> EngineError {
+        let blocked_ids: HashSet<i32> = blocked.iter().map(|(plugin_id, _)| *plugin_id).collect();
+        let next = |plugin_id: i32| {
+            blocked
+                .iter()
+                .find(|(id, _)| *id == plugin_id)
+                .and_then(|(_, dependencies)| dependencies.iter().find(|d| blocked_ids.contains(d)))
+                .copied()
+        };
+        // following the dependencies from any blocked plugin comes back to one already seen
+        let mut path = vec![blocked[0].0];
+        let start = loop {
+            let dependency =
+                next(*path.last().unwrap()).expect("blocked plugins depend on another");
+            if let Some(start) = path.iter().position(|id| *id == dependency) {
+                break start;
+            }
+            path.push(dependency);
+        };
+        let describe = |plugin_id: &i32| {
+            let name = self
+                .plugins
+                .iter()
+                .find(|p| p.plugin.id() == *plugin_id)
+                .map(|p| p.plugin.name())
+                .unwrap_or("external");
+            format!("{} ({})", name, plugin_id)
+        };
+        EngineError::DependencyCycle {
+            cycle: path[start..].iter().map(describe).collect(),
+        }
+    }
+
+    /// Ids of all registered plugins, those run by the engine first.
+    pub fn plugin_ids(&self) -> Vec<i32> {
+        self.plugins
+            .iter()
+            .map(|p| p.plugin.id())
+            .chain(self.external_plugins.iter().map(|p| p.plugin_id))
+            .collect()
+    }
+
+    /// Ids of the registered external plugins.
+    pub fn external_plugin_ids(&self) -> Vec<i32> {
+        self.external_plugins.iter().map(|p| p.plugin_id).collect()
+    }
+
+    pub fn is_external(&self, plugin_id: i32) -> bool {
+        self.external_plugins
+            .iter()
+            .any(|p| p.plugin_id == plugin_id)
+    }
+
+    // Check the ids of all the plugins at once, before the engine creates any socket for them:
+    // two plugins sharing an id would sync on the same endpoint and never get through it.
+    pub(crate) fn check_ids(&self) -> Result<(), EngineError> {
+        let internal: Vec<i32> = self.plugins.iter().map(|p| p.plugin.id()).collect();
+        check_plugin_ids(&internal, &self.external_plugin_ids())
+    }
+
+    fn check_unique(&self, plugin_id: i32) -> Result<(), EngineError> {
+        if self.plugin_ids().contains(&plugin_id) {
+            return Err(EngineError::DuplicatePluginId { plugin_id });
+        }
+        Ok(())
+    }
+}
+
+// Fail with the problems with the ids of the plugins `internal`, run by the engine, and
+// `external`: each offending id is named once for each of its problems, in the order of the
+// plugins.
+pub(crate) fn check_plugin_ids(internal: &[i32], external: &[i32]) -> Result<(), EngineError> {
+    let mut problems = Vec::new();
+    let all = internal
+        .iter()
+        .map(|id| (*id, false))
+        .chain(external.iter().map(|id| (*id, true)));
+    for (i, (plugin_id, is_external)) in all.clone().enumerate() {
+        let seen = || all.clone().take(i);
+        let problem = if plugin_id < 0 {
+            InvalidPluginId::Negative { plugin_id }
+        } else if RESERVED_PLUGIN_IDS.contains(&plugin_id) {
+            InvalidPluginId::Reserved { plugin_id }
+        } else if seen().any(|seen| seen == (plugin_id, is_external)) {
+            InvalidPluginId::Duplicate { plugin_id }
+        } else if seen().any(|(seen, _)| seen == plugin_id) {
+            InvalidPluginId::InternalAndExternal { plugin_id }
+        } else {
+            continue;
+        };
+        if !problems.contains(&problem) {
+            problems.push(problem);
+        }
+    }
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(EngineError::InvalidPluginConfig { problems })
+    }
+}
+
+/// The image pipeline: a plugin that generates new images (0), one that scores them (1) and one
+/// that stores or deletes them depending on the score (2), plus the Python observer (3). Each
+/// plugin depends on the one handling its events, so that none is published before its
+/// subscriber runs.
+pub fn default_plugins() -> PluginRegistry {
+    let mut plugins = PluginRegistry::new();
+    plugins
+        .register_plugin(Box::new(NewImagePlugin::new(0)))
+        .and_then(|p| p.register_plugin(Box::new(ImageScorePlugin::new(1))))
+        .and_then(|p| p.register_plugin(Box::new(ImageStorePlugin::new(2))))
+        .and_then(|p| p.register_external(3))
+        .and_then(|p| p.depends_on(0, &[1]))
+        .and_then(|p| p.depends_on(1, &[2]))
+        .expect("default plugin ids are unique and registered");
+    plugins
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::new_image_plugin;
+
+    #[test]
+    fn test_default_plugins() {
+        let plugins = default_plugins();
+        assert_eq!(plugins.plugin_ids(), vec![0, 1, 2, 3]);
+        assert!(plugins.is_external(3));
+        assert!(!plugins.is_external(0));
+    }
+
+    #[test]
+    fn test_profiles_leave_the_other_plugins_out() {
+        let mut plugins = default_plugins();
+        plugins
+            .profile("scoring", &[1])
+            .unwrap()
+            .profile("observed", &[0, 1, 3])
+            .unwrap();
+        assert!(matches!(
+            plugins.profile("missing", &[0, 7]),
+            Err(EngineError::UnknownPluginId { plugin_id: 7 })
+        ));
+        assert_eq!(plugins.profiles(), ["scoring", "observed"]);
+
+        let mut scoring = default_plugins();
+        scoring.profile("scoring", &[1]).unwrap();
+        scoring.select_profile("scoring").unwrap();
+        assert_eq!(scoring.plugin_ids(), vec![1]);
+        // its dependency on the store plugin, left out, is dropped
+        assert!(scoring.plugins[0].depends_on.is_empty());
+        assert_eq!(scoring.startup_waves().unwrap(), vec![vec![1]]);
+
+        plugins.select_profile("observed").unwrap();
+        assert_eq!(plugins.plugin_ids(), vec![0, 1, 3]);
+        assert_eq!(plugins.plugins[0].depends_on, vec![Dependency::Id(1)]);
+        match plugins.select_profile("full") {
+            Err(EngineError::UnknownProfile { profile, profiles }) => {
+                assert_eq!(profile, "full");
+                assert_eq!(profiles, ["scoring", "observed"]);
+            }
+            result => panic!("expected an UnknownProfile error, got {:?}", result.err()),
+        }
+    }
+
+    #[test]
+    fn test_config_entries_apply_to_the_plugins_of_their_name() {
+        let policy = RestartPolicy::Always {
+            max_retries: 1,
+            backoff: Duration::from_millis(10),
+        };
+        let mut plugins = default_plugins();
+        plugins
+            .register_restartable(policy, || Box::new(ImageScorePlugin::new(5)))
+            .unwrap()
+            .settings(1, PluginSettings::new().set("threshold", 0.9))
+            .unwrap();
+        let mut score = PluginEntry::new("image-score");
+        score.subscriptions = Some(vec!["ImageStoredEvent".to_string()]);
+        score.settings = PluginSettings::new().set("threshold", 0.5).set("labels", 3);
+        let mut store = PluginEntry::new("image-store");
+        store.enabled = false;
+        plugins
+            .apply_entries(&[score, store, PluginEntry::new("new-image")])
+            .unwrap();
+
+        // the store is left out, with the dependency of the scoring plugin on it
+        assert_eq!(plugins.plugin_ids(), vec![0, 1, 5, 3]);
+        assert!(plugins.plugins[1].depends_on.is_empty());
+        // an entry without subscriptions leaves those of its plugins
+        assert_eq!(
+            plugins.plugins[0].plugin.subscriptions(),
+            NewImagePlugin::new(0).subscriptions()
+        );
+        for scoring in &plugins.plugins[1..] {
+            assert_eq!(scoring.plugin.name(), "image-score");
+            assert_eq!(scoring.plugin.subscriptions(), ["ImageStoredEvent"]);
+        }
+        // including the instances of a restartable plugin
+        let restarted = (plugins.plugins[2].factory.as_ref().unwrap())();
+        assert_eq!(restarted.subscriptions(), ["ImageStoredEvent"]);
+        // the settings given in code win
+        let settings = &plugins.plugins[1].settings;
+        assert_eq!(settings.get_f64("threshold").unwrap(), 0.9);
+        assert_eq!(settings.get_i64("labels").unwrap(), 3);
+        assert_eq!(
+            plugins.plugins[2].settings.get_f64("threshold").unwrap(),
+            0.5
+        );
+
+        match default_plugins().apply_entries(&[PluginEntry::new("image-scorer")]) {
+            Err(e @ EngineError::UnknownPluginName { .. }) => assert_eq!(
+                e.to_string(),
+                "no plugin run by the engine is named \"image-scorer\" (plugins: [\"new-image\", \
+                 \"image-score\", \"image-store\"])"
+            ),
+            result => panic!(
+                "expected an UnknownPluginName error, got {:?}",
+                result.err()
+            ),
+        }
+    }
+
+    #[test]
+    fn test_register_restartable() {
+        let mut plugins = PluginRegistry::new();
+        let policy = RestartPolicy::Always {
+            max_retries: 3,
+            backoff: Duration::from_millis(10),
+        };
+        plugins
+            .register_restartable(policy, || Box::new(NewImagePlugin::new(0)))
+            .unwrap();
+        assert_eq!(plugins.plugin_ids(), vec![0]);
+        assert_eq!(plugins.plugins[0].restart_policy, policy);
+        assert!(matches!(
+            plugins.register_restartable(policy, || Box::new(NewImagePlugin::new(0))),
+            Err(EngineError::DuplicatePluginId { plugin_id: 0 })
+        ));
+    }
+
+    #[test]
+    fn test_register_pool() {
+        let mut plugins = PluginRegistry::new();
+        plugins
+            .register_pool(4, || Box::new(NewImagePlugin::new(0)))
+            .unwrap();
+        assert_eq!(plugins.plugins[0].workers, 4);
+        assert!(matches!(
+            plugins.register_pool(0, || Box::new(NewImagePlugin::new(1))),
+            Err(EngineError::PluginSpawn { plugin_id: 1, .. })
+        ));
+        assert!(plugins
+            .register_pool(2, || Box::new(NewImagePlugin::new(0)))
+            .is_err());
+    }
+
+    #[test]
+    fn test_hwm_of_unknown_plugin_rejected() {
+        let mut plugins = default_plugins();
+        plugins.hwm(2, Hwm::new(10)).unwrap();
+        assert_eq!(plugins.plugins[2].hwm, Hwm::new(10));
+        assert_eq!(plugins.plugins[1].hwm, Hwm::default());
+        // the external plugin's sockets are not the engine's to configure
+        assert!(matches!(
+            plugins.hwm(3, Hwm::new(10)),
+            Err(EngineError::UnknownPluginId { plugin_id: 3 })
+        ));
+    }
+
+    #[test]
+    fn test_stack_size_of_unknown_plugin_rejected() {
+        let mut plugins = default_plugins();
+        plugins.stack_size(1, 8 << 20).unwrap();
+        assert_eq!(plugins.plugins[1].stack_size, Some(8 << 20));
+        assert_eq!(plugins.plugins[0].stack_size, None);
+        assert!(matches!(
+            plugins.stack_size(3, 8 << 20),
+            Err(EngineError::UnknownPluginId { plugin_id: 3 })
+        ));
+    }
+
+    #[test]
+    fn test_settings_of_unknown_plugin_rejected() {
+        let mut plugins = default_plugins();
+        let settings = PluginSettings::new().set("threshold", 0.5);
+        plugins.settings(1, settings.clone()).unwrap();
+        assert_eq!(plugins.plugins[1].settings, settings);
+        assert!(plugins.plugins[0].settings.is_empty());
+        assert!(matches!(
+            plugins.settings(3, settings),
+            Err(EngineError::UnknownPluginId { plugin_id: 3 })
+        ));
+    }
+
+    #[test]
+    fn test_optional_of_unknown_plugin_rejected() {
+        let mut plugins = default_plugins();
+        plugins.optional(1, true).unwrap();
+        assert!(plugins.plugins[1].optional);
+        assert!(!plugins.plugins[0].optional);
+        plugins.optional(1, false).unwrap();
+        assert!(!plugins.plugins[1].optional);
+        assert!(matches!(
+            plugins.optional(3, true),
+            Err(EngineError::UnknownPluginId { plugin_id: 3 })
+        ));
+    }
+
+    #[test]
+    fn test_startup_waves_follow_dependencies() {
+        let mut plugins = default_plugins();
+        assert_eq!(
+            plugins.startup_waves().unwrap(),
+            vec![vec![2, 3], vec![1], vec![0]]
+        );
+        plugins.register(4, &[], new_image_plugin::start).unwrap();
+        plugins.register(5, &[], new_image_plugin::start).unwrap();
+        plugins
+            .depends_on(4, &["image-store"])
+            .unwrap()
+            .depends_on(4, &[3])
+            .unwrap()
+            .depends_on(5, &[0, 4])
+            .unwrap();
+        assert_eq!(
+            plugins.startup_waves().unwrap(),
+            vec![vec![2, 3], vec![1, 4], vec![0], vec![5]]
+        );
+
+        // independent plugins all start in the first wave
+        let mut plugins = PluginRegistry::new();
+        plugins
+            .register(0, &[], new_image_plugin::start)
+            .unwrap()
+            .register(1, &[], new_image_plugin::start)
+            .unwrap();
+        assert_eq!(plugins.startup_waves().unwrap(), vec![vec![0, 1]]);
+        assert!(matches!(
+            plugins.depends_on(7, &[0]),
+            Err(EngineError::UnknownPluginId { plugin_id: 7 })
+        ));
+    }
+
+    #[test]
+    fn test_dependency_cycles_and_unknown_dependencies_rejected() {
+        let mut plugins = default_plugins();
+        plugins.depends_on(2, &["new-image"]).unwrap();
+        let error = plugins.startup_waves().unwrap_err();
+        match &error {
+            EngineError::DependencyCycle { cycle } => assert_eq!(
+                cycle,
+                &["new-image (0)", "image-score (1)", "image-store (2)"]
+            ),
+            e => panic!("expected a dependency cycle, got: {}", e),
+        }
+        assert_eq!(
+            error.to_string(),
+            "plugins depend on each other in a cycle: new-image (0) -> image-score (1) -> \
+             image-store (2) -> new-image (0)"
+        );
+
+        let mut plugins = default_plugins();
+        plugins.depends_on(1, &[1]).unwrap();
+        assert!(matches!(
+            plugins.startup_waves(),
+            Err(EngineError::DependencyCycle { cycle }) if cycle == ["image-score (1)"]
+        ));
+
+        let mut plugins = PluginRegistry::new();
+        plugins
+            .register(0, &[], new_image_plugin::start)
+            .unwrap()
+            .register_optional_external(1)
+            .unwrap()
+            .depends_on(0, &[1])
+            .unwrap();
+        assert!(matches!(
+            plugins.startup_waves(),
+            Err(EngineError::UnknownDependency { plugin_id: 0, dependency }) if dependency == "plugin 1"
+        ));
+        plugins.plugins[0].depends_on = vec!["image-score".into()];
+        assert!(matches!(
+            plugins.startup_waves(),
+            Err(EngineError::UnknownDependency { plugin_id: 0, dependency })
+                if dependency == "plugin \"image-score\""
+        ));
+    }
+
+    #[test]
+    fn test_duplicate_plugin_id_rejected() {
+        let mut plugins = default_plugins();
+        match plugins.register(1, &[], new_image_plugin::start) {
+            Err(EngineError::DuplicatePluginId { plugin_id }) => assert_eq!(plugin_id, 1),
+            Err(e) => panic!("expected a duplicate id error, got: {}", e),
+            Ok(_) => panic!("registered a second plugin with id 1"),
+        }
+        assert!(matches!(
+            plugins.register_external(3),
+            Err(EngineError::DuplicatePluginId { plugin_id: 3 })
+        ));
+    }
+
+    #[test]
+    fn test_duplicate_ids_are_invalid() {
+        let mut plugins = PluginRegistry::new();
+        plugins.register(1, &[], new_image_plugin::start).unwrap();
+        let mut others = PluginRegistry::new();
+        others.register(1, &[], new_image_plugin::start).unwrap();
+        others.register_external(4).unwrap();
+        // registries assembled from others are only checked as a whole
+        plugins.plugins.extend(others.plugins);
+        plugins.external_plugins.extend(others.external_plugins);
+        assert!(plugins.register_external(4).is_err());
+        plugins.external_plugins.push(ExternalPluginConfig {
+            plugin_id: 4,
+            required: false,
+        });
+        assert!(matches!(
+            plugins.check_ids(),
+            Err(EngineError::InvalidPluginConfig { problems }) if problems == [
+                InvalidPluginId::Duplicate { plugin_id: 1 },
+                InvalidPluginId::Duplicate { plugin_id: 4 },
+            ]
+        ));
+    }
+
+    #[test]
+    fn test_ids_both_internal_and_external_are_invalid() {
+        let mut plugins = PluginRegistry::new();
+        plugins.register(2, &[], new_image_plugin::start).unwrap();
+        plugins.external_plugins.push(ExternalPluginConfig {
+            plugin_id: 2,
+            required: true,
+        });
+        let error = plugins.check_ids().unwrap_err();
+        assert!(matches!(
+            &error,
+            EngineError::InvalidPluginConfig { problems }
+                if problems == &[InvalidPluginId::InternalAndExternal { plugin_id: 2 }]
+        ));
+        assert_eq!(
+            error.to_string(),
+            "invalid plugin ids: 2 (registered both as run by the engine and external)"
+        );
+    }
+
+    #[test]
+    fn test_negative_and_reserved_ids_are_invalid() {
+        let mut plugins = PluginRegistry::new();
+        plugins
+            .register(-1, &[], new_image_plugin::start)
+            .unwrap()
+            .register(0, &[], new_image_plugin::start)
+            .unwrap()
+            .register_external(*RESERVED_PLUGIN_IDS.start())
+            .unwrap();
+        assert!(matches!(
+            plugins.check_ids(),
+            Err(EngineError::InvalidPluginConfig { problems }) if problems == [
+                InvalidPluginId::Negative { plugin_id: -1 },
+                InvalidPluginId::Reserved { plugin_id: 1_000_000 },
+            ]
+        ));
+        assert!(default_plugins().check_ids().is_ok());
+        assert!(check_plugin_ids(&[i32::MAX], &[]).is_err());
+        assert!(check_plugin_ids(&[RESERVED_PLUGIN_IDS.start() - 1], &[]).is_ok());
+    }
+}
@@ -0,0 +1,319 @@
+//! Prometheus metrics exporter (feature `prometheus`).
+//! An engine started with `EventEngineBuilder::metrics_addr` serves its counters on
+//! `http://<metrics addr>/metrics`, in the Prometheus text format:
+//!
+//! - `plyoreacto_events_proxied_total{event_type}`: events forwarded by the proxy
+//! - `plyoreacto_plugin_events_received_total{plugin_id}` and
+//!   `plyoreacto_plugin_events_published_total{plugin_id}`: events received with
+//!   `PluginContext::next_event` and published by the plugins the engine started
+//! - `plyoreacto_plugin_restarts_total{plugin_id}`: restarts of those plugins
+//! - `plyoreacto_plugin_events_dropped_total{plugin_id}`: events they did not receive, as
+//!   detected by `PluginContext::dropped_events`
+//! - `plyoreacto_sync_duration_seconds`: how long the plugins took to sync at startup
+//!
+//! The proxy sends a copy of every event it forwards to a capture socket, read by the exporter
+//! thread, and the plugin counters are atomics, so counting takes no lock on the path of the
+//! events. The capture socket drops copies rather than hold the proxy up, which the counts of
+//! proxied events would then miss.
+//!
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use log::{error, info};
+use tiny_http::{Header, Response, Server};
+use zmq::Socket;
+
+use crate::event_engine::{
+    bind, connect, create_socket, subscribe_to_capture, EngineConfig, EngineError,
+};
+use crate::events::{
+    event_type_names, get_event_type_from_bytes, recv_event_frames, retry_on_eintr,
+};
+use crate::plugin_registry::PluginRegistry;
+
+// How long the exporter waits for captured events before it checks for scrapes.
+const SCRAPE_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+// Control socket and thread of the exporter.
+pub(crate) type Exporter = (Socket, JoinHandle<()>);
+
+/// The counters of a plugin started by the engine.
+#[derive(Debug, Default)]
+pub(crate) struct PluginCounters {
+    pub(crate) received: AtomicU64,
+    pub(crate) published: AtomicU64,
+    pub(crate) restarts: AtomicU64,
+    pub(crate) dropped: AtomicU64,
+}
+
+// One of the counters of a plugin.
+type PluginCounter = fn(&PluginCounters) -> &AtomicU64;
+
+// The counters of an engine, shared with its plugin threads.
+#[derive(Debug)]
+pub(crate) struct EngineMetrics {
+    // events forwarded by the proxy, in the order of `event_type_names()`
+    proxied: Vec<AtomicU64>,
+    sync_duration_us: AtomicU64,
+    plugins: BTreeMap<i32, Arc<PluginCounters>>,
+}
+
+impl EngineMetrics {
+    // Counters for the plugins of `plugins` that the engine starts.
+    pub(crate) fn new(plugins: &PluginRegistry) -> Self {
+        EngineMetrics {
+            proxied: event_type_names()
+                .iter()
+                .map(|_| AtomicU64::new(0))
+                .collect(),
+            sync_duration_us: AtomicU64::new(0),
+            plugins: plugins
+                .plugins
+                .iter()
+                .map(|p| (p.plugin.id(), Arc::default()))
+                .collect(),
+        }
+    }
+
+    // The counters of the plugin `plugin_id`, which the engine started.
+    pub(crate) fn plugin(&self, plugin_id: i32) -> Option<Arc<PluginCounters>> {
+        self.plugins.get(&plugin_id).cloned()
+    }
+
+    pub(crate) fn set_sync_duration(&self, duration: Duration) {
+        self.sync_duration_us
+            .store(duration.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    // Count the event with header frame `header` forwarded by the proxy.
+    fn record_proxied(&self, header: &[u8]) {
+        let index = get_event_type_from_bytes(header)
+            .and_then(|event_type| event_type_names().iter().position(|n| *n == event_type));
+        if let Some(index) = index {
+            self.proxied[index].fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    // The counters in the Prometheus text format.
+    fn render(&self) -> String {
+        let mut text = String::new();
+        metric_header(
+            &mut text,
+            "plyoreacto_events_proxied_total",
+            "Events forwarded by the engine's proxy.",
+            "counter",
+        );
+        for (event_type, count) in event_type_names().iter().zip(&self.proxied) {
+            let _ = writeln!(
+                text,
+                "plyoreacto_events_proxied_total{{event_type=\"{}\"}} {}",
+                event_type,
+                count.load(Ordering::Relaxed)
+            );
+        }
+        let plugin_metrics: [(&str, &str, PluginCounter); 4] = [
+            (
+                "plyoreacto_plugin_events_received_total",
+                "Events received by a plugin started by the engine.",
+                |c| &c.received,
+            ),
+            (
+                "plyoreacto_plugin_events_published_total",
+                "Events published by a plugin started by the engine.",
+                |c| &c.published,
+            ),
+            (
+                "plyoreacto_plugin_restarts_total",
+                "Restarts of a plugin started by the engine.",
+                |c| &c.restarts,
+            ),
+            (
+                "plyoreacto_plugin_events_dropped_total",
+                "Events a plugin started by the engine did not receive.",
+                |c| &c.dropped,
+            ),
+        ];
+        for (name, help, counter) in plugin_metrics {
+            metric_header(&mut text, name, help, "counter");
+            for (plugin_id, counters) in &self.plugins {
+                let count = counter(counters).load(Ordering::Relaxed);
+                let _ = writeln!(text, "{}{{plugin_id=\"{}\"}} {}", name, plugin_id, count);
+            }
+        }
+        metric_header(
+            &mut text,
+            "plyoreacto_sync_duration_seconds",
+            "How long the plugins took to sync with the engine at startup.",
+            "gauge",
+        );
+        let sync_duration_us = self.sync_duration_us.load(Ordering::Relaxed);
+        let _ = writeln!(
+            text,
+            "plyoreacto_sync_duration_seconds {}",
+            sync_duration_us as f64 / 1_000_000.0
+        );
+        text
+    }
+}
+
+fn metric_header(text: &mut String, name: &str, help: &str, metric_type: &str) {
+    let _ = writeln!(text, "# HELP {} {}", name, help);
+    let _ = writeln!(text, "# TYPE {} {}", name, metric_type);
+}
+
+// Count the events captured on `capture` and answer the scrapes received by `server`, until
+// TERMINATE is received on `control`.
+fn export(server: Server, capture: Socket, control: Socket, metrics: Arc<EngineMetrics>) {
+    loop {
+        let mut items = [
+            capture.as_poll_item(zmq::POLLIN),
+            control.as_poll_item(zmq::POLLIN),
+        ];
+        let timeout = SCRAPE_POLL_INTERVAL.as_millis() as i64;
+        if retry_on_eintr(|| zmq::poll(&mut items, timeout)).is_err() || items[1].is_readable() {
+            return;
+        }
+        if items[0].is_readable() {
+            // take every event captured so far, so that a burst is counted in one go
+            while let Ok(frames) = recv_event_frames(&capture, zmq::DONTWAIT) {
+                if let Some(header) = frames.first() {
+                    metrics.record_proxied(header);
+                }
+            }
+        }
+        while let Ok(Some(request)) = server.try_recv() {
+            let response = if request.url() == "/metrics" {
+                let content_type = Header::from_bytes("Content-Type", "text/plain; version=0.0.4")
+                    .expect("the content type header is valid");
+                Response::from_string(metrics.render()).with_header(content_type)
+            } else {
+                Response::from_string("not found\n").with_status_code(404)
+            };
+            if let Err(e) = request.respond(response) {
+                error!("Engine could not answer metrics scrape: {}", e);
+            }
+        }
+    }
+}
+
+// Start the exporter thread, if the engine has a metrics address, reading the events the proxy
+// captures.
+pub(crate) fn start_exporter(
+    context: &zmq::Context,
+    config: &EngineConfig,
+    metrics: &Arc<EngineMetrics>,
+) -> Result<Option<Exporter>, EngineError> {
+    let addr = match &config.metrics_addr {
+        Some(addr) => addr,
+        None => return Ok(None),
+    };
+    let server = Server::http(addr.as_str()).map_err(|e| EngineError::MetricsServer {
+        addr: addr.clone(),
+        reason: e.to_string(),
+    })?;
+
+    let captured = subscribe_to_capture(context, config, "captured events")?;
+
+    let control_endpoint = format!("inproc://{}-exporter-control", config.outgoing_inproc);
+    let exporter_control = create_socket(context, config, zmq::PAIR, "exporter control")?;
+    bind(&exporter_control, &control_endpoint)?;
+    let control = create_socket(context, config, zmq::PAIR, "exporter")?;
+    connect(&control, &control_endpoint)?;
+    info!("Engine serving metrics on http://{}/metrics", addr);
+    let metrics = Arc::clone(metrics);
+    let exporter_thread =
+        thread::spawn(move || export(server, captured, exporter_control, metrics));
+    Ok(Some((control, exporter_thread)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::event_engine::EventEngineBuilder;
+    use crate::image_score_plugin::{FixedScorer, ImageScorePlugin};
+    use crate::image_store_plugin::ImageStorePlugin;
+    use crate::new_image_plugin::NewImagePlugin;
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+    use std::time::Instant;
+
+    fn scrape(addr: &str) -> String {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream
+            .write_all(b"GET /metrics HTTP/1.0\r\nHost: localhost\r\n\r\n")
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn test_pipeline_counters_are_scraped() {
+        let labrador = FixedScorer::new(vec![("labrador".to_string(), 0.9)]);
+        let mut plugins = PluginRegistry::new();
+        plugins
+            .register_plugin(Box::new(NewImagePlugin::new(0)))
+            .unwrap()
+            .register_plugin(Box::new(ImageScorePlugin::with_scorer(
+                1,
+                Box::new(labrador),
+            )))
+            .unwrap()
+            .register_plugin(Box::new(ImageStorePlugin::new(2)))
+            .unwrap();
+        let engine = EventEngineBuilder::new()
+            .incoming_port(2659)
+            .outgoing_port(2660)
+            .sync_port(2600)
+            .metrics_addr("127.0.0.1:2680")
+            .plugins(plugins)
+            .start()
+            .unwrap();
+        let results = engine.wait_for_plugins(Duration::from_secs(10));
+        assert_eq!(results.len(), 3, "pipeline did not finish: {:?}", results);
+
+        // the exporter counts the captured events on its own time
+        let expected = "plyoreacto_events_proxied_total{event_type=\"ImageStoredEvent\"} 5";
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let mut metrics = scrape("127.0.0.1:2680");
+        while !metrics.contains(expected) && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(20));
+            metrics = scrape("127.0.0.1:2680");
+        }
+        engine.shutdown().unwrap();
+
+        assert!(metrics.starts_with("HTTP/1.0 200 OK"), "{}", metrics);
+        for line in [
+            expected,
+            "plyoreacto_events_proxied_total{event_type=\"NewImageEvent\"} 5",
+            "plyoreacto_events_proxied_total{event_type=\"ImageScoredEvent\"} 5",
+            "plyoreacto_plugin_events_published_total{plugin_id=\"0\"} 5",
+            "plyoreacto_plugin_events_received_total{plugin_id=\"1\"} 5",
+            "plyoreacto_plugin_events_published_total{plugin_id=\"1\"} 5",
+            "plyoreacto_plugin_events_published_total{plugin_id=\"2\"} 5",
+            // the store plugin only subscribes to the scores
+            "plyoreacto_plugin_events_received_total{plugin_id=\"2\"} 5",
+            "plyoreacto_plugin_restarts_total{plugin_id=\"1\"} 0",
+            "plyoreacto_plugin_events_dropped_total{plugin_id=\"2\"} 0",
+        ] {
+            assert!(
+                metrics.contains(line),
+                "missing {:?} in:\n{}",
+                line,
+                metrics
+            );
+        }
+        let sync_duration: f64 = metrics
+            .lines()
+            .find_map(|l| l.strip_prefix("plyoreacto_sync_duration_seconds "))
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!(sync_duration > 0.0, "{}", metrics);
+    }
+}
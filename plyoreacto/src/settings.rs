@@ -0,0 +1,283 @@
+//! The configuration values of a plugin, e.g., the directory it reads images from, the
+//! threshold it rejects them under or the root of the storage it keeps them in, given when the
+//! plugin is registered with `PluginRegistry::settings` and read by the plugin once it starts
+//! from `PluginContext::settings`, rather than from globals or environment variables.
+//!
+//! Settings are JSON values by key. The typed getters fail with a `SettingsError` naming the
+//! key for a setting that is missing or of another type; the `_or` getters return a default
+//! for a missing one instead.
+//!
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// The settings of a plugin, by key.
+///
+/// ```
+/// use plyoreacto::settings::PluginSettings;
+///
+/// let settings = PluginSettings::new()
+///     .set("image_dir", "/var/spool/images")
+///     .set("threshold", 0.5);
+/// assert_eq!(settings.get_str("image_dir").unwrap(), "/var/spool/images");
+/// assert_eq!(settings.get_f64_or("threshold", 0.0).unwrap(), 0.5);
+/// assert_eq!(settings.get_i64_or("images", 5).unwrap(), 5);
+/// assert!(settings.get_i64("threshold").is_err());
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct PluginSettings {
+    values: BTreeMap<String, Value>,
+}
+
+/// Why a setting could not be read.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SettingsError {
+    /// No setting `key` was given.
+    Missing { key: String },
+    /// The setting `key` is `found`, which is not `expected` (e.g., "a string").
+    WrongType {
+        key: String,
+        expected: &'static str,
+        found: Value,
+    },
+}
+
+impl fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SettingsError::Missing { key } => write!(f, "missing setting {:?}", key),
+            SettingsError::WrongType {
+                key,
+                expected,
+                found,
+            } => write!(f, "setting {:?} should be {}, got {}", key, expected, found),
+        }
+    }
+}
+
+impl std::error::Error for SettingsError {}
+
+impl PluginSettings {
+    pub fn new() -> Self {
+        PluginSettings::default()
+    }
+
+    /// Set `key` to `value`, replacing any value it had.
+    pub fn set(mut self, key: &str, value: impl Into<Value>) -> Self {
+        self.values.insert(key.to_string(), value.into());
+        self
+    }
+
+    /// These settings, with those of `defaults` they do not set.
+    pub fn with_defaults(mut self, defaults: &PluginSettings) -> Self {
+        for (key, value) in &defaults.values {
+            self.values
+                .entry(key.clone())
+                .or_insert_with(|| value.clone());
+        }
+        self
+    }
+
+    /// Whether `key` is set.
+    pub fn contains(&self, key: &str) -> bool {
+        self.values.contains_key(key)
+    }
+
+    /// The value of `key`, of whatever type, if it is set.
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.values.get(key)
+    }
+
+    /// The keys set, in order.
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.values.keys().map(String::as_str)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// The string `key` is set to.
+    pub fn get_str(&self, key: &str) -> Result<&str, SettingsError> {
+        self.typed(key, "a string", Value::as_str)
+    }
+
+    /// The integer `key` is set to; a number with a fraction is of the wrong type.
+    pub fn get_i64(&self, key: &str) -> Result<i64, SettingsError> {
+        self.typed(key, "an integer", Value::as_i64)
+    }
+
+    /// The number `key` is set to, integers included.
+    pub fn get_f64(&self, key: &str) -> Result<f64, SettingsError> {
+        self.typed(key, "a number", Value::as_f64)
+    }
+
+    /// The path `key` is set to, as a string.
+    pub fn get_path(&self, key: &str) -> Result<PathBuf, SettingsError> {
+        self.typed(key, "a path", |value| value.as_str().map(PathBuf::from))
+    }
+
+    /// Like `get_str`, with `default` if `key` is not set.
+    pub fn get_str_or<'a>(&'a self, key: &str, default: &'a str) -> Result<&'a str, SettingsError> {
+        or_default(self.get_str(key), default)
+    }
+
+    /// Like `get_i64`, with `default` if `key` is not set.
+    pub fn get_i64_or(&self, key: &str, default: i64) -> Result<i64, SettingsError> {
+        or_default(self.get_i64(key), default)
+    }
+
+    /// Like `get_f64`, with `default` if `key` is not set.
+    pub fn get_f64_or(&self, key: &str, default: f64) -> Result<f64, SettingsError> {
+        or_default(self.get_f64(key), default)
+    }
+
+    /// Like `get_path`, with `default` if `key` is not set.
+    pub fn get_path_or(
+        &self,
+        key: &str,
+        default: impl Into<PathBuf>,
+    ) -> Result<PathBuf, SettingsError> {
+        or_default(self.get_path(key), default.into())
+    }
+
+    // The value of `key` as `as_type` reads it, failing for a value it does not, i.e., one that
+    // is not `expected`.
+    fn typed<'a, T>(
+        &'a self,
+        key: &str,
+        expected: &'static str,
+        as_type: impl Fn(&'a Value) -> Option<T>,
+    ) -> Result<T, SettingsError> {
+        let value = self.get(key).ok_or_else(|| SettingsError::Missing {
+            key: key.to_string(),
+        })?;
+        as_type(value).ok_or_else(|| SettingsError::WrongType {
+            key: key.to_string(),
+            expected,
+            found: value.clone(),
+        })
+    }
+}
+
+// `default` for a setting that is missing, the error of one of the wrong type.
+fn or_default<T>(setting: Result<T, SettingsError>, default: T) -> Result<T, SettingsError> {
+    match setting {
+        Err(SettingsError::Missing { .. }) => Ok(default),
+        setting => setting,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    fn settings() -> PluginSettings {
+        PluginSettings::new()
+            .set("image_dir", "/var/spool/images")
+            .set("images", 12)
+            .set("threshold", 0.25)
+            .set("labels", json!(["labrador", "poodle"]))
+    }
+
+    #[test]
+    fn test_settings_present_are_read_as_their_type() {
+        let settings = settings();
+        assert_eq!(settings.get_str("image_dir").unwrap(), "/var/spool/images");
+        assert_eq!(
+            settings.get_path("image_dir").unwrap(),
+            PathBuf::from("/var/spool/images")
+        );
+        assert_eq!(settings.get_i64("images").unwrap(), 12);
+        assert_eq!(settings.get_f64("threshold").unwrap(), 0.25);
+        // an integer is a number too
+        assert_eq!(settings.get_f64("images").unwrap(), 12.0);
+        assert_eq!(settings.get("labels"), Some(&json!(["labrador", "poodle"])));
+        // a default is only used for a missing setting
+        assert_eq!(settings.get_i64_or("images", 5).unwrap(), 12);
+        assert_eq!(
+            settings.get_str_or("image_dir", "images").unwrap(),
+            "/var/spool/images"
+        );
+        assert_eq!(
+            settings.keys().collect::<Vec<_>>(),
+            ["image_dir", "images", "labels", "threshold"]
+        );
+    }
+
+    #[test]
+    fn test_missing_settings_get_their_default() {
+        let settings = settings();
+        assert_eq!(
+            settings.get_i64("retries"),
+            Err(SettingsError::Missing {
+                key: "retries".to_string()
+            })
+        );
+        assert_eq!(
+            settings.get_str("url").unwrap_err().to_string(),
+            "missing setting \"url\""
+        );
+        assert_eq!(settings.get_i64_or("retries", 3).unwrap(), 3);
+        assert_eq!(settings.get_f64_or("ratio", 0.5).unwrap(), 0.5);
+        assert_eq!(
+            settings.get_str_or("url", "http://localhost").unwrap(),
+            "http://localhost"
+        );
+        assert_eq!(
+            settings.get_path_or("storage_root", "images").unwrap(),
+            PathBuf::from("images")
+        );
+        assert!(PluginSettings::new().is_empty());
+    }
+
+    #[test]
+    fn test_settings_of_the_wrong_type_are_errors() {
+        let settings = settings();
+        assert_eq!(
+            settings.get_i64("threshold"),
+            Err(SettingsError::WrongType {
+                key: "threshold".to_string(),
+                expected: "an integer",
+                found: json!(0.25),
+            })
+        );
+        assert_eq!(
+            settings.get_f64("image_dir").unwrap_err().to_string(),
+            "setting \"image_dir\" should be a number, got \"/var/spool/images\""
+        );
+        assert_eq!(
+            settings.get_path("images").unwrap_err().to_string(),
+            "setting \"images\" should be a path, got 12"
+        );
+        // even with a default
+        assert!(settings.get_str_or("labels", "labrador").is_err());
+        assert!(settings.get_path_or("threshold", "images").is_err());
+    }
+
+    #[test]
+    fn test_settings_set_win_over_their_defaults() {
+        let defaults = PluginSettings::new()
+            .set("threshold", 0.5)
+            .set("retries", 3);
+        let settings = settings().with_defaults(&defaults);
+        assert_eq!(settings.get_f64("threshold").unwrap(), 0.25);
+        assert_eq!(settings.get_i64("retries").unwrap(), 3);
+        assert_eq!(settings.get_i64("images").unwrap(), 12);
+    }
+
+    #[test]
+    fn test_settings_round_trip_as_a_json_object() {
+        let settings = settings();
+        let json = serde_json::to_value(&settings).unwrap();
+        assert_eq!(json["images"], json!(12));
+        let read: PluginSettings = serde_json::from_value(json).unwrap();
+        assert_eq!(read, settings);
+    }
+}
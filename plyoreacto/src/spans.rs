@@ -0,0 +1,267 @@
+//! Tracing spans following each chain of events through the plugins (feature `tracing`).
+//! Every event published with `PluginContext::publish` opens a root span named after its event
+//! type (e.g., "NewImageEvent"), with the event's `uuid`, its `correlation_id` and the publishing
+//! `plugin_id` as fields. An event published with `PluginContext::publish_reply` gets a span of
+//! the same kind whose parent is the root span of its chain, found by the correlation id, so the
+//! journey of an image through the score and store plugins shows up as a single tree. A chain
+//! started outside of the process, e.g., by an external plugin, gets its root span when
+//! `PluginContext::next_event` first receives one of its events.
+//!
+//! The crate does not install a subscriber; spans are only recorded once the host does.
+//!
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Mutex, OnceLock};
+
+use tracing::span::Id;
+use tracing::{Level, Span};
+use uuid::Uuid;
+
+use crate::events::EventMeta;
+
+// How many chains keep their root span open for later events to attach to; once a new chain
+// starts beyond that, the root span of the oldest one is closed.
+const MAX_OPEN_CHAINS: usize = 1024;
+
+// The root spans of the chains seen last, by correlation id.
+#[derive(Default)]
+struct Chains {
+    roots: HashMap<Uuid, Span>,
+    // correlation ids, oldest first
+    order: VecDeque<Uuid>,
+}
+
+impl Chains {
+    fn insert(&mut self, correlation_id: Uuid, root: Span) {
+        if self.order.len() == MAX_OPEN_CHAINS {
+            if let Some(oldest) = self.order.pop_front() {
+                self.roots.remove(&oldest);
+            }
+        }
+        self.order.push_back(correlation_id);
+        self.roots.insert(correlation_id, root);
+    }
+}
+
+fn chains() -> &'static Mutex<Chains> {
+    static CHAINS: OnceLock<Mutex<Chains>> = OnceLock::new();
+    CHAINS.get_or_init(Mutex::default)
+}
+
+// The span of the event with envelope `meta`, named after `event_type`, as a child of `parent`
+// or, without one, as a root span. Span names are static, so every event type is listed here.
+fn event_span(event_type: &str, meta: &EventMeta, plugin_id: i32, parent: Option<Id>) -> Span {
+    macro_rules! event_spans {
+        ($($name:literal),*) => {
+            match event_type {
+                $($name => tracing::span!(
+                    parent: parent,
+                    Level::INFO,
+                    $name,
+                    uuid = %meta.event_id,
+                    correlation_id = %meta.correlation_id,
+                    plugin_id
+                ),)*
+                _ => tracing::span!(
+                    parent: parent,
+                    Level::INFO,
+                    "Event",
+                    event_type,
+                    uuid = %meta.event_id,
+                    correlation_id = %meta.correlation_id,
+                    plugin_id
+                ),
+            }
+        };
+    }
+    event_spans!(
+        "NewImageEvent",
+        "ImageScoredEvent",
+        "ImageStoredEvent",
+        "ImageDeletedEvent",
+        "PluginTerminateEvent",
+        "PluginFailedEvent",
+        "PluginRestartedEvent",
+        "ImageScoreFailedEvent",
+        "ImageRejectedEvent",
+        "ImageStoreFailedEvent",
+        "ImageDeletedRequestEvent",
+        "MetricsSnapshotEvent",
+        "WebhookDeliveryFailedEvent",
+        "PluginJoinedEvent",
+        "PluginLeftEvent",
+        "EngineHeartbeatEvent",
+        "PluginHeartbeatEvent",
+        "EventsDroppedEvent",
+        "EventRejectedEvent",
+        "BackpressureEvent",
+        "BackpressureRelievedEvent",
+        "DeadLetterEvent",
+        "SyncProbeEvent",
+        "EngineStartedEvent",
+        "EngineStoppingEvent",
+        "PluginSkippedEvent",
+        "TickEvent"
+    )
+}
+
+// Record the span of an event of type `event_type` that `plugin_id` is about to publish with
+// envelope `meta`: the root span of a new chain, or a child of the root span of its chain.
+pub(crate) fn published(event_type: &str, meta: &EventMeta, plugin_id: i32) {
+    if !tracing::enabled!(Level::INFO) {
+        return;
+    }
+    let mut chains = chains().lock().expect("span chains lock poisoned");
+    let parent = chains
+        .roots
+        .get(&meta.correlation_id)
+        .and_then(|root| root.id());
+    match parent {
+        // the span of a reply only marks when it was published
+        Some(parent) if meta.event_id != meta.correlation_id => {
+            event_span(event_type, meta, plugin_id, Some(parent));
+        }
+        // a new chain, or a reply in a chain whose start was not seen
+        _ => {
+            let root = event_span(event_type, meta, plugin_id, None);
+            chains.insert(meta.correlation_id, root);
+        }
+    }
+}
+
+// Record the root span of the chain of an event of type `event_type` that `plugin_id` received
+// with envelope `meta`, unless the chain already has one.
+pub(crate) fn received(event_type: &str, meta: &EventMeta, plugin_id: i32) {
+    if !tracing::enabled!(Level::INFO) {
+        return;
+    }
+    let mut chains = chains().lock().expect("span chains lock poisoned");
+    if !chains.roots.contains_key(&meta.correlation_id) {
+        let root = event_span(event_type, meta, plugin_id, None);
+        chains.insert(meta.correlation_id, root);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::event_engine::EventEngineBuilder;
+    use crate::image_score_plugin::{FixedScorer, ImageScorePlugin};
+    use crate::image_store_plugin::ImageStorePlugin;
+    use crate::new_image_plugin::NewImagePlugin;
+    use crate::plugin_registry::PluginRegistry;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::time::Duration;
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Record};
+    use tracing::{Event, Metadata, Subscriber};
+
+    // A span as recorded by the capturing subscriber.
+    #[derive(Clone, Debug)]
+    struct CapturedSpan {
+        id: u64,
+        name: &'static str,
+        parent: Option<u64>,
+        fields: HashMap<&'static str, String>,
+    }
+
+    impl Visit for CapturedSpan {
+        fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+            self.fields.insert(field.name(), format!("{:?}", value));
+        }
+    }
+
+    static SPANS: Mutex<Vec<CapturedSpan>> = Mutex::new(Vec::new());
+
+    // Records every span created in the test process, in order.
+    struct CaptureSubscriber {
+        next_id: AtomicU64,
+    }
+
+    impl Subscriber for CaptureSubscriber {
+        fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, span: &Attributes<'_>) -> Id {
+            let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+            let mut captured = CapturedSpan {
+                id,
+                name: span.metadata().name(),
+                parent: span.parent().map(|parent| parent.into_u64()),
+                fields: HashMap::new(),
+            };
+            span.record(&mut captured);
+            SPANS.lock().unwrap().push(captured);
+            Id::from_u64(id)
+        }
+
+        fn record(&self, _span: &Id, _values: &Record<'_>) {}
+
+        fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+        fn event(&self, _event: &Event<'_>) {}
+
+        fn enter(&self, _span: &Id) {}
+
+        fn exit(&self, _span: &Id) {}
+    }
+
+    #[test]
+    fn test_image_journey_is_one_root_and_two_children() {
+        tracing::subscriber::set_global_default(CaptureSubscriber {
+            next_id: AtomicU64::new(1),
+        })
+        .unwrap();
+        // every image is stored, so each one gets a scored and a stored event
+        let labrador = FixedScorer::new(vec![("labrador".to_string(), 0.9)]);
+        let mut plugins = PluginRegistry::new();
+        plugins
+            .register_plugin(Box::new(NewImagePlugin::new(40)))
+            .unwrap()
+            .register_plugin(Box::new(ImageScorePlugin::with_scorer(
+                41,
+                Box::new(labrador),
+            )))
+            .unwrap()
+            .register_plugin(Box::new(ImageStorePlugin::new(42)))
+            .unwrap();
+        let engine = EventEngineBuilder::new()
+            .incoming_port(1559)
+            .outgoing_port(1560)
+            .sync_port(1500)
+            .plugins(plugins)
+            .start()
+            .unwrap();
+        let results = engine.wait_for_plugins(Duration::from_secs(10));
+        assert_eq!(results.len(), 3, "pipeline did not finish: {:?}", results);
+        engine.shutdown().unwrap();
+
+        let spans = SPANS.lock().unwrap().clone();
+        let root = spans
+            .iter()
+            .find(|s| s.parent.is_none() && s.fields["plugin_id"] == "40")
+            .expect("no root span for the new images");
+        assert_eq!(root.name, "NewImageEvent");
+        assert_eq!(root.fields["uuid"], root.fields["correlation_id"]);
+        let roots_of_chain = spans
+            .iter()
+            .filter(|s| s.parent.is_none())
+            .filter(|s| s.fields["correlation_id"] == root.fields["correlation_id"])
+            .count();
+        assert_eq!(roots_of_chain, 1);
+        let children: Vec<(&str, &str)> = spans
+            .iter()
+            .filter(|s| s.parent == Some(root.id))
+            .map(|s| (s.name, s.fields["plugin_id"].as_str()))
+            .collect();
+        assert_eq!(
+            children,
+            vec![("ImageScoredEvent", "41"), ("ImageStoredEvent", "42")]
+        );
+        assert!(spans
+            .iter()
+            .filter(|s| s.parent == Some(root.id))
+            .all(|s| s.fields["correlation_id"] == root.fields["correlation_id"]));
+    }
+}
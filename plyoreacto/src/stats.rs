@@ -0,0 +1,212 @@
+//! Counts of the events the engine forwards, by type, for telling which types make up the
+//! traffic without subscribing a metrics plugin to all of it. The engine's forwarding loop (see
+//! the `middleware` module) counts every event it forwards from the publishers, its size (of
+//! every frame, envelope included) and when it was last seen; `EngineHandle::stats()` returns a
+//! snapshot of the counts, and so does the `stats` request of the admin socket.
+//!
+//! An engine forwarding with `zmq::proxy`, which it only does when started with
+//! `max_payload_size(0)` and nothing else needing its own loop, counts nothing; give it
+//! `forwarding_loop(true)` to count. The events the engine publishes itself, the events sent
+//! again and the events dropped by a middleware or for their size are not counted.
+//!
+
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::event_type_registry::EventTypeRegistry;
+use crate::events::{event_type_header, event_type_names};
+
+/// The events of one type the engine forwarded; see `EngineHandle::stats`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct EventTypeStats {
+    /// How many were forwarded.
+    pub count: u64,
+    /// Their size, in bytes, summed over every frame of each.
+    pub bytes: u64,
+    /// When the latest was forwarded; None if none was.
+    pub last_seen: Option<SystemTime>,
+}
+
+/// A snapshot of the events the engine forwarded, by type.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EngineStats {
+    /// The types of which at least one event was forwarded, by name.
+    pub event_types: BTreeMap<String, EventTypeStats>,
+    /// The messages of types unknown to the engine when it started, all together.
+    pub other: EventTypeStats,
+}
+
+// The counters of one event type, updated by the forwarding loop alone.
+#[derive(Default)]
+struct Counters {
+    count: AtomicU64,
+    bytes: AtomicU64,
+    // microseconds since the Unix epoch; 0 until the first event
+    last_seen: AtomicU64,
+}
+
+impl Counters {
+    fn snapshot(&self) -> EventTypeStats {
+        let last_seen = self.last_seen.load(Ordering::Relaxed);
+        EventTypeStats {
+            count: self.count.load(Ordering::Relaxed),
+            bytes: self.bytes.load(Ordering::Relaxed),
+            last_seen: (last_seen > 0).then(|| UNIX_EPOCH + Duration::from_micros(last_seen)),
+        }
+    }
+}
+
+// The counters of each event type known when the engine started, built-in or registered; the
+// table does not change afterwards, so counting takes no lock.
+pub(crate) struct ForwardingStats {
+    // the header frame and name of each type, indexed like `counters`
+    types: Vec<(Vec<u8>, String)>,
+    counters: Vec<Counters>,
+    // those of the messages of any other type
+    other: Counters,
+}
+
+impl ForwardingStats {
+    pub(crate) fn new() -> Self {
+        let registered = EventTypeRegistry::global().types();
+        let types: Vec<(Vec<u8>, String)> = event_type_names()
+            .iter()
+            .copied()
+            .chain(registered.iter().map(|t| t.name))
+            .map(|name| (event_type_header(name), name.to_string()))
+            .collect();
+        ForwardingStats {
+            counters: types.iter().map(|_| Counters::default()).collect(),
+            types,
+            other: Counters::default(),
+        }
+    }
+
+    // Count the message `frames` forwarded at `now`.
+    pub(crate) fn count(&self, frames: &[zmq::Message], now: SystemTime) {
+        let Some(header) = frames.first() else {
+            return;
+        };
+        // a single frame message starts with the header just as well
+        let counters = self
+            .types
+            .iter()
+            .position(|(known, _)| header.starts_with(known))
+            .map_or(&self.other, |i| &self.counters[i]);
+        let bytes: usize = frames.iter().map(|frame| frame.len()).sum();
+        let micros = now
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_micros() as u64;
+        counters.count.fetch_add(1, Ordering::Relaxed);
+        counters.bytes.fetch_add(bytes as u64, Ordering::Relaxed);
+        counters.last_seen.store(micros, Ordering::Relaxed);
+    }
+
+    pub(crate) fn snapshot(&self) -> EngineStats {
+        let event_types = self
+            .types
+            .iter()
+            .zip(&self.counters)
+            .map(|((_, name), counters)| (name.clone(), counters.snapshot()))
+            .filter(|(_, stats)| stats.count > 0)
+            .collect();
+        EngineStats {
+            event_types,
+            other: self.other.snapshot(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::event_engine::{EventEngineBuilder, Transport};
+    use crate::events::{Event, ImageScored, NewImage, PluginHeartbeat};
+    use crate::plugin_registry::PluginRegistry;
+    use std::time::Instant;
+
+    #[test]
+    fn test_frames_are_counted_by_type() {
+        let stats = ForwardingStats::new();
+        let now = SystemTime::now();
+        let heartbeat = [
+            zmq::Message::from(event_type_header("PluginHeartbeatEvent")),
+            zmq::Message::from(vec![0; 10]),
+        ];
+        stats.count(&heartbeat, now);
+        stats.count(&heartbeat, now);
+        // an event sent as a single frame, and a message of a type the engine does not know
+        let single = event_type_header("NewImageEvent").into_iter().chain([1, 2]);
+        stats.count(&[zmq::Message::from(single.collect::<Vec<u8>>())], now);
+        stats.count(&[zmq::Message::from(&b"SomeEvent\0"[..])], now);
+
+        let snapshot = stats.snapshot();
+        let heartbeats = snapshot.event_types["PluginHeartbeatEvent"];
+        let header_bytes = "PluginHeartbeatEvent".len() as u64 + 1;
+        assert_eq!(heartbeats.count, 2);
+        assert_eq!(heartbeats.bytes, 2 * (header_bytes + 10));
+        // to the microsecond
+        let last_seen = heartbeats.last_seen.unwrap();
+        assert!(now.duration_since(last_seen).unwrap() < Duration::from_micros(1));
+        assert_eq!(snapshot.event_types["NewImageEvent"].count, 1);
+        assert_eq!(snapshot.event_types.len(), 2);
+        assert_eq!(snapshot.other.count, 1);
+        assert_eq!(snapshot.other.bytes, 10);
+    }
+
+    #[test]
+    fn test_stats_count_what_was_published() {
+        let engine = EventEngineBuilder::new()
+            .incoming_inproc("messages-stats")
+            .outgoing_inproc("events-stats")
+            .transport(Transport::InprocOnly)
+            .plugins(PluginRegistry::new())
+            .start()
+            .unwrap();
+        let image = Event::NewImage(NewImage {
+            image_uuid: "counted".to_string(),
+            image_format: "png".to_string(),
+            image: vec![0; 1000],
+            location: None,
+        });
+        let scored = Event::ImageScored(ImageScored {
+            image_uuid: "counted".to_string(),
+            scores: Vec::new(),
+        });
+        let published = Instant::now();
+        for i in 0..30 {
+            engine.publish(&image).unwrap();
+            if i % 2 == 0 {
+                engine.publish(&scored).unwrap();
+            }
+            let heartbeat = Event::PluginHeartbeat(PluginHeartbeat {
+                plugin_id: 1,
+                seq: i,
+            });
+            engine.publish(&heartbeat).unwrap();
+        }
+
+        // the proxy forwards them in its own time, with or without subscribers
+        let counts = |stats: &EngineStats| -> Vec<(String, u64)> {
+            let types = stats.event_types.iter();
+            types.map(|(name, s)| (name.clone(), s.count)).collect()
+        };
+        let expected = vec![
+            ("ImageScoredEvent".to_string(), 15),
+            ("NewImageEvent".to_string(), 30),
+            ("PluginHeartbeatEvent".to_string(), 30),
+        ];
+        let mut stats = engine.stats();
+        while counts(&stats) != expected && published.elapsed() < Duration::from_secs(5) {
+            std::thread::sleep(Duration::from_millis(1));
+            stats = engine.stats();
+        }
+        assert_eq!(counts(&stats), expected);
+        assert!(stats.event_types["NewImageEvent"].bytes > 30 * 1000);
+        assert!(stats.event_types.values().all(|s| s.last_seen.is_some()));
+        assert_eq!(stats.other, EventTypeStats::default());
+        engine.shutdown().unwrap();
+    }
+}
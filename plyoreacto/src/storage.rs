@@ -0,0 +1,521 @@
+//! Where the image storing plugin keeps the images it stores.
+//! Storage is pluggable through the `StorageBackend` trait. `FilesystemStore` writes every image
+//! to `<root>/<ab>/<cd>/<uuid>.<format>`, where `ab` and `cd` are the first four characters of
+//! its uuid, so that no directory ends up holding all of the images; `InMemoryStore` keeps them
+//! in memory, e.g., for tests. A `DedupIndex` remembers where images were stored by the SHA-256
+//! of their bytes, so that the same bytes are not stored twice.
+//!
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use sha2::{Digest, Sha256};
+
+/// Where a backend stored an image (e.g., the path of its file); published in the
+/// `ImageStoredEvent` of the image.
+pub type StoredLocation = String;
+
+/// Storage for the images kept by an `ImageStorePlugin`.
+pub trait StorageBackend {
+    /// Store `image`, replacing any image stored earlier with the same uuid.
+    fn put(
+        &mut self,
+        image_uuid: &str,
+        image_format: &str,
+        image: &[u8],
+    ) -> Result<StoredLocation, StorageError>;
+
+    /// The stored image with uuid `image_uuid`, if there is one.
+    fn get(&self, image_uuid: &str) -> Result<Option<Vec<u8>>, StorageError>;
+
+    /// Remove the image with uuid `image_uuid`; returns whether there was one.
+    fn delete(&mut self, image_uuid: &str) -> Result<bool, StorageError>;
+
+    /// Every stored image, e.g., for an `ImageRetentionPlugin` to purge the old ones. Backends
+    /// that cannot list their images return an error.
+    fn list(&self) -> Result<Vec<StoredImage>, StorageError> {
+        Err(StorageError::Other(
+            "the storage backend cannot list its images".to_string(),
+        ))
+    }
+
+    /// Write out the images the backend holds back, e.g., in a write-behind buffer; the image
+    /// storing plugin calls it when the engine starts shutting down. Backends writing every
+    /// image as it is put, as those of this crate do, have nothing to flush.
+    fn flush(&mut self) -> Result<(), StorageError> {
+        Ok(())
+    }
+}
+
+/// An image held by a `StorageBackend`, as returned by `list`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StoredImage {
+    pub image_uuid: String,
+    // size of the image, in bytes
+    pub size: u64,
+    pub stored_at: SystemTime,
+}
+
+/// Errors returned by a `StorageBackend`.
+#[derive(Debug)]
+pub enum StorageError {
+    /// The uuid or format of an image cannot be used by the backend (e.g., "image uuid").
+    InvalidName { what: &'static str, name: String },
+    /// Reading or writing the storage failed.
+    Io(io::Error),
+    /// Any other failure, described by the backend.
+    Other(String),
+}
+
+impl fmt::Display for StorageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StorageError::InvalidName { what, name } => write!(f, "invalid {} {:?}", what, name),
+            StorageError::Io(source) => write!(f, "{}", source),
+            StorageError::Other(reason) => write!(f, "{}", reason),
+        }
+    }
+}
+
+impl std::error::Error for StorageError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            StorageError::Io(source) => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for StorageError {
+    fn from(error: io::Error) -> Self {
+        StorageError::Io(error)
+    }
+}
+
+/// Stores images as files under a root directory, which is created if needed. The location of
+/// an image is the path of its file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FilesystemStore {
+    root: PathBuf,
+    fsync: bool,
+}
+
+impl FilesystemStore {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        FilesystemStore {
+            root: root.into(),
+            fsync: false,
+        }
+    }
+
+    /// Flush every image to disk before reporting it stored.
+    pub fn fsync(mut self, fsync: bool) -> Self {
+        self.fsync = fsync;
+        self
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// The file an image is stored in. Uuids and formats may only contain ASCII letters,
+    /// digits, `-` and `_`, so that they cannot name a file outside of the root directory.
+    pub fn path(&self, image_uuid: &str, image_format: &str) -> Result<PathBuf, StorageError> {
+        check_name("image format", image_format)?;
+        Ok(self
+            .shard_dir(image_uuid)?
+            .join(format!("{}.{}", image_uuid, image_format)))
+    }
+
+    fn shard_dir(&self, image_uuid: &str) -> Result<PathBuf, StorageError> {
+        check_name("image uuid", image_uuid)?;
+        // uuids shorter than four characters share the "_" directories
+        let shard = |range: std::ops::Range<usize>| image_uuid.get(range).unwrap_or("_");
+        Ok(self.root.join(shard(0..2)).join(shard(2..4)))
+    }
+
+    // The file of the image with uuid `image_uuid`, whatever its format.
+    fn find(&self, image_uuid: &str) -> Result<Option<PathBuf>, StorageError> {
+        let entries = match fs::read_dir(self.shard_dir(image_uuid)?) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        for entry in entries {
+            let path = entry?.path();
+            if path.file_stem().and_then(|stem| stem.to_str()) == Some(image_uuid) {
+                return Ok(Some(path));
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl StorageBackend for FilesystemStore {
+    fn put(
+        &mut self,
+        image_uuid: &str,
+        image_format: &str,
+        image: &[u8],
+    ) -> Result<StoredLocation, StorageError> {
+        let path = self.path(image_uuid, image_format)?;
+        if let Some(earlier) = self.find(image_uuid)? {
+            fs::remove_file(earlier)?;
+        }
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut file = File::create(&path)?;
+        file.write_all(image)?;
+        if self.fsync {
+            file.sync_all()?;
+        }
+        Ok(path.display().to_string())
+    }
+
+    fn get(&self, image_uuid: &str) -> Result<Option<Vec<u8>>, StorageError> {
+        match self.find(image_uuid)? {
+            Some(path) => Ok(Some(fs::read(path)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn delete(&mut self, image_uuid: &str) -> Result<bool, StorageError> {
+        match self.find(image_uuid)? {
+            Some(path) => {
+                fs::remove_file(path)?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    fn list(&self) -> Result<Vec<StoredImage>, StorageError> {
+        // the files are two levels of shard directories below the root
+        let mut dirs = vec![self.root.clone()];
+        for _ in 0..2 {
+            let mut subdirs = Vec::new();
+            for dir in dirs {
+                match fs::read_dir(&dir) {
+                    Ok(entries) => {
+                        for entry in entries {
+                            let entry = entry?;
+                            if entry.file_type()?.is_dir() {
+                                subdirs.push(entry.path());
+                            }
+                        }
+                    }
+                    Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                    Err(e) => return Err(e.into()),
+                }
+            }
+            dirs = subdirs;
+        }
+        let mut images = Vec::new();
+        for dir in dirs {
+            for entry in fs::read_dir(dir)? {
+                let entry = entry?;
+                let metadata = entry.metadata()?;
+                let path = entry.path();
+                let image_uuid = path.file_stem().and_then(|stem| stem.to_str());
+                if let (true, Some(image_uuid)) = (metadata.is_file(), image_uuid) {
+                    images.push(StoredImage {
+                        image_uuid: image_uuid.to_string(),
+                        size: metadata.len(),
+                        stored_at: metadata.modified()?,
+                    });
+                }
+            }
+        }
+        Ok(images)
+    }
+}
+
+fn check_name(what: &'static str, name: &str) -> Result<(), StorageError> {
+    let valid = |c: char| c.is_ascii_alphanumeric() || c == '-' || c == '_';
+    if name.is_empty() || !name.chars().all(valid) {
+        return Err(StorageError::InvalidName {
+            what,
+            name: name.to_string(),
+        });
+    }
+    Ok(())
+}
+
+/// Keeps images in memory. Clones share the same images, so a test can keep a clone to look at
+/// what the plugin stored. The location of an image is `memory:<uuid>.<format>`.
+#[derive(Clone, Debug, Default)]
+pub struct InMemoryStore {
+    images: Arc<Mutex<HashMap<String, InMemoryImage>>>,
+}
+
+// An image held by an `InMemoryStore`, and when it was put.
+#[derive(Debug)]
+struct InMemoryImage {
+    image: Vec<u8>,
+    stored_at: SystemTime,
+}
+
+impl InMemoryStore {
+    pub fn new() -> Self {
+        InMemoryStore::default()
+    }
+
+    /// Number of images stored.
+    pub fn len(&self) -> usize {
+        self.images.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl StorageBackend for InMemoryStore {
+    fn put(
+        &mut self,
+        image_uuid: &str,
+        image_format: &str,
+        image: &[u8],
+    ) -> Result<StoredLocation, StorageError> {
+        let stored = InMemoryImage {
+            image: image.to_vec(),
+            stored_at: SystemTime::now(),
+        };
+        self.images
+            .lock()
+            .unwrap()
+            .insert(image_uuid.to_string(), stored);
+        Ok(format!("memory:{}.{}", image_uuid, image_format))
+    }
+
+    fn get(&self, image_uuid: &str) -> Result<Option<Vec<u8>>, StorageError> {
+        let images = self.images.lock().unwrap();
+        Ok(images.get(image_uuid).map(|stored| stored.image.clone()))
+    }
+
+    fn delete(&mut self, image_uuid: &str) -> Result<bool, StorageError> {
+        Ok(self.images.lock().unwrap().remove(image_uuid).is_some())
+    }
+
+    fn list(&self) -> Result<Vec<StoredImage>, StorageError> {
+        let images = self.images.lock().unwrap();
+        Ok(images
+            .iter()
+            .map(|(image_uuid, stored)| StoredImage {
+                image_uuid: image_uuid.clone(),
+                size: stored.image.len() as u64,
+                stored_at: stored.stored_at,
+            })
+            .collect())
+    }
+}
+
+/// Where images were stored, by the SHA-256 of their bytes. An index opened with `open` is
+/// backed by a file, one `<sha256> <location>` line per image, so that it survives the plugin
+/// being restarted; the file is appended to as images are stored, and a line with only the
+/// hash records that the image was removed.
+#[derive(Debug, Default)]
+pub struct DedupIndex {
+    locations: HashMap<String, StoredLocation>,
+    file: Option<File>,
+}
+
+impl DedupIndex {
+    /// An index that is kept in memory only.
+    pub fn new() -> Self {
+        DedupIndex::default()
+    }
+
+    /// The index backed by the file at `path`, created if it does not exist yet.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, StorageError> {
+        let path = path.as_ref();
+        let mut locations = HashMap::new();
+        match File::open(path) {
+            Ok(file) => {
+                for line in BufReader::new(file).lines() {
+                    let line = line?;
+                    match line.split_once(' ') {
+                        Some((hash, location)) => {
+                            locations.insert(hash.to_string(), location.to_string())
+                        }
+                        None => locations.remove(&line),
+                    };
+                }
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(DedupIndex {
+            locations,
+            file: Some(file),
+        })
+    }
+
+    /// The SHA-256 of `image`, as a hex string.
+    pub fn hash(image: &[u8]) -> String {
+        Sha256::digest(image)
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect()
+    }
+
+    /// Where the image with SHA-256 `hash` was stored, if it was.
+    pub fn get(&self, hash: &str) -> Option<&StoredLocation> {
+        self.locations.get(hash)
+    }
+
+    /// Record that the image with SHA-256 `hash` was stored at `location`.
+    pub fn insert(&mut self, hash: &str, location: &str) -> Result<(), StorageError> {
+        if let Some(file) = &mut self.file {
+            writeln!(file, "{} {}", hash, location)?;
+        }
+        self.locations
+            .insert(hash.to_string(), location.to_string());
+        Ok(())
+    }
+
+    /// Forget the image with SHA-256 `hash`, e.g., because it was deleted from the backend, so
+    /// that the same bytes are stored again next time.
+    pub fn remove(&mut self, hash: &str) -> Result<(), StorageError> {
+        if self.locations.remove(hash).is_some() {
+            if let Some(file) = &mut self.file {
+                writeln!(file, "{}", hash)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Make sure the lines written to the file of the index so far are on disk.
+    pub fn flush(&mut self) -> Result<(), StorageError> {
+        if let Some(file) = &mut self.file {
+            file.sync_data()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn temp_root() -> PathBuf {
+        std::env::temp_dir().join(format!("plyoreacto-store-{}", uuid::Uuid::new_v4()))
+    }
+
+    // Put, get, delete and list images, as every backend should.
+    fn check_backend(store: &mut impl StorageBackend) {
+        assert_eq!(store.get("1234abcd").unwrap(), None);
+        store.put("1234abcd", "png", &[1, 2, 3]).unwrap();
+        assert_eq!(store.get("1234abcd").unwrap(), Some(vec![1, 2, 3]));
+        // a later image replaces the earlier one, even in another format
+        store.put("1234abcd", "jpeg", &[4, 5]).unwrap();
+        assert_eq!(store.get("1234abcd").unwrap(), Some(vec![4, 5]));
+        assert!(store.delete("1234abcd").unwrap());
+        assert!(!store.delete("1234abcd").unwrap());
+        assert_eq!(store.get("1234abcd").unwrap(), None);
+
+        assert_eq!(store.list().unwrap(), vec![]);
+        store.put("1234abcd", "png", &[1, 2, 3]).unwrap();
+        store.put("abc", "jpeg", &[4]).unwrap();
+        let mut listed: Vec<(String, u64)> = store
+            .list()
+            .unwrap()
+            .into_iter()
+            .map(|image| (image.image_uuid, image.size))
+            .collect();
+        listed.sort();
+        assert_eq!(
+            listed,
+            vec![("1234abcd".to_string(), 3), ("abc".to_string(), 1)]
+        );
+        assert!(store.delete("1234abcd").unwrap());
+        assert!(store.delete("abc").unwrap());
+    }
+
+    #[test]
+    fn test_in_memory_store() {
+        let mut store = InMemoryStore::new();
+        check_backend(&mut store);
+        let shared = store.clone();
+        assert_eq!(
+            store.put("1234abcd", "png", &[1]).unwrap(),
+            "memory:1234abcd.png"
+        );
+        assert_eq!(shared.len(), 1);
+    }
+
+    #[test]
+    fn test_dedup_index_survives_reopening() {
+        let root = temp_root();
+        fs::create_dir(&root).unwrap();
+        let path = root.join("dedup.idx");
+        let hash = DedupIndex::hash(&[1, 2, 3]);
+        assert_eq!(hash.len(), 64);
+        assert_ne!(hash, DedupIndex::hash(&[1, 2, 4]));
+
+        let mut index = DedupIndex::open(&path).unwrap();
+        assert_eq!(index.get(&hash), None);
+        index.insert(&hash, "/images/12/34/1234.png").unwrap();
+        assert_eq!(index.get(&hash).unwrap(), "/images/12/34/1234.png");
+        drop(index);
+        let mut index = DedupIndex::open(&path).unwrap();
+        assert_eq!(index.get(&hash).unwrap(), "/images/12/34/1234.png");
+        index.remove(&hash).unwrap();
+        drop(index);
+        assert_eq!(DedupIndex::open(&path).unwrap().get(&hash), None);
+        // an index without a file forgets everything
+        let mut index = DedupIndex::new();
+        index.insert(&hash, "memory:1234.png").unwrap();
+        assert_eq!(DedupIndex::new().get(&hash), None);
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_paths_are_sharded_by_uuid() {
+        let store = FilesystemStore::new("/images");
+        assert_eq!(
+            store.path("1234abcd", "png").unwrap(),
+            PathBuf::from("/images/12/34/1234abcd.png")
+        );
+        assert_eq!(
+            store.path("abc", "jpeg").unwrap(),
+            PathBuf::from("/images/ab/_/abc.jpeg")
+        );
+        for (image_uuid, image_format) in [("../1234", "png"), ("1234", "png/x"), ("", "png")] {
+            assert!(matches!(
+                store.path(image_uuid, image_format),
+                Err(StorageError::InvalidName { .. })
+            ));
+        }
+        assert_eq!(
+            store.path("../1234", "png").unwrap_err().to_string(),
+            "invalid image uuid \"../1234\""
+        );
+    }
+
+    #[test]
+    fn test_filesystem_store() {
+        let root = temp_root();
+        let mut store = FilesystemStore::new(&root).fsync(true);
+        check_backend(&mut store);
+        let location = store.put("1234abcd", "png", &[1, 2, 3]).unwrap();
+        let path = root.join("12").join("34").join("1234abcd.png");
+        assert_eq!(location, path.display().to_string());
+        assert_eq!(fs::read(&path).unwrap(), vec![1, 2, 3]);
+        // a root that is not a directory
+        let mut store = FilesystemStore::new(&path);
+        assert!(matches!(
+            store.put("5678abcd", "png", &[1, 2, 3]),
+            Err(StorageError::Io(_))
+        ));
+        assert_eq!(fs::read(&path).unwrap(), vec![1, 2, 3]);
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
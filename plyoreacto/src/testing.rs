@@ -0,0 +1,299 @@
+//! A harness for testing plugins in a running engine: it starts an engine with the plugins under
+//! test, publishes the events a test makes up and collects those the plugins publish, and shuts
+//! the engine down once it is dropped, even when an assertion panics first. The engine is bound on
+//! inproc endpoints only unless `tcp` is set, in which case it binds TCP ports the system picks,
+//! so tests can run side by side.
+//!
+//! ```
+//! use plyoreacto::events::{Event, ImageRejected, NewImage};
+//! use plyoreacto::plugin::{Plugin, PluginContext, PluginError};
+//! use plyoreacto::testing::TestHarness;
+//! use std::time::Duration;
+//!
+//! // Rejects every image.
+//! struct RejectAll;
+//!
+//! impl Plugin for RejectAll {
+//!     fn id(&self) -> i32 { 7 }
+//!     fn name(&self) -> &str { "reject all" }
+//!     fn subscriptions(&self) -> &[&str] { &["NewImageEvent"] }
+//!     fn start(self: Box<Self>, mut ctx: PluginContext) -> Result<(), PluginError> {
+//!         while let Event::NewImage(image) = ctx.next_event()?.decode()? {
+//!             let (image_uuid, top_label) = (image.image_uuid, "none".to_string());
+//!             ctx.publish(&ImageRejected { image_uuid, top_label, probability: 0.0 })?;
+//!         }
+//!         Ok(())
+//!     }
+//! }
+//!
+//! let mut harness = TestHarness::builder().plugin(Box::new(RejectAll)).start()?;
+//! let image = vec![1, 2, 3];
+//! let (image_uuid, image_format) = ("5b0c".to_string(), "png".to_string());
+//! harness.publish(&Event::NewImage(NewImage { image_uuid, image_format, image, location: None }))?;
+//! let rejected = harness.collect("ImageRejectedEvent", 1, Duration::from_secs(5));
+//! assert!(matches!(&rejected[..], [Event::ImageRejected(r)] if r.image_uuid == "5b0c"));
+//! harness.shutdown()?;
+//! # Ok::<(), plyoreacto::event_engine::EngineError>(())
+//! ```
+//!
+//! Only the feature `test-util` builds this module.
+//!
+
+use std::collections::VecDeque;
+use std::net::TcpListener;
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::time::{Duration, Instant};
+
+use log::warn;
+
+use crate::event_engine::{EngineConfig, EngineError, EngineHandle, EventEngineBuilder, Transport};
+use crate::event_type_registry::EventTypeRegistry;
+use crate::events::{event_type_names, Event};
+use crate::plugin::Plugin;
+use crate::plugin_registry::PluginRegistry;
+
+// How long the harness's subscription is given to reach the engine, which `subscribe` does not
+// wait for, before `start` returns.
+const SUBSCRIPTION_SETTLE: Duration = Duration::from_millis(200);
+
+/// A TCP port on 127.0.0.1 that was free a moment ago, e.g., for a client the test points at
+/// nothing; panics if the system has none to give. Another process may take it meanwhile, so
+/// engines are better started on port 0, as `TestHarnessBuilder::tcp` does.
+pub fn free_port() -> u16 {
+    TcpListener::bind("127.0.0.1:0")
+        .and_then(|listener| listener.local_addr())
+        .expect("could not find a free TCP port")
+        .port()
+}
+
+/// How a `TestHarness` starts its engine; see `TestHarness::builder`.
+pub struct TestHarnessBuilder {
+    engine: EventEngineBuilder,
+    plugins: PluginRegistry,
+    // registered with `plugins` at startup, so that a duplicate id fails `start`
+    extra_plugins: Vec<Box<dyn Plugin>>,
+}
+
+impl Default for TestHarnessBuilder {
+    fn default() -> Self {
+        TestHarnessBuilder {
+            engine: EventEngineBuilder::new().transport(Transport::InprocOnly),
+            plugins: PluginRegistry::new(),
+            extra_plugins: Vec::new(),
+        }
+    }
+}
+
+impl TestHarnessBuilder {
+    pub fn new() -> Self {
+        TestHarnessBuilder::default()
+    }
+
+    /// Start the plugins of `plugins`, along with those passed to `plugin`; none by default,
+    /// rather than the `default_plugins()` of an engine.
+    pub fn plugins(mut self, plugins: PluginRegistry) -> Self {
+        self.plugins = plugins;
+        self
+    }
+
+    /// Start `plugin` too.
+    pub fn plugin(mut self, plugin: Box<dyn Plugin>) -> Self {
+        self.extra_plugins.push(plugin);
+        self
+    }
+
+    /// Bind the engine on TCP ports the system picks as well as inproc, for external plugins
+    /// and other processes, e.g., the binaries of the crate, to reach it; the ports are in
+    /// `config()`.
+    pub fn tcp(mut self) -> Self {
+        self.engine = self
+            .engine
+            .transport(Transport::Both)
+            .incoming_port(0)
+            .outgoing_port(0)
+            .sync_port(0);
+        self
+    }
+
+    /// Set up the engine further with `configure`, e.g., with a codec or a middleware. The
+    /// plugins set on it are replaced with those of the harness.
+    pub fn configure(
+        mut self,
+        configure: impl FnOnce(EventEngineBuilder) -> EventEngineBuilder,
+    ) -> Self {
+        self.engine = configure(self.engine);
+        self
+    }
+
+    /// Start the engine, once its plugins have synced, and subscribe the harness to every
+    /// built-in event type and those registered with the `EventTypeRegistry` so far.
+    pub fn start(self) -> Result<TestHarness, EngineError> {
+        let TestHarnessBuilder {
+            engine,
+            mut plugins,
+            extra_plugins,
+        } = self;
+        for plugin in extra_plugins {
+            plugins.register_plugin(plugin)?;
+        }
+        let engine = engine.plugins(plugins).start()?;
+        let custom_types = EventTypeRegistry::global().types();
+        let event_types: Vec<&str> = event_type_names()
+            .iter()
+            .copied()
+            .chain(custom_types.iter().map(|custom| custom.name))
+            .collect();
+        let events = match engine.subscribe(&event_types) {
+            Ok(events) => events,
+            Err(e) => {
+                engine.shutdown()?;
+                return Err(e);
+            }
+        };
+        std::thread::sleep(SUBSCRIPTION_SETTLE);
+        Ok(TestHarness {
+            engine: Some(engine),
+            events,
+            pending: VecDeque::new(),
+        })
+    }
+}
+
+/// An engine started for a test, with a subscription to the events published in it; see the
+/// module documentation. Dropping the harness shuts the engine down and joins its threads.
+pub struct TestHarness {
+    // None once shut down
+    engine: Option<EngineHandle>,
+    events: Receiver<Event>,
+    // the events received but not collected yet, in order
+    pending: VecDeque<Event>,
+}
+
+impl TestHarness {
+    /// A harness without plugins bound on inproc endpoints only, to set up and `start`.
+    pub fn builder() -> TestHarnessBuilder {
+        TestHarnessBuilder::new()
+    }
+
+    /// The running engine, e.g., for the status of its plugins.
+    pub fn engine(&self) -> &EngineHandle {
+        self.engine.as_ref().expect("the engine is running")
+    }
+
+    /// The configuration the engine was started with, e.g., for the TCP ports chosen by `tcp`.
+    pub fn config(&self) -> &EngineConfig {
+        self.engine().config()
+    }
+
+    /// Publish `event` to the plugins, as if it came from a plugin.
+    pub fn publish(&self, event: &Event) -> Result<(), EngineError> {
+        self.engine().publish(event)
+    }
+
+    /// The next `n` events of type `event_type` (e.g., "ImageScoredEvent") published since the
+    /// harness started and not collected yet, in order, or fewer if they do not all come within
+    /// `timeout`. Events of other types received in the meantime are kept for later calls.
+    pub fn collect(&mut self, event_type: &str, n: usize, timeout: Duration) -> Vec<Event> {
+        let mut collected = Vec::new();
+        let mut kept = VecDeque::new();
+        while let Some(event) = self.pending.pop_front() {
+            if collected.len() < n && event.type_name() == event_type {
+                collected.push(event);
+            } else {
+                kept.push_back(event);
+            }
+        }
+        self.pending = kept;
+        let deadline = Instant::now() + timeout;
+        while collected.len() < n {
+            let wait = deadline.saturating_duration_since(Instant::now());
+            match self.events.recv_timeout(wait) {
+                Ok(event) if event.type_name() == event_type => collected.push(event),
+                Ok(event) => self.pending.push_back(event),
+                Err(RecvTimeoutError::Timeout | RecvTimeoutError::Disconnected) => break,
+            }
+        }
+        collected
+    }
+
+    /// Shut the engine down, as dropping the harness does, but returning whether it shut down
+    /// cleanly.
+    pub fn shutdown(mut self) -> Result<(), EngineError> {
+        match self.engine.take() {
+            Some(engine) => engine.shutdown(),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for TestHarness {
+    fn drop(&mut self) {
+        if let Some(engine) = self.engine.take() {
+            if let Err(e) = engine.shutdown() {
+                warn!("test harness could not shut the engine down: {}", e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::events::{ImageDeletedRequest, ImageStored};
+
+    #[test]
+    fn test_events_are_collected_by_type() {
+        let mut harness = TestHarness::builder().start().unwrap();
+        for image_uuid in ["1", "2"] {
+            let stored = ImageStored {
+                image_uuid: image_uuid.to_string(),
+                path: format!("/images/{}.png", image_uuid),
+                deduplicated: false,
+            };
+            harness.publish(&Event::ImageStored(stored)).unwrap();
+            let request = ImageDeletedRequest {
+                image_uuid: image_uuid.to_string(),
+            };
+            harness
+                .publish(&Event::ImageDeletedRequest(request))
+                .unwrap();
+        }
+
+        let requests = harness.collect("ImageDeletedRequestEvent", 2, Duration::from_secs(5));
+        assert_eq!(requests.len(), 2);
+        // the events of other types received meanwhile are still there, in order
+        let stored = harness.collect("ImageStoredEvent", 3, Duration::from_millis(200));
+        let uuids: Vec<_> = stored
+            .iter()
+            .map(|event| match event {
+                Event::ImageStored(stored) => stored.image_uuid.as_str(),
+                event => panic!("expected an ImageStoredEvent, got {:?}", event),
+            })
+            .collect();
+        assert_eq!(uuids, ["1", "2"]);
+        harness.shutdown().unwrap();
+    }
+
+    #[test]
+    fn test_custom_events_are_collected() {
+        let weather = EventTypeRegistry::global()
+            .register("HarnessWeatherEvent")
+            .unwrap();
+        let mut harness = TestHarness::builder().start().unwrap();
+        harness.publish(&weather.event(b"sunny".to_vec())).unwrap();
+        let collected = harness.collect("HarnessWeatherEvent", 1, Duration::from_secs(5));
+        assert_eq!(collected, [weather.event(b"sunny".to_vec())]);
+        harness.shutdown().unwrap();
+    }
+
+    #[test]
+    fn test_engines_on_free_ports_run_side_by_side() {
+        let first = TestHarness::builder().tcp().start().unwrap();
+        let second = TestHarness::builder().tcp().start().unwrap();
+        // the ports the system picked, not 0
+        assert_ne!(first.config().incoming_port, 0);
+        assert_ne!(first.config().incoming_port, second.config().incoming_port);
+        assert!(first.engine().is_running() && second.engine().is_running());
+        // dropping the harnesses shuts their engines down
+    }
+}
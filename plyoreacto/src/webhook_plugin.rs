@@ -0,0 +1,325 @@
+//! Webhook plugin, built with the `webhook` feature.
+//! This plugin forwards events to systems outside the engine: it is configured with (event
+//! type, URL) pairs, subscribes to the event types listed and POSTs each event of one of them,
+//! converted to JSON (see `EventMsg::to_json`), to its URLs. Connection errors, 429 and 5xx
+//! responses are retried with exponential backoff; an event that still cannot be delivered gets
+//! a WebhookDeliveryFailedEvent with the status of the last response or the error. Replayed
+//! events are posted too, marked `"replayed": true`, unless the plugin is made to skip them.
+//!
+
+use std::thread;
+use std::time::Duration;
+
+use log::{debug, error, info, warn};
+
+use crate::event_engine::EngineError;
+use crate::events::{known_event_type, WebhookDeliveryFailed};
+use crate::plugin::{Plugin, PluginContext, PluginError};
+
+// Used unless set with the builder methods of `WebhookPlugin`.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+const DEFAULT_MAX_RETRIES: u32 = 3;
+const DEFAULT_RETRY_BACKOFF: Duration = Duration::from_millis(100);
+
+/// The webhook plugin, for registering with a `PluginRegistry`.
+pub struct WebhookPlugin {
+    plugin_id: i32,
+    // (event type, URL) pairs, in the order they were given
+    hooks: Vec<(String, String)>,
+    subscriptions: Vec<&'static str>,
+    timeout: Duration,
+    max_retries: u32,
+    retry_backoff: Duration,
+    skip_replayed: bool,
+}
+
+impl WebhookPlugin {
+    /// A plugin POSTing the events of each type of `hooks` to its URL; a type may be listed
+    /// with several URLs. Fails if one of the types is not an event type.
+    pub fn new(plugin_id: i32, hooks: Vec<(String, String)>) -> Result<Self, EngineError> {
+        let mut subscriptions = Vec::new();
+        for (event_type, _) in &hooks {
+            let name =
+                known_event_type(event_type).ok_or_else(|| EngineError::UnknownEventType {
+                    event_type: event_type.clone(),
+                })?;
+            if !subscriptions.contains(&name) {
+                subscriptions.push(name);
+            }
+        }
+        Ok(WebhookPlugin {
+            plugin_id,
+            hooks,
+            subscriptions,
+            timeout: DEFAULT_TIMEOUT,
+            max_retries: DEFAULT_MAX_RETRIES,
+            retry_backoff: DEFAULT_RETRY_BACKOFF,
+            skip_replayed: false,
+        })
+    }
+
+    /// How long a request may take, connecting included.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// How many times a request failing with a transient error is retried before giving up.
+    pub fn max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Wait before the first retry, doubled before each further one.
+    pub fn retry_backoff(mut self, retry_backoff: Duration) -> Self {
+        self.retry_backoff = retry_backoff;
+        self
+    }
+
+    /// Do not post the events replayed from an event log, nor those published in reply to them.
+    pub fn skip_replayed(mut self, skip: bool) -> Self {
+        self.skip_replayed = skip;
+        self
+    }
+
+    // POST `body` to `url`, retrying transient errors. On failure, returns the status of the
+    // last response (0 if there was none) and the error.
+    fn deliver(&self, agent: &ureq::Agent, url: &str, body: &str) -> Result<(), (u16, String)> {
+        let mut backoff = self.retry_backoff;
+        let mut attempt = 0;
+        loop {
+            let request = agent.post(url).set("Content-Type", "application/json");
+            let (status_code, error) = match request.send_string(body) {
+                Ok(_) => return Ok(()),
+                Err(ureq::Error::Status(status, response)) => {
+                    let error = format!(
+                        "POST {} failed with status {}: {}",
+                        url,
+                        status,
+                        response.status_text()
+                    );
+                    if !is_transient(status) {
+                        return Err((status, error));
+                    }
+                    (status, error)
+                }
+                Err(e) => (0, format!("POST {} failed: {}", url, e)),
+            };
+            if attempt == self.max_retries {
+                return Err((status_code, error));
+            }
+            attempt += 1;
+            warn!(
+                plugin_id = self.plugin_id;
+                "Webhook plugin retrying ({} of {}) after: {}",
+                attempt, self.max_retries, error
+            );
+            thread::sleep(backoff);
+            backoff *= 2;
+        }
+    }
+}
+
+// Whether a request failing with `status` may succeed if sent again.
+fn is_transient(status: u16) -> bool {
+    status == 429 || status >= 500
+}
+
+impl Plugin for WebhookPlugin {
+    fn id(&self) -> i32 {
+        self.plugin_id
+    }
+
+    fn name(&self) -> &str {
+        "webhook"
+    }
+
+    fn subscriptions(&self) -> &[&str] {
+        &self.subscriptions
+    }
+
+    fn start(self: Box<Self>, mut ctx: PluginContext) -> Result<(), PluginError> {
+        let agent = ureq::AgentBuilder::new().timeout(self.timeout).build();
+        loop {
+            let msg = ctx.next_event()?;
+            if msg.event_type == "PluginTerminateEvent" {
+                info!(plugin_id = ctx.plugin_id; "Webhook plugin got terminate event, exiting");
+                return Ok(());
+            }
+            if self.skip_replayed && msg.is_replayed() {
+                debug!(
+                    plugin_id = ctx.plugin_id, event_type = msg.event_type.as_str();
+                    "Webhook plugin skipping replayed {}", msg.event_type
+                );
+                continue;
+            }
+            let json = msg.to_json();
+            let body = json.to_string();
+            let urls = self
+                .hooks
+                .iter()
+                .filter(|(event_type, _)| *event_type == msg.event_type)
+                .map(|(_, url)| url);
+            for url in urls {
+                let (status_code, error) = match self.deliver(&agent, url, &body) {
+                    Ok(()) => continue,
+                    Err(failure) => failure,
+                };
+                error!(
+                    plugin_id = ctx.plugin_id, event_type = msg.event_type.as_str();
+                    "Webhook plugin could not deliver {}: {}",
+                    msg.event_type, error
+                );
+                let failed = WebhookDeliveryFailed {
+                    image_uuid: json["uuid"].as_str().unwrap_or_default().to_string(),
+                    event_type: msg.event_type.clone(),
+                    url: url.clone(),
+                    status_code,
+                    error,
+                };
+                match &msg.meta {
+                    Some(meta) => ctx.publish_reply(meta, &failed)?,
+                    None => ctx.publish(&failed)?,
+                };
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::event_engine::EventEngineBuilder;
+    use crate::events::{Event, NewImage};
+    use crate::image_score_plugin::{FixedScorer, ImageScorePlugin};
+    use crate::image_store_plugin::ImageStorePlugin;
+    use crate::plugin_registry::PluginRegistry;
+    use serde_json::Value;
+    use std::sync::mpsc;
+
+    // Answer every request to `port` with `status`, sending the bodies received to the
+    // returned channel.
+    fn start_server(port: u16, status: u16) -> mpsc::Receiver<String> {
+        let server = tiny_http::Server::http(("127.0.0.1", port)).unwrap();
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            for mut request in server.incoming_requests() {
+                let mut body = String::new();
+                request.as_reader().read_to_string(&mut body).unwrap();
+                let _ = request.respond(tiny_http::Response::empty(status));
+                if tx.send(body).is_err() {
+                    return;
+                }
+            }
+        });
+        rx
+    }
+
+    fn start_webhook_engine(
+        webhook: WebhookPlugin,
+        incoming_port: u16,
+    ) -> crate::event_engine::EngineHandle {
+        let scorer = FixedScorer::new(vec![("labrador".to_string(), 1.0)]);
+        let mut plugins = PluginRegistry::new();
+        plugins
+            .register_plugin(Box::new(ImageScorePlugin::with_scorer(1, Box::new(scorer))))
+            .unwrap()
+            .register_plugin(Box::new(ImageStorePlugin::new(2)))
+            .unwrap()
+            .register_plugin(Box::new(webhook))
+            .unwrap();
+        EventEngineBuilder::new()
+            .incoming_port(incoming_port)
+            .outgoing_port(incoming_port + 1)
+            .plugins(plugins)
+            .start()
+            .unwrap()
+    }
+
+    fn new_image() -> Event {
+        Event::NewImage(NewImage {
+            image_uuid: uuid::Uuid::new_v4().to_string(),
+            image_format: "png".to_string(),
+            image: vec![1, 2, 3],
+            location: None,
+        })
+    }
+
+    #[test]
+    fn test_unknown_event_types_are_rejected() {
+        let hooks = vec![(
+            "ImageStoredEvent".to_string(),
+            "http://localhost/".to_string(),
+        )];
+        assert!(WebhookPlugin::new(3, hooks).is_ok());
+        let hooks = vec![(
+            "ImageLostEvent".to_string(),
+            "http://localhost/".to_string(),
+        )];
+        assert!(matches!(
+            WebhookPlugin::new(3, hooks),
+            Err(EngineError::UnknownEventType { event_type }) if event_type == "ImageLostEvent"
+        ));
+    }
+
+    #[test]
+    fn test_stored_images_are_posted() {
+        let bodies = start_server(46580, 200);
+        let hooks = vec![(
+            "ImageStoredEvent".to_string(),
+            "http://127.0.0.1:46580/stored".to_string(),
+        )];
+        let engine = start_webhook_engine(WebhookPlugin::new(3, hooks).unwrap(), 46559);
+        let rx = engine.subscribe(&["ImageStoredEvent"]).unwrap();
+        // give the subscription time to reach the engine
+        thread::sleep(Duration::from_millis(200));
+        engine.publish(&new_image()).unwrap();
+
+        let timeout = Duration::from_secs(10);
+        let stored = match rx.recv_timeout(timeout).unwrap() {
+            Event::ImageStored(e) => e,
+            event => panic!("expected an ImageStoredEvent, got {:?}", event),
+        };
+        let body: Value = serde_json::from_str(&bodies.recv_timeout(timeout).unwrap()).unwrap();
+        engine.shutdown().unwrap();
+        assert_eq!(body["type"], "ImageStoredEvent");
+        assert_eq!(body["uuid"], stored.image_uuid.as_str());
+    }
+
+    #[test]
+    fn test_failed_deliveries_are_published() {
+        let bodies = start_server(3130, 500);
+        let hooks = vec![(
+            "ImageStoredEvent".to_string(),
+            "http://127.0.0.1:3130/stored".to_string(),
+        )];
+        let webhook = WebhookPlugin::new(3, hooks)
+            .unwrap()
+            .max_retries(2)
+            .retry_backoff(Duration::from_millis(10));
+        let engine = start_webhook_engine(webhook, 3131);
+        let rx = engine
+            .subscribe(&["ImageStoredEvent", "WebhookDeliveryFailedEvent"])
+            .unwrap();
+        // give the subscription time to reach the engine
+        thread::sleep(Duration::from_millis(200));
+        engine.publish(&new_image()).unwrap();
+
+        let timeout = Duration::from_secs(10);
+        let stored = match rx.recv_timeout(timeout).unwrap() {
+            Event::ImageStored(e) => e,
+            event => panic!("expected an ImageStoredEvent, got {:?}", event),
+        };
+        let failed = match rx.recv_timeout(timeout).unwrap() {
+            Event::WebhookDeliveryFailed(e) => e,
+            event => panic!("expected a WebhookDeliveryFailedEvent, got {:?}", event),
+        };
+        engine.shutdown().unwrap();
+        assert_eq!(failed.image_uuid, stored.image_uuid);
+        assert_eq!(failed.event_type, "ImageStoredEvent");
+        assert_eq!(failed.url, "http://127.0.0.1:3130/stored");
+        assert_eq!(failed.status_code, 500);
+        // the first attempt and the retries
+        assert_eq!(bodies.try_iter().count(), 3);
+    }
+}
@@ -0,0 +1,480 @@
+//! WebSocket gateway plugin, built with the `ws-gateway` feature.
+//! This plugin runs a WebSocket server so that browsers, e.g., a live dashboard, can watch the
+//! events of the engine. A client sends a subscribe message listing the event types it wants,
+//!
+//! ```json
+//! {"subscribe": ["ImageScoredEvent"]}
+//! ```
+//!
+//! and the plugin answers with `{"subscribed": [...]}`, or with `{"error": "..."}` if a type is
+//! not an event type, then sends each event of those types as a text frame holding its JSON (see
+//! `EventMsg::to_json`). A later subscribe message replaces the event types of the client.
+//! The JSON of a NewImageEvent also holds the image, base64-encoded, in `image`, unless it is
+//! larger than `WsGatewayPlugin::max_image_size`: then `image` is left out and `image_omitted`
+//! is true.
+//!
+//! Each client has a bounded queue of frames, sent by a thread of its own, so that a slow client
+//! does not hold the plugin up: frames for a client with a full queue are dropped, and the next
+//! frame it gets tells how many were, in `dropped`.
+//!
+
+use std::collections::HashSet;
+use std::io;
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine as _;
+use log::{debug, info, warn};
+use serde_json::{json, Value};
+use tungstenite::Message;
+
+use crate::events::{event_type_names, known_event_type, Event};
+use crate::plugin::{EventMsg, Plugin, PluginContext, PluginError};
+
+// Used unless set with the builder methods of `WsGatewayPlugin`.
+const DEFAULT_QUEUE_SIZE: usize = 256;
+const DEFAULT_MAX_IMAGE_SIZE: usize = 1024 * 1024;
+
+// How often the plugin checks for new clients while no event arrives, and how often the thread
+// of a client checks for frames to send while the client sends nothing.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+// How long a client may take to complete the handshake, or to take a frame.
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The WebSocket gateway plugin, for registering with a `PluginRegistry`. It subscribes to every
+/// event type and sends each client the events of the types it subscribed to.
+pub struct WsGatewayPlugin {
+    plugin_id: i32,
+    port: u16,
+    queue_size: usize,
+    max_image_size: usize,
+}
+
+impl WsGatewayPlugin {
+    /// A plugin serving WebSocket clients on `port`; the port is bound when the plugin starts.
+    pub fn new(plugin_id: i32, port: u16) -> Self {
+        WsGatewayPlugin {
+            plugin_id,
+            port,
+            queue_size: DEFAULT_QUEUE_SIZE,
+            max_image_size: DEFAULT_MAX_IMAGE_SIZE,
+        }
+    }
+
+    /// How many frames may wait to be sent to a client before the next ones are dropped.
+    pub fn queue_size(mut self, queue_size: usize) -> Self {
+        self.queue_size = queue_size;
+        self
+    }
+
+    /// Largest image, in bytes, sent with its NewImageEvent; larger ones are left out.
+    pub fn max_image_size(mut self, max_image_size: usize) -> Self {
+        self.max_image_size = max_image_size;
+        self
+    }
+
+    // The frame of the event `msg`.
+    fn frame(&self, msg: &EventMsg) -> Value {
+        let mut frame = msg.to_json();
+        if let Ok(Event::NewImage(new_image)) = msg.decode() {
+            if new_image.image.len() <= self.max_image_size {
+                frame["image"] = json!(BASE64.encode(&new_image.image));
+            } else {
+                frame["image_omitted"] = json!(true);
+            }
+        }
+        frame
+    }
+
+    // Start the thread serving the client connected on `stream`.
+    fn accept(&self, stream: TcpStream, addr: SocketAddr) -> io::Result<Client> {
+        stream.set_nonblocking(false)?;
+        stream.set_read_timeout(Some(CLIENT_TIMEOUT))?;
+        stream.set_write_timeout(Some(CLIENT_TIMEOUT))?;
+        let (queue, frames) = mpsc::sync_channel(self.queue_size);
+        let subscriptions = Arc::new(Mutex::new(HashSet::new()));
+        let client_subscriptions = subscriptions.clone();
+        let plugin_id = self.plugin_id;
+        let thread = thread::Builder::new()
+            .name(format!("ws client {}", addr))
+            .spawn(move || match serve(stream, &client_subscriptions, frames) {
+                Ok(()) => debug!(plugin_id; "WebSocket client {} disconnected", addr),
+                Err(e) => info!(plugin_id; "WebSocket client {} disconnected: {}", addr, e),
+            })?;
+        Ok(Client {
+            addr,
+            subscriptions,
+            queue,
+            dropped: 0,
+            thread,
+        })
+    }
+}
+
+impl Plugin for WsGatewayPlugin {
+    fn id(&self) -> i32 {
+        self.plugin_id
+    }
+
+    fn name(&self) -> &str {
+        "ws-gateway"
+    }
+
+    fn subscriptions(&self) -> &[&str] {
+        event_type_names()
+    }
+
+    fn start(self: Box<Self>, mut ctx: PluginContext) -> Result<(), PluginError> {
+        let listener = TcpListener::bind(("0.0.0.0", self.port)).map_err(|e| {
+            PluginError::Other(format!(
+                "could not start WebSocket server on port {}: {}",
+                self.port, e
+            ))
+        })?;
+        listener.set_nonblocking(true)?;
+        info!(plugin_id = ctx.plugin_id; "WebSocket gateway plugin listening on port {}", self.port);
+        let mut clients: Vec<Client> = Vec::new();
+        loop {
+            loop {
+                match listener.accept() {
+                    Ok((stream, addr)) => match self.accept(stream, addr) {
+                        Ok(client) => clients.push(client),
+                        Err(e) => warn!(
+                            plugin_id = ctx.plugin_id;
+                            "WebSocket gateway plugin could not serve {}: {}", addr, e
+                        ),
+                    },
+                    Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                    Err(e) => {
+                        warn!(
+                            plugin_id = ctx.plugin_id;
+                            "WebSocket gateway plugin could not accept a client: {}", e
+                        );
+                        break;
+                    }
+                }
+            }
+            ctx.sub_socket
+                .poll(zmq::POLLIN, POLL_INTERVAL.as_millis() as i64)?;
+            while let Some(msg) = ctx.try_next_event()? {
+                if msg.event_type == "PluginTerminateEvent" {
+                    info!(
+                        plugin_id = ctx.plugin_id;
+                        "WebSocket gateway plugin got terminate event, exiting"
+                    );
+                    close(clients);
+                    return Ok(());
+                }
+                let mut frame = None;
+                for client in clients.iter_mut() {
+                    if client.subscribes_to(&msg.event_type) {
+                        let frame = frame.get_or_insert_with(|| self.frame(&msg));
+                        client.offer(frame, ctx.plugin_id);
+                    }
+                }
+            }
+            clients.retain(|client| !client.thread.is_finished());
+            if ctx.is_stopping() {
+                close(clients);
+                return Err(PluginError::Stopped);
+            }
+        }
+    }
+}
+
+// A connected client, as the plugin sees it.
+struct Client {
+    addr: SocketAddr,
+    // the event types the client subscribed to, set by its thread
+    subscriptions: Arc<Mutex<HashSet<&'static str>>>,
+    queue: SyncSender<String>,
+    // frames dropped since the last one queued
+    dropped: u64,
+    thread: JoinHandle<()>,
+}
+
+impl Client {
+    fn subscribes_to(&self, event_type: &str) -> bool {
+        self.subscriptions.lock().unwrap().contains(event_type)
+    }
+
+    // Queue `frame` for the client, or drop it if the queue is full.
+    fn offer(&mut self, frame: &Value, plugin_id: i32) {
+        let text = match self.dropped {
+            0 => frame.to_string(),
+            dropped => {
+                let mut frame = frame.clone();
+                frame["dropped"] = json!(dropped);
+                frame.to_string()
+            }
+        };
+        match self.queue.try_send(text) {
+            Ok(()) => self.dropped = 0,
+            Err(TrySendError::Full(_)) => {
+                if self.dropped == 0 {
+                    warn!(
+                        plugin_id;
+                        "WebSocket client {} is not keeping up, dropping frames", self.addr
+                    );
+                }
+                self.dropped += 1;
+            }
+            // the client is gone, and its thread about to finish
+            Err(TrySendError::Disconnected(_)) => {}
+        }
+    }
+}
+
+// Close the connections of `clients`, once their thread sent the frames already queued.
+fn close(clients: Vec<Client>) {
+    let threads: Vec<_> = clients.into_iter().map(|client| client.thread).collect();
+    for thread in threads {
+        let _ = thread.join();
+    }
+}
+
+// Serve a client on `stream`: complete the handshake, then send it the frames queued in
+// `frames` and handle its subscribe messages, until it disconnects or the plugin is done with
+// it (drops its queue).
+fn serve(
+    stream: TcpStream,
+    subscriptions: &Mutex<HashSet<&'static str>>,
+    frames: Receiver<String>,
+) -> Result<(), Box<tungstenite::Error>> {
+    let mut socket = tungstenite::accept(stream).map_err(|e| match e {
+        tungstenite::HandshakeError::Failure(e) => e,
+        tungstenite::HandshakeError::Interrupted(_) => {
+            tungstenite::Error::Io(io::ErrorKind::TimedOut.into())
+        }
+    })?;
+    socket
+        .get_ref()
+        .set_read_timeout(Some(POLL_INTERVAL))
+        .map_err(tungstenite::Error::Io)?;
+    loop {
+        match socket.read() {
+            Ok(Message::Text(text)) => {
+                let reply = subscribe(text.as_str(), subscriptions);
+                socket.send(Message::text(reply.to_string()))?;
+            }
+            Ok(Message::Binary(_)) => {
+                let reply = json!({"error": "expected a subscribe message as text"});
+                socket.send(Message::text(reply.to_string()))?;
+            }
+            // pings are answered by the socket itself
+            Ok(Message::Close(_)) | Err(tungstenite::Error::ConnectionClosed) => return Ok(()),
+            Ok(_) => {}
+            Err(tungstenite::Error::Io(e))
+                if matches!(
+                    e.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) => {}
+            Err(e) => return Err(e.into()),
+        }
+        loop {
+            match frames.try_recv() {
+                Ok(frame) => socket.write(Message::text(frame))?,
+                Err(mpsc::TryRecvError::Empty) => break,
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    // the client is not waited for to answer the close frame
+                    socket.close(None)?;
+                    return Ok(socket.flush()?);
+                }
+            }
+        }
+        socket.flush()?;
+    }
+}
+
+// Handle the subscribe message `text` of a client, returning the reply.
+fn subscribe(text: &str, subscriptions: &Mutex<HashSet<&'static str>>) -> Value {
+    let event_types = match serde_json::from_str::<Value>(text) {
+        Ok(Value::Object(mut message)) => match message.remove("subscribe") {
+            Some(Value::Array(event_types)) => event_types,
+            _ => return json!({"error": "expected a \"subscribe\" list of event types"}),
+        },
+        _ => return json!({"error": "expected a subscribe message, as a JSON object"}),
+    };
+    let mut subscribed = HashSet::new();
+    for event_type in &event_types {
+        let event_type = event_type.as_str().unwrap_or_default();
+        match known_event_type(event_type) {
+            Some(name) => subscribed.insert(name),
+            None => return json!({"error": format!("unknown event type: {}", event_type)}),
+        };
+    }
+    let mut names: Vec<_> = subscribed.iter().copied().collect();
+    names.sort();
+    *subscriptions.lock().unwrap() = subscribed;
+    json!({ "subscribed": names })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::event_engine::EventEngineBuilder;
+    use crate::events::NewImage;
+    use crate::image_score_plugin::ImageScorePlugin;
+    use crate::plugin_registry::PluginRegistry;
+    use std::time::Instant;
+    use tungstenite::WebSocket;
+
+    // Connect a WebSocket client to `port` and subscribe it to `event_types`. The plugin binds
+    // its port after it has synced, so connecting is retried for a while.
+    fn connect(port: u16, event_types: &[&str]) -> WebSocket<TcpStream> {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        let stream = loop {
+            match TcpStream::connect(("127.0.0.1", port)) {
+                Ok(stream) => break stream,
+                Err(e) if Instant::now() > deadline => panic!("could not connect: {}", e),
+                Err(_) => std::thread::sleep(Duration::from_millis(20)),
+            }
+        };
+        stream
+            .set_read_timeout(Some(Duration::from_secs(10)))
+            .unwrap();
+        let url = format!("ws://127.0.0.1:{}/", port);
+        let (mut socket, _) = tungstenite::client(url, stream).unwrap();
+        let message = json!({ "subscribe": event_types });
+        socket.send(Message::text(message.to_string())).unwrap();
+        assert_eq!(recv(&mut socket), json!({ "subscribed": event_types }));
+        socket
+    }
+
+    fn recv(socket: &mut WebSocket<TcpStream>) -> Value {
+        match socket.read().unwrap() {
+            Message::Text(text) => serde_json::from_str(text.as_str()).unwrap(),
+            message => panic!("expected a text frame, got {:?}", message),
+        }
+    }
+
+    fn start_gateway_engine(
+        gateway: WsGatewayPlugin,
+        incoming_port: u16,
+    ) -> crate::event_engine::EngineHandle {
+        let mut plugins = PluginRegistry::new();
+        plugins
+            .register_plugin(Box::new(gateway))
+            .unwrap()
+            .register_plugin(Box::new(ImageScorePlugin::new(1)))
+            .unwrap();
+        EventEngineBuilder::new()
+            .incoming_port(incoming_port)
+            .outgoing_port(incoming_port + 1)
+            .plugins(plugins)
+            .start()
+            .unwrap()
+    }
+
+    fn new_image(image: Vec<u8>) -> NewImage {
+        NewImage {
+            image_uuid: uuid::Uuid::new_v4().to_string(),
+            image_format: "png".to_string(),
+            image,
+            location: None,
+        }
+    }
+
+    #[test]
+    fn test_scored_images_are_sent_to_subscribed_clients() {
+        let engine = start_gateway_engine(WsGatewayPlugin::new(0, 26821), 26822);
+        let mut socket = connect(26821, &["ImageScoredEvent"]);
+
+        let images: Vec<_> = (0..3).map(|i| new_image(vec![i; 16])).collect();
+        for image in &images {
+            engine.publish(&Event::NewImage(image.clone())).unwrap();
+        }
+        for image in &images {
+            // only the events subscribed to are sent, in order
+            let frame = recv(&mut socket);
+            assert_eq!(frame["type"], "ImageScoredEvent");
+            assert_eq!(frame["uuid"], image.image_uuid.as_str());
+            assert!(frame["fields"]["scores"].is_object());
+        }
+        engine.shutdown().unwrap();
+    }
+
+    #[test]
+    fn test_large_images_are_omitted() {
+        let gateway = WsGatewayPlugin::new(0, 26831).max_image_size(4);
+        let engine = start_gateway_engine(gateway, 26832);
+        let mut socket = connect(26831, &["NewImageEvent"]);
+
+        let small = new_image(vec![1, 2, 3, 4]);
+        let large = new_image(vec![1, 2, 3, 4, 5]);
+        engine.publish(&Event::NewImage(small.clone())).unwrap();
+        engine.publish(&Event::NewImage(large.clone())).unwrap();
+        let frame = recv(&mut socket);
+        assert_eq!(frame["uuid"], small.image_uuid.as_str());
+        assert_eq!(frame["image"], BASE64.encode([1, 2, 3, 4]));
+        assert_eq!(frame.get("image_omitted"), None);
+        let frame = recv(&mut socket);
+        assert_eq!(frame["uuid"], large.image_uuid.as_str());
+        assert_eq!(frame.get("image"), None);
+        assert_eq!(frame["image_omitted"], true);
+        assert_eq!(frame["fields"]["image_size"], 5);
+
+        // an unknown type leaves the subscriptions as they were
+        let message = json!({"subscribe": ["ImageScoredEvent", "NoSuchEvent"]});
+        socket.send(Message::text(message.to_string())).unwrap();
+        assert_eq!(
+            recv(&mut socket),
+            json!({"error": "unknown event type: NoSuchEvent"})
+        );
+        engine.publish(&Event::NewImage(small.clone())).unwrap();
+        assert_eq!(recv(&mut socket)["type"], "NewImageEvent");
+        engine.shutdown().unwrap();
+    }
+
+    #[test]
+    fn test_frames_for_slow_clients_are_dropped() {
+        let (queue, frames) = mpsc::sync_channel(2);
+        let mut client = Client {
+            addr: "127.0.0.1:1".parse().unwrap(),
+            subscriptions: Arc::new(Mutex::new(HashSet::new())),
+            queue,
+            dropped: 0,
+            thread: thread::spawn(|| {}),
+        };
+        for seq in 0..5 {
+            client.offer(&json!({ "seq": seq }), 0);
+        }
+        assert_eq!(client.dropped, 3);
+        let received: Vec<String> = frames.try_iter().collect();
+        assert_eq!(received, [r#"{"seq":0}"#, r#"{"seq":1}"#]);
+        // the next frame queued tells how many were dropped
+        client.offer(&json!({ "seq": 5 }), 0);
+        client.offer(&json!({ "seq": 6 }), 0);
+        let received: Vec<String> = frames.try_iter().collect();
+        assert_eq!(received, [r#"{"dropped":3,"seq":5}"#, r#"{"seq":6}"#]);
+        assert_eq!(client.dropped, 0);
+    }
+
+    #[test]
+    fn test_subscribe_messages() {
+        let subscriptions = Mutex::new(HashSet::new());
+        let reply = subscribe(
+            r#"{"subscribe": ["ImageStoredEvent", "ImageScoredEvent"]}"#,
+            &subscriptions,
+        );
+        assert_eq!(
+            reply,
+            json!({"subscribed": ["ImageScoredEvent", "ImageStoredEvent"]})
+        );
+        assert_eq!(subscriptions.lock().unwrap().len(), 2);
+        assert!(
+            subscribe(r#"{"subscribe": "ImageStoredEvent"}"#, &subscriptions)["error"].is_string()
+        );
+        assert!(subscribe("not json", &subscriptions)["error"].is_string());
+        assert_eq!(subscriptions.lock().unwrap().len(), 2);
+        assert_eq!(
+            subscribe(r#"{"subscribe": []}"#, &subscriptions),
+            json!({"subscribed": []})
+        );
+        assert!(subscriptions.lock().unwrap().is_empty());
+    }
+}
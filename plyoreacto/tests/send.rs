@@ -0,0 +1,110 @@
+// Runs plyoreacto-send against an engine in this process and checks the events arrive.
+
+use std::process::{Command, Output};
+use std::time::Duration;
+
+use plyoreacto::events::Event;
+use plyoreacto::testing::{free_port, TestHarness};
+
+// An engine without plugins the binary can reach over TCP.
+fn start_engine() -> TestHarness {
+    TestHarness::builder().tcp().start().unwrap()
+}
+
+fn run_send(incoming_port: u16, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_plyoreacto-send"))
+        .args(args)
+        .args(["--address", &format!("127.0.0.1:{}", incoming_port)])
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn test_new_image_reaches_subscribers() {
+    let mut harness = start_engine();
+    let file = std::env::temp_dir().join(format!("plyoreacto-send-{}.JPG", std::process::id()));
+    std::fs::write(&file, [0xff, 0xd8, 0xff, 0xd9]).unwrap();
+
+    let incoming_port = harness.config().incoming_port;
+    let output = run_send(
+        incoming_port,
+        &["new-image", "--file", file.to_str().unwrap()],
+    );
+    std::fs::remove_file(&file).unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let image_uuid = String::from_utf8(output.stdout).unwrap().trim().to_string();
+    match &harness.collect("NewImageEvent", 1, Duration::from_secs(10))[..] {
+        [Event::NewImage(new_image)] => {
+            assert_eq!(new_image.image_uuid, image_uuid);
+            assert_eq!(new_image.image_format, "jpg");
+            assert_eq!(new_image.image, [0xff, 0xd8, 0xff, 0xd9]);
+        }
+        events => panic!("expected a NewImageEvent, got {:?}", events),
+    }
+    harness.shutdown().unwrap();
+}
+
+#[test]
+fn test_raw_json_event_reaches_subscribers() {
+    let mut harness = start_engine();
+    let incoming_port = harness.config().incoming_port;
+
+    let json = r#"{"image_uuid": "5b0c", "path": "/images/5b0c.png", "deduplicated": true}"#;
+    let args = [
+        "raw",
+        "--type",
+        "ImageStoredEvent",
+        "--json",
+        json,
+        "--codec",
+        "json",
+    ];
+    let output = run_send(incoming_port, &args);
+    assert!(output.status.success(), "{:?}", output);
+    match &harness.collect("ImageStoredEvent", 1, Duration::from_secs(10))[..] {
+        [Event::ImageStored(stored)] => {
+            assert_eq!(stored.image_uuid, "5b0c");
+            assert_eq!(stored.path, "/images/5b0c.png");
+            assert!(stored.deduplicated);
+        }
+        events => panic!("expected an ImageStoredEvent, got {:?}", events),
+    }
+
+    // an event that cannot be made is not sent
+    let args = [
+        "raw",
+        "--type",
+        "ImageStoredEvent",
+        "--json",
+        r#"{"path": 1}"#,
+    ];
+    let output = run_send(incoming_port, &args);
+    assert_eq!(output.status.code(), Some(1), "{:?}", output);
+    assert!(harness
+        .collect("ImageStoredEvent", 1, Duration::from_millis(500))
+        .is_empty());
+    harness.shutdown().unwrap();
+}
+
+#[test]
+fn test_send_fails_without_an_engine() {
+    let json = r#"{"image_uuid": "5b0c"}"#;
+    let args = [
+        "raw",
+        "--type",
+        "ImageDeletedRequestEvent",
+        "--json",
+        json,
+        "--timeout",
+        "200",
+    ];
+    // nothing listens on it
+    let port = free_port();
+    let output = run_send(port, &args);
+    assert_eq!(output.status.code(), Some(1), "{:?}", output);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("no engine subscribed"), "{}", stderr);
+    // a usage error
+    let output = run_send(port, &["new-image"]);
+    assert_eq!(output.status.code(), Some(2), "{:?}", output);
+}
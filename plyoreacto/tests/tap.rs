@@ -0,0 +1,99 @@
+// Runs plyoreacto-tap against an engine in this process and checks what it prints.
+
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+use plyoreacto::events::{Event, NewImage};
+use plyoreacto::image_score_plugin::ImageScorePlugin;
+use plyoreacto::testing::TestHarness;
+use serde_json::Value;
+
+// An engine scoring images the binary can reach over TCP.
+fn start_engine() -> TestHarness {
+    TestHarness::builder()
+        .plugin(Box::new(ImageScorePlugin::new(1)))
+        .tcp()
+        .start()
+        .unwrap()
+}
+
+// Run the tap with `args` against the engine, publishing an image every 100 ms until it exits,
+// since it may take a while to subscribe; returns its stdout and the uuids of the images.
+fn run_tap(harness: &TestHarness, args: &[&str]) -> (String, Vec<String>) {
+    let address = format!("127.0.0.1:{}", harness.config().outgoing_port);
+    let mut tap = Command::new(env!("CARGO_BIN_EXE_plyoreacto-tap"))
+        .args(["--address", &address])
+        .args(args)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    let deadline = Instant::now() + Duration::from_secs(20);
+    let mut image_uuids = Vec::new();
+    while tap.try_wait().unwrap().is_none() {
+        if Instant::now() > deadline {
+            tap.kill().unwrap();
+            panic!("the tap did not exit");
+        }
+        let image_uuid = uuid::Uuid::new_v4().to_string();
+        let new_image = NewImage {
+            image_uuid: image_uuid.clone(),
+            image_format: "png".to_string(),
+            image: vec![0; 100],
+            location: None,
+        };
+        harness.publish(&Event::NewImage(new_image)).unwrap();
+        image_uuids.push(image_uuid);
+        std::thread::sleep(Duration::from_millis(100));
+    }
+    let output = tap.wait_with_output().unwrap();
+    assert!(output.status.success());
+    (String::from_utf8(output.stdout).unwrap(), image_uuids)
+}
+
+#[test]
+fn test_tap_prints_summaries() {
+    let harness = start_engine();
+    let (stdout, image_uuids) = run_tap(&harness, &["--count", "2", "ImageScoredEvent"]);
+    let lines: Vec<_> = stdout.lines().collect();
+    assert_eq!(lines.len(), 2, "{}", stdout);
+    for line in lines {
+        // e.g., "2024-05-01 12:00:00.250 ImageScoredEvent <uuid> 120 B scores={"labrador":0.93}"
+        let fields: Vec<_> = line.split(' ').collect();
+        assert_eq!(fields[2], "ImageScoredEvent", "{}", line);
+        assert!(image_uuids.iter().any(|uuid| uuid == fields[3]), "{}", line);
+        assert_eq!(fields[5], "B", "{}", line);
+        assert!(fields[6].starts_with("scores={"), "{}", line);
+    }
+    harness.shutdown().unwrap();
+}
+
+#[test]
+fn test_tap_prints_json() {
+    let harness = start_engine();
+    let (stdout, image_uuids) = run_tap(&harness, &["--json", "--count", "3"]);
+    let entries: Vec<Value> = stdout
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(entries.len(), 3, "{}", stdout);
+    for entry in &entries {
+        // the engine publishes events of its own too, e.g., heartbeats
+        if let Some(image_uuid) = entry["uuid"].as_str() {
+            assert!(
+                image_uuids.iter().any(|uuid| uuid == image_uuid),
+                "{}",
+                entry
+            );
+        }
+    }
+    // all event types are tapped, in the envelope they were published in, if any
+    assert!(entries.iter().any(|entry| entry["type"] == "NewImageEvent"));
+    for entry in entries
+        .iter()
+        .filter(|entry| entry["type"] == "ImageScoredEvent")
+    {
+        assert!(entry["event_id"].is_string(), "{}", entry);
+    }
+    harness.shutdown().unwrap();
+}